@@ -0,0 +1,26 @@
+use std::process::Command;
+
+fn main() {
+  let git_hash = Command::new("git")
+    .args(["rev-parse", "--short", "HEAD"])
+    .output()
+    .ok()
+    .filter(|output| output.status.success())
+    .and_then(|output| String::from_utf8(output.stdout).ok())
+    .map(|hash| hash.trim().to_string())
+    .unwrap_or_else(|| "unknown".to_string());
+  println!("cargo:rustc-env=WINDSHIELD_GIT_HASH={}", git_hash);
+
+  let mut features: Vec<String> = std::env::vars()
+    .filter_map(|(key, _)| key.strip_prefix("CARGO_FEATURE_").map(str::to_string))
+    .map(|feature| feature.to_lowercase().replace('_', "-"))
+    .collect();
+  features.sort();
+  println!("cargo:rustc-env=WINDSHIELD_FEATURES={}", features.join(","));
+
+  let target = std::env::var("TARGET").unwrap_or_default();
+  println!("cargo:rustc-env=WINDSHIELD_TARGET={}", target);
+
+  println!("cargo:rerun-if-changed=.git/HEAD");
+  println!("cargo:rerun-if-changed=.git/refs");
+}
@@ -0,0 +1,104 @@
+//! A screen over the HUD identifying the build and the GPU it runs on, for
+//! telling support what a deployed unit runs, see
+//! [`Action::About`](crate::input::Action::About).
+
+use wgpu::AdapterInfo;
+
+use crate::build_info::BuildInfo;
+use crate::canvas::Style;
+use crate::frame::Frame;
+use crate::text::{Align, TextSection, VAlign};
+
+/// What the screen shows, a line each.
+fn lines(build: &BuildInfo, adapter: &AdapterInfo, size: [u32; 2]) -> Vec<String> {
+  let features = match build.features.is_empty() {
+    true => "none".to_string(),
+    false => build.features.join(", "),
+  };
+  let driver = match (adapter.driver.as_str(), adapter.driver_info.as_str()) {
+    ("", "") => String::new(),
+    (driver, "") | ("", driver) => format!(", {}", driver),
+    (driver, info) => format!(", {} {}", driver, info),
+  };
+  vec![
+    format!("windshield-rs {}", build.version),
+    format!("commit {}", build.git_hash),
+    format!(
+      "{} build for {}",
+      if build.release { "release" } else { "debug" },
+      build.target
+    ),
+    format!("features: {}", features),
+    format!("license: {}", build.license.unwrap_or("unknown")),
+    format!("GPU: {} on {:?}{}", adapter.name, adapter.backend, driver),
+    format!("{:?}, {}×{} pixels", adapter.device_type, size[0], size[1]),
+  ]
+}
+
+/// Draws the screen over everything in `frame`.
+pub(crate) fn draw(frame: &mut Frame, build: &BuildInfo) {
+  let (width, height) = (frame.width as f32, frame.height as f32);
+  let palette = frame.palette;
+  frame.canvas.rect(
+    [0.0, 0.0],
+    [width, height],
+    Style::fill([0.0, 0.0, 0.0, 0.85]),
+  );
+  let lines = lines(build, frame.adapter, [frame.width, frame.height]);
+  let line = height / (lines.len() + 2) as f32;
+  let size = (line * 0.5).min(width / 24.0);
+  for (index, text) in lines.into_iter().enumerate() {
+    let color = if index == 0 {
+      palette.text
+    } else {
+      palette.muted
+    };
+    frame.text.queue(
+      &TextSection::new(text)
+        .at(width / 2.0, line * (index as f32 + 1.5))
+        .with_size(if index == 0 { size * 1.4 } else { size })
+        .with_color(color)
+        .with_align(Align::Center, VAlign::Center),
+    );
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use wgpu::{Backend, DeviceType};
+
+  use super::*;
+
+  #[test]
+  fn identifies_the_build_and_gpu() {
+    let build = BuildInfo {
+      version: "1.2.0",
+      git_hash: "abc1234",
+      features: vec!["inspector"],
+      target: "aarch64-unknown-linux-gnu",
+      release: true,
+      license: None,
+    };
+    let adapter = AdapterInfo {
+      name: "V3D 4.2".to_string(),
+      vendor: 0,
+      device: 0,
+      device_type: DeviceType::IntegratedGpu,
+      driver: "Mesa".to_string(),
+      driver_info: String::new(),
+      backend: Backend::Vulkan,
+    };
+    assert_eq!(
+      lines(&build, &adapter, [800, 480]),
+      [
+        "windshield-rs 1.2.0",
+        "commit abc1234",
+        "release build for aarch64-unknown-linux-gnu",
+        "features: inspector",
+        "license: unknown",
+        "GPU: V3D 4.2 on Vulkan, Mesa",
+        "IntegratedGpu, 800×480 pixels",
+      ]
+    );
+  }
+}
@@ -0,0 +1,222 @@
+//! Making the HUD easier to read for drivers who need it, see
+//! [`Settings::accessibility`](crate::Settings::accessibility).
+//!
+//! The profile applies to everything drawn, whatever widgets ask for:
+//! text is enlarged by the renderer, high contrast forced on, and scene
+//! transitions and scrolling text hold still. It's switched on and off
+//! while running with [`Action::Accessibility`](crate::input::Action::Accessibility):
+//!
+//! ```toml
+//! [accessibility]
+//! enabled = true
+//! text_scale = 1.2
+//! min_text_size = 24
+//! ```
+//!
+//! Widgets also [describe themselves](crate::widgets::Widget::accessible)
+//! to screen readers, with [`Scene::accessible`](crate::scene::Scene::accessible)
+//! listing those shown. Built with the `screen-reader` feature, the app
+//! exposes them through AccessKit to the platform's assistive
+//! technologies, like Orca on Linux, which read menus and dialogs out and
+//! move the focus through them.
+
+use std::borrow::Cow;
+
+use serde::Deserialize;
+
+use crate::text::TextSection;
+use crate::widgets::Rect;
+
+/// What the accessibility profile changes.
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Accessibility {
+  /// Whether the profile applies.
+  pub enabled: bool,
+  /// Forces [high contrast](crate::Settings::high_contrast) on.
+  pub high_contrast: bool,
+  /// Multiplies the size of all text.
+  pub text_scale: f32,
+  /// Smallest size of text, in pixels scaled like the layout, see
+  /// [`Frame::scale`](crate::Frame::scale).
+  pub min_text_size: f32,
+  /// Forces [reduced motion](crate::Settings::reduce_motion) on.
+  pub reduce_motion: bool,
+}
+
+impl Default for Accessibility {
+  fn default() -> Self {
+    Self {
+      enabled: false,
+      high_contrast: true,
+      text_scale: 1.25,
+      min_text_size: 20.0,
+      reduce_motion: true,
+    }
+  }
+}
+
+impl Accessibility {
+  pub(crate) fn forces_high_contrast(&self) -> bool {
+    self.enabled && self.high_contrast
+  }
+
+  pub(crate) fn reduces_motion(&self) -> bool {
+    self.enabled && self.reduce_motion
+  }
+
+  /// Text sizes as drawn, at a UI scale of `scale`.
+  pub(crate) fn text_sizes(&self, scale: f32) -> TextSizes {
+    match self.enabled {
+      true => TextSizes {
+        scale: self.text_scale,
+        min: self.min_text_size * scale,
+      },
+      false => TextSizes::default(),
+    }
+  }
+}
+
+/// How the text renderer enlarges text.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) struct TextSizes {
+  scale: f32,
+  min: f32,
+}
+
+impl Default for TextSizes {
+  fn default() -> Self {
+    Self {
+      scale: 1.0,
+      min: 0.0,
+    }
+  }
+}
+
+impl TextSizes {
+  /// `section` at the size it's drawn at.
+  pub(crate) fn apply<'s>(&self, section: &'s TextSection) -> Cow<'s, TextSection> {
+    let size = (section.size * self.scale).max(self.min);
+    match size == section.size {
+      true => Cow::Borrowed(section),
+      false => Cow::Owned(section.clone().with_size(size)),
+    }
+  }
+}
+
+/// What a widget is to screen readers.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Role {
+  /// Text read as it is, like a label.
+  Text,
+  /// A value within a range, like a gauge.
+  Meter,
+  Button,
+  /// On or off, [selected](Accessible::selected) while on.
+  Switch,
+  Slider,
+  /// Options of which the [selected](Accessible::selected) one is chosen.
+  RadioGroup,
+  RadioButton,
+  Menu,
+  MenuItem,
+  Dialog,
+  SearchBox,
+}
+
+/// How a widget is presented to screen readers, see
+/// [`Widget::accessible`](crate::widgets::Widget::accessible).
+#[derive(Clone, Debug, PartialEq)]
+pub struct Accessible {
+  pub role: Role,
+  /// What's read first, like the text of a label or the title of a menu.
+  pub name: String,
+  /// What it shows or is set to, like the speed on a speedometer.
+  pub value: Option<String>,
+  /// Whether it's on, or the one chosen among its siblings, like the
+  /// highlighted item of a menu.
+  pub selected: bool,
+  /// Parts read on their own, like the items of a menu.
+  pub children: Vec<Accessible>,
+}
+
+impl Accessible {
+  pub fn new(role: Role, name: impl Into<String>) -> Self {
+    Self {
+      role,
+      name: name.into(),
+      value: None,
+      selected: false,
+      children: Vec::new(),
+    }
+  }
+
+  pub fn with_value(mut self, value: impl Into<String>) -> Self {
+    self.value = Some(value.into());
+    self
+  }
+
+  pub fn with_selected(mut self, selected: bool) -> Self {
+    self.selected = selected;
+    self
+  }
+
+  pub fn with_children(mut self, children: Vec<Accessible>) -> Self {
+    self.children = children;
+    self
+  }
+}
+
+/// A widget shown as screen readers see it, see
+/// [`Node::accessible`](crate::layout::Node::accessible).
+#[derive(Clone, Debug, PartialEq)]
+pub struct AccessibleWidget {
+  /// Derived from where the widget is in the scene, so it stays the same
+  /// from one frame to the next.
+  pub id: u64,
+  pub accessible: Accessible,
+  /// Where it was last laid out.
+  pub rect: Rect,
+  /// Whether navigation actions go to it.
+  pub focused: bool,
+  /// Whether it can be focused, e.g. by a screen reader.
+  pub focusable: bool,
+}
+
+/// The id of the part at `index` of what has the id `parent`.
+pub(crate) fn child_id(parent: u64, index: usize) -> u64 {
+  // FNV-1a over the path, spreads neighbouring paths apart
+  (parent ^ (index as u64 + 1)).wrapping_mul(0x0100_0000_01b3)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::config::toml_example;
+
+  #[test]
+  fn enlarges_text_only_while_enabled() {
+    let mut profile = Accessibility {
+      text_scale: 1.5,
+      min_text_size: 20.0,
+      ..Default::default()
+    };
+    let size = |profile: &Accessibility, size| {
+      let section = TextSection::new("60").with_size(size);
+      profile.text_sizes(2.0).apply(&section).size
+    };
+    assert_eq!(size(&profile, 10.0), 10.0);
+    profile.enabled = true;
+    assert_eq!(size(&profile, 10.0), 40.0);
+    assert_eq!(size(&profile, 40.0), 60.0);
+  }
+
+  #[test]
+  fn parses_the_module_example() {
+    let example = toml_example(include_str!("accessibility.rs"), "//!");
+    let example: toml::Value = toml::from_str(&example).unwrap();
+    let profile: Accessibility = example["accessibility"].clone().try_into().unwrap();
+    assert!(profile.enabled && profile.reduce_motion);
+    assert_eq!(profile.min_text_size, 24.0);
+  }
+}
@@ -0,0 +1,63 @@
+use std::ffi::CString;
+use std::os::raw::{c_char, c_int};
+use std::thread;
+use std::time::Duration;
+
+use ndk::native_activity::WindowFlags;
+use tracing_subscriber::filter::LevelFilter;
+use tracing_subscriber::prelude::*;
+
+use crate::config::Config;
+use crate::logging::LineWriter;
+use crate::WindshieldApp;
+
+#[link(name = "log")]
+extern "C" {
+  fn __android_log_write(priority: c_int, tag: *const c_char, text: *const c_char) -> c_int;
+}
+
+/// Started by the activity when the APK is launched: shows the dashboard of
+/// `config.toml` in the app's internal storage, see
+/// [`Config::default_path`].
+#[ndk_glue::main(backtrace = "on")]
+pub fn main() {
+  let layer = tracing_subscriber::fmt::layer()
+    .without_time()
+    .with_ansi(false)
+    .with_writer(LineWriter(log))
+    .with_filter(LevelFilter::INFO);
+  tracing_subscriber::registry().with(layer).init();
+
+  let mut builder = WindshieldApp::builder();
+  if let Some(path) = Config::default_path().filter(|path| path.exists()) {
+    builder = builder.with_config(path);
+  }
+  if let Err(err) = pollster::block_on(builder.build().run()) {
+    tracing::error!("{}", err);
+  }
+}
+
+/// Writes a line to logcat, as stdout goes nowhere on Android.
+fn log(line: &str) {
+  const INFO: c_int = 4;
+  let tag = b"windshield\0";
+  // Lines with a nul in them are cut short rather than dropped
+  let line = line.split('\0').next().unwrap_or_default();
+  if let Ok(text) = CString::new(line) {
+    unsafe { __android_log_write(INFO, tag.as_ptr().cast(), text.as_ptr()) };
+  }
+}
+
+/// Blocks until Android created the window of the activity, there is
+/// nothing to create a surface for before.
+pub(crate) fn wait_for_window() {
+  while ndk_glue::native_window().is_none() {
+    thread::sleep(Duration::from_millis(10));
+  }
+}
+
+/// Keeps the screen from dimming and locking while the HUD is shown, as
+/// nobody touches a phone lying on the dashboard.
+pub(crate) fn keep_screen_on() {
+  ndk_glue::native_activity().set_window_flags(WindowFlags::KEEP_SCREEN_ON, WindowFlags::empty());
+}
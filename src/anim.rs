@@ -0,0 +1,176 @@
+use std::time::Duration;
+
+use serde::Deserialize;
+
+/// Distance from their target below which animated values count as
+/// settled, in the normalized units they animate in.
+pub const SETTLED: f32 = 1e-3;
+
+/// Longest time step springs are advanced by at once, so they stay stable
+/// on long frames.
+const MAX_STEP: f32 = 1.0 / 240.0;
+
+/// The point `t` of the way from `from` to `to`.
+pub fn lerp(from: f32, to: f32, t: f32) -> f32 {
+  from + (to - from) * t
+}
+
+/// The color `t` of the way from `from` to `to`, channel by channel. Unlike
+/// [`lerp`] exactly `to` at 1, so cross-fades end on the color faded to.
+pub fn mix(from: [f32; 4], to: [f32; 4], t: f32) -> [f32; 4] {
+  [0, 1, 2, 3].map(|index| from[index] * (1.0 - t) + to[index] * t)
+}
+
+/// Moves `value` towards `target` by the share of the distance left that
+/// `response` catches up on per second, the same however the frames are
+/// spaced. Zero or less jumps to the target.
+///
+/// Follows noisy sensor values smoothly without overshooting them, like a
+/// gauge needle.
+pub fn follow(value: f32, target: f32, response: f32, delta: Duration) -> f32 {
+  if response <= 0.0 {
+    return target;
+  }
+  let t = 1.0 - (-response * delta.as_secs_f32()).exp();
+  lerp(value, target, t)
+}
+
+/// How a [`Tween`] speeds up and slows down, mapping the share of its time
+/// that has passed onto the share of the way it has moved.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Easing {
+  Linear,
+  /// Starts slowly and stops abruptly.
+  EaseIn,
+  /// Starts abruptly and stops slowly, for things coming in.
+  EaseOut,
+  /// Starts and stops slowly.
+  #[default]
+  EaseInOut,
+}
+
+impl Easing {
+  pub fn apply(self, t: f32) -> f32 {
+    let t = t.clamp(0.0, 1.0);
+    match self {
+      Self::Linear => t,
+      Self::EaseIn => t * t * t,
+      Self::EaseOut => 1.0 - (1.0 - t).powi(3),
+      Self::EaseInOut => t * t * (3.0 - 2.0 * t),
+    }
+  }
+}
+
+/// Moves from one value to another in a fixed time.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Tween {
+  pub from: f32,
+  pub to: f32,
+  pub duration: Duration,
+  pub easing: Easing,
+  elapsed: Duration,
+}
+
+impl Tween {
+  pub fn new(from: f32, to: f32, duration: Duration) -> Self {
+    Self {
+      from,
+      to,
+      duration,
+      easing: Easing::default(),
+      elapsed: Duration::ZERO,
+    }
+  }
+
+  pub fn with_easing(mut self, easing: Easing) -> Self {
+    self.easing = easing;
+    self
+  }
+
+  pub fn update(&mut self, delta: Duration) {
+    self.elapsed = (self.elapsed + delta).min(self.duration);
+  }
+
+  /// Share of the time that has passed, from 0 to 1.
+  pub fn progress(&self) -> f32 {
+    if self.duration.is_zero() {
+      return 1.0;
+    }
+    self.elapsed.as_secs_f32() / self.duration.as_secs_f32()
+  }
+
+  pub fn value(&self) -> f32 {
+    lerp(self.from, self.to, self.easing.apply(self.progress()))
+  }
+
+  pub fn is_finished(&self) -> bool {
+    self.elapsed >= self.duration
+  }
+}
+
+/// A value pulled towards a target like a mass on a spring, carrying on
+/// with the speed it was given, e.g. by a flick.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Spring {
+  /// Per second squared, higher settles faster.
+  pub stiffness: f32,
+  /// Per second, critically damped at `2 * stiffness.sqrt()`. Less
+  /// overshoots, more creeps.
+  pub damping: f32,
+  pub target: f32,
+  value: f32,
+  velocity: f32,
+}
+
+impl Spring {
+  /// A critically damped spring at rest at `value`, settling as fast as
+  /// it can without overshooting.
+  pub fn new(value: f32, stiffness: f32) -> Self {
+    Self {
+      stiffness,
+      damping: 2.0 * stiffness.sqrt(),
+      target: value,
+      value,
+      velocity: 0.0,
+    }
+  }
+
+  pub fn with_damping(mut self, damping: f32) -> Self {
+    self.damping = damping;
+    self
+  }
+
+  pub fn value(&self) -> f32 {
+    self.value
+  }
+
+  /// Moves the value without the spring, e.g. while it's dragged.
+  pub fn set_value(&mut self, value: f32) {
+    self.value = value;
+  }
+
+  /// Change of the value per second.
+  pub fn velocity(&self) -> f32 {
+    self.velocity
+  }
+
+  pub fn set_velocity(&mut self, velocity: f32) {
+    self.velocity = velocity;
+  }
+
+  pub fn update(&mut self, delta: Duration) {
+    let mut remaining = delta.as_secs_f32();
+    while remaining > 0.0 {
+      let dt = remaining.min(MAX_STEP);
+      let force = self.stiffness * (self.target - self.value) - self.damping * self.velocity;
+      self.velocity += force * dt;
+      self.value += self.velocity * dt;
+      remaining -= dt;
+    }
+  }
+
+  pub fn is_settled(&self) -> bool {
+    (self.target - self.value).abs() <= SETTLED && self.velocity.abs() <= SETTLED
+  }
+}
@@ -0,0 +1,1688 @@
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use instant::Instant;
+use wgpu::{Color, CompositeAlphaMode, PowerPreference, PresentMode, SurfaceError};
+use winit::{
+  dpi::{LogicalSize, PhysicalSize},
+  event::*,
+  event_loop::{ControlFlow, EventLoop},
+  window::{Window, WindowBuilder},
+};
+
+use crate::accessibility::Accessibility;
+#[cfg(target_os = "android")]
+use crate::android;
+use crate::backlight::{self, Backlight};
+use crate::burn_in::BurnIn;
+use crate::checksum::ChecksumRegion;
+use crate::clock::Clock;
+use crate::config::{Config, Fullscreen};
+use crate::crash::CrashReporter;
+use crate::data::arbiter::Origin;
+use crate::data::gps::{GpsDevice, GpsSource};
+use crate::data::light::LightSensor;
+use crate::data::obd::{self, ObdSource};
+use crate::data::process::Processor;
+use crate::data::{DataSource, Field, Registry};
+use crate::error::WindshieldError;
+use crate::frame::Frame;
+use crate::input::{Action, Bindings, GestureRecognizer, PointerEvent, PointerTracker};
+#[cfg(feature = "input-evdev")]
+use crate::input_map::{EvdevInput, InputMap};
+use crate::layer_shell::LayerShell;
+#[cfg(all(feature = "layer-shell", target_os = "linux"))]
+use crate::layer_shell::LayerSurface;
+use crate::logging::LogBuffer;
+use crate::mirror::Mirror;
+use crate::package::{self, PackageSwitch};
+use crate::pipeline::{COLOR_SHADER, WARP_SHADER};
+use crate::power::Power;
+use crate::prewarm::{VariantList, VariantRecorder};
+use crate::reload::FileWatcher;
+use crate::replay::{Recorder, Replay};
+use crate::rules::Rule;
+use crate::safe_area::SafeArea;
+use crate::scene::Scene;
+#[cfg(feature = "screen-reader")]
+use crate::screen_reader::ScreenReader;
+use crate::screensaver;
+use crate::settings::Settings;
+use crate::snapshot::Snapshot;
+use crate::startup::{StartupReport, StartupTimer};
+use crate::state::State;
+use crate::stats::FrameStats;
+use crate::theme::{ColorVision, ThemeMode, Themes};
+use crate::wake::Waker;
+use crate::warp::{self, Keystone};
+use crate::watchdog::{Heartbeat, Watchdog};
+#[cfg(target_arch = "wasm32")]
+use crate::web;
+
+type FrameCallback = Box<dyn FnMut(&FrameStats)>;
+type DrawCallback = Box<dyn FnMut(&mut Frame)>;
+type PointerCallback = Box<dyn FnMut(&PointerEvent) -> bool>;
+type SceneCallback = Box<dyn FnMut(&mut Scene)>;
+type StartupCallback = Box<dyn FnOnce(&StartupReport)>;
+type RuleCallback = Box<dyn FnMut(&Rule, bool)>;
+type PackageCallback = Box<dyn FnMut(&Path, Result<(), &WindshieldError>)>;
+
+/// The HUD renderer: owns the window, the GPU state and the event loop.
+///
+/// ```no_run
+/// # async fn example() {
+/// windshield_rs::WindshieldApp::builder()
+///   .with_title("HUD")
+///   .with_size(800, 480)
+///   .build()
+///   .run()
+///   .await
+///   .expect("unable to start HUD");
+/// # }
+/// ```
+pub struct WindshieldApp {
+  settings: Settings,
+  on_frame: Option<FrameCallback>,
+  on_draw: Option<DrawCallback>,
+  on_pointer: Option<PointerCallback>,
+  on_scene: Option<SceneCallback>,
+  on_startup: Option<StartupCallback>,
+  on_rule: Option<RuleCallback>,
+  on_package: Option<PackageCallback>,
+  packages: Option<PackageSwitch>,
+  sources: Registry,
+  watchdog: Option<Watchdog>,
+  clock: Option<Box<dyn Clock>>,
+}
+
+impl WindshieldApp {
+  pub fn new(settings: Settings) -> Self {
+    Self {
+      settings,
+      on_frame: None,
+      on_draw: None,
+      on_pointer: None,
+      on_scene: None,
+      on_startup: None,
+      on_rule: None,
+      on_package: None,
+      packages: None,
+      sources: Registry::new(),
+      watchdog: None,
+      clock: None,
+    }
+  }
+
+  pub fn builder() -> WindshieldAppBuilder {
+    WindshieldAppBuilder {
+      app: Self::new(Settings::default()),
+    }
+  }
+
+  /// Renders a single frame without a window and writes it to `path` as a
+  /// PNG, e.g. to compare widgets against golden images in CI or to
+  /// preview a layout without a display attached.
+  ///
+  /// The frame has the configured size, 800 × 480 if unset. It is the
+  /// first one the scene and `on_draw` draw: animations haven't started
+  /// and no data sources are read. A [restored](Settings::restore)
+  /// snapshot is shown as it was saved.
+  pub async fn screenshot(self, path: impl AsRef<Path>) -> Result<(), WindshieldError> {
+    let (mut state, mut scene, mut on_draw) = self.headless().await?;
+    let size = state.size;
+    state.update();
+    let pixels = state.screenshot(&mut |frame| {
+      if let Some(scene) = &mut scene {
+        scene.draw(frame);
+      }
+      if let Some(on_draw) = &mut on_draw {
+        on_draw(frame);
+      }
+    })?;
+    write_png(path.as_ref(), size, &pixels)
+  }
+
+  /// Draws frames without a window, one after the other as quickly as
+  /// they can be drawn, for `duration`, and returns how long each took
+  /// from updating until the GPU was done with it, see
+  /// [`BenchReport`](crate::bench::BenchReport).
+  ///
+  /// Set up like [screenshots](Self::screenshot): no data sources are read.
+  pub async fn bench(self, duration: Duration) -> Result<Vec<Duration>, WindshieldError> {
+    let (mut state, mut scene, mut on_draw) = self.headless().await?;
+    let mut draw = |frame: &mut Frame| {
+      if let Some(scene) = &mut scene {
+        scene.draw(frame);
+      }
+      if let Some(on_draw) = &mut on_draw {
+        on_draw(frame);
+      }
+    };
+    let mut times = Vec::new();
+    let started = Instant::now();
+    while started.elapsed() < duration {
+      let frame = Instant::now();
+      state.update();
+      state.render_offscreen(&mut draw);
+      times.push(frame.elapsed());
+    }
+    Ok(times)
+  }
+
+  /// The GPU state, scene and `on_draw` of a frame without a window, in
+  /// the configured size or 800 × 480.
+  async fn headless(self) -> Result<(State, Option<Scene>, Option<DrawCallback>), WindshieldError> {
+    let Self {
+      mut settings,
+      on_draw,
+      mut on_scene,
+      clock,
+      ..
+    } = self;
+    let mut config = settings.config.as_ref().map(Config::load).transpose()?;
+    if let Some(config) = &config {
+      config.apply(&mut settings);
+    }
+
+    let size = settings
+      .size
+      .map_or(PhysicalSize::new(800, 480), |size| size.to_physical(1.0));
+    let mut state = State::headless(&settings, clock, size).await?;
+    let mut scene = match &config {
+      Some(config) => Some(config.scene(&mut state.text)?),
+      None => None,
+    };
+    if let Some(path) = &settings.restore {
+      restore(&Snapshot::load(path)?, &mut state, &mut config, &mut scene);
+    }
+    set_up(&mut scene, &mut on_scene);
+    if let Some(scene) = &mut scene {
+      state.show_rules(scene);
+    }
+    state.startup_phase("assets");
+    // With the config's fonts registered
+    prewarm(&mut state, &settings);
+    Ok((state, scene, on_draw))
+  }
+
+  /// Opens the window and runs the event loop until the window is closed.
+  ///
+  /// Only returns if initialization fails; closing the window exits the
+  /// process.
+  pub async fn run(self) -> Result<(), WindshieldError> {
+    let Self {
+      mut settings,
+      mut on_frame,
+      mut on_draw,
+      mut on_pointer,
+      mut on_scene,
+      mut on_startup,
+      mut on_rule,
+      mut on_package,
+      packages,
+      mut sources,
+      mut watchdog,
+      clock,
+    } = self;
+
+    let mut startup = StartupTimer::new();
+    // Kept to start over from when the config is reloaded, with the config
+    // of the package switched to
+    let mut defaults = settings.clone();
+    let mut config = load_config(&settings).await?;
+    if let Some(config) = &config {
+      config.apply(&mut settings);
+    }
+    startup.set_budget(settings.startup_budget);
+    startup.phase("config");
+
+    let crash = settings
+      .crash_dir
+      .clone()
+      .map(|dir| CrashReporter::install(dir, settings.log_buffer.clone()));
+    let event_loop = EventLoop::new();
+    // Input and data from other threads wake the loop, which may be waiting
+    // for the next heartbeat
+    let waker = Waker::event_loop(event_loop.create_proxy());
+    let mut builder = WindowBuilder::new()
+      .with_title(&settings.title)
+      .with_transparent(settings.is_transparent())
+      .with_always_on_top(settings.always_on_top);
+    if let Some(size) = settings.size {
+      builder = builder.with_inner_size(size);
+    }
+    #[cfg(target_arch = "wasm32")]
+    {
+      use winit::platform::web::WindowBuilderExtWebSys;
+      builder = builder.with_canvas(settings.canvas.clone());
+    }
+    if settings.overlay {
+      builder = builder
+        .with_decorations(false)
+        .with_always_on_top(true)
+        .with_maximized(true);
+    }
+    // AccessKit has to be set up before the window is first shown
+    #[cfg(feature = "screen-reader")]
+    let builder = builder.with_visible(false);
+    let window = builder.build(&event_loop)?;
+    #[cfg(feature = "screen-reader")]
+    let screen_reader = {
+      let screen_reader = ScreenReader::new(&window, &settings.title, waker.clone());
+      window.set_visible(true);
+      screen_reader
+    };
+    window.set_fullscreen(settings.fullscreen.map(|mode| fullscreen(mode, &window)));
+    window.set_cursor_visible(!settings.hide_cursor);
+    if settings.keep_awake {
+      screensaver::inhibit(&window);
+    }
+    if settings.overlay {
+      // Let clicks fall through to whatever is below the HUD
+      if let Err(err) = window.set_cursor_hittest(false) {
+        tracing::warn!("unable to make overlay click-through: {}", err);
+      }
+    }
+    // Follows the layout of the page unless given a size
+    #[cfg(target_arch = "wasm32")]
+    let fit = {
+      web::attach(&window);
+      settings.size.is_none()
+    };
+
+    startup.phase("window");
+    #[cfg(target_os = "android")]
+    {
+      android::wait_for_window();
+      android::keep_screen_on();
+    }
+
+    #[cfg(all(feature = "layer-shell", target_os = "linux"))]
+    let mut layer = settings
+      .layer_shell
+      .as_ref()
+      .and_then(|shell| layer_surface(shell, &settings, &window));
+    #[cfg(not(all(feature = "layer-shell", target_os = "linux")))]
+    if settings.layer_shell.is_some() {
+      tracing::warn!("built without the layer-shell feature, opening a window instead");
+    }
+    // The window only runs the event loop then, its size doesn't matter
+    #[cfg(all(feature = "layer-shell", target_os = "linux"))]
+    let layered = layer.is_some();
+    #[cfg(not(all(feature = "layer-shell", target_os = "linux")))]
+    let layered = false;
+    #[cfg(all(feature = "layer-shell", target_os = "linux"))]
+    let mut state = match &layer {
+      Some(layer) => State::on_layer(layer, &settings, clock, startup, crash).await?,
+      None => State::new(&window, &settings, clock, startup, crash).await?,
+    };
+    #[cfg(not(all(feature = "layer-shell", target_os = "linux")))]
+    let mut state = State::new(&window, &settings, clock, startup, crash).await?;
+    // Layer surfaces are drawn at their physical size
+    if !layered {
+      state.set_scale_factor(window.scale_factor());
+    }
+    if let Some(device) = &settings.obd {
+      sources.add(ObdSource::new(device.clone(), obd::DEFAULT_BAUD_RATE));
+    }
+    if let Some(device) = &settings.gps {
+      sources.add(GpsSource::new(device.clone()));
+    }
+    if let Some(device) = &settings.light_sensor {
+      sources.add(LightSensor::new(device));
+    }
+    if settings.deterministic {
+      // Telemetry arrives whenever it does, which would change the frames
+      if !sources.is_empty() {
+        tracing::warn!("not reading data sources in deterministic mode");
+      }
+    } else if !sources.is_empty() {
+      state.add_source(sources.start(waker.clone()));
+    }
+    #[cfg(feature = "input-evdev")]
+    let input = match &settings.input_map {
+      Some(path) => open_input(path, waker.clone())?,
+      None => None,
+    };
+    #[cfg(not(feature = "input-evdev"))]
+    if settings.input_map.is_some() {
+      tracing::warn!("built without the input-evdev feature, ignoring the input map");
+    }
+    let mut scene = match &config {
+      Some(config) => Some(config.scene(&mut state.text)?),
+      None => None,
+    };
+    if let Some(path) = &settings.restore {
+      restore(&Snapshot::load(path)?, &mut state, &mut config, &mut scene);
+    }
+    set_up(&mut scene, &mut on_scene);
+    state.startup_phase("assets");
+    // With the config's fonts registered
+    prewarm(&mut state, &settings);
+    // What M switches between, mirroring horizontally unless configured
+    // otherwise
+    let mirror = match settings.mirror {
+      Mirror::None => Mirror::Horizontal,
+      mirror => mirror,
+    };
+    let backlight = settings.backlight.as_deref().map(Backlight::new);
+    // Done once the events in the queue are handled
+    let mut actions = Vec::new();
+    // What F11 switches to
+    let fullscreen_mode = settings.fullscreen.unwrap_or(Fullscreen::Borderless);
+    // Corner of the keystone moved by the arrow keys while calibrating
+    let mut calibrating = None;
+    let mut pointers = PointerTracker::default();
+    let mut gestures = GestureRecognizer::default();
+    if let Some(packages) = &packages {
+      packages.set_waker(waker.clone());
+      // So the first switch can be rolled back to the package started with
+      if let Some(dir) = settings.config.as_deref().and_then(package::package_dir) {
+        packages.started(dir);
+      }
+    }
+    let mut watcher = if settings.hot_reload {
+      watch(&settings, theme_file(&config), waker.clone())
+    } else {
+      None
+    };
+
+    let mut recorder = settings
+      .record
+      .as_deref()
+      .map(Recorder::create)
+      .transpose()?;
+    let mut replay = settings.replay.as_deref().map(Replay::load).transpose()?;
+    // Drawn so far, what recorded input is played back by
+    let mut frames = 0;
+
+    event_loop.run(move |event, _, control_flow| {
+      // Played back ahead of what just came in, as it came first
+      let replayed = replay
+        .as_mut()
+        .map_or(Vec::new(), |replay| replay.due(frames));
+      let replayed = replayed.into_iter().map(|event| Event::WindowEvent {
+        window_id: window.id(),
+        event,
+      });
+      for event in replayed.chain(std::iter::once(event)) {
+        match event {
+          Event::WindowEvent {
+            ref event,
+            window_id,
+          } if window_id == window.id() => {
+            // What the inspector uses doesn't reach the HUD
+            #[cfg(feature = "inspector")]
+            if state.inspector_event(event) {
+              continue;
+            }
+            #[cfg(feature = "screen-reader")]
+            screen_reader.on_event(&window, event);
+            if let Some(recorder) = &mut recorder {
+              recorder.record(frames, event);
+            }
+            // Typing while the logs or diagnostics are shown goes to them
+            if state.logs_event(event) || state.diagnostics_event(event) {
+              continue;
+            }
+            // The arrow keys and Tab move the keystone while calibrating
+            // instead of doing what they're bound to
+            let calibrated = match (&mut calibrating, event) {
+              (
+                Some(corner),
+                WindowEvent::KeyboardInput {
+                  input:
+                    KeyboardInput {
+                      state: ElementState::Pressed,
+                      virtual_keycode: Some(key),
+                      ..
+                    },
+                  ..
+                },
+              ) => calibrate(*key, corner, &mut state),
+              _ => false,
+            };
+            if calibrated {
+              state.invalidate();
+            } else {
+              actions.extend(state.input(event));
+            }
+            match event {
+              WindowEvent::CloseRequested
+              | WindowEvent::KeyboardInput {
+                input:
+                  KeyboardInput {
+                    state: ElementState::Pressed,
+                    virtual_keycode: Some(VirtualKeyCode::Escape),
+                    ..
+                  },
+                ..
+              } => *control_flow = ControlFlow::Exit,
+              WindowEvent::Resized(_) | WindowEvent::ScaleFactorChanged { .. } if layered => {}
+              WindowEvent::Resized(physical_size) => {
+                state.resize(*physical_size);
+              }
+              WindowEvent::ScaleFactorChanged {
+                scale_factor,
+                new_inner_size,
+              } => {
+                // Moved to another display, text and pixel sizes follow
+                state.set_scale_factor(*scale_factor);
+                // new_inner_size is &&mut so we have to dereference it twice
+                state.resize(**new_inner_size);
+              }
+              event => {
+                // Nothing to touch on a blank display
+                let pointer = pointers
+                  .translate(event)
+                  .filter(|_| state.power() == Power::On);
+                if let Some(mut pointer) = pointer {
+                  // Touches land on the warped and mirrored image, widgets
+                  // expect where it was drawn
+                  let size = [state.size.width as f32, state.size.height as f32];
+                  let unwarped = state.keystone().unwarp(pointer.position, size);
+                  pointer.position = state.mirror().apply(unwarped, size);
+                  // What on_draw draws is on top of the scene, so it goes first
+                  let used = on_pointer
+                    .as_mut()
+                    .is_some_and(|on_pointer| on_pointer(&pointer))
+                    || scene.as_mut().is_some_and(|scene| scene.pointer(&pointer));
+                  // Whatever nobody used may still swipe between pages
+                  let gesture = gestures.pointer(&pointer, used, state.now());
+                  if let Some(action) = gesture.and_then(|trigger| state.bound(trigger)) {
+                    actions.push(action);
+                  }
+                }
+              }
+            }
+          }
+          // Android takes the window away while the app is in the background
+          Event::Suspended => state.suspend(),
+          Event::Resumed => state.resume(&window),
+          Event::RedrawRequested(window_id)
+            if window_id == window.id() && !state.is_suspended() =>
+          {
+            frames += 1;
+            state.update();
+            for (rule, active) in state.rule_changes() {
+              if let Some(on_rule) = &mut on_rule {
+                on_rule(rule, active);
+              }
+            }
+            if let Some(scene) = &mut scene {
+              state.show_rules(scene);
+            }
+            #[cfg(feature = "inspector")]
+            state.inspect(&window, scene.as_mut());
+            let mirror = state.mirror();
+            let mut draw = |frame: &mut Frame| {
+              if let Some(scene) = &mut scene {
+                scene.draw(frame);
+              }
+              if let Some(on_draw) = &mut on_draw {
+                on_draw(frame);
+              }
+              if let Some(corner) = calibrating {
+                let size = [frame.width as f32, frame.height as f32];
+                let [x, y] = Keystone::default().corners[corner];
+                let handle = mirror.apply([x * size[0], y * size[1]], size);
+                warp::draw_calibration(frame, handle);
+              }
+            };
+            match state.render(&mut draw) {
+              Ok(stats) => {
+                #[cfg(feature = "screen-reader")]
+                if let Some(scene) = &scene {
+                  screen_reader.update(scene);
+                }
+                if let Some(watchdog) = &mut watchdog {
+                  watchdog.presented();
+                }
+                if let Some(on_frame) = &mut on_frame {
+                  on_frame(&stats);
+                }
+                if let Some(report) = state.take_startup_report() {
+                  if let Some(on_startup) = on_startup.take() {
+                    on_startup(&report);
+                  }
+                }
+              }
+              // Reconfigure the surface if lost
+              Err(SurfaceError::Lost) => state.surface_lost(),
+              // The system is out of memory, we should probably quit
+              Err(SurfaceError::OutOfMemory) => {
+                if let Some(crash) = &state.crash {
+                  crash.write("surface out of memory");
+                }
+                *control_flow = ControlFlow::Exit
+              }
+              // All other errors (Outdated, Timeout) should be resolved by the next frame
+              Err(e) => eprintln!("{:?}", e),
+            }
+          }
+          Event::MainEventsCleared => {
+            // Resized and closed by the compositor, noticed once awake
+            #[cfg(all(feature = "layer-shell", target_os = "linux"))]
+            if let Some(surface) = &mut layer {
+              match surface.poll() {
+                Ok(Some(size)) => state.resize(size),
+                Ok(None) => {}
+                Err(()) => *control_flow = ControlFlow::Exit,
+              }
+            }
+            #[cfg(target_arch = "wasm32")]
+            if fit {
+              web::fit(&window);
+            }
+            #[cfg(feature = "input-evdev")]
+            if let Some(input) = &input {
+              actions.extend(input.actions());
+            }
+            let long_press = gestures.poll(state.now());
+            actions.extend(long_press.and_then(|trigger| state.bound(trigger)));
+            actions.extend(state.poll_buttons());
+            #[cfg(feature = "screen-reader")]
+            if let Some(scene) = &mut scene {
+              actions.extend(screen_reader.take_actions(scene));
+            }
+            // Turns the display off and on as soon as the ignition is reported
+            state.poll_sources();
+            if let Some(power) = state.poll_ignition() {
+              set_power(power, &mut state, backlight.as_ref());
+            }
+            for action in actions.drain(..) {
+              state.invalidate();
+              match action {
+                Action::Calibrate => calibrating = toggle_calibration(calibrating, &state),
+                Action::Fullscreen => window.set_fullscreen(match window.fullscreen() {
+                  Some(_) => None,
+                  None => Some(fullscreen(fullscreen_mode, &window)),
+                }),
+                Action::Units => toggle_units(&mut config, &mut state, &mut scene),
+                Action::Snapshot => {
+                  let dir = settings.snapshot_dir.clone().unwrap_or_default();
+                  match snapshot(&state, &config, &scene).save_in(dir) {
+                    Ok(path) => tracing::info!("snapshot saved to {}", path.display()),
+                    Err(err) => tracing::error!("{}", err),
+                  }
+                }
+                Action::BrightnessUp | Action::BrightnessDown => match &backlight {
+                  Some(backlight) if action == Action::BrightnessUp => {
+                    backlight.adjust(backlight::STEP)
+                  }
+                  Some(backlight) => backlight.adjust(-backlight::STEP),
+                  None => tracing::warn!("no backlight to change the brightness of"),
+                },
+                Action::Power => set_power(state.power().toggled(), &mut state, backlight.as_ref()),
+                #[cfg(feature = "inspector")]
+                Action::Inspector => state.toggle_inspector(),
+                #[cfg(not(feature = "inspector"))]
+                Action::Inspector => tracing::warn!("built without the inspector feature"),
+                Action::Diagnostics => state.toggle_diagnostics(),
+                Action::Logs => state.toggle_logs(),
+                Action::About => state.toggle_about(),
+                Action::Accessibility => state.toggle_accessibility(),
+                Action::ColorVision => state.cycle_simulated_color_vision(),
+                Action::Back if state.about_open() => state.toggle_about(),
+                action if action.is_navigation() && state.logs_open() => state.logs_action(action),
+                action if action.is_navigation() && state.diagnostics_open() => {
+                  state.diagnostics_action(action)
+                }
+                action if action.is_navigation() => {
+                  if let Some(scene) = &mut scene {
+                    scene.action(action);
+                  }
+                }
+                action => display_action(action, mirror, &mut state),
+              }
+            }
+            if let Some(dir) = packages.as_ref().and_then(PackageSwitch::take) {
+              let switched = switch_package(
+                &dir,
+                &mut defaults,
+                &window,
+                &mut state,
+                &mut config,
+                &mut scene,
+              );
+              match &switched {
+                Ok(()) => {
+                  tracing::info!("switched to package {}", dir.display());
+                  if watcher.is_some() {
+                    watcher = watch(&defaults, theme_file(&config), waker.clone());
+                  }
+                }
+                Err(err) => tracing::error!("not switching to package {}: {}", dir.display(), err),
+              }
+              if let Some(on_package) = &mut on_package {
+                on_package(&dir, switched.as_ref().copied());
+              }
+              if let (Ok(()), Some(packages)) = (&switched, &packages) {
+                packages.switched(dir);
+              }
+            }
+            if let Some(current) = &watcher {
+              let mut reload = false;
+              for path in current.changed() {
+                state.invalidate();
+                if Some(path) == defaults.config.as_deref() || Some(path) == theme_file(&config) {
+                  reload = true;
+                } else if let Some(dir) = &defaults.shader_dir {
+                  state.reload_shaders(dir);
+                }
+              }
+              if let (true, Some(path)) = (reload, &defaults.config) {
+                let watched = theme_file(&config).map(Path::to_path_buf);
+                reload_config(
+                  path,
+                  &defaults,
+                  &window,
+                  &mut state,
+                  &mut config,
+                  &mut scene,
+                );
+                // Follows the config to another theme file
+                if theme_file(&config) != watched.as_deref() {
+                  watcher = watch(&defaults, theme_file(&config), waker.clone());
+                }
+              }
+            }
+            // Scenes rebuilt by the actions and reloads above
+            set_up(&mut scene, &mut on_scene);
+            // Frames come back to back until the recording caught up
+            match &replay {
+              Some(playing) if playing.is_finished() => {
+                tracing::info!("replayed the recording after {} frames", frames);
+                replay = None;
+              }
+              Some(_) => state.invalidate(),
+              None => {}
+            }
+            // An idle HUD still draws a frame for every watchdog heartbeat
+            let watchdog_due = watchdog.as_ref().and_then(Watchdog::due);
+            if watchdog_due.is_some_and(|due| due <= Instant::now()) {
+              state.invalidate();
+            }
+            // RedrawRequested will only trigger once, unless we manually
+            // request it.
+            let next = [
+              state.next_frame(),
+              watchdog_due.filter(|due| *due > Instant::now()),
+              // Wakes up in time to tell a long press from a finger held still
+              gestures.due().map(|due| state.wake_at(due)),
+              // And to tell a key or mouse button pressed once from held or
+              // pressed twice
+              state.buttons_due(),
+              // And to turn the display off once the ignition stayed off
+              state.ignition_due(),
+            ]
+            .into_iter()
+            .flatten()
+            .min();
+            match next {
+              _ if state.is_suspended() => *control_flow = ControlFlow::Wait,
+              // Only input and data wake a display that's off
+              None => *control_flow = ControlFlow::Wait,
+              Some(next) if next > Instant::now() => *control_flow = ControlFlow::WaitUntil(next),
+              Some(_) => {
+                *control_flow = ControlFlow::Poll;
+                window.request_redraw();
+              }
+            }
+          }
+          _ => {}
+        }
+      }
+    });
+  }
+}
+
+/// A layer surface as configured, the configured size or the window's
+/// along edges it isn't stretched between, hiding the window. `None` if
+/// the compositor has no layer shell, or isn't Wayland's.
+#[cfg(all(feature = "layer-shell", target_os = "linux"))]
+fn layer_surface(shell: &LayerShell, settings: &Settings, window: &Window) -> Option<LayerSurface> {
+  let size = settings.size.map_or(window.inner_size(), |size| {
+    size.to_physical(window.scale_factor())
+  });
+  match LayerSurface::create(shell, size) {
+    Ok(layer) => {
+      window.set_visible(false);
+      Some(layer)
+    }
+    Err(err) => {
+      tracing::warn!("{}, opening a window instead", err);
+      None
+    }
+  }
+}
+
+fn write_png(path: &Path, size: PhysicalSize<u32>, pixels: &[u8]) -> Result<(), WindshieldError> {
+  let error = |source| WindshieldError::WriteImage {
+    path: path.to_path_buf(),
+    source,
+  };
+  let file = File::create(path).map_err(|err| error(err.into()))?;
+  let mut encoder = png::Encoder::new(BufWriter::new(file), size.width, size.height);
+  encoder.set_color(png::ColorType::Rgba);
+  encoder.set_depth(png::BitDepth::Eight);
+  encoder.set_srgb(png::SrgbRenderingIntent::Perceptual);
+  let mut writer = encoder.write_header().map_err(error)?;
+  writer.write_image_data(pixels).map_err(error)
+}
+
+/// Fullscreen on the monitor `window` is on, borderless if the monitor
+/// isn't known or has no video modes to switch to.
+fn fullscreen(mode: Fullscreen, window: &Window) -> winit::window::Fullscreen {
+  let monitor = window.current_monitor();
+  let video_mode = match (mode, &monitor) {
+    (Fullscreen::Exclusive, Some(monitor)) => monitor.video_modes().max_by_key(|video_mode| {
+      let size = video_mode.size();
+      (
+        size.width * size.height,
+        video_mode.refresh_rate_millihertz(),
+      )
+    }),
+    _ => None,
+  };
+  match video_mode {
+    Some(video_mode) => winit::window::Fullscreen::Exclusive(video_mode),
+    None => winit::window::Fullscreen::Borderless(monitor),
+  }
+}
+
+/// Blanks the display or shows it again, powering the backlight down and
+/// back up along with it if there is one.
+fn set_power(power: Power, state: &mut State, backlight: Option<&Backlight>) {
+  if power == state.power() {
+    return;
+  }
+  tracing::info!("display {:?}", power);
+  state.set_power(power);
+  if let Some(backlight) = backlight {
+    if let Err(err) = backlight.set_power(power == Power::On) {
+      tracing::warn!("unable to power the backlight {:?}: {}", power, err);
+    }
+  }
+}
+
+/// Applies an action changing how everything is shown, `mirror` being
+/// what mirroring toggles to.
+fn display_action(action: Action, mirror: Mirror, state: &mut State) {
+  match action {
+    Action::Mirror => state.set_mirror(if state.mirror() == Mirror::None {
+      mirror
+    } else {
+      Mirror::None
+    }),
+    Action::HighContrast => state.set_high_contrast(!state.high_contrast()),
+    Action::Theme => {
+      let mode = state.theme_mode().next();
+      tracing::info!("theme mode {:?}", mode);
+      state.set_theme_mode(mode);
+    }
+    _ => {}
+  }
+}
+
+/// Starts reading the devices of the input map at `path`. Devices that
+/// can't be opened only disable input, the HUD is still useful without.
+#[cfg(feature = "input-evdev")]
+fn open_input(path: &Path, waker: Waker) -> Result<Option<EvdevInput>, WindshieldError> {
+  let map = InputMap::load(path)?;
+  match EvdevInput::open_waking(&map, waker) {
+    Ok(input) => Ok(Some(input)),
+    Err(err) => {
+      tracing::warn!("unable to open input devices: {}", err);
+      Ok(None)
+    }
+  }
+}
+
+/// Loads the config file the settings name, if any.
+async fn load_config(settings: &Settings) -> Result<Option<Config>, WindshieldError> {
+  match &settings.config {
+    #[cfg(target_arch = "wasm32")]
+    Some(url) => web::fetch_config(url).await.map(Some),
+    #[cfg(not(target_arch = "wasm32"))]
+    Some(path) => Config::load(path).map(Some),
+    None => Ok(None),
+  }
+}
+
+/// The theme file of the config, if it has one, see [`Theme::file`].
+///
+/// [`Theme::file`]: crate::config::Theme::file
+fn theme_file(config: &Option<Config>) -> Option<&Path> {
+  config.as_ref()?.theme.file.as_deref()
+}
+
+/// Watches the files hot reloading applies to, if it can.
+fn watch(settings: &Settings, theme_file: Option<&Path>, waker: Waker) -> Option<FileWatcher> {
+  if cfg!(target_arch = "wasm32") {
+    tracing::warn!("there are no files to watch on the web, hot reload is disabled");
+    return None;
+  }
+  let mut paths = Vec::new();
+  if let Some(config) = &settings.config {
+    paths.push(config.clone());
+  }
+  paths.extend(theme_file.map(Path::to_path_buf));
+  if let Some(dir) = &settings.shader_dir {
+    paths.push(dir.join(COLOR_SHADER.0));
+    paths.push(dir.join(WARP_SHADER.0));
+  }
+
+  match FileWatcher::new(&paths, waker) {
+    Ok(watcher) => Some(watcher),
+    Err(err) => {
+      tracing::warn!("unable to watch files, hot reload is disabled: {}", err);
+      None
+    }
+  }
+}
+
+/// Starts calibrating the keystone at the first corner, or ends and logs it.
+fn toggle_calibration(calibrating: Option<usize>, state: &State) -> Option<usize> {
+  match calibrating {
+    Some(_) => {
+      tracing::info!(
+        "keystone calibrated, set `keystone = {}` under [display] in the config",
+        state.keystone()
+      );
+      None
+    }
+    None => Some(0),
+  }
+}
+
+/// Rebuilds the scene in the other units, keeping the current one if that
+/// fails.
+fn toggle_units(config: &mut Option<Config>, state: &mut State, scene: &mut Option<Scene>) {
+  let Some(config) = config else {
+    return;
+  };
+  let mut toggled = config.clone();
+  toggled.units = config.units.toggled();
+  match toggled.scene(&mut state.text) {
+    Ok(new_scene) => {
+      tracing::info!("units {:?}", toggled.units);
+      *config = toggled;
+      replace_scene(scene, new_scene);
+    }
+    Err(err) => tracing::error!("unable to switch units: {}", err),
+  }
+}
+
+/// Lets `on_scene` set up a scene built from the config, once per scene.
+fn set_up(scene: &mut Option<Scene>, on_scene: &mut Option<SceneCallback>) {
+  if let (Some(scene), Some(on_scene)) = (scene, on_scene) {
+    if !scene.set_up {
+      scene.set_up = true;
+      on_scene(scene);
+    }
+  }
+}
+
+/// Draws what `settings` asks to prewarm, and starts recording what's
+/// drawn into its prewarm list.
+fn prewarm(state: &mut State, settings: &Settings) {
+  // Everything until there's a list
+  let list = settings
+    .prewarm_list
+    .as_ref()
+    .filter(|path| path.exists())
+    .and_then(|path| match VariantList::load(path) {
+      Ok(list) => Some(list),
+      Err(err) => {
+        tracing::warn!("{}", err);
+        None
+      }
+    });
+  if settings.prewarm {
+    let variants = match &list {
+      Some(list) => list.variants.iter().copied().collect(),
+      None => state.registered_variants(),
+    };
+    state.prewarm(&variants);
+  }
+  if let Some(path) = &settings.prewarm_list {
+    let recorder = VariantRecorder::new(path.clone(), list.unwrap_or_default());
+    state.record_variants(recorder);
+  }
+}
+
+/// Swaps in a rebuilt scene, staying on the page that was shown.
+fn replace_scene(scene: &mut Option<Scene>, mut new_scene: Scene) {
+  if let Some(scene) = scene {
+    new_scene.show_page(scene.page());
+  }
+  *scene = Some(new_scene);
+}
+
+/// What's shown, to save as a snapshot.
+fn snapshot(state: &State, config: &Option<Config>, scene: &Option<Scene>) -> Snapshot {
+  Snapshot {
+    page: scene.as_ref().map_or(0, Scene::page),
+    units: config.as_ref().map(|config| config.units),
+    telemetry: state.telemetry().clone(),
+    mirror: state.mirror(),
+    high_contrast: state.high_contrast(),
+    theme_mode: state.theme_mode(),
+    keystone: state.keystone(),
+    ..Snapshot::default()
+  }
+}
+
+/// Shows what `snapshot` saved, rebuilding the scene if it was in other
+/// units.
+fn restore(
+  snapshot: &Snapshot,
+  state: &mut State,
+  config: &mut Option<Config>,
+  scene: &mut Option<Scene>,
+) {
+  if let (Some(config), Some(units)) = (config.as_mut(), snapshot.units) {
+    if config.units != units {
+      config.units = units;
+      match config.scene(&mut state.text) {
+        Ok(new_scene) => *scene = Some(new_scene),
+        Err(err) => tracing::error!("unable to switch units: {}", err),
+      }
+    }
+  }
+  if let Some(scene) = scene {
+    scene.show_page(snapshot.page);
+  }
+  state.set_telemetry(snapshot.telemetry.clone());
+  state.set_mirror(snapshot.mirror);
+  state.set_high_contrast(snapshot.high_contrast);
+  state.set_theme_mode(snapshot.theme_mode);
+  state.set_keystone(snapshot.keystone);
+}
+
+/// Moves the keystone `corner` with the arrow keys, `Tab` moves on to the
+/// next one. Returns whether `key` was one of them.
+fn calibrate(key: VirtualKeyCode, corner: &mut usize, state: &mut State) -> bool {
+  let step = warp::CALIBRATION_STEP;
+  let [dx, dy] = match key {
+    VirtualKeyCode::Left => [-step, 0.0],
+    VirtualKeyCode::Right => [step, 0.0],
+    VirtualKeyCode::Up => [0.0, -step],
+    VirtualKeyCode::Down => [0.0, step],
+    VirtualKeyCode::Tab => {
+      *corner = (*corner + 1) % 4;
+      return true;
+    }
+    _ => return false,
+  };
+  let mut keystone = state.keystone();
+  let [x, y] = &mut keystone.corners[*corner];
+  *x += dx;
+  *y += dy;
+  state.set_keystone(keystone);
+  true
+}
+
+/// Rebuilds the scene from a changed config file, keeping the current one
+/// if the file is invalid.
+fn reload_config(
+  path: &Path,
+  defaults: &Settings,
+  window: &Window,
+  state: &mut State,
+  config: &mut Option<Config>,
+  scene: &mut Option<Scene>,
+) {
+  let reloaded = Config::load(path).and_then(|config| Ok((config.scene(&mut state.text)?, config)));
+  match reloaded {
+    Ok((new_scene, new_config)) => {
+      apply_config(
+        new_config, new_scene, defaults, window, state, config, scene,
+      );
+      tracing::info!("reloaded {}", path.display());
+    }
+    Err(err) => tracing::error!("{}", err),
+  }
+}
+
+/// Switches to the package in `dir`, see [`package`](crate::package).
+/// Nothing changes unless its config loads and its scene can be built.
+fn switch_package(
+  dir: &Path,
+  defaults: &mut Settings,
+  window: &Window,
+  state: &mut State,
+  config: &mut Option<Config>,
+  scene: &mut Option<Scene>,
+) -> Result<(), WindshieldError> {
+  let path = package::config_path(dir);
+  let new_config = Config::load(&path)?;
+  let new_scene = new_config.scene(&mut state.text)?;
+  apply_config(
+    new_config, new_scene, defaults, window, state, config, scene,
+  );
+  defaults.config = Some(path);
+  state.invalidate();
+  Ok(())
+}
+
+/// Shows `new_scene` and applies the settings of `new_config` that differ
+/// from those of `config`, both on top of `defaults`.
+fn apply_config(
+  new_config: Config,
+  new_scene: Scene,
+  defaults: &Settings,
+  window: &Window,
+  state: &mut State,
+  config: &mut Option<Config>,
+  scene: &mut Option<Scene>,
+) {
+  let mut old = defaults.clone();
+  if let Some(config) = config {
+    config.apply(&mut old);
+  }
+  let mut settings = defaults.clone();
+  new_config.apply(&mut settings);
+  let restart = restart_needed(&old, &settings);
+  if !restart.is_empty() {
+    tracing::warn!("restart to apply the new {}", restart.join(", "));
+  }
+  // Only what changed, so fullscreen isn't entered again and what was
+  // toggled while running, like the mirror, stays so
+  let mut applied = Vec::new();
+  if settings.title != old.title {
+    window.set_title(&settings.title);
+    applied.push("title");
+  }
+  // Keeps the size the window was given since, unless it changed
+  if let Some(size) = settings.size.filter(|_| settings.size != old.size) {
+    window.set_inner_size(size);
+    applied.push("size");
+  }
+  if settings.keep_awake && !old.keep_awake {
+    screensaver::inhibit(window);
+    applied.push("keep_awake");
+  }
+  if settings.fullscreen != old.fullscreen {
+    window.set_fullscreen(settings.fullscreen.map(|mode| fullscreen(mode, window)));
+    applied.push("fullscreen");
+  }
+  if settings.hide_cursor != old.hide_cursor {
+    window.set_cursor_visible(!settings.hide_cursor);
+    applied.push("hide_cursor");
+  }
+  if settings.always_on_top != old.always_on_top {
+    window.set_always_on_top(settings.always_on_top || settings.overlay);
+    applied.push("always_on_top");
+  }
+  if settings.clear_color() != old.clear_color() {
+    state.set_clear_color(settings.clear_color());
+    applied.push("background");
+  }
+  if settings.themes != old.themes {
+    state.set_themes(settings.themes.clone());
+    applied.push("theme");
+  }
+  if settings.mirror != old.mirror {
+    state.set_mirror(settings.mirror);
+    applied.push("mirror");
+  }
+  if settings.high_contrast != old.high_contrast {
+    state.set_high_contrast(settings.high_contrast);
+    applied.push("high_contrast");
+  }
+  if settings.reduce_motion != old.reduce_motion {
+    state.set_reduce_motion(settings.reduce_motion);
+    applied.push("reduce_motion");
+  }
+  if settings.right_to_left != old.right_to_left {
+    state.set_right_to_left(settings.right_to_left);
+    applied.push("right_to_left");
+  }
+  if settings.keystone != old.keystone {
+    state.set_keystone(settings.keystone);
+    applied.push("keystone");
+  }
+  if settings.simulate_color_vision != old.simulate_color_vision {
+    state.set_simulated_color_vision(settings.simulate_color_vision);
+    applied.push("simulate_color_vision");
+  }
+  if settings.checksum_regions != old.checksum_regions {
+    state.set_checksum_regions(settings.checksum_regions.clone());
+    applied.push("checksums");
+  }
+  if settings.present_mode != old.present_mode {
+    state.set_present_mode(settings.present_mode);
+    applied.push("present_mode");
+  }
+  if settings.max_fps != old.max_fps {
+    state.set_max_fps(settings.max_fps);
+    applied.push("max_fps");
+  }
+  if settings.heartbeat != old.heartbeat {
+    state.set_heartbeat(settings.heartbeat);
+    applied.push("heartbeat");
+  }
+  if settings.stale_after != old.stale_after {
+    state.set_stale_after(settings.stale_after);
+    applied.push("stale_after");
+  }
+  if settings.burn_in != old.burn_in {
+    state.set_burn_in(settings.burn_in);
+    applied.push("burn_in");
+  }
+  if settings.accessibility != old.accessibility {
+    state.set_accessibility(settings.accessibility);
+    applied.push("accessibility");
+  }
+  if settings.ignition_off_delay != old.ignition_off_delay {
+    state.set_ignition_off_delay(settings.ignition_off_delay);
+    applied.push("ignition_off_delay");
+  }
+  if settings.safe_area != old.safe_area {
+    state.set_safe_area(settings.safe_area);
+    applied.push("safe_area");
+  }
+  if settings.ui_scale != old.ui_scale {
+    state.set_ui_scale(settings.ui_scale);
+    applied.push("ui_scale");
+  }
+  if settings.priorities != old.priorities || settings.fallback_after != old.fallback_after {
+    state.set_priorities(&settings.priorities, settings.fallback_after);
+    applied.push("priorities");
+  }
+  if settings.signals != old.signals {
+    match state.set_signals(&settings.signals) {
+      Ok(()) => applied.push("signals"),
+      Err(err) => tracing::error!("{}", err),
+    }
+  }
+  if settings.rules != old.rules {
+    state.set_rules(settings.rules.clone());
+    applied.push("rules");
+  }
+  if settings.bindings != old.bindings {
+    state.set_bindings(settings.bindings);
+    applied.push("bindings");
+  }
+  if !applied.is_empty() {
+    tracing::info!("applied the new {}", applied.join(", "));
+  }
+  *config = Some(new_config);
+  replace_scene(scene, new_scene);
+}
+
+/// Settings that changed from `old` to `new` but only apply to a new
+/// window.
+fn restart_needed(old: &Settings, new: &Settings) -> Vec<&'static str> {
+  let mut names = Vec::new();
+  if old.is_transparent() != new.is_transparent() {
+    names.push("transparent");
+  }
+  // The screen saver is only resumed once the window is gone
+  if old.keep_awake && !new.keep_awake {
+    names.push("keep_awake");
+  }
+  // Or the other way around, either is only opened at startup
+  if old.layer_shell != new.layer_shell {
+    names.push("layer_shell");
+  }
+  names
+}
+
+/// Configures a [`WindshieldApp`] before it is started.
+pub struct WindshieldAppBuilder {
+  app: WindshieldApp,
+}
+
+impl WindshieldAppBuilder {
+  /// Replaces all settings at once, e.g. with ones loaded from elsewhere.
+  pub fn with_settings(mut self, settings: Settings) -> Self {
+    self.app.settings = settings;
+    self
+  }
+
+  pub fn with_title(mut self, title: impl Into<String>) -> Self {
+    self.app.settings.title = title.into();
+    self
+  }
+
+  /// Initial inner size of the window in logical pixels.
+  pub fn with_size(mut self, width: u32, height: u32) -> Self {
+    self.app.settings.size = Some(LogicalSize::new(width, height).into());
+    self
+  }
+
+  pub fn with_power_preference(mut self, power_preference: PowerPreference) -> Self {
+    self.app.settings.power_preference = power_preference;
+    self
+  }
+
+  /// See [`Settings::layer_shell`].
+  pub fn with_layer_shell(mut self, layer_shell: LayerShell) -> Self {
+    self.app.settings.layer_shell = Some(layer_shell);
+    self
+  }
+
+  pub fn with_overlay(mut self, overlay: bool) -> Self {
+    self.app.settings.overlay = overlay;
+    self
+  }
+
+  pub fn with_transparent(mut self, transparent: bool) -> Self {
+    self.app.settings.transparent = transparent;
+    self
+  }
+
+  pub fn with_fullscreen(mut self, fullscreen: Fullscreen) -> Self {
+    self.app.settings.fullscreen = Some(fullscreen);
+    self
+  }
+
+  pub fn with_hide_cursor(mut self, hide_cursor: bool) -> Self {
+    self.app.settings.hide_cursor = hide_cursor;
+    self
+  }
+
+  pub fn with_always_on_top(mut self, always_on_top: bool) -> Self {
+    self.app.settings.always_on_top = always_on_top;
+    self
+  }
+
+  /// Keeps the screen from blanking, see [`Settings::keep_awake`].
+  pub fn with_keep_awake(mut self, keep_awake: bool) -> Self {
+    self.app.settings.keep_awake = keep_awake;
+    self
+  }
+
+  /// Starts straight into borderless fullscreen on top of everything else,
+  /// without a cursor and keeping the screen on, for dedicated dashboard
+  /// displays.
+  pub fn with_kiosk(self) -> Self {
+    self
+      .with_fullscreen(Fullscreen::Borderless)
+      .with_always_on_top(true)
+      .with_hide_cursor(true)
+      .with_keep_awake(true)
+  }
+
+  /// Beats `heartbeat` every `interval` while frames make it to the
+  /// screen, so an external supervisor can power-cycle the display once
+  /// the renderer hangs, see [`watchdog`](crate::watchdog).
+  pub fn with_watchdog(mut self, heartbeat: impl Heartbeat + 'static, interval: Duration) -> Self {
+    self.app.watchdog = Some(Watchdog::new(heartbeat, interval));
+    self
+  }
+
+  /// Marks telemetry as stale after `after` without an update, or never,
+  /// see [`Settings::stale_after`].
+  pub fn with_stale_after(mut self, after: Option<Duration>) -> Self {
+    self.app.settings.stale_after = after;
+    self
+  }
+
+  /// Scales text and sizes given in pixels by `scale`, on top of the
+  /// display's scale factor, see [`Settings::ui_scale`].
+  pub fn with_ui_scale(mut self, scale: f32) -> Self {
+    self.app.settings.ui_scale = scale;
+    self
+  }
+
+  /// Keeps an OLED panel from burning in what the HUD shows for long, see
+  /// [`Settings::burn_in`].
+  pub fn with_burn_in(mut self, burn_in: BurnIn) -> Self {
+    self.app.settings.burn_in = Some(burn_in);
+    self
+  }
+
+  /// Enlarges text, forces high contrast and reduces motion while
+  /// `accessibility` is enabled, see [`Settings::accessibility`].
+  pub fn with_accessibility(mut self, accessibility: Accessibility) -> Self {
+    self.app.settings.accessibility = accessibility;
+    self
+  }
+
+  /// Keeps the display on until the ignition stayed off for `delay`, see
+  /// [`Settings::ignition_off_delay`].
+  pub fn with_ignition_off_delay(mut self, delay: Duration) -> Self {
+    self.app.settings.ignition_off_delay = delay;
+    self
+  }
+
+  /// Keeps the layout clear of the display's bezel and masks its rounded
+  /// corners, see [`Settings::safe_area`].
+  pub fn with_safe_area(mut self, safe_area: SafeArea) -> Self {
+    self.app.settings.safe_area = safe_area;
+    self
+  }
+
+  /// Takes `field` from the first of `origins` still reporting it, see
+  /// [`Settings::priorities`].
+  pub fn with_priorities(
+    mut self,
+    field: Field,
+    origins: impl IntoIterator<Item = Origin>,
+  ) -> Self {
+    let origins = origins.into_iter().collect();
+    self.app.settings.priorities.insert(field, origins);
+    self
+  }
+
+  /// See [`Settings::fallback_after`].
+  pub fn with_fallback_after(mut self, after: Duration) -> Self {
+    self.app.settings.fallback_after = after;
+    self
+  }
+
+  /// Adds `processor` to those the values of `field` go through, see
+  /// [`Settings::signals`].
+  pub fn with_processor(mut self, field: Field, processor: Processor) -> Self {
+    self
+      .app
+      .settings
+      .signals
+      .entry(field)
+      .or_default()
+      .push(processor);
+    self
+  }
+
+  /// Adds `rule` to those evaluated every frame, see [`Settings::rules`].
+  pub fn with_rule(mut self, rule: Rule) -> Self {
+    self.app.settings.rules.push(rule);
+    self
+  }
+
+  /// Checksums `region` of every frame, see [`Settings::checksum_regions`].
+  pub fn with_checksum_region(mut self, region: ChecksumRegion) -> Self {
+    self.app.settings.checksum_regions.push(region);
+    self
+  }
+
+  pub fn with_alpha_mode(mut self, alpha_mode: CompositeAlphaMode) -> Self {
+    self.app.settings.alpha_mode = Some(alpha_mode);
+    self
+  }
+
+  pub fn with_present_mode(mut self, present_mode: PresentMode) -> Self {
+    self.app.settings.present_mode = present_mode;
+    self
+  }
+
+  /// Only redraws on change and every `heartbeat` otherwise, see
+  /// [`Settings::heartbeat`].
+  pub fn with_heartbeat(mut self, heartbeat: Duration) -> Self {
+    self.app.settings.heartbeat = Some(heartbeat);
+    self
+  }
+
+  /// Draws the same frames every run, see [`Settings::deterministic`].
+  pub fn with_deterministic(mut self, deterministic: bool) -> Self {
+    self.app.settings.deterministic = deterministic;
+    self
+  }
+
+  /// Takes "now" for animations, the clock widget and timeouts from `clock`
+  /// rather than the wall clock, or the stepped clock of
+  /// [deterministic mode](Settings::deterministic). A
+  /// [`ScaledClock`](crate::clock::ScaledClock) fast-forwards a replay.
+  pub fn with_clock(mut self, clock: impl Clock + 'static) -> Self {
+    self.app.clock = Some(Box::new(clock));
+    self
+  }
+
+  /// Draws at most `max_fps` frames per second.
+  pub fn with_max_fps(mut self, max_fps: f32) -> Self {
+    self.app.settings.max_fps = Some(max_fps);
+    self
+  }
+
+  pub fn with_clear_color(mut self, clear_color: Color) -> Self {
+    self.app.settings.clear_color = Some(clear_color);
+    self
+  }
+
+  pub fn with_crash_dir(mut self, dir: impl Into<PathBuf>) -> Self {
+    self.app.settings.crash_dir = Some(dir.into());
+    self
+  }
+
+  /// Where [`Action::Snapshot`] saves snapshots, see
+  /// [`Settings::snapshot_dir`].
+  pub fn with_snapshot_dir(mut self, dir: impl Into<PathBuf>) -> Self {
+    self.app.settings.snapshot_dir = Some(dir.into());
+    self
+  }
+
+  /// Records input to a file, see [`Settings::record`].
+  pub fn with_record(mut self, path: impl Into<PathBuf>) -> Self {
+    self.app.settings.record = Some(path.into());
+    self
+  }
+
+  /// Plays back recorded input, see [`Settings::replay`].
+  pub fn with_replay(mut self, path: impl Into<PathBuf>) -> Self {
+    self.app.settings.replay = Some(path.into());
+    self
+  }
+
+  /// Starts from a saved snapshot, see [`Settings::restore`].
+  pub fn with_restore(mut self, path: impl Into<PathBuf>) -> Self {
+    self.app.settings.restore = Some(path.into());
+    self
+  }
+
+  pub fn with_log_buffer(mut self, log_buffer: LogBuffer) -> Self {
+    self.app.settings.log_buffer = Some(log_buffer);
+    self
+  }
+
+  /// Loads the dashboard from a [`Config`] file when the app starts.
+  pub fn with_config(mut self, path: impl Into<PathBuf>) -> Self {
+    self.app.settings.config = Some(path.into());
+    self
+  }
+
+  pub fn with_shader_dir(mut self, dir: impl Into<PathBuf>) -> Self {
+    self.app.settings.shader_dir = Some(dir.into());
+    self
+  }
+
+  /// Draws every pipeline once before the first frame, see
+  /// [`Settings::prewarm`].
+  pub fn with_prewarm(mut self, prewarm: bool) -> Self {
+    self.app.settings.prewarm = prewarm;
+    self
+  }
+
+  /// Keeps what was drawn in `path`, to only prewarm that next time, see
+  /// [`Settings::prewarm_list`].
+  pub fn with_prewarm_list(mut self, path: impl Into<PathBuf>) -> Self {
+    self.app.settings.prewarm_list = Some(path.into());
+    self
+  }
+
+  /// Warns when startup takes longer than `budget` until the first frame
+  /// is presented, see [`Settings::startup_budget`].
+  pub fn with_startup_budget(mut self, budget: Duration) -> Self {
+    self.app.settings.startup_budget = Some(budget);
+    self
+  }
+
+  /// Adds a source of the telemetry frames and widgets see.
+  pub fn with_data_source(mut self, source: impl DataSource + 'static) -> Self {
+    self.app.sources.add(source);
+    self
+  }
+
+  /// Reads vehicle data from an ELM327 adapter on this serial port, see
+  /// [`obd::spawn`].
+  pub fn with_obd(mut self, device: impl Into<PathBuf>) -> Self {
+    self.app.settings.obd = Some(device.into());
+    self
+  }
+
+  pub fn with_gps(mut self, device: GpsDevice) -> Self {
+    self.app.settings.gps = Some(device);
+    self
+  }
+
+  pub fn with_mirror(mut self, mirror: Mirror) -> Self {
+    self.app.settings.mirror = mirror;
+    self
+  }
+
+  pub fn with_high_contrast(mut self, high_contrast: bool) -> Self {
+    self.app.settings.high_contrast = high_contrast;
+    self
+  }
+
+  /// See [`Settings::reduce_motion`].
+  pub fn with_reduce_motion(mut self, reduce_motion: bool) -> Self {
+    self.app.settings.reduce_motion = reduce_motion;
+    self
+  }
+
+  /// See [`Settings::right_to_left`].
+  pub fn with_right_to_left(mut self, right_to_left: bool) -> Self {
+    self.app.settings.right_to_left = right_to_left;
+    self
+  }
+
+  pub fn with_keystone(mut self, keystone: Keystone) -> Self {
+    self.app.settings.keystone = keystone;
+    self
+  }
+
+  /// See [`Settings::simulate_color_vision`].
+  pub fn with_simulated_color_vision(mut self, vision: ColorVision) -> Self {
+    self.app.settings.simulate_color_vision = vision;
+    self
+  }
+
+  pub fn with_themes(mut self, themes: Themes) -> Self {
+    self.app.settings.themes = themes;
+    self
+  }
+
+  pub fn with_theme_mode(mut self, mode: ThemeMode) -> Self {
+    self.app.settings.themes.mode = mode;
+    self
+  }
+
+  pub fn with_light_sensor(mut self, device: impl Into<PathBuf>) -> Self {
+    self.app.settings.light_sensor = Some(device.into());
+    self
+  }
+
+  /// Mirrors every frame into this file, see [`Settings::frame_output`].
+  pub fn with_frame_output(mut self, path: impl Into<PathBuf>) -> Self {
+    self.app.settings.frame_output = Some(path.into());
+    self
+  }
+
+  /// Lets the brightness actions step this Linux backlight, see
+  /// [`Settings::backlight`].
+  pub fn with_backlight(mut self, device: impl Into<PathBuf>) -> Self {
+    self.app.settings.backlight = Some(device.into());
+    self
+  }
+
+  /// Replaces the default key, mouse and tap bindings, see
+  /// [`Settings::bindings`].
+  pub fn with_bindings(mut self, bindings: Bindings) -> Self {
+    self.app.settings.bindings = bindings;
+    self
+  }
+
+  /// Reads rotary encoders and buttons as mapped in the file at `path`,
+  /// see [`InputMap`](crate::input_map::InputMap).
+  pub fn with_input_map(mut self, path: impl Into<PathBuf>) -> Self {
+    self.app.settings.input_map = Some(path.into());
+    self
+  }
+
+  /// Draws in this canvas of the page instead of a new one appended to the
+  /// body.
+  #[cfg(target_arch = "wasm32")]
+  pub fn with_canvas(mut self, canvas: web_sys::HtmlCanvasElement) -> Self {
+    self.app.settings.canvas = Some(canvas);
+    self
+  }
+
+  /// Applies changes to the config and theme files and shaders while
+  /// running, see [`Settings::hot_reload`].
+  pub fn with_hot_reload(mut self, hot_reload: bool) -> Self {
+    self.app.settings.hot_reload = hot_reload;
+    self
+  }
+
+  /// Called every frame to queue what should be drawn on top of the
+  /// background and the configured scene.
+  pub fn on_draw(mut self, callback: impl FnMut(&mut Frame) + 'static) -> Self {
+    self.app.on_draw = Some(Box::new(callback));
+    self
+  }
+
+  /// Called with mouse and touch input, returning whether it was used.
+  /// Input that wasn't is passed on to the widgets of the scene.
+  pub fn on_pointer(mut self, callback: impl FnMut(&PointerEvent) -> bool + 'static) -> Self {
+    self.app.on_pointer = Some(Box::new(callback));
+    self
+  }
+
+  /// Called with every scene built from the config, at startup and
+  /// whenever it's rebuilt, e.g. to connect widgets found by name or type
+  /// to the rest of the application.
+  pub fn on_scene(mut self, callback: impl FnMut(&mut Scene) + 'static) -> Self {
+    self.app.on_scene = Some(Box::new(callback));
+    self
+  }
+
+  /// Called once the first frame has been presented, with how long each
+  /// phase of startup took. Loading what the first frame can do without,
+  /// like pages not shown at first or data for later, is best left until
+  /// then, to get something on the screen as early as possible.
+  pub fn on_startup(mut self, callback: impl FnOnce(&StartupReport) + 'static) -> Self {
+    self.app.on_startup = Some(Box::new(callback));
+    self
+  }
+
+  /// Called whenever one of the [rules](crate::rules) becomes active or
+  /// inactive, with whether it's active now, e.g. to play its chime or
+  /// show its notification.
+  pub fn on_rule(mut self, callback: impl FnMut(&Rule, bool) + 'static) -> Self {
+    self.app.on_rule = Some(Box::new(callback));
+    self
+  }
+
+  /// Lets `switch` switch packages while the app runs, see
+  /// [`package`](crate::package).
+  pub fn with_package_switch(mut self, switch: PackageSwitch) -> Self {
+    self.app.packages = Some(switch);
+    self
+  }
+
+  /// Called after every package switch, with the package's directory and
+  /// why it wasn't switched to if it wasn't.
+  pub fn on_package(
+    mut self,
+    callback: impl FnMut(&Path, Result<(), &WindshieldError>) + 'static,
+  ) -> Self {
+    self.app.on_package = Some(Box::new(callback));
+    self
+  }
+
+  /// Called with the stats of every presented frame.
+  pub fn on_frame(mut self, callback: impl FnMut(&FrameStats) + 'static) -> Self {
+    self.app.on_frame = Some(Box::new(callback));
+    self
+  }
+
+  pub fn build(self) -> WindshieldApp {
+    self.app
+  }
+}
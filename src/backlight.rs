@@ -0,0 +1,62 @@
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// How much of the full range [`Action::BrightnessUp`] and
+/// [`Action::BrightnessDown`] step the backlight by.
+///
+/// [`Action::BrightnessUp`]: crate::input::Action::BrightnessUp
+/// [`Action::BrightnessDown`]: crate::input::Action::BrightnessDown
+pub(crate) const STEP: f32 = 0.1;
+
+/// A Linux backlight, e.g. `/sys/class/backlight/rpi_backlight`.
+pub(crate) struct Backlight {
+  device: PathBuf,
+}
+
+impl Backlight {
+  pub(crate) fn new(device: impl Into<PathBuf>) -> Self {
+    Self {
+      device: device.into(),
+    }
+  }
+
+  /// Changes the brightness by `delta` of the full range and returns the
+  /// new one, from 0 to 1. Never goes fully dark, a HUD nobody can see is
+  /// hard to turn back up.
+  pub(crate) fn step(&self, delta: f32) -> io::Result<f32> {
+    let max = read_number(&self.device.join("max_brightness"))?;
+    let current = read_number(&self.device.join("brightness"))?;
+    let step = (max as f32 * delta).round() as i64;
+    let brightness = (current as i64 + step).clamp(1, max as i64) as u64;
+    fs::write(self.device.join("brightness"), brightness.to_string())?;
+    Ok(brightness as f32 / max.max(1) as f32)
+  }
+
+  /// Powers the backlight down or back up, keeping the brightness.
+  pub(crate) fn set_power(&self, on: bool) -> io::Result<()> {
+    // FB_BLANK_UNBLANK and FB_BLANK_POWERDOWN
+    let power = if on { "0" } else { "4" };
+    fs::write(self.device.join("bl_power"), power)
+  }
+
+  /// Steps the brightness, only logging if it can't, as it's no reason to
+  /// stop showing anything.
+  pub(crate) fn adjust(&self, delta: f32) {
+    match self.step(delta) {
+      Ok(brightness) => tracing::info!("brightness {:.0}%", brightness * 100.0),
+      Err(err) => tracing::warn!(
+        "unable to change the brightness of {}: {}",
+        self.device.display(),
+        err
+      ),
+    }
+  }
+}
+
+fn read_number(path: &Path) -> io::Result<u64> {
+  fs::read_to_string(path)?
+    .trim()
+    .parse()
+    .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))
+}
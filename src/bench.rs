@@ -0,0 +1,251 @@
+//! Stress scenes to measure how long frames take, and what those times
+//! come to, so performance can be compared across commits, see
+//! [`WindshieldApp::bench`](crate::WindshieldApp::bench).
+
+use std::f32::consts::TAU;
+use std::str::FromStr;
+use std::time::Duration;
+
+use serde::Serialize;
+
+use crate::frame::Frame;
+use crate::image::{ColorMatrix, Fit, Image};
+use crate::map::{Coordinate, Feature, FeatureKind, Geometry, MapData};
+use crate::widgets::{MapView, RadialGauge, Rect};
+
+/// What a benchmark draws every frame, moving all of it so nothing can be
+/// cached from one frame to the next.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BenchScene {
+  /// 10 000 small images drifting across the frame.
+  Sprites,
+  /// 1 000 radial gauges in a grid, their needles swinging.
+  Gauges,
+  /// A city of 50 × 50 blocks with roads, buildings and labels, turning
+  /// and zooming.
+  Map,
+}
+
+impl BenchScene {
+  pub const ALL: [Self; 3] = [Self::Sprites, Self::Gauges, Self::Map];
+
+  pub fn name(self) -> &'static str {
+    match self {
+      Self::Sprites => "sprites",
+      Self::Gauges => "gauges",
+      Self::Map => "map",
+    }
+  }
+
+  /// Draws the scene, for [`on_draw`](crate::WindshieldAppBuilder::on_draw).
+  pub fn on_draw(self) -> Box<dyn FnMut(&mut Frame)> {
+    let mut time = 0.0;
+    match self {
+      Self::Sprites => {
+        let sprite = sprite();
+        Box::new(move |frame: &mut Frame| {
+          time += frame.delta.as_secs_f32();
+          let [width, height] = [frame.width as f32, frame.height as f32];
+          for index in 0..10_000 {
+            // Spread out evenly, each going its own way
+            let seed = index as f32 * 0.618_034;
+            let angle = seed.fract() * TAU;
+            let speed = 20.0 + (seed * 7.0).fract() * 80.0;
+            let x = ((seed * 13.0).fract() * width + angle.cos() * speed * time).rem_euclid(width);
+            let y =
+              ((seed * 17.0).fract() * height + angle.sin() * speed * time).rem_euclid(height);
+            let rect = Rect::new(x - 4.0, y - 4.0, 8.0, 8.0);
+            frame.image(&sprite, rect, Fit::Fill, 0.0, ColorMatrix::IDENTITY);
+          }
+          frame.animate();
+        })
+      }
+      Self::Gauges => {
+        let mut gauges: Vec<_> = (0..1000).map(|_| RadialGauge::new()).collect();
+        Box::new(move |frame: &mut Frame| {
+          time += frame.delta.as_secs_f32();
+          let (columns, rows) = (40, 25);
+          let width = frame.width as f32 / columns as f32;
+          let height = frame.height as f32 / rows as f32;
+          for (index, gauge) in gauges.iter_mut().enumerate() {
+            let phase = index as f32 * 0.1;
+            gauge.set_value(0.5 + 0.5 * (time * 2.0 + phase).sin());
+            let (column, row) = (index % columns, index / columns);
+            let rect = Rect::new(column as f32 * width, row as f32 * height, width, height);
+            frame.widget(gauge, rect);
+          }
+          frame.animate();
+        })
+      }
+      Self::Map => {
+        let center = Coordinate::new(52.52, 13.405);
+        let mut map = MapView::new(city(center)).with_camera(center, 15.0);
+        Box::new(move |frame: &mut Frame| {
+          time += frame.delta.as_secs_f32();
+          map.camera.bearing = (time * 20.0).rem_euclid(360.0);
+          map.camera.zoom = 15.0 + 1.5 * (time * 0.5).sin() as f64;
+          let rect = Rect::new(0.0, 0.0, frame.width as f32, frame.height as f32);
+          frame.widget(&mut map, rect);
+          frame.animate();
+        })
+      }
+    }
+  }
+}
+
+impl FromStr for BenchScene {
+  type Err = String;
+
+  fn from_str(name: &str) -> Result<Self, Self::Err> {
+    Self::ALL
+      .into_iter()
+      .find(|scene| scene.name() == name)
+      .ok_or_else(|| {
+        let names: Vec<_> = Self::ALL.iter().map(|scene| scene.name()).collect();
+        format!(
+          "unknown bench scene {:?}, expected one of {}",
+          name,
+          names.join(", ")
+        )
+      })
+  }
+}
+
+/// A white dot fading out to its edge.
+fn sprite() -> Image {
+  let size = 16;
+  let mut rgba = Vec::with_capacity(size * size * 4);
+  for y in 0..size {
+    for x in 0..size {
+      let middle = size as f32 / 2.0 - 0.5;
+      let distance = (x as f32 - middle).hypot(y as f32 - middle) / middle;
+      let alpha = ((1.0 - distance).clamp(0.0, 1.0) * 255.0) as u8;
+      rgba.extend_from_slice(&[255, 255, 255, alpha]);
+    }
+  }
+  Image::from_rgba(size as u32, size as u32, rgba)
+}
+
+/// Blocks of buildings between roads around `center`, every fifth road a
+/// major one, with a label on every block.
+fn city(center: Coordinate) -> MapData {
+  const BLOCKS: usize = 50;
+  // About 100 m in Web Mercator at Berlin's latitude
+  let block = 1.0 / 2f64.powi(18) * 6.0;
+  let [x0, y0] = center.project();
+  let origin = [
+    x0 - block * BLOCKS as f64 / 2.0,
+    y0 - block * BLOCKS as f64 / 2.0,
+  ];
+  let at = |column: f64, row: f64| [origin[0] + column * block, origin[1] + row * block];
+  let mut features = Vec::new();
+  for line in 0..=BLOCKS {
+    let kind = if line % 5 == 0 {
+      FeatureKind::MajorRoad
+    } else {
+      FeatureKind::Road
+    };
+    let line = line as f64;
+    let along = vec![at(line, 0.0), at(line, BLOCKS as f64)];
+    let across = vec![at(0.0, line), at(BLOCKS as f64, line)];
+    features.push(Feature::new(
+      kind,
+      None,
+      Geometry::Lines(vec![along, across]),
+    ));
+  }
+  for row in 0..BLOCKS {
+    for column in 0..BLOCKS {
+      let (column, row) = (column as f64, row as f64);
+      let building = vec![
+        at(column + 0.15, row + 0.15),
+        at(column + 0.85, row + 0.15),
+        at(column + 0.85, row + 0.85),
+        at(column + 0.15, row + 0.85),
+      ];
+      features.push(Feature::new(
+        FeatureKind::Building,
+        None,
+        Geometry::Polygons(vec![vec![building]]),
+      ));
+      let name = format!("Block {}", row as usize * BLOCKS + column as usize);
+      let label = Geometry::Point(at(column + 0.5, row + 0.5));
+      features.push(Feature::new(FeatureKind::Place, Some(name), label));
+    }
+  }
+  MapData::new(features)
+}
+
+/// How long the frames of a benchmark took, in milliseconds, printed as
+/// JSON to be compared across commits.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct BenchReport {
+  pub scene: String,
+  pub frames: usize,
+  /// Seconds all frames took together.
+  pub seconds: f64,
+  pub mean: f64,
+  pub p50: f64,
+  pub p90: f64,
+  pub p95: f64,
+  pub p99: f64,
+  pub max: f64,
+}
+
+impl BenchReport {
+  pub fn new(scene: impl Into<String>, frame_times: &[Duration]) -> Self {
+    let mut millis: Vec<f64> = frame_times
+      .iter()
+      .map(|time| time.as_secs_f64() * 1000.0)
+      .collect();
+    millis.sort_by(f64::total_cmp);
+    let total: f64 = millis.iter().sum();
+    // Nearest rank, the time no more than that share of frames stay below
+    let percentile = |share: f64| {
+      let rank = (share * millis.len() as f64).ceil() as usize;
+      millis
+        .get(rank.clamp(1, millis.len().max(1)) - 1)
+        .copied()
+        .unwrap_or(0.0)
+    };
+    Self {
+      scene: scene.into(),
+      frames: millis.len(),
+      seconds: total / 1000.0,
+      mean: if millis.is_empty() {
+        0.0
+      } else {
+        total / millis.len() as f64
+      },
+      p50: percentile(0.5),
+      p90: percentile(0.9),
+      p95: percentile(0.95),
+      p99: percentile(0.99),
+      max: millis.last().copied().unwrap_or(0.0),
+    }
+  }
+
+  pub fn to_json(&self) -> String {
+    serde_json::to_string(self).expect("reports serialize")
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn reports_nearest_rank_percentiles() {
+    let times: Vec<_> = (1..=100).rev().map(Duration::from_millis).collect();
+    let report = BenchReport::new("gauges", &times);
+    assert_eq!(report.frames, 100);
+    assert_eq!(
+      (report.p50, report.p90, report.p99, report.max),
+      (50.0, 90.0, 99.0, 100.0)
+    );
+    assert!((report.mean - 50.5).abs() < 1e-9);
+    assert_eq!(BenchReport::new("map", &[]).p99, 0.0);
+    assert_eq!("map".parse(), Ok(BenchScene::Map));
+    assert!("maps".parse::<BenchScene>().is_err());
+  }
+}
@@ -0,0 +1,43 @@
+use std::fmt;
+
+/// Identifies the build that is running.
+#[derive(Clone, Debug)]
+pub struct BuildInfo {
+  pub version: &'static str,
+  /// Short commit hash, or `unknown` if built outside of a git checkout.
+  pub git_hash: &'static str,
+  /// Cargo features the crate was compiled with.
+  pub features: Vec<&'static str>,
+  /// Target triple it was compiled for, like
+  /// `aarch64-unknown-linux-gnu`.
+  pub target: &'static str,
+  /// Whether it was compiled with optimizations, debug builds being far
+  /// slower on a device.
+  pub release: bool,
+  /// SPDX license expression of the crate, if its manifest has one.
+  pub license: Option<&'static str>,
+}
+
+pub fn build_info() -> BuildInfo {
+  BuildInfo {
+    version: env!("CARGO_PKG_VERSION"),
+    git_hash: env!("WINDSHIELD_GIT_HASH"),
+    features: env!("WINDSHIELD_FEATURES")
+      .split(',')
+      .filter(|feature| !feature.is_empty())
+      .collect(),
+    target: env!("WINDSHIELD_TARGET"),
+    release: !cfg!(debug_assertions),
+    license: Some(env!("CARGO_PKG_LICENSE")).filter(|license| !license.is_empty()),
+  }
+}
+
+impl fmt::Display for BuildInfo {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    write!(f, "windshield-rs {} ({})", self.version, self.git_hash)?;
+    if !self.features.is_empty() {
+      write!(f, " [{}]", self.features.join(", "))?;
+    }
+    Ok(())
+  }
+}
@@ -0,0 +1,141 @@
+//! Protecting OLED panels from burning in what the HUD shows for hours,
+//! see [`Settings::burn_in`](crate::Settings::burn_in).
+//!
+//! The layout wanders a pixel at a time around where it belongs, too
+//! slowly to notice, and widgets showing the same for long are dimmed
+//! until what they show changes:
+//!
+//! ```toml
+//! [display.burn_in]
+//! shift = 3
+//! shift_every = 120
+//! dim_after = 600
+//! dim_opacity = 0.5
+//! ```
+//!
+//! Widgets with a [source](crate::layout::Node::source) show the same as
+//! long as their value doesn't change, those without one, like labels and
+//! icons, unless they're animating.
+
+use std::time::Duration;
+
+use serde::Deserialize;
+
+/// How the panel is protected.
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct BurnIn {
+  /// Farthest the layout wanders from where it belongs, in whole physical
+  /// pixels along either axis. 0 keeps it in place.
+  pub shift: u32,
+  /// Seconds the layout stays at each position.
+  pub shift_every: f32,
+  /// Seconds a widget may show the same before it's dimmed, 0 for never.
+  pub dim_after: f32,
+  /// Opacity of dimmed widgets.
+  pub dim_opacity: f32,
+}
+
+impl Default for BurnIn {
+  fn default() -> Self {
+    Self {
+      shift: 2,
+      shift_every: 60.0,
+      dim_after: 300.0,
+      dim_opacity: 0.6,
+    }
+  }
+}
+
+impl BurnIn {
+  pub fn with_shift(mut self, shift: u32, every: Duration) -> Self {
+    self.shift = shift;
+    self.shift_every = every.as_secs_f32();
+    self
+  }
+
+  /// Dims widgets to `opacity` after showing the same for `after`, or
+  /// never.
+  pub fn with_dimming(mut self, after: Option<Duration>, opacity: f32) -> Self {
+    self.dim_after = after.map_or(0.0, |after| after.as_secs_f32());
+    self.dim_opacity = opacity;
+    self
+  }
+
+  /// How long widgets may show the same before they're dimmed.
+  pub(crate) fn dim_after(&self) -> Option<Duration> {
+    Duration::try_from_secs_f32(self.dim_after)
+      .ok()
+      .filter(|after| !after.is_zero())
+  }
+
+  /// Which position the layout is at after `elapsed`, changing every
+  /// [`shift_every`](Self::shift_every).
+  pub(crate) fn step(&self, elapsed: Duration) -> u64 {
+    if self.shift == 0 || self.shift_every <= 0.0 {
+      return 0;
+    }
+    (elapsed.as_secs_f64() / self.shift_every as f64) as u64
+  }
+
+  /// Where the layout is moved at `step`. Goes back and forth through
+  /// every position of the square `shift` pixels around the origin in
+  /// rows of alternating direction, so every step moves it by a pixel,
+  /// starting at the origin.
+  pub(crate) fn offset(&self, step: u64) -> [f32; 2] {
+    let side = 2 * self.shift as u64 + 1;
+    let positions = side * side;
+    if positions == 1 {
+      return [0.0, 0.0];
+    }
+    let period = 2 * (positions - 1);
+    let step = (step + (positions - 1) / 2) % period;
+    let index = if step < positions {
+      step
+    } else {
+      period - step
+    };
+    let (row, column) = (index / side, index % side);
+    let column = if row % 2 == 0 {
+      column
+    } else {
+      side - 1 - column
+    };
+    let shift = self.shift as f32;
+    [column as f32 - shift, row as f32 - shift]
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::config::toml_example;
+
+  #[test]
+  fn wanders_a_pixel_at_a_time_within_the_shift() {
+    let burn_in = BurnIn::default().with_shift(1, Duration::from_secs(60));
+    assert_eq!(burn_in.step(Duration::from_secs(119)), 1);
+    let offsets: Vec<_> = (0..40).map(|step| burn_in.offset(step)).collect();
+    assert_eq!(offsets[0], [0.0, 0.0]);
+    assert_eq!(offsets[4], [1.0, 1.0]);
+    assert_eq!(offsets[12], [-1.0, -1.0]);
+    assert_eq!(offsets[16], offsets[0]);
+    for pair in offsets.windows(2) {
+      let moved = (pair[1][0] - pair[0][0]).abs() + (pair[1][1] - pair[0][1]).abs();
+      assert_eq!(moved, 1.0);
+    }
+    assert_eq!(
+      BurnIn::default().with_shift(0, Duration::ZERO).offset(7),
+      [0.0, 0.0]
+    );
+  }
+
+  #[test]
+  fn parses_the_module_example() {
+    let example = toml_example(include_str!("burn_in.rs"), "//!");
+    let example: toml::Value = toml::from_str(&example).unwrap();
+    let burn_in: BurnIn = example["display"]["burn_in"].clone().try_into().unwrap();
+    assert_eq!(burn_in.shift, 3);
+    assert_eq!(burn_in.dim_after(), Some(Duration::from_secs(600)));
+  }
+}
@@ -0,0 +1,325 @@
+//! Widgets drawn into a texture once and shown from it until they change,
+//! for those that take long to tessellate like detailed vector icons, see
+//! [`Frame::cached_widget`] and [`Node::cache`](crate::layout::Node::cache).
+
+use std::collections::{HashMap, HashSet};
+use std::ops::Range;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use wgpu::util::{BufferInitDescriptor, DeviceExt};
+use wgpu::{
+  BindGroup, BindGroupDescriptor, BindGroupEntry, BindGroupLayout, BindGroupLayoutDescriptor,
+  BindGroupLayoutEntry, BindingResource, BindingType, BlendState, Buffer, BufferBindingType,
+  BufferUsages, Device, Extent3d, Queue, RenderPass, RenderPipeline, ShaderStages, Texture,
+  TextureDescriptor, TextureDimension, TextureFormat, TextureSampleType, TextureUsages,
+  TextureView, TextureViewDescriptor, TextureViewDimension,
+};
+
+use crate::canvas::Layer;
+use crate::error::WindshieldError;
+use crate::frame::Frame;
+use crate::pipeline::{create_shader, PipelineBuilder};
+use crate::stats::FrameStats;
+use crate::widgets::{Rect, Widget};
+
+/// Always bundled, it only copies pixels.
+const SHADER: (&str, &str) = ("cache.wgsl", include_str!("shaders/cache.wgsl"));
+
+static NEXT_ID: AtomicU64 = AtomicU64::new(0);
+
+/// What a cached widget was drawn like, kept with the widget from one
+/// frame to the next, see [`Frame::cached_widget`].
+#[derive(Debug)]
+pub struct WidgetCache {
+  id: u64,
+  // The rect, clip and scale it was drawn with last
+  drawn: Option<(Rect, Option<Rect>, f32)>,
+  // Of the last `set_value`
+  value: Option<f32>,
+  dirty: bool,
+}
+
+impl Default for WidgetCache {
+  fn default() -> Self {
+    Self::new()
+  }
+}
+
+impl WidgetCache {
+  pub fn new() -> Self {
+    Self {
+      id: NEXT_ID.fetch_add(1, Ordering::Relaxed),
+      drawn: None,
+      value: None,
+      dirty: true,
+    }
+  }
+
+  /// Draws the widget again next frame, after changing what it shows.
+  pub fn invalidate(&mut self) {
+    self.dirty = true;
+  }
+
+  /// Invalidates the cache if the widget is given another value than
+  /// before.
+  pub(crate) fn set_value(&mut self, value: f32) {
+    if self.value != Some(value) {
+      self.value = Some(value);
+      self.dirty = true;
+    }
+  }
+}
+
+impl Frame<'_> {
+  /// Like [`widget`](Self::widget), but draws `widget` into a texture of
+  /// its own and shows that, only drawing it again once `cache` is
+  /// [invalidated](WidgetCache::invalidate), it's moved, resized, clipped
+  /// or [scaled](Self::scale) otherwise, or while it's animating. Fading it with
+  /// [`Canvas::set_opacity`](crate::canvas::Canvas::set_opacity) doesn't
+  /// draw it again.
+  ///
+  /// In a [`Node`](crate::layout::Node) its value and palette changing
+  /// invalidate it as well, anything else it shows, like what it takes
+  /// from [`Widget::set_telemetry`], only does when invalidated by hand.
+  /// Every cached widget has a texture the size of the frame, so it pays
+  /// off for a few widgets with lots of shapes, not for all of them.
+  pub fn cached_widget(&mut self, widget: &mut dyn Widget, rect: Rect, cache: &mut WidgetCache) {
+    widget.set_telemetry(self.telemetry);
+    widget.update(self.delta);
+    let outer = self.canvas.layer();
+    let drawn = Some((rect, outer.clip, self.scale));
+    let stale = cache.dirty || cache.drawn != drawn || !self.caches.is_ready(cache.id);
+    if stale || widget.animating() {
+      self.set_layer(Layer {
+        cache: Some(cache.id),
+        ..outer
+      });
+      // Faded when shown instead
+      let opacity = (self.canvas.opacity(), self.text.opacity());
+      self.canvas.set_opacity(1.0);
+      self.text.set_opacity(1.0);
+      widget.draw(self, rect);
+      self.canvas.set_opacity(opacity.0);
+      self.text.set_opacity(opacity.1);
+      cache.drawn = drawn;
+      cache.dirty = false;
+    }
+    self.caches.keep(cache.id);
+    self.set_layer(Layer {
+      shows: Some((cache.id, self.canvas.opacity())),
+      ..outer
+    });
+    if widget.animating() {
+      self.animate();
+    }
+  }
+}
+
+/// Layout of `Shown` in `shaders/cache.wgsl`.
+type ShownUniform = [f32; 4];
+
+struct Cached {
+  _texture: Texture,
+  view: TextureView,
+  uniform: Buffer,
+  bind_group: BindGroup,
+  opacity: f32,
+  // Whether it has been drawn into
+  ready: bool,
+}
+
+/// The textures of the widgets drawn cached, and the pipeline showing
+/// them. Textures of widgets not drawn in a frame are dropped after it,
+/// all of them when the frame's size, mirroring or contrast changes.
+pub(crate) struct CachePass {
+  format: TextureFormat,
+  pipeline: RenderPipeline,
+  layout: BindGroupLayout,
+  textures: HashMap<u64, Cached>,
+  // Drawn or shown in the frame being built
+  kept: HashSet<u64>,
+}
+
+impl CachePass {
+  pub(crate) async fn new(device: &Device, format: TextureFormat) -> Result<Self, WindshieldError> {
+    let (name, source) = SHADER;
+    let shader = create_shader(device, name, source).await?;
+    let layout = device.create_bind_group_layout(&BindGroupLayoutDescriptor {
+      label: Some("Cache"),
+      entries: &[
+        BindGroupLayoutEntry {
+          binding: 0,
+          visibility: ShaderStages::FRAGMENT,
+          ty: BindingType::Buffer {
+            ty: BufferBindingType::Uniform,
+            has_dynamic_offset: false,
+            min_binding_size: None,
+          },
+          count: None,
+        },
+        BindGroupLayoutEntry {
+          binding: 1,
+          visibility: ShaderStages::FRAGMENT,
+          ty: BindingType::Texture {
+            sample_type: TextureSampleType::Float { filterable: false },
+            view_dimension: TextureViewDimension::D2,
+            multisampled: false,
+          },
+          count: None,
+        },
+      ],
+    });
+    // Drawn over transparent black, so the textures hold premultiplied
+    // colors
+    let pipeline = PipelineBuilder::new("Cache Pipeline", &shader)
+      .bind_group_layout(&layout)
+      .blend(Some(BlendState::PREMULTIPLIED_ALPHA_BLENDING))
+      .build(device, format);
+
+    Ok(Self {
+      format,
+      pipeline,
+      layout,
+      textures: HashMap::new(),
+      kept: HashSet::new(),
+    })
+  }
+
+  /// Whether the texture of cache `id` shows what was drawn into it in an
+  /// earlier frame.
+  pub(crate) fn is_ready(&self, id: u64) -> bool {
+    self.textures.get(&id).is_some_and(|cached| cached.ready)
+  }
+
+  /// Keeps the texture of cache `id` after this frame.
+  pub(crate) fn keep(&mut self, id: u64) {
+    self.kept.insert(id);
+  }
+
+  /// Drops every texture, for when what's drawn into them would come out
+  /// differently.
+  pub(crate) fn clear(&mut self) {
+    self.textures.clear();
+  }
+
+  /// Drops the textures not kept while building the frame and creates
+  /// those its `layers` draw into, `size` big.
+  pub(crate) fn prepare(
+    &mut self,
+    device: &Device,
+    queue: &Queue,
+    size: [u32; 2],
+    layers: &[(Range<u32>, Layer)],
+  ) {
+    let kept = std::mem::take(&mut self.kept);
+    self.textures.retain(|id, _| kept.contains(id));
+    for (_, layer) in layers {
+      if let Some(id) = layer.cache {
+        let cached = self
+          .textures
+          .entry(id)
+          .or_insert_with(|| cached(device, self.format, size, &self.layout));
+        cached.ready = true;
+      }
+      let shown = layer.shows.and_then(|(id, opacity)| {
+        let cached = self.textures.get_mut(&id)?;
+        Some((cached, opacity))
+      });
+      if let Some((cached, opacity)) = shown.filter(|(cached, opacity)| cached.opacity != *opacity)
+      {
+        cached.opacity = opacity;
+        queue.write_buffer(&cached.uniform, 0, bytemuck::bytes_of(&[opacity; 4]));
+      }
+    }
+  }
+
+  /// What cache `id` is drawn into.
+  pub(crate) fn view(&self, id: u64) -> Option<&TextureView> {
+    self.textures.get(&id).map(|cached| &cached.view)
+  }
+
+  /// Shows cache `id` on what `pass` draws into.
+  pub(crate) fn draw<'a>(&'a self, pass: &mut RenderPass<'a>, id: u64, stats: &mut FrameStats) {
+    let Some(cached) = self.textures.get(&id) else {
+      return;
+    };
+    pass.set_pipeline(&self.pipeline);
+    pass.set_bind_group(0, &cached.bind_group, &[]);
+    pass.draw(0..3, 0..1);
+    stats.draw_calls += 1;
+    stats.triangles += 1;
+  }
+}
+
+fn cached(
+  device: &Device,
+  format: TextureFormat,
+  [width, height]: [u32; 2],
+  layout: &BindGroupLayout,
+) -> Cached {
+  let texture = device.create_texture(&TextureDescriptor {
+    label: Some("Cache"),
+    size: Extent3d {
+      width,
+      height,
+      depth_or_array_layers: 1,
+    },
+    mip_level_count: 1,
+    sample_count: 1,
+    dimension: TextureDimension::D2,
+    format,
+    usage: TextureUsages::RENDER_ATTACHMENT | TextureUsages::TEXTURE_BINDING,
+  });
+  let view = texture.create_view(&TextureViewDescriptor::default());
+  let opacity = 1.0;
+  let uniform = device.create_buffer_init(&BufferInitDescriptor {
+    label: Some("Cache"),
+    contents: bytemuck::bytes_of::<ShownUniform>(&[opacity; 4]),
+    usage: BufferUsages::UNIFORM | BufferUsages::COPY_DST,
+  });
+  let bind_group = device.create_bind_group(&BindGroupDescriptor {
+    label: Some("Cache"),
+    layout,
+    entries: &[
+      BindGroupEntry {
+        binding: 0,
+        resource: uniform.as_entire_binding(),
+      },
+      BindGroupEntry {
+        binding: 1,
+        resource: BindingResource::TextureView(&view),
+      },
+    ],
+  });
+  Cached {
+    _texture: texture,
+    view,
+    uniform,
+    bind_group,
+    opacity,
+    ready: false,
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn only_another_value_invalidates() {
+    let mut cache = WidgetCache::new();
+    cache.dirty = false;
+    cache.set_value(1.0);
+    assert!(cache.dirty);
+    cache.dirty = false;
+    cache.set_value(1.0);
+    assert!(!cache.dirty);
+    cache.invalidate();
+    assert!(cache.dirty);
+  }
+
+  #[test]
+  fn ids_are_unique() {
+    assert_ne!(WidgetCache::new().id, WidgetCache::new().id);
+  }
+}
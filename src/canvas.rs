@@ -0,0 +1,307 @@
+use std::ops::Range;
+
+use lyon::geom::{Angle, Arc};
+use lyon::math::{point, vector, Box2D, Point};
+use lyon::path::builder::BorderRadii;
+use lyon::path::{Event, Path, Winding};
+use lyon::tessellation::{
+  BuffersBuilder, FillOptions, FillRule, FillTessellator, FillVertex, LineCap, StrokeOptions,
+  StrokeTessellator, StrokeVertex, VertexBuffers,
+};
+
+use crate::lru::{content_hash, LruCache};
+use crate::pipeline::ColorVertex;
+use crate::widgets::Rect;
+
+/// How a shape is painted. Colors are linear RGBA in straight alpha.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Style {
+  Fill { color: [f32; 4] },
+  Stroke { color: [f32; 4], width: f32 },
+}
+
+impl Style {
+  pub fn fill(color: [f32; 4]) -> Self {
+    Self::Fill { color }
+  }
+
+  pub fn stroke(color: [f32; 4], width: f32) -> Self {
+    Self::Stroke { color, width }
+  }
+}
+
+/// Shapes kept tessellated, enough for a few detailed icons and a map.
+const SHAPE_CAPACITY: usize = 4096;
+/// Frames a tessellated shape is kept without being drawn.
+const SHAPE_MAX_AGE: u64 = 120;
+
+/// What a layer of the frame is clipped to and drawn into, see
+/// `Frame::next_layer`.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub(crate) struct Layer {
+  /// The pixels it's clipped to if not the whole frame.
+  pub(crate) clip: Option<Rect>,
+  /// The widget cache it's drawn into instead of the frame, see
+  /// `Frame::cached_widget`.
+  pub(crate) cache: Option<u64>,
+  /// A widget cache shown below everything else of the layer, and how
+  /// opaque. Not passed on to the next one.
+  pub(crate) shows: Option<(u64, f32)>,
+}
+
+/// Immediate mode 2D drawing, tessellated on the CPU and flushed into the
+/// render pass at the end of the frame.
+///
+/// Coordinates are physical pixels with the origin in the top left corner.
+/// Angles are in radians, clockwise from the positive x axis (since y points
+/// down).
+///
+/// Shapes drawn again in a later frame, in any color, reuse what they were
+/// tessellated into, as long as they're drawn at least every couple of
+/// seconds.
+pub struct Canvas {
+  fill: FillTessellator,
+  stroke: StrokeTessellator,
+  // By the hash of the path and how it's painted but its color
+  shapes: LruCache<VertexBuffers<[f32; 2], u32>>,
+  geometry: VertexBuffers<ColorVertex, u32>,
+  // Index each layer after the first starts at and what it's clipped to
+  // and drawn into, see `Frame::next_layer`
+  layers: Vec<(u32, Layer)>,
+  // The current one, never showing a cache
+  layer: Layer,
+  opacity: f32,
+}
+
+impl Canvas {
+  pub(crate) fn new() -> Self {
+    Self {
+      fill: FillTessellator::new(),
+      stroke: StrokeTessellator::new(),
+      shapes: LruCache::new(SHAPE_CAPACITY, SHAPE_MAX_AGE),
+      geometry: VertexBuffers::new(),
+      layers: Vec::new(),
+      layer: Layer::default(),
+      opacity: 1.0,
+    }
+  }
+
+  /// Multiplies the alpha of everything drawn from now on, e.g. to fade a
+  /// whole page of widgets. Set it back to 1 when done.
+  pub fn set_opacity(&mut self, opacity: f32) {
+    self.opacity = opacity.clamp(0.0, 1.0);
+  }
+
+  pub fn opacity(&self) -> f32 {
+    self.opacity
+  }
+
+  pub fn line(&mut self, from: [f32; 2], to: [f32; 2], color: [f32; 4], width: f32) {
+    let mut builder = Path::builder();
+    builder.begin(point(from[0], from[1]));
+    builder.line_to(point(to[0], to[1]));
+    builder.end(false);
+    self.path(&builder.build(), Style::stroke(color, width));
+  }
+
+  /// A circular arc. Filling it draws the sector between the arc and the
+  /// center.
+  pub fn arc(&mut self, center: [f32; 2], radius: f32, start: f32, sweep: f32, style: Style) {
+    let center = point(center[0], center[1]);
+    let arc = Arc {
+      center,
+      radii: vector(radius, radius),
+      start_angle: Angle::radians(start),
+      sweep_angle: Angle::radians(sweep),
+      x_rotation: Angle::zero(),
+    };
+
+    let mut builder = Path::builder();
+    let filled = matches!(style, Style::Fill { .. });
+    if filled {
+      builder.begin(center);
+      builder.line_to(arc.from());
+    } else {
+      builder.begin(arc.from());
+    }
+    arc.for_each_quadratic_bezier(&mut |segment| {
+      builder.quadratic_bezier_to(segment.ctrl, segment.to);
+    });
+    builder.end(filled);
+    self.path(&builder.build(), style);
+  }
+
+  pub fn circle(&mut self, center: [f32; 2], radius: f32, style: Style) {
+    let mut builder = Path::builder();
+    builder.add_circle(point(center[0], center[1]), radius, Winding::Positive);
+    self.path(&builder.build(), style);
+  }
+
+  /// A closed polygon through `points`.
+  pub fn polygon(&mut self, points: &[[f32; 2]], style: Style) {
+    if points.len() < 2 {
+      return;
+    }
+    let points: Vec<Point> = points.iter().map(|p| point(p[0], p[1])).collect();
+    let mut builder = Path::builder();
+    builder.add_polygon(lyon::path::Polygon {
+      points: &points,
+      closed: true,
+    });
+    self.path(&builder.build(), style);
+  }
+
+  pub fn rect(&mut self, position: [f32; 2], size: [f32; 2], style: Style) {
+    let [x, y] = position;
+    let [w, h] = size;
+    self.polygon(&[[x, y], [x + w, y], [x + w, y + h], [x, y + h]], style);
+  }
+
+  /// A rect with its corners rounded by `radius`, at most half of the
+  /// shorter side.
+  pub fn rounded_rect(&mut self, position: [f32; 2], size: [f32; 2], radius: f32, style: Style) {
+    let [x, y] = position;
+    let [w, h] = size;
+    let radius = radius.clamp(0.0, w.min(h).max(0.0) / 2.0);
+    let mut builder = Path::builder();
+    builder.add_rounded_rectangle(
+      &Box2D::new(point(x, y), point(x + w, y + h)),
+      &BorderRadii::new(radius),
+      Winding::Positive,
+    );
+    self.path(&builder.build(), style);
+  }
+
+  /// Any lyon path, for shapes the helpers above don't cover.
+  pub fn path(&mut self, path: &Path, style: Style) {
+    let (color, width) = match style {
+      Style::Fill { color } => (color, None),
+      Style::Stroke { color, width } => (color, Some(width)),
+    };
+    let key = shape_hash(path, width);
+    let shape = match self.shapes.get(key) {
+      Some(shape) => shape,
+      None => {
+        let mut shape = VertexBuffers::new();
+        let result = match width {
+          None => self.fill.tessellate_path(
+            path,
+            // Non-zero like SVG, glyph outlines depend on it
+            &FillOptions::default().with_fill_rule(FillRule::NonZero),
+            &mut BuffersBuilder::new(&mut shape, |vertex: FillVertex| {
+              vertex.position().to_array()
+            }),
+          ),
+          Some(width) => self.stroke.tessellate_path(
+            path,
+            &StrokeOptions::default()
+              .with_line_width(width)
+              .with_line_cap(LineCap::Round),
+            &mut BuffersBuilder::new(&mut shape, |vertex: StrokeVertex| {
+              vertex.position().to_array()
+            }),
+          ),
+        };
+        if let Err(err) = result {
+          tracing::warn!("unable to tessellate path: {:?}", err);
+          return;
+        }
+        self.shapes.insert(key, shape)
+      }
+    };
+
+    let [r, g, b, a] = color;
+    let color = [r, g, b, a * self.opacity];
+    let start = self.geometry.vertices.len() as u32;
+    self
+      .geometry
+      .vertices
+      .extend(shape.vertices.iter().map(|position| ColorVertex {
+        position: *position,
+        color,
+      }));
+    self
+      .geometry
+      .indices
+      .extend(shape.indices.iter().map(|index| start + index));
+  }
+
+  /// Starts a layer drawn above the text of the previous ones, clipped
+  /// and drawn into like the current one.
+  pub(crate) fn next_layer(&mut self) {
+    self
+      .layers
+      .push((self.geometry.indices.len() as u32, self.layer));
+  }
+
+  pub(crate) fn layer(&self) -> Layer {
+    self.layer
+  }
+
+  pub(crate) fn clip(&self) -> Option<Rect> {
+    self.layer.clip
+  }
+
+  /// Starts a `layer`, returning the current one.
+  pub(crate) fn set_layer(&mut self, layer: Layer) -> Layer {
+    let previous = std::mem::replace(&mut self.layer, layer);
+    self.next_layer();
+    self.layer.shows = None;
+    previous
+  }
+
+  /// Everything drawn since the last [`clear`](Self::clear).
+  pub(crate) fn geometry(&self) -> (&[ColorVertex], &[u32]) {
+    (&self.geometry.vertices, &self.geometry.indices)
+  }
+
+  /// Indices of every layer in [`geometry`](Self::geometry), bottom first,
+  /// and how it's drawn.
+  pub(crate) fn layers(&self) -> Vec<(Range<u32>, Layer)> {
+    let end = self.geometry.indices.len() as u32;
+    let starts = std::iter::once((0, Layer::default())).chain(self.layers.iter().copied());
+    let ends = self
+      .layers
+      .iter()
+      .map(|(start, _)| *start)
+      .chain(std::iter::once(end));
+    starts
+      .zip(ends)
+      .map(|((start, layer), end)| (start..end, layer))
+      .collect()
+  }
+
+  pub(crate) fn clear(&mut self) {
+    self.geometry.vertices.clear();
+    self.geometry.indices.clear();
+    self.layers.clear();
+    self.layer = Layer::default();
+    self.shapes.next_generation();
+  }
+}
+
+/// Hash of `path` filled, or stroked `width` wide.
+fn shape_hash(path: &Path, width: Option<f32>) -> u64 {
+  let bits = |at: Point| [at.x.to_bits(), at.y.to_bits()];
+  let mut words = vec![width.map_or(u32::MAX, f32::to_bits)];
+  for event in path.iter() {
+    match event {
+      Event::Begin { at } => words.extend([0].into_iter().chain(bits(at))),
+      Event::Line { to, .. } => words.extend([1].into_iter().chain(bits(to))),
+      Event::Quadratic { ctrl, to, .. } => {
+        words.extend([2].into_iter().chain(bits(ctrl)).chain(bits(to)));
+      }
+      Event::Cubic {
+        ctrl1, ctrl2, to, ..
+      } => words.extend(
+        [3]
+          .into_iter()
+          .chain(bits(ctrl1))
+          .chain(bits(ctrl2))
+          .chain(bits(to)),
+      ),
+      Event::End { close, .. } => words.push(4 + close as u32),
+    }
+  }
+  content_hash(&words)
+}
@@ -0,0 +1,391 @@
+use std::num::NonZeroU32;
+use std::sync::mpsc::{self, Receiver, TryRecvError};
+
+use bytemuck::{Pod, Zeroable};
+use serde::Deserialize;
+use wgpu::util::{BufferInitDescriptor, DeviceExt};
+use wgpu::{
+  AddressMode, BindGroupDescriptor, BindGroupEntry, BindGroupLayout, BindGroupLayoutDescriptor,
+  BindGroupLayoutEntry, BindingResource, BindingType, Buffer, BufferAsyncError, BufferBindingType,
+  BufferDescriptor, BufferUsages, Color, CommandEncoder, Device, Extent3d, FilterMode,
+  ImageCopyBuffer, ImageDataLayout, LoadOp, Maintain, MapMode, Operations, Queue,
+  RenderPassColorAttachment, RenderPassDescriptor, RenderPipeline, Sampler, SamplerBindingType,
+  SamplerDescriptor, ShaderStages, Texture, TextureDescriptor, TextureDimension, TextureFormat,
+  TextureSampleType, TextureUsages, TextureView, TextureViewDescriptor, TextureViewDimension,
+  COPY_BYTES_PER_ROW_ALIGNMENT,
+};
+
+use crate::error::WindshieldError;
+use crate::layout::Length;
+use crate::mirror::Mirror;
+use crate::pipeline::{create_shader, PipelineBuilder};
+use crate::stats::FrameStats;
+
+/// Always bundled, a monitor can't trust checksums of a replaced shader.
+const SHADER: (&str, &str) = ("checksum.wgsl", include_str!("shaders/checksum.wgsl"));
+
+/// Texels per side a region is downsampled to. Must match
+/// `shaders/checksum.wgsl`.
+const TILE: u32 = 16;
+
+/// How many regions can be checked, the rest are ignored. Must match
+/// `shaders/checksum.wgsl`.
+pub const MAX_REGIONS: usize = 16;
+
+/// A part of the frame an external safety monitor wants to verify, like
+/// the speed readout, see [`Settings::checksum_regions`](crate::Settings::checksum_regions).
+///
+/// ```toml
+/// [[checksums]]
+/// name = "speed"
+/// x = "5%"
+/// y = "20%"
+/// width = "40%"
+/// height = 120
+/// ```
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ChecksumRegion {
+  pub name: String,
+  /// From the top left corner of the frame as it's laid out, before it's
+  /// mirrored or warped.
+  pub x: Length,
+  pub y: Length,
+  pub width: Length,
+  pub height: Length,
+}
+
+impl ChecksumRegion {
+  pub fn new(
+    name: impl Into<String>,
+    x: impl Into<Length>,
+    y: impl Into<Length>,
+    width: impl Into<Length>,
+    height: impl Into<Length>,
+  ) -> Self {
+    Self {
+      name: name.into(),
+      x: x.into(),
+      y: y.into(),
+      width: width.into(),
+      height: height.into(),
+    }
+  }
+
+  /// x, y, width and height from 0 to 1 across a frame of `size`, where it
+  /// ends up after mirroring.
+  fn uv(&self, [width, height]: [f32; 2], mirror: Mirror) -> [f32; 4] {
+    let x = self.x.resolve(width);
+    let y = self.y.resolve(height);
+    let size = [self.width.resolve(width), self.height.resolve(height)];
+    let [ax, ay] = mirror.apply([x, y], [width, height]);
+    let [bx, by] = mirror.apply([x + size[0], y + size[1]], [width, height]);
+    [
+      ax.min(bx) / width,
+      ay.min(by) / height,
+      size[0] / width,
+      size[1] / height,
+    ]
+  }
+}
+
+/// The CRC-32 of a region, downsampled on the GPU so it stays cheap to
+/// read back every frame.
+///
+/// The same pixels give the same checksum on the same GPU and driver, but
+/// not necessarily on another one. A monitor can tell a readout is alive
+/// by the checksum changing along with the value shown, and compare it
+/// against checksums it recorded before on the same hardware.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RegionChecksum {
+  pub name: String,
+  /// Index of the frame the checksum is of, see
+  /// [`FrameStats::frame`](crate::stats::FrameStats::frame).
+  pub frame: u64,
+  pub crc: u32,
+}
+
+/// Layout of `Checksum` in `shaders/checksum.wgsl`, padded to 16 bytes.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, Pod, Zeroable)]
+struct ChecksumUniform {
+  regions: [[f32; 4]; MAX_REGIONS],
+  count: u32,
+  padding: [u32; 3],
+}
+
+/// Downsamples the regions of the rendered frame into a row of tiles,
+/// copies them into a buffer and reads that back once the GPU is done with
+/// it, without ever waiting for it.
+///
+/// While a readback is still in flight, frames aren't checked, so the
+/// checksums skip frames on a slow GPU rather than slowing it down more.
+pub(crate) struct ChecksumPass {
+  regions: Vec<ChecksumRegion>,
+  pipeline: RenderPipeline,
+  layout: BindGroupLayout,
+  sampler: Sampler,
+  uniform: Buffer,
+  checksum: ChecksumUniform,
+  tiles: Texture,
+  readback: Buffer,
+  padded_row: u32,
+  // Frame copied into the readback buffer by the last encode
+  copied: Option<u64>,
+  mapping: Option<(u64, Receiver<Result<(), BufferAsyncError>>)>,
+}
+
+impl ChecksumPass {
+  /// `regions` must not be empty.
+  pub(crate) async fn new(
+    device: &Device,
+    mut regions: Vec<ChecksumRegion>,
+  ) -> Result<Self, WindshieldError> {
+    if regions.len() > MAX_REGIONS {
+      tracing::warn!(
+        "only checking the first {} of {} checksum regions",
+        MAX_REGIONS,
+        regions.len()
+      );
+      regions.truncate(MAX_REGIONS);
+    }
+    let (name, source) = SHADER;
+    let shader = create_shader(device, name, source).await?;
+    let layout = device.create_bind_group_layout(&BindGroupLayoutDescriptor {
+      label: Some("Checksum"),
+      entries: &[
+        BindGroupLayoutEntry {
+          binding: 0,
+          visibility: ShaderStages::VERTEX,
+          ty: BindingType::Buffer {
+            ty: BufferBindingType::Uniform,
+            has_dynamic_offset: false,
+            min_binding_size: None,
+          },
+          count: None,
+        },
+        BindGroupLayoutEntry {
+          binding: 1,
+          visibility: ShaderStages::FRAGMENT,
+          ty: BindingType::Texture {
+            sample_type: TextureSampleType::Float { filterable: true },
+            view_dimension: TextureViewDimension::D2,
+            multisampled: false,
+          },
+          count: None,
+        },
+        BindGroupLayoutEntry {
+          binding: 2,
+          visibility: ShaderStages::FRAGMENT,
+          ty: BindingType::Sampler(SamplerBindingType::Filtering),
+          count: None,
+        },
+      ],
+    });
+    // Unorm rather than sRGB, so the bytes read back are what was stored
+    let format = TextureFormat::Rgba8Unorm;
+    let pipeline = PipelineBuilder::new("Checksum Pipeline", &shader)
+      .bind_group_layout(&layout)
+      .blend(None)
+      .build(device, format);
+    let sampler = device.create_sampler(&SamplerDescriptor {
+      label: Some("Checksum"),
+      address_mode_u: AddressMode::ClampToEdge,
+      address_mode_v: AddressMode::ClampToEdge,
+      mag_filter: FilterMode::Linear,
+      min_filter: FilterMode::Linear,
+      ..Default::default()
+    });
+    let checksum = ChecksumUniform {
+      regions: [[0.0; 4]; MAX_REGIONS],
+      count: regions.len() as u32,
+      padding: [0; 3],
+    };
+    let uniform = device.create_buffer_init(&BufferInitDescriptor {
+      label: Some("Checksum"),
+      contents: bytemuck::bytes_of(&checksum),
+      usage: BufferUsages::UNIFORM | BufferUsages::COPY_DST,
+    });
+    let tiles = device.create_texture(&TextureDescriptor {
+      label: Some("Checksum Tiles"),
+      size: tiles_extent(regions.len()),
+      mip_level_count: 1,
+      sample_count: 1,
+      dimension: TextureDimension::D2,
+      format,
+      usage: TextureUsages::RENDER_ATTACHMENT | TextureUsages::COPY_SRC,
+    });
+    // Rows of buffer copies have to be aligned
+    let row = TILE * regions.len() as u32 * 4;
+    let padded_row = row.div_ceil(COPY_BYTES_PER_ROW_ALIGNMENT) * COPY_BYTES_PER_ROW_ALIGNMENT;
+    let readback = device.create_buffer(&BufferDescriptor {
+      label: Some("Checksum Readback"),
+      size: padded_row as u64 * TILE as u64,
+      usage: BufferUsages::COPY_DST | BufferUsages::MAP_READ,
+      mapped_at_creation: false,
+    });
+
+    Ok(Self {
+      regions,
+      pipeline,
+      layout,
+      sampler,
+      uniform,
+      checksum,
+      tiles,
+      readback,
+      padded_row,
+      copied: None,
+      mapping: None,
+    })
+  }
+
+  pub(crate) fn regions(&self) -> &[ChecksumRegion] {
+    &self.regions
+  }
+
+  /// Places the regions on a frame of `size`, shown with `mirror`.
+  pub(crate) fn place(&mut self, queue: &Queue, size: [u32; 2], mirror: Mirror) {
+    let size = size.map(|length| length as f32);
+    let before = self.checksum;
+    for (uv, region) in self.checksum.regions.iter_mut().zip(&self.regions) {
+      *uv = region.uv(size, mirror);
+    }
+    if self.checksum != before {
+      queue.write_buffer(&self.uniform, 0, bytemuck::bytes_of(&self.checksum));
+    }
+  }
+
+  /// Records downsampling the frame `stats` are collected for from
+  /// `source` and copying it into the readback buffer, unless that is
+  /// still being read from.
+  pub(crate) fn encode(
+    &mut self,
+    device: &Device,
+    encoder: &mut CommandEncoder,
+    source: &TextureView,
+    stats: &mut FrameStats,
+  ) {
+    if self.mapping.is_some() {
+      return;
+    }
+    // The frame texture is recreated on resize, the bind group is cheap
+    let bind_group = device.create_bind_group(&BindGroupDescriptor {
+      label: Some("Checksum"),
+      layout: &self.layout,
+      entries: &[
+        BindGroupEntry {
+          binding: 0,
+          resource: self.uniform.as_entire_binding(),
+        },
+        BindGroupEntry {
+          binding: 1,
+          resource: BindingResource::TextureView(source),
+        },
+        BindGroupEntry {
+          binding: 2,
+          resource: BindingResource::Sampler(&self.sampler),
+        },
+      ],
+    });
+    let view = self.tiles.create_view(&TextureViewDescriptor::default());
+    {
+      let mut pass = encoder.begin_render_pass(&RenderPassDescriptor {
+        label: Some("Checksum Pass"),
+        color_attachments: &[Some(RenderPassColorAttachment {
+          view: &view,
+          resolve_target: None,
+          ops: Operations {
+            load: LoadOp::Clear(Color::TRANSPARENT),
+            store: true,
+          },
+        })],
+        depth_stencil_attachment: None,
+      });
+      pass.set_pipeline(&self.pipeline);
+      pass.set_bind_group(0, &bind_group, &[]);
+      pass.draw(0..6, 0..self.regions.len() as u32);
+      stats.draw_calls += 1;
+      stats.triangles += 2 * self.regions.len() as u32;
+      stats.texture_binds += 1;
+    }
+    encoder.copy_texture_to_buffer(
+      self.tiles.as_image_copy(),
+      ImageCopyBuffer {
+        buffer: &self.readback,
+        layout: ImageDataLayout {
+          offset: 0,
+          bytes_per_row: NonZeroU32::new(self.padded_row),
+          rows_per_image: None,
+        },
+      },
+      tiles_extent(self.regions.len()),
+    );
+    self.copied = Some(stats.frame);
+  }
+
+  /// Starts reading back what the last [`encode`](Self::encode) copied,
+  /// once its commands are submitted.
+  pub(crate) fn submitted(&mut self) {
+    if let Some(frame) = self.copied.take() {
+      let (sender, mapped) = mpsc::channel();
+      self
+        .readback
+        .slice(..)
+        .map_async(MapMode::Read, move |result| {
+          let _ = sender.send(result);
+        });
+      self.mapping = Some((frame, mapped));
+    }
+  }
+
+  /// The checksums of the last frame read back since the last call, if
+  /// the GPU is done with one.
+  pub(crate) fn poll(&mut self, device: &Device) -> Option<Vec<RegionChecksum>> {
+    let (frame, mapped) = self.mapping.as_ref()?;
+    device.poll(Maintain::Poll);
+    let result = match mapped.try_recv() {
+      Err(TryRecvError::Empty) => return None,
+      Ok(result) => result,
+      Err(TryRecvError::Disconnected) => Err(BufferAsyncError),
+    };
+    let frame = *frame;
+    self.mapping = None;
+    if let Err(err) = result {
+      tracing::warn!("unable to read back frame checksums: {}", err);
+      return None;
+    }
+
+    let tile_row = (TILE * 4) as usize;
+    let mut hashers = vec![crc32fast::Hasher::new(); self.regions.len()];
+    {
+      let padded = self.readback.slice(..).get_mapped_range();
+      for row in padded.chunks(self.padded_row as usize) {
+        for (hasher, tile) in hashers.iter_mut().zip(row.chunks(tile_row)) {
+          hasher.update(tile);
+        }
+      }
+    }
+    self.readback.unmap();
+    Some(
+      self
+        .regions
+        .iter()
+        .zip(hashers)
+        .map(|(region, hasher)| RegionChecksum {
+          name: region.name.clone(),
+          frame,
+          crc: hasher.finalize(),
+        })
+        .collect(),
+    )
+  }
+}
+
+fn tiles_extent(regions: usize) -> Extent3d {
+  Extent3d {
+    width: TILE * regions as u32,
+    height: TILE,
+    depth_or_array_layers: 1,
+  }
+}
@@ -0,0 +1,148 @@
+use std::time::{Duration, SystemTime};
+
+use instant::Instant;
+
+/// Source of "now" for everything that is time dependent.
+///
+/// Times are measured from the creation of the clock, so implementations
+/// don't have to agree on an epoch.
+pub trait Clock {
+  /// Time elapsed since the clock was created.
+  fn now(&self) -> Duration;
+
+  /// Called once per frame before `now` is read for that frame.
+  fn tick(&mut self) {}
+
+  /// Whether time goes on without frames being drawn, as it does for
+  /// clocks following the wall clock.
+  fn runs_between_frames(&self) -> bool {
+    true
+  }
+}
+
+/// Wall clock time.
+pub struct RealClock {
+  start: Instant,
+}
+
+impl RealClock {
+  pub fn new() -> Self {
+    Self {
+      start: Instant::now(),
+    }
+  }
+}
+
+impl Default for RealClock {
+  fn default() -> Self {
+    Self::new()
+  }
+}
+
+impl Clock for RealClock {
+  fn now(&self) -> Duration {
+    self.start.elapsed()
+  }
+}
+
+/// Wall clock time sped up or slowed down by a constant factor,
+/// e.g. `2.0` to fast-forward a replay.
+pub struct ScaledClock {
+  inner: RealClock,
+  scale: f64,
+}
+
+impl ScaledClock {
+  pub fn new(scale: f64) -> Self {
+    Self {
+      inner: RealClock::new(),
+      scale,
+    }
+  }
+}
+
+impl Clock for ScaledClock {
+  fn now(&self) -> Duration {
+    self.inner.now().mul_f64(self.scale)
+  }
+}
+
+/// How far time advances per frame in
+/// [deterministic mode](crate::Settings::deterministic), as if drawing at
+/// 60 frames per second.
+pub const DETERMINISTIC_STEP: Duration = Duration::from_nanos(16_666_667);
+
+/// Advances by a fixed step on every tick, independent of how long a frame
+/// actually took. Makes frame-by-frame output deterministic.
+pub struct SteppedClock {
+  now: Duration,
+  step: Duration,
+}
+
+impl SteppedClock {
+  pub fn new(step: Duration) -> Self {
+    Self {
+      now: Duration::ZERO,
+      step,
+    }
+  }
+
+  /// Moves the clock forward by an arbitrary amount.
+  pub fn advance(&mut self, by: Duration) {
+    self.now += by;
+  }
+}
+
+impl Clock for SteppedClock {
+  fn now(&self) -> Duration {
+    self.now
+  }
+
+  fn tick(&mut self) {
+    self.now += self.step;
+  }
+
+  fn runs_between_frames(&self) -> bool {
+    false
+  }
+}
+
+/// Spaces frames out to at most a number per second, so a HUD that doesn't
+/// need the screen's full refresh rate draws less power.
+#[derive(Clone, Debug)]
+pub struct FrameLimiter {
+  interval: Duration,
+  next: Instant,
+}
+
+impl FrameLimiter {
+  pub fn new(max_fps: f32) -> Self {
+    Self {
+      interval: Duration::from_secs_f32(1.0 / max_fps.max(1.0)),
+      next: Instant::now(),
+    }
+  }
+
+  /// The earliest the next frame may be drawn.
+  pub fn next_frame(&self) -> Instant {
+    self.next
+  }
+
+  /// Counts a frame as drawn at `now`.
+  pub fn frame(&mut self, now: Instant) {
+    // Frames that were missed are skipped rather than caught up on
+    self.next = (self.next + self.interval).max(now);
+  }
+}
+
+/// The current date and time like `SystemTime::now`, which panics on the
+/// web where there is no system clock to read.
+pub fn system_time() -> SystemTime {
+  #[cfg(target_arch = "wasm32")]
+  {
+    let elapsed = instant::SystemTime::now().duration_since(instant::SystemTime::UNIX_EPOCH);
+    std::time::UNIX_EPOCH + elapsed.unwrap_or_default()
+  }
+  #[cfg(not(target_arch = "wasm32"))]
+  SystemTime::now()
+}
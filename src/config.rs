@@ -0,0 +1,1361 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::time::Duration;
+
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use wgpu::PresentMode;
+use winit::dpi::LogicalSize;
+
+use crate::accessibility::Accessibility;
+use crate::anim::Easing;
+use crate::burn_in::BurnIn;
+use crate::checksum::ChecksumRegion;
+use crate::data::arbiter::Origin;
+use crate::data::expr::Expr;
+use crate::data::process::Processor;
+use crate::data::{Field, ValueSource};
+use crate::error::WindshieldError;
+use crate::image::{Fit, Image};
+use crate::input::{Action, Trigger};
+use crate::input_map::Timing;
+use crate::layer_shell::LayerShell;
+use crate::layout::{Anchor, Length, Node};
+use crate::locale::Locale;
+use crate::map::bundle::MapBundle;
+use crate::map::{Coordinate, MapData};
+use crate::mirror::Mirror;
+use crate::rules::Rule;
+use crate::safe_area::SafeArea;
+use crate::scene::{Scene, Transition, Viewport, DEFAULT_TRANSITION_TIME};
+use crate::settings::Settings;
+use crate::text::{FontId, TextRenderer, TextSection};
+use crate::theme::{ColorVision, Palette, ThemeMode};
+use crate::warp::Keystone;
+use crate::widgets::{
+  Correction, EnergyFlow, FollowingGap, GMeter, Label, MapView, Marquee, NavigationArrow,
+  ParkingAssist, Picture, PressureUnit, QrCode, RadialGauge, SearchScreen, TrafficLegend,
+  VehicleSchematic, WeatherPanel,
+};
+
+/// A dashboard described in a TOML or JSON file: which widgets are shown
+/// where, and how they look.
+///
+/// ```toml
+/// title = "Dashboard"
+/// units = "imperial"
+/// locale = "en-US"
+/// font_fallbacks = ["symbols"]
+///
+/// [display]
+/// mirror = "horizontal"
+/// max_fps = 30
+/// ui_scale = 1.25
+/// fullscreen = "borderless"
+/// hide_cursor = true
+///
+/// [theme]
+/// mode = "auto"
+///
+/// [theme.day]
+/// accent = [1.0, 0.5, 0.0, 1.0]
+///
+/// [theme.night]
+/// background = [0.0, 0.0, 0.0, 1.0]
+///
+/// [fonts]
+/// display = "/usr/share/fonts/TTF/Inter.ttf"
+/// symbols = "/usr/share/fonts/noto/NotoSansSymbols2-Regular.ttf"
+///
+/// [[widgets]]
+/// type = "speedometer"
+/// name = "speed"
+/// max = 160
+/// anchor = "bottom-left"
+/// width = "40%"
+/// height = "80%"
+/// square = true
+///
+/// [[widgets]]
+/// type = "label"
+/// text = "Now playing"
+/// font = "display"
+/// marquee = true
+/// anchor = "top"
+/// width = "50%"
+/// height = 48
+///
+/// [[widgets]]
+/// type = "label"
+/// text = "SHIFT"
+/// visible_when = "rpm > 6500"
+/// anchor = "center"
+/// width = "20%"
+/// height = 64
+///
+/// [transition]
+/// kind = "crossfade"
+/// duration = 0.4
+/// easing = "ease-out"
+///
+/// [[pages]]
+/// name = "engine"
+///
+/// [[pages.widgets]]
+/// type = "tachometer"
+/// max = 7000
+/// redline = 6000
+/// anchor = "center"
+/// width = "60%"
+/// square = true
+///
+/// [bindings]
+/// PageDown = "next_page"
+/// MouseRight = "back"
+///
+/// [[checksums]]
+/// name = "speed"
+/// x = 0
+/// y = "20%"
+/// width = "40%"
+/// height = "60%"
+/// ```
+///
+/// Colors are linear RGBA in straight alpha, lengths are pixels or
+/// percentages of the window.
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+  pub title: Option<String>,
+  pub units: Units,
+  /// How readouts and other widgets showing numbers, times or dates
+  /// write them, as the tag of a locale like `"de-DE"`, see
+  /// [`Locale::for_tag`].
+  pub locale: Option<Locale>,
+  pub display: Display,
+  pub theme: Theme,
+  /// Font files by the name widgets refer to them with, next to the
+  /// fonts the application registered, see
+  /// [`TextRenderer::register_font`].
+  pub fonts: HashMap<String, PathBuf>,
+  /// Names of fonts tried in order for characters a widget's font has no
+  /// glyph for, like `["cjk", "symbols", "emoji"]`, replacing the
+  /// fallbacks set before, see [`TextRenderer::set_fallbacks`].
+  pub font_fallbacks: Vec<String>,
+  /// Shown on their own if there are no pages, and on top of every page
+  /// otherwise.
+  pub widgets: Vec<WidgetConfig>,
+  /// Pages the widgets are shown on one at a time, see [`Scene`].
+  pub pages: Vec<PageConfig>,
+  pub transition: TransitionConfig,
+  /// Parts of the window with widgets and pages of their own, below the
+  /// widgets and pages above, see [`Viewport`].
+  pub viewports: Vec<ViewportConfig>,
+  /// See [`Settings::checksum_regions`], replacing those set before.
+  pub checksums: Vec<ChecksumRegion>,
+  /// See [`Settings::rules`], replacing those set before.
+  pub rules: Vec<Rule>,
+  /// See [`Settings::signals`], replacing the processors set before for
+  /// the fields it has.
+  pub signals: HashMap<Field, Vec<Processor>>,
+  /// See [`Settings::priorities`], replacing the sources set before for
+  /// the fields it has.
+  pub priorities: HashMap<Field, Vec<Origin>>,
+  /// See [`Settings::accessibility`].
+  pub accessibility: Option<Accessibility>,
+  pub bindings: BindingsConfig,
+}
+
+/// Actions by the [`Trigger`](crate::input::Trigger) doing them, on top of
+/// the defaults, see [`Bindings`](crate::input::Bindings).
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct BindingsConfig {
+  /// Keys and mouse buttons held.
+  pub long_press: HashMap<String, Action>,
+  /// Keys and mouse buttons pressed twice.
+  pub double_tap: HashMap<String, Action>,
+  pub timing: Option<Timing>,
+  /// Triggers pressed.
+  #[serde(flatten)]
+  pub press: HashMap<String, Action>,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Units {
+  /// km/h
+  #[default]
+  Metric,
+  /// mph
+  Imperial,
+}
+
+impl Units {
+  /// The other units.
+  pub fn toggled(self) -> Self {
+    match self {
+      Self::Metric => Self::Imperial,
+      Self::Imperial => Self::Metric,
+    }
+  }
+}
+
+/// How the image is shown, see [`Settings::mirror`].
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Display {
+  pub mirror: Option<Mirror>,
+  pub high_contrast: Option<bool>,
+  /// See [`Settings::reduce_motion`].
+  pub reduce_motion: Option<bool>,
+  /// See [`Settings::right_to_left`].
+  pub right_to_left: Option<bool>,
+  /// See [`Settings::simulate_color_vision`].
+  pub simulate_color_vision: Option<ColorVision>,
+  /// Screen positions of the frame's corners, see [`Keystone`].
+  pub keystone: Option<Keystone>,
+  pub present_mode: Option<Presentation>,
+  /// See [`Settings::max_fps`].
+  pub max_fps: Option<f32>,
+  /// Seconds between frames while nothing changes, see
+  /// [`Settings::heartbeat`].
+  pub heartbeat: Option<f32>,
+  /// Seconds without an update before telemetry is stale, 0 for never,
+  /// see [`Settings::stale_after`].
+  pub stale_after: Option<f32>,
+  /// Seconds, see [`Settings::fallback_after`].
+  pub fallback_after: Option<f32>,
+  /// See [`Settings::ui_scale`].
+  pub ui_scale: Option<f32>,
+  /// See [`Settings::safe_area`].
+  pub safe_area: Option<SafeArea>,
+  /// See [`Settings::burn_in`].
+  pub burn_in: Option<BurnIn>,
+  /// Seconds, see [`Settings::ignition_off_delay`].
+  pub ignition_off_delay: Option<f32>,
+  /// Inner size of the window in logical pixels.
+  pub size: Option<[u32; 2]>,
+  /// See [`Settings::transparent`], only changed by restarting.
+  pub transparent: Option<bool>,
+  pub fullscreen: Option<Fullscreen>,
+  pub hide_cursor: Option<bool>,
+  pub always_on_top: Option<bool>,
+  /// See [`Settings::keep_awake`].
+  pub keep_awake: Option<bool>,
+  /// See [`Settings::layer_shell`], only changed by restarting.
+  pub layer_shell: Option<LayerShell>,
+}
+
+/// How the pages make way for each other, see [`Scene::transition`].
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct TransitionConfig {
+  pub kind: Transition,
+  /// In seconds.
+  pub duration: f32,
+  pub easing: Easing,
+}
+
+impl Default for TransitionConfig {
+  fn default() -> Self {
+    Self {
+      kind: Transition::default(),
+      duration: DEFAULT_TRANSITION_TIME.as_secs_f32(),
+      easing: Easing::default(),
+    }
+  }
+}
+
+/// One page of the dashboard, e.g. an engine page or a trip computer.
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct PageConfig {
+  /// Lets the application find the page, see [`Scene::find_page`].
+  pub name: Option<String>,
+  pub widgets: Vec<WidgetConfig>,
+}
+
+/// A part of the window showing widgets and pages of its own, like a
+/// cluster next to the HUD:
+///
+/// ```toml
+/// [[viewports]]
+/// x = "60%"
+/// y = 0
+/// width = "40%"
+/// height = "100%"
+///
+/// [[viewports.pages]]
+/// name = "engine"
+/// ```
+#[derive(Clone, Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ViewportConfig {
+  /// From the top left corner of the window.
+  pub x: Length,
+  pub y: Length,
+  pub width: Length,
+  pub height: Length,
+  /// Shown on their own if there are no pages, and on top of every page
+  /// otherwise.
+  #[serde(default)]
+  pub widgets: Vec<WidgetConfig>,
+  #[serde(default)]
+  pub pages: Vec<PageConfig>,
+  #[serde(default)]
+  pub transition: TransitionConfig,
+}
+
+/// The present modes that make sense to pick, see
+/// [`Settings::present_mode`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Presentation {
+  /// Waits for vertical sync, no tearing.
+  Fifo,
+  /// Replaces queued frames with newer ones, no tearing but lower latency.
+  Mailbox,
+  /// Shows frames right away, lowest latency but may tear.
+  Immediate,
+}
+
+impl Presentation {
+  pub fn mode(self) -> PresentMode {
+    match self {
+      Self::Fifo => PresentMode::Fifo,
+      Self::Mailbox => PresentMode::Mailbox,
+      Self::Immediate => PresentMode::Immediate,
+    }
+  }
+}
+
+impl FromStr for Presentation {
+  type Err = String;
+
+  fn from_str(name: &str) -> Result<Self, Self::Err> {
+    match name {
+      "fifo" => Ok(Self::Fifo),
+      "mailbox" => Ok(Self::Mailbox),
+      "immediate" => Ok(Self::Immediate),
+      _ => Err(format!(
+        "unknown present mode {:?}, expected fifo, mailbox or immediate",
+        name
+      )),
+    }
+  }
+}
+
+/// How the window covers the screen, see [`Settings::fullscreen`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Fullscreen {
+  /// A window the size of the monitor at its current resolution, quick to
+  /// switch to and from.
+  Borderless,
+  /// Switches the monitor to its highest resolution and refresh rate and
+  /// bypasses the compositor.
+  Exclusive,
+}
+
+impl FromStr for Fullscreen {
+  type Err = String;
+
+  fn from_str(name: &str) -> Result<Self, Self::Err> {
+    match name {
+      "borderless" => Ok(Self::Borderless),
+      "exclusive" => Ok(Self::Exclusive),
+      _ => Err(format!(
+        "unknown fullscreen mode {:?}, expected borderless or exclusive",
+        name
+      )),
+    }
+  }
+}
+
+/// The day and night palettes and when to switch between them, see
+/// [`Themes`](crate::theme::Themes).
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Theme {
+  /// File with more of the theme, laid out like this table and relative to
+  /// the config file, e.g. to share it between dashboards. What this table
+  /// sets itself takes precedence. Hot reloading watches it as well.
+  pub file: Option<PathBuf>,
+  /// Keeps the mode set with `--theme` or the builder if unset.
+  pub mode: Option<ThemeMode>,
+  /// Lux below which it's night, if there is a light sensor.
+  pub night_below: Option<f32>,
+  /// See [`Themes::color_vision`](crate::theme::Themes::color_vision).
+  pub color_vision: Option<ColorVision>,
+  pub day: Colors,
+  pub night: Colors,
+}
+
+impl Theme {
+  /// This theme with what it doesn't set taken from `base`.
+  fn or(self, base: Theme) -> Self {
+    Self {
+      file: self.file,
+      mode: self.mode.or(base.mode),
+      night_below: self.night_below.or(base.night_below),
+      color_vision: self.color_vision.or(base.color_vision),
+      day: self.day.or(base.day),
+      night: self.night.or(base.night),
+    }
+  }
+}
+
+/// The key or mouse button `name` triggers with, warning if it isn't one.
+fn button_trigger(name: &str) -> Option<Trigger> {
+  match name.parse::<Trigger>() {
+    Ok(trigger) if trigger.is_button() => Some(trigger),
+    Ok(_) => {
+      tracing::warn!(
+        "ignoring binding: only keys and mouse buttons are held or tapped twice, not {:?}",
+        name
+      );
+      None
+    }
+    Err(err) => {
+      tracing::warn!("ignoring binding: {}", err);
+      None
+    }
+  }
+}
+
+/// Colors overriding those of a [`Palette`].
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Colors {
+  pub background: Option<[f32; 4]>,
+  /// Panels and gauge faces.
+  pub surface: Option<[f32; 4]>,
+  pub text: Option<[f32; 4]>,
+  /// Ticks, tracks and other secondary parts.
+  pub muted: Option<[f32; 4]>,
+  /// Needles, highlights and controls that are on.
+  pub accent: Option<[f32; 4]>,
+  pub warning: Option<[f32; 4]>,
+}
+
+impl Colors {
+  fn or(self, base: Colors) -> Self {
+    Self {
+      background: self.background.or(base.background),
+      surface: self.surface.or(base.surface),
+      text: self.text.or(base.text),
+      muted: self.muted.or(base.muted),
+      accent: self.accent.or(base.accent),
+      warning: self.warning.or(base.warning),
+    }
+  }
+
+  fn apply(&self, palette: &mut Palette) {
+    for (color, value) in [
+      (&mut palette.background, self.background),
+      (&mut palette.surface, self.surface),
+      (&mut palette.text, self.text),
+      (&mut palette.muted, self.muted),
+      (&mut palette.accent, self.accent),
+      (&mut palette.warning, self.warning),
+    ] {
+      if let Some(value) = value {
+        *color = value;
+      }
+    }
+  }
+}
+
+/// One widget and its place in the window.
+#[derive(Clone, Debug, Deserialize)]
+pub struct WidgetConfig {
+  /// Lets the application find the widget in the scene to update it.
+  pub name: Option<String>,
+  #[serde(flatten)]
+  pub kind: WidgetKind,
+  #[serde(default)]
+  pub anchor: Anchor,
+  #[serde(default = "zero")]
+  pub x: Length,
+  #[serde(default = "zero")]
+  pub y: Length,
+  #[serde(default = "full")]
+  pub width: Length,
+  #[serde(default = "full")]
+  pub height: Length,
+  #[serde(default)]
+  pub square: bool,
+  /// Scale of the widget in place of [`Settings::ui_scale`] times the
+  /// display's scale factor, see [`Node::scale`].
+  pub scale: Option<f32>,
+  #[serde(default)]
+  pub z: i32,
+  #[serde(default = "visible")]
+  pub visible: bool,
+  /// Shows the widget only while this [expression](crate::data::expr)
+  /// holds, like `"rpm > 6500"`.
+  pub visible_when: Option<Expr>,
+  /// Draws the widget into a texture and shows that until it changes, see
+  /// [`Node::cache`].
+  #[serde(default)]
+  pub cache: bool,
+  /// What screen readers call the widget, see [`Node::accessible_name`].
+  pub accessible_name: Option<String>,
+  /// Mirrors the widget in right-to-left layouts, see [`Node::mirror`].
+  #[serde(default = "mirror")]
+  pub mirror: bool,
+  /// Name of an entry in [`Config::fonts`], the default font if unset.
+  pub font: Option<String>,
+  /// Telemetry shown by the widget, a field or an
+  /// [expression](crate::data::expr) of them like `"speed * 0.621"`.
+  /// Gauges show the matching value by default, e.g. a speedometer the
+  /// speed.
+  pub source: Option<Expr>,
+  /// Telemetry value shown at the start of the widget's range, in the
+  /// units telemetry is reported in. Not called `min` so it doesn't take
+  /// the key from the widget's own settings, like a speedometer's `max`.
+  pub source_min: Option<f32>,
+  /// Telemetry value shown at the end of the widget's range.
+  pub source_max: Option<f32>,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum WidgetKind {
+  /// `max` is in the configured units.
+  Speedometer {
+    max: f32,
+  },
+  Tachometer {
+    max: f32,
+    redline: f32,
+  },
+  Fuel,
+  Temperature,
+  Label {
+    text: String,
+    #[serde(default = "text_size")]
+    size: f32,
+    #[serde(default)]
+    marquee: bool,
+  },
+  /// `text` as a QR code, like a link to the companion app.
+  QrCode {
+    text: String,
+    #[serde(default)]
+    correction: Correction,
+  },
+  /// A PNG file, or nothing until the application sets the image of the
+  /// [`Picture`] it finds by name, e.g. to show a camera.
+  Picture {
+    file: Option<PathBuf>,
+    #[serde(default)]
+    fit: Fit,
+    #[serde(default)]
+    radius: f32,
+    #[serde(default)]
+    border: f32,
+  },
+  /// The current weather and the next `days`, in °F with imperial units.
+  Weather {
+    #[serde(default = "forecast_days")]
+    days: usize,
+  },
+  /// Power flowing between the battery and the motor of an electric
+  /// vehicle with the charge and range, dashes running fastest at
+  /// `max_power` kW.
+  EnergyFlow {
+    #[serde(default = "max_power")]
+    max_power: f32,
+  },
+  /// The following gap of adaptive cruise control and the set speed,
+  /// cautioning below `caution_gap` and alerting below `alert_gap` seconds
+  /// to the vehicle ahead.
+  FollowingGap {
+    #[serde(default = "caution_gap")]
+    caution_gap: f32,
+    #[serde(default = "alert_gap")]
+    alert_gap: f32,
+  },
+  /// Lateral and longitudinal acceleration up to `max_g` with a trail and
+  /// the largest seen.
+  GMeter {
+    #[serde(default = "max_g")]
+    max_g: f32,
+  },
+  /// A vector map read from a GeoJSON `file`, see
+  /// [`MapData::from_geojson`], or the offline [`MapBundle`] in the
+  /// `bundle` directory, following the vehicle from `zoom` or looking at
+  /// `center` if set. Bundles start out at their region until there's a
+  /// position.
+  Map {
+    file: Option<PathBuf>,
+    bundle: Option<PathBuf>,
+    #[serde(default = "map_zoom")]
+    zoom: f64,
+    center: Option<Coordinate>,
+  },
+  /// The next maneuver of the route navigated along and how far away it
+  /// is, in miles with imperial units, warning beyond `off_route` m from
+  /// the route.
+  Navigation {
+    #[serde(default = "off_route")]
+    off_route: f64,
+  },
+  /// A text field with an on-screen keyboard searching for destinations,
+  /// the places found listed below it. Searches and navigates once the
+  /// application [connects](SearchScreen::connect) it.
+  Search,
+  /// What the colors of traffic on the map mean.
+  TrafficLegend,
+  /// Parking sensor arcs around the vehicle, lit for obstacles below the
+  /// far, near and close `ranges` in m.
+  ParkingAssist {
+    #[serde(default = "parking_ranges")]
+    ranges: [f32; 3],
+  },
+  /// The vehicle from above with tire pressures, doors and lights. Tires
+  /// alert outside `min_pressure..=max_pressure` kPa, pressures are shown
+  /// in psi with imperial units unless `unit` says otherwise.
+  Vehicle {
+    #[serde(default = "min_pressure")]
+    min_pressure: f32,
+    #[serde(default = "max_pressure")]
+    max_pressure: f32,
+    unit: Option<PressureUnit>,
+  },
+}
+
+const KM_PER_MILE: f32 = 1.609344;
+
+fn named_font(text: &TextRenderer, name: &str) -> Result<FontId, WindshieldError> {
+  text
+    .font_named(name)
+    .ok_or_else(|| WindshieldError::UnknownFont(name.to_string()))
+}
+
+fn load<T: DeserializeOwned>(path: &Path) -> Result<T, WindshieldError> {
+  let source = std::fs::read_to_string(path).map_err(|source| WindshieldError::ReadFile {
+    path: path.to_path_buf(),
+    source,
+  })?;
+  parse(&source, path)
+}
+
+fn parse<T: DeserializeOwned>(source: &str, path: &Path) -> Result<T, WindshieldError> {
+  let parsed = if path.extension() == Some("json".as_ref()) {
+    serde_json::from_str(source).map_err(|err| err.to_string())
+  } else {
+    toml::from_str(source).map_err(|err| err.to_string())
+  };
+  parsed.map_err(|message| WindshieldError::InvalidConfig {
+    path: path.to_path_buf(),
+    message,
+  })
+}
+
+fn zero() -> Length {
+  Length::Px(0.0)
+}
+
+fn full() -> Length {
+  Length::Percent(100.0)
+}
+
+fn visible() -> bool {
+  true
+}
+
+fn mirror() -> bool {
+  true
+}
+
+fn text_size() -> f32 {
+  32.0
+}
+
+fn forecast_days() -> usize {
+  3
+}
+
+fn max_power() -> f32 {
+  150.0
+}
+
+fn caution_gap() -> f32 {
+  1.2
+}
+
+fn alert_gap() -> f32 {
+  0.6
+}
+
+fn max_g() -> f32 {
+  1.5
+}
+
+fn map_zoom() -> f64 {
+  15.0
+}
+
+fn off_route() -> f64 {
+  50.0
+}
+
+fn parking_ranges() -> [f32; 3] {
+  [1.5, 0.9, 0.4]
+}
+
+fn min_pressure() -> f32 {
+  200.0
+}
+
+fn max_pressure() -> f32 {
+  300.0
+}
+
+impl Config {
+  /// Reads a config file, as JSON if it ends in `.json` and as TOML
+  /// otherwise, along with its [theme file](Theme::file).
+  pub fn load(path: impl AsRef<Path>) -> Result<Self, WindshieldError> {
+    let path = path.as_ref();
+    let mut config: Self = load(path)?;
+    if let Some(file) = config.theme.file.take() {
+      let file = path.parent().unwrap_or(Path::new("")).join(file);
+      let theme: Theme = load(&file)?;
+      config.theme = config.theme.or(theme);
+      config.theme.file = Some(file);
+    }
+    Ok(config)
+  }
+
+  /// Parses a config read from elsewhere, as JSON if `path` ends in
+  /// `.json` and as TOML otherwise. `path` only names it in errors. Its
+  /// theme file isn't read.
+  pub fn parse(source: &str, path: impl AsRef<Path>) -> Result<Self, WindshieldError> {
+    parse(source, path.as_ref())
+  }
+
+  /// `$XDG_CONFIG_HOME/windshield/config.toml`, falling back to
+  /// `~/.config` if the variable isn't set. `config.toml` in the app's
+  /// internal storage on Android.
+  pub fn default_path() -> Option<PathBuf> {
+    #[cfg(target_os = "android")]
+    let dir = ndk_glue::native_activity()
+      .internal_data_path()
+      .to_path_buf();
+    #[cfg(not(target_os = "android"))]
+    let dir = std::env::var_os("XDG_CONFIG_HOME")
+      .filter(|dir| !dir.is_empty())
+      .map(PathBuf::from)
+      .or_else(|| std::env::var_os("HOME").map(|home| Path::new(&home).join(".config")))?
+      .join("windshield");
+    Some(dir.join("config.toml"))
+  }
+
+  /// Overrides the startup settings the config has values for.
+  pub fn apply(&self, settings: &mut Settings) {
+    if let Some(title) = &self.title {
+      settings.title = title.clone();
+    }
+    if let Some(mirror) = self.display.mirror {
+      settings.mirror = mirror;
+    }
+    if let Some(high_contrast) = self.display.high_contrast {
+      settings.high_contrast = high_contrast;
+    }
+    if let Some(reduce_motion) = self.display.reduce_motion {
+      settings.reduce_motion = reduce_motion;
+    }
+    if let Some(right_to_left) = self.display.right_to_left {
+      settings.right_to_left = right_to_left;
+    }
+    if let Some(vision) = self.display.simulate_color_vision {
+      settings.simulate_color_vision = vision;
+    }
+    if let Some(keystone) = self.display.keystone {
+      settings.keystone = keystone;
+    }
+    if let Some(present_mode) = self.display.present_mode {
+      settings.present_mode = present_mode.mode();
+    }
+    if let Some(max_fps) = self.display.max_fps {
+      settings.max_fps = Some(max_fps);
+    }
+    // Negative durations are ignored
+    if let Some(Ok(heartbeat)) = self.display.heartbeat.map(Duration::try_from_secs_f32) {
+      settings.heartbeat = Some(heartbeat);
+    }
+    if let Some(Ok(after)) = self.display.stale_after.map(Duration::try_from_secs_f32) {
+      settings.stale_after = (!after.is_zero()).then_some(after);
+    }
+    if let Some(Ok(after)) = self.display.fallback_after.map(Duration::try_from_secs_f32) {
+      settings.fallback_after = after;
+    }
+    if let Some(scale) = self.display.ui_scale.filter(|scale| *scale > 0.0) {
+      settings.ui_scale = scale;
+    }
+    if let Some(Ok(delay)) = self
+      .display
+      .ignition_off_delay
+      .map(Duration::try_from_secs_f32)
+    {
+      settings.ignition_off_delay = delay;
+    }
+    if let Some(burn_in) = self.display.burn_in {
+      settings.burn_in = Some(burn_in);
+    }
+    if let Some(safe_area) = self.display.safe_area {
+      settings.safe_area = safe_area;
+    }
+    if let Some([width, height]) = self.display.size {
+      settings.size = Some(LogicalSize::new(width, height).into());
+    }
+    if let Some(transparent) = self.display.transparent {
+      settings.transparent = transparent;
+    }
+    if let Some(fullscreen) = self.display.fullscreen {
+      settings.fullscreen = Some(fullscreen);
+    }
+    if let Some(hide_cursor) = self.display.hide_cursor {
+      settings.hide_cursor = hide_cursor;
+    }
+    if let Some(always_on_top) = self.display.always_on_top {
+      settings.always_on_top = always_on_top;
+    }
+    if let Some(keep_awake) = self.display.keep_awake {
+      settings.keep_awake = keep_awake;
+    }
+    if let Some(layer_shell) = &self.display.layer_shell {
+      settings.layer_shell = Some(layer_shell.clone());
+    }
+    let bindings = &self.bindings;
+    for (name, action) in &bindings.press {
+      match name.parse() {
+        Ok(trigger) => settings.bindings.bind(trigger, *action),
+        Err(err) => tracing::warn!("ignoring binding: {}", err),
+      }
+    }
+    for (name, action) in &bindings.long_press {
+      if let Some(trigger) = button_trigger(name) {
+        settings.bindings.bind_long_press(trigger, *action);
+      }
+    }
+    for (name, action) in &bindings.double_tap {
+      if let Some(trigger) = button_trigger(name) {
+        settings.bindings.bind_double_tap(trigger, *action);
+      }
+    }
+    if let Some(timing) = bindings.timing {
+      settings.bindings.set_timing(timing);
+    }
+    if !self.checksums.is_empty() {
+      settings.checksum_regions = self.checksums.clone();
+    }
+    if !self.rules.is_empty() {
+      settings.rules = self.rules.clone();
+    }
+    settings.signals.extend(self.signals.clone());
+    settings.priorities.extend(self.priorities.clone());
+    if let Some(accessibility) = self.accessibility {
+      settings.accessibility = accessibility;
+    }
+    let theme = &self.theme;
+    let themes = &mut settings.themes;
+    if let Some(mode) = theme.mode {
+      themes.mode = mode;
+    }
+    if let Some(night_below) = theme.night_below {
+      themes.night_below = night_below;
+    }
+    if let Some(color_vision) = theme.color_vision {
+      themes.color_vision = color_vision;
+    }
+    for (palette, colors) in [
+      (&mut themes.day, &theme.day),
+      (&mut themes.night, &theme.night),
+    ] {
+      colors.apply(palette);
+    }
+  }
+
+  /// Builds the pages of widgets, loading the fonts they use into `text`.
+  pub fn scene(&self, text: &mut TextRenderer) -> Result<Scene, WindshieldError> {
+    // Loaded in the same order every time, so fonts get the same ids from
+    // one run to the next
+    let mut names: Vec<_> = self.fonts.keys().collect();
+    names.sort();
+    for name in names {
+      let path = &self.fonts[name];
+      let data = std::fs::read(path).map_err(|source| WindshieldError::ReadFile {
+        path: path.clone(),
+        source,
+      })?;
+      text.register_font(name.as_str(), data)?;
+    }
+    if !self.font_fallbacks.is_empty() {
+      let fallbacks = self
+        .font_fallbacks
+        .iter()
+        .map(|name| named_font(text, name))
+        .collect::<Result<_, _>>()?;
+      text.set_fallbacks(fallbacks);
+    }
+
+    let mut scene = self.pages(&self.widgets, &self.pages, &self.transition, text)?;
+    for viewport in &self.viewports {
+      let pages = self.pages(
+        &viewport.widgets,
+        &viewport.pages,
+        &viewport.transition,
+        text,
+      )?;
+      scene = scene.with_viewport(Viewport::new(
+        viewport.x,
+        viewport.y,
+        viewport.width,
+        viewport.height,
+        pages,
+      ));
+    }
+    Ok(scene)
+  }
+
+  /// A scene of `pages` with `widgets` on top, or of only the widgets.
+  fn pages(
+    &self,
+    widgets: &[WidgetConfig],
+    pages: &[PageConfig],
+    transition: &TransitionConfig,
+    text: &mut TextRenderer,
+  ) -> Result<Scene, WindshieldError> {
+    let widgets = self.tree(widgets, text)?;
+    if pages.is_empty() {
+      return Ok(Scene::single(widgets));
+    }
+    // Negative durations are ignored
+    let duration = Duration::try_from_secs_f32(transition.duration);
+    let mut scene = Scene::new()
+      .with_overlay(widgets)
+      .with_transition(transition.kind, duration.unwrap_or(DEFAULT_TRANSITION_TIME))
+      .with_easing(transition.easing);
+    for page in pages {
+      let mut root = self.tree(&page.widgets, text)?;
+      root.name = page.name.clone();
+      scene = scene.with_page(root);
+    }
+    Ok(scene)
+  }
+
+  /// A node holding `widgets`, with fonts looked up by name in `fonts`.
+  fn tree(&self, widgets: &[WidgetConfig], fonts: &TextRenderer) -> Result<Node, WindshieldError> {
+    let mut root = Node::new();
+    for widget in widgets {
+      let font = match &widget.font {
+        Some(name) => named_font(fonts, name)?,
+        None => FontId::default(),
+      };
+      // What the widget shows unless configured otherwise, in the units
+      // telemetry is reported in
+      let mut source = None;
+      let mut node = match &widget.kind {
+        WidgetKind::Label {
+          text,
+          size,
+          marquee,
+        } => {
+          let section = TextSection::new(text.as_str())
+            .with_font(font)
+            .with_size(*size);
+          let mut label = Label::new(text.as_str()).with_section(section);
+          if *marquee {
+            label = label.with_marquee(Marquee::default());
+          }
+          Node::widget(label)
+        }
+        WidgetKind::QrCode { text, correction } => {
+          Node::widget(QrCode::new(text.as_str()).with_correction(*correction))
+        }
+        WidgetKind::Picture {
+          file,
+          fit,
+          radius,
+          border,
+        } => {
+          let image = match file {
+            Some(file) => Image::load_in_background(file)?,
+            None => Image::new(),
+          };
+          let picture = Picture::new(image)
+            .with_fit(*fit)
+            .with_radius(*radius)
+            .with_border(*border);
+          Node::widget(picture)
+        }
+        WidgetKind::Weather { days } => {
+          let mut panel = WeatherPanel::new().with_days(*days);
+          panel.fahrenheit = self.units == Units::Imperial;
+          Node::widget(panel)
+        }
+        WidgetKind::EnergyFlow { max_power } => {
+          let mut flow = EnergyFlow::new()
+            .with_max_power(*max_power)
+            .with_number_format(self.locale.unwrap_or_default().number);
+          flow.miles = self.units == Units::Imperial;
+          Node::widget(flow)
+        }
+        WidgetKind::FollowingGap {
+          caution_gap,
+          alert_gap,
+        } => {
+          let mut gap = FollowingGap::new()
+            .with_thresholds(*caution_gap, *alert_gap)
+            .with_number_format(self.locale.unwrap_or_default().number);
+          gap.miles = self.units == Units::Imperial;
+          Node::widget(gap)
+        }
+        WidgetKind::GMeter { max_g } => Node::widget(
+          GMeter::new()
+            .with_max_g(*max_g)
+            .with_number_format(self.locale.unwrap_or_default().number),
+        ),
+        WidgetKind::Map {
+          file,
+          bundle,
+          zoom,
+          center,
+        } => {
+          let bundle = bundle.as_ref().map(MapBundle::open).transpose()?;
+          let region = bundle.as_ref().map(|bundle| bundle.info.region.center());
+          let data = match (file, bundle) {
+            (_, Some(bundle)) => bundle.data,
+            (Some(file), None) => {
+              let source =
+                std::fs::read_to_string(file).map_err(|source| WindshieldError::ReadFile {
+                  path: file.clone(),
+                  source,
+                })?;
+              MapData::from_geojson(&source).map_err(|message| WindshieldError::InvalidMap {
+                path: file.clone(),
+                message,
+              })?
+            }
+            (None, None) => MapData::default(),
+          };
+          let map = match (center, region) {
+            (Some(center), _) => MapView::new(data).with_camera(*center, *zoom),
+            (None, Some(region)) => {
+              // Somewhere sensible until the first position comes in
+              let mut map = MapView::new(data).with_camera(region, *zoom);
+              map.recenter();
+              map
+            }
+            (None, None) => MapView::new(data).with_zoom(*zoom),
+          };
+          Node::widget(map)
+        }
+        WidgetKind::Navigation { off_route } => {
+          let mut arrow = NavigationArrow::new();
+          arrow.off_route = *off_route;
+          arrow.miles = self.units == Units::Imperial;
+          arrow.number_format = self.locale.unwrap_or_default().number;
+          Node::widget(arrow)
+        }
+        WidgetKind::Search => Node::widget(SearchScreen::new()),
+        WidgetKind::TrafficLegend => Node::widget(TrafficLegend::new()),
+        WidgetKind::ParkingAssist {
+          ranges: [far, near, close],
+        } => Node::widget(ParkingAssist::new().with_ranges(*far, *near, *close)),
+        WidgetKind::Vehicle {
+          min_pressure,
+          max_pressure,
+          unit,
+        } => {
+          let unit = unit.unwrap_or(match self.units {
+            Units::Metric => PressureUnit::Kpa,
+            Units::Imperial => PressureUnit::Psi,
+          });
+          let schematic = VehicleSchematic::new()
+            .with_pressure_range(*min_pressure, *max_pressure)
+            .with_pressure_unit(unit)
+            .with_number_format(self.locale.unwrap_or_default().number);
+          Node::widget(schematic)
+        }
+        WidgetKind::Speedometer { max } => {
+          let mut gauge = RadialGauge::speedometer(*max);
+          let readout = gauge.readout.as_mut().expect("speedometers have a readout");
+          readout.number_format = self.locale.unwrap_or_default().number;
+          let max = match self.units {
+            Units::Metric => readout.max,
+            Units::Imperial => {
+              readout.unit = "mph".to_string();
+              gauge.label = Some("mph".to_string());
+              readout.max * KM_PER_MILE
+            }
+          };
+          source = Some(ValueSource::new(Field::Speed, 0.0, max));
+          Node::widget(gauge)
+        }
+        WidgetKind::Tachometer { max, redline } => {
+          let mut gauge = RadialGauge::tachometer(*max, *redline);
+          if let Some(readout) = &mut gauge.readout {
+            readout.number_format = self.locale.unwrap_or_default().number;
+          }
+          let max = gauge.readout.as_ref().map_or(*max, |readout| readout.max);
+          source = Some(ValueSource::new(Field::Rpm, 0.0, max));
+          Node::widget(gauge)
+        }
+        WidgetKind::Fuel => {
+          source = Some(ValueSource::new(Field::FuelLevel, 0.0, 100.0));
+          Node::widget(RadialGauge::fuel())
+        }
+        WidgetKind::Temperature => {
+          source = Some(ValueSource::new(Field::CoolantTemp, 50.0, 130.0));
+          Node::widget(RadialGauge::temperature())
+        }
+      };
+      if let Some(expr) = &widget.source {
+        let (min, max) = source.map_or((0.0, 100.0), |source| (source.min, source.max));
+        source = Some(ValueSource::new(expr.clone(), min, max));
+      }
+      if let Some(source) = &mut source {
+        source.min = widget.source_min.unwrap_or(source.min);
+        source.max = widget.source_max.unwrap_or(source.max);
+      }
+
+      node.name = widget.name.clone();
+      node.anchor = widget.anchor;
+      node.offset = [widget.x, widget.y];
+      node.width = widget.width;
+      node.height = widget.height;
+      node.square = widget.square;
+      node.scale = widget.scale;
+      node.z = widget.z;
+      node.visible = widget.visible;
+      node.visible_when = widget.visible_when.clone();
+      node.cache = widget.cache;
+      node.accessible_name = widget.accessible_name.clone();
+      node.mirror = widget.mirror;
+      node.source = source;
+      root.push(node);
+    }
+    Ok(root)
+  }
+}
+
+/// The TOML example in the doc comments of `source`, the lines starting
+/// with `prefix` like `//!` between the first "```toml" and the "```"
+/// ending it, for tests to check that examples parse.
+#[cfg(test)]
+pub(crate) fn toml_example(source: &str, prefix: &str) -> String {
+  let start = format!("{} ```toml", prefix);
+  let end = format!("{} ```", prefix);
+  source
+    .lines()
+    .skip_while(|line| *line != start)
+    .skip(1)
+    .take_while(|line| *line != end)
+    .map(|line| line.trim_start_matches(prefix).trim_start())
+    .collect::<Vec<_>>()
+    .join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+  use winit::event::VirtualKeyCode;
+
+  use super::*;
+
+  /// The example in the docs of [`Config`].
+  fn doc_example() -> String {
+    toml_example(include_str!("config.rs"), "///")
+  }
+
+  #[test]
+  fn parses_doc_example() {
+    let config = Config::parse(&doc_example(), "config.toml").unwrap();
+    assert_eq!(config.units, Units::Imperial);
+    assert_eq!(config.locale, Locale::for_tag("en-US"));
+    assert!(matches!(
+      config.widgets[0].kind,
+      WidgetKind::Speedometer { max } if max == 160.0
+    ));
+    let shift = config.widgets[2].visible_when.as_ref().unwrap();
+    assert_eq!(shift.to_string(), "rpm > 6500");
+    assert!(matches!(
+      config.pages[0].widgets[0].kind,
+      WidgetKind::Tachometer { max, redline } if max == 7000.0 && redline == 6000.0
+    ));
+    assert_eq!(config.transition.kind, Transition::Crossfade);
+  }
+
+  #[test]
+  fn source_range_overrides_keep_widget_settings() {
+    let config = Config::parse(
+      r#"
+        [[widgets]]
+        type = "speedometer"
+        max = 200
+        source_min = 10
+        source_max = 180
+      "#,
+      "config.toml",
+    )
+    .unwrap();
+    let widget = &config.widgets[0];
+    assert!(matches!(widget.kind, WidgetKind::Speedometer { max } if max == 200.0));
+    assert_eq!(widget.source_min, Some(10.0));
+    assert_eq!(widget.source_max, Some(180.0));
+  }
+
+  #[test]
+  fn parses_json() {
+    let config = Config::parse(
+      r#"{ "widgets": [{ "type": "fuel", "width": "50%", "height": 120 }] }"#,
+      "config.json",
+    )
+    .unwrap();
+    let widget = &config.widgets[0];
+    assert!(matches!(widget.kind, WidgetKind::Fuel));
+    assert_eq!(widget.width, Length::Percent(50.0));
+    assert_eq!(widget.height, Length::Px(120.0));
+  }
+
+  #[test]
+  fn parses_viewports() {
+    let config = Config::parse(
+      r#"
+        [[viewports]]
+        x = "60%"
+        y = 0
+        width = "40%"
+        height = "100%"
+
+        [[viewports.pages]]
+        name = "engine"
+      "#,
+      "config.toml",
+    )
+    .unwrap();
+    let viewport = &config.viewports[0];
+    assert_eq!(viewport.x, Length::Percent(60.0));
+    assert_eq!(viewport.height, Length::Percent(100.0));
+    assert_eq!(viewport.pages[0].name.as_deref(), Some("engine"));
+    assert!(viewport.widgets.is_empty());
+  }
+
+  #[test]
+  fn applies_only_what_is_set() {
+    let config = Config::parse(
+      r#"
+        title = "Dashboard"
+
+        [display]
+        mirror = "horizontal"
+        max_fps = 30
+        reduce_motion = true
+        right_to_left = true
+
+        [bindings]
+        F5 = "snapshot"
+        "Hyper+K" = "mirror"
+      "#,
+      "config.toml",
+    )
+    .unwrap();
+    let mut settings = Settings {
+      high_contrast: true,
+      ..Default::default()
+    };
+    config.apply(&mut settings);
+    assert_eq!(settings.title, "Dashboard");
+    assert_eq!(settings.mirror, Mirror::Horizontal);
+    assert_eq!(settings.max_fps, Some(30.0));
+    assert!(settings.high_contrast);
+    assert!(settings.reduce_motion);
+    assert!(settings.right_to_left);
+    assert_eq!(
+      settings.bindings.get(Trigger::key(VirtualKeyCode::F5)),
+      Some(Action::Snapshot)
+    );
+    // Invalid bindings are skipped, the defaults stay
+    assert_eq!(
+      settings.bindings.get(Trigger::key(VirtualKeyCode::M)),
+      Some(Action::Mirror)
+    );
+  }
+
+  #[test]
+  fn theme_mode_is_kept_unless_set() {
+    let mut settings = Settings::default();
+    settings.themes.mode = ThemeMode::Night;
+    let config = Config::parse("[theme]\nnight_below = 20", "config.toml").unwrap();
+    config.apply(&mut settings);
+    assert_eq!(settings.themes.mode, ThemeMode::Night);
+
+    let config = Config::parse("[theme]\nmode = \"day\"", "config.toml").unwrap();
+    config.apply(&mut settings);
+    assert_eq!(settings.themes.mode, ThemeMode::Day);
+
+    let config = Config::parse("[theme]\ncolor_vision = \"protanopia\"", "config.toml").unwrap();
+    config.apply(&mut settings);
+    assert_eq!(settings.themes.mode, ThemeMode::Day);
+    assert_eq!(settings.themes.color_vision, ColorVision::Protanopia);
+  }
+
+  #[test]
+  fn theme_file_is_overridden_by_the_config() {
+    let dir = std::env::temp_dir().join(format!("windshield-theme-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    std::fs::write(
+      dir.join("theme.toml"),
+      "mode = \"night\"\n[day]\naccent = [1.0, 0.0, 0.0, 1.0]\ntext = [0.5, 0.5, 0.5, 1.0]",
+    )
+    .unwrap();
+    std::fs::write(
+      dir.join("config.toml"),
+      "[theme]\nfile = \"theme.toml\"\n[theme.day]\naccent = [0.0, 1.0, 0.0, 1.0]",
+    )
+    .unwrap();
+
+    let config = Config::load(dir.join("config.toml")).unwrap();
+    assert_eq!(config.theme.file, Some(dir.join("theme.toml")));
+    assert_eq!(config.theme.mode, Some(ThemeMode::Night));
+    assert_eq!(config.theme.day.accent, Some([0.0, 1.0, 0.0, 1.0]));
+    assert_eq!(config.theme.day.text, Some([0.5, 0.5, 0.5, 1.0]));
+    std::fs::remove_dir_all(dir).unwrap();
+  }
+
+  #[test]
+  fn parses_held_and_double_tapped_bindings() {
+    let config = Config::parse(
+      r#"
+        [bindings]
+        PageDown = "next_page"
+
+        [bindings.long_press]
+        K = "calibrate"
+
+        [bindings.timing]
+        long_press_ms = 800
+      "#,
+      "config.toml",
+    )
+    .unwrap();
+    let bindings = &config.bindings;
+    assert_eq!(bindings.press["PageDown"], Action::NextPage);
+    assert_eq!(bindings.long_press["K"], Action::Calibrate);
+    assert!(bindings.double_tap.is_empty());
+    assert_eq!(
+      bindings.timing.map(|timing| timing.long_press_ms),
+      Some(800)
+    );
+  }
+
+  #[test]
+  fn rejects_unknown_fields() {
+    assert!(Config::parse("colour = 1", "config.toml").is_err());
+  }
+}
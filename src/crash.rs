@@ -0,0 +1,126 @@
+use std::backtrace::Backtrace;
+use std::fmt::Write;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::UNIX_EPOCH;
+
+use tracing::Level;
+use wgpu::{AdapterInfo, SurfaceConfiguration};
+
+use crate::build_info::build_info;
+use crate::clock;
+use crate::logging::{self, LogBuffer};
+use crate::stats::FrameStats;
+
+/// Frames in a row the surface may be lost before it's reported, by when
+/// the GPU is likely gone or reset rather than the window resized.
+pub(crate) const LOST_FRAMES: u32 = 10;
+
+#[derive(Default)]
+struct Context {
+  adapter: Option<AdapterInfo>,
+  surface: Option<SurfaceConfiguration>,
+  last_frame: Option<FrameStats>,
+}
+
+/// Writes a report to `dir` whenever the renderer panics or loses its GPU,
+/// including what's known about the GPU state at that moment.
+#[derive(Clone)]
+pub(crate) struct CrashReporter {
+  dir: PathBuf,
+  logs: Option<LogBuffer>,
+  context: Arc<Mutex<Context>>,
+}
+
+impl CrashReporter {
+  /// Creates the reporter and hooks it into panics, keeping the previous
+  /// panic hook running afterwards.
+  pub(crate) fn install(dir: PathBuf, logs: Option<LogBuffer>) -> Self {
+    let reporter = Self {
+      dir,
+      logs,
+      context: Arc::default(),
+    };
+
+    let hook_reporter = reporter.clone();
+    let previous = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+      hook_reporter.write(&info.to_string());
+      previous(info);
+    }));
+
+    reporter
+  }
+
+  pub(crate) fn set_adapter(&self, info: AdapterInfo) {
+    self.context.lock().unwrap().adapter = Some(info);
+  }
+
+  pub(crate) fn set_surface(&self, config: &SurfaceConfiguration) {
+    self.context.lock().unwrap().surface = Some(config.clone());
+  }
+
+  pub(crate) fn set_last_frame(&self, stats: &FrameStats) {
+    self.context.lock().unwrap().last_frame = Some(stats.clone());
+  }
+
+  /// Writes a report, logging instead of failing if that isn't possible.
+  pub(crate) fn write(&self, reason: &str) {
+    match self.try_write(reason) {
+      Ok(path) => eprintln!("crash report written to {}", path.display()),
+      Err(err) => eprintln!("unable to write crash report: {}", err),
+    }
+  }
+
+  fn try_write(&self, reason: &str) -> std::io::Result<PathBuf> {
+    let time = clock::system_time()
+      .duration_since(UNIX_EPOCH)
+      .unwrap_or_default()
+      .as_secs();
+
+    let mut report = String::new();
+    let _ = writeln!(report, "{} crash report", build_info());
+    let _ = writeln!(report, "time: {}", time);
+    let _ = writeln!(report, "reason: {}", reason);
+
+    // The mutex may be poisoned if we panicked while holding it, the data
+    // is still worth reporting.
+    let context = self.context.lock().unwrap_or_else(|err| err.into_inner());
+    let _ = writeln!(report, "\n[adapter]\n{:#?}", context.adapter);
+    let _ = writeln!(report, "\n[surface]\n{:#?}", context.surface);
+    let _ = writeln!(report, "\n[last frame]\n{:#?}", context.last_frame);
+    drop(context);
+
+    if let Some(logs) = &self.logs {
+      let _ = writeln!(report, "\n[log]");
+      for record in logs.records(Level::TRACE) {
+        let _ = writeln!(
+          report,
+          "{} {:>5} {}: {}",
+          logging::time_of_day(record.time),
+          record.level,
+          record.target,
+          record.message
+        );
+      }
+    }
+
+    let _ = writeln!(report, "\n[backtrace]\n{}", Backtrace::force_capture());
+
+    fs::create_dir_all(&self.dir)?;
+    let path = unused_path(&self.dir, time);
+    fs::write(&path, report)?;
+    Ok(path)
+  }
+}
+
+fn unused_path(dir: &Path, time: u64) -> PathBuf {
+  let mut path = dir.join(format!("crash-{}.txt", time));
+  let mut n = 1;
+  while path.exists() {
+    path = dir.join(format!("crash-{}-{}.txt", time, n));
+    n += 1;
+  }
+  path
+}
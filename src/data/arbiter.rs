@@ -0,0 +1,189 @@
+//! Choosing between data sources reporting the same field, see
+//! [`Settings::priorities`](crate::Settings::priorities).
+//!
+//! A field with priorities only takes values from the sources listed for
+//! it, and of those from the first one still reporting. Once a source has
+//! gone without a value for
+//! [`Settings::fallback_after`](crate::Settings::fallback_after) the next
+//! one takes over, handing back as soon as the source reports again. Sources
+//! are listed by their [name](super::DataSource::name), and can stand in
+//! with another field of theirs, like GPS speed over ground while the
+//! vehicle's speed is unavailable:
+//!
+//! ```toml
+//! [priorities]
+//! speed = ["can", "obd", "gps:ground_speed"]
+//! ```
+
+use std::collections::HashMap;
+use std::fmt;
+use std::time::Duration;
+
+use serde::Deserialize;
+
+use super::{Field, Telemetry};
+
+/// Default of [`Settings::fallback_after`](crate::Settings::fallback_after),
+/// long enough for the slower OBD-II values.
+pub const DEFAULT_FALLBACK_AFTER: Duration = Duration::from_secs(1);
+
+/// Where a field may come from, written as the source's name, followed by
+/// `:` and a field of the source's if it's not the same field.
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize)]
+#[serde(try_from = "String")]
+pub struct Origin {
+  pub source: String,
+  pub field: Option<Field>,
+}
+
+impl Origin {
+  pub fn new(source: impl Into<String>) -> Self {
+    Self {
+      source: source.into(),
+      field: None,
+    }
+  }
+
+  pub fn with_field(mut self, field: Field) -> Self {
+    self.field = Some(field);
+    self
+  }
+}
+
+impl TryFrom<String> for Origin {
+  type Error = String;
+
+  fn try_from(text: String) -> Result<Self, Self::Error> {
+    match text.split_once(':') {
+      Some((source, field)) => Ok(Self::new(source).with_field(field.to_string().try_into()?)),
+      None => Ok(Self::new(text)),
+    }
+  }
+}
+
+impl fmt::Display for Origin {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    f.write_str(&self.source)?;
+    if let Some(field) = self.field {
+      write!(f, ":{}", field.name())?;
+    }
+    Ok(())
+  }
+}
+
+/// The origins of a field and when each last had a value.
+struct Arbitrated {
+  field: Field,
+  origins: Vec<Origin>,
+  reported: Vec<Option<Duration>>,
+  /// The origin the latest value came from.
+  active: Option<usize>,
+}
+
+/// Keeps the values of fields with priorities to those of the preferred
+/// source still reporting.
+pub(crate) struct Arbiter {
+  fields: Vec<Arbitrated>,
+  fallback_after: Duration,
+}
+
+impl Arbiter {
+  pub(crate) fn new(priorities: &HashMap<Field, Vec<Origin>>, fallback_after: Duration) -> Self {
+    let fields = priorities
+      .iter()
+      .map(|(field, origins)| Arbitrated {
+        field: *field,
+        origins: origins.clone(),
+        reported: vec![None; origins.len()],
+        active: None,
+      })
+      .collect();
+    Self {
+      fields,
+      fallback_after,
+    }
+  }
+
+  /// Leaves the values of `update`, reported by the source called `source`
+  /// at `now`, that the source is the preferred one for.
+  pub(crate) fn arbitrate(&mut self, source: &str, update: &mut Telemetry, now: Duration) {
+    // Read before any field is replaced, for origins standing in with a
+    // field that has priorities itself
+    let reported = update.clone();
+    for arbitrated in &mut self.fields {
+      let value = arbitrated
+        .origins
+        .iter()
+        .enumerate()
+        .filter(|(_, origin)| origin.source == source)
+        .find_map(|(index, origin)| {
+          let value = reported.get(origin.field.unwrap_or(arbitrated.field))?;
+          Some((index, value))
+        });
+      let value = value.and_then(|(index, value)| {
+        arbitrated.reported[index] = Some(now);
+        let preferred = arbitrated.reported[..index]
+          .iter()
+          .flatten()
+          .any(|reported| now.saturating_sub(*reported) < self.fallback_after);
+        if preferred {
+          return None;
+        }
+        if arbitrated.active != Some(index) {
+          let origin = &arbitrated.origins[index];
+          match arbitrated.active {
+            Some(_) => tracing::info!("{} switched to {}", arbitrated.field.name(), origin),
+            None => tracing::debug!("{} from {}", arbitrated.field.name(), origin),
+          }
+          arbitrated.active = Some(index);
+        }
+        Some(value)
+      });
+      *update.get_mut(arbitrated.field) = value;
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::config::toml_example;
+
+  #[test]
+  fn falls_back_while_the_preferred_source_is_quiet() {
+    let origins = vec![
+      Origin::new("can"),
+      "gps:ground_speed".to_string().try_into().unwrap(),
+    ];
+    let mut arbiter = Arbiter::new(&[(Field::Speed, origins)].into(), Duration::from_secs(1));
+    let mut report = |source, speed, ground_speed, millis| {
+      let mut update = Telemetry {
+        speed: Some(speed),
+        ground_speed: Some(ground_speed),
+        ..Default::default()
+      };
+      arbiter.arbitrate(source, &mut update, Duration::from_millis(millis));
+      update.speed
+    };
+    assert_eq!(report("gps", 0.0, 48.0, 0), Some(48.0));
+    assert_eq!(report("can", 50.0, 0.0, 100), Some(50.0));
+    assert_eq!(report("gps", 0.0, 48.0, 200), None);
+    // Unlisted sources never count
+    assert_eq!(report("obd", 49.0, 0.0, 300), None);
+    assert_eq!(report("gps", 0.0, 47.0, 1200), Some(47.0));
+    assert_eq!(report("can", 51.0, 0.0, 1300), Some(51.0));
+  }
+
+  #[test]
+  fn parses_the_module_example() {
+    let example = toml_example(include_str!("arbiter.rs"), "//!");
+    #[derive(Deserialize)]
+    struct Example {
+      priorities: HashMap<Field, Vec<Origin>>,
+    }
+    let example: Example = toml::from_str(&example).unwrap();
+    let origins = &example.priorities[&Field::Speed];
+    assert_eq!(origins[0], Origin::new("can"));
+    assert_eq!(origins[2].to_string(), "gps:ground_speed");
+  }
+}
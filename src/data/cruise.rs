@@ -0,0 +1,59 @@
+use serde::{Deserialize, Serialize};
+
+/// Longest following gap drivers can choose, in bars.
+pub const MAX_GAP: u8 = 4;
+
+/// Adaptive cruise control, as far as data sources reported it.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Cruise {
+  /// Whether the cruise control is holding the speed or gap.
+  pub engaged: Option<bool>,
+  /// km/h the cruise control holds without a vehicle ahead.
+  pub set_speed: Option<f32>,
+  /// Following gap the driver chose, from one bar for the closest to
+  /// [`MAX_GAP`].
+  pub gap: Option<u8>,
+  /// Whether a vehicle ahead is being followed.
+  pub lead: Option<bool>,
+  /// m to the vehicle ahead, if there is one.
+  pub lead_distance: Option<f32>,
+}
+
+impl Cruise {
+  /// Overwrites every value `update` has.
+  pub fn merge(&mut self, update: &Cruise) {
+    let Cruise {
+      engaged,
+      set_speed,
+      gap,
+      lead,
+      lead_distance,
+    } = *update;
+    for (value, update) in [(&mut self.engaged, engaged), (&mut self.lead, lead)] {
+      if update.is_some() {
+        *value = update;
+      }
+    }
+    for (value, update) in [
+      (&mut self.set_speed, set_speed),
+      (&mut self.lead_distance, lead_distance),
+    ] {
+      if update.is_some() {
+        *value = update;
+      }
+    }
+    if gap.is_some() {
+      self.gap = gap;
+    }
+  }
+
+  /// Seconds until reaching where the vehicle ahead is now at `speed`
+  /// km/h, none without one or while standing.
+  pub fn time_gap(&self, speed: f32) -> Option<f32> {
+    if self.lead != Some(true) || speed < 1.0 {
+      return None;
+    }
+    Some(self.lead_distance? / (speed / 3.6))
+  }
+}
@@ -0,0 +1,431 @@
+//! Expressions over [`Telemetry`] for configs to derive values and
+//! conditions without code, like `speed * 0.621` or
+//! `rpm > 6500 && throttle > 90`.
+//!
+//! Fields are named as in [`Field`], numbers are decimal. There are
+//! `+ - * /`, comparisons `< <= > >= == !=`, `&& || !` and parentheses,
+//! with the usual precedence, nested at most 64 deep. Conditions are 1 when true and 0 when false,
+//! and any value other than 0 counts as true. An expression has no value
+//! while a field it needs isn't known, or if it divides by zero.
+
+use std::fmt;
+use std::str::FromStr;
+
+use serde::Deserialize;
+
+use super::{Field, Telemetry};
+
+/// A parsed expression, shown as it was written.
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+#[serde(try_from = "String")]
+pub struct Expr {
+  text: String,
+  term: Term,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Term {
+  Number(f32),
+  Field(Field),
+  Neg(Box<Term>),
+  Not(Box<Term>),
+  Binary(Box<Term>, Op, Box<Term>),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Op {
+  Add,
+  Sub,
+  Mul,
+  Div,
+  Lt,
+  Le,
+  Gt,
+  Ge,
+  Eq,
+  Ne,
+  And,
+  Or,
+}
+
+impl Expr {
+  /// The value with the latest `telemetry`, if its fields are known.
+  pub fn eval(&self, telemetry: &Telemetry) -> Option<f32> {
+    self.term.eval(telemetry)
+  }
+
+  /// Whether the expression holds, if its fields are known.
+  pub fn holds(&self, telemetry: &Telemetry) -> Option<bool> {
+    self.eval(telemetry).map(truth)
+  }
+
+  /// Whether the value depends on `field`.
+  pub fn uses(&self, field: Field) -> bool {
+    self.term.uses(field)
+  }
+
+  /// The field it is, if it's nothing but one.
+  pub fn field(&self) -> Option<Field> {
+    match self.term {
+      Term::Field(field) => Some(field),
+      _ => None,
+    }
+  }
+}
+
+impl From<Field> for Expr {
+  fn from(field: Field) -> Self {
+    Self {
+      text: field.name().to_string(),
+      term: Term::Field(field),
+    }
+  }
+}
+
+impl FromStr for Expr {
+  type Err = String;
+
+  fn from_str(text: &str) -> Result<Self, Self::Err> {
+    let mut parser = Parser {
+      text,
+      tokens: tokenize(text)?,
+      next: 0,
+      depth: 0,
+    };
+    let term = parser.or()?;
+    if let Some((at, token)) = parser.tokens.get(parser.next) {
+      return Err(parser.error(*at, &format!("unexpected {}", token)));
+    }
+    Ok(Self {
+      text: text.trim().to_string(),
+      term,
+    })
+  }
+}
+
+impl TryFrom<String> for Expr {
+  type Error = String;
+
+  fn try_from(text: String) -> Result<Self, Self::Error> {
+    text.parse()
+  }
+}
+
+impl fmt::Display for Expr {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    f.write_str(&self.text)
+  }
+}
+
+fn truth(value: f32) -> bool {
+  value != 0.0
+}
+
+fn number(condition: bool) -> f32 {
+  if condition {
+    1.0
+  } else {
+    0.0
+  }
+}
+
+impl Term {
+  fn uses(&self, field: Field) -> bool {
+    match self {
+      Term::Number(_) => false,
+      Term::Field(used) => *used == field,
+      Term::Neg(term) | Term::Not(term) => term.uses(field),
+      Term::Binary(left, _, right) => left.uses(field) || right.uses(field),
+    }
+  }
+
+  fn eval(&self, telemetry: &Telemetry) -> Option<f32> {
+    let value = match self {
+      Term::Number(value) => *value,
+      Term::Field(field) => telemetry.get(*field)?,
+      Term::Neg(term) => -term.eval(telemetry)?,
+      Term::Not(term) => number(!truth(term.eval(telemetry)?)),
+      Term::Binary(left, op, right) => {
+        let (left, right) = (left.eval(telemetry)?, right.eval(telemetry)?);
+        match op {
+          Op::Add => left + right,
+          Op::Sub => left - right,
+          Op::Mul => left * right,
+          Op::Div => left / right,
+          Op::Lt => number(left < right),
+          Op::Le => number(left <= right),
+          Op::Gt => number(left > right),
+          Op::Ge => number(left >= right),
+          Op::Eq => number(left == right),
+          Op::Ne => number(left != right),
+          Op::And => number(truth(left) && truth(right)),
+          Op::Or => number(truth(left) || truth(right)),
+        }
+      }
+    };
+    value.is_finite().then_some(value)
+  }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Token {
+  Number(f32),
+  Name(String),
+  Op(Op),
+  Not,
+  Open,
+  Close,
+}
+
+impl fmt::Display for Token {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      Token::Number(value) => write!(f, "number {}", value),
+      Token::Name(name) => write!(f, "name {:?}", name),
+      Token::Op(op) => write!(f, "operator {:?}", op),
+      Token::Not => f.write_str("\"!\""),
+      Token::Open => f.write_str("\"(\""),
+      Token::Close => f.write_str("\")\""),
+    }
+  }
+}
+
+/// The tokens of `text` with the byte offsets they start at.
+fn tokenize(text: &str) -> Result<Vec<(usize, Token)>, String> {
+  let mut tokens = Vec::new();
+  let mut chars = text.char_indices().peekable();
+  while let Some((at, c)) = chars.next() {
+    let mut next_is = |expected| chars.next_if(|(_, c)| *c == expected).is_some();
+    let token = match c {
+      _ if c.is_whitespace() => continue,
+      '(' => Token::Open,
+      ')' => Token::Close,
+      '+' => Token::Op(Op::Add),
+      '-' => Token::Op(Op::Sub),
+      '*' => Token::Op(Op::Mul),
+      '/' => Token::Op(Op::Div),
+      '<' if next_is('=') => Token::Op(Op::Le),
+      '<' => Token::Op(Op::Lt),
+      '>' if next_is('=') => Token::Op(Op::Ge),
+      '>' => Token::Op(Op::Gt),
+      '=' if next_is('=') => Token::Op(Op::Eq),
+      '!' if next_is('=') => Token::Op(Op::Ne),
+      '!' => Token::Not,
+      '&' if next_is('&') => Token::Op(Op::And),
+      '|' if next_is('|') => Token::Op(Op::Or),
+      _ if c.is_ascii_digit() || c == '.' => {
+        let end = word_end(text, at, |c| c.is_ascii_digit() || c == '.');
+        while chars.next_if(|(i, _)| *i < end).is_some() {}
+        let value = text[at..end]
+          .parse()
+          .map_err(|_| format!("invalid number {:?} in {:?}", &text[at..end], text))?;
+        Token::Number(value)
+      }
+      _ if c.is_ascii_alphabetic() || c == '_' => {
+        let end = word_end(text, at, |c| c.is_ascii_alphanumeric() || c == '_');
+        while chars.next_if(|(i, _)| *i < end).is_some() {}
+        Token::Name(text[at..end].to_string())
+      }
+      _ => return Err(format!("unexpected {:?} at {} in {:?}", c, at, text)),
+    };
+    tokens.push((at, token));
+  }
+  Ok(tokens)
+}
+
+/// Where the run of characters matching `part` from `start` ends.
+fn word_end(text: &str, start: usize, part: impl Fn(char) -> bool) -> usize {
+  text[start..]
+    .find(|c| !part(c))
+    .map_or(text.len(), |end| start + end)
+}
+
+/// How deeply parentheses, negations and nots may nest, so configs can't
+/// overflow the stack.
+const MAX_DEPTH: usize = 64;
+
+/// Recursive descent, one method per level of precedence from the lowest.
+struct Parser<'a> {
+  text: &'a str,
+  tokens: Vec<(usize, Token)>,
+  next: usize,
+  // Of parentheses, negations and nots around the next token
+  depth: usize,
+}
+
+impl Parser<'_> {
+  fn or(&mut self) -> Result<Term, String> {
+    self.binary(Self::and, &[Op::Or])
+  }
+
+  fn and(&mut self) -> Result<Term, String> {
+    self.binary(Self::comparison, &[Op::And])
+  }
+
+  /// Only one, `a < b < c` doesn't mean what it looks like.
+  fn comparison(&mut self) -> Result<Term, String> {
+    let left = self.sum()?;
+    let comparisons = [Op::Lt, Op::Le, Op::Gt, Op::Ge, Op::Eq, Op::Ne];
+    match self.take_op(&comparisons) {
+      Some(op) => Ok(Term::Binary(Box::new(left), op, Box::new(self.sum()?))),
+      None => Ok(left),
+    }
+  }
+
+  fn sum(&mut self) -> Result<Term, String> {
+    self.binary(Self::product, &[Op::Add, Op::Sub])
+  }
+
+  fn product(&mut self) -> Result<Term, String> {
+    self.binary(Self::unary, &[Op::Mul, Op::Div])
+  }
+
+  fn unary(&mut self) -> Result<Term, String> {
+    let at = self.tokens.get(self.next).map(|(at, _)| *at);
+    if self.take_op(&[Op::Sub]).is_some() {
+      return Ok(Term::Neg(Box::new(self.nested(at, Self::unary)?)));
+    }
+    if self.peek() == Some(&Token::Not) {
+      self.next += 1;
+      return Ok(Term::Not(Box::new(self.nested(at, Self::unary)?)));
+    }
+    self.atom()
+  }
+
+  fn atom(&mut self) -> Result<Term, String> {
+    let Some((at, token)) = self.tokens.get(self.next).cloned() else {
+      return Err(self.error(self.text.len(), "unexpected end"));
+    };
+    self.next += 1;
+    match token {
+      Token::Number(value) => Ok(Term::Number(value)),
+      Token::Name(name) => match Field::ALL.iter().find(|field| field.name() == name) {
+        Some(field) => Ok(Term::Field(*field)),
+        None => Err(self.error(at, &format!("unknown field {:?}", name))),
+      },
+      Token::Open => {
+        let term = self.nested(Some(at), Self::or)?;
+        match self.tokens.get(self.next) {
+          Some((_, Token::Close)) => {
+            self.next += 1;
+            Ok(term)
+          }
+          Some((at, token)) => Err(self.error(*at, &format!("expected \")\", not {}", token))),
+          None => Err(self.error(self.text.len(), "expected \")\"")),
+        }
+      }
+      token => Err(self.error(at, &format!("unexpected {}", token))),
+    }
+  }
+
+  /// `parse` one level deeper, for the token at `at`.
+  fn nested(
+    &mut self,
+    at: Option<usize>,
+    parse: fn(&mut Self) -> Result<Term, String>,
+  ) -> Result<Term, String> {
+    if self.depth == MAX_DEPTH {
+      let at = at.unwrap_or(self.text.len());
+      return Err(self.error(at, "nested too deeply"));
+    }
+    self.depth += 1;
+    let term = parse(self);
+    self.depth -= 1;
+    term
+  }
+
+  /// Terms of `operand` joined by any of `ops`, from left to right.
+  fn binary(
+    &mut self,
+    operand: fn(&mut Self) -> Result<Term, String>,
+    ops: &[Op],
+  ) -> Result<Term, String> {
+    let mut term = operand(self)?;
+    while let Some(op) = self.take_op(ops) {
+      term = Term::Binary(Box::new(term), op, Box::new(operand(self)?));
+    }
+    Ok(term)
+  }
+
+  fn peek(&self) -> Option<&Token> {
+    self.tokens.get(self.next).map(|(_, token)| token)
+  }
+
+  fn take_op(&mut self, ops: &[Op]) -> Option<Op> {
+    match self.peek() {
+      Some(Token::Op(op)) if ops.contains(op) => {
+        let op = *op;
+        self.next += 1;
+        Some(op)
+      }
+      _ => None,
+    }
+  }
+
+  fn error(&self, at: usize, message: &str) -> String {
+    format!("{} at {} in {:?}", message, at, self.text)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn eval(text: &str, telemetry: &Telemetry) -> Option<f32> {
+    text.parse::<Expr>().unwrap().eval(telemetry)
+  }
+
+  #[test]
+  fn evaluates_with_precedence() {
+    let telemetry = Telemetry {
+      speed: Some(100.0),
+      rpm: Some(7000.0),
+      throttle: Some(50.0),
+      ..Default::default()
+    };
+    assert_eq!(eval("speed * 0.5 + 2", &telemetry), Some(52.0));
+    assert_eq!(eval("-(speed - 40) / 2", &telemetry), Some(-30.0));
+    assert_eq!(eval("rpm > 6500", &telemetry), Some(1.0));
+    assert_eq!(
+      eval("rpm>6500 && throttle >= 90 || !speed", &telemetry),
+      Some(0.0)
+    );
+    assert_eq!(eval("speed == 100 && 2 - 1 != 0", &telemetry), Some(1.0));
+    // Unknown until a source reports it
+    assert_eq!(eval("fuel_level < 10", &telemetry), None);
+    assert_eq!(eval("speed / (rpm - 7000)", &telemetry), None);
+    assert_eq!("speed".parse::<Expr>().unwrap().field(), Some(Field::Speed));
+    let expr: Expr = "-(speed - rpm)".parse().unwrap();
+    assert!(expr.uses(Field::Rpm) && !expr.uses(Field::Throttle));
+  }
+
+  #[test]
+  fn rejects_what_it_cant_parse() {
+    for (text, error) in [
+      ("speed *", "unexpected end at 7 in \"speed *\""),
+      ("spede > 5", "unknown field \"spede\" at 0 in \"spede > 5\""),
+      ("(rpm > 5", "expected \")\" at 8 in \"(rpm > 5\""),
+      (
+        "1 < rpm < 5",
+        "unexpected operator Lt at 8 in \"1 < rpm < 5\"",
+      ),
+      ("rpm & 1", "unexpected '&' at 4 in \"rpm & 1\""),
+    ] {
+      assert_eq!(text.parse::<Expr>().unwrap_err(), error);
+    }
+  }
+
+  #[test]
+  fn rejects_nesting_too_deep() {
+    let nested = |open: &str, depth| format!("{}1{}", open.repeat(depth), ")".repeat(depth));
+    assert!(nested("(", MAX_DEPTH).parse::<Expr>().is_ok());
+    for text in [
+      nested("(", 5000),
+      "-".repeat(5000) + "1",
+      "!-".repeat(2500) + "1",
+    ] {
+      let error = text.parse::<Expr>().unwrap_err();
+      assert!(error.starts_with("nested too deeply at "), "{}", error);
+    }
+  }
+}
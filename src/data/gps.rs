@@ -0,0 +1,157 @@
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::TcpStream;
+use std::path::PathBuf;
+use std::thread;
+use std::time::Duration;
+
+use async_trait::async_trait;
+use tokio::sync::mpsc::{self, UnboundedReceiver, UnboundedSender};
+
+use crate::data::{DataSource, Fix, Telemetry};
+
+/// Default baud rate of NMEA 0183 receivers.
+pub const DEFAULT_BAUD_RATE: u32 = 9600;
+/// Where gpsd listens by default.
+pub const GPSD_ADDRESS: &str = "127.0.0.1:2947";
+
+/// How long to wait before reconnecting after the receiver went away.
+const RECONNECT_DELAY: Duration = Duration::from_secs(5);
+
+const KM_PER_NAUTICAL_MILE: f32 = 1.852;
+
+/// Where NMEA sentences are read from.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum GpsDevice {
+  /// A receiver on a serial port, like `/dev/ttyACM0`.
+  Serial { device: PathBuf, baud_rate: u32 },
+  /// A gpsd daemon, asked to pass on the raw sentences of its receivers.
+  Gpsd { address: String },
+}
+
+/// Position, ground speed, heading, altitude and fix quality read from
+/// NMEA 0183 sentences on a background thread, which keeps reconnecting if
+/// the receiver can't be reached.
+pub struct GpsSource {
+  receiver: UnboundedReceiver<Telemetry>,
+}
+
+impl GpsSource {
+  pub fn new(device: GpsDevice) -> Self {
+    let (sender, receiver) = mpsc::unbounded_channel();
+    let result = thread::Builder::new()
+      .name("gps".to_string())
+      .spawn(move || {
+        while !sender.is_closed() {
+          if let Err(err) = read(&device, &sender) {
+            tracing::warn!("GPS receiver {:?}: {}", device, err);
+          }
+          thread::sleep(RECONNECT_DELAY);
+        }
+      });
+    if let Err(err) = result {
+      tracing::error!("unable to start GPS thread: {}", err);
+    }
+    Self { receiver }
+  }
+}
+
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+impl DataSource for GpsSource {
+  async fn poll(&mut self) -> Telemetry {
+    match self.receiver.recv().await {
+      Some(telemetry) => telemetry,
+      // The thread is gone, there's nothing more to come
+      None => std::future::pending().await,
+    }
+  }
+
+  fn name(&self) -> &str {
+    "gps"
+  }
+}
+
+fn read(device: &GpsDevice, sender: &UnboundedSender<Telemetry>) -> io::Result<()> {
+  let reader: Box<dyn Read> = match device {
+    GpsDevice::Serial { device, baud_rate } => Box::new(
+      serialport::new(device.to_string_lossy(), *baud_rate)
+        .timeout(Duration::from_secs(5))
+        .open()?,
+    ),
+    GpsDevice::Gpsd { address } => {
+      let mut stream = TcpStream::connect(address)?;
+      stream.write_all(b"?WATCH={\"enable\":true,\"nmea\":true};\n")?;
+      Box::new(stream)
+    }
+  };
+  tracing::info!("connected to GPS receiver {:?}", device);
+
+  for line in BufReader::new(reader).lines() {
+    // gpsd mixes its own JSON reports in, which aren't sentences
+    if let Some(telemetry) = parse(line?.trim()) {
+      if sender.send(telemetry).is_err() {
+        return Ok(());
+      }
+    }
+  }
+  Err(io::ErrorKind::UnexpectedEof.into())
+}
+
+/// Decodes the values of an RMC, GGA or VTG sentence from any talker.
+fn parse(sentence: &str) -> Option<Telemetry> {
+  let body = checked(sentence)?;
+  let fields: Vec<&str> = body.split(',').collect();
+  let number = |i: usize| fields.get(i).and_then(|field| field.parse::<f32>().ok());
+
+  let mut telemetry = Telemetry::default();
+  match fields[0].get(2..)? {
+    "RMC" => {
+      // Void positions carry stale values
+      if fields.get(2) != Some(&"A") {
+        telemetry.fix = Some(Fix::None);
+        return Some(telemetry);
+      }
+      telemetry.latitude = coordinate(fields.get(3)?, fields.get(4)?, "S");
+      telemetry.longitude = coordinate(fields.get(5)?, fields.get(6)?, "W");
+      telemetry.ground_speed = number(7).map(|knots| knots * KM_PER_NAUTICAL_MILE);
+      telemetry.heading = number(8);
+    }
+    "GGA" => {
+      telemetry.fix = Some(match *fields.get(6)? {
+        "1" => Fix::Gps,
+        "2" | "3" => Fix::Differential,
+        "4" | "5" => Fix::Rtk,
+        "6" => Fix::Estimated,
+        _ => Fix::None,
+      });
+      telemetry.altitude = number(9);
+    }
+    "VTG" => {
+      telemetry.heading = number(1);
+      telemetry.ground_speed = number(7);
+    }
+    _ => return None,
+  }
+  Some(telemetry)
+}
+
+/// Degrees from NMEA's `dddmm.mmmm` and a hemisphere, negative in the
+/// `negative` one.
+fn coordinate(value: &str, hemisphere: &str, negative: &str) -> Option<f32> {
+  let value: f32 = value.parse().ok()?;
+  let degrees = (value / 100.0).trunc() + value % 100.0 / 60.0;
+  Some(if hemisphere == negative {
+    -degrees
+  } else {
+    degrees
+  })
+}
+
+/// The part of `$<body>*<checksum>` between the markers, if the checksum
+/// matches.
+fn checked(sentence: &str) -> Option<&str> {
+  let (body, checksum) = sentence.strip_prefix('$')?.split_once('*')?;
+  let expected = u8::from_str_radix(checksum.get(..2)?, 16).ok()?;
+  let actual = body.bytes().fold(0, |sum, byte| sum ^ byte);
+  (actual == expected).then_some(body)
+}
@@ -0,0 +1,107 @@
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use async_trait::async_trait;
+use serde::Deserialize;
+
+use crate::data::{DataSource, Telemetry};
+
+/// How often the sensor is read, fast enough for a smooth G meter.
+const INTERVAL: Duration = Duration::from_millis(20);
+/// m/s² in one g.
+const STANDARD_GRAVITY: f32 = 9.806_65;
+
+/// An axis of the sensor and which way along it counts as positive.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Axis {
+  X,
+  Y,
+  Z,
+  NegativeX,
+  NegativeY,
+  NegativeZ,
+}
+
+impl Axis {
+  fn name(self) -> &'static str {
+    match self {
+      Axis::X | Axis::NegativeX => "x",
+      Axis::Y | Axis::NegativeY => "y",
+      Axis::Z | Axis::NegativeZ => "z",
+    }
+  }
+
+  fn sign(self) -> f32 {
+    match self {
+      Axis::X | Axis::Y | Axis::Z => 1.0,
+      _ => -1.0,
+    }
+  }
+}
+
+/// Lateral and longitudinal acceleration from a Linux IIO accelerometer,
+/// e.g. `/sys/bus/iio/devices/iio:device1`.
+///
+/// The sensor is expected to be mounted level, by default with its x axis
+/// pointing to the right and its y axis to the front, see
+/// [`with_axes`](Self::with_axes).
+pub struct Accelerometer {
+  device: PathBuf,
+  lateral: Axis,
+  longitudinal: Axis,
+}
+
+impl Accelerometer {
+  pub fn new(device: impl Into<PathBuf>) -> Self {
+    Self {
+      device: device.into(),
+      lateral: Axis::X,
+      longitudinal: Axis::Y,
+    }
+  }
+
+  /// The axes pointing to the right and to the front of the vehicle.
+  pub fn with_axes(mut self, lateral: Axis, longitudinal: Axis) -> Self {
+    self.lateral = lateral;
+    self.longitudinal = longitudinal;
+    self
+  }
+
+  /// g along `axis`, from the scaled raw value. The scale of the axis is
+  /// used if the driver has one and the shared one otherwise.
+  fn read(&self, axis: Axis) -> Option<f32> {
+    let name = axis.name();
+    let raw = read_number(&self.device.join(format!("in_accel_{}_raw", name)))?;
+    let scale = read_number(&self.device.join(format!("in_accel_{}_scale", name)))
+      .or_else(|| read_number(&self.device.join("in_accel_scale")))
+      .unwrap_or(1.0);
+    Some(raw * scale * axis.sign() / STANDARD_GRAVITY)
+  }
+}
+
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+impl DataSource for Accelerometer {
+  async fn poll(&mut self) -> Telemetry {
+    super::sleep(INTERVAL).await;
+    let lateral_g = self.read(self.lateral);
+    let longitudinal_g = self.read(self.longitudinal);
+    if lateral_g.is_none() || longitudinal_g.is_none() {
+      tracing::debug!("unable to read accelerometer {}", self.device.display());
+    }
+    Telemetry {
+      lateral_g,
+      longitudinal_g,
+      ..Default::default()
+    }
+  }
+
+  fn name(&self) -> &str {
+    "imu"
+  }
+}
+
+fn read_number(path: &Path) -> Option<f32> {
+  std::fs::read_to_string(path).ok()?.trim().parse().ok()
+}
@@ -0,0 +1,58 @@
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use async_trait::async_trait;
+
+use crate::data::{DataSource, Telemetry};
+
+/// How often the sensor is read.
+const INTERVAL: Duration = Duration::from_secs(1);
+
+/// Ambient light from a Linux IIO light sensor, e.g.
+/// `/sys/bus/iio/devices/iio:device0`.
+pub struct LightSensor {
+  device: PathBuf,
+}
+
+impl LightSensor {
+  pub fn new(device: impl Into<PathBuf>) -> Self {
+    Self {
+      device: device.into(),
+    }
+  }
+
+  /// Lux, from the processed value if the driver has one and from the
+  /// scaled raw value otherwise.
+  fn read(&self) -> Option<f32> {
+    if let Some(lux) = read_number(&self.device.join("in_illuminance_input")) {
+      return Some(lux);
+    }
+    let raw = read_number(&self.device.join("in_illuminance_raw"))?;
+    let scale = read_number(&self.device.join("in_illuminance_scale")).unwrap_or(1.0);
+    Some(raw * scale)
+  }
+}
+
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+impl DataSource for LightSensor {
+  async fn poll(&mut self) -> Telemetry {
+    super::sleep(INTERVAL).await;
+    let illuminance = self.read();
+    if illuminance.is_none() {
+      tracing::debug!("unable to read light sensor {}", self.device.display());
+    }
+    Telemetry {
+      illuminance,
+      ..Default::default()
+    }
+  }
+
+  fn name(&self) -> &str {
+    "light"
+  }
+}
+
+fn read_number(path: &Path) -> Option<f32> {
+  std::fs::read_to_string(path).ok()?.trim().parse().ok()
+}
@@ -0,0 +1,376 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+use crate::map::geocode::SearchResults;
+use crate::map::route::Route;
+use crate::map::traffic::Traffic;
+use crate::safety::Telltales;
+
+use self::cruise::Cruise;
+use self::expr::Expr;
+use self::vehicle::{ParkingSensors, VehicleStatus};
+use self::weather::Weather;
+
+pub mod arbiter;
+pub mod cruise;
+pub mod expr;
+pub mod gps;
+pub mod imu;
+pub mod light;
+pub mod obd;
+pub mod process;
+mod source;
+pub mod vehicle;
+pub mod weather;
+
+pub(crate) use self::source::Report;
+pub use self::source::{sleep, DataSource, MockSource, Registry};
+
+/// Default of [`Settings::stale_after`](crate::Settings::stale_after), a
+/// few times the interval of the slowest sources.
+pub const DEFAULT_STALE_AFTER: Duration = Duration::from_secs(3);
+
+/// The latest known vehicle values, `None` until a source has reported
+/// them.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Telemetry {
+  /// km/h
+  pub speed: Option<f32>,
+  /// Engine revolutions per minute.
+  pub rpm: Option<f32>,
+  /// °C
+  pub coolant_temp: Option<f32>,
+  /// Throttle position in percent.
+  pub throttle: Option<f32>,
+  /// Fuel tank level in percent.
+  pub fuel_level: Option<f32>,
+  /// Traction battery charge in percent.
+  pub state_of_charge: Option<f32>,
+  /// kW leaving the traction battery, negative while it is charged by
+  /// regenerative braking or a charger.
+  pub battery_power: Option<f32>,
+  /// km left on the battery's charge.
+  pub range: Option<f32>,
+  /// Speed over ground from GPS in km/h, kept apart from the vehicle's own
+  /// `speed` since the two rarely agree exactly.
+  pub ground_speed: Option<f32>,
+  /// Sideways acceleration in g, positive to the right.
+  pub lateral_g: Option<f32>,
+  /// Acceleration along the direction of travel in g, negative while
+  /// braking.
+  pub longitudinal_g: Option<f32>,
+  /// Direction of travel in degrees clockwise from true north.
+  pub heading: Option<f32>,
+  /// Meters above mean sea level.
+  pub altitude: Option<f32>,
+  /// Degrees, positive north of the equator.
+  pub latitude: Option<f32>,
+  /// Degrees, positive east of Greenwich.
+  pub longitude: Option<f32>,
+  pub fix: Option<Fix>,
+  /// Ambient light in lux.
+  pub illuminance: Option<f32>,
+  /// Whether the ignition is on, turning the display off and on as it
+  /// changes, see [`Power`](crate::power::Power) and
+  /// [`Settings::ignition_off_delay`](crate::Settings::ignition_off_delay).
+  pub ignition: Option<bool>,
+  /// Warning lamps shown by the safety layer.
+  pub telltales: Telltales,
+  /// Shown by [`FollowingGap`](crate::widgets::FollowingGap).
+  pub cruise: Cruise,
+  /// Shown by [`VehicleSchematic`](crate::widgets::VehicleSchematic).
+  pub vehicle: VehicleStatus,
+  /// Shown by [`ParkingAssist`](crate::widgets::ParkingAssist). Unlike the
+  /// other values, sensors are replaced all at once since seeing nothing
+  /// is news too.
+  pub parking: Option<ParkingSensors>,
+  /// Shown by [`WeatherPanel`](crate::widgets::WeatherPanel), see
+  /// [`WeatherSource`](weather::WeatherSource).
+  pub weather: Option<Weather>,
+  /// Where to drive, shown by [`MapView`](crate::widgets::MapView) and
+  /// [`NavigationArrow`](crate::widgets::NavigationArrow), see
+  /// [`RouteSource`](crate::map::route::RouteSource). Shared since it's
+  /// copied along with every update.
+  pub route: Option<Arc<Route>>,
+  /// Places found for the latest query of a
+  /// [`SearchSource`](crate::map::geocode::SearchSource), shown by
+  /// [`SearchScreen`](crate::widgets::SearchScreen).
+  pub search: Option<Arc<SearchResults>>,
+  /// Drawn above the roads by [`MapView`](crate::widgets::MapView), see
+  /// [`TrafficSource`](crate::map::traffic::TrafficSource).
+  pub traffic: Option<Arc<Traffic>>,
+}
+
+/// Quality of a GPS position.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Fix {
+  /// No position, the other GPS values are stale.
+  None,
+  Gps,
+  /// Corrected by a ground or satellite based augmentation system.
+  Differential,
+  /// Real time kinematic, centimeter accuracy.
+  Rtk,
+  /// Dead reckoning.
+  Estimated,
+}
+
+impl Telemetry {
+  /// Overwrites every value `update` has.
+  pub fn merge(&mut self, update: &Telemetry) {
+    let Telemetry {
+      speed,
+      rpm,
+      coolant_temp,
+      throttle,
+      fuel_level,
+      state_of_charge,
+      battery_power,
+      range,
+      ground_speed,
+      lateral_g,
+      longitudinal_g,
+      heading,
+      altitude,
+      latitude,
+      longitude,
+      fix,
+      illuminance,
+      ignition,
+      telltales,
+      cruise,
+      vehicle,
+      parking,
+      weather,
+      route,
+      search,
+      traffic,
+    } = update;
+    for (value, update) in [
+      (&mut self.speed, speed),
+      (&mut self.rpm, rpm),
+      (&mut self.coolant_temp, coolant_temp),
+      (&mut self.throttle, throttle),
+      (&mut self.fuel_level, fuel_level),
+      (&mut self.state_of_charge, state_of_charge),
+      (&mut self.battery_power, battery_power),
+      (&mut self.range, range),
+      (&mut self.ground_speed, ground_speed),
+      (&mut self.lateral_g, lateral_g),
+      (&mut self.longitudinal_g, longitudinal_g),
+      (&mut self.heading, heading),
+      (&mut self.altitude, altitude),
+      (&mut self.latitude, latitude),
+      (&mut self.longitude, longitude),
+      (&mut self.illuminance, illuminance),
+    ] {
+      if update.is_some() {
+        *value = *update;
+      }
+    }
+    if parking.is_some() {
+      self.parking = *parking;
+    }
+    if fix.is_some() {
+      self.fix = *fix;
+    }
+    if ignition.is_some() {
+      self.ignition = *ignition;
+    }
+    self.telltales.merge(telltales);
+    self.cruise.merge(cruise);
+    self.vehicle.merge(vehicle);
+    if weather.is_some() {
+      self.weather.clone_from(weather);
+    }
+    if route.is_some() {
+      self.route.clone_from(route);
+    }
+    if search.is_some() {
+      self.search.clone_from(search);
+    }
+    if traffic.is_some() {
+      self.traffic.clone_from(traffic);
+    }
+  }
+
+  pub fn get(&self, field: Field) -> Option<f32> {
+    match field {
+      Field::Speed => self.speed,
+      Field::Rpm => self.rpm,
+      Field::CoolantTemp => self.coolant_temp,
+      Field::Throttle => self.throttle,
+      Field::FuelLevel => self.fuel_level,
+      Field::StateOfCharge => self.state_of_charge,
+      Field::BatteryPower => self.battery_power,
+      Field::Range => self.range,
+      Field::GroundSpeed => self.ground_speed,
+      Field::LateralG => self.lateral_g,
+      Field::LongitudinalG => self.longitudinal_g,
+      Field::Heading => self.heading,
+      Field::Altitude => self.altitude,
+    }
+  }
+  pub fn get_mut(&mut self, field: Field) -> &mut Option<f32> {
+    match field {
+      Field::Speed => &mut self.speed,
+      Field::Rpm => &mut self.rpm,
+      Field::CoolantTemp => &mut self.coolant_temp,
+      Field::Throttle => &mut self.throttle,
+      Field::FuelLevel => &mut self.fuel_level,
+      Field::StateOfCharge => &mut self.state_of_charge,
+      Field::BatteryPower => &mut self.battery_power,
+      Field::Range => &mut self.range,
+      Field::GroundSpeed => &mut self.ground_speed,
+      Field::LateralG => &mut self.lateral_g,
+      Field::LongitudinalG => &mut self.longitudinal_g,
+      Field::Heading => &mut self.heading,
+      Field::Altitude => &mut self.altitude,
+    }
+  }
+}
+
+/// One of the values in [`Telemetry`].
+// By name rather than as an enum, so it works for the keys of TOML tables
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Deserialize)]
+#[serde(try_from = "String")]
+pub enum Field {
+  Speed,
+  Rpm,
+  CoolantTemp,
+  Throttle,
+  FuelLevel,
+  StateOfCharge,
+  BatteryPower,
+  Range,
+  GroundSpeed,
+  LateralG,
+  LongitudinalG,
+  Heading,
+  Altitude,
+}
+
+impl Field {
+  pub const ALL: [Field; 13] = [
+    Field::Speed,
+    Field::Rpm,
+    Field::CoolantTemp,
+    Field::Throttle,
+    Field::FuelLevel,
+    Field::StateOfCharge,
+    Field::BatteryPower,
+    Field::Range,
+    Field::GroundSpeed,
+    Field::LateralG,
+    Field::LongitudinalG,
+    Field::Heading,
+    Field::Altitude,
+  ];
+
+  /// As written in configs and [expressions](expr).
+  pub fn name(self) -> &'static str {
+    match self {
+      Field::Speed => "speed",
+      Field::Rpm => "rpm",
+      Field::CoolantTemp => "coolant_temp",
+      Field::Throttle => "throttle",
+      Field::FuelLevel => "fuel_level",
+      Field::StateOfCharge => "state_of_charge",
+      Field::BatteryPower => "battery_power",
+      Field::Range => "range",
+      Field::GroundSpeed => "ground_speed",
+      Field::LateralG => "lateral_g",
+      Field::LongitudinalG => "longitudinal_g",
+      Field::Heading => "heading",
+      Field::Altitude => "altitude",
+    }
+  }
+}
+
+impl TryFrom<String> for Field {
+  type Error = String;
+
+  fn try_from(name: String) -> Result<Self, Self::Error> {
+    Field::ALL
+      .into_iter()
+      .find(|field| field.name() == name)
+      .ok_or_else(|| format!("unknown field {:?}", name))
+  }
+}
+
+/// Drives a widget's value from telemetry, mapping `min..=max` onto the
+/// widget's normalized range.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ValueSource {
+  /// A field, or an [expression](expr) of them.
+  pub expr: Expr,
+  pub min: f32,
+  pub max: f32,
+}
+
+impl ValueSource {
+  pub fn new(expr: impl Into<Expr>, min: f32, max: f32) -> Self {
+    Self {
+      expr: expr.into(),
+      min,
+      max,
+    }
+  }
+
+  /// The normalized value, if the fields it needs are known.
+  pub fn value(&self, telemetry: &Telemetry) -> Option<f32> {
+    let value = self.expr.eval(telemetry)?;
+    Some(((value - self.min) / (self.max - self.min)).clamp(0.0, 1.0))
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::safety::Telltale;
+
+  #[test]
+  fn merge_keeps_what_the_update_doesnt_have() {
+    let mut telemetry = Telemetry {
+      speed: Some(50.0),
+      rpm: Some(2000.0),
+      fix: Some(Fix::Rtk),
+      ..Default::default()
+    };
+    telemetry.merge(&Telemetry {
+      rpm: Some(2500.0),
+      latitude: Some(52.5),
+      ..Default::default()
+    });
+    assert_eq!(
+      telemetry,
+      Telemetry {
+        speed: Some(50.0),
+        rpm: Some(2500.0),
+        latitude: Some(52.5),
+        fix: Some(Fix::Rtk),
+        ..Default::default()
+      }
+    );
+    assert_eq!(telemetry.get(Field::Rpm), Some(2500.0));
+    assert_eq!(telemetry.get(Field::Throttle), None);
+  }
+
+  #[test]
+  fn merge_only_changes_telltales_the_update_knows() {
+    let mut telemetry = Telemetry::default();
+    telemetry.telltales.set(Telltale::Oil, true);
+    telemetry.telltales.set(Telltale::Brake, true);
+    let mut update = Telemetry::default();
+    update.telltales.set(Telltale::Brake, false);
+    update.telltales.set(Telltale::Coolant, true);
+    telemetry.merge(&update);
+    let lit = Telltale::ALL.map(|telltale| telemetry.telltales.is_lit(telltale));
+    // Brake, check engine, coolant, oil, battery
+    assert_eq!(lit, [false, false, true, true, false]);
+  }
+}
@@ -0,0 +1,160 @@
+use std::io;
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::Duration;
+
+use async_trait::async_trait;
+use serialport::SerialPort;
+use tokio::sync::mpsc::{self, UnboundedReceiver, UnboundedSender};
+
+use crate::data::{DataSource, Telemetry};
+use crate::safety::Telltale;
+
+/// Default baud rate of USB ELM327 adapters.
+pub const DEFAULT_BAUD_RATE: u32 = 38400;
+
+/// How long to wait before reconnecting after the adapter went away.
+const RECONNECT_DELAY: Duration = Duration::from_secs(5);
+
+/// Commands sent after connecting: reset, no echo, no line feeds, no
+/// spaces, no headers, automatic protocol.
+const INIT: [&str; 6] = ["ATZ", "ATE0", "ATL0", "ATS0", "ATH0", "ATSP0"];
+
+/// Mode 01 PIDs polled in turn.
+const PIDS: [u8; 7] = [
+  0x01, // monitor status, with the check engine light
+  0x0D, // vehicle speed
+  0x0C, // engine rpm
+  0x05, // coolant temperature
+  0x11, // throttle position
+  0x2F, // fuel tank level
+  0x5B, // hybrid battery pack remaining life
+];
+
+/// Vehicle speed, engine rpm, coolant temperature, throttle position, fuel
+/// level, the hybrid battery's charge and the check engine light read from
+/// an ELM327 OBD-II adapter, see [`spawn`].
+pub struct ObdSource {
+  receiver: UnboundedReceiver<Telemetry>,
+}
+
+impl ObdSource {
+  pub fn new(device: PathBuf, baud_rate: u32) -> Self {
+    Self {
+      receiver: spawn(device, baud_rate),
+    }
+  }
+}
+
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+impl DataSource for ObdSource {
+  async fn poll(&mut self) -> Telemetry {
+    match self.receiver.recv().await {
+      Some(telemetry) => telemetry,
+      // The thread is gone, there's nothing more to come
+      None => std::future::pending().await,
+    }
+  }
+
+  fn name(&self) -> &str {
+    "obd"
+  }
+}
+
+/// Polls an ELM327 OBD-II adapter on a background thread and sends every
+/// value read.
+///
+/// `device` is a serial port, like `/dev/ttyUSB0` for USB adapters or
+/// `/dev/rfcomm0` for Bluetooth adapters bound with `rfcomm bind`. The
+/// thread keeps reconnecting if the adapter can't be reached, and stops
+/// once the receiver is dropped.
+pub fn spawn(device: PathBuf, baud_rate: u32) -> UnboundedReceiver<Telemetry> {
+  let (sender, receiver) = mpsc::unbounded_channel();
+  let result = thread::Builder::new()
+    .name("obd".to_string())
+    .spawn(move || {
+      while !sender.is_closed() {
+        if let Err(err) = poll(&device, baud_rate, &sender) {
+          tracing::warn!("OBD adapter {}: {}", device.display(), err);
+        }
+        thread::sleep(RECONNECT_DELAY);
+      }
+    });
+  if let Err(err) = result {
+    tracing::error!("unable to start OBD thread: {}", err);
+  }
+  receiver
+}
+
+fn poll(device: &Path, baud_rate: u32, sender: &UnboundedSender<Telemetry>) -> io::Result<()> {
+  let mut port = serialport::new(device.to_string_lossy(), baud_rate)
+    .timeout(Duration::from_secs(2))
+    .open()?;
+  for command in INIT {
+    query(port.as_mut(), command)?;
+  }
+  tracing::info!("connected to OBD adapter {}", device.display());
+
+  loop {
+    for pid in PIDS {
+      let response = query(port.as_mut(), &format!("01{:02X}", pid))?;
+      let Some(telemetry) = parse(pid, &response) else {
+        // Not every vehicle supports every PID
+        tracing::trace!("no value for PID {:02X}: {}", pid, response);
+        continue;
+      };
+      if sender.send(telemetry).is_err() {
+        return Ok(());
+      }
+    }
+  }
+}
+
+/// Sends a command and reads the response up to the adapter's prompt.
+fn query(port: &mut dyn SerialPort, command: &str) -> io::Result<String> {
+  port.write_all(command.as_bytes())?;
+  port.write_all(b"\r")?;
+
+  let mut response = Vec::new();
+  let mut byte = [0];
+  loop {
+    port.read_exact(&mut byte)?;
+    if byte[0] == b'>' {
+      break;
+    }
+    response.push(byte[0]);
+  }
+  Ok(String::from_utf8_lossy(&response).into_owned())
+}
+
+/// Decodes the data bytes of a mode 01 response, ignoring status lines
+/// like `SEARCHING...`.
+fn parse(pid: u8, response: &str) -> Option<Telemetry> {
+  let prefix = format!("41{:02X}", pid);
+  let line = response
+    .split(['\r', '\n'])
+    .map(|line| line.trim().replace(' ', ""))
+    .find(|line| line.starts_with(&prefix))?;
+  let data = &line[prefix.len()..];
+  let byte = |i: usize| {
+    let hex = data.get(i * 2..i * 2 + 2)?;
+    u8::from_str_radix(hex, 16).ok().map(f32::from)
+  };
+
+  let mut telemetry = Telemetry::default();
+  match pid {
+    // The lamp is the top bit of the first byte
+    0x01 => telemetry
+      .telltales
+      .set(Telltale::CheckEngine, byte(0)? >= 128.0),
+    0x0D => telemetry.speed = Some(byte(0)?),
+    0x0C => telemetry.rpm = Some((byte(0)? * 256.0 + byte(1)?) / 4.0),
+    0x05 => telemetry.coolant_temp = Some(byte(0)? - 40.0),
+    0x11 => telemetry.throttle = Some(byte(0)? * 100.0 / 255.0),
+    0x2F => telemetry.fuel_level = Some(byte(0)? * 100.0 / 255.0),
+    0x5B => telemetry.state_of_charge = Some(byte(0)? * 100.0 / 255.0),
+    _ => return None,
+  }
+  Some(telemetry)
+}
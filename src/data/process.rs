@@ -0,0 +1,293 @@
+//! Cleaning up telemetry before widgets see it, per field, see
+//! [`Settings::signals`](crate::Settings::signals).
+//!
+//! Every value a data source reports for a field goes through the
+//! processors configured for it in order, like a noisy coolant sensor
+//! through a moving average and a clamp:
+//!
+//! ```toml
+//! [signals]
+//! coolant_temp = [
+//!   { type = "moving_average", samples = 8 },
+//!   { type = "clamp", min = -40, max = 150 },
+//! ]
+//! throttle = [{ type = "map_range", from = [0.4, 4.6], to = [0, 100] }]
+//! ```
+
+use std::collections::{HashMap, VecDeque};
+use std::time::Duration;
+
+use serde::Deserialize;
+
+use super::{Field, Telemetry};
+use crate::error::WindshieldError;
+
+/// One step values of a field go through.
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case", deny_unknown_fields)]
+pub enum Processor {
+  /// Mean of the last `samples` values.
+  MovingAverage { samples: usize },
+  /// Estimate of a value that changes slowly, trusting new values less
+  /// the noisier they are compared to how much the value itself drifts
+  /// between them. Follows trends with less lag than a moving average of
+  /// the same smoothness.
+  Kalman {
+    /// Variance the value drifts by from one sample to the next.
+    process_noise: f32,
+    /// Variance of the sensor's noise.
+    measurement_noise: f32,
+  },
+  /// Within `min` and `max`, which may not be the wrong way around.
+  Clamp { min: f32, max: f32 },
+  /// Linearly from one range to another, like a sensor's voltage to
+  /// percent. Values outside `from` end up outside `to`, and `from` may
+  /// not be empty.
+  MapRange { from: [f32; 2], to: [f32; 2] },
+  /// Change per second, rather than the value, like acceleration from
+  /// speed. Has no value until the second sample.
+  RateOfChange,
+}
+
+impl Processor {
+  /// Why values can't go through it, if they can't, rather than panicking
+  /// or turning them into infinities.
+  fn check(&self) -> Result<(), String> {
+    match *self {
+      Processor::Clamp { min, max } if min.is_nan() || max.is_nan() || min > max => {
+        Err(format!("clamp needs min {} at most max {}", min, max))
+      }
+      Processor::MapRange { from, to } => {
+        if from.iter().chain(&to).any(|bound| !bound.is_finite()) {
+          Err(format!(
+            "map_range needs finite bounds, not {:?} and {:?}",
+            from, to
+          ))
+        } else if from[0] == from[1] {
+          Err(format!("map_range from {:?} is empty", from))
+        } else {
+          Ok(())
+        }
+      }
+      _ => Ok(()),
+    }
+  }
+}
+
+/// A [`Processor`] and what it remembers of earlier values.
+#[derive(Clone, Debug)]
+struct Stage {
+  processor: Processor,
+  /// The values a moving average is over.
+  window: VecDeque<f32>,
+  /// The estimate and its variance of a Kalman filter, or the last value
+  /// and when it came of a rate of change.
+  last: Option<(f32, f32)>,
+}
+
+impl Stage {
+  fn new(processor: Processor) -> Self {
+    Self {
+      processor,
+      window: VecDeque::new(),
+      last: None,
+    }
+  }
+
+  /// The processed `value` that came at `now`, if there is one yet.
+  fn apply(&mut self, value: f32, now: Duration) -> Option<f32> {
+    match self.processor {
+      Processor::MovingAverage { samples } => {
+        self.window.push_back(value);
+        while self.window.len() > samples.max(1) {
+          self.window.pop_front();
+        }
+        Some(self.window.iter().sum::<f32>() / self.window.len() as f32)
+      }
+      Processor::Kalman {
+        process_noise,
+        measurement_noise,
+      } => {
+        let (estimate, variance) = match self.last {
+          Some((estimate, variance)) => {
+            let variance = variance + process_noise;
+            let gain = variance / (variance + measurement_noise);
+            (
+              estimate + gain * (value - estimate),
+              (1.0 - gain) * variance,
+            )
+          }
+          None => (value, measurement_noise),
+        };
+        self.last = Some((estimate, variance));
+        Some(estimate)
+      }
+      Processor::Clamp { min, max } => Some(value.clamp(min, max)),
+      Processor::MapRange {
+        from: [from_start, from_end],
+        to: [to_start, to_end],
+      } => {
+        let t = (value - from_start) / (from_end - from_start);
+        Some(to_start + t * (to_end - to_start))
+      }
+      Processor::RateOfChange => {
+        let now = now.as_secs_f32();
+        let rate = match self.last {
+          // Several values in one frame can't tell how fast it changes
+          Some((_, then)) if then >= now => return None,
+          Some((last, then)) => Some((value - last) / (now - then)),
+          None => None,
+        };
+        self.last = Some((value, now));
+        rate
+      }
+    }
+  }
+}
+
+/// The processors of every field, with their state.
+pub(crate) struct Signals {
+  fields: Vec<(Field, Vec<Stage>)>,
+}
+
+impl Signals {
+  /// Fails with a processor values can't go through, like a clamp with
+  /// `min` above `max`.
+  pub(crate) fn new(processors: &HashMap<Field, Vec<Processor>>) -> Result<Self, WindshieldError> {
+    let fields = processors
+      .iter()
+      .map(|(field, processors)| {
+        let stages = processors
+          .iter()
+          .map(|processor| {
+            processor
+              .check()
+              .map_err(|message| WindshieldError::InvalidProcessor {
+                field: field.name().to_string(),
+                message,
+              })?;
+            Ok(Stage::new(processor.clone()))
+          })
+          .collect::<Result<_, WindshieldError>>()?;
+        Ok((*field, stages))
+      })
+      .collect::<Result<_, WindshieldError>>()?;
+    Ok(Self { fields })
+  }
+
+  /// Processes the values `update` has of fields with processors, leaving
+  /// out those without a value yet.
+  pub(crate) fn process(&mut self, update: &mut Telemetry, now: Duration) {
+    for (field, stages) in &mut self.fields {
+      let value = update.get_mut(*field);
+      if let Some(raw) = *value {
+        *value = stages
+          .iter_mut()
+          .try_fold(raw, |value, stage| stage.apply(value, now));
+      }
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::config::toml_example;
+
+  fn run(processors: Vec<Processor>, values: &[f32]) -> Vec<Option<f32>> {
+    let mut signals = Signals::new(&[(Field::Speed, processors)].into()).unwrap();
+    let step = Duration::from_millis(500);
+    let mut now = Duration::ZERO;
+    values
+      .iter()
+      .map(|value| {
+        now += step;
+        let mut update = Telemetry {
+          speed: Some(*value),
+          rpm: Some(*value),
+          ..Default::default()
+        };
+        signals.process(&mut update, now);
+        // Fields without processors are left alone
+        assert_eq!(update.rpm, Some(*value));
+        update.speed
+      })
+      .collect()
+  }
+
+  #[test]
+  fn processes_in_order() {
+    let average = Processor::MovingAverage { samples: 2 };
+    let clamp = Processor::Clamp {
+      min: 0.0,
+      max: 15.0,
+    };
+    assert_eq!(
+      run(vec![average, clamp], &[10.0, 30.0, 0.0]),
+      [Some(10.0), Some(15.0), Some(15.0)]
+    );
+    let map = Processor::MapRange {
+      from: [0.5, 4.5],
+      to: [0.0, 100.0],
+    };
+    assert_eq!(run(vec![map], &[2.5, 5.5]), [Some(50.0), Some(125.0)]);
+    assert_eq!(
+      run(vec![Processor::RateOfChange], &[10.0, 12.0, 11.0]),
+      [None, Some(4.0), Some(-2.0)]
+    );
+  }
+
+  #[test]
+  fn rejects_processors_values_cant_go_through() {
+    let invalid = [
+      Processor::Clamp { min: 2.0, max: 1.0 },
+      Processor::Clamp {
+        min: f32::NAN,
+        max: 1.0,
+      },
+      Processor::MapRange {
+        from: [1.0, 1.0],
+        to: [0.0, 100.0],
+      },
+      Processor::MapRange {
+        from: [0.0, 1.0],
+        to: [0.0, f32::INFINITY],
+      },
+    ];
+    for processor in invalid {
+      let processors = [(Field::CoolantTemp, vec![processor])].into();
+      assert!(matches!(
+        Signals::new(&processors),
+        Err(WindshieldError::InvalidProcessor { field, .. }) if field == "coolant_temp"
+      ));
+    }
+    let clamp = Processor::Clamp { min: 1.0, max: 1.0 };
+    assert_eq!(run(vec![clamp], &[0.0]), [Some(1.0)]);
+  }
+
+  #[test]
+  fn kalman_settles_between_noisy_values() {
+    let kalman = Processor::Kalman {
+      process_noise: 0.01,
+      measurement_noise: 4.0,
+    };
+    let values = run(vec![kalman], &[90.0, 94.0, 86.0, 94.0, 86.0, 94.0]);
+    let last = values.last().unwrap().unwrap();
+    assert!((last - 90.0).abs() < 2.0, "{}", last);
+  }
+
+  #[test]
+  fn parses_the_module_example() {
+    let example = toml_example(include_str!("process.rs"), "//!");
+    #[derive(Deserialize)]
+    struct Example {
+      signals: HashMap<Field, Vec<Processor>>,
+    }
+    let example: Example = toml::from_str(&example).unwrap();
+    assert_eq!(
+      example.signals[&Field::CoolantTemp][0],
+      Processor::MovingAverage { samples: 8 }
+    );
+    assert_eq!(example.signals[&Field::Throttle].len(), 1);
+  }
+}
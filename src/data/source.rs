@@ -0,0 +1,233 @@
+use std::f32::consts::TAU;
+use std::sync::Arc;
+use std::time::Duration;
+
+use async_trait::async_trait;
+use instant::Instant;
+use tokio::sync::mpsc::{self, UnboundedReceiver, UnboundedSender};
+
+use crate::data::cruise::Cruise;
+use crate::data::vehicle::{Lights, VehicleStatus};
+use crate::data::{Fix, Telemetry};
+use crate::safety::{Telltale, Telltales};
+use crate::wake::Waker;
+
+/// Something that produces telemetry, e.g. an OBD-II adapter or a GPS
+/// receiver.
+///
+/// Sources are polled over and over on a runtime of their own, so they can
+/// wait for data without holding up rendering. That is a tokio runtime on a
+/// background thread natively and the browser's event loop on the web, so
+/// sources meant to run on both wait with [`sleep`] rather than tokio's
+/// timer. Their futures don't have to be `Send` on the web, implement the
+/// trait with `#[async_trait(?Send)]` there.
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+pub trait DataSource: Send {
+  /// Waits for the next values. Fields the source doesn't know stay
+  /// `None` and keep whatever other sources reported.
+  async fn poll(&mut self) -> Telemetry;
+
+  /// What [priorities](crate::Settings::priorities) call the source by,
+  /// the name of its type by default.
+  fn name(&self) -> &str {
+    let name = std::any::type_name::<Self>();
+    name.rsplit("::").next().unwrap_or(name)
+  }
+}
+
+/// Data sources whose values are merged into the telemetry frames see.
+#[derive(Default)]
+pub struct Registry {
+  sources: Vec<Box<dyn DataSource>>,
+}
+
+impl Registry {
+  pub fn new() -> Self {
+    Self::default()
+  }
+
+  pub fn add(&mut self, source: impl DataSource + 'static) {
+    self.sources.push(Box::new(source));
+  }
+
+  pub fn is_empty(&self) -> bool {
+    self.sources.is_empty()
+  }
+
+  /// Polls every source on a background thread, sending what they report
+  /// until the receiver is dropped and waking `waker` for every report.
+  #[cfg(not(target_arch = "wasm32"))]
+  pub(crate) fn start(self, waker: Waker) -> UnboundedReceiver<Report> {
+    let (sender, receiver) = mpsc::unbounded_channel();
+    let result = std::thread::Builder::new()
+      .name("data sources".to_string())
+      .spawn(move || {
+        let runtime = match tokio::runtime::Builder::new_current_thread()
+          .enable_time()
+          .build()
+        {
+          Ok(runtime) => runtime,
+          Err(err) => {
+            tracing::error!("unable to start data source runtime: {}", err);
+            return;
+          }
+        };
+
+        runtime.block_on(async move {
+          let tasks: Vec<_> = self
+            .sources
+            .into_iter()
+            .map(|source| tokio::spawn(forward(source, sender.clone(), waker.clone())))
+            .collect();
+          for task in tasks {
+            let _ = task.await;
+          }
+        });
+      });
+    if let Err(err) = result {
+      tracing::error!("unable to start data source thread: {}", err);
+    }
+    receiver
+  }
+
+  /// Polls every source on the browser's event loop, there are no threads
+  /// to run a runtime on.
+  #[cfg(target_arch = "wasm32")]
+  pub(crate) fn start(self, waker: Waker) -> UnboundedReceiver<Report> {
+    let (sender, receiver) = mpsc::unbounded_channel();
+    for source in self.sources {
+      let waker = waker.clone();
+      wasm_bindgen_futures::spawn_local(forward(source, sender.clone(), waker));
+    }
+    receiver
+  }
+}
+
+/// Values a data source reported, with its [name](DataSource::name).
+pub(crate) type Report = (Arc<str>, Telemetry);
+
+/// Sends what `source` reports until the receiver is dropped, waking
+/// `waker` for it.
+async fn forward(mut source: Box<dyn DataSource>, sender: UnboundedSender<Report>, waker: Waker) {
+  let name: Arc<str> = source.name().into();
+  loop {
+    let telemetry = source.poll().await;
+    if sender.send((name.clone(), telemetry)).is_err() {
+      break;
+    }
+    waker.wake();
+  }
+}
+
+/// Waits for `duration` on the runtime data sources are polled on, see
+/// [`DataSource`].
+#[cfg(not(target_arch = "wasm32"))]
+pub async fn sleep(duration: Duration) {
+  tokio::time::sleep(duration).await;
+}
+
+/// Waits for `duration` on the runtime data sources are polled on, see
+/// [`DataSource`].
+#[cfg(target_arch = "wasm32")]
+pub async fn sleep(duration: Duration) {
+  let millis = duration.as_millis().min(i32::MAX as u128) as i32;
+  let promise = js_sys::Promise::new(&mut |resolve, _| {
+    let window = web_sys::window().expect("running in a window");
+    if window
+      .set_timeout_with_callback_and_timeout_and_arguments_0(&resolve, millis)
+      .is_err()
+    {
+      tracing::warn!("unable to set a timeout");
+    }
+  });
+  let _ = wasm_bindgen_futures::JsFuture::from(promise).await;
+}
+
+/// Made up values sweeping up and down, for developing dashboards without
+/// a vehicle.
+pub struct MockSource {
+  started: Instant,
+  interval: Duration,
+}
+
+impl MockSource {
+  /// Reports new values every `interval`.
+  pub fn new(interval: Duration) -> Self {
+    Self {
+      started: Instant::now(),
+      interval,
+    }
+  }
+}
+
+impl Default for MockSource {
+  fn default() -> Self {
+    Self::new(Duration::from_millis(50))
+  }
+}
+
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+impl DataSource for MockSource {
+  async fn poll(&mut self) -> Telemetry {
+    sleep(self.interval).await;
+    let t = self.started.elapsed().as_secs_f32();
+    // Between 0 and 1 with the given period in seconds
+    let wave = |period: f32| 0.5 - 0.5 * (t / period * TAU).cos();
+    let mut telltales = Telltales::default();
+    // For five seconds every minute
+    telltales.set(Telltale::CheckEngine, t % 60.0 >= 55.0);
+    // Closing in on a vehicle ahead and falling back again
+    let cruise = Cruise {
+      engaged: Some(true),
+      set_speed: Some(130.0),
+      gap: Some(3),
+      lead: Some(true),
+      lead_distance: Some(15.0 + wave(30.0) * 60.0),
+    };
+    let vehicle = VehicleStatus {
+      // The rear left tire slowly losing air
+      tire_pressure: [240.0, 238.0, 240.0 - (t / 2.0) % 60.0, 241.0].map(Some),
+      // The driver's door open for five seconds every half a minute
+      doors: [t % 30.0 >= 25.0, false, false, false].map(Some),
+      trunk: Some(false),
+      hood: Some(false),
+      lights: Some(Lights::Low),
+    };
+    Telemetry {
+      speed: Some(wave(20.0) * 180.0),
+      rpm: Some(800.0 + wave(7.0) * 5200.0),
+      coolant_temp: Some(50.0 + (t / 2.0).min(40.0)),
+      throttle: Some(wave(7.0) * 100.0),
+      fuel_level: Some(100.0 - (t / 10.0) % 100.0),
+      state_of_charge: Some(100.0 - (t / 12.0) % 100.0),
+      // Regenerating while slowing down
+      battery_power: Some((t / 20.0 * TAU).sin() * 120.0),
+      range: Some(4.2 * (100.0 - (t / 12.0) % 100.0)),
+      ground_speed: Some(wave(20.0) * 175.0),
+      // Circling while speeding up and slowing down
+      lateral_g: Some((t / 3.0 * TAU).sin() * 0.8),
+      longitudinal_g: Some((t / 20.0 * TAU).sin() * 0.4),
+      heading: Some(t * 3.0 % 360.0),
+      altitude: Some(120.0 + wave(60.0) * 30.0),
+      latitude: Some(52.52),
+      longitude: Some(13.405),
+      fix: Some(Fix::Gps),
+      illuminance: None,
+      ignition: None,
+      telltales,
+      cruise,
+      vehicle,
+      parking: None,
+      weather: None,
+      route: None,
+      search: None,
+      traffic: None,
+    }
+  }
+
+  fn name(&self) -> &str {
+    "mock"
+  }
+}
@@ -0,0 +1,137 @@
+use serde::{Deserialize, Serialize};
+
+/// A corner of the vehicle, for the values there is one of at every wheel.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Corner {
+  FrontLeft,
+  FrontRight,
+  RearLeft,
+  RearRight,
+}
+
+impl Corner {
+  pub const ALL: [Corner; 4] = [
+    Corner::FrontLeft,
+    Corner::FrontRight,
+    Corner::RearLeft,
+    Corner::RearRight,
+  ];
+
+  /// Index into the per corner arrays of [`VehicleStatus`].
+  pub fn index(self) -> usize {
+    self as usize
+  }
+
+  pub fn is_front(self) -> bool {
+    matches!(self, Corner::FrontLeft | Corner::FrontRight)
+  }
+
+  pub fn is_left(self) -> bool {
+    matches!(self, Corner::FrontLeft | Corner::RearLeft)
+  }
+}
+
+/// Which headlights are on.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Lights {
+  Off,
+  Parking,
+  Low,
+  High,
+}
+
+/// Tires, doors and lights, as far as data sources reported them. The per
+/// corner arrays are in the order of [`Corner::ALL`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct VehicleStatus {
+  /// kPa, from the tire pressure monitoring system.
+  pub tire_pressure: [Option<f32>; 4],
+  /// Whether the door is open.
+  pub doors: [Option<bool>; 4],
+  pub trunk: Option<bool>,
+  pub hood: Option<bool>,
+  pub lights: Option<Lights>,
+}
+
+impl VehicleStatus {
+  pub fn tire_pressure(&self, corner: Corner) -> Option<f32> {
+    self.tire_pressure[corner.index()]
+  }
+
+  pub fn door(&self, corner: Corner) -> Option<bool> {
+    self.doors[corner.index()]
+  }
+
+  /// Overwrites every value `update` has.
+  pub fn merge(&mut self, update: &VehicleStatus) {
+    for (value, update) in self.tire_pressure.iter_mut().zip(update.tire_pressure) {
+      if update.is_some() {
+        *value = update;
+      }
+    }
+    for (value, update) in self.doors.iter_mut().zip(update.doors) {
+      if update.is_some() {
+        *value = update;
+      }
+    }
+    for (value, update) in [
+      (&mut self.trunk, update.trunk),
+      (&mut self.hood, update.hood),
+    ] {
+      if update.is_some() {
+        *value = update;
+      }
+    }
+    if update.lights.is_some() {
+      self.lights = update.lights;
+    }
+  }
+}
+
+/// Ultrasonic parking sensor distances in m, left to right as seen from
+/// above with the front up, none for sensors that see nothing.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ParkingSensors {
+  pub front: [Option<f32>; 4],
+  pub rear: [Option<f32>; 4],
+}
+
+impl ParkingSensors {
+  /// Front sensors first, then the rear ones.
+  pub fn all(&self) -> [Option<f32>; 8] {
+    let mut all = [None; 8];
+    all[..4].copy_from_slice(&self.front);
+    all[4..].copy_from_slice(&self.rear);
+    all
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn merge_keeps_corners_the_update_doesnt_have() {
+    let mut status = VehicleStatus {
+      tire_pressure: [Some(240.0); 4],
+      doors: [Some(false); 4],
+      ..Default::default()
+    };
+    let mut update = VehicleStatus::default();
+    update.tire_pressure[Corner::RearLeft.index()] = Some(180.0);
+    update.doors[Corner::FrontRight.index()] = Some(true);
+    update.lights = Some(Lights::Low);
+    status.merge(&update);
+    assert_eq!(status.tire_pressure(Corner::RearLeft), Some(180.0));
+    assert_eq!(status.tire_pressure(Corner::FrontLeft), Some(240.0));
+    assert_eq!(
+      status.doors,
+      [Some(false), Some(true), Some(false), Some(false)]
+    );
+    assert_eq!(status.lights, Some(Lights::Low));
+  }
+}
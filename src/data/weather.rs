@@ -0,0 +1,271 @@
+use std::time::Duration;
+
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+
+use crate::data::{DataSource, Telemetry};
+
+/// How often weather is fetched unless told otherwise.
+pub const DEFAULT_INTERVAL: Duration = Duration::from_secs(15 * 60);
+
+/// What the sky is doing, drawn as an icon by
+/// [`WeatherPanel`](crate::widgets::WeatherPanel).
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Condition {
+  Clear,
+  PartlyCloudy,
+  Cloudy,
+  Fog,
+  Drizzle,
+  Rain,
+  Snow,
+  Thunderstorm,
+}
+
+impl Condition {
+  /// From a WMO weather interpretation code, which most weather services
+  /// report conditions as.
+  pub fn from_wmo(code: u32) -> Option<Self> {
+    Some(match code {
+      0 => Self::Clear,
+      1 | 2 => Self::PartlyCloudy,
+      3 => Self::Cloudy,
+      45 | 48 => Self::Fog,
+      51..=57 => Self::Drizzle,
+      61..=67 | 80..=82 => Self::Rain,
+      71..=77 | 85 | 86 => Self::Snow,
+      95..=99 => Self::Thunderstorm,
+      _ => return None,
+    })
+  }
+}
+
+/// The weather of a day to come.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Forecast {
+  /// Shown above the day's icon, like `"Mon"`.
+  pub day: String,
+  pub condition: Condition,
+  /// °C
+  pub high: f32,
+  /// °C
+  pub low: f32,
+}
+
+/// The current weather and what's to come.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Weather {
+  pub condition: Condition,
+  /// °C
+  pub temperature: f32,
+  /// The next days, today first.
+  pub forecast: Vec<Forecast>,
+}
+
+/// Where [`WeatherSource`] gets the weather from, e.g. a web service, see
+/// [`OpenMeteo`] with the `weather-http` feature.
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+pub trait WeatherProvider: Send {
+  async fn fetch(&mut self) -> Result<Weather, String>;
+}
+
+/// Reports the weather of a [`WeatherProvider`] as
+/// [`Telemetry::weather`], right away and then every `interval`.
+pub struct WeatherSource<P> {
+  provider: P,
+  interval: Duration,
+  fetched: bool,
+}
+
+impl<P: WeatherProvider> WeatherSource<P> {
+  pub fn new(provider: P, interval: Duration) -> Self {
+    Self {
+      provider,
+      interval,
+      fetched: false,
+    }
+  }
+}
+
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+impl<P: WeatherProvider> DataSource for WeatherSource<P> {
+  async fn poll(&mut self) -> Telemetry {
+    loop {
+      if self.fetched {
+        super::sleep(self.interval).await;
+      }
+      self.fetched = true;
+      match self.provider.fetch().await {
+        Ok(weather) => {
+          return Telemetry {
+            weather: Some(weather),
+            ..Default::default()
+          }
+        }
+        // Keeps showing what it fetched last
+        Err(err) => tracing::warn!("unable to fetch the weather: {}", err),
+      }
+    }
+  }
+}
+
+/// Short name of the weekday of an ISO 8601 date like `"2026-10-14"`.
+pub fn weekday(date: &str) -> Option<&'static str> {
+  let mut parts = date.get(..10)?.split('-').map(|part| part.parse::<i32>());
+  let (Some(Ok(year)), Some(Ok(month)), Some(Ok(day))) = (parts.next(), parts.next(), parts.next())
+  else {
+    return None;
+  };
+  if !(1..=12).contains(&month) {
+    return None;
+  }
+  // Sakamoto's method, zero is Sunday
+  const OFFSETS: [i32; 12] = [0, 3, 2, 5, 0, 3, 5, 1, 4, 6, 2, 4];
+  let year = if month < 3 { year - 1 } else { year };
+  let index = (year + year / 4 - year / 100 + year / 400 + OFFSETS[month as usize - 1] + day) % 7;
+  Some(["Sun", "Mon", "Tue", "Wed", "Thu", "Fri", "Sat"][index.rem_euclid(7) as usize])
+}
+
+/// The free forecast API of [open-meteo.com](https://open-meteo.com) for a
+/// fixed position, needs no key. Not available on the web.
+#[cfg(all(feature = "weather-http", not(target_arch = "wasm32")))]
+pub struct OpenMeteo {
+  pub latitude: f32,
+  pub longitude: f32,
+  /// Days of forecast, today included.
+  pub days: usize,
+}
+
+#[cfg(all(feature = "weather-http", not(target_arch = "wasm32")))]
+impl OpenMeteo {
+  pub fn new(latitude: f32, longitude: f32) -> Self {
+    Self {
+      latitude,
+      longitude,
+      days: 4,
+    }
+  }
+
+  fn url(&self) -> String {
+    format!(
+      "https://api.open-meteo.com/v1/forecast?latitude={}&longitude={}&current_weather=true\
+       &daily=weathercode,temperature_2m_max,temperature_2m_min&timezone=auto&forecast_days={}",
+      self.latitude, self.longitude, self.days
+    )
+  }
+}
+
+#[cfg(all(feature = "weather-http", not(target_arch = "wasm32")))]
+#[async_trait]
+impl WeatherProvider for OpenMeteo {
+  async fn fetch(&mut self) -> Result<Weather, String> {
+    let url = self.url();
+    // ureq blocks, which would hold up the other sources
+    let body = tokio::task::spawn_blocking(move || {
+      ureq::get(&url)
+        .timeout(Duration::from_secs(30))
+        .call()
+        .map_err(|err| err.to_string())?
+        .into_string()
+        .map_err(|err| err.to_string())
+    })
+    .await
+    .map_err(|err| err.to_string())??;
+    parse_open_meteo(&body)
+  }
+}
+
+#[cfg(all(feature = "weather-http", not(target_arch = "wasm32")))]
+fn parse_open_meteo(body: &str) -> Result<Weather, String> {
+  #[derive(Deserialize)]
+  struct Response {
+    current_weather: Current,
+    daily: Daily,
+  }
+  #[derive(Deserialize)]
+  struct Current {
+    temperature: f32,
+    weathercode: u32,
+  }
+  #[derive(Deserialize)]
+  struct Daily {
+    time: Vec<String>,
+    weathercode: Vec<u32>,
+    temperature_2m_max: Vec<f32>,
+    temperature_2m_min: Vec<f32>,
+  }
+
+  let response: Response = serde_json::from_str(body).map_err(|err| err.to_string())?;
+  let condition = |code| Condition::from_wmo(code).ok_or(format!("unknown weather code {}", code));
+  let daily = &response.daily;
+  let forecast = daily
+    .time
+    .iter()
+    .zip(&daily.weathercode)
+    .zip(
+      daily
+        .temperature_2m_max
+        .iter()
+        .zip(&daily.temperature_2m_min),
+    )
+    .map(|((date, code), (high, low))| {
+      Ok(Forecast {
+        day: weekday(date).unwrap_or_default().to_string(),
+        condition: condition(*code)?,
+        high: *high,
+        low: *low,
+      })
+    })
+    .collect::<Result<_, String>>()?;
+  Ok(Weather {
+    condition: condition(response.current_weather.weathercode)?,
+    temperature: response.current_weather.temperature,
+    forecast,
+  })
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn names_weekdays() {
+    assert_eq!(weekday("2026-10-14"), Some("Wed"));
+    assert_eq!(weekday("2000-01-01"), Some("Sat"));
+    assert_eq!(weekday("2024-02-29T12:00"), Some("Thu"));
+    assert_eq!(weekday("2024-13-01"), None);
+    assert_eq!(weekday("today"), None);
+  }
+
+  #[test]
+  fn groups_wmo_codes() {
+    assert_eq!(Condition::from_wmo(2), Some(Condition::PartlyCloudy));
+    assert_eq!(Condition::from_wmo(81), Some(Condition::Rain));
+    assert_eq!(Condition::from_wmo(86), Some(Condition::Snow));
+    assert_eq!(Condition::from_wmo(42), None);
+  }
+
+  #[cfg(all(feature = "weather-http", not(target_arch = "wasm32")))]
+  #[test]
+  fn parses_open_meteo_forecasts() {
+    let weather = parse_open_meteo(
+      r#"{
+        "current_weather": { "temperature": 11.4, "weathercode": 61, "windspeed": 9.2 },
+        "daily": {
+          "time": ["2026-10-14", "2026-10-15"],
+          "weathercode": [61, 0],
+          "temperature_2m_max": [13.1, 15.0],
+          "temperature_2m_min": [7.2, 6.5]
+        }
+      }"#,
+    )
+    .unwrap();
+    assert_eq!(weather.condition, Condition::Rain);
+    assert_eq!(weather.forecast[1].day, "Thu");
+    assert_eq!(weather.forecast[1].condition, Condition::Clear);
+    assert_eq!(weather.forecast[1].low, 6.5);
+  }
+}
@@ -0,0 +1,341 @@
+//! A screen over the HUD listing every telemetry field with its value, how
+//! often it's updated, the source it came from last and a sparkline of its
+//! recent values, for bringing up a vehicle's data sources, see
+//! [`Action::Diagnostics`](crate::input::Action::Diagnostics).
+//!
+//! Typing while it's shown searches the fields by name and source,
+//! `Backspace` takes back what was typed and `Escape` clears it, or hides
+//! the screen once there's nothing to clear. The arrow keys and the
+//! navigation actions scroll.
+
+use std::collections::VecDeque;
+use std::sync::Arc;
+use std::time::Duration;
+
+use winit::event::{ElementState, KeyboardInput, ModifiersState, VirtualKeyCode, WindowEvent};
+
+use crate::canvas::Style;
+use crate::data::{Field, Telemetry};
+use crate::frame::Frame;
+use crate::input::Action;
+use crate::text::{Align, TextSection, VAlign};
+
+/// Values kept of every field for its sparkline.
+const HISTORY: usize = 120;
+
+/// Over how long update rates are counted.
+const RATE_WINDOW: Duration = Duration::from_secs(1);
+
+/// Rows shown at a time, so they stay readable on small displays.
+const ROWS: usize = 10;
+
+/// What's known about one field.
+struct Signal {
+  field: Field,
+  value: Option<f32>,
+  /// The source of the latest value, see
+  /// [`DataSource::name`](crate::data::DataSource::name).
+  source: Option<Arc<str>>,
+  /// When the updates of the last [`RATE_WINDOW`] came.
+  updates: VecDeque<Duration>,
+  history: VecDeque<f32>,
+}
+
+impl Signal {
+  fn new(field: Field) -> Self {
+    Self {
+      field,
+      value: None,
+      source: None,
+      updates: VecDeque::new(),
+      history: VecDeque::with_capacity(HISTORY),
+    }
+  }
+
+  /// Updates per second up to `now`.
+  fn rate(&self, now: Duration) -> f32 {
+    let recent = self
+      .updates
+      .iter()
+      .filter(|updated| now.saturating_sub(**updated) < RATE_WINDOW)
+      .count();
+    recent as f32 / RATE_WINDOW.as_secs_f32()
+  }
+
+  fn matches(&self, query: &str) -> bool {
+    self.field.name().contains(query)
+      || self
+        .source
+        .as_deref()
+        .is_some_and(|source| source.to_lowercase().contains(query))
+  }
+}
+
+/// The diagnostics screen, recording telemetry whether it's shown or not.
+pub(crate) struct Diagnostics {
+  signals: Vec<Signal>,
+  open: bool,
+  query: String,
+  /// Rows of the matching fields scrolled past.
+  scroll: usize,
+  modifiers: ModifiersState,
+}
+
+impl Diagnostics {
+  pub(crate) fn new() -> Self {
+    Self {
+      signals: Field::ALL.into_iter().map(Signal::new).collect(),
+      open: false,
+      query: String::new(),
+      scroll: 0,
+      modifiers: ModifiersState::empty(),
+    }
+  }
+
+  pub(crate) fn is_open(&self) -> bool {
+    self.open
+  }
+
+  pub(crate) fn toggle(&mut self) {
+    self.open = !self.open;
+  }
+
+  /// Records the values of `update`, reported by the source called
+  /// `source` at `now`.
+  pub(crate) fn record(&mut self, source: &Arc<str>, update: &Telemetry, now: Duration) {
+    for signal in &mut self.signals {
+      let Some(value) = update.get(signal.field) else {
+        continue;
+      };
+      signal.value = Some(value);
+      signal.source = Some(source.clone());
+      while signal
+        .updates
+        .front()
+        .is_some_and(|updated| now.saturating_sub(*updated) >= RATE_WINDOW)
+      {
+        signal.updates.pop_front();
+      }
+      signal.updates.push_back(now);
+      if signal.history.len() == HISTORY {
+        signal.history.pop_front();
+      }
+      signal.history.push_back(value);
+    }
+  }
+
+  /// The fields matching what was typed.
+  fn matching(&self) -> impl Iterator<Item = &Signal> {
+    let query = self.query.to_lowercase();
+    self
+      .signals
+      .iter()
+      .filter(move |signal| signal.matches(&query))
+  }
+
+  fn scroll_by(&mut self, rows: isize) {
+    let last = self.matching().count().saturating_sub(ROWS);
+    self.scroll = self.scroll.saturating_add_signed(rows).min(last);
+  }
+
+  /// Searches and scrolls with `event`, returning whether it was used
+  /// rather than going to the HUD. Only used while open, and never with
+  /// `Ctrl`, `Alt` or the logo key held, so key combos still work.
+  pub(crate) fn event(&mut self, event: &WindowEvent) -> bool {
+    if let WindowEvent::ModifiersChanged(modifiers) = event {
+      self.modifiers = *modifiers;
+    }
+    let combo = ModifiersState::CTRL | ModifiersState::ALT | ModifiersState::LOGO;
+    if !self.open || self.modifiers.intersects(combo) {
+      return false;
+    }
+    match event {
+      WindowEvent::ReceivedCharacter(character) if !character.is_control() => {
+        self.query.push(*character);
+        self.scroll = 0;
+        true
+      }
+      WindowEvent::KeyboardInput {
+        input: KeyboardInput {
+          state,
+          virtual_keycode,
+          ..
+        },
+        ..
+      } => {
+        if *state == ElementState::Pressed {
+          match virtual_keycode {
+            Some(VirtualKeyCode::Back) => {
+              self.query.pop();
+              self.scroll = 0;
+            }
+            Some(VirtualKeyCode::Escape) if self.query.is_empty() => self.open = false,
+            Some(VirtualKeyCode::Escape) => self.query.clear(),
+            Some(VirtualKeyCode::Down) => self.scroll_by(1),
+            Some(VirtualKeyCode::Up) => self.scroll_by(-1),
+            Some(VirtualKeyCode::PageDown) => self.scroll_by(ROWS as isize),
+            Some(VirtualKeyCode::PageUp) => self.scroll_by(-(ROWS as isize)),
+            _ => {}
+          }
+        }
+        true
+      }
+      _ => false,
+    }
+  }
+
+  /// Scrolls with navigation `action`s, `Back` hides the screen.
+  pub(crate) fn action(&mut self, action: Action) {
+    match action {
+      Action::Next => self.scroll_by(1),
+      Action::Previous => self.scroll_by(-1),
+      Action::NextPage => self.scroll_by(ROWS as isize),
+      Action::PreviousPage => self.scroll_by(-(ROWS as isize)),
+      Action::Back => self.open = false,
+      _ => {}
+    }
+  }
+
+  /// Draws the screen over everything in `frame`, with rates up to `now`.
+  pub(crate) fn draw(&self, frame: &mut Frame, now: Duration) {
+    let (width, height) = (frame.width as f32, frame.height as f32);
+    let palette = frame.palette;
+    frame.canvas.rect(
+      [0.0, 0.0],
+      [width, height],
+      Style::fill([0.0, 0.0, 0.0, 0.85]),
+    );
+    let row = height / (ROWS + 1) as f32;
+    let size = row * 0.45;
+    let pad = size * 0.5;
+    let search = match self.query.is_empty() {
+      true => "type to search".to_string(),
+      false => format!("search: {}_", self.query),
+    };
+    let header = [
+      ("Diagnostics", pad, Align::Left, palette.text),
+      (&search, width - pad, Align::Right, palette.muted),
+    ];
+    for (text, x, align, color) in header {
+      frame.text.queue(
+        &TextSection::new(text)
+          .at(x, row / 2.0)
+          .with_size(size)
+          .with_color(color)
+          .with_align(align, VAlign::Center),
+      );
+    }
+    // Name, value, rate and source, then the sparkline
+    let columns = [0.0, 0.25, 0.4, 0.52, 0.68].map(|column| pad + column * (width - 2.0 * pad));
+    let signals = self.matching().skip(self.scroll).take(ROWS);
+    for (index, signal) in signals.enumerate() {
+      let top = row * (index + 1) as f32;
+      let middle = top + row / 2.0;
+      if index % 2 == 0 {
+        frame
+          .canvas
+          .rect([0.0, top], [width, row], Style::fill(palette.surface));
+      }
+      let stale = frame.stale.contains(&signal.field);
+      let value = match signal.value {
+        Some(value) => format!("{:.2}", value),
+        None => "--".to_string(),
+      };
+      let cells = [
+        (signal.field.name().to_string(), palette.text),
+        (value, if stale { palette.warning } else { palette.text }),
+        (format!("{:.0} Hz", signal.rate(now)), palette.muted),
+        (
+          signal.source.as_deref().unwrap_or("no source").to_string(),
+          palette.muted,
+        ),
+      ];
+      for ((text, color), x) in cells.into_iter().zip(columns) {
+        frame.text.queue(
+          &TextSection::new(text)
+            .at(x, middle)
+            .with_size(size)
+            .with_color(color)
+            .with_align(Align::Left, VAlign::Center),
+        );
+      }
+      let left = columns[4];
+      let points = sparkline(
+        &signal.history,
+        [left, top + row * 0.2],
+        [width - pad - left, row * 0.6],
+      );
+      for segment in points.windows(2) {
+        frame
+          .canvas
+          .line(segment[0], segment[1], palette.accent, 2.0);
+      }
+    }
+  }
+}
+
+/// Points of a line through `values` from oldest to newest, filling the
+/// rectangle at `position` of `size` from the smallest to the largest.
+fn sparkline(values: &VecDeque<f32>, position: [f32; 2], size: [f32; 2]) -> Vec<[f32; 2]> {
+  let min = values.iter().copied().fold(f32::INFINITY, f32::min);
+  let max = values.iter().copied().fold(f32::NEG_INFINITY, f32::max);
+  // Flat lines go through the middle
+  let range = if max > min { max - min } else { 1.0 };
+  let offset = if max > min { 0.0 } else { 0.5 };
+  let step = size[0] / (HISTORY - 1) as f32;
+  let start = HISTORY - values.len();
+  values
+    .iter()
+    .enumerate()
+    .map(|(index, value)| {
+      let t = (value - min) / range + offset;
+      let x = position[0] + (start + index) as f32 * step;
+      [x, position[1] + size[1] * (1.0 - t)]
+    })
+    .collect()
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn records_rates_sources_and_history() {
+    let mut diagnostics = Diagnostics::new();
+    let (obd, gps): (Arc<str>, Arc<str>) = ("obd".into(), "gps".into());
+    for tick in 0..20 {
+      let update = Telemetry {
+        rpm: Some(tick as f32),
+        ..Default::default()
+      };
+      diagnostics.record(&obd, &update, Duration::from_millis(tick * 100));
+    }
+    let update = Telemetry {
+      ground_speed: Some(50.0),
+      ..Default::default()
+    };
+    diagnostics.record(&gps, &update, Duration::from_millis(1900));
+    let now = Duration::from_millis(1950);
+    let rpm = diagnostics
+      .matching()
+      .find(|signal| signal.field == Field::Rpm);
+    let rpm = rpm.unwrap();
+    assert_eq!(rpm.rate(now), 10.0);
+    assert_eq!(rpm.value, Some(19.0));
+    assert_eq!(rpm.history.len(), 20);
+    diagnostics.query = "GPS".to_string();
+    let found: Vec<_> = diagnostics.matching().map(|signal| signal.field).collect();
+    assert_eq!(found, [Field::GroundSpeed]);
+  }
+
+  #[test]
+  fn sparklines_fill_their_rectangle_from_the_right() {
+    let values = VecDeque::from([2.0, 4.0, 3.0]);
+    let points = sparkline(&values, [0.0, 10.0], [(HISTORY - 1) as f32, 20.0]);
+    let start = (HISTORY - 3) as f32;
+    assert_eq!(
+      points,
+      [[start, 30.0], [start + 1.0, 10.0], [start + 2.0, 20.0]]
+    );
+  }
+}
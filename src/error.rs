@@ -0,0 +1,65 @@
+use std::path::PathBuf;
+
+use thiserror::Error;
+
+/// Errors that prevent the renderer from starting.
+#[derive(Debug, Error)]
+pub enum WindshieldError {
+  #[error("unable to create window: {0}")]
+  CreateWindow(#[from] winit::error::OsError),
+  #[error("no graphics adapter compatible with the window surface was found")]
+  AdapterNotFound,
+  #[error("unable to request graphics device: {0}")]
+  RequestDevice(#[from] wgpu::RequestDeviceError),
+  #[error("the window surface does not support any texture format on this adapter")]
+  UnsupportedSurface,
+  #[error("unable to compile shader {name}: {message}")]
+  ShaderCompile { name: String, message: String },
+  #[error("unable to read {}: {source}", path.display())]
+  ReadFile {
+    path: PathBuf,
+    source: std::io::Error,
+  },
+  #[cfg(target_arch = "wasm32")]
+  #[error("unable to fetch {}: {message}", path.display())]
+  Fetch { path: PathBuf, message: String },
+  #[error("font data is not a valid TrueType or OpenType font")]
+  InvalidFont,
+  #[error("invalid configuration in {}: {message}", path.display())]
+  InvalidConfig { path: PathBuf, message: String },
+  #[error("configuration refers to unknown font {0:?}")]
+  UnknownFont(String),
+  #[error("unable to read the rendered frame back from the graphics device")]
+  ReadBack(#[from] wgpu::BufferAsyncError),
+  #[error("invalid snapshot {}: {message}", path.display())]
+  InvalidSnapshot { path: PathBuf, message: String },
+  #[error("unable to write {}: {source}", path.display())]
+  WriteFile {
+    path: PathBuf,
+    source: std::io::Error,
+  },
+  #[error("unable to read image {}: {source}", path.display())]
+  ReadImage {
+    path: PathBuf,
+    source: png::DecodingError,
+  },
+  #[error("unable to write {}: {source}", path.display())]
+  WriteImage {
+    path: PathBuf,
+    source: png::EncodingError,
+  },
+  #[error(
+    "{width}x{height} image is larger than the {max}x{max} pixels the graphics device takes"
+  )]
+  ImageTooLarge { width: u32, height: u32, max: u32 },
+  #[error("invalid map {}: {message}", path.display())]
+  InvalidMap { path: PathBuf, message: String },
+  #[error("invalid input recording {}: {message}", path.display())]
+  InvalidRecording { path: PathBuf, message: String },
+  #[error("invalid prewarm list {}: {message}", path.display())]
+  InvalidVariantList { path: PathBuf, message: String },
+  #[error("invalid processor for {field}: {message}")]
+  InvalidProcessor { field: String, message: String },
+  #[error("unable to create a layer-shell surface: {0}")]
+  LayerShell(String),
+}
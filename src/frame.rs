@@ -0,0 +1,110 @@
+use std::collections::HashSet;
+use std::time::Duration;
+
+use wgpu::AdapterInfo;
+
+use crate::burn_in::BurnIn;
+use crate::cache::CachePass;
+use crate::canvas::{Canvas, Layer};
+use crate::data::{Field, Telemetry};
+use crate::image::ImageBatch;
+use crate::text::TextRenderer;
+use crate::theme::Palette;
+use crate::widgets::Rect;
+
+/// What user code gets to draw into while a frame is being built.
+pub struct Frame<'a> {
+  pub canvas: &'a mut Canvas,
+  pub text: &'a mut TextRenderer,
+  // Drawn with `Frame::image`
+  pub(crate) images: &'a mut ImageBatch,
+  // Of `Frame::cached_widget`
+  pub(crate) caches: &'a mut CachePass,
+  /// Size of the render target in physical pixels.
+  pub width: u32,
+  pub height: u32,
+  /// The part of the frame the display's bezel leaves visible, see
+  /// [`Settings::safe_area`](crate::Settings::safe_area). Scenes and nodes
+  /// are laid out inside it.
+  pub safe_area: Rect,
+  /// Physical pixels a pixel of the layout takes, the display's scale
+  /// factor times [`Settings::ui_scale`](crate::Settings::ui_scale), or a
+  /// node's own [scale](crate::layout::Node::scale) while its widget draws.
+  /// Widgets multiply sizes given in pixels, like those of text, with it.
+  pub scale: f32,
+  /// Whether widgets hold still where they'd only move to look nice, like
+  /// scrolling text, see [`Settings::reduce_motion`](crate::Settings::reduce_motion).
+  /// Scenes cut between pages then.
+  pub reduce_motion: bool,
+  /// Whether the HUD reads from right to left, see
+  /// [`Settings::right_to_left`](crate::Settings::right_to_left). Nodes are
+  /// laid out mirrored then, and widgets flip icons pointing along the
+  /// reading direction. Unset while a node that opted out draws.
+  pub right_to_left: bool,
+  /// Time since the previous frame on the app's clock.
+  pub delta: Duration,
+  /// Vehicle values reported by the data sources so far.
+  pub telemetry: &'a Telemetry,
+  /// Fields of the telemetry that stopped updating, see
+  /// [`Settings::stale_after`](crate::Settings::stale_after).
+  pub stale: &'a HashSet<Field>,
+  /// The GPU drawing the frame, with the backend wgpu uses for it, see
+  /// [`build_info`](crate::build_info::build_info) for what's running on
+  /// it.
+  pub adapter: &'a AdapterInfo,
+  /// Colors of the active theme.
+  pub palette: &'a Palette,
+  // Dims what nodes show unchanged for long
+  pub(crate) burn_in: Option<BurnIn>,
+  // Set if another frame should follow right away
+  pub(crate) animating: bool,
+}
+
+impl Frame<'_> {
+  /// Asks for another frame right after this one, for animations drawn by
+  /// `on_draw`. Only needed when redrawing on change, see
+  /// [`Settings::heartbeat`](crate::Settings::heartbeat).
+  pub fn animate(&mut self) {
+    self.animating = true;
+  }
+
+  /// Draws everything from now on above all text and images drawn so far.
+  /// Within a layer images are drawn above the shapes and text above both,
+  /// so overlapping widgets need a layer each, which
+  /// [`Node`](crate::layout::Node) starts for every `z`.
+  pub fn next_layer(&mut self) {
+    // Shapes are drawn in order anyway
+    if self.text.layer_is_empty() && self.images.layer_is_empty() {
+      return;
+    }
+    self.text.next_layer();
+    self.canvas.next_layer();
+    self.images.next_layer();
+  }
+
+  /// What's drawn is clipped to, see [`set_clip`](Self::set_clip).
+  pub fn clip(&self) -> Option<Rect> {
+    self.canvas.clip()
+  }
+
+  /// Only draws inside `rect` from now on, or everywhere if `None`, in a
+  /// layer of its own. Returns what was clipped to before, to set it back
+  /// when done.
+  pub fn set_clip(&mut self, rect: Option<Rect>) -> Option<Rect> {
+    let layer = self.canvas.layer();
+    self
+      .set_layer(Layer {
+        clip: rect,
+        ..layer
+      })
+      .clip
+  }
+
+  /// Starts a `layer`, even if the current one is empty, returning the
+  /// current one.
+  pub(crate) fn set_layer(&mut self, layer: Layer) -> Layer {
+    self.text.next_layer();
+    self.images.next_layer();
+    self.canvas.set_layer(layer)
+  }
+}
@@ -0,0 +1,168 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+use std::time::Duration;
+
+use crate::widgets::Feedback;
+
+/// One vibration of a haptic actuator.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Pulse {
+  pub duration: Duration,
+  /// From 0 to 1.
+  pub strength: f32,
+  /// Quiet time after the pulse, before the next one of a pattern.
+  pub pause: Duration,
+}
+
+impl Pulse {
+  pub const fn new(duration: Duration, strength: f32) -> Self {
+    Self {
+      duration,
+      strength,
+      pause: Duration::ZERO,
+    }
+  }
+
+  pub const fn with_pause(mut self, pause: Duration) -> Self {
+    self.pause = pause;
+    self
+  }
+}
+
+/// A short, sharp tick, e.g. for a press.
+pub const TICK: &[Pulse] = &[Pulse::new(Duration::from_millis(10), 0.6)];
+/// A lighter tick, e.g. for a slider passing a step.
+pub const DETENT: &[Pulse] = &[Pulse::new(Duration::from_millis(6), 0.35)];
+/// Three strong pulses demanding attention, e.g. for a warning.
+pub const ALERT: &[Pulse] = &[
+  Pulse::new(Duration::from_millis(120), 1.0).with_pause(Duration::from_millis(80)),
+  Pulse::new(Duration::from_millis(120), 1.0).with_pause(Duration::from_millis(80)),
+  Pulse::new(Duration::from_millis(120), 1.0),
+];
+
+/// Something that can vibrate, like a touchscreen with a haptic actuator.
+pub trait Haptics {
+  /// Plays `pattern`, replacing whatever is playing. Must not block, the
+  /// pattern plays while frames keep being drawn.
+  fn pattern(&mut self, pattern: &[Pulse]);
+
+  fn pulse(&mut self, duration: Duration, strength: f32) {
+    self.pattern(&[Pulse::new(duration, strength)]);
+  }
+
+  /// Plays [`ALERT`].
+  fn alert(&mut self) {
+    self.pattern(ALERT);
+  }
+}
+
+/// A [`Haptics`] implementation shared between the widgets triggering it.
+///
+/// ```no_run
+/// # use windshield_rs::haptics::{Haptics, Pulse, SharedHaptics};
+/// # use windshield_rs::widgets::{Binding, Toggle};
+/// # struct Motor;
+/// # impl Haptics for Motor {
+/// #   fn pattern(&mut self, _pattern: &[Pulse]) {}
+/// # }
+/// let haptics = SharedHaptics::new(Motor);
+/// let toggle = Toggle::new(Binding::new(false)).on_feedback(haptics.on_feedback());
+/// ```
+#[derive(Clone)]
+pub struct SharedHaptics(Rc<RefCell<dyn Haptics>>);
+
+impl SharedHaptics {
+  pub fn new(haptics: impl Haptics + 'static) -> Self {
+    Self(Rc::new(RefCell::new(haptics)))
+  }
+
+  pub fn pattern(&self, pattern: &[Pulse]) {
+    self.0.borrow_mut().pattern(pattern);
+  }
+
+  pub fn alert(&self) {
+    self.0.borrow_mut().alert();
+  }
+
+  /// A hook for the `on_feedback` of controls, ticking on presses and
+  /// steps.
+  pub fn on_feedback(&self) -> impl FnMut(Feedback) + 'static {
+    let haptics = self.clone();
+    move |feedback| match feedback {
+      Feedback::Press => haptics.pattern(TICK),
+      Feedback::Detent => haptics.pattern(DETENT),
+      // Changes follow a press or a detent, which already ticked
+      Feedback::Release | Feedback::Change => {}
+    }
+  }
+}
+
+/// Rumble of a Linux input device supporting force feedback, e.g.
+/// `/dev/input/event3`.
+#[cfg(feature = "haptics-evdev")]
+pub struct EvdevHaptics {
+  device: evdev::Device,
+  // Removed from the device when dropped, which stops them
+  playing: Vec<evdev::FFEffect>,
+}
+
+#[cfg(feature = "haptics-evdev")]
+impl EvdevHaptics {
+  pub fn open(path: impl AsRef<std::path::Path>) -> std::io::Result<Self> {
+    let device = evdev::Device::open(path)?;
+    let rumbles = device
+      .supported_ff()
+      .is_some_and(|effects| effects.contains(evdev::FFEffectType::FF_RUMBLE));
+    if !rumbles {
+      return Err(std::io::Error::new(
+        std::io::ErrorKind::Unsupported,
+        "device does not support rumble effects",
+      ));
+    }
+    Ok(Self {
+      device,
+      playing: Vec::new(),
+    })
+  }
+
+  fn play(&mut self, pattern: &[Pulse]) -> std::io::Result<()> {
+    use evdev::{FFEffectData, FFEffectKind, FFReplay, FFTrigger};
+
+    self.playing.clear();
+    // Every pulse is its own effect, delayed until the ones before are done
+    let mut delay = Duration::ZERO;
+    for pulse in pattern.iter().take(self.device.max_ff_effects()) {
+      let magnitude = (pulse.strength.clamp(0.0, 1.0) * u16::MAX as f32) as u16;
+      let mut effect = self.device.upload_ff_effect(FFEffectData {
+        direction: 0,
+        trigger: FFTrigger::default(),
+        replay: FFReplay {
+          length: millis(pulse.duration),
+          delay: millis(delay),
+        },
+        kind: FFEffectKind::Rumble {
+          strong_magnitude: magnitude,
+          weak_magnitude: magnitude,
+        },
+      })?;
+      effect.play(1)?;
+      self.playing.push(effect);
+      delay += pulse.duration + pulse.pause;
+    }
+    Ok(())
+  }
+}
+
+#[cfg(feature = "haptics-evdev")]
+impl Haptics for EvdevHaptics {
+  fn pattern(&mut self, pattern: &[Pulse]) {
+    if let Err(err) = self.play(pattern) {
+      tracing::warn!("unable to play haptic feedback: {}", err);
+    }
+  }
+}
+
+#[cfg(feature = "haptics-evdev")]
+fn millis(duration: Duration) -> u16 {
+  duration.as_millis().min(u16::MAX as u128) as u16
+}
@@ -0,0 +1,945 @@
+use std::collections::{HashMap, HashSet};
+use std::fs::File;
+use std::io::BufReader;
+use std::mem;
+use std::ops::Range;
+use std::path::Path;
+use std::sync::atomic::{AtomicU64, Ordering};
+#[cfg(not(target_arch = "wasm32"))]
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Arc, Mutex};
+
+use bytemuck::{Pod, Zeroable};
+use serde::Deserialize;
+use wgpu::{
+  vertex_attr_array, AddressMode, BindGroup, BindGroupDescriptor, BindGroupEntry, BindGroupLayout,
+  BindGroupLayoutDescriptor, BindGroupLayoutEntry, BindingResource, BindingType, Buffer,
+  BufferDescriptor, BufferUsages, CommandEncoderDescriptor, Device, Extent3d, FilterMode,
+  ImageCopyBuffer, ImageDataLayout, Queue, RenderPass, RenderPipeline, Sampler, SamplerBindingType,
+  SamplerDescriptor, ShaderStages, TextureDescriptor, TextureDimension, TextureFormat,
+  TextureSampleType, TextureUsages, TextureViewDescriptor, TextureViewDimension, VertexAttribute,
+  VertexBufferLayout, VertexStepMode, COPY_BYTES_PER_ROW_ALIGNMENT,
+};
+
+use crate::error::WindshieldError;
+use crate::frame::Frame;
+use crate::mirror::Mirror;
+use crate::pipeline::{create_shader, GeometryBuffer, PipelineBuilder, ScreenUniform, Vertex};
+use crate::stats::FrameStats;
+use crate::widgets::Rect;
+
+static NEXT_ID: AtomicU64 = AtomicU64::new(0);
+
+/// Pixels for widgets to show, like the frames of a rear camera or cover
+/// art.
+///
+/// Clones share the pixels, so a thread decoding video can keep setting
+/// them while the widget showing them draws. Only what changed is uploaded
+/// again.
+#[derive(Clone, Debug)]
+pub struct Image {
+  id: u64,
+  pixels: Arc<Mutex<Pixels>>,
+}
+
+#[derive(Debug, Default)]
+struct Pixels {
+  size: [u32; 2],
+  rgba: Vec<u8>,
+  // Counts every set, so the pipeline knows what it has uploaded already
+  version: u64,
+}
+
+impl Default for Image {
+  fn default() -> Self {
+    Self::new()
+  }
+}
+
+impl Image {
+  /// An image without pixels, which draws nothing until they are set.
+  pub fn new() -> Self {
+    Self {
+      id: NEXT_ID.fetch_add(1, Ordering::Relaxed),
+      pixels: Arc::default(),
+    }
+  }
+
+  /// See [`set`](Self::set).
+  pub fn from_rgba(width: u32, height: u32, rgba: Vec<u8>) -> Self {
+    let image = Self::new();
+    image.set(width, height, rgba);
+    image
+  }
+
+  /// Reads a PNG file.
+  pub fn load(path: impl AsRef<Path>) -> Result<Self, WindshieldError> {
+    let path = path.as_ref();
+    let (width, height, rgba) = decode(path, open(path)?)?;
+    Ok(Self::from_rgba(width, height, rgba))
+  }
+
+  /// Like [`load`](Self::load), but decodes the file on a thread of its
+  /// own and returns an image without pixels until it's done, so large
+  /// ones don't hold up the frame. Only fails if the file can't be opened,
+  /// errors decoding it are logged.
+  pub fn load_in_background(path: impl AsRef<Path>) -> Result<Self, WindshieldError> {
+    let path = path.as_ref().to_path_buf();
+    let file = open(&path)?;
+    let image = Self::new();
+    let pixels = image.clone();
+    let load = move || match decode(&path, file) {
+      Ok((width, height, rgba)) => pixels.set(width, height, rgba),
+      Err(err) => tracing::error!("{}", err),
+    };
+    // The web has no threads to decode on
+    #[cfg(target_arch = "wasm32")]
+    load();
+    #[cfg(not(target_arch = "wasm32"))]
+    if let Err(err) = std::thread::Builder::new()
+      .name("image decoding".to_string())
+      .spawn(load)
+    {
+      tracing::error!("unable to start decoding image: {}", err);
+    }
+    Ok(image)
+  }
+
+  /// Replaces the pixels, four bytes each in sRGB with straight alpha and
+  /// row by row from the top.
+  ///
+  /// Pixels wider or higher than the graphics device's textures, usually
+  /// 8192 pixels and 2048 on WebGL, aren't drawn but logged as an error.
+  ///
+  /// # Panics
+  ///
+  /// If `rgba` doesn't hold `width` by `height` pixels.
+  pub fn set(&self, width: u32, height: u32, rgba: Vec<u8>) {
+    assert_eq!(
+      rgba.len(),
+      width as usize * height as usize * 4,
+      "{}x{} image with {} bytes of pixels",
+      width,
+      height,
+      rgba.len()
+    );
+    let mut pixels = self.pixels.lock().expect("image isn't poisoned");
+    pixels.size = [width, height];
+    pixels.rgba = rgba;
+    pixels.version += 1;
+  }
+
+  /// Width and height in pixels, if there are any.
+  pub fn size(&self) -> Option<[u32; 2]> {
+    let pixels = self.pixels.lock().expect("image isn't poisoned");
+    let [width, height] = pixels.size;
+    (width > 0 && height > 0).then_some(pixels.size)
+  }
+}
+
+fn open(path: &Path) -> Result<File, WindshieldError> {
+  File::open(path).map_err(|source| WindshieldError::ReadFile {
+    path: path.to_path_buf(),
+    source,
+  })
+}
+
+/// Decodes the PNG `file` at `path` into its width, height and RGBA
+/// pixels.
+fn decode(path: &Path, file: File) -> Result<(u32, u32, Vec<u8>), WindshieldError> {
+  let invalid = |source| WindshieldError::ReadImage {
+    path: path.to_path_buf(),
+    source,
+  };
+  let mut decoder = png::Decoder::new(BufReader::new(file));
+  // Palettes and other bit depths to 8 bit gray or color
+  decoder.set_transformations(png::Transformations::normalize_to_color8());
+  let mut reader = decoder.read_info().map_err(invalid)?;
+  let mut data = vec![0; reader.output_buffer_size()];
+  let info = reader.next_frame(&mut data).map_err(invalid)?;
+  data.truncate(info.buffer_size());
+  let rgba = match info.color_type {
+    png::ColorType::Rgba => data,
+    png::ColorType::Rgb => data
+      .chunks_exact(3)
+      .flat_map(|rgb| [rgb[0], rgb[1], rgb[2], u8::MAX])
+      .collect(),
+    png::ColorType::GrayscaleAlpha => data
+      .chunks_exact(2)
+      .flat_map(|gray| [gray[0], gray[0], gray[0], gray[1]])
+      .collect(),
+    png::ColorType::Grayscale | png::ColorType::Indexed => data
+      .iter()
+      .flat_map(|gray| [*gray, *gray, *gray, u8::MAX])
+      .collect(),
+  };
+  Ok((info.width, info.height, rgba))
+}
+
+/// How an image fills a rect whose aspect ratio it doesn't have.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Fit {
+  /// Shows all of the image as large as it fits, leaving bars at two of
+  /// the sides.
+  #[default]
+  Contain,
+  /// Covers all of the rect, cutting off two of the image's sides.
+  Cover,
+  /// Stretches the image to the rect.
+  Fill,
+}
+
+impl Fit {
+  /// Where an image of `size` is drawn in `rect`, and the part of it shown
+  /// there as texture coordinates from the top left to the bottom right.
+  pub fn place(self, [width, height]: [u32; 2], rect: Rect) -> (Rect, [f32; 4]) {
+    let whole = [0.0, 0.0, 1.0, 1.0];
+    if width == 0 || height == 0 || rect.width <= 0.0 || rect.height <= 0.0 {
+      return (rect, whole);
+    }
+    let image = width as f32 / height as f32;
+    let target = rect.width / rect.height;
+    match self {
+      Fit::Fill => (rect, whole),
+      // Wider than the rect, bars above and below
+      Fit::Contain if image > target => {
+        let height = rect.width / image;
+        let y = rect.y + (rect.height - height) / 2.0;
+        (Rect::new(rect.x, y, rect.width, height), whole)
+      }
+      Fit::Contain => {
+        let width = rect.height * image;
+        let x = rect.x + (rect.width - width) / 2.0;
+        (Rect::new(x, rect.y, width, rect.height), whole)
+      }
+      // Wider than the rect, the sides are cut off
+      Fit::Cover if image > target => {
+        let margin = (1.0 - target / image) / 2.0;
+        (rect, [margin, 0.0, 1.0 - margin, 1.0])
+      }
+      Fit::Cover => {
+        let margin = (1.0 - image / target) / 2.0;
+        (rect, [0.0, margin, 1.0, 1.0 - margin])
+      }
+    }
+  }
+}
+
+/// Changes the colors of an image as it's drawn, like dimming it, tinting
+/// it to the palette or taking its color away.
+///
+/// Every row makes a channel of the linear RGBA result in straight alpha:
+/// the sum of the image's channels times the row's first four numbers,
+/// plus its fifth, like SVG's `feColorMatrix`. Results are clamped to
+/// `0.0..=1.0`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ColorMatrix(pub [[f32; 5]; 4]);
+
+impl Default for ColorMatrix {
+  fn default() -> Self {
+    Self::IDENTITY
+  }
+}
+
+impl ColorMatrix {
+  /// Leaves the colors as they are.
+  pub const IDENTITY: Self = Self([
+    [1.0, 0.0, 0.0, 0.0, 0.0],
+    [0.0, 1.0, 0.0, 0.0, 0.0],
+    [0.0, 0.0, 1.0, 0.0, 0.0],
+    [0.0, 0.0, 0.0, 1.0, 0.0],
+  ]);
+
+  /// Darker, black with a `brightness` of 0.0 and unchanged with 1.0.
+  pub fn dim(brightness: f32) -> Self {
+    let mut matrix = Self::IDENTITY;
+    for (channel, row) in matrix.0.iter_mut().take(3).enumerate() {
+      row[channel] = brightness;
+    }
+    matrix
+  }
+
+  /// Grayer, all gray with an `amount` of 1.0 and unchanged with 0.0.
+  pub fn desaturate(amount: f32) -> Self {
+    let mut matrix = Self::IDENTITY;
+    for row in matrix.0.iter_mut().take(3) {
+      for (weight, luminance) in row.iter_mut().zip(LUMINANCE) {
+        *weight = *weight * (1.0 - amount) + luminance * amount;
+      }
+    }
+    matrix
+  }
+
+  /// Shades of `color`, like those of the palette: black stays black and
+  /// white turns into `color`, with its alpha.
+  pub fn tint(color: [f32; 4]) -> Self {
+    let mut matrix = Self([[0.0; 5]; 4]);
+    for (row, value) in matrix.0.iter_mut().zip(color).take(3) {
+      for (weight, luminance) in row.iter_mut().zip(LUMINANCE) {
+        *weight = luminance * value;
+      }
+    }
+    matrix.0[3][3] = color[3];
+    matrix
+  }
+
+  /// This, then `next` on what it results in.
+  pub fn then(self, next: Self) -> Self {
+    let Self(first) = self;
+    let mut matrix = [[0.0; 5]; 4];
+    for (row, next) in matrix.iter_mut().zip(next.0) {
+      for (column, value) in row.iter_mut().enumerate() {
+        *value = (0..4).map(|k| next[k] * first[k][column]).sum();
+      }
+      row[4] += next[4];
+    }
+    Self(matrix)
+  }
+
+  /// `rgba` changed, see [`ColorMatrix`].
+  pub fn apply(&self, rgba: [f32; 4]) -> [f32; 4] {
+    self.0.map(|row| {
+      let sum: f32 = row
+        .iter()
+        .zip(rgba)
+        .map(|(weight, value)| weight * value)
+        .sum();
+      (sum + row[4]).clamp(0.0, 1.0)
+    })
+  }
+}
+
+// Weights of linear RGB making up the luminance, same as
+// `mirror::high_contrast`
+const LUMINANCE: [f32; 3] = [0.2126, 0.7152, 0.0722];
+
+impl Frame<'_> {
+  /// Draws `image` into `rect` as `fit` says, with its corners rounded by
+  /// `radius` pixels and its colors changed by `matrix`. Images go above
+  /// the shapes of their layer and below its text, see
+  /// [`next_layer`](Self::next_layer).
+  pub fn image(&mut self, image: &Image, rect: Rect, fit: Fit, radius: f32, matrix: ColorMatrix) {
+    let Some(size) = image.size() else {
+      return;
+    };
+    let (placed, [u0, v0, u1, v1]) = fit.place(size, rect);
+    // Cut to where the image is, for rounded corners on the bars' side
+    let clip = [placed.x, placed.y, placed.width, placed.height];
+    let shape = [radius, self.canvas.opacity()];
+    let ColorMatrix(rows) = matrix;
+    let weights = rows.map(|[r, g, b, a, _]| [r, g, b, a]);
+    let offset = rows.map(|row| row[4]);
+    let vertex = |x: f32, y: f32, u: f32, v: f32| ImageVertex {
+      position: [x, y],
+      uv: [u, v],
+      clip,
+      shape,
+      weights,
+      offset,
+    };
+    let (x0, y0) = (placed.x, placed.y);
+    let (x1, y1) = (x0 + placed.width, y0 + placed.height);
+    self.images.push(
+      image,
+      [
+        vertex(x0, y0, u0, v0),
+        vertex(x1, y0, u1, v0),
+        vertex(x1, y1, u1, v1),
+        vertex(x0, y1, u0, v1),
+      ],
+    );
+  }
+}
+
+/// Textured vertex, see `shaders/image.wgsl`.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Pod, Zeroable)]
+pub(crate) struct ImageVertex {
+  position: [f32; 2],
+  uv: [f32; 2],
+  clip: [f32; 4],
+  shape: [f32; 2],
+  // Rows of the color matrix, without their last column in `offset`
+  weights: [[f32; 4]; 4],
+  offset: [f32; 4],
+}
+
+impl ImageVertex {
+  const ATTRIBUTES: [VertexAttribute; 9] = vertex_attr_array![
+    0 => Float32x2,
+    1 => Float32x2,
+    2 => Float32x4,
+    3 => Float32x2,
+    4 => Float32x4,
+    5 => Float32x4,
+    6 => Float32x4,
+    7 => Float32x4,
+    8 => Float32x4,
+  ];
+}
+
+impl Vertex for ImageVertex {
+  fn layout() -> VertexBufferLayout<'static> {
+    VertexBufferLayout {
+      array_stride: mem::size_of::<Self>() as u64,
+      step_mode: VertexStepMode::Vertex,
+      attributes: &Self::ATTRIBUTES,
+    }
+  }
+}
+
+/// The images drawn this frame by layer, like the canvas' shapes.
+pub(crate) struct ImageBatch {
+  layers: Vec<Vec<(Image, [ImageVertex; 4])>>,
+}
+
+impl ImageBatch {
+  pub(crate) fn new() -> Self {
+    Self {
+      layers: vec![Vec::new()],
+    }
+  }
+
+  fn push(&mut self, image: &Image, quad: [ImageVertex; 4]) {
+    self
+      .layers
+      .last_mut()
+      .expect("there is always a layer")
+      .push((image.clone(), quad));
+  }
+
+  pub(crate) fn layer_is_empty(&self) -> bool {
+    self.layers.last().is_none_or(Vec::is_empty)
+  }
+
+  pub(crate) fn is_empty(&self) -> bool {
+    self.layers.iter().all(Vec::is_empty)
+  }
+
+  pub(crate) fn next_layer(&mut self) {
+    self.layers.push(Vec::new());
+  }
+}
+
+/// An image's pixels on the GPU.
+struct Uploaded {
+  size: [u32; 2],
+  version: u64,
+  texture: wgpu::Texture,
+  bind_group: BindGroup,
+}
+
+/// The pixels of an image copied into a buffer, ready to be copied into
+/// its texture, see [`Stager`].
+struct Staged {
+  id: u64,
+  version: u64,
+  size: [u32; 2],
+  // Rows padded to `COPY_BYTES_PER_ROW_ALIGNMENT`, none without pixels
+  // or with more than a texture takes
+  buffer: Option<Buffer>,
+}
+
+impl Staged {
+  fn new(device: &Device, image: &Image) -> Self {
+    let pixels = image.pixels.lock().expect("image isn't poisoned");
+    let [width, height] = pixels.size;
+    let row = width as usize * 4;
+    let padded_row = padded_row(width) as usize;
+    let fits = match check_size(pixels.size, device.limits().max_texture_dimension_2d) {
+      Ok(()) => true,
+      Err(err) => {
+        tracing::error!("{}", err);
+        false
+      }
+    };
+    let buffer = (fits && width > 0 && height > 0).then(|| {
+      let buffer = device.create_buffer(&BufferDescriptor {
+        label: Some("Image Staging"),
+        size: (padded_row * height as usize) as u64,
+        usage: BufferUsages::COPY_SRC,
+        mapped_at_creation: true,
+      });
+      {
+        let mut staging = buffer.slice(..).get_mapped_range_mut();
+        for (pixels, staging) in pixels
+          .rgba
+          .chunks_exact(row)
+          .zip(staging.chunks_exact_mut(padded_row))
+        {
+          staging[..row].copy_from_slice(pixels);
+        }
+      }
+      buffer.unmap();
+      buffer
+    });
+    Self {
+      id: image.id,
+      version: pixels.version,
+      size: pixels.size,
+      buffer,
+    }
+  }
+}
+
+/// Fails if pixels of `size` don't fit a texture at most `max` pixels
+/// wide and high, which the device would otherwise panic about.
+fn check_size([width, height]: [u32; 2], max: u32) -> Result<(), WindshieldError> {
+  if width > max || height > max {
+    return Err(WindshieldError::ImageTooLarge { width, height, max });
+  }
+  Ok(())
+}
+
+/// Bytes of a row of `width` pixels in a staging buffer.
+fn padded_row(width: u32) -> u32 {
+  (width * 4).div_ceil(COPY_BYTES_PER_ROW_ALIGNMENT) * COPY_BYTES_PER_ROW_ALIGNMENT
+}
+
+/// Stages the pixels of images on a thread of its own, so that copying
+/// a batch of large ones doesn't hold up the frame. On the web, which has
+/// no threads, and if the thread can't be started, right away.
+enum Stager {
+  #[cfg(not(target_arch = "wasm32"))]
+  Thread {
+    requests: Sender<Image>,
+    staged: Receiver<Staged>,
+    // Requested but not received yet
+    in_flight: usize,
+  },
+  Inline {
+    device: Arc<Device>,
+    staged: Vec<Staged>,
+  },
+}
+
+impl Stager {
+  fn new(device: &Arc<Device>) -> Self {
+    #[cfg(not(target_arch = "wasm32"))]
+    {
+      let (requests, received) = mpsc::channel::<Image>();
+      let (sender, staged) = mpsc::channel();
+      let thread_device = Arc::clone(device);
+      let result = std::thread::Builder::new()
+        .name("image uploads".to_string())
+        .spawn(move || {
+          for image in received {
+            // Only fails once the pipeline is gone
+            if sender.send(Staged::new(&thread_device, &image)).is_err() {
+              break;
+            }
+          }
+        });
+      match result {
+        Ok(_) => {
+          return Self::Thread {
+            requests,
+            staged,
+            in_flight: 0,
+          }
+        }
+        Err(err) => tracing::error!("unable to start image uploads: {}", err),
+      }
+    }
+    Self::Inline {
+      device: Arc::clone(device),
+      staged: Vec::new(),
+    }
+  }
+
+  fn request(&mut self, image: &Image) {
+    match self {
+      #[cfg(not(target_arch = "wasm32"))]
+      Self::Thread {
+        requests,
+        in_flight,
+        ..
+      } => {
+        if requests.send(image.clone()).is_ok() {
+          *in_flight += 1;
+        }
+      }
+      Self::Inline { device, staged } => staged.push(Staged::new(device, image)),
+    }
+  }
+
+  /// The images staged since the last call, waiting for all of those
+  /// requested if `wait`.
+  fn staged(&mut self, wait: bool) -> Vec<Staged> {
+    match self {
+      #[cfg(not(target_arch = "wasm32"))]
+      Self::Thread {
+        staged, in_flight, ..
+      } => {
+        let mut received = Vec::new();
+        while *in_flight > 0 {
+          let next = if wait {
+            staged.recv().ok()
+          } else {
+            staged.try_recv().ok()
+          };
+          let Some(next) = next else {
+            break;
+          };
+          *in_flight -= 1;
+          received.push(next);
+        }
+        received
+      }
+      Self::Inline { staged, .. } => std::mem::take(staged),
+    }
+  }
+}
+
+/// Textured quads cut to rounded rects, drawn with [`IMAGE_SHADER`].
+///
+/// Changed pixels are staged off the render thread and copied into their
+/// textures in a submission of their own before the frame, so an image
+/// keeps showing its last pixels until then, and a new one shows up a
+/// frame or two late.
+///
+/// [`IMAGE_SHADER`]: crate::pipeline::IMAGE_SHADER
+pub(crate) struct ImagePipeline {
+  pipeline: RenderPipeline,
+  screen: ScreenUniform,
+  layout: BindGroupLayout,
+  sampler: Sampler,
+  geometry: GeometryBuffer<ImageVertex>,
+  // By image, only those drawn in the last frame are kept
+  textures: HashMap<u64, Uploaded>,
+  stager: Stager,
+  // Images being staged
+  staging: HashSet<u64>,
+  // Versions of images staged without pixels to upload, like those too
+  // large, so they aren't staged again until they change
+  skipped: HashMap<u64, u64>,
+  // Image and indices of every quad by layer, for the frame being drawn
+  draws: Vec<Vec<(u64, Range<u32>)>>,
+}
+
+impl ImagePipeline {
+  /// Builds the pipeline from `shader`, usually
+  /// [`IMAGE_SHADER`](crate::pipeline::IMAGE_SHADER).
+  pub(crate) async fn new(
+    device: &Arc<Device>,
+    format: TextureFormat,
+    width: u32,
+    height: u32,
+    (name, source): (&str, &str),
+  ) -> Result<Self, WindshieldError> {
+    let shader = create_shader(device, name, source).await?;
+    let screen = ScreenUniform::new(device, width, height);
+    let layout = device.create_bind_group_layout(&BindGroupLayoutDescriptor {
+      label: Some("Image"),
+      entries: &[
+        BindGroupLayoutEntry {
+          binding: 0,
+          visibility: ShaderStages::FRAGMENT,
+          ty: BindingType::Texture {
+            sample_type: TextureSampleType::Float { filterable: true },
+            view_dimension: TextureViewDimension::D2,
+            multisampled: false,
+          },
+          count: None,
+        },
+        BindGroupLayoutEntry {
+          binding: 1,
+          visibility: ShaderStages::FRAGMENT,
+          ty: BindingType::Sampler(SamplerBindingType::Filtering),
+          count: None,
+        },
+      ],
+    });
+    let pipeline = PipelineBuilder::new("Image Pipeline", &shader)
+      .vertex::<ImageVertex>()
+      .bind_group_layout(screen.layout())
+      .bind_group_layout(&layout)
+      .build(device, format);
+    let sampler = device.create_sampler(&SamplerDescriptor {
+      label: Some("Image"),
+      address_mode_u: AddressMode::ClampToEdge,
+      address_mode_v: AddressMode::ClampToEdge,
+      mag_filter: FilterMode::Linear,
+      min_filter: FilterMode::Linear,
+      ..Default::default()
+    });
+
+    Ok(Self {
+      pipeline,
+      screen,
+      layout,
+      sampler,
+      geometry: GeometryBuffer::new(device, "Image Geometry"),
+      textures: HashMap::new(),
+      stager: Stager::new(device),
+      staging: HashSet::new(),
+      skipped: HashMap::new(),
+      draws: Vec::new(),
+    })
+  }
+
+  pub(crate) fn resize(&mut self, queue: &Queue, width: u32, height: u32) {
+    self.screen.resize(queue, width, height);
+  }
+
+  pub(crate) fn set_mirror(&mut self, queue: &Queue, mirror: Mirror) {
+    self.screen.set_mirror(queue, mirror);
+  }
+
+  pub(crate) fn set_high_contrast(&mut self, queue: &Queue, high_contrast: bool) {
+    self.screen.set_high_contrast(queue, high_contrast);
+  }
+
+  /// Whether images drawn are still being staged, to draw another frame
+  /// once they are.
+  pub(crate) fn uploading(&self) -> bool {
+    !self.staging.is_empty()
+  }
+
+  /// Stages the images drawn into `batch` that changed, submits copying
+  /// those staged by now and uploads their quads, emptying the batch for
+  /// the next frame. Waits for every image to be staged if `wait`, for
+  /// frames that have to be complete like screenshots.
+  pub(crate) fn prepare(
+    &mut self,
+    device: &Device,
+    queue: &Queue,
+    batch: &mut ImageBatch,
+    wait: bool,
+  ) {
+    let mut drawn = HashSet::new();
+    let mut vertices = Vec::new();
+    let mut indices = Vec::new();
+    self.draws.clear();
+    for layer in batch.layers.drain(..) {
+      let mut draws = Vec::new();
+      for (image, quad) in layer {
+        if drawn.insert(image.id) {
+          let version = image.pixels.lock().expect("image isn't poisoned").version;
+          let uploaded = self
+            .textures
+            .get(&image.id)
+            .map(|uploaded| uploaded.version);
+          let skipped = self.skipped.get(&image.id) == Some(&version);
+          if uploaded != Some(version) && !skipped && self.staging.insert(image.id) {
+            self.stager.request(&image);
+          }
+        }
+        let start = vertices.len() as u32;
+        let first = indices.len() as u32;
+        vertices.extend(quad);
+        indices.extend([0, 1, 2, 0, 2, 3].map(|index| start + index));
+        draws.push((image.id, first..indices.len() as u32));
+      }
+      self.draws.push(draws);
+    }
+    batch.layers.push(Vec::new());
+    // Images no longer drawn let go of their textures
+    self.textures.retain(|id, _| drawn.contains(id));
+    self.staging.retain(|id| drawn.contains(id));
+    self.skipped.retain(|id, _| drawn.contains(id));
+
+    let staged = self.stager.staged(wait);
+    let mut encoder = device.create_command_encoder(&CommandEncoderDescriptor {
+      label: Some("Image Upload Encoder"),
+    });
+    let mut copied = false;
+    for staged in staged {
+      if !self.staging.remove(&staged.id) {
+        continue;
+      }
+      let Some(buffer) = &staged.buffer else {
+        self.skipped.insert(staged.id, staged.version);
+        continue;
+      };
+      self.skipped.remove(&staged.id);
+      let uploaded = match self.textures.remove(&staged.id) {
+        Some(uploaded) if uploaded.size == staged.size => uploaded,
+        _ => self.create_texture(device, staged.size),
+      };
+      let [width, height] = staged.size;
+      encoder.copy_buffer_to_texture(
+        ImageCopyBuffer {
+          buffer,
+          layout: ImageDataLayout {
+            offset: 0,
+            bytes_per_row: std::num::NonZeroU32::new(padded_row(width)),
+            rows_per_image: None,
+          },
+        },
+        uploaded.texture.as_image_copy(),
+        Extent3d {
+          width,
+          height,
+          depth_or_array_layers: 1,
+        },
+      );
+      copied = true;
+      let uploaded = Uploaded {
+        version: staged.version,
+        ..uploaded
+      };
+      self.textures.insert(staged.id, uploaded);
+    }
+    if copied {
+      queue.submit(std::iter::once(encoder.finish()));
+    }
+    self.geometry.upload(device, queue, &vertices, &indices);
+  }
+
+  /// A texture for pixels of `size`, without any.
+  fn create_texture(&self, device: &Device, [width, height]: [u32; 2]) -> Uploaded {
+    let texture = device.create_texture(&TextureDescriptor {
+      label: Some("Image"),
+      size: Extent3d {
+        width,
+        height,
+        depth_or_array_layers: 1,
+      },
+      mip_level_count: 1,
+      sample_count: 1,
+      dimension: TextureDimension::D2,
+      // Sampled in linear like the other colors
+      format: TextureFormat::Rgba8UnormSrgb,
+      usage: TextureUsages::TEXTURE_BINDING | TextureUsages::COPY_DST,
+    });
+    let view = texture.create_view(&TextureViewDescriptor::default());
+    let bind_group = device.create_bind_group(&BindGroupDescriptor {
+      label: Some("Image"),
+      layout: &self.layout,
+      entries: &[
+        BindGroupEntry {
+          binding: 0,
+          resource: BindingResource::TextureView(&view),
+        },
+        BindGroupEntry {
+          binding: 1,
+          resource: BindingResource::Sampler(&self.sampler),
+        },
+      ],
+    });
+    Uploaded {
+      size: [width, height],
+      version: 0,
+      texture,
+      bind_group,
+    }
+  }
+
+  /// Draws the images of `layer`.
+  pub(crate) fn draw<'a>(
+    &'a self,
+    pass: &mut RenderPass<'a>,
+    layer: usize,
+    stats: &mut FrameStats,
+  ) {
+    let Some(draws) = self.draws.get(layer).filter(|draws| !draws.is_empty()) else {
+      return;
+    };
+    pass.set_pipeline(&self.pipeline);
+    pass.set_bind_group(0, self.screen.bind_group(), &[]);
+    for (id, indices) in draws {
+      let Some(uploaded) = self.textures.get(id) else {
+        continue;
+      };
+      pass.set_bind_group(1, &uploaded.bind_group, &[]);
+      stats.texture_binds += 1;
+      self.geometry.draw_range(pass, indices.clone(), stats);
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn contain_leaves_bars_and_cover_cuts_off() {
+    let rect = Rect::new(0.0, 0.0, 200.0, 100.0);
+    // Square in a two by one rect
+    let (placed, uv) = Fit::Contain.place([300, 300], rect);
+    assert_eq!(placed, Rect::new(50.0, 0.0, 100.0, 100.0));
+    assert_eq!(uv, [0.0, 0.0, 1.0, 1.0]);
+
+    let (placed, uv) = Fit::Cover.place([300, 300], rect);
+    assert_eq!(placed, rect);
+    assert_eq!(uv, [0.0, 0.25, 1.0, 0.75]);
+
+    // Sixteen by nine, wider than the rect
+    let (placed, _) = Fit::Contain.place([160, 90], Rect::new(0.0, 0.0, 100.0, 100.0));
+    assert_eq!(placed, Rect::new(0.0, 21.875, 100.0, 56.25));
+    assert_eq!(
+      Fit::Fill.place([160, 90], rect),
+      (rect, [0.0, 0.0, 1.0, 1.0])
+    );
+  }
+
+  #[test]
+  fn rejects_images_larger_than_a_texture() {
+    assert!(check_size([8192, 200], 8192).is_ok());
+    assert!(matches!(
+      check_size([10000, 200], 8192),
+      Err(WindshieldError::ImageTooLarge { max: 8192, .. })
+    ));
+    assert!(check_size([200, 2049], 2048).is_err());
+  }
+
+  #[test]
+  fn color_matrices_dim_tint_and_desaturate() {
+    let close = |a: [f32; 4], b: [f32; 4]| a.iter().zip(b).all(|(a, b)| (a - b).abs() < 1e-4);
+    let color = [0.8, 0.4, 0.2, 0.5];
+    assert_eq!(ColorMatrix::IDENTITY.apply(color), color);
+    assert_eq!(ColorMatrix::dim(0.5).apply(color), [0.4, 0.2, 0.1, 0.5]);
+    assert!(close(
+      ColorMatrix::desaturate(1.0).apply(color),
+      [0.4706, 0.4706, 0.4706, 0.5]
+    ));
+    assert_eq!(ColorMatrix::desaturate(0.0), ColorMatrix::IDENTITY);
+
+    let accent = [0.2, 0.6, 1.0, 0.8];
+    assert!(close(ColorMatrix::tint(accent).apply([1.0; 4]), accent));
+    assert_eq!(
+      ColorMatrix::tint(accent).apply([0.0, 0.0, 0.0, 1.0]),
+      [0.0, 0.0, 0.0, 0.8]
+    );
+
+    // Combined like applied one after the other
+    let combined = ColorMatrix::desaturate(0.5).then(ColorMatrix::dim(0.5));
+    let expected = ColorMatrix::dim(0.5).apply(ColorMatrix::desaturate(0.5).apply(color));
+    assert!(close(combined.apply(color), expected));
+    let offset = ColorMatrix([[0.0, 0.0, 0.0, 0.0, 0.25]; 4]);
+    assert_eq!(
+      offset.then(ColorMatrix::dim(2.0)).apply(color),
+      [0.5, 0.5, 0.5, 0.25]
+    );
+    // Clamped
+    assert_eq!(ColorMatrix::dim(4.0).apply(color), [1.0, 1.0, 0.8, 0.5]);
+  }
+
+  #[test]
+  fn images_count_their_changes() {
+    let image = Image::new();
+    assert_eq!(image.size(), None);
+    image.set(2, 1, vec![0; 8]);
+    let shared = image.clone();
+    shared.set(1, 1, vec![0; 4]);
+    assert_eq!(image.size(), Some([1, 1]));
+    assert_eq!(image.pixels.lock().unwrap().version, 2);
+  }
+
+  #[test]
+  fn staged_rows_are_aligned() {
+    assert_eq!(padded_row(1), 256);
+    assert_eq!(padded_row(64), 256);
+    assert_eq!(padded_row(65), 512);
+  }
+
+  #[test]
+  fn loading_in_background_fails_without_file() {
+    assert!(matches!(
+      Image::load_in_background("missing.png"),
+      Err(WindshieldError::ReadFile { .. })
+    ));
+  }
+}
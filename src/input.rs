@@ -0,0 +1,1034 @@
+use std::collections::hash_map::Entry;
+use std::collections::{HashMap, HashSet};
+use std::str::FromStr;
+use std::time::Duration;
+
+use instant::Instant;
+use serde::de::value::{self, StrDeserializer};
+use serde::Deserialize;
+use winit::event::{
+  ElementState, KeyboardInput, ModifiersState, MouseButton, Touch, TouchPhase, VirtualKeyCode,
+  WindowEvent,
+};
+
+use crate::input_map::{ButtonBinding, ButtonMapper, Timing};
+
+/// Which finger or mouse a [`PointerEvent`] belongs to.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum PointerId {
+  Mouse,
+  Touch(u64),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PointerPhase {
+  /// A finger touched down or the left mouse button was pressed.
+  Down,
+  /// The pointer moved, pressed or not.
+  Move,
+  Up,
+  /// The system took the touch away, e.g. for a gesture of its own.
+  Cancel,
+}
+
+/// Mouse and touch input unified, positions are in physical pixels like
+/// everything drawn.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PointerEvent {
+  pub id: PointerId,
+  pub phase: PointerPhase,
+  pub position: [f32; 2],
+}
+
+/// Input without the touchscreen, from rotary encoders and buttons on the
+/// steering wheel or dashboard. Navigation goes to the focused widget, see
+/// [`Node::action`](crate::layout::Node::action).
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Action {
+  /// E.g. turning an encoder clockwise.
+  Next,
+  Previous,
+  /// E.g. pressing an encoder.
+  Activate,
+  Back,
+  /// Toggles mirroring, like `M`.
+  Mirror,
+  /// Toggles high contrast, like `H`.
+  HighContrast,
+  /// Cycles the theme mode, like `N`.
+  Theme,
+  /// Switches between metric and imperial units, like `U`.
+  Units,
+  /// Turns the page of a carousel, wherever the focus is, like `PageDown`.
+  NextPage,
+  PreviousPage,
+  /// Steps the backlight, see [`Settings::backlight`](crate::Settings::backlight).
+  BrightnessUp,
+  BrightnessDown,
+  /// Starts or ends calibrating the keystone, like `K`.
+  Calibrate,
+  /// Toggles fullscreen, like `F11`.
+  Fullscreen,
+  /// Saves what's shown to a file, like `F12`, see
+  /// [`Snapshot`](crate::snapshot::Snapshot).
+  Snapshot,
+  /// Turns the display off or back on, like `P`, see
+  /// [`Power`](crate::power::Power).
+  Power,
+  /// Shows or hides the window listing the scene's widgets and editing
+  /// them, like `F10`. Built with the `inspector` feature only.
+  Inspector,
+  /// Shows or hides the screen listing every telemetry field with its
+  /// value, update rate, source and history, like `Ctrl+Shift+D`.
+  Diagnostics,
+  /// Shows or hides the latest log events, like `Ctrl+Shift+L`, see
+  /// [`Settings::log_buffer`](crate::Settings::log_buffer).
+  Logs,
+  /// Shows or hides the screen identifying the build and the GPU, like
+  /// `F1`.
+  About,
+  /// Switches the accessibility profile on or off, like `A`, see
+  /// [`Settings::accessibility`](crate::Settings::accessibility).
+  Accessibility,
+  /// Cycles through simulating kinds of color blindness, like
+  /// `Ctrl+Shift+C`, see
+  /// [`Settings::simulate_color_vision`](crate::Settings::simulate_color_vision).
+  ColorVision,
+}
+
+impl Action {
+  /// Whether the action moves through or uses widgets, rather than
+  /// changing how everything is shown.
+  pub fn is_navigation(self) -> bool {
+    matches!(
+      self,
+      Self::Next
+        | Self::Previous
+        | Self::Activate
+        | Self::Back
+        | Self::NextPage
+        | Self::PreviousPage
+    )
+  }
+
+  /// Whether holding a key down repeats the action, for those stepping
+  /// through something rather than toggling it.
+  pub fn repeats(self) -> bool {
+    matches!(
+      self,
+      Self::Next
+        | Self::Previous
+        | Self::NextPage
+        | Self::PreviousPage
+        | Self::BrightnessUp
+        | Self::BrightnessDown
+    )
+  }
+}
+
+/// What can be bound to an [`Action`] in the window: a key with the
+/// modifiers held, a mouse button other than the left one, a tap with
+/// several fingers or a gesture with one.
+///
+/// Written like `M`, `Ctrl+Shift+F1`, `MouseRight`, `Tap2` or `SwipeLeft`
+/// in [`Bindings`]. Keys are named like winit's `VirtualKeyCode`, e.g.
+/// `PageDown`, `Key1` or `Plus`. The left mouse button and single fingers
+/// go to the widgets first, gestures are only made of what none of them
+/// used, e.g. swiping across a label or the background.
+///
+/// Gamepads and other evdev devices are bound in the
+/// [`InputMap`](crate::input_map::InputMap) instead, e.g. `BTN_SOUTH`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Trigger {
+  Key {
+    key: VirtualKeyCode,
+    modifiers: ModifiersState,
+  },
+  Mouse(MouseButton),
+  /// A quick tap with this many fingers. A single one is a gesture.
+  Tap(u8),
+  /// Holding a finger or the mouse button still for [`LONG_PRESS`].
+  LongPress,
+  /// A quick stroke towards the left, like turning a page forward.
+  SwipeLeft,
+  SwipeRight,
+}
+
+impl Trigger {
+  pub fn key(key: VirtualKeyCode) -> Self {
+    Self::Key {
+      key,
+      modifiers: ModifiersState::empty(),
+    }
+  }
+
+  /// Whether it's a key or mouse button, which can be held and tapped
+  /// twice.
+  pub fn is_button(self) -> bool {
+    matches!(self, Self::Key { .. } | Self::Mouse(_))
+  }
+}
+
+impl FromStr for Trigger {
+  type Err = String;
+
+  fn from_str(name: &str) -> Result<Self, Self::Err> {
+    let mut parts: Vec<_> = name.split('+').map(str::trim).collect();
+    let last = parts.pop().unwrap_or_default();
+    let mut modifiers = ModifiersState::empty();
+    for part in parts {
+      modifiers |= match part.to_ascii_lowercase().as_str() {
+        "shift" => ModifiersState::SHIFT,
+        "ctrl" | "control" => ModifiersState::CTRL,
+        "alt" => ModifiersState::ALT,
+        "super" | "logo" | "meta" => ModifiersState::LOGO,
+        _ => return Err(format!("unknown modifier {:?} in {:?}", part, name)),
+      };
+    }
+    let trigger = match last {
+      "MouseRight" => Self::Mouse(MouseButton::Right),
+      "MouseMiddle" => Self::Mouse(MouseButton::Middle),
+      "Tap" => Self::Tap(1),
+      "LongPress" => Self::LongPress,
+      "SwipeLeft" => Self::SwipeLeft,
+      "SwipeRight" => Self::SwipeRight,
+      _ => match last.strip_prefix("Tap").map(str::parse) {
+        Some(Ok(fingers @ 1..=10)) => Self::Tap(fingers),
+        Some(_) => return Err(format!("taps need 1 to 10 fingers, not {:?}", name)),
+        None => {
+          let key = VirtualKeyCode::deserialize(StrDeserializer::<value::Error>::new(last))
+            .map_err(|_| format!("unknown key {:?} in {:?}", last, name))?;
+          return Ok(Self::Key { key, modifiers });
+        }
+      },
+    };
+    if modifiers.is_empty() {
+      Ok(trigger)
+    } else {
+      Err(format!("only keys take modifiers, not {:?}", name))
+    }
+  }
+}
+
+/// Which [`Trigger`]s in the window do which [`Action`]s, see
+/// [`Settings::bindings`](crate::Settings::bindings).
+///
+/// Keys and mouse buttons can also be held or tapped twice, told apart
+/// like the buttons of an [`InputMap`](crate::input_map::InputMap):
+///
+/// ```toml
+/// [bindings]
+/// PageDown = "next_page"
+/// "Ctrl+Plus" = "brightness_up"
+/// "Ctrl+Minus" = "brightness_down"
+/// MouseRight = "back"
+/// Tap3 = "calibrate"
+/// LongPress = "theme"
+///
+/// [bindings.long_press]
+/// K = "calibrate"
+///
+/// [bindings.double_tap]
+/// MouseRight = "snapshot"
+///
+/// [bindings.timing]
+/// long_press_ms = 800
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Bindings {
+  actions: HashMap<Trigger, Action>,
+  long_presses: HashMap<Trigger, Action>,
+  double_taps: HashMap<Trigger, Action>,
+  timing: Timing,
+}
+
+impl Bindings {
+  /// No bindings at all, not even the defaults.
+  pub fn empty() -> Self {
+    Self {
+      actions: HashMap::new(),
+      long_presses: HashMap::new(),
+      double_taps: HashMap::new(),
+      timing: Timing::default(),
+    }
+  }
+
+  /// Binds `trigger` to `action`, replacing what it was bound to.
+  pub fn bind(&mut self, trigger: Trigger, action: Action) {
+    self.actions.insert(trigger, action);
+  }
+
+  /// Binds holding the key or mouse button of `trigger` for
+  /// [`Timing::long_press_ms`] to `action`. Other triggers can't be held
+  /// and are ignored.
+  pub fn bind_long_press(&mut self, trigger: Trigger, action: Action) {
+    if trigger.is_button() {
+      self.long_presses.insert(trigger, action);
+    }
+  }
+
+  /// Binds pressing the key or mouse button of `trigger` twice within
+  /// [`Timing::double_tap_ms`] to `action`. Other triggers are ignored.
+  pub fn bind_double_tap(&mut self, trigger: Trigger, action: Action) {
+    if trigger.is_button() {
+      self.double_taps.insert(trigger, action);
+    }
+  }
+
+  /// How long keys and mouse buttons have to be held, and how quickly
+  /// tapped twice.
+  pub fn set_timing(&mut self, timing: Timing) {
+    self.timing = timing;
+  }
+
+  /// Unbinds `trigger` from everything, pressed, held and tapped twice.
+  pub fn unbind(&mut self, trigger: Trigger) {
+    self.actions.remove(&trigger);
+    self.long_presses.remove(&trigger);
+    self.double_taps.remove(&trigger);
+  }
+
+  /// What pressing `trigger` is bound to.
+  pub fn get(&self, trigger: Trigger) -> Option<Action> {
+    self.actions.get(&trigger).copied()
+  }
+
+  /// What the system repeating `trigger` while it's held does, the press
+  /// action unless that toggles something or holding does something else.
+  pub(crate) fn repeated(&self, trigger: Trigger) -> Option<Action> {
+    let waits = self.long_presses.contains_key(&trigger) || self.double_taps.contains_key(&trigger);
+    self
+      .get(trigger)
+      .filter(|action| action.repeats() && !waits)
+  }
+
+  /// Tells presses, long presses and double taps of the keys and mouse
+  /// buttons bound apart.
+  pub(crate) fn buttons(&self) -> ButtonMapper<Trigger> {
+    let mut buttons = HashMap::new();
+    for (trigger, action) in &self.actions {
+      if trigger.is_button() {
+        button(&mut buttons, *trigger).press = Some(*action);
+      }
+    }
+    for (trigger, action) in &self.long_presses {
+      button(&mut buttons, *trigger).long_press = Some(*action);
+    }
+    for (trigger, action) in &self.double_taps {
+      button(&mut buttons, *trigger).double_tap = Some(*action);
+    }
+    ButtonMapper::new(buttons.into_values().collect(), self.timing)
+  }
+}
+
+fn button(
+  buttons: &mut HashMap<Trigger, ButtonBinding<Trigger>>,
+  trigger: Trigger,
+) -> &mut ButtonBinding<Trigger> {
+  buttons
+    .entry(trigger)
+    .or_insert_with(|| ButtonBinding::new(vec![trigger]))
+}
+
+/// `M` mirror, `H` high contrast, `A` accessibility, `N` theme, `U` units,
+/// `K` calibrate,
+/// `F1` about, `F11` fullscreen, `F12` snapshot, `Ctrl+Shift+D` diagnostics,
+/// `Ctrl+Shift+L` logs, `Ctrl+Shift+C` color vision, and `PageDown` and `PageUp` or swiping to turn
+/// pages.
+impl Default for Bindings {
+  fn default() -> Self {
+    let mut bindings = Self::empty();
+    for (key, action) in [
+      (VirtualKeyCode::M, Action::Mirror),
+      (VirtualKeyCode::H, Action::HighContrast),
+      (VirtualKeyCode::A, Action::Accessibility),
+      (VirtualKeyCode::N, Action::Theme),
+      (VirtualKeyCode::U, Action::Units),
+      (VirtualKeyCode::K, Action::Calibrate),
+      (VirtualKeyCode::F11, Action::Fullscreen),
+      (VirtualKeyCode::F12, Action::Snapshot),
+      (VirtualKeyCode::P, Action::Power),
+      (VirtualKeyCode::F1, Action::About),
+      (VirtualKeyCode::F10, Action::Inspector),
+      (VirtualKeyCode::PageDown, Action::NextPage),
+      (VirtualKeyCode::PageUp, Action::PreviousPage),
+    ] {
+      bindings.bind(Trigger::key(key), action);
+    }
+    for (key, action) in [
+      (VirtualKeyCode::D, Action::Diagnostics),
+      (VirtualKeyCode::L, Action::Logs),
+      (VirtualKeyCode::C, Action::ColorVision),
+    ] {
+      let modifiers = ModifiersState::CTRL | ModifiersState::SHIFT;
+      bindings.bind(Trigger::Key { key, modifiers }, action);
+    }
+    bindings.bind(Trigger::SwipeLeft, Action::NextPage);
+    bindings.bind(Trigger::SwipeRight, Action::PreviousPage);
+    bindings
+  }
+}
+
+/// Longest a tap with several fingers may take from the first touch to the
+/// last release.
+const TAP_TIME: Duration = Duration::from_millis(300);
+
+/// A [`Trigger`] starting or ending, taps and gestures only start.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum TriggerEvent {
+  Press(Trigger),
+  /// The system repeating a key held down.
+  Repeat(Trigger),
+  Release(Trigger),
+}
+
+/// Turns window events into [`Trigger`]s, remembering the modifiers and
+/// keys held and the fingers down since the events don't say.
+#[derive(Default)]
+pub(crate) struct TriggerTracker {
+  modifiers: ModifiersState,
+  /// Keys held by the trigger pressing them made, so releasing them after
+  /// their modifiers still ends it.
+  keys: HashMap<VirtualKeyCode, Trigger>,
+  touches: HashSet<u64>,
+  // Most fingers down at once and when the first touched, since all were
+  // released last
+  fingers: u8,
+  touched: Option<Instant>,
+}
+
+impl TriggerTracker {
+  /// What `event` does to the triggers, `now` on the app's clock.
+  pub(crate) fn translate(&mut self, event: &WindowEvent, now: Instant) -> Option<TriggerEvent> {
+    match *event {
+      WindowEvent::ModifiersChanged(modifiers) => {
+        self.modifiers = modifiers;
+        None
+      }
+      WindowEvent::KeyboardInput {
+        input:
+          KeyboardInput {
+            state: ElementState::Pressed,
+            virtual_keycode: Some(key),
+            ..
+          },
+        ..
+      } => Some(match self.keys.entry(key) {
+        Entry::Occupied(held) => TriggerEvent::Repeat(*held.get()),
+        Entry::Vacant(entry) => TriggerEvent::Press(*entry.insert(Trigger::Key {
+          key,
+          modifiers: self.modifiers,
+        })),
+      }),
+      WindowEvent::KeyboardInput {
+        input:
+          KeyboardInput {
+            state: ElementState::Released,
+            virtual_keycode: Some(key),
+            ..
+          },
+        ..
+      } => self.keys.remove(&key).map(TriggerEvent::Release),
+      // Keys released elsewhere aren't reported
+      WindowEvent::Focused(false) => {
+        self.keys.clear();
+        None
+      }
+      WindowEvent::MouseInput { state, button, .. } if button != MouseButton::Left => {
+        Some(match state {
+          ElementState::Pressed => TriggerEvent::Press(Trigger::Mouse(button)),
+          ElementState::Released => TriggerEvent::Release(Trigger::Mouse(button)),
+        })
+      }
+      WindowEvent::Touch(Touch { phase, id, .. }) => {
+        self.touch(phase, id, now).map(TriggerEvent::Press)
+      }
+      _ => None,
+    }
+  }
+
+  fn touch(&mut self, phase: TouchPhase, id: u64, now: Instant) -> Option<Trigger> {
+    match phase {
+      TouchPhase::Started => {
+        if self.touches.is_empty() {
+          self.touched = Some(now);
+          self.fingers = 0;
+        }
+        self.touches.insert(id);
+        self.fingers = self.fingers.max(self.touches.len() as u8);
+        None
+      }
+      TouchPhase::Moved => None,
+      TouchPhase::Ended => {
+        self.touches.remove(&id);
+        let quick = self
+          .touched
+          .is_some_and(|touched| now.saturating_duration_since(touched) <= TAP_TIME);
+        (self.touches.is_empty() && self.fingers >= 2 && quick)
+          .then_some(Trigger::Tap(self.fingers))
+      }
+      // Whatever the system took the touch for, it wasn't a tap
+      TouchPhase::Cancelled => {
+        self.touches.remove(&id);
+        self.touched = None;
+        None
+      }
+    }
+  }
+}
+
+/// Longest a single finger may be held still before it's a
+/// [`LongPress`](Trigger::LongPress) rather than a tap.
+pub const LONG_PRESS: Duration = Duration::from_millis(600);
+/// Distance in physical pixels a pointer may move and still be held still
+/// or tap.
+const GESTURE_SLOP: f32 = 12.0;
+/// Distance in physical pixels a swipe has to cover sideways, at least
+/// twice as far as it moves up or down.
+const SWIPE_DISTANCE: f32 = 80.0;
+/// Longest a swipe may take from press to release.
+const SWIPE_TIME: Duration = Duration::from_millis(500);
+
+struct Press {
+  id: PointerId,
+  start: [f32; 2],
+  pressed: Instant,
+  /// Whether it moved too far to be a tap or held still any more.
+  moved: bool,
+  /// Whether it was held long enough to be a long press already.
+  held: bool,
+}
+
+/// Recognizes taps, long presses and swipes with a single pointer in the
+/// [`PointerEvent`]s the widgets didn't use.
+#[derive(Default)]
+pub(crate) struct GestureRecognizer {
+  down: HashSet<PointerId>,
+  press: Option<Press>,
+}
+
+impl GestureRecognizer {
+  /// Follows `event` at `now`, `used` telling whether a widget used it.
+  /// Gestures only start with presses nobody used, and none start while
+  /// several pointers are down.
+  pub(crate) fn pointer(
+    &mut self,
+    event: &PointerEvent,
+    used: bool,
+    now: Instant,
+  ) -> Option<Trigger> {
+    let tracked = self.press.as_ref().filter(|press| press.id == event.id);
+    match event.phase {
+      PointerPhase::Down => {
+        self.down.insert(event.id);
+        self.press = (self.down.len() == 1 && !used).then_some(Press {
+          id: event.id,
+          start: event.position,
+          pressed: now,
+          moved: false,
+          held: false,
+        });
+        None
+      }
+      PointerPhase::Move => {
+        if let Some(press) = self.press.as_mut().filter(|press| press.id == event.id) {
+          let [dx, dy] = [
+            event.position[0] - press.start[0],
+            event.position[1] - press.start[1],
+          ];
+          press.moved |= dx.hypot(dy) > GESTURE_SLOP;
+        }
+        None
+      }
+      PointerPhase::Up => {
+        self.down.remove(&event.id);
+        tracked?;
+        let press = self.press.take()?;
+        let [dx, dy] = [
+          event.position[0] - press.start[0],
+          event.position[1] - press.start[1],
+        ];
+        let elapsed = now.saturating_duration_since(press.pressed);
+        if press.held {
+          None
+        } else if dx.abs() >= SWIPE_DISTANCE && dx.abs() >= dy.abs() * 2.0 && elapsed <= SWIPE_TIME
+        {
+          Some(if dx < 0.0 {
+            Trigger::SwipeLeft
+          } else {
+            Trigger::SwipeRight
+          })
+        } else {
+          (!press.moved && dx.hypot(dy) <= GESTURE_SLOP && elapsed <= TAP_TIME)
+            .then_some(Trigger::Tap(1))
+        }
+      }
+      PointerPhase::Cancel => {
+        self.down.remove(&event.id);
+        if tracked.is_some() {
+          self.press = None;
+        }
+        None
+      }
+    }
+  }
+
+  /// A [`LongPress`](Trigger::LongPress) once the pointer has been held
+  /// still long enough, at most once per press.
+  pub(crate) fn poll(&mut self, now: Instant) -> Option<Trigger> {
+    let press = self.press.as_mut()?;
+    if press.moved || press.held || now < press.pressed + LONG_PRESS {
+      return None;
+    }
+    press.held = true;
+    Some(Trigger::LongPress)
+  }
+
+  /// When a pointer held still becomes a long press, to wake up for it.
+  pub(crate) fn due(&self) -> Option<Instant> {
+    self
+      .press
+      .as_ref()
+      .filter(|press| !press.moved && !press.held)
+      .map(|press| press.pressed + LONG_PRESS)
+  }
+}
+
+/// How the pointers of a [`Manipulation`] moved since the previous event.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Motion {
+  /// Where the pointers are now, the middle between them for two.
+  pub center: [f32; 2],
+  /// How far the center moved in physical pixels.
+  pub translation: [f32; 2],
+  /// How much further apart two pointers are, 1 for a single one.
+  pub scale: f32,
+  /// Radians two pointers turned clockwise around their center.
+  pub rotation: f32,
+}
+
+/// Recognizes dragging with one pointer and pinching and rotating with two,
+/// e.g. to move a map around. Further pointers are ignored until one of
+/// the first two is released.
+///
+/// Feed it every [`PointerEvent`] of the widget, it reports how the
+/// pointers it follows moved:
+///
+/// ```
+/// use windshield_rs::input::{Manipulation, PointerEvent, PointerId, PointerPhase};
+///
+/// let mut manipulation = Manipulation::new();
+/// let finger = |id, phase, position| PointerEvent {
+///   id: PointerId::Touch(id),
+///   phase,
+///   position,
+/// };
+/// manipulation.pointer(&finger(1, PointerPhase::Down, [100.0, 100.0]));
+/// manipulation.pointer(&finger(2, PointerPhase::Down, [200.0, 100.0]));
+/// let motion = manipulation
+///   .pointer(&finger(2, PointerPhase::Move, [300.0, 100.0]))
+///   .unwrap();
+/// assert_eq!(motion.scale, 2.0);
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct Manipulation {
+  pointers: Vec<(PointerId, [f32; 2])>,
+}
+
+impl Manipulation {
+  pub fn new() -> Self {
+    Self::default()
+  }
+
+  /// Whether any pointer is being followed.
+  pub fn is_active(&self) -> bool {
+    !self.pointers.is_empty()
+  }
+
+  pub fn pointers(&self) -> usize {
+    self.pointers.len()
+  }
+
+  pub fn follows(&self, id: PointerId) -> bool {
+    self.pointers.iter().any(|(pointer, _)| *pointer == id)
+  }
+
+  /// Follows `event`, returning how the pointers moved if they did.
+  /// Presses and releases only change which pointers are followed, so the
+  /// center doesn't jump as a second finger comes or goes.
+  pub fn pointer(&mut self, event: &PointerEvent) -> Option<Motion> {
+    let index = self.pointers.iter().position(|(id, _)| *id == event.id);
+    match (event.phase, index) {
+      (PointerPhase::Down, None) if self.pointers.len() < 2 => {
+        self.pointers.push((event.id, event.position));
+        None
+      }
+      (PointerPhase::Move, Some(index)) => {
+        let before = self.pointers.clone();
+        self.pointers[index].1 = event.position;
+        Some(motion(&before, &self.pointers))
+      }
+      (PointerPhase::Up | PointerPhase::Cancel, Some(index)) => {
+        self.pointers.remove(index);
+        None
+      }
+      _ => None,
+    }
+  }
+
+  /// Forgets every pointer, e.g. when something else took them over.
+  pub fn cancel(&mut self) {
+    self.pointers.clear();
+  }
+}
+
+fn motion(before: &[(PointerId, [f32; 2])], after: &[(PointerId, [f32; 2])]) -> Motion {
+  let center = |pointers: &[(PointerId, [f32; 2])]| {
+    let count = pointers.len() as f32;
+    let [x, y] = pointers
+      .iter()
+      .fold([0.0, 0.0], |[x, y], (_, [px, py])| [x + px, y + py]);
+    [x / count, y / count]
+  };
+  let (from, to) = (center(before), center(after));
+  let (mut scale, mut rotation) = (1.0, 0.0);
+  if let ([(_, a0), (_, b0)], [(_, a1), (_, b1)]) = (before, after) {
+    let (v0, v1) = (
+      [b0[0] - a0[0], b0[1] - a0[1]],
+      [b1[0] - a1[0], b1[1] - a1[1]],
+    );
+    let (l0, l1) = (v0[0].hypot(v0[1]), v1[0].hypot(v1[1]));
+    // Pointers on top of each other can't tell how they turned
+    if l0 > 1.0 && l1 > 1.0 {
+      scale = l1 / l0;
+      rotation = v1[1].atan2(v1[0]) - v0[1].atan2(v0[0]);
+      if rotation > std::f32::consts::PI {
+        rotation -= std::f32::consts::TAU;
+      } else if rotation < -std::f32::consts::PI {
+        rotation += std::f32::consts::TAU;
+      }
+    }
+  }
+  Motion {
+    center: to,
+    translation: [to[0] - from[0], to[1] - from[1]],
+    scale,
+    rotation,
+  }
+}
+
+/// Turns window events into [`PointerEvent`]s, remembering where the mouse
+/// is since button events don't say.
+#[derive(Default)]
+pub(crate) struct PointerTracker {
+  cursor: [f32; 2],
+}
+
+impl PointerTracker {
+  pub(crate) fn translate(&mut self, event: &WindowEvent) -> Option<PointerEvent> {
+    let (id, phase, position) = match *event {
+      WindowEvent::CursorMoved { position, .. } => {
+        self.cursor = [position.x as f32, position.y as f32];
+        (PointerId::Mouse, PointerPhase::Move, self.cursor)
+      }
+      WindowEvent::MouseInput {
+        state,
+        button: MouseButton::Left,
+        ..
+      } => {
+        let phase = match state {
+          ElementState::Pressed => PointerPhase::Down,
+          ElementState::Released => PointerPhase::Up,
+        };
+        (PointerId::Mouse, phase, self.cursor)
+      }
+      WindowEvent::Touch(Touch {
+        phase,
+        location,
+        id,
+        ..
+      }) => {
+        let phase = match phase {
+          TouchPhase::Started => PointerPhase::Down,
+          TouchPhase::Moved => PointerPhase::Move,
+          TouchPhase::Ended => PointerPhase::Up,
+          TouchPhase::Cancelled => PointerPhase::Cancel,
+        };
+        (
+          PointerId::Touch(id),
+          phase,
+          [location.x as f32, location.y as f32],
+        )
+      }
+      _ => return None,
+    };
+    Some(PointerEvent {
+      id,
+      phase,
+      position,
+    })
+  }
+}
+
+/// Decides which of several overlapping widgets a pointer belongs to, so
+/// that e.g. a slider on a swipeable page isn't dragged while the page is
+/// swiped.
+///
+/// Every widget a press reaches [`join`](Self::join)s the contest for that
+/// pointer, first joined first served. The first member to
+/// [`claim`](Self::claim) the pointer wins it, and the others have to be
+/// cancelled. If nobody claimed it by the time it is released, the member
+/// that joined first wins.
+#[derive(Clone, Debug)]
+pub struct GestureArena<K> {
+  contests: HashMap<PointerId, Contest<K>>,
+}
+
+#[derive(Clone, Debug)]
+struct Contest<K> {
+  members: Vec<K>,
+  winner: Option<K>,
+}
+
+impl<K> Default for GestureArena<K> {
+  fn default() -> Self {
+    Self {
+      contests: HashMap::new(),
+    }
+  }
+}
+
+impl<K: Copy + PartialEq> GestureArena<K> {
+  pub fn new() -> Self {
+    Self::default()
+  }
+
+  pub fn join(&mut self, id: PointerId, member: K) {
+    let contest = self.contests.entry(id).or_insert_with(|| Contest {
+      members: Vec::new(),
+      winner: None,
+    });
+    if contest.winner.is_none() && !contest.members.contains(&member) {
+      contest.members.push(member);
+    }
+  }
+
+  /// Whether anyone is contesting the pointer.
+  pub fn is_open(&self, id: PointerId) -> bool {
+    self.contests.contains_key(&id)
+  }
+
+  /// Who events of the pointer go to: the winner once there is one, every
+  /// member until then.
+  pub fn members(&self, id: PointerId) -> Vec<K> {
+    match self.contests.get(&id) {
+      Some(Contest {
+        winner: Some(winner),
+        ..
+      }) => vec![*winner],
+      Some(contest) => contest.members.clone(),
+      None => Vec::new(),
+    }
+  }
+
+  pub fn winner(&self, id: PointerId) -> Option<K> {
+    self.contests.get(&id)?.winner
+  }
+
+  /// Gives the pointer to `member` unless it already has a winner,
+  /// returning the members that lost it.
+  pub fn claim(&mut self, id: PointerId, member: K) -> Vec<K> {
+    let Some(contest) = self.contests.get_mut(&id) else {
+      return Vec::new();
+    };
+    if contest.winner.is_some() || !contest.members.contains(&member) {
+      return Vec::new();
+    }
+    contest.winner = Some(member);
+    contest
+      .members
+      .drain(..)
+      .filter(|other| *other != member)
+      .collect()
+  }
+
+  /// Gives an unclaimed pointer to the member that joined first, returning
+  /// the members that lost it. Called when the pointer is released.
+  pub fn resolve(&mut self, id: PointerId) -> Vec<K> {
+    match self
+      .contests
+      .get(&id)
+      .map(|contest| contest.members.first())
+    {
+      Some(Some(first)) => {
+        let first = *first;
+        self.claim(id, first)
+      }
+      _ => Vec::new(),
+    }
+  }
+
+  /// Ends the contest, e.g. once the pointer is up.
+  pub fn close(&mut self, id: PointerId) {
+    self.contests.remove(&id);
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  const FINGER: PointerId = PointerId::Touch(1);
+
+  #[test]
+  fn parses_triggers() {
+    let trigger = |name: &str| name.parse::<Trigger>();
+    assert_eq!(trigger("M"), Ok(Trigger::key(VirtualKeyCode::M)));
+    assert_eq!(
+      trigger("Ctrl + Shift+F1"),
+      Ok(Trigger::Key {
+        key: VirtualKeyCode::F1,
+        modifiers: ModifiersState::CTRL | ModifiersState::SHIFT,
+      })
+    );
+    assert_eq!(
+      trigger("super+PageDown"),
+      Ok(Trigger::Key {
+        key: VirtualKeyCode::PageDown,
+        modifiers: ModifiersState::LOGO,
+      })
+    );
+    assert_eq!(
+      trigger("MouseRight"),
+      Ok(Trigger::Mouse(MouseButton::Right))
+    );
+    assert_eq!(trigger("Tap"), Ok(Trigger::Tap(1)));
+    assert_eq!(trigger("Tap3"), Ok(Trigger::Tap(3)));
+    assert_eq!(trigger("LongPress"), Ok(Trigger::LongPress));
+    assert_eq!(trigger("SwipeLeft"), Ok(Trigger::SwipeLeft));
+  }
+
+  #[test]
+  fn rejects_invalid_triggers() {
+    for name in ["Tap0", "Tap11", "Hyper+M", "Ctrl+Tap2", "Escpe", ""] {
+      assert!(name.parse::<Trigger>().is_err(), "{:?}", name);
+    }
+  }
+
+  #[test]
+  fn only_stepping_actions_repeat() {
+    let mut bindings = Bindings::default();
+    bindings.bind(Trigger::key(VirtualKeyCode::Down), Action::Next);
+    assert_eq!(
+      bindings.repeated(Trigger::key(VirtualKeyCode::Down)),
+      Some(Action::Next)
+    );
+    assert_eq!(bindings.repeated(Trigger::key(VirtualKeyCode::M)), None);
+    // Holding it does something else than pressing it repeatedly
+    bindings.bind_long_press(Trigger::key(VirtualKeyCode::Down), Action::Back);
+    assert_eq!(bindings.repeated(Trigger::key(VirtualKeyCode::Down)), None);
+  }
+
+  #[test]
+  fn keys_are_held_and_tapped_twice() {
+    let k = Trigger::key(VirtualKeyCode::K);
+    let mut bindings = Bindings::default();
+    bindings.bind_long_press(k, Action::Calibrate);
+    bindings.bind_double_tap(Trigger::SwipeLeft, Action::Theme);
+    let mut buttons = bindings.buttons();
+    let start = Instant::now();
+    // M doesn't wait, nothing else is bound to it
+    assert_eq!(
+      buttons.press(Trigger::key(VirtualKeyCode::M), start),
+      [Action::Mirror]
+    );
+    assert!(buttons.press(k, start).is_empty());
+    let held = Duration::from_millis(Timing::default().long_press_ms);
+    assert_eq!(buttons.poll(start + held), [Action::Calibrate]);
+    // Gestures can't be held or tapped twice
+    assert!(bindings.double_taps.is_empty());
+  }
+
+  #[test]
+  fn pinches_and_rotates_around_the_center() {
+    let finger = |id, phase, position| PointerEvent {
+      id: PointerId::Touch(id),
+      phase,
+      position,
+    };
+    let mut manipulation = Manipulation::new();
+    manipulation.pointer(&finger(1, PointerPhase::Down, [0.0, 0.0]));
+    let drag = manipulation
+      .pointer(&finger(1, PointerPhase::Move, [10.0, 0.0]))
+      .unwrap();
+    assert_eq!(drag.translation, [10.0, 0.0]);
+    assert_eq!(drag.scale, 1.0);
+
+    manipulation.pointer(&finger(2, PointerPhase::Down, [10.0, 100.0]));
+    // A third finger is ignored
+    manipulation.pointer(&finger(3, PointerPhase::Down, [50.0, 50.0]));
+    assert_eq!(manipulation.pointers(), 2);
+    // Turned a quarter clockwise around the first finger
+    let turn = manipulation
+      .pointer(&finger(2, PointerPhase::Move, [-90.0, 0.0]))
+      .unwrap();
+    assert_eq!(turn.center, [-40.0, 0.0]);
+    assert!((turn.rotation - std::f32::consts::FRAC_PI_2).abs() < 1e-6);
+    assert!((turn.scale - 1.0).abs() < 1e-6);
+
+    manipulation.pointer(&finger(1, PointerPhase::Up, [10.0, 0.0]));
+    assert!(manipulation
+      .pointer(&finger(3, PointerPhase::Move, [0.0, 0.0]))
+      .is_none());
+    manipulation.pointer(&finger(2, PointerPhase::Cancel, [-90.0, 0.0]));
+    assert!(!manipulation.is_active());
+  }
+
+  #[test]
+  fn first_claim_wins_the_pointer() {
+    let mut arena = GestureArena::new();
+    for member in ["page", "slider", "label"] {
+      arena.join(FINGER, member);
+    }
+    assert!(arena.is_open(FINGER));
+    assert_eq!(arena.members(FINGER), ["page", "slider", "label"]);
+    assert_eq!(arena.winner(FINGER), None);
+
+    assert_eq!(arena.claim(FINGER, "slider"), ["page", "label"]);
+    assert_eq!(arena.winner(FINGER), Some("slider"));
+    assert_eq!(arena.members(FINGER), ["slider"]);
+    // Once won, later claims and members are left out
+    assert!(arena.claim(FINGER, "page").is_empty());
+    arena.join(FINGER, "gauge");
+    assert_eq!(arena.members(FINGER), ["slider"]);
+  }
+
+  #[test]
+  fn unclaimed_pointer_goes_to_the_first_member() {
+    let mut arena = GestureArena::new();
+    arena.join(FINGER, 1);
+    arena.join(FINGER, 2);
+    arena.join(FINGER, 1);
+    assert_eq!(arena.resolve(FINGER), [2]);
+    assert_eq!(arena.winner(FINGER), Some(1));
+
+    arena.close(FINGER);
+    assert!(!arena.is_open(FINGER));
+    assert!(arena.members(FINGER).is_empty());
+    assert!(arena.resolve(FINGER).is_empty());
+  }
+
+  #[test]
+  fn pointers_are_contested_separately() {
+    let mut arena = GestureArena::new();
+    arena.join(FINGER, 'a');
+    arena.join(PointerId::Mouse, 'b');
+    // Only members may claim a pointer
+    assert!(arena.claim(FINGER, 'b').is_empty());
+    assert_eq!(arena.winner(FINGER), None);
+    assert!(arena.claim(PointerId::Mouse, 'b').is_empty());
+    assert_eq!(arena.winner(PointerId::Mouse), Some('b'));
+    assert_eq!(arena.members(FINGER), ['a']);
+  }
+}
@@ -0,0 +1,602 @@
+use std::collections::hash_map::Entry;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use instant::Instant;
+use serde::Deserialize;
+
+use crate::error::WindshieldError;
+use crate::input::Action;
+#[cfg(feature = "input-evdev")]
+use crate::wake::Waker;
+
+/// Which input devices trigger which [`Action`]s, read from a TOML file:
+///
+/// ```toml
+/// [[device]]
+/// path = "/dev/input/by-path/platform-rotary@0-event"
+/// encoder = "REL_DIAL"
+///
+/// [[device]]
+/// path = "/dev/input/by-path/platform-matrix-keypad-event"
+///
+/// [device.buttons]
+/// KEY_ENTER = "activate"
+/// KEY_UP = "previous"
+/// KEY_DOWN = "next"
+/// "KEY_LEFTCTRL+KEY_M" = "mirror"
+///
+/// [device.long_press]
+/// KEY_ENTER = "back"
+///
+/// [device.double_tap]
+/// KEY_ENTER = "theme"
+/// ```
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct InputMap {
+  #[serde(default, rename = "device")]
+  pub devices: Vec<InputDevice>,
+  #[serde(default)]
+  pub timing: Timing,
+}
+
+/// A Linux input device, like a rotary encoder or the button matrix of a
+/// steering wheel.
+///
+/// Buttons are named by their evdev name, like `KEY_ENTER` or `BTN_0`.
+/// Chords are named by their buttons joined with `+`, the last one
+/// triggering the action while the others are held.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct InputDevice {
+  pub path: PathBuf,
+  /// Relative axis a rotary encoder reports turns on, like `REL_DIAL`.
+  /// Turning it clockwise is [`Action::Next`].
+  pub encoder: Option<String>,
+  /// Steps the encoder has to turn per action, for encoders reporting
+  /// several per detent.
+  #[serde(default = "steps")]
+  pub steps: u32,
+  /// Actions of buttons and chords when pressed.
+  #[serde(default)]
+  pub buttons: HashMap<String, Action>,
+  /// Actions of buttons and chords held for [`Timing::long_press_ms`].
+  #[serde(default)]
+  pub long_press: HashMap<String, Action>,
+  /// Actions of buttons and chords pressed twice within
+  /// [`Timing::double_tap_ms`].
+  #[serde(default)]
+  pub double_tap: HashMap<String, Action>,
+}
+
+fn steps() -> u32 {
+  1
+}
+
+/// How long presses are told apart.
+///
+/// Buttons with a long press or double tap action only trigger their
+/// press action once it's clear they weren't held or tapped again, the
+/// others trigger it right away.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Timing {
+  pub long_press_ms: u64,
+  pub double_tap_ms: u64,
+}
+
+impl Default for Timing {
+  fn default() -> Self {
+    Self {
+      long_press_ms: 500,
+      double_tap_ms: 300,
+    }
+  }
+}
+
+impl InputMap {
+  pub fn load(path: impl AsRef<Path>) -> Result<Self, WindshieldError> {
+    let path = path.as_ref();
+    let source = std::fs::read_to_string(path).map_err(|source| WindshieldError::ReadFile {
+      path: path.to_path_buf(),
+      source,
+    })?;
+    toml::from_str(&source).map_err(|err| WindshieldError::InvalidConfig {
+      path: path.to_path_buf(),
+      message: err.to_string(),
+    })
+  }
+}
+
+impl InputDevice {
+  /// The buttons and chords as [`ButtonMapper`] bindings, with names
+  /// turned into buttons by `parse`.
+  pub fn bindings<K, E>(
+    &self,
+    mut parse: impl FnMut(&str) -> Result<K, E>,
+  ) -> Result<Vec<ButtonBinding<K>>, E> {
+    let mut bindings: HashMap<&str, ButtonBinding<K>> = HashMap::new();
+    let kinds = [
+      (&self.buttons, Gesture::Press),
+      (&self.long_press, Gesture::LongPress),
+      (&self.double_tap, Gesture::DoubleTap),
+    ];
+    for (actions, gesture) in kinds {
+      for (name, action) in actions {
+        let binding = match bindings.entry(name.as_str()) {
+          Entry::Occupied(entry) => entry.into_mut(),
+          Entry::Vacant(entry) => {
+            let buttons = name
+              .split('+')
+              .map(|button| parse(button.trim()))
+              .collect::<Result<_, _>>()?;
+            entry.insert(ButtonBinding::new(buttons))
+          }
+        };
+        match gesture {
+          Gesture::Press => binding.press = Some(*action),
+          Gesture::LongPress => binding.long_press = Some(*action),
+          Gesture::DoubleTap => binding.double_tap = Some(*action),
+        }
+      }
+    }
+    Ok(bindings.into_values().collect())
+  }
+}
+
+#[derive(Clone, Copy)]
+enum Gesture {
+  Press,
+  LongPress,
+  DoubleTap,
+}
+
+/// What a button, or a chord of them, does.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ButtonBinding<K> {
+  /// Held buttons followed by the one triggering the binding.
+  pub buttons: Vec<K>,
+  pub press: Option<Action>,
+  pub long_press: Option<Action>,
+  pub double_tap: Option<Action>,
+}
+
+impl<K> ButtonBinding<K> {
+  pub fn new(buttons: Vec<K>) -> Self {
+    Self {
+      buttons,
+      press: None,
+      long_press: None,
+      double_tap: None,
+    }
+  }
+
+  fn trigger(&self) -> Option<&K> {
+    self.buttons.last()
+  }
+
+  /// Whether pressing the trigger has to wait to tell what it was.
+  fn waits(&self) -> bool {
+    self.long_press.is_some() || self.double_tap.is_some()
+  }
+}
+
+/// Turns button presses and releases into actions, telling chords, long
+/// presses and double taps apart.
+///
+/// Times are passed in so it can be driven from anywhere; call
+/// [`poll`](Self::poll) by [`deadline`](Self::deadline) for long presses
+/// and single taps to be noticed without another event.
+pub struct ButtonMapper<K> {
+  bindings: Vec<ButtonBinding<K>>,
+  long_press: Duration,
+  double_tap: Duration,
+  held: Vec<K>,
+  pending: Option<Pending>,
+}
+
+/// A press of a binding that waits, see [`ButtonBinding::waits`].
+struct Pending {
+  binding: usize,
+  pressed: Instant,
+  // Set once released, while waiting whether it's tapped again
+  released: Option<Instant>,
+}
+
+impl<K: Copy + Eq> ButtonMapper<K> {
+  pub fn new(bindings: Vec<ButtonBinding<K>>, timing: Timing) -> Self {
+    Self {
+      bindings,
+      long_press: Duration::from_millis(timing.long_press_ms),
+      double_tap: Duration::from_millis(timing.double_tap_ms),
+      held: Vec::new(),
+      pending: None,
+    }
+  }
+
+  pub fn press(&mut self, button: K, now: Instant) -> Vec<Action> {
+    let mut actions = self.poll(now);
+    if !self.held.contains(&button) {
+      self.held.push(button);
+    }
+    // The chord with the most buttons held wins over the buttons alone
+    let binding = self
+      .bindings
+      .iter()
+      .enumerate()
+      .filter(|(_, binding)| binding.trigger() == Some(&button))
+      .filter(|(_, binding)| binding.buttons.iter().all(|held| self.held.contains(held)))
+      .max_by_key(|(_, binding)| binding.buttons.len())
+      .map(|(index, _)| index);
+    let Some(index) = binding else {
+      return actions;
+    };
+
+    match self.pending.take() {
+      Some(pending) if pending.binding == index && pending.released.is_some() => {
+        actions.extend(self.bindings[index].double_tap);
+        return actions;
+      }
+      // Another button interrupts a tap waiting for a second one
+      Some(Pending {
+        binding,
+        released: Some(_),
+        ..
+      }) => actions.extend(self.bindings[binding].press),
+      // A button still held keeps waiting for its long press
+      Some(pending) => self.pending = Some(pending),
+      None => {}
+    }
+
+    let binding = &self.bindings[index];
+    if binding.waits() && self.pending.is_none() {
+      self.pending = Some(Pending {
+        binding: index,
+        pressed: now,
+        released: None,
+      });
+    } else {
+      actions.extend(binding.press);
+    }
+    actions
+  }
+
+  pub fn release(&mut self, button: K, now: Instant) -> Vec<Action> {
+    let mut actions = self.poll(now);
+    self.held.retain(|held| *held != button);
+    let Some(pending) = &mut self.pending else {
+      return actions;
+    };
+    let binding = &self.bindings[pending.binding];
+    if pending.released.is_some() || binding.trigger() != Some(&button) {
+      return actions;
+    }
+    if binding.double_tap.is_some() {
+      pending.released = Some(now);
+    } else {
+      actions.extend(binding.press);
+      self.pending = None;
+    }
+    actions
+  }
+
+  /// Triggers long presses held long enough and taps that weren't
+  /// followed by a second one in time.
+  pub fn poll(&mut self, now: Instant) -> Vec<Action> {
+    let Some(deadline) = self.deadline() else {
+      return Vec::new();
+    };
+    if now < deadline {
+      return Vec::new();
+    }
+    let Some(pending) = self.pending.take() else {
+      return Vec::new();
+    };
+    let binding = &self.bindings[pending.binding];
+    let action = match pending.released {
+      Some(_) => binding.press,
+      None => binding.long_press,
+    };
+    action.into_iter().collect()
+  }
+
+  /// When [`poll`](Self::poll) may trigger something next.
+  pub fn deadline(&self) -> Option<Instant> {
+    let pending = self.pending.as_ref()?;
+    match pending.released {
+      Some(released) => Some(released + self.double_tap),
+      None if self.bindings[pending.binding].long_press.is_some() => {
+        Some(pending.pressed + self.long_press)
+      }
+      // Only waiting for the release
+      None => None,
+    }
+  }
+}
+
+/// Reads the devices of an [`InputMap`] in the background, turning their
+/// events into actions.
+#[cfg(feature = "input-evdev")]
+pub struct EvdevInput {
+  actions: std::sync::mpsc::Receiver<Action>,
+}
+
+#[cfg(feature = "input-evdev")]
+impl EvdevInput {
+  /// Opens every device of `map`, failing if one can't be opened or names
+  /// an axis or key evdev doesn't know.
+  pub fn open(map: &InputMap) -> std::io::Result<Self> {
+    Self::open_waking(map, Waker::none())
+  }
+
+  /// Like [`open`](Self::open), waking `waker` for every action.
+  pub(crate) fn open_waking(map: &InputMap, waker: Waker) -> std::io::Result<Self> {
+    let (sender, actions) = std::sync::mpsc::channel();
+    let sender = WakingSender { sender, waker };
+    for config in &map.devices {
+      let reader = DeviceReader::open(config)?;
+      let mapper = ButtonMapper::new(config.bindings(|name| parse(name, "key"))?, map.timing);
+      let (buttons, presses) = std::sync::mpsc::channel();
+      let turns = sender.clone();
+      std::thread::Builder::new()
+        .name("evdev input".to_string())
+        .spawn(move || reader.run(turns, buttons))?;
+      let sender = sender.clone();
+      std::thread::Builder::new()
+        .name("evdev buttons".to_string())
+        .spawn(move || map_buttons(mapper, presses, sender))?;
+    }
+    Ok(Self { actions })
+  }
+
+  /// Actions triggered since the last call, in order.
+  pub fn actions(&self) -> Vec<Action> {
+    self.actions.try_iter().collect()
+  }
+}
+
+/// Sends actions to the event loop, waking it up for them.
+#[cfg(feature = "input-evdev")]
+#[derive(Clone)]
+struct WakingSender {
+  sender: std::sync::mpsc::Sender<Action>,
+  waker: Waker,
+}
+
+#[cfg(feature = "input-evdev")]
+impl WakingSender {
+  /// Fails once the receiver is gone.
+  fn send(&self, action: Action) -> Result<(), std::sync::mpsc::SendError<Action>> {
+    self.sender.send(action)?;
+    self.waker.wake();
+    Ok(())
+  }
+}
+
+/// A button pressed (`true`) or released, and when.
+#[cfg(feature = "input-evdev")]
+type ButtonEvent = (evdev::Key, bool, Instant);
+
+/// Feeds button events to `mapper` until either side hangs up, waking up
+/// for its deadlines in between.
+#[cfg(feature = "input-evdev")]
+fn map_buttons(
+  mut mapper: ButtonMapper<evdev::Key>,
+  events: std::sync::mpsc::Receiver<ButtonEvent>,
+  sender: WakingSender,
+) {
+  use std::sync::mpsc::RecvTimeoutError;
+
+  loop {
+    let event = match mapper.deadline() {
+      Some(deadline) => events.recv_timeout(deadline.saturating_duration_since(Instant::now())),
+      None => events.recv().map_err(|_| RecvTimeoutError::Disconnected),
+    };
+    let actions = match event {
+      Ok((key, true, time)) => mapper.press(key, time),
+      Ok((key, false, time)) => mapper.release(key, time),
+      Err(RecvTimeoutError::Timeout) => mapper.poll(Instant::now()),
+      Err(RecvTimeoutError::Disconnected) => return,
+    };
+    for action in actions {
+      if sender.send(action).is_err() {
+        return;
+      }
+    }
+  }
+}
+
+#[cfg(feature = "input-evdev")]
+struct DeviceReader {
+  path: PathBuf,
+  device: evdev::Device,
+  encoder: Option<evdev::RelativeAxisType>,
+  steps: i32,
+}
+
+#[cfg(feature = "input-evdev")]
+impl DeviceReader {
+  fn open(config: &InputDevice) -> std::io::Result<Self> {
+    let encoder = config
+      .encoder
+      .as_deref()
+      .map(|name| parse(name, "axis"))
+      .transpose()?;
+    Ok(Self {
+      path: config.path.clone(),
+      device: evdev::Device::open(&config.path)?,
+      encoder,
+      steps: config.steps.clamp(1, i32::MAX as u32) as i32,
+    })
+  }
+
+  /// Reads events until the device goes away or nobody listens anymore.
+  /// Turns are sent as actions right away, buttons to be mapped.
+  fn run(mut self, sender: WakingSender, buttons: std::sync::mpsc::Sender<ButtonEvent>) {
+    use evdev::InputEventKind;
+
+    // Encoder steps not yet turned into an action
+    let mut turned = 0;
+    loop {
+      let events = match self.device.fetch_events() {
+        Ok(events) => events,
+        Err(err) => {
+          tracing::warn!("unable to read {}: {}", self.path.display(), err);
+          return;
+        }
+      };
+      let mut actions = Vec::new();
+      let mut presses = Vec::new();
+      for event in events {
+        match event.kind() {
+          // Ignores key repeats, holding is told apart by the mapper
+          InputEventKind::Key(key) if event.value() != 2 => {
+            presses.push((key, event.value() == 1, Instant::now()));
+          }
+          InputEventKind::RelAxis(axis) if Some(axis) == self.encoder => {
+            turned += event.value();
+            while turned >= self.steps {
+              turned -= self.steps;
+              actions.push(Action::Next);
+            }
+            while turned <= -self.steps {
+              turned += self.steps;
+              actions.push(Action::Previous);
+            }
+          }
+          _ => {}
+        }
+      }
+      for action in actions {
+        if sender.send(action).is_err() {
+          return;
+        }
+      }
+      for press in presses {
+        if buttons.send(press).is_err() {
+          return;
+        }
+      }
+    }
+  }
+}
+
+#[cfg(feature = "input-evdev")]
+fn parse<T: std::str::FromStr>(name: &str, kind: &str) -> std::io::Result<T> {
+  name.parse().map_err(|_| {
+    std::io::Error::new(
+      std::io::ErrorKind::InvalidInput,
+      format!("unknown evdev {} {:?}", kind, name),
+    )
+  })
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  const TIMING: Timing = Timing {
+    long_press_ms: 500,
+    double_tap_ms: 300,
+  };
+
+  fn ms(start: Instant, ms: u64) -> Instant {
+    start + Duration::from_millis(ms)
+  }
+
+  fn mapper(toml: &str) -> ButtonMapper<&'static str> {
+    let map: InputMap = toml::from_str(toml).unwrap();
+    let bindings = map.devices[0]
+      .bindings(|name| {
+        ["ENTER", "UP", "CTRL"]
+          .into_iter()
+          .find(|button| *button == name)
+          .ok_or(name.to_string())
+      })
+      .unwrap();
+    ButtonMapper::new(bindings, map.timing)
+  }
+
+  #[test]
+  fn presses_without_other_gestures_trigger_right_away() {
+    let mut mapper = mapper(
+      r#"
+        [[device]]
+        path = "/dev/input/event0"
+        buttons = { UP = "previous", "CTRL+UP" = "mirror" }
+      "#,
+    );
+    let now = Instant::now();
+    assert_eq!(mapper.press("UP", now), [Action::Previous]);
+    assert!(mapper.release("UP", now).is_empty());
+    // The chord wins over the button alone
+    assert!(mapper.press("CTRL", now).is_empty());
+    assert_eq!(mapper.press("UP", now), [Action::Mirror]);
+    assert_eq!(mapper.deadline(), None);
+  }
+
+  #[test]
+  fn tells_presses_long_presses_and_double_taps_apart() {
+    let mut mapper = mapper(
+      r#"
+        [[device]]
+        path = "/dev/input/event0"
+        buttons = { ENTER = "activate", UP = "previous" }
+        long_press = { ENTER = "back" }
+        double_tap = { ENTER = "theme" }
+      "#,
+    );
+    let start = Instant::now();
+    // Pressed once, only clear once no second press followed
+    assert!(mapper.press("ENTER", start).is_empty());
+    assert!(mapper.release("ENTER", ms(start, 100)).is_empty());
+    assert_eq!(mapper.deadline(), Some(ms(start, 400)));
+    assert!(mapper.poll(ms(start, 399)).is_empty());
+    assert_eq!(mapper.poll(ms(start, 400)), [Action::Activate]);
+
+    // Pressed twice
+    let start = ms(start, 1000);
+    mapper.press("ENTER", start);
+    mapper.release("ENTER", ms(start, 50));
+    assert_eq!(mapper.press("ENTER", ms(start, 200)), [Action::Theme]);
+    mapper.release("ENTER", ms(start, 250));
+    assert_eq!(mapper.deadline(), None);
+
+    // Held
+    let start = ms(start, 1000);
+    mapper.press("ENTER", start);
+    assert_eq!(mapper.deadline(), Some(ms(start, TIMING.long_press_ms)));
+    assert_eq!(mapper.poll(ms(start, 600)), [Action::Back]);
+    assert!(mapper.release("ENTER", ms(start, 700)).is_empty());
+
+    // Another button cuts the wait for a second press short
+    let start = ms(start, 1000);
+    mapper.press("ENTER", start);
+    mapper.release("ENTER", ms(start, 50));
+    assert_eq!(
+      mapper.press("UP", ms(start, 100)),
+      [Action::Activate, Action::Previous]
+    );
+  }
+
+  #[test]
+  fn rejects_unknown_buttons() {
+    let map: InputMap = toml::from_str(
+      r#"
+        [[device]]
+        path = "/dev/input/event0"
+        buttons = { "CTRL+DOWN" = "next" }
+      "#,
+    )
+    .unwrap();
+    assert_eq!(
+      map.devices[0].bindings(|name| match name {
+        "CTRL" => Ok(0),
+        _ => Err(name.to_string()),
+      }),
+      Err("DOWN".to_string())
+    );
+  }
+}
@@ -0,0 +1,387 @@
+use egui::{CollapsingHeader, ComboBox, Context, DragValue, Ui};
+use egui_wgpu::renderer::ScreenDescriptor;
+use egui_wgpu::Renderer;
+use wgpu::{
+  CommandBuffer, CommandEncoder, Device, LoadOp, Operations, Queue, RenderPassColorAttachment,
+  RenderPassDescriptor, TextureFormat, TextureView,
+};
+use winit::event::WindowEvent;
+use winit::window::Window;
+
+use crate::data::Telemetry;
+use crate::layout::{Anchor, Length, Node};
+use crate::scene::Scene;
+use crate::stats::FrameStats;
+use crate::widgets::Rect;
+
+const ANCHORS: [Anchor; 9] = [
+  Anchor::TopLeft,
+  Anchor::Top,
+  Anchor::TopRight,
+  Anchor::Left,
+  Anchor::Center,
+  Anchor::Right,
+  Anchor::BottomLeft,
+  Anchor::Bottom,
+  Anchor::BottomRight,
+];
+
+/// Which tree of the scene a node is in.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Root {
+  Overlay,
+  Page(usize),
+}
+
+/// A node by the indices of the children leading to it.
+#[derive(Clone, PartialEq, Eq)]
+struct Selection {
+  root: Root,
+  path: Vec<usize>,
+}
+
+/// A window over the HUD listing the scene's nodes with where they were
+/// laid out and what telemetry they show, and editing the selected one
+/// while running, see [`Action::Inspector`](crate::input::Action::Inspector).
+///
+/// It's drawn on the window only, straight and after the frame is
+/// mirrored, warped and copied to the [frame output](crate::output),
+/// which see the outline of the selected node.
+pub(crate) struct Inspector {
+  context: Context,
+  winit: egui_winit::State,
+  renderer: Renderer,
+  open: bool,
+  selected: Option<Selection>,
+  // Where the selected node was laid out, outlined in the frame
+  highlight: Option<Rect>,
+  // Of the last frame, shown in the next
+  pub(crate) stats: FrameStats,
+  // Tessellated by `run`, drawn by `encode`
+  output: Option<(Vec<egui::ClippedPrimitive>, egui::TexturesDelta)>,
+}
+
+impl Inspector {
+  pub(crate) fn new(device: &Device, format: TextureFormat, window: &Window) -> Self {
+    let mut winit = egui_winit::State::new_with_wayland_display(None);
+    winit.set_pixels_per_point(egui_winit::native_pixels_per_point(window));
+    winit.set_max_texture_side(device.limits().max_texture_dimension_2d as usize);
+    Self {
+      context: Context::default(),
+      winit,
+      renderer: Renderer::new(device, format, None, 1),
+      open: false,
+      selected: None,
+      highlight: None,
+      stats: FrameStats::default(),
+      output: None,
+    }
+  }
+
+  pub(crate) fn is_open(&self) -> bool {
+    self.open
+  }
+
+  pub(crate) fn toggle(&mut self) {
+    self.open = !self.open;
+    self.highlight = None;
+  }
+
+  /// Passes a window event on, returning whether the inspector used it
+  /// rather than the HUD. Only used while open.
+  pub(crate) fn event(&mut self, event: &WindowEvent) -> bool {
+    let response = self.winit.on_event(&self.context, event);
+    self.open && response.consumed
+  }
+
+  pub(crate) fn highlight(&self) -> Option<Rect> {
+    self.highlight
+  }
+
+  /// Builds the window for the next frame.
+  pub(crate) fn run(&mut self, window: &Window, scene: Option<&mut Scene>, telemetry: &Telemetry) {
+    if !self.open {
+      self.output = None;
+      return;
+    }
+    let input = self.winit.take_egui_input(window);
+    let Self {
+      context,
+      selected,
+      stats,
+      ..
+    } = self;
+    let mut highlight = None;
+    let output = context.clone().run(input, |context| {
+      egui::Window::new("Inspector")
+        .default_width(320.0)
+        .show(context, |ui| {
+          CollapsingHeader::new("Frame").show(ui, |ui| frame_stats(ui, stats));
+          CollapsingHeader::new("Telemetry").show(ui, |ui| {
+            ui.monospace(format!("{:#?}", telemetry));
+          });
+          let Some(scene) = scene else {
+            ui.label("No scene");
+            return;
+          };
+          CollapsingHeader::new("Scene")
+            .default_open(true)
+            .show(ui, |ui| scene_tree(ui, scene, selected));
+          let Some(node) = selected
+            .as_ref()
+            .and_then(|selection| node_mut(scene, selection))
+          else {
+            return;
+          };
+          highlight = Some(node.rect());
+          ui.separator();
+          properties(ui, node);
+        });
+    });
+    self.highlight = highlight;
+    self
+      .winit
+      .handle_platform_output(window, &self.context, output.platform_output);
+    let primitives = self.context.tessellate(output.shapes);
+    self.output = Some((primitives, output.textures_delta));
+  }
+
+  /// Records drawing the window built by [`run`](Self::run) on top of
+  /// `target`, returning what has to be submitted before.
+  pub(crate) fn encode(
+    &mut self,
+    device: &Device,
+    queue: &Queue,
+    encoder: &mut CommandEncoder,
+    target: &TextureView,
+    size: [u32; 2],
+  ) -> Vec<CommandBuffer> {
+    let Some((primitives, textures)) = self.output.take() else {
+      return Vec::new();
+    };
+    let screen = ScreenDescriptor {
+      size_in_pixels: size,
+      pixels_per_point: self.context.pixels_per_point(),
+    };
+    for (id, delta) in &textures.set {
+      self.renderer.update_texture(device, queue, *id, delta);
+    }
+    let callbacks = self
+      .renderer
+      .update_buffers(device, queue, encoder, &primitives, &screen);
+    {
+      let mut pass = encoder.begin_render_pass(&RenderPassDescriptor {
+        label: Some("Inspector Pass"),
+        color_attachments: &[Some(RenderPassColorAttachment {
+          view: target,
+          resolve_target: None,
+          ops: Operations {
+            load: LoadOp::Load,
+            store: true,
+          },
+        })],
+        depth_stencil_attachment: None,
+      });
+      self.renderer.render(&mut pass, &primitives, &screen);
+    }
+    for id in &textures.free {
+      self.renderer.free_texture(id);
+    }
+    callbacks
+  }
+}
+
+fn frame_stats(ui: &mut Ui, stats: &FrameStats) {
+  ui.label(format!("frame {}", stats.frame));
+  ui.label(format!("update {:.2?}", stats.update_time));
+  ui.label(format!("encode {:.2?}", stats.encode_time));
+  if let Some(gpu_time) = stats.gpu_time {
+    ui.label(format!("gpu {:.2?}", gpu_time));
+  }
+  ui.label(format!(
+    "{} draw calls, {} triangles, {} texture binds",
+    stats.draw_calls, stats.triangles, stats.texture_binds
+  ));
+}
+
+/// Lists the overlay and the pages, turning to a page when its root is
+/// selected.
+fn scene_tree(ui: &mut Ui, scene: &mut Scene, selected: &mut Option<Selection>) {
+  let current = scene.page();
+  if let Some(overlay) = scene.overlay_mut() {
+    node_tree(
+      ui,
+      overlay,
+      "overlay",
+      Root::Overlay,
+      &mut Vec::new(),
+      selected,
+    );
+  }
+  for page in 0..scene.page_count() {
+    let node = scene.page_mut(page).expect("page is in range");
+    let name = match page == current {
+      true => format!("page {} (shown)", page),
+      false => format!("page {}", page),
+    };
+    let before = selected.clone();
+    node_tree(ui, node, &name, Root::Page(page), &mut Vec::new(), selected);
+    if *selected != before
+      && selected
+        .as_ref()
+        .is_some_and(|s| s.root == Root::Page(page))
+    {
+      scene.set_page(page);
+    }
+  }
+}
+
+fn node_tree(
+  ui: &mut Ui,
+  node: &Node,
+  fallback: &str,
+  root: Root,
+  path: &mut Vec<usize>,
+  selected: &mut Option<Selection>,
+) {
+  let rect = node.rect();
+  let label = format!(
+    "{} {}  {:.0},{:.0} {:.0}×{:.0}",
+    node.name.as_deref().unwrap_or(fallback),
+    node.widget_name().unwrap_or(""),
+    rect.x,
+    rect.y,
+    rect.width,
+    rect.height,
+  );
+  let selection = Selection {
+    root,
+    path: path.clone(),
+  };
+  let is_selected = selected.as_ref() == Some(&selection);
+  let mut select = |ui: &mut Ui| {
+    if ui.selectable_label(is_selected, &label).clicked() {
+      *selected = Some(selection.clone());
+    }
+  };
+  if node.children().is_empty() {
+    select(ui);
+    return;
+  }
+  let id = ui.make_persistent_id((root_id(root), path.as_slice()));
+  egui::collapsing_header::CollapsingState::load_with_default_open(ui.ctx(), id, path.is_empty())
+    .show_header(ui, |ui| select(ui))
+    .body(|ui| {
+      for (index, child) in node.children().iter().enumerate() {
+        path.push(index);
+        node_tree(ui, child, "node", root, path, selected);
+        path.pop();
+      }
+    });
+}
+
+fn root_id(root: Root) -> i64 {
+  match root {
+    Root::Overlay => -1,
+    Root::Page(page) => page as i64,
+  }
+}
+
+fn node_mut<'a>(scene: &'a mut Scene, selection: &Selection) -> Option<&'a mut Node> {
+  let mut node = match selection.root {
+    Root::Overlay => scene.overlay_mut()?,
+    Root::Page(page) => scene.page_mut(page)?,
+  };
+  for index in &selection.path {
+    node = node.children_mut().get_mut(*index)?;
+  }
+  Some(node)
+}
+
+/// Edits what places and shows `node`, applied from the next frame on.
+fn properties(ui: &mut Ui, node: &mut Node) {
+  egui::Grid::new("properties").num_columns(2).show(ui, |ui| {
+    ui.label("name");
+    let mut name = node.name.clone().unwrap_or_default();
+    if ui.text_edit_singleline(&mut name).changed() {
+      node.name = (!name.is_empty()).then_some(name);
+    }
+    ui.end_row();
+
+    ui.label("visible");
+    ui.checkbox(&mut node.visible, "");
+    ui.end_row();
+
+    ui.label("z");
+    ui.add(DragValue::new(&mut node.z));
+    ui.end_row();
+
+    ui.label("anchor");
+    ComboBox::from_id_source("anchor")
+      .selected_text(format!("{:?}", node.anchor))
+      .show_ui(ui, |ui| {
+        for anchor in ANCHORS {
+          ui.selectable_value(&mut node.anchor, anchor, format!("{:?}", anchor));
+        }
+      });
+    ui.end_row();
+
+    let [x, y] = &mut node.offset;
+    for (name, length) in [
+      ("x", x),
+      ("y", y),
+      ("width", &mut node.width),
+      ("height", &mut node.height),
+    ] {
+      ui.label(name);
+      length_editor(ui, name, length);
+      ui.end_row();
+    }
+
+    ui.label("square");
+    ui.checkbox(&mut node.square, "");
+    ui.end_row();
+
+    ui.label("padding");
+    ui.add(DragValue::new(&mut node.padding).clamp_range(0.0..=f32::MAX));
+    ui.end_row();
+
+    if let Some(source) = &mut node.source {
+      ui.label("source");
+      ui.label(source.expr.to_string());
+      ui.end_row();
+      ui.label("min");
+      ui.add(DragValue::new(&mut source.min).speed(0.5));
+      ui.end_row();
+      ui.label("max");
+      ui.add(DragValue::new(&mut source.max).speed(0.5));
+      ui.end_row();
+    }
+  });
+}
+
+fn length_editor(ui: &mut Ui, id: &str, length: &mut Length) {
+  ui.horizontal(|ui| {
+    let (value, percent) = match length {
+      Length::Px(value) => (value, false),
+      Length::Percent(value) => (value, true),
+    };
+    ui.add(DragValue::new(value));
+    let mut unit = percent;
+    ComboBox::from_id_source(id)
+      .width(48.0)
+      .selected_text(if unit { "%" } else { "px" })
+      .show_ui(ui, |ui| {
+        ui.selectable_value(&mut unit, false, "px");
+        ui.selectable_value(&mut unit, true, "%");
+      });
+    let value = *value;
+    if unit != percent {
+      *length = if unit {
+        Length::Percent(value)
+      } else {
+        Length::Px(value)
+      };
+    }
+  });
+}
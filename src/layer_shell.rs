@@ -0,0 +1,369 @@
+//! Drawing into a wlr-layer-shell surface instead of a window on Wayland
+//! compositors that support it, like sway and most embedded ones, so the
+//! HUD stays composited above other applications, see
+//! [`Settings::layer_shell`](crate::Settings::layer_shell). Needs the
+//! `layer-shell` feature, without it or off Wayland a window opens
+//! instead.
+//!
+//! Layer surfaces get no input: touches and clicks go to what's below,
+//! like with [`Settings::overlay`](crate::Settings::overlay), and keys
+//! only reach the input map.
+
+use std::str::FromStr;
+
+use serde::Deserialize;
+
+/// Which of the compositor's layers the HUD is shown in, from the bottom.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Layer {
+  Background,
+  Bottom,
+  /// Above windows, below fullscreen ones.
+  #[default]
+  Top,
+  /// Above everything, fullscreen windows too.
+  Overlay,
+}
+
+impl FromStr for Layer {
+  type Err = String;
+
+  fn from_str(name: &str) -> Result<Self, Self::Err> {
+    match name {
+      "background" => Ok(Self::Background),
+      "bottom" => Ok(Self::Bottom),
+      "top" => Ok(Self::Top),
+      "overlay" => Ok(Self::Overlay),
+      _ => Err(format!(
+        "unknown layer {:?}, expected background, bottom, top or overlay",
+        name
+      )),
+    }
+  }
+}
+
+/// Edges of the output the surface is attached to. Stretched between two
+/// opposite ones, the configured size is used along the others.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Anchor {
+  pub top: bool,
+  pub bottom: bool,
+  pub left: bool,
+  pub right: bool,
+}
+
+impl Anchor {
+  /// Covering the whole output.
+  pub const ALL: Self = Self {
+    top: true,
+    bottom: true,
+    left: true,
+    right: true,
+  };
+
+  /// A strip along the bottom edge, like a cluster below a HUD.
+  pub const BOTTOM: Self = Self {
+    top: false,
+    bottom: true,
+    left: true,
+    right: true,
+  };
+}
+
+impl Default for Anchor {
+  fn default() -> Self {
+    Self::ALL
+  }
+}
+
+impl FromStr for Anchor {
+  type Err = String;
+
+  /// Edges separated by commas, like `bottom,left,right`.
+  fn from_str(edges: &str) -> Result<Self, Self::Err> {
+    let mut anchor = Self {
+      top: false,
+      bottom: false,
+      left: false,
+      right: false,
+    };
+    for edge in edges.split(',') {
+      match edge.trim() {
+        "top" => anchor.top = true,
+        "bottom" => anchor.bottom = true,
+        "left" => anchor.left = true,
+        "right" => anchor.right = true,
+        edge => {
+          return Err(format!(
+            "unknown edge {:?}, expected top, bottom, left or right",
+            edge
+          ))
+        }
+      }
+    }
+    Ok(anchor)
+  }
+}
+
+/// How the HUD's layer surface is placed, as in the `[display.layer_shell]`
+/// table of the config:
+///
+/// ```toml
+/// [display.layer_shell]
+/// layer = "overlay"
+/// anchor = { top = false }
+/// exclusive_zone = 200
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct LayerShell {
+  pub layer: Layer,
+  pub anchor: Anchor,
+  /// Pixels along the anchored edge that other surfaces keep clear of,
+  /// like a panel, `0` to be drawn over them and `-1` to also cover
+  /// other surfaces' exclusive zones. Only kept clear when anchored to
+  /// one edge, or to three with the middle one the HUD is along.
+  pub exclusive_zone: i32,
+  /// Tells the compositor what the surface is for, e.g. to place it.
+  pub namespace: String,
+}
+
+impl Default for LayerShell {
+  fn default() -> Self {
+    Self {
+      layer: Layer::default(),
+      anchor: Anchor::default(),
+      exclusive_zone: 0,
+      namespace: "windshield-rs".to_string(),
+    }
+  }
+}
+
+#[cfg(all(feature = "layer-shell", target_os = "linux"))]
+pub(crate) use surface::LayerSurface;
+
+#[cfg(all(feature = "layer-shell", target_os = "linux"))]
+mod surface {
+  use std::cell::Cell;
+  use std::io::ErrorKind;
+  use std::rc::Rc;
+
+  use raw_window_handle::{
+    HasRawDisplayHandle, HasRawWindowHandle, RawDisplayHandle, RawWindowHandle,
+    WaylandDisplayHandle, WaylandWindowHandle,
+  };
+  use wayland_client::protocol::{wl_compositor::WlCompositor, wl_surface::WlSurface};
+  use wayland_client::{Display, EventQueue, GlobalManager, Main};
+  use wayland_protocols::wlr::unstable::layer_shell::v1::client::{
+    zwlr_layer_shell_v1::{self, ZwlrLayerShellV1},
+    zwlr_layer_surface_v1::{self, ZwlrLayerSurfaceV1},
+  };
+  use winit::dpi::PhysicalSize;
+
+  use super::{Anchor, Layer, LayerShell};
+  use crate::error::WindshieldError;
+
+  #[derive(Clone, Copy, Debug, PartialEq, Eq)]
+  enum Configured {
+    Size(u32, u32),
+    Closed,
+  }
+
+  /// A layer surface on its own connection to the compositor, next to
+  /// winit's, which keeps running the event loop with a window that is
+  /// never drawn into and so never shown.
+  pub(crate) struct LayerSurface {
+    display: Display,
+    queue: EventQueue,
+    surface: Main<WlSurface>,
+    layer: Main<ZwlrLayerSurfaceV1>,
+    configured: Rc<Cell<Option<Configured>>>,
+    size: PhysicalSize<u32>,
+  }
+
+  impl LayerSurface {
+    /// Asks for a surface `size` big along the edges it isn't stretched
+    /// between, and waits for the compositor to say how big it is.
+    pub(crate) fn create(
+      shell: &LayerShell,
+      size: PhysicalSize<u32>,
+    ) -> Result<Self, WindshieldError> {
+      let error = |err: &dyn std::fmt::Display| WindshieldError::LayerShell(err.to_string());
+      let display = Display::connect_to_env().map_err(|err| error(&err))?;
+      let mut queue = display.create_event_queue();
+      let attached = (*display).clone().attach(queue.token());
+      let globals = GlobalManager::new(&attached);
+      queue
+        .sync_roundtrip(&mut (), |_, _, _| {})
+        .map_err(|err| error(&err))?;
+      let compositor = globals
+        .instantiate_exact::<WlCompositor>(1)
+        .map_err(|err| error(&err))?;
+      let shell_global = globals
+        .instantiate_range::<ZwlrLayerShellV1>(1, 4)
+        .map_err(|err| error(&err))?;
+
+      let surface = compositor.create_surface();
+      let layer = shell_global.get_layer_surface(
+        &surface,
+        None,
+        match shell.layer {
+          Layer::Background => zwlr_layer_shell_v1::Layer::Background,
+          Layer::Bottom => zwlr_layer_shell_v1::Layer::Bottom,
+          Layer::Top => zwlr_layer_shell_v1::Layer::Top,
+          Layer::Overlay => zwlr_layer_shell_v1::Layer::Overlay,
+        },
+        shell.namespace.clone(),
+      );
+      let Anchor {
+        top,
+        bottom,
+        left,
+        right,
+      } = shell.anchor;
+      let mut anchor = zwlr_layer_surface_v1::Anchor::empty();
+      for (edge, anchored) in [
+        (zwlr_layer_surface_v1::Anchor::Top, top),
+        (zwlr_layer_surface_v1::Anchor::Bottom, bottom),
+        (zwlr_layer_surface_v1::Anchor::Left, left),
+        (zwlr_layer_surface_v1::Anchor::Right, right),
+      ] {
+        anchor.set(edge, anchored);
+      }
+      layer.set_anchor(anchor);
+      // Zero lets the compositor size the surface between anchored edges
+      layer.set_size(
+        if left && right { 0 } else { size.width },
+        if top && bottom { 0 } else { size.height },
+      );
+      layer.set_exclusive_zone(shell.exclusive_zone);
+      // Touches and clicks fall through to whatever is below the HUD
+      let region = compositor.create_region();
+      surface.set_input_region(Some(&region));
+      region.destroy();
+
+      let configured = Rc::new(Cell::new(None));
+      let handle = Rc::clone(&configured);
+      layer.quick_assign(move |layer, event, _| match event {
+        zwlr_layer_surface_v1::Event::Configure {
+          serial,
+          width,
+          height,
+        } => {
+          layer.ack_configure(serial);
+          if handle.get() != Some(Configured::Closed) {
+            handle.set(Some(Configured::Size(width, height)));
+          }
+        }
+        zwlr_layer_surface_v1::Event::Closed => handle.set(Some(Configured::Closed)),
+        _ => {}
+      });
+      // Nothing may be drawn before the first configure
+      surface.commit();
+      let mut this = Self {
+        display,
+        queue,
+        surface,
+        layer,
+        configured,
+        size,
+      };
+      while this.configured.get().is_none() {
+        this
+          .queue
+          .sync_roundtrip(&mut (), |_, _, _| {})
+          .map_err(|err| error(&err))?;
+      }
+      match this.configured.take() {
+        Some(Configured::Size(width, height)) => this.set_size(width, height),
+        _ => return Err(error(&"closed by the compositor")),
+      }
+      Ok(this)
+    }
+
+    pub(crate) fn size(&self) -> PhysicalSize<u32> {
+      self.size
+    }
+
+    /// Handles what the compositor sent without waiting for more, and
+    /// returns the new size if it changed it, `Err` if it closed the
+    /// surface.
+    pub(crate) fn poll(&mut self) -> Result<Option<PhysicalSize<u32>>, ()> {
+      let _ = self.display.flush();
+      if let Some(guard) = self.queue.prepare_read() {
+        match guard.read_events() {
+          Ok(()) => {}
+          Err(err) if err.kind() == ErrorKind::WouldBlock => {}
+          Err(err) => {
+            tracing::error!("lost the connection to the compositor: {}", err);
+            return Err(());
+          }
+        }
+      }
+      let _ = self.queue.dispatch_pending(&mut (), |_, _, _| {});
+      match self.configured.take() {
+        Some(Configured::Closed) => Err(()),
+        Some(Configured::Size(width, height)) => {
+          let size = self.size;
+          self.set_size(width, height);
+          Ok(Some(self.size).filter(|resized| *resized != size))
+        }
+        None => Ok(None),
+      }
+    }
+
+    // Zero along an edge leaves it to us, keeping what was asked for
+    fn set_size(&mut self, width: u32, height: u32) {
+      if width > 0 {
+        self.size.width = width;
+      }
+      if height > 0 {
+        self.size.height = height;
+      }
+    }
+  }
+
+  impl Drop for LayerSurface {
+    fn drop(&mut self) {
+      self.layer.destroy();
+      self.surface.destroy();
+      let _ = self.display.flush();
+    }
+  }
+
+  unsafe impl HasRawWindowHandle for LayerSurface {
+    fn raw_window_handle(&self) -> RawWindowHandle {
+      let mut handle = WaylandWindowHandle::empty();
+      handle.surface = self.surface.as_ref().c_ptr().cast();
+      RawWindowHandle::Wayland(handle)
+    }
+  }
+
+  unsafe impl HasRawDisplayHandle for LayerSurface {
+    fn raw_display_handle(&self) -> RawDisplayHandle {
+      let mut handle = WaylandDisplayHandle::empty();
+      handle.display = self.display.get_display_ptr().cast();
+      RawDisplayHandle::Wayland(handle)
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn reads_layers_and_edges() {
+    let shell: LayerShell =
+      toml::from_str("layer = \"overlay\"\nanchor = { top = false }\nexclusive_zone = 200")
+        .unwrap();
+    assert_eq!(shell.layer, Layer::Overlay);
+    assert_eq!(shell.anchor, Anchor::BOTTOM);
+    assert_eq!(shell.namespace, "windshield-rs");
+    assert_eq!("bottom, left,right".parse(), Ok(Anchor::BOTTOM));
+    assert!("middle".parse::<Anchor>().is_err());
+    assert_eq!("background".parse(), Ok(Layer::Background));
+  }
+}
@@ -0,0 +1,1219 @@
+use std::any::Any;
+use std::collections::HashSet;
+use std::fmt;
+use std::time::Duration;
+
+use serde::de::{self, Deserializer, Visitor};
+use serde::Deserialize;
+
+use crate::accessibility::{child_id, AccessibleWidget};
+use crate::cache::WidgetCache;
+use crate::canvas::Style;
+use crate::data::expr::Expr;
+use crate::data::{Field, Telemetry, ValueSource};
+use crate::frame::Frame;
+use crate::input::{Action, GestureArena, PointerEvent, PointerPhase};
+use crate::text::{Align, TextSection, VAlign};
+use crate::theme::Palette;
+use crate::widgets::{Rect, Widget};
+
+/// A distance, either absolute or relative to the parent's content rect.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Length {
+  /// Pixels, multiplied by the [scale](Node::scale) in a layout.
+  Px(f32),
+  /// Percent of the parent's width or height, whichever axis it's used on.
+  Percent(f32),
+}
+
+impl Length {
+  pub(crate) fn resolve(self, parent: f32) -> f32 {
+    self.resolve_scaled(parent, 1.0)
+  }
+
+  /// Like [`resolve`](Self::resolve) with pixels taking `scale` physical
+  /// pixels.
+  pub(crate) fn resolve_scaled(self, parent: f32, scale: f32) -> f32 {
+    match self {
+      Length::Px(px) => px * scale,
+      Length::Percent(percent) => parent * percent / 100.0,
+    }
+  }
+}
+
+impl From<f32> for Length {
+  fn from(px: f32) -> Self {
+    Length::Px(px)
+  }
+}
+
+/// Numbers are pixels, strings like `"50%"` percentages.
+impl<'de> Deserialize<'de> for Length {
+  fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+    struct LengthVisitor;
+
+    impl Visitor<'_> for LengthVisitor {
+      type Value = Length;
+
+      fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("a number of pixels or a percentage like \"50%\"")
+      }
+
+      fn visit_f64<E: de::Error>(self, value: f64) -> Result<Length, E> {
+        Ok(Length::Px(value as f32))
+      }
+
+      fn visit_i64<E: de::Error>(self, value: i64) -> Result<Length, E> {
+        Ok(Length::Px(value as f32))
+      }
+
+      fn visit_u64<E: de::Error>(self, value: u64) -> Result<Length, E> {
+        Ok(Length::Px(value as f32))
+      }
+
+      fn visit_str<E: de::Error>(self, value: &str) -> Result<Length, E> {
+        let percent = value
+          .trim()
+          .strip_suffix('%')
+          .ok_or_else(|| E::invalid_value(de::Unexpected::Str(value), &self))?;
+        percent
+          .trim()
+          .parse()
+          .map(Length::Percent)
+          .map_err(|_| E::invalid_value(de::Unexpected::Str(value), &self))
+      }
+    }
+
+    deserializer.deserialize_any(LengthVisitor)
+  }
+}
+
+/// The point of the parent a node is aligned to, the same point of the node
+/// is placed on it. In rows and columns only the cross axis is used.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Anchor {
+  #[default]
+  TopLeft,
+  Top,
+  TopRight,
+  Left,
+  Center,
+  Right,
+  BottomLeft,
+  Bottom,
+  BottomRight,
+}
+
+impl Anchor {
+  /// Position of the anchor as a fraction of a rect's size.
+  fn factors(self) -> [f32; 2] {
+    match self {
+      Anchor::TopLeft => [0.0, 0.0],
+      Anchor::Top => [0.5, 0.0],
+      Anchor::TopRight => [1.0, 0.0],
+      Anchor::Left => [0.0, 0.5],
+      Anchor::Center => [0.5, 0.5],
+      Anchor::Right => [1.0, 0.5],
+      Anchor::BottomLeft => [0.0, 1.0],
+      Anchor::Bottom => [0.5, 1.0],
+      Anchor::BottomRight => [1.0, 1.0],
+    }
+  }
+}
+
+/// How a node places its children.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum Arrange {
+  /// Every child by its own anchor.
+  #[default]
+  Anchored,
+  /// Left to right, `gap` pixels apart, scaled like [`Length::Px`].
+  Row { gap: f32 },
+  /// Top to bottom, `gap` pixels apart, scaled like [`Length::Px`].
+  Column { gap: f32 },
+}
+
+/// An element of the HUD: a box positioned relative to its parent, holding
+/// a widget, children or both.
+///
+/// The tree is laid out again every frame from the size of the window, so
+/// it reflows on resize without any extra work:
+///
+/// ```no_run
+/// use windshield_rs::layout::{Anchor, Length, Node};
+/// use windshield_rs::widgets::{Label, RadialGauge};
+///
+/// let mut root = Node::new()
+///   .with_child(
+///     Node::new()
+///       .with_anchor(Anchor::Bottom)
+///       .with_size(Length::Percent(60.0), Length::Percent(40.0))
+///       .row(16.0)
+///       .with_child(Node::widget(RadialGauge::speedometer(240.0)).named("speed").square())
+///       .with_child(Node::widget(RadialGauge::tachometer(7000.0, 6000.0)).square()),
+///   )
+///   .with_child(Node::widget(Label::new("Main St")).with_size(Length::Percent(100.0), 48.0.into()));
+///
+/// let app = windshield_rs::WindshieldApp::builder()
+///   .on_draw(move |frame| {
+///     if let Some(speed) = root.get_mut::<RadialGauge>("speed") {
+///       speed.set_value(0.3);
+///     }
+///     root.draw(frame);
+///   })
+///   .build();
+/// ```
+pub struct Node {
+  pub name: Option<String>,
+  pub anchor: Anchor,
+  /// Translation from the anchored position, percentages are of the
+  /// parent's content size.
+  pub offset: [Length; 2],
+  pub width: Length,
+  pub height: Length,
+  /// Keeps the node square, using the smaller of its resolved sides.
+  pub square: bool,
+  /// Space between the node's edges and its children, scaled like
+  /// [`Length::Px`].
+  pub padding: f32,
+  /// Scale of the node and everything below it in place of the frame's,
+  /// see [`Frame::scale`]. Like `Some(1.0)` to keep a widget to physical
+  /// pixels, or larger for a readout that has to stand out more on every
+  /// display.
+  pub scale: Option<f32>,
+  pub arrange: Arrange,
+  /// Mirrors the node and everything below it when laid out from right to
+  /// left, see [`Frame::right_to_left`]. Off for what has to stay as it
+  /// is, like a map or a gauge on the driver's side.
+  pub mirror: bool,
+  /// Nodes with a higher value are drawn on top, equal values in tree
+  /// order. Applies across the whole tree.
+  pub z: i32,
+  /// Hides the node and everything below it, hidden nodes don't take up
+  /// space in rows and columns.
+  pub visible: bool,
+  /// Sets [`visible`](Self::visible) from telemetry every frame, to
+  /// whether this holds. Kept as it is while the fields aren't known.
+  pub visible_when: Option<Expr>,
+  /// Sets the widget's value from telemetry every frame.
+  pub source: Option<ValueSource>,
+  // Whether the source's fields stopped updating
+  stale: bool,
+  // The source's value last applied and how long the widget has shown
+  // the same, for burn-in protection
+  shown: Option<f32>,
+  unchanged: Duration,
+  /// Draws the widget into a texture and shows that until it changes,
+  /// see [`Frame::cached_widget`] and [`invalidate`](Self::invalidate).
+  pub cache: bool,
+  /// What screen readers call the widget in place of what it calls
+  /// itself, e.g. for a switch, see [`Widget::accessible`].
+  pub accessible_name: Option<String>,
+  widget: Option<Box<dyn Widget>>,
+  cached: WidgetCache,
+  // Type of the widget without its path, for the inspector
+  widget_name: Option<&'static str>,
+  children: Vec<Node>,
+  rect: Rect,
+  // The scale it was last laid out at, and whether from right to left
+  laid_out_scale: f32,
+  laid_out_right_to_left: bool,
+  // Only used by the node pointer input is passed to
+  arena: GestureArena<usize>,
+  // Last applied by draw, only used by the drawn node
+  palette: Option<Palette>,
+  // Index of the focused widget among the focusable ones in tree order,
+  // none until the first action so touch only setups show no focus
+  focus: Option<usize>,
+}
+
+impl Default for Node {
+  fn default() -> Self {
+    Self::new()
+  }
+}
+
+impl Node {
+  /// An empty node filling its parent.
+  pub fn new() -> Self {
+    Self {
+      name: None,
+      anchor: Anchor::TopLeft,
+      offset: [Length::Px(0.0); 2],
+      width: Length::Percent(100.0),
+      height: Length::Percent(100.0),
+      square: false,
+      padding: 0.0,
+      scale: None,
+      arrange: Arrange::Anchored,
+      mirror: true,
+      z: 0,
+      visible: true,
+      visible_when: None,
+      source: None,
+      stale: false,
+      shown: None,
+      unchanged: Duration::ZERO,
+      cache: false,
+      accessible_name: None,
+      widget: None,
+      cached: WidgetCache::new(),
+      widget_name: None,
+      children: Vec::new(),
+      rect: Rect::default(),
+      laid_out_scale: 1.0,
+      laid_out_right_to_left: false,
+      arena: GestureArena::new(),
+      palette: None,
+      focus: None,
+    }
+  }
+
+  /// A node filling its parent with `widget`.
+  pub fn widget<W: Widget>(widget: W) -> Self {
+    // Without the path or generic parameters
+    let name = std::any::type_name::<W>().split('<').next();
+    Self {
+      widget: Some(Box::new(widget)),
+      widget_name: name.and_then(|name| name.rsplit("::").next()),
+      ..Self::new()
+    }
+  }
+
+  /// The type of the node's widget, like `RadialGauge`.
+  pub fn widget_name(&self) -> Option<&'static str> {
+    self.widget_name
+  }
+
+  pub fn named(mut self, name: impl Into<String>) -> Self {
+    self.name = Some(name.into());
+    self
+  }
+
+  pub fn with_anchor(mut self, anchor: Anchor) -> Self {
+    self.anchor = anchor;
+    self
+  }
+
+  pub fn with_offset(mut self, x: Length, y: Length) -> Self {
+    self.offset = [x, y];
+    self
+  }
+
+  pub fn with_size(mut self, width: Length, height: Length) -> Self {
+    self.width = width;
+    self.height = height;
+    self
+  }
+
+  pub fn square(mut self) -> Self {
+    self.square = true;
+    self
+  }
+
+  pub fn with_padding(mut self, padding: f32) -> Self {
+    self.padding = padding;
+    self
+  }
+
+  pub fn with_scale(mut self, scale: f32) -> Self {
+    self.scale = Some(scale);
+    self
+  }
+
+  pub fn row(mut self, gap: f32) -> Self {
+    self.arrange = Arrange::Row { gap };
+    self
+  }
+
+  pub fn column(mut self, gap: f32) -> Self {
+    self.arrange = Arrange::Column { gap };
+    self
+  }
+
+  /// Keeps the node as it is in right-to-left layouts, see
+  /// [`mirror`](Self::mirror).
+  pub fn unmirrored(mut self) -> Self {
+    self.mirror = false;
+    self
+  }
+
+  pub fn with_z(mut self, z: i32) -> Self {
+    self.z = z;
+    self
+  }
+
+  pub fn with_source(mut self, source: ValueSource) -> Self {
+    self.source = Some(source);
+    self
+  }
+
+  pub fn visible_when(mut self, condition: Expr) -> Self {
+    self.visible_when = Some(condition);
+    self
+  }
+
+  pub fn cached(mut self) -> Self {
+    self.cache = true;
+    self
+  }
+
+  pub fn with_accessible_name(mut self, name: impl Into<String>) -> Self {
+    self.accessible_name = Some(name.into());
+    self
+  }
+
+  pub fn with_child(mut self, child: Node) -> Self {
+    self.children.push(child);
+    self
+  }
+
+  pub fn push(&mut self, child: Node) {
+    self.children.push(child);
+  }
+
+  pub fn children(&self) -> &[Node] {
+    &self.children
+  }
+
+  pub fn children_mut(&mut self) -> &mut [Node] {
+    &mut self.children
+  }
+
+  /// Where the node ended up in the last layout.
+  pub fn rect(&self) -> Rect {
+    self.rect
+  }
+
+  /// The first node called `name`, searching depth first.
+  pub fn find_mut(&mut self, name: &str) -> Option<&mut Node> {
+    if self.name.as_deref() == Some(name) {
+      return Some(self);
+    }
+    self
+      .children
+      .iter_mut()
+      .find_map(|child| child.find_mut(name))
+  }
+
+  /// The widget of the node called `name`, if it is a `W`.
+  pub fn get_mut<W: Widget>(&mut self, name: &str) -> Option<&mut W> {
+    let widget: &mut dyn Any = self.find_mut(name)?.widget.as_deref_mut()?;
+    widget.downcast_mut()
+  }
+
+  /// Calls `f` with every widget of the tree that is a `W`, depth first.
+  pub fn for_each_mut<W: Widget>(&mut self, mut f: impl FnMut(&mut W)) {
+    self.visit(&mut f);
+  }
+
+  fn visit<W: Widget>(&mut self, f: &mut dyn FnMut(&mut W)) {
+    let widget: Option<&mut dyn Any> = self.widget.as_deref_mut().map(|widget| widget as _);
+    if let Some(widget) = widget.and_then(|widget| widget.downcast_mut()) {
+      f(widget);
+    }
+    for child in &mut self.children {
+      child.visit(f);
+    }
+  }
+
+  /// Positions the tree inside `parent`, with pixels as they are.
+  pub fn layout(&mut self, parent: Rect) {
+    self.layout_scaled(parent, 1.0);
+  }
+
+  /// Positions the tree inside `parent`, with pixels taking `scale`
+  /// physical pixels in nodes without a [scale](Self::scale) of their own.
+  pub fn layout_scaled(&mut self, parent: Rect, scale: f32) {
+    self.place(parent, parent, scale, false);
+  }
+
+  /// Like [`layout_scaled`](Self::layout_scaled), mirrored from right to
+  /// left: anchors, offsets and rows start on the right, except below
+  /// nodes that aren't [mirrored](Self::mirror).
+  pub fn layout_right_to_left(&mut self, parent: Rect, scale: f32) {
+    self.place(parent, parent, scale, true);
+  }
+
+  /// Anchors the node inside `slot`, with sizes and offsets relative to the
+  /// parent's content rect.
+  fn place(&mut self, slot: Rect, content: Rect, scale: f32, right_to_left: bool) {
+    let scale = self.scale.unwrap_or(scale);
+    let right_to_left = right_to_left && self.mirror;
+    self.laid_out_scale = scale;
+    self.laid_out_right_to_left = right_to_left;
+    let [width, height] = self.size(content, scale);
+    let [mut ax, ay] = self.anchor.factors();
+    let [x, y] = self.offset;
+    let mut x = x.resolve_scaled(content.width, scale);
+    if right_to_left {
+      ax = 1.0 - ax;
+      x = -x;
+    }
+    self.rect = Rect::new(
+      slot.x + ax * (slot.width - width) + x,
+      slot.y + ay * (slot.height - height) + y.resolve_scaled(content.height, scale),
+      width,
+      height,
+    );
+
+    let padding = self.padding * scale;
+    let content = Rect::new(
+      self.rect.x + padding,
+      self.rect.y + padding,
+      (self.rect.width - 2.0 * padding).max(0.0),
+      (self.rect.height - 2.0 * padding).max(0.0),
+    );
+    let mut cursor = 0.0;
+    for child in self.children.iter_mut().filter(|child| child.visible) {
+      let child_scale = child.scale.unwrap_or(scale);
+      let slot = match self.arrange {
+        Arrange::Anchored => content,
+        Arrange::Row { gap } => {
+          let [width, _] = child.size(content, child_scale);
+          let x = match right_to_left {
+            true => content.x + content.width - cursor - width,
+            false => content.x + cursor,
+          };
+          cursor += width + gap * scale;
+          Rect::new(x, content.y, width, content.height)
+        }
+        Arrange::Column { gap } => {
+          let [_, height] = child.size(content, child_scale);
+          let slot = Rect::new(content.x, content.y + cursor, content.width, height);
+          cursor += height + gap * scale;
+          slot
+        }
+      };
+      child.place(slot, content, scale, right_to_left);
+    }
+  }
+
+  fn size(&self, parent: Rect, scale: f32) -> [f32; 2] {
+    let width = self.width.resolve_scaled(parent.width, scale);
+    let height = self.height.resolve_scaled(parent.height, scale);
+    if self.square {
+      [width.min(height); 2]
+    } else {
+      [width, height]
+    }
+  }
+
+  /// Lays the tree out to fill the frame's
+  /// [safe area](Frame::safe_area) and draws every visible widget, in the
+  /// frame's palette.
+  pub fn draw(&mut self, frame: &mut Frame) {
+    self.draw_in(frame, frame.safe_area);
+  }
+
+  /// Like [`draw`](Self::draw) with the tree laid out inside `rect`, e.g.
+  /// to slide it in from the side.
+  pub fn draw_in(&mut self, frame: &mut Frame, rect: Rect) {
+    if self.palette.as_ref() != Some(frame.palette) {
+      self.palette = Some(*frame.palette);
+      self.set_palette(frame.palette);
+    }
+    self.age(frame.delta);
+    self.apply_sources(frame.telemetry, frame.stale);
+    self.place(rect, rect, frame.scale, frame.right_to_left);
+
+    let focus = self.focus;
+    let mut widgets = Vec::new();
+    self.collect_drawn(&mut widgets);
+    let focused = focus.and_then(|focus| {
+      let mut focusable = widgets.iter().filter(|drawn| drawn.widget.focusable());
+      focusable.nth(focus).map(|drawn| drawn.rect)
+    });
+    // Stable, so equal z keeps tree order
+    widgets.sort_by_key(|drawn| drawn.z);
+    // Above what was drawn before, like the page below an overlay
+    frame.next_layer();
+    let mut layer = widgets.first().map(|drawn| drawn.z);
+    let frame_scale = frame.scale;
+    let frame_right_to_left = frame.right_to_left;
+    let dim = frame
+      .burn_in
+      .and_then(|burn_in| Some((burn_in.dim_after()?, burn_in.dim_opacity)));
+    for Drawn {
+      z,
+      widget,
+      rect,
+      scale,
+      right_to_left,
+      cached,
+      stale,
+      unchanged,
+    } in widgets
+    {
+      if layer != Some(z) {
+        layer = Some(z);
+        frame.next_layer();
+      }
+      // Dimmed, so frozen values don't look like they're current, and so
+      // what shows the same for long doesn't burn in
+      let mut dimmed = if stale { STALE_OPACITY } else { 1.0 };
+      if let Some((_, opacity)) = dim.filter(|(after, _)| unchanged >= *after) {
+        dimmed *= opacity;
+      }
+      let opacity = (frame.canvas.opacity(), frame.text.opacity());
+      frame.canvas.set_opacity(opacity.0 * dimmed);
+      frame.text.set_opacity(opacity.1 * dimmed);
+      frame.scale = scale;
+      frame.right_to_left = right_to_left;
+      match cached {
+        Some(cached) => frame.cached_widget(widget, rect, cached),
+        None => frame.widget(widget, rect),
+      }
+      frame.canvas.set_opacity(opacity.0);
+      frame.text.set_opacity(opacity.1);
+      if stale {
+        stale_badge(frame, rect);
+      }
+      frame.scale = frame_scale;
+      frame.right_to_left = frame_right_to_left;
+    }
+    // A modal widget keeps the focus to itself
+    if let Some(rect) = focused.filter(|_| !self.modal()) {
+      let width = 3.0 * frame.scale;
+      frame.canvas.rect(
+        [rect.x - width, rect.y - width],
+        [rect.width + width * 2.0, rect.height + width * 2.0],
+        Style::stroke(frame.palette.accent, width),
+      );
+    }
+  }
+
+  /// Passes pointer input on to the widgets, see [`Widget::pointer`].
+  ///
+  /// A press goes to every widget it hits, topmost first. Those that use
+  /// it share the pointer until one [captures](Widget::captures) it or it
+  /// is released, which gives it to the topmost one. The others are
+  /// cancelled then. Positions are matched against the last layout.
+  pub fn pointer(&mut self, event: &PointerEvent) -> bool {
+    let mut arena = std::mem::take(&mut self.arena);
+    let mut widgets = Vec::new();
+    self.collect(&mut widgets);
+    widgets.sort_by_key(|(z, _, _)| *z);
+    // Topmost first, the reverse of drawing
+    widgets.reverse();
+    // Nothing below a modal widget gets new presses
+    let reachable = widgets
+      .iter()
+      .position(|(_, widget, _)| widget.modal())
+      .map_or(widgets.len(), |index| index + 1);
+    let used = pointer(&mut arena, &mut widgets, reachable, event);
+    self.arena = arena;
+    used
+  }
+
+  /// Passes a navigation action on to the focused widget, see
+  /// [`Widget::action`].
+  ///
+  /// Focus starts on the first [focusable](Widget::focusable) widget in
+  /// tree order. A [`Next`](Action::Next) or [`Previous`](Action::Previous)
+  /// the focused widget doesn't use moves it on to the neighbouring one,
+  /// stopping at the ends. [`NextPage`](Action::NextPage) and
+  /// [`PreviousPage`](Action::PreviousPage) go to the first widget using
+  /// them instead, wherever the focus is. While a [modal](Widget::modal)
+  /// widget is shown every action goes to the topmost one. Returns whether
+  /// the action did anything.
+  pub fn action(&mut self, action: Action) -> bool {
+    let focus = self.focus;
+    let mut widgets = Vec::new();
+    self.collect(&mut widgets);
+    // The last of the highest is drawn on top
+    let modal = widgets
+      .iter_mut()
+      .filter(|(_, widget, _)| widget.modal())
+      .max_by_key(|(z, _, _)| *z);
+    if let Some((_, modal, _)) = modal {
+      return modal.action(action);
+    }
+    if matches!(action, Action::NextPage | Action::PreviousPage) {
+      return widgets
+        .into_iter()
+        .any(|(_, widget, _)| widget.action(action));
+    }
+    let mut focusable: Vec<_> = widgets
+      .into_iter()
+      .map(|(_, widget, _)| widget)
+      .filter(|widget| widget.focusable())
+      .collect();
+    let Some(last) = focusable.len().checked_sub(1) else {
+      return false;
+    };
+
+    let current = focus.unwrap_or(0).min(last);
+    let used = focusable[current].action(action);
+    let moved = match action {
+      _ if used => current,
+      Action::Next => (current + 1).min(last),
+      Action::Previous => current.saturating_sub(1),
+      _ => current,
+    };
+    self.focus = Some(moved);
+    used || moved != current
+  }
+
+  /// The visible widgets screen readers are told about, in tree order,
+  /// with ids derived from `seed` and where they are in the tree. While a
+  /// [modal](Widget::modal) widget is shown the topmost one has the focus,
+  /// like for [`action`](Self::action).
+  pub fn accessible(&self, seed: u64) -> Vec<AccessibleWidget> {
+    self
+      .accessible_focus(seed)
+      .into_iter()
+      .map(|(_, widget)| widget)
+      .collect()
+  }
+
+  /// Moves the focus to the widget with `id` among those listed by
+  /// [`accessible`](Self::accessible) with `seed`, e.g. for a screen
+  /// reader. Returns whether it's there and can be focused.
+  pub fn focus_accessible(&mut self, seed: u64, id: u64) -> bool {
+    let index = self
+      .accessible_focus(seed)
+      .into_iter()
+      .find_map(|(index, widget)| index.filter(|_| widget.id == id));
+    if index.is_some() {
+      self.focus = index;
+    }
+    index.is_some()
+  }
+
+  // Along with their index among the focusable widgets, if they are
+  fn accessible_focus(&self, seed: u64) -> Vec<(Option<usize>, AccessibleWidget)> {
+    let mut listed = Vec::new();
+    let mut focusable = 0;
+    self.collect_accessible(seed, self.focus, &mut focusable, &mut listed);
+    // The last of the highest is drawn on top
+    let modal = listed
+      .iter()
+      .enumerate()
+      .filter(|(_, listed)| listed.modal)
+      .max_by_key(|(_, listed)| listed.z)
+      .map(|(index, _)| index);
+    listed
+      .into_iter()
+      .enumerate()
+      .filter_map(|(index, listed)| {
+        let mut widget = listed.accessible?;
+        if let Some(modal) = modal {
+          widget.focused = index == modal;
+        }
+        Some((listed.focus, widget))
+      })
+      .collect()
+  }
+
+  fn collect_accessible(
+    &self,
+    id: u64,
+    focus: Option<usize>,
+    focusable: &mut usize,
+    listed: &mut Vec<Listed>,
+  ) {
+    if !self.visible {
+      return;
+    }
+    if let Some(widget) = &self.widget {
+      let index = widget.focusable().then(|| {
+        *focusable += 1;
+        *focusable - 1
+      });
+      let accessible = widget.accessible().map(|mut accessible| {
+        if let Some(name) = &self.accessible_name {
+          accessible.name = name.clone();
+        }
+        AccessibleWidget {
+          id,
+          accessible,
+          rect: self.rect,
+          focused: index.is_some() && index == focus,
+          focusable: index.is_some(),
+        }
+      });
+      listed.push(Listed {
+        z: self.z,
+        modal: widget.modal(),
+        focus: index,
+        accessible,
+      });
+    }
+    for (index, child) in self.children.iter().enumerate() {
+      child.collect_accessible(child_id(id, index), focus, focusable, listed);
+    }
+  }
+
+  /// Passes `palette` on to every widget, see [`Widget::set_palette`].
+  pub fn set_palette(&mut self, palette: &Palette) {
+    if let Some(widget) = &mut self.widget {
+      widget.set_palette(palette);
+    }
+    self.cached.invalidate();
+    for child in &mut self.children {
+      child.set_palette(palette);
+    }
+  }
+
+  fn apply_sources(&mut self, telemetry: &Telemetry, stale: &HashSet<Field>) {
+    if let Some(visible) = self
+      .visible_when
+      .as_ref()
+      .and_then(|when| when.holds(telemetry))
+    {
+      self.visible = visible;
+    }
+    if let (Some(source), Some(widget)) = (&self.source, &mut self.widget) {
+      if let Some(value) = source.value(telemetry) {
+        widget.set_value(value);
+        self.cached.set_value(value);
+        if self.shown != Some(value) {
+          self.shown = Some(value);
+          self.unchanged = Duration::ZERO;
+        }
+      }
+      let is_stale = stale.iter().any(|field| source.expr.uses(*field));
+      if is_stale != self.stale {
+        self.stale = is_stale;
+        widget.set_stale(is_stale);
+        self.cached.invalidate();
+      }
+    }
+    for child in &mut self.children {
+      child.apply_sources(telemetry, stale);
+    }
+  }
+
+  /// Adds `delta` to how long the widgets of the tree have shown the same,
+  /// unless they're animating.
+  fn age(&mut self, delta: Duration) {
+    let animating = self
+      .widget
+      .as_ref()
+      .is_some_and(|widget| widget.animating());
+    self.unchanged = match animating {
+      true => Duration::ZERO,
+      false => self.unchanged.saturating_add(delta),
+    };
+    for child in &mut self.children {
+      child.age(delta);
+    }
+  }
+
+  /// Draws the cached widgets of the tree again next frame, after changing
+  /// them other than through their source or palette, see
+  /// [`cache`](Self::cache).
+  pub fn invalidate(&mut self) {
+    self.cached.invalidate();
+    for child in &mut self.children {
+      child.invalidate();
+    }
+  }
+
+  /// Whether a [modal](Widget::modal) widget is shown in the tree.
+  pub fn modal(&self) -> bool {
+    self.visible
+      && (self.widget.as_ref().is_some_and(|widget| widget.modal())
+        || self.children.iter().any(Node::modal))
+  }
+
+  fn collect<'a>(&'a mut self, widgets: &mut Vec<(i32, &'a mut dyn Widget, Rect)>) {
+    if !self.visible {
+      return;
+    }
+    if let Some(widget) = &mut self.widget {
+      widgets.push((self.z, widget.as_mut(), self.rect));
+    }
+    for child in &mut self.children {
+      child.collect(widgets);
+    }
+  }
+
+  // Like `collect`, with the caches of those drawn cached
+  fn collect_drawn<'a>(&'a mut self, widgets: &mut Vec<Drawn<'a>>) {
+    if !self.visible {
+      return;
+    }
+    if let Some(widget) = &mut self.widget {
+      let cached = self.cache.then_some(&mut self.cached);
+      widgets.push(Drawn {
+        z: self.z,
+        widget: widget.as_mut(),
+        rect: self.rect,
+        scale: self.laid_out_scale,
+        right_to_left: self.laid_out_right_to_left,
+        cached,
+        stale: self.stale,
+        unchanged: self.unchanged,
+      });
+    }
+    for child in &mut self.children {
+      child.collect_drawn(widgets);
+    }
+  }
+}
+
+/// A visible widget, including those screen readers skip, to find the
+/// modal one.
+struct Listed {
+  z: i32,
+  modal: bool,
+  /// Index among the focusable widgets, if it is.
+  focus: Option<usize>,
+  accessible: Option<AccessibleWidget>,
+}
+
+/// A widget to draw and how.
+struct Drawn<'a> {
+  z: i32,
+  widget: &'a mut dyn Widget,
+  rect: Rect,
+  scale: f32,
+  right_to_left: bool,
+  cached: Option<&'a mut WidgetCache>,
+  stale: bool,
+  unchanged: Duration,
+}
+
+/// Opacity of widgets showing stale telemetry.
+const STALE_OPACITY: f32 = 0.4;
+
+/// A warning triangle in the top right corner of `rect`, marking what's
+/// drawn there as stale.
+fn stale_badge(frame: &mut Frame, rect: Rect) {
+  let size = (rect.min_side() * 0.15).clamp(12.0 * frame.scale, 32.0 * frame.scale);
+  let [right, top] = [rect.x + rect.width, rect.y];
+  let triangle = [
+    [right - size / 2.0, top],
+    [right, top + size],
+    [right - size, top + size],
+  ];
+  frame
+    .canvas
+    .polygon(&triangle, Style::fill(frame.palette.warning));
+  frame.text.queue(
+    &TextSection::new("!")
+      .at(right - size / 2.0, top + size * 0.6)
+      .with_size(size * 0.8)
+      .with_color(frame.palette.background)
+      .with_align(Align::Center, VAlign::Center),
+  );
+}
+
+/// Routes `event` to the `widgets` contesting it in `arena`, indexed by
+/// their position in `widgets`. Only the first `reachable` widgets get
+/// presses and hovering.
+fn pointer(
+  arena: &mut GestureArena<usize>,
+  widgets: &mut [(i32, &mut dyn Widget, Rect)],
+  reachable: usize,
+  event: &PointerEvent,
+) -> bool {
+  let id = event.id;
+  let mut used = false;
+  match event.phase {
+    PointerPhase::Down => {
+      for (index, (_, widget, rect)) in widgets[..reachable].iter_mut().enumerate() {
+        if widget.hit(*rect, event.position) && widget.pointer(event, *rect) {
+          arena.join(id, index);
+          used = true;
+        }
+      }
+    }
+    // Hovering, nobody is tracking the pointer
+    _ if !arena.is_open(id) => {
+      for (_, widget, rect) in widgets[..reachable].iter_mut() {
+        used |= widget.pointer(event, *rect);
+      }
+    }
+    PointerPhase::Move => {
+      for index in arena.members(id) {
+        if let Some((_, widget, rect)) = widgets.get_mut(index) {
+          used |= widget.pointer(event, *rect);
+        }
+      }
+      if arena.winner(id).is_none() {
+        let claimed = arena.members(id).into_iter().find(|index| {
+          widgets
+            .get(*index)
+            .is_some_and(|(_, widget, _)| widget.captures(id))
+        });
+        if let Some(index) = claimed {
+          let losers = arena.claim(id, index);
+          cancel(widgets, &losers, event);
+        }
+      }
+    }
+    PointerPhase::Up => {
+      let losers = arena.resolve(id);
+      cancel(widgets, &losers, event);
+      for index in arena.members(id) {
+        if let Some((_, widget, rect)) = widgets.get_mut(index) {
+          used |= widget.pointer(event, *rect);
+        }
+      }
+      arena.close(id);
+    }
+    PointerPhase::Cancel => {
+      cancel(widgets, &arena.members(id), event);
+      arena.close(id);
+      used = true;
+    }
+  }
+  used
+}
+
+fn cancel(widgets: &mut [(i32, &mut dyn Widget, Rect)], indices: &[usize], event: &PointerEvent) {
+  let cancel = PointerEvent {
+    phase: PointerPhase::Cancel,
+    ..*event
+  };
+  for index in indices {
+    if let Some((_, widget, rect)) = widgets.get_mut(*index) {
+      widget.pointer(&cancel, *rect);
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use std::cell::Cell;
+  use std::rc::Rc;
+
+  use super::*;
+  use crate::accessibility::Role;
+  use crate::input::PointerId;
+  use crate::widgets::{Binding, Choice, Dialog, Label, Slider, Toggle};
+
+  fn press(phase: PointerPhase, x: f32) -> PointerEvent {
+    PointerEvent {
+      id: PointerId::Mouse,
+      phase,
+      position: [x, 50.0],
+    }
+  }
+
+  /// A slider from 0 to 1 across the window with a dialog above it.
+  fn slider_and_dialog(value: &Binding<f32>) -> Node {
+    let mut root = Node::new()
+      .with_child(Node::widget(Slider::new(value.clone(), 0.0..=1.0)))
+      .with_child(
+        Node::widget(Dialog::new("Reset?", ""))
+          .named("dialog")
+          .with_z(1),
+      );
+    root.layout(Rect::new(0.0, 0.0, 800.0, 100.0));
+    root
+  }
+
+  #[test]
+  fn lengths_are_pixels_or_percentages() {
+    let length = |json| serde_json::from_str::<Length>(json);
+    assert_eq!(length("120").unwrap(), Length::Px(120.0));
+    assert_eq!(length("12.5").unwrap(), Length::Px(12.5));
+    assert_eq!(length("-4").unwrap(), Length::Px(-4.0));
+    assert_eq!(length(r#"" 50 % ""#).unwrap(), Length::Percent(50.0));
+    assert!(length(r#""50""#).is_err());
+    assert!(length(r#""half%""#).is_err());
+    assert_eq!(Length::Percent(25.0).resolve(800.0), 200.0);
+    assert_eq!(Length::Px(25.0).resolve(800.0), 25.0);
+  }
+
+  #[test]
+  fn right_to_left_mirrors_rows_anchors_and_offsets() {
+    let sized = || Node::new().with_size(Length::Px(100.0), Length::Px(50.0));
+    let row = Node::new()
+      .with_size(Length::Px(250.0), Length::Px(50.0))
+      .row(10.0);
+    let mut root = Node::new()
+      .with_child(row.with_child(sized()).with_child(sized()))
+      .with_child(
+        sized()
+          .with_anchor(Anchor::TopRight)
+          .with_offset(Length::Px(-20.0), Length::Px(5.0)),
+      )
+      .with_child(sized().with_anchor(Anchor::Right).unmirrored());
+    let rects = |root: &Node| -> Vec<Rect> {
+      let row = root.children()[0].children().iter().map(Node::rect);
+      row
+        .chain(root.children()[1..].iter().map(Node::rect))
+        .collect()
+    };
+    let window = Rect::new(0.0, 0.0, 800.0, 400.0);
+
+    root.layout(window);
+    assert_eq!(
+      rects(&root),
+      [
+        Rect::new(0.0, 0.0, 100.0, 50.0),
+        Rect::new(110.0, 0.0, 100.0, 50.0),
+        Rect::new(680.0, 5.0, 100.0, 50.0),
+        Rect::new(700.0, 175.0, 100.0, 50.0),
+      ]
+    );
+    root.layout_right_to_left(window, 1.0);
+    assert_eq!(
+      rects(&root),
+      [
+        // The row is on the right, starting with its first child
+        Rect::new(700.0, 0.0, 100.0, 50.0),
+        Rect::new(590.0, 0.0, 100.0, 50.0),
+        Rect::new(20.0, 5.0, 100.0, 50.0),
+        Rect::new(700.0, 175.0, 100.0, 50.0),
+      ]
+    );
+  }
+
+  #[test]
+  fn pixels_follow_the_scale_unless_a_node_has_its_own() {
+    let mut root = Node::new().with_padding(10.0).column(4.0);
+    for scale in [None, Some(1.0)] {
+      let mut node = Node::new().with_size(Length::Percent(50.0), Length::Px(40.0));
+      node.scale = scale;
+      root.push(node);
+    }
+    root.layout_scaled(Rect::new(0.0, 0.0, 800.0, 480.0), 2.0);
+    assert_eq!(root.children[0].rect, Rect::new(20.0, 20.0, 380.0, 80.0));
+    assert_eq!(root.children[1].rect, Rect::new(20.0, 108.0, 380.0, 40.0));
+    assert_eq!(root.children[1].laid_out_scale, 1.0);
+  }
+
+  #[test]
+  fn widgets_of_fields_without_updates_go_stale() {
+    let source = ValueSource::new("speed * 2".parse::<Expr>().unwrap(), 0.0, 1.0);
+    let mut root = Node::new()
+      .with_child(Node::widget(Label::new("")).with_source(source))
+      .with_child(Node::widget(Label::new("")).with_source(ValueSource::new(Field::Rpm, 0.0, 1.0)));
+    let telemetry = Telemetry::default();
+    root.apply_sources(&telemetry, &[Field::Speed].into());
+    assert!(root.children[0].stale);
+    assert!(!root.children[1].stale);
+    root.apply_sources(&telemetry, &HashSet::new());
+    assert!(!root.children[0].stale);
+  }
+
+  #[test]
+  fn widgets_show_the_same_until_their_value_changes() {
+    let mut root = Node::new()
+      .with_child(Node::widget(Label::new("km/h")))
+      .with_child(Node::widget(Label::new("")).with_source(ValueSource::new(
+        Field::Speed,
+        0.0,
+        1.0,
+      )));
+    let mut telemetry = Telemetry {
+      speed: Some(0.5),
+      ..Default::default()
+    };
+    for _ in 0..3 {
+      root.age(Duration::from_secs(60));
+      root.apply_sources(&telemetry, &HashSet::new());
+    }
+    assert_eq!(root.children[0].unchanged, Duration::from_secs(180));
+    assert_eq!(root.children[1].unchanged, Duration::from_secs(120));
+    telemetry.speed = Some(0.6);
+    root.age(Duration::from_secs(60));
+    root.apply_sources(&telemetry, &HashSet::new());
+    assert_eq!(root.children[1].unchanged, Duration::ZERO);
+  }
+
+  #[test]
+  fn nodes_know_the_type_of_their_widget() {
+    assert_eq!(Node::widget(Label::new("")).widget_name(), Some("Label"));
+    assert_eq!(Node::new().widget_name(), None);
+  }
+
+  #[test]
+  fn encoder_moves_the_focus_past_widgets_done_with_it() {
+    let (first, value, last) = (Binding::new(false), Binding::new(0.0), Binding::new(false));
+    let mut root = Node::new()
+      .with_child(Node::widget(Toggle::new(first.clone())))
+      .with_child(Node::widget(
+        Slider::new(value.clone(), 0.0..=1.0).with_step(0.5),
+      ))
+      .with_child(Node::widget(Label::new("Speed")))
+      .with_child(Node::widget(Toggle::new(last.clone())));
+    root.layout(Rect::new(0.0, 0.0, 800.0, 100.0));
+
+    assert!(root.action(Action::Activate));
+    assert!(first.get());
+    // Onto the slider, which takes turns until it's at the end
+    assert!(root.action(Action::Next));
+    assert!(root.action(Action::Next));
+    assert!(root.action(Action::Next));
+    assert_eq!(value.get(), 1.0);
+    // Past the label, which can't be focused, and no further
+    assert!(root.action(Action::Next));
+    assert!(!root.action(Action::Next));
+    root.action(Action::Activate);
+    assert!(last.get());
+    assert!(root.action(Action::Previous));
+    root.action(Action::Previous);
+    assert_eq!(value.get(), 0.5);
+  }
+
+  #[test]
+  fn closed_dialog_lets_presses_through() {
+    let value = Binding::new(0.0);
+    let mut root = slider_and_dialog(&value);
+    assert!(root.pointer(&press(PointerPhase::Down, 700.0)));
+    assert!(value.get() > 0.5);
+  }
+
+  #[test]
+  fn open_dialog_takes_presses_and_actions() {
+    let value = Binding::new(0.0);
+    let mut root = slider_and_dialog(&value);
+    root.get_mut::<Dialog>("dialog").unwrap().open();
+    assert!(root.modal());
+
+    root.pointer(&press(PointerPhase::Down, 700.0));
+    root.pointer(&press(PointerPhase::Up, 700.0));
+    assert!(root.action(Action::Next));
+    assert_eq!(value.get(), 0.0);
+
+    root.action(Action::Back);
+    assert!(!root.get_mut::<Dialog>("dialog").unwrap().is_open());
+    assert!(!root.modal());
+  }
+
+  #[test]
+  fn dialog_answers_with_the_focused_button() {
+    let choice = Rc::new(Cell::new(None));
+    let answer = choice.clone();
+    let mut dialog = Dialog::new("Reset?", "").on_close(move |choice| answer.set(Some(choice)));
+    dialog.open();
+    dialog.action(Action::Next);
+    dialog.action(Action::Activate);
+    assert_eq!(choice.get(), Some(Choice::Confirm));
+  }
+
+  #[test]
+  fn screen_readers_are_told_about_the_widgets_shown() {
+    let value = Binding::new(0.25);
+    let mut root = slider_and_dialog(&value);
+    root.children[0].accessible_name = Some("Brightness".into());
+    let listed = root.accessible(1);
+    // The closed dialog isn't there
+    assert_eq!(listed.len(), 1);
+    let slider = &listed[0];
+    assert_eq!(slider.accessible.role, Role::Slider);
+    assert_eq!(slider.accessible.name, "Brightness");
+    assert_eq!(slider.accessible.value.as_deref(), Some("0.25"));
+    assert!(slider.focusable);
+
+    root.get_mut::<Dialog>("dialog").unwrap().open();
+    let listed = root.accessible(1);
+    assert_eq!(listed.len(), 2);
+    assert!(!listed[0].focused);
+    assert!(listed[1].focused);
+    assert_eq!(listed[1].accessible.role, Role::Dialog);
+    assert_eq!(listed[1].accessible.name, "Reset?");
+
+    // Ids don't change with what else is shown
+    assert!(root.focus_accessible(1, slider.id));
+    assert!(!root.focus_accessible(1, slider.id ^ 1));
+  }
+}
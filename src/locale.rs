@@ -0,0 +1,294 @@
+//! Writing numbers, times and dates the way the driver's locale does,
+//! like `1.234,5` and `14:05` in German but `2:05 PM` in American
+//! English, set with the locale's tag next to the units:
+//!
+//! ```toml
+//! locale = "de-DE"
+//! ```
+//!
+//! Only the language and region of the tag matter. Separators, the hour
+//! cycle and the order of dates follow CLDR for the languages listed in
+//! [`NumberFormat::for_tag`], see [`Locale`].
+
+use std::fmt::Write;
+
+use serde::Deserialize;
+
+/// How numbers, times and dates are written in a locale.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize)]
+#[serde(try_from = "String")]
+pub struct Locale {
+  pub number: NumberFormat,
+  pub time: TimeFormat,
+}
+
+impl Locale {
+  /// The locale with the BCP 47 `tag`, for the languages
+  /// [`NumberFormat::for_tag`] knows.
+  pub fn for_tag(tag: &str) -> Option<Self> {
+    Some(Self {
+      number: NumberFormat::for_tag(tag)?,
+      time: TimeFormat::for_tag(tag)?,
+    })
+  }
+}
+
+impl TryFrom<String> for Locale {
+  type Error = String;
+
+  fn try_from(tag: String) -> Result<Self, Self::Error> {
+    Self::for_tag(&tag).ok_or_else(|| format!("unknown locale {:?}", tag))
+  }
+}
+
+/// The lowercase language and uppercase region of a BCP 47 `tag`, like
+/// `en-US` or `pt_BR`.
+fn subtags(tag: &str) -> Option<(String, Option<String>)> {
+  let mut subtags = tag.split(['-', '_']);
+  let language = subtags.next()?.to_ascii_lowercase();
+  let region = subtags
+    .find(|subtag| subtag.len() == 2 || subtag.len() == 3 && subtag.parse::<u16>().is_ok())
+    .map(|region| region.to_ascii_uppercase());
+  Some((language, region))
+}
+
+/// How numbers are written: the separators and when the digits of the
+/// integer part are grouped by thousands.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize)]
+#[serde(try_from = "String")]
+pub struct NumberFormat {
+  pub decimal: char,
+  /// None to never group.
+  pub grouping: Option<char>,
+  /// Digits before the first group for there to be groups at all, 2 to
+  /// write `1234` but `12 345`.
+  pub min_grouping: usize,
+}
+
+impl Default for NumberFormat {
+  /// Like Rust writes numbers, `1234.5`.
+  fn default() -> Self {
+    Self {
+      decimal: '.',
+      grouping: None,
+      min_grouping: 1,
+    }
+  }
+}
+
+impl NumberFormat {
+  pub fn new(decimal: char, grouping: Option<char>) -> Self {
+    Self {
+      decimal,
+      grouping,
+      min_grouping: 1,
+    }
+  }
+
+  pub fn with_min_grouping(mut self, min_grouping: usize) -> Self {
+    self.min_grouping = min_grouping.max(1);
+    self
+  }
+
+  /// The format of the locale with the BCP 47 `tag`, like `en-US` or
+  /// `pt_BR`, if its language is one of the common ones in cars: English,
+  /// German, French, Spanish, Italian, Portuguese, Dutch, the Nordic and
+  /// Slavic languages, Turkish, Greek, Hungarian, Japanese, Chinese and
+  /// Korean.
+  pub fn for_tag(tag: &str) -> Option<Self> {
+    let (language, region) = subtags(tag)?;
+    // Narrow no-break space, so numbers never wrap
+    const SPACE: char = '\u{202f}';
+    let format = match (language.as_str(), region.as_deref()) {
+      ("de" | "it", Some("CH" | "LI")) => Self::new('.', Some('’')),
+      ("fr", Some("CH")) => Self::new(',', Some(SPACE)),
+      ("pt", Some("PT")) => Self::new(',', Some(SPACE)).with_min_grouping(2),
+      ("en" | "ja" | "zh" | "ko" | "th" | "he", _) => Self::new('.', Some(',')),
+      ("de" | "it" | "nl" | "da" | "id" | "tr" | "el" | "pt", _) => Self::new(',', Some('.')),
+      ("es", _) => Self::new(',', Some('.')).with_min_grouping(2),
+      ("pl", _) => Self::new(',', Some(SPACE)).with_min_grouping(2),
+      ("fr" | "ru" | "uk" | "cs" | "sk" | "sv" | "fi" | "nb" | "nn" | "no" | "hu", _) => {
+        Self::new(',', Some(SPACE))
+      }
+      _ => return None,
+    };
+    Some(format)
+  }
+
+  /// `value` with `decimals` digits after the separator.
+  pub fn format(&self, value: f32, decimals: usize) -> String {
+    let plain = format!("{:.*}", decimals, value);
+    let (sign, plain) = match plain.strip_prefix('-') {
+      Some(unsigned) => ("-", unsigned),
+      None => ("", plain.as_str()),
+    };
+    let (integer, fraction) = match plain.split_once('.') {
+      Some((integer, fraction)) => (integer, Some(fraction)),
+      None => (plain, None),
+    };
+    let mut text = sign.to_string();
+    match self.grouping {
+      Some(grouping) if integer.len() >= 3 + self.min_grouping => {
+        for (index, digit) in integer.chars().enumerate() {
+          if index > 0 && (integer.len() - index) % 3 == 0 {
+            text.push(grouping);
+          }
+          text.push(digit);
+        }
+      }
+      _ => text.push_str(integer),
+    }
+    if let Some(fraction) = fraction {
+      let _ = write!(text, "{}{}", self.decimal, fraction);
+    }
+    text
+  }
+}
+
+impl TryFrom<String> for NumberFormat {
+  type Error = String;
+
+  fn try_from(tag: String) -> Result<Self, Self::Error> {
+    Self::for_tag(&tag).ok_or_else(|| format!("unknown locale {:?}", tag))
+  }
+}
+
+/// In which order dates list the day, month and year.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DateOrder {
+  DayMonthYear,
+  MonthDayYear,
+  YearMonthDay,
+}
+
+/// How times of day and dates are written, like clocks and arrival times
+/// show them.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TimeFormat {
+  /// Writes `2:05 PM` rather than `14:05`.
+  pub twelve_hour: bool,
+  /// Between hours and minutes.
+  pub time_separator: char,
+  pub date_order: DateOrder,
+  /// Between the day, month and year.
+  pub date_separator: char,
+}
+
+impl Default for TimeFormat {
+  /// Like ISO 8601, `14:05` and `2024-01-05`.
+  fn default() -> Self {
+    Self {
+      twelve_hour: false,
+      time_separator: ':',
+      date_order: DateOrder::YearMonthDay,
+      date_separator: '-',
+    }
+  }
+}
+
+impl TimeFormat {
+  /// The format of the locale with the BCP 47 `tag`, for the languages
+  /// [`NumberFormat::for_tag`] knows. Dates always have two digit days
+  /// and months and the full year, so they're the same width all year.
+  pub fn for_tag(tag: &str) -> Option<Self> {
+    // The others would silently get the fallbacks below
+    NumberFormat::for_tag(tag)?;
+    let (language, region) = subtags(tag)?;
+    let twelve_hour = matches!(
+      (language.as_str(), region.as_deref()),
+      ("en", None | Some("US" | "CA" | "AU" | "NZ" | "IN" | "PH"))
+    );
+    let time_separator = match language.as_str() {
+      "fi" | "da" => '.',
+      _ => ':',
+    };
+    let (date_order, date_separator) = match (language.as_str(), region.as_deref()) {
+      ("en", None | Some("US" | "PH")) => (DateOrder::MonthDayYear, '/'),
+      ("en" | "fr", Some("CA")) | ("sv", _) => (DateOrder::YearMonthDay, '-'),
+      ("ja" | "zh", _) => (DateOrder::YearMonthDay, '/'),
+      ("ko" | "hu", _) => (DateOrder::YearMonthDay, '.'),
+      ("nl", _) => (DateOrder::DayMonthYear, '-'),
+      ("de" | "ru" | "uk" | "pl" | "cs" | "sk" | "fi" | "nb" | "nn" | "no" | "da" | "tr", _) => {
+        (DateOrder::DayMonthYear, '.')
+      }
+      _ => (DateOrder::DayMonthYear, '/'),
+    };
+    Some(Self {
+      twelve_hour,
+      time_separator,
+      date_order,
+      date_separator,
+    })
+  }
+
+  /// `hour` from 0 to 23 and `minute` as a time of day.
+  pub fn time(&self, hour: u32, minute: u32) -> String {
+    let separator = self.time_separator;
+    if self.twelve_hour {
+      let period = if hour % 24 < 12 { "AM" } else { "PM" };
+      let hour = match hour % 12 {
+        0 => 12,
+        hour => hour,
+      };
+      format!("{}{}{:02} {}", hour, separator, minute, period)
+    } else {
+      format!("{:02}{}{:02}", hour % 24, separator, minute)
+    }
+  }
+
+  /// `day` of `month`, both from 1, in `year`.
+  pub fn date(&self, year: i32, month: u32, day: u32) -> String {
+    let year = year.to_string();
+    let [month, day] = [month, day].map(|part| format!("{:02}", part));
+    let parts = match self.date_order {
+      DateOrder::DayMonthYear => [day, month, year],
+      DateOrder::MonthDayYear => [month, day, year],
+      DateOrder::YearMonthDay => [year, month, day],
+    };
+    parts.join(&self.date_separator.to_string())
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn writes_numbers_like_the_locale() {
+    let format =
+      |tag: &str, value, decimals| NumberFormat::for_tag(tag).unwrap().format(value, decimals);
+    assert_eq!(format("en-US", 1234.5, 1), "1,234.5");
+    assert_eq!(format("de-DE", 1234.5, 1), "1.234,5");
+    assert_eq!(format("de_CH", 1234.5, 1), "1’234.5");
+    assert_eq!(format("fr", 123456.0, 0), "123\u{202f}456");
+    assert_eq!(format("es", 1234.0, 0), "1234");
+    assert_eq!(format("es", 12345.0, 0), "12.345");
+    assert_eq!(format("en", -1234567.0, 0), "-1,234,567");
+    assert_eq!(format("en", 999.96, 1), "1,000.0");
+    assert_eq!(format("de", 0.5, 2), "0,50");
+    assert_eq!(NumberFormat::default().format(1234.5, 1), "1234.5");
+    assert!(NumberFormat::for_tag("tlh").is_none());
+    assert!(NumberFormat::try_from(String::new()).is_err());
+  }
+
+  #[test]
+  fn writes_times_and_dates_like_the_locale() {
+    let format = |tag: &str| TimeFormat::for_tag(tag).unwrap();
+    assert_eq!(format("en-US").time(14, 5), "2:05 PM");
+    assert_eq!(format("en").time(0, 30), "12:30 AM");
+    assert_eq!(format("en-GB").time(14, 5), "14:05");
+    assert_eq!(format("de-DE").time(9, 5), "09:05");
+    assert_eq!(format("fi").time(14, 5), "14.05");
+    assert_eq!(format("en-US").date(2024, 1, 5), "01/05/2024");
+    assert_eq!(format("en-GB").date(2024, 1, 5), "05/01/2024");
+    assert_eq!(format("de").date(2024, 1, 5), "05.01.2024");
+    assert_eq!(format("sv-SE").date(2024, 1, 5), "2024-01-05");
+    assert_eq!(format("ja").date(2024, 1, 5), "2024/01/05");
+    assert_eq!(TimeFormat::default().date(2024, 1, 5), "2024-01-05");
+    assert!(TimeFormat::for_tag("tlh").is_none());
+
+    let locale = Locale::try_from("de-CH".to_string()).unwrap();
+    assert_eq!(locale.number.format(1234.5, 1), "1’234.5");
+    assert_eq!(locale.time.time(14, 5), "14:05");
+  }
+}
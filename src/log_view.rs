@@ -0,0 +1,227 @@
+//! An overlay showing the latest events of the
+//! [`log buffer`](crate::Settings::log_buffer), for looking into issues in
+//! the field without a shell on the device, see
+//! [`Action::Logs`](crate::input::Action::Logs).
+//!
+//! While it's shown `Tab` or the activate action show more verbose levels,
+//! wrapping around to errors only, the arrow keys and the navigation
+//! actions scroll back and `Escape` or the back action hide it.
+
+use tracing::Level;
+use winit::event::{ElementState, KeyboardInput, ModifiersState, VirtualKeyCode, WindowEvent};
+
+use crate::canvas::Style;
+use crate::frame::Frame;
+use crate::input::Action;
+use crate::logging::{self, LogBuffer, LogRecord};
+use crate::text::{Align, TextSection, VAlign};
+use crate::theme::Palette;
+
+/// Lines shown at a time.
+const LINES: usize = 20;
+
+/// The log overlay.
+pub(crate) struct LogView {
+  buffer: Option<LogBuffer>,
+  open: bool,
+  /// The most verbose level shown.
+  level: Level,
+  /// Lines scrolled back from the latest.
+  scroll: usize,
+  modifiers: ModifiersState,
+}
+
+impl LogView {
+  pub(crate) fn new(buffer: Option<LogBuffer>) -> Self {
+    Self {
+      buffer,
+      open: false,
+      level: Level::INFO,
+      scroll: 0,
+      modifiers: ModifiersState::empty(),
+    }
+  }
+
+  pub(crate) fn is_open(&self) -> bool {
+    self.open
+  }
+
+  /// Shows or hides the overlay, warning instead if there's no log buffer
+  /// to show.
+  pub(crate) fn toggle(&mut self) {
+    if self.buffer.is_none() {
+      tracing::warn!("no log buffer to show");
+      return;
+    }
+    self.open = !self.open;
+    self.scroll = 0;
+  }
+
+  fn records(&self) -> Vec<LogRecord> {
+    self
+      .buffer
+      .as_ref()
+      .map_or(Vec::new(), |buffer| buffer.records(self.level))
+  }
+
+  fn scroll_by(&mut self, lines: isize) {
+    let last = self.records().len().saturating_sub(LINES);
+    self.scroll = self.scroll.saturating_add_signed(lines).min(last);
+  }
+
+  fn more_verbose(&mut self) {
+    self.level = match self.level {
+      Level::ERROR => Level::WARN,
+      Level::WARN => Level::INFO,
+      Level::INFO => Level::DEBUG,
+      Level::DEBUG => Level::TRACE,
+      _ => Level::ERROR,
+    };
+    self.scroll = 0;
+  }
+
+  /// Scrolls and filters with `event`, returning whether it was used
+  /// rather than going to the HUD. Only used while open, and never with
+  /// `Ctrl`, `Alt` or the logo key held, so key combos still work.
+  pub(crate) fn event(&mut self, event: &WindowEvent) -> bool {
+    if let WindowEvent::ModifiersChanged(modifiers) = event {
+      self.modifiers = *modifiers;
+    }
+    let combo = ModifiersState::CTRL | ModifiersState::ALT | ModifiersState::LOGO;
+    if !self.open || self.modifiers.intersects(combo) {
+      return false;
+    }
+    let WindowEvent::KeyboardInput { input, .. } = event else {
+      return false;
+    };
+    let KeyboardInput {
+      state: ElementState::Pressed,
+      virtual_keycode: Some(key),
+      ..
+    } = input
+    else {
+      return true;
+    };
+    match key {
+      VirtualKeyCode::Tab => self.more_verbose(),
+      VirtualKeyCode::Escape => self.open = false,
+      VirtualKeyCode::Up => self.scroll_by(1),
+      VirtualKeyCode::Down => self.scroll_by(-1),
+      VirtualKeyCode::PageUp => self.scroll_by(LINES as isize),
+      VirtualKeyCode::PageDown => self.scroll_by(-(LINES as isize)),
+      VirtualKeyCode::End => self.scroll = 0,
+      _ => {}
+    }
+    true
+  }
+
+  /// Scrolls back with `Previous`, forward with `Next`, and filters and
+  /// hides the overlay with `Activate` and `Back`.
+  pub(crate) fn action(&mut self, action: Action) {
+    match action {
+      Action::Previous => self.scroll_by(1),
+      Action::Next => self.scroll_by(-1),
+      Action::PreviousPage => self.scroll_by(LINES as isize),
+      Action::NextPage => self.scroll_by(-(LINES as isize)),
+      Action::Activate => self.more_verbose(),
+      Action::Back => self.open = false,
+      _ => {}
+    }
+  }
+
+  /// Draws the overlay over everything in `frame`, latest events at the
+  /// bottom.
+  pub(crate) fn draw(&self, frame: &mut Frame) {
+    let (width, height) = (frame.width as f32, frame.height as f32);
+    let palette = frame.palette;
+    frame.canvas.rect(
+      [0.0, 0.0],
+      [width, height],
+      Style::fill([0.0, 0.0, 0.0, 0.85]),
+    );
+    let line = height / (LINES + 1) as f32;
+    let size = line * 0.7;
+    let pad = size * 0.5;
+    let records = self.records();
+    let header = match self.scroll {
+      0 => format!("Log, {} and more severe", self.level),
+      scroll => format!("Log, {} and more severe, {} back", self.level, scroll),
+    };
+    frame.text.queue(
+      &TextSection::new(header)
+        .at(pad, line / 2.0)
+        .with_size(size)
+        .with_color(palette.text)
+        .with_align(Align::Left, VAlign::Center),
+    );
+    let shown = records.iter().rev().skip(self.scroll).take(LINES);
+    for (index, record) in shown.enumerate() {
+      let middle = height - line * (index as f32 + 0.5);
+      let text = format!(
+        "{} {:>5} {}: {}",
+        logging::time_of_day(record.time),
+        record.level,
+        record.target,
+        record.message
+      );
+      frame.text.queue(
+        &TextSection::new(text)
+          .at(pad, middle)
+          .with_size(size)
+          .with_color(color(record.level, palette))
+          .with_align(Align::Left, VAlign::Center)
+          .with_clip(0.0, width - pad, pad),
+      );
+    }
+  }
+}
+
+fn color(level: Level, palette: &Palette) -> [f32; 4] {
+  match level {
+    Level::ERROR => palette.warning,
+    Level::WARN => palette.accent,
+    Level::INFO => palette.text,
+    _ => palette.muted,
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use std::time::UNIX_EPOCH;
+
+  use super::*;
+
+  fn record(level: Level) -> LogRecord {
+    LogRecord {
+      time: UNIX_EPOCH,
+      level,
+      target: "windshield".to_string(),
+      message: String::new(),
+    }
+  }
+
+  #[test]
+  fn filters_and_scrolls_within_the_buffer() {
+    let buffer = LogBuffer::new(64);
+    let mut view = LogView::new(Some(buffer.clone()));
+    for index in 0..30 {
+      buffer.push(record(if index % 2 == 0 {
+        Level::INFO
+      } else {
+        Level::DEBUG
+      }));
+    }
+    assert_eq!(view.records().len(), 15);
+    view.action(Action::PreviousPage);
+    assert_eq!(view.scroll, 0);
+    view.action(Action::Activate);
+    assert_eq!(view.records().len(), 30);
+    view.action(Action::PreviousPage);
+    assert_eq!(view.scroll, 10);
+    for _ in 0..3 {
+      view.action(Action::Activate);
+    }
+    assert_eq!(view.level, Level::WARN);
+    assert!(view.records().is_empty());
+  }
+}
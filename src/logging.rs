@@ -0,0 +1,185 @@
+use std::collections::VecDeque;
+use std::fmt::{self, Write};
+#[cfg(any(target_arch = "wasm32", target_os = "android"))]
+use std::io;
+use std::sync::{Arc, Mutex};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use tracing::field::{Field, Visit};
+use tracing::{Event, Level, Subscriber};
+#[cfg(any(target_arch = "wasm32", target_os = "android"))]
+use tracing_subscriber::fmt::MakeWriter;
+use tracing_subscriber::layer::Context;
+use tracing_subscriber::Layer;
+
+use crate::clock;
+
+/// A tracing event as kept by a [`LogBuffer`].
+#[derive(Clone, Debug)]
+pub struct LogRecord {
+  pub time: SystemTime,
+  pub level: Level,
+  pub target: String,
+  pub message: String,
+}
+
+/// Ring buffer holding the most recent tracing events, so they can be shown
+/// inside the app or attached to reports.
+#[derive(Clone)]
+pub struct LogBuffer {
+  records: Arc<Mutex<VecDeque<LogRecord>>>,
+  capacity: usize,
+}
+
+impl LogBuffer {
+  pub fn new(capacity: usize) -> Self {
+    Self {
+      records: Arc::new(Mutex::new(VecDeque::with_capacity(capacity))),
+      capacity,
+    }
+  }
+
+  /// A subscriber layer mirroring every event into this buffer.
+  pub fn layer(&self) -> LogLayer {
+    LogLayer {
+      buffer: self.clone(),
+    }
+  }
+
+  /// Buffered events at `level` or more severe, oldest first.
+  pub fn records(&self, level: Level) -> Vec<LogRecord> {
+    self
+      .records
+      .lock()
+      .unwrap()
+      .iter()
+      .filter(|record| record.level <= level)
+      .cloned()
+      .collect()
+  }
+
+  pub(crate) fn push(&self, record: LogRecord) {
+    let mut records = self.records.lock().unwrap();
+    if records.len() == self.capacity {
+      records.pop_front();
+    }
+    records.push_back(record);
+  }
+}
+
+impl fmt::Debug for LogBuffer {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    f.debug_struct("LogBuffer")
+      .field("capacity", &self.capacity)
+      .finish_non_exhaustive()
+  }
+}
+
+pub struct LogLayer {
+  buffer: LogBuffer,
+}
+
+impl<S: Subscriber> Layer<S> for LogLayer {
+  fn on_event(&self, event: &Event<'_>, _ctx: Context<'_, S>) {
+    let metadata = event.metadata();
+    let mut visitor = MessageVisitor::default();
+    event.record(&mut visitor);
+
+    self.buffer.push(LogRecord {
+      time: clock::system_time(),
+      level: *metadata.level(),
+      target: metadata.target().to_string(),
+      message: visitor.message + &visitor.fields,
+    });
+  }
+}
+
+/// `time` as `HH:MM:SS.mmm` in UTC, the date hardly matters for what's
+/// still in a [`LogBuffer`].
+pub(crate) fn time_of_day(time: SystemTime) -> String {
+  let since_epoch = time.duration_since(UNIX_EPOCH).unwrap_or_default();
+  let seconds = since_epoch.as_secs() % (24 * 60 * 60);
+  format!(
+    "{:02}:{:02}:{:02}.{:03}",
+    seconds / 3600,
+    seconds / 60 % 60,
+    seconds % 60,
+    since_epoch.subsec_millis()
+  )
+}
+
+/// Collects the message and the remaining fields as `key=value` pairs.
+#[derive(Default)]
+struct MessageVisitor {
+  message: String,
+  fields: String,
+}
+
+impl Visit for MessageVisitor {
+  fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+    if field.name() == "message" {
+      let _ = write!(self.message, "{:?}", value);
+    } else {
+      let _ = write!(self.fields, " {}={:?}", field.name(), value);
+    }
+  }
+}
+
+/// Makes the writers of a [`fmt`](tracing_subscriber::fmt) layer pass each
+/// formatted event on as one line, for platforms where stdout goes
+/// nowhere, like the web and Android.
+#[cfg(any(target_arch = "wasm32", target_os = "android"))]
+#[derive(Clone, Copy)]
+pub(crate) struct LineWriter(pub(crate) fn(&str));
+
+#[cfg(any(target_arch = "wasm32", target_os = "android"))]
+impl<'a> MakeWriter<'a> for LineWriter {
+  type Writer = Line;
+
+  fn make_writer(&'a self) -> Self::Writer {
+    Line {
+      text: Vec::new(),
+      log: self.0,
+    }
+  }
+}
+
+#[cfg(any(target_arch = "wasm32", target_os = "android"))]
+/// One event, passed on when the formatter is done writing it.
+pub(crate) struct Line {
+  text: Vec<u8>,
+  log: fn(&str),
+}
+
+#[cfg(any(target_arch = "wasm32", target_os = "android"))]
+impl io::Write for Line {
+  fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+    self.text.extend_from_slice(buf);
+    Ok(buf.len())
+  }
+
+  fn flush(&mut self) -> io::Result<()> {
+    Ok(())
+  }
+}
+
+#[cfg(any(target_arch = "wasm32", target_os = "android"))]
+impl Drop for Line {
+  fn drop(&mut self) {
+    (self.log)(String::from_utf8_lossy(&self.text).trim_end());
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use std::time::Duration;
+
+  use super::*;
+
+  #[test]
+  fn shows_the_time_of_day() {
+    // The next day
+    let time = UNIX_EPOCH + Duration::from_secs(37 * 60 * 60 + 7) + Duration::from_millis(42);
+    assert_eq!(time_of_day(time), "13:00:07.042");
+  }
+}
@@ -0,0 +1,133 @@
+//! Results kept from one frame to the next by a hash of what they were
+//! computed from, like tessellated shapes and laid out text, see
+//! [`LruCache`].
+
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+
+/// Hash of `value` to key an [`LruCache`] with.
+pub(crate) fn content_hash(value: &impl Hash) -> u64 {
+  let mut hasher = DefaultHasher::new();
+  value.hash(&mut hasher);
+  hasher.finish()
+}
+
+/// Values by the [`content_hash`] of what they were computed from.
+///
+/// Every frame is a generation, started with
+/// [`next_generation`](Self::next_generation). Values not used for
+/// `max_age` generations are dropped then, and the least recently used
+/// ones beyond `capacity`.
+pub(crate) struct LruCache<V> {
+  // With the generation each was last used in
+  entries: HashMap<u64, (V, u64)>,
+  generation: u64,
+  capacity: usize,
+  max_age: u64,
+}
+
+impl<V> LruCache<V> {
+  pub(crate) fn new(capacity: usize, max_age: u64) -> Self {
+    Self {
+      entries: HashMap::new(),
+      generation: 0,
+      capacity,
+      max_age,
+    }
+  }
+
+  /// The value of `key`, keeping it for another `max_age` generations.
+  pub(crate) fn get(&mut self, key: u64) -> Option<&V> {
+    let (value, used) = self.entries.get_mut(&key)?;
+    *used = self.generation;
+    Some(value)
+  }
+
+  pub(crate) fn insert(&mut self, key: u64, value: V) -> &V {
+    self.entries.insert(key, (value, self.generation));
+    &self.entries[&key].0
+  }
+
+  /// The value of `key`, computed with `value` if there is none.
+  pub(crate) fn get_or_insert_with(&mut self, key: u64, value: impl FnOnce() -> V) -> &V {
+    let generation = self.generation;
+    let (value, used) = self
+      .entries
+      .entry(key)
+      .or_insert_with(|| (value(), generation));
+    *used = generation;
+    value
+  }
+
+  /// Drops every value, for when they'd come out differently.
+  pub(crate) fn clear(&mut self) {
+    self.entries.clear();
+  }
+
+  /// Drops the values that are too old or too many, then starts a
+  /// generation.
+  pub(crate) fn next_generation(&mut self) {
+    let oldest = self.generation.saturating_sub(self.max_age);
+    self.entries.retain(|_, (_, used)| *used >= oldest);
+    if self.entries.len() > self.capacity {
+      let excess = self.entries.len() - self.capacity;
+      let mut used: Vec<(u64, u64)> = self
+        .entries
+        .iter()
+        .map(|(key, (_, used))| (*used, *key))
+        .collect();
+      used.select_nth_unstable(excess - 1);
+      for (_, key) in &used[..excess] {
+        self.entries.remove(key);
+      }
+    }
+    self.generation += 1;
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn drops_values_not_used_for_max_age() {
+    let mut cache = LruCache::new(16, 2);
+    cache.insert(1, "kept");
+    cache.insert(2, "dropped");
+    for _ in 0..4 {
+      cache.get(1);
+      cache.next_generation();
+    }
+    assert_eq!(cache.get(1), Some(&"kept"));
+    assert_eq!(cache.get(2), None);
+  }
+
+  #[test]
+  fn drops_least_recently_used_beyond_capacity() {
+    let mut cache = LruCache::new(2, 100);
+    cache.insert(1, 1);
+    cache.next_generation();
+    cache.insert(2, 2);
+    cache.next_generation();
+    cache.insert(3, 3);
+    cache.get(1);
+    cache.next_generation();
+    assert_eq!(cache.get(2), None);
+    assert_eq!(cache.get(1), Some(&1));
+    assert_eq!(cache.get(3), Some(&3));
+  }
+
+  #[test]
+  fn computes_missing_values_once() {
+    let mut cache = LruCache::new(16, 2);
+    let mut computed = 0;
+    for _ in 0..3 {
+      cache.get_or_insert_with(content_hash(&"text"), || {
+        computed += 1;
+        4
+      });
+    }
+    assert_eq!(computed, 1);
+  }
+}
@@ -1,7 +1,392 @@
-use windshield_rs::run;
+// Only the web build's empty main is left on wasm32
+#![cfg_attr(target_arch = "wasm32", allow(dead_code, unused_imports))]
 
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+use tracing_subscriber::filter::LevelFilter;
+use tracing_subscriber::prelude::*;
+use windshield_rs::bench::{BenchReport, BenchScene};
+use windshield_rs::build_info::build_info;
+use windshield_rs::clock::ScaledClock;
+use windshield_rs::config::{Config, Fullscreen, Presentation};
+use windshield_rs::data::gps::{self, GpsDevice};
+use windshield_rs::data::imu::Accelerometer;
+use windshield_rs::data::MockSource;
+use windshield_rs::layer_shell::{Layer, LayerShell};
+use windshield_rs::logging::LogBuffer;
+use windshield_rs::mirror::Mirror;
+use windshield_rs::pipeline_cache;
+use windshield_rs::theme::ThemeMode;
+use windshield_rs::watchdog::{self, SysfsPin, TogglePin, TouchFile, UdpPacket};
+use windshield_rs::WindshieldApp;
+
+// The web build is started from the page instead, see windshield_rs::web
+#[cfg(target_arch = "wasm32")]
+fn main() {}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn main() {
+  let args: Vec<String> = std::env::args().collect();
+  if args.iter().any(|arg| arg == "--version") {
+    println!("{}", build_info());
+    return;
+  }
+
+  let log_buffer = LogBuffer::new(512);
+  tracing_subscriber::registry()
+    .with(tracing_subscriber::fmt::layer().with_filter(LevelFilter::INFO))
+    .with(log_buffer.layer().with_filter(LevelFilter::DEBUG))
+    .init();
+
+  // Sets environment variables, so before the runtime or anything else
+  // starts threads that might read them
+  if let Some(dir) = arg_value(&args, "--pipeline-cache") {
+    if let Err(err) = pipeline_cache::use_dir(Path::new(dir)) {
+      tracing::warn!("not caching shaders: {}", err);
+    }
+  }
+  run(args, log_buffer);
+}
+
+#[cfg(not(target_arch = "wasm32"))]
 #[tokio::main(flavor = "current_thread")]
-async fn main() {
-  tracing_subscriber::fmt::init();
-  run().await;
+async fn run(args: Vec<String>, log_buffer: LogBuffer) {
+  let mut builder = WindshieldApp::builder()
+    .with_overlay(args.iter().any(|arg| arg == "--overlay"))
+    .with_transparent(args.iter().any(|arg| arg == "--transparent"))
+    .with_hot_reload(args.iter().any(|arg| arg == "--hot-reload"))
+    .with_high_contrast(args.iter().any(|arg| arg == "--high-contrast"))
+    .with_reduce_motion(args.iter().any(|arg| arg == "--reduce-motion"))
+    .with_right_to_left(args.iter().any(|arg| arg == "--rtl"))
+    .with_deterministic(args.iter().any(|arg| arg == "--deterministic"))
+    .with_prewarm(args.iter().any(|arg| arg == "--prewarm"))
+    .with_log_buffer(log_buffer);
+  // `--layer-shell` alone is the top layer, covering the output
+  if let Some(index) = args.iter().position(|arg| arg == "--layer-shell") {
+    let mut shell = LayerShell::default();
+    if let Some(Ok(layer)) = args.get(index + 1).map(|name| name.parse::<Layer>()) {
+      shell.layer = layer;
+    }
+    if let Some(edges) = arg_value(&args, "--layer-anchor") {
+      match edges.parse() {
+        Ok(anchor) => shell.anchor = anchor,
+        Err(err) => tracing::warn!("{}", err),
+      }
+    }
+    if let Some(zone) = arg_value(&args, "--exclusive-zone") {
+      match zone.parse() {
+        Ok(zone) => shell.exclusive_zone = zone,
+        Err(_) => tracing::warn!("invalid exclusive zone {:?}", zone),
+      }
+    }
+    builder = builder.with_layer_shell(shell);
+  }
+  if args.iter().any(|arg| arg == "--kiosk") {
+    builder = builder.with_kiosk();
+  }
+  if let Some(name) = arg_value(&args, "--fullscreen") {
+    match name.parse::<Fullscreen>() {
+      Ok(fullscreen) => builder = builder.with_fullscreen(fullscreen),
+      Err(err) => tracing::warn!("{}", err),
+    }
+  }
+  if args.iter().any(|arg| arg == "--hide-cursor") {
+    builder = builder.with_hide_cursor(true);
+  }
+  if let Some(dir) = arg_value(&args, "--crash-dir") {
+    builder = builder.with_crash_dir(PathBuf::from(dir));
+  }
+  if let Some(dir) = arg_value(&args, "--snapshot-dir") {
+    builder = builder.with_snapshot_dir(PathBuf::from(dir));
+  }
+  if let Some(path) = arg_value(&args, "--restore") {
+    builder = builder.with_restore(PathBuf::from(path));
+  }
+  if let Some(path) = arg_value(&args, "--record") {
+    builder = builder.with_record(PathBuf::from(path));
+  }
+  if let Some(path) = arg_value(&args, "--replay") {
+    builder = builder.with_replay(PathBuf::from(path));
+  }
+  if args.iter().any(|arg| arg == "--mirror") {
+    builder = builder.with_mirror(Mirror::Horizontal);
+  } else if args.iter().any(|arg| arg == "--mirror-both") {
+    builder = builder.with_mirror(Mirror::Both);
+  }
+  if args.iter().any(|arg| arg == "--mock-data") {
+    builder = builder.with_data_source(MockSource::default());
+  }
+  if let Some(device) = arg_value(&args, "--obd") {
+    builder = builder.with_obd(PathBuf::from(device));
+  }
+  if let Some(device) = arg_value(&args, "--gps") {
+    builder = builder.with_gps(GpsDevice::Serial {
+      device: PathBuf::from(device),
+      baud_rate: gps::DEFAULT_BAUD_RATE,
+    });
+  } else if args.iter().any(|arg| arg == "--gpsd") {
+    builder = builder.with_gps(GpsDevice::Gpsd {
+      address: gps::GPSD_ADDRESS.to_string(),
+    });
+  }
+  #[cfg(feature = "weather-http")]
+  if let Some(position) = arg_value(&args, "--weather") {
+    use windshield_rs::data::weather::{self, OpenMeteo, WeatherSource};
+    match position
+      .split_once(',')
+      .map(|(lat, lon)| (lat.parse(), lon.parse()))
+    {
+      Some((Ok(latitude), Ok(longitude))) => {
+        let provider = OpenMeteo::new(latitude, longitude);
+        builder = builder.with_data_source(WeatherSource::new(provider, weather::DEFAULT_INTERVAL));
+      }
+      _ => tracing::warn!(
+        "invalid weather position {:?}, expected <lat>,<lon>",
+        position
+      ),
+    }
+  }
+  #[cfg(feature = "routing-http")]
+  {
+    builder = navigation(&args, builder);
+  }
+  if let Some(path) = arg_value(&args, "--traffic") {
+    use windshield_rs::map::traffic::{self, TrafficFile, TrafficSource};
+    builder = builder.with_data_source(TrafficSource::new(
+      TrafficFile::new(path),
+      traffic::DEFAULT_INTERVAL,
+    ));
+  }
+  if let Some(device) = arg_value(&args, "--light-sensor") {
+    builder = builder.with_light_sensor(PathBuf::from(device));
+  }
+  if let Some(device) = arg_value(&args, "--accelerometer") {
+    builder = builder.with_data_source(Accelerometer::new(device));
+  }
+  if let Some(device) = arg_value(&args, "--backlight") {
+    builder = builder.with_backlight(PathBuf::from(device));
+  }
+  if let Some(path) = arg_value(&args, "--frame-output") {
+    builder = builder.with_frame_output(PathBuf::from(path));
+  }
+  if let Some(path) = arg_value(&args, "--input-map") {
+    builder = builder.with_input_map(PathBuf::from(path));
+  }
+  match arg_value(&args, "--theme") {
+    Some("day") => builder = builder.with_theme_mode(ThemeMode::Day),
+    Some("night") => builder = builder.with_theme_mode(ThemeMode::Night),
+    _ => {}
+  }
+  if let Some(name) = arg_value(&args, "--present-mode") {
+    match name.parse::<Presentation>() {
+      Ok(presentation) => builder = builder.with_present_mode(presentation.mode()),
+      Err(err) => tracing::warn!("{}", err),
+    }
+  }
+  if let Some(max_fps) = arg_value(&args, "--max-fps") {
+    match max_fps.parse() {
+      Ok(max_fps) => builder = builder.with_max_fps(max_fps),
+      Err(_) => tracing::warn!("invalid frame rate {:?}", max_fps),
+    }
+  }
+  if let Some(scale) = arg_value(&args, "--time-scale") {
+    match scale.parse::<f64>() {
+      Ok(scale) if scale > 0.0 => builder = builder.with_clock(ScaledClock::new(scale)),
+      _ => tracing::warn!("invalid time scale {:?}", scale),
+    }
+  }
+  if let Some(heartbeat) = arg_value(&args, "--heartbeat") {
+    let seconds = heartbeat.parse().ok();
+    match seconds.and_then(|seconds| Duration::try_from_secs_f32(seconds).ok()) {
+      Some(heartbeat) => builder = builder.with_heartbeat(heartbeat),
+      None => tracing::warn!("invalid heartbeat {:?}", heartbeat),
+    }
+  }
+  if let Some(path) = arg_value(&args, "--watchdog-file") {
+    builder = builder.with_watchdog(TouchFile::new(path), watchdog::DEFAULT_INTERVAL);
+  } else if let Some(address) = arg_value(&args, "--watchdog-udp") {
+    match address.parse().map(UdpPacket::new) {
+      Ok(Ok(packet)) => builder = builder.with_watchdog(packet, watchdog::DEFAULT_INTERVAL),
+      Ok(Err(err)) => tracing::warn!("unable to open watchdog socket: {}", err),
+      Err(_) => tracing::warn!("invalid watchdog address {:?}", address),
+    }
+  } else if let Some(line) = arg_value(&args, "--watchdog-gpio") {
+    match line.parse() {
+      Ok(line) => {
+        let pin = TogglePin::new(SysfsPin::new(line));
+        builder = builder.with_watchdog(pin, watchdog::DEFAULT_INTERVAL);
+      }
+      Err(_) => tracing::warn!("invalid watchdog GPIO line {:?}", line),
+    }
+  }
+  if let Some(dir) = arg_value(&args, "--shader-dir") {
+    builder = builder.with_shader_dir(PathBuf::from(dir));
+  }
+  if let Some(path) = arg_value(&args, "--prewarm-list") {
+    builder = builder.with_prewarm_list(PathBuf::from(path));
+  }
+  if let Some(after) = arg_value(&args, "--fallback-after") {
+    let seconds = after.parse().ok();
+    match seconds.and_then(|seconds| Duration::try_from_secs_f32(seconds).ok()) {
+      Some(after) => builder = builder.with_fallback_after(after),
+      None => tracing::warn!("invalid fallback timeout {:?}", after),
+    }
+  }
+  if let Some(after) = arg_value(&args, "--stale-after") {
+    let seconds = after.parse().ok();
+    match seconds.and_then(|seconds| Duration::try_from_secs_f32(seconds).ok()) {
+      Some(after) => builder = builder.with_stale_after((!after.is_zero()).then_some(after)),
+      None => tracing::warn!("invalid stale timeout {:?}", after),
+    }
+  }
+  if let Some(delay) = arg_value(&args, "--ignition-off-delay") {
+    let seconds = delay.parse().ok();
+    match seconds.and_then(|seconds| Duration::try_from_secs_f32(seconds).ok()) {
+      Some(delay) => builder = builder.with_ignition_off_delay(delay),
+      None => tracing::warn!("invalid ignition off delay {:?}", delay),
+    }
+  }
+  if let Some(scale) = arg_value(&args, "--ui-scale") {
+    match scale.parse().ok().filter(|scale: &f32| *scale > 0.0) {
+      Some(scale) => builder = builder.with_ui_scale(scale),
+      None => tracing::warn!("invalid UI scale {:?}", scale),
+    }
+  }
+  if let Some(budget) = arg_value(&args, "--startup-budget") {
+    let seconds = budget.parse().ok();
+    match seconds.and_then(|seconds| Duration::try_from_secs_f32(seconds).ok()) {
+      Some(budget) => builder = builder.with_startup_budget(budget),
+      None => tracing::warn!("invalid startup budget {:?}", budget),
+    }
+  }
+  let config = arg_value(&args, "--config")
+    .map(PathBuf::from)
+    .or_else(|| Config::default_path().filter(|path| path.exists()));
+  if let Some(path) = config {
+    builder = builder.with_config(path);
+  }
+  // Renders a single frame to a file instead of opening a window
+  let screenshot = arg_value(&args, "--screenshot").or_else(|| {
+    args
+      .iter()
+      .any(|arg| arg == "--headless")
+      .then_some("screenshot.png")
+  });
+  let bench = match arg_value(&args, "--bench").map(str::parse::<BenchScene>) {
+    Some(Ok(scene)) => Some(scene),
+    Some(Err(err)) => {
+      tracing::error!("{}", err);
+      std::process::exit(1);
+    }
+    None => None,
+  };
+  let seconds = arg_value(&args, "--bench-seconds").map_or(Ok(10.0), str::parse::<f64>);
+  let duration = match seconds
+    .ok()
+    .and_then(|seconds| Duration::try_from_secs_f64(seconds).ok())
+  {
+    Some(duration) => duration,
+    None => {
+      tracing::error!("invalid bench duration, expected seconds");
+      std::process::exit(1);
+    }
+  };
+  let headless = args.iter().any(|arg| arg == "--headless");
+  let result = match (bench, screenshot) {
+    // Prints how long frames took instead, windowed timed by how often
+    // they're presented, so without vsync with `--present-mode immediate`
+    (Some(scene), _) if headless => {
+      let app = builder.on_draw(scene.on_draw()).build();
+      app.bench(duration).await.map(|times| {
+        println!("{}", BenchReport::new(scene.name(), &times).to_json());
+      })
+    }
+    (Some(scene), _) => {
+      let mut times = Vec::new();
+      let mut last: Option<Instant> = None;
+      let started = Instant::now();
+      let app = builder
+        .on_draw(scene.on_draw())
+        .on_frame(move |_| {
+          let now = Instant::now();
+          times.extend(last.map(|last| now - last));
+          last = Some(now);
+          if started.elapsed() >= duration {
+            println!("{}", BenchReport::new(scene.name(), &times).to_json());
+            std::process::exit(0);
+          }
+        })
+        .build();
+      app.run().await
+    }
+    (None, Some(path)) => builder.build().screenshot(path).await,
+    (None, None) => builder.build().run().await,
+  };
+  if let Err(err) = result {
+    tracing::error!("{}", err);
+    std::process::exit(1);
+  }
+}
+
+/// Finds routes with `--valhalla <url>`, `--osrm <url>` or the OSRM demo
+/// server and places with `--nominatim <url>` or OpenStreetMap's server
+/// for the search screens of the scene, navigating `--route
+/// <lat>,<lon>:<lat>,<lon>` right away if given.
+#[cfg(feature = "routing-http")]
+fn navigation(
+  args: &[String],
+  builder: windshield_rs::WindshieldAppBuilder,
+) -> windshield_rs::WindshieldAppBuilder {
+  use windshield_rs::map::geocode::{Nominatim, SearchSource, NOMINATIM_PUBLIC};
+  use windshield_rs::map::route::{Osrm, RouteSource, Valhalla, OSRM_DEMO};
+  use windshield_rs::map::Coordinate;
+  use windshield_rs::widgets::SearchScreen;
+
+  let trip = arg_value(args, "--route");
+  if trip.is_none() && !args.iter().any(|arg| arg == "--search") {
+    return builder;
+  }
+  let (builder, navigator) = match arg_value(args, "--valhalla") {
+    Some(url) => {
+      let (source, navigator) = RouteSource::new(Valhalla::new(url));
+      (builder.with_data_source(source), navigator)
+    }
+    None => {
+      let url = arg_value(args, "--osrm").unwrap_or(OSRM_DEMO);
+      let (source, navigator) = RouteSource::new(Osrm::new(url));
+      (builder.with_data_source(source), navigator)
+    }
+  };
+  let url = arg_value(args, "--nominatim").unwrap_or(NOMINATIM_PUBLIC);
+  let (source, searcher) = SearchSource::new(Nominatim::new(url));
+  let builder = builder.with_data_source(source);
+
+  let coordinate = |position: &str| match position.split_once(',') {
+    Some((lat, lon)) => Some(Coordinate::new(lat.parse().ok()?, lon.parse().ok()?)),
+    None => None,
+  };
+  if let Some(trip) = trip {
+    match trip
+      .split_once(':')
+      .map(|(from, to)| (coordinate(from), coordinate(to)))
+    {
+      Some((Some(from), Some(to))) => navigator.navigate(from, to),
+      _ => tracing::warn!("invalid route {:?}, expected <lat>,<lon>:<lat>,<lon>", trip),
+    }
+  }
+  builder.on_scene(move |scene| {
+    scene.for_each_mut(|search: &mut SearchScreen| {
+      search.connect(searcher.clone(), navigator.clone());
+    })
+  })
+}
+
+/// Value following `name` on the command line, e.g. `--crash-dir <dir>`.
+fn arg_value<'a>(args: &'a [String], name: &str) -> Option<&'a str> {
+  args
+    .iter()
+    .position(|arg| arg == name)
+    .and_then(|index| args.get(index + 1))
+    .map(String::as_str)
 }
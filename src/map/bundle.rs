@@ -0,0 +1,197 @@
+use std::path::{Component, Path, PathBuf};
+
+use serde::Deserialize;
+
+use crate::error::WindshieldError;
+use crate::map::{Coordinate, MapData};
+
+/// File describing a bundle, in its directory.
+pub const INFO_FILE: &str = "bundle.toml";
+
+/// Part of the world a [`MapBundle`] covers, in degrees.
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize)]
+pub struct Region {
+  pub south: f64,
+  pub west: f64,
+  pub north: f64,
+  pub east: f64,
+}
+
+impl Region {
+  pub fn center(&self) -> Coordinate {
+    Coordinate::new(
+      (self.south + self.north) / 2.0,
+      (self.west + self.east) / 2.0,
+    )
+  }
+
+  pub fn contains(&self, coordinate: Coordinate) -> bool {
+    (self.south..=self.north).contains(&coordinate.latitude)
+      && (self.west..=self.east).contains(&coordinate.longitude)
+  }
+}
+
+/// A GeoJSON file of a [`MapBundle`], see [`MapData::from_geojson`].
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+pub struct Layer {
+  /// Relative to the bundle's directory, and within it.
+  pub file: PathBuf,
+  /// Bytes the file has.
+  pub size: u64,
+  /// CRC-32 of the file, as hexadecimal digits.
+  pub crc32: String,
+}
+
+/// What's in [`INFO_FILE`].
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+pub struct BundleInfo {
+  /// What the region is called, like `Berlin`.
+  pub name: String,
+  /// When the bundle was made or from which data, to tell bundles apart.
+  #[serde(default)]
+  pub version: String,
+  pub region: Region,
+  pub layers: Vec<Layer>,
+}
+
+/// A map prepared for use without a connection: a directory of GeoJSON
+/// layers described by a [`INFO_FILE`] with the region they cover and the
+/// size and checksum of every layer, so a bundle copied over incompletely
+/// or corrupted on the way isn't shown half broken.
+///
+/// ```toml
+/// name = "Berlin"
+/// version = "2026-10-01"
+/// region = { south = 52.33, west = 13.08, north = 52.68, east = 13.76 }
+///
+/// [[layers]]
+/// file = "roads.geojson"
+/// size = 48213
+/// crc32 = "1c291ca3"
+/// ```
+#[derive(Clone, Debug)]
+pub struct MapBundle {
+  pub info: BundleInfo,
+  pub data: MapData,
+}
+
+impl MapBundle {
+  /// Reads and checks the bundle in `dir`, failing if any layer is missing,
+  /// has another size or checksum than described or isn't valid GeoJSON.
+  pub fn open(dir: impl AsRef<Path>) -> Result<Self, WindshieldError> {
+    let dir = dir.as_ref();
+    let invalid = |message: String| WindshieldError::InvalidMap {
+      path: dir.to_path_buf(),
+      message,
+    };
+    let info_path = dir.join(INFO_FILE);
+    let source =
+      std::fs::read_to_string(&info_path).map_err(|source| WindshieldError::ReadFile {
+        path: info_path.clone(),
+        source,
+      })?;
+    let info: BundleInfo = toml::from_str(&source).map_err(|err| invalid(err.to_string()))?;
+
+    let mut features = Vec::new();
+    for layer in &info.layers {
+      let name = layer.file.display();
+      // Bundles come from elsewhere, their layers stay inside them
+      let inside = layer
+        .file
+        .components()
+        .all(|component| matches!(component, Component::Normal(_)));
+      if !inside {
+        return Err(invalid(format!("layer {} is outside the bundle", name)));
+      }
+      let path = dir.join(&layer.file);
+      let bytes = std::fs::read(&path).map_err(|source| WindshieldError::ReadFile {
+        path: path.clone(),
+        source,
+      })?;
+      if bytes.len() as u64 != layer.size {
+        return Err(invalid(format!(
+          "layer {} has {} bytes rather than {}",
+          name,
+          bytes.len(),
+          layer.size
+        )));
+      }
+      let expected = u32::from_str_radix(&layer.crc32, 16)
+        .map_err(|_| invalid(format!("invalid checksum {:?} of {}", layer.crc32, name)))?;
+      let actual = crc32fast::hash(&bytes);
+      if actual != expected {
+        return Err(invalid(format!(
+          "layer {} has checksum {:08x} rather than {:08x}",
+          name, actual, expected
+        )));
+      }
+      let source = String::from_utf8(bytes).map_err(|err| invalid(format!("{}: {}", name, err)))?;
+      let layer =
+        MapData::from_geojson(&source).map_err(|err| invalid(format!("{}: {}", name, err)))?;
+      features.extend_from_slice(layer.features());
+    }
+    tracing::info!(
+      "opened map bundle {} {} with {} features",
+      info.name,
+      info.version,
+      features.len()
+    );
+    Ok(Self {
+      info,
+      data: MapData::new(features),
+    })
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  const LAYER: &str = r#"{ "type": "FeatureCollection", "features": [
+    { "type": "Feature", "properties": { "kind": "road", "name": "Main Street" },
+      "geometry": { "type": "LineString", "coordinates": [[13.4, 52.5], [13.41, 52.51]] } }
+  ] }"#;
+
+  fn bundle(name: &str, layer: &str, size: usize, crc32: u32) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("windshield-{}-{}", name, std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    std::fs::write(dir.join("roads.geojson"), LAYER).unwrap();
+    std::fs::write(
+      dir.join(INFO_FILE),
+      format!(
+        "name = \"Test\"\nregion = {{ south = 52.0, west = 13.0, north = 53.0, east = 14.0 }}\n\
+         [[layers]]\nfile = \"{}\"\nsize = {}\ncrc32 = \"{:08x}\"",
+        layer, size, crc32
+      ),
+    )
+    .unwrap();
+    dir
+  }
+
+  #[test]
+  fn opens_bundles_checking_their_layers() {
+    let crc32 = crc32fast::hash(LAYER.as_bytes());
+    let dir = bundle("bundle", "roads.geojson", LAYER.len(), crc32);
+    let opened = MapBundle::open(&dir).unwrap();
+    assert_eq!(opened.info.name, "Test");
+    assert_eq!(opened.data.features().len(), 1);
+    assert!(opened.info.region.contains(opened.info.region.center()));
+    std::fs::remove_dir_all(dir).unwrap();
+
+    for (name, layer, size, crc32) in [
+      ("truncated", "roads.geojson", LAYER.len() + 1, crc32),
+      ("corrupted", "roads.geojson", LAYER.len(), crc32 ^ 1),
+      ("escaping", "../roads.geojson", LAYER.len(), crc32),
+    ] {
+      let dir = bundle(name, layer, size, crc32);
+      let err = MapBundle::open(&dir).unwrap_err();
+      assert!(
+        matches!(err, WindshieldError::InvalidMap { .. }),
+        "{}: {}",
+        name,
+        err
+      );
+      std::fs::remove_dir_all(dir).unwrap();
+    }
+  }
+}
@@ -0,0 +1,172 @@
+//! Cutting shapes off at the edges of a widget, since the canvas draws
+//! everything it's given.
+
+use crate::widgets::Rect;
+
+/// The part of `polygon` inside `rect`, with the edge of `rect` closing it
+/// where it was cut off.
+pub(crate) fn polygon(polygon: &[[f32; 2]], rect: Rect) -> Vec<[f32; 2]> {
+  let mut points = polygon.to_vec();
+  // Sutherland-Hodgman, one edge of the rect at a time
+  for edge in [
+    Edge::Left(rect.x),
+    Edge::Right(rect.x + rect.width),
+    Edge::Top(rect.y),
+    Edge::Bottom(rect.y + rect.height),
+  ] {
+    let Some(&last) = points.last() else {
+      break;
+    };
+    let mut clipped = Vec::with_capacity(points.len() + 4);
+    let mut previous = last;
+    for &point in &points {
+      match (edge.inside(previous), edge.inside(point)) {
+        (true, true) => clipped.push(point),
+        (true, false) => clipped.push(edge.cross(previous, point)),
+        (false, true) => {
+          clipped.push(edge.cross(previous, point));
+          clipped.push(point);
+        }
+        (false, false) => {}
+      }
+      previous = point;
+    }
+    points = clipped;
+  }
+  points
+}
+
+#[derive(Clone, Copy)]
+enum Edge {
+  Left(f32),
+  Right(f32),
+  Top(f32),
+  Bottom(f32),
+}
+
+impl Edge {
+  fn inside(self, [x, y]: [f32; 2]) -> bool {
+    match self {
+      Edge::Left(left) => x >= left,
+      Edge::Right(right) => x <= right,
+      Edge::Top(top) => y >= top,
+      Edge::Bottom(bottom) => y <= bottom,
+    }
+  }
+
+  /// Where the edge crosses the segment from `a` to `b`.
+  fn cross(self, a: [f32; 2], b: [f32; 2]) -> [f32; 2] {
+    match self {
+      Edge::Left(x) | Edge::Right(x) => {
+        let t = (x - a[0]) / (b[0] - a[0]);
+        [x, a[1] + (b[1] - a[1]) * t]
+      }
+      Edge::Top(y) | Edge::Bottom(y) => {
+        let t = (y - a[1]) / (b[1] - a[1]);
+        [a[0] + (b[0] - a[0]) * t, y]
+      }
+    }
+  }
+}
+
+/// The parts of the line through `points` inside `rect`.
+pub(crate) fn line(points: &[[f32; 2]], rect: Rect) -> Vec<Vec<[f32; 2]>> {
+  let mut parts = Vec::new();
+  let mut part: Vec<[f32; 2]> = Vec::new();
+  for pair in points.windows(2) {
+    match segment(pair[0], pair[1], rect) {
+      Some((from, to)) => {
+        if part.last() != Some(&from) {
+          if part.len() > 1 {
+            parts.push(std::mem::take(&mut part));
+          }
+          part.clear();
+          part.push(from);
+        }
+        part.push(to);
+      }
+      None => {
+        if part.len() > 1 {
+          parts.push(std::mem::take(&mut part));
+        }
+        part.clear();
+      }
+    }
+  }
+  if part.len() > 1 {
+    parts.push(part);
+  }
+  parts
+}
+
+/// The part of the segment from `a` to `b` inside `rect`, by Liang-Barsky.
+fn segment(a: [f32; 2], b: [f32; 2], rect: Rect) -> Option<([f32; 2], [f32; 2])> {
+  let [dx, dy] = [b[0] - a[0], b[1] - a[1]];
+  let (mut start, mut end) = (0.0f32, 1.0f32);
+  for (p, q) in [
+    (-dx, a[0] - rect.x),
+    (dx, rect.x + rect.width - a[0]),
+    (-dy, a[1] - rect.y),
+    (dy, rect.y + rect.height - a[1]),
+  ] {
+    if p == 0.0 {
+      if q < 0.0 {
+        return None;
+      }
+      continue;
+    }
+    let t = q / p;
+    if p < 0.0 {
+      start = start.max(t);
+    } else {
+      end = end.min(t);
+    }
+    if start > end {
+      return None;
+    }
+  }
+  let at = |t: f32| [a[0] + dx * t, a[1] + dy * t];
+  Some((at(start), at(end)))
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  const RECT: Rect = Rect {
+    x: 0.0,
+    y: 0.0,
+    width: 10.0,
+    height: 10.0,
+  };
+
+  #[test]
+  fn cuts_polygons_at_the_edges() {
+    let clipped = polygon(&[[-5.0, 5.0], [5.0, -5.0], [15.0, 5.0], [5.0, 15.0]], RECT);
+    assert!(clipped
+      .iter()
+      .all(|[x, y]| (0.0..=10.0).contains(x) && (0.0..=10.0).contains(y)));
+    assert!(polygon(&[[20.0, 20.0], [30.0, 20.0], [30.0, 30.0]], RECT).is_empty());
+  }
+
+  #[test]
+  fn splits_lines_leaving_and_coming_back() {
+    let parts = line(
+      &[
+        [-5.0, 5.0],
+        [5.0, 5.0],
+        [5.0, 20.0],
+        [8.0, 20.0],
+        [8.0, 5.0],
+      ],
+      RECT,
+    );
+    assert_eq!(
+      parts,
+      [
+        vec![[0.0, 5.0], [5.0, 5.0], [5.0, 10.0]],
+        vec![[8.0, 10.0], [8.0, 5.0]],
+      ]
+    );
+  }
+}
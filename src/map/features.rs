@@ -0,0 +1,207 @@
+use serde::Deserialize;
+use serde_json::Value;
+
+use crate::map::Coordinate;
+
+/// What a [`Feature`] is, which decides how it's drawn. Declared in the
+/// order features are drawn in, bottom first.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FeatureKind {
+  Land,
+  Park,
+  Water,
+  Building,
+  Path,
+  Rail,
+  Road,
+  MajorRoad,
+  Motorway,
+  /// Named places like towns and points of interest, drawn as labels.
+  Place,
+}
+
+/// Shape of a [`Feature`] in points on the Web Mercator square, see
+/// [`Coordinate::project`].
+#[derive(Clone, Debug, PartialEq)]
+pub enum Geometry {
+  Point([f64; 2]),
+  Lines(Vec<Vec<[f64; 2]>>),
+  /// Polygons of an outer ring followed by their holes.
+  Polygons(Vec<Vec<Vec<[f64; 2]>>>),
+}
+
+/// One thing on the map, like a road, a lake or a town.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Feature {
+  pub kind: FeatureKind,
+  pub name: Option<String>,
+  pub geometry: Geometry,
+  /// Labels of higher priority are kept when labels overlap, like those
+  /// of cities over those of villages.
+  pub priority: i32,
+  // Smallest and largest point, to skip what isn't visible
+  bounds: ([f64; 2], [f64; 2]),
+}
+
+impl Feature {
+  pub fn new(kind: FeatureKind, name: Option<String>, geometry: Geometry) -> Self {
+    let mut min = [f64::MAX; 2];
+    let mut max = [f64::MIN; 2];
+    let mut extend = |[x, y]: [f64; 2]| {
+      min = [min[0].min(x), min[1].min(y)];
+      max = [max[0].max(x), max[1].max(y)];
+    };
+    match &geometry {
+      Geometry::Point(point) => extend(*point),
+      Geometry::Lines(lines) => lines.iter().flatten().copied().for_each(&mut extend),
+      Geometry::Polygons(polygons) => polygons
+        .iter()
+        .flatten()
+        .flatten()
+        .copied()
+        .for_each(&mut extend),
+    }
+    Self {
+      kind,
+      name,
+      geometry,
+      priority: 0,
+      bounds: (min, max),
+    }
+  }
+
+  pub fn with_priority(mut self, priority: i32) -> Self {
+    self.priority = priority;
+    self
+  }
+
+  /// The middle of the feature on the Web Mercator square.
+  pub fn center(&self) -> [f64; 2] {
+    let (min, max) = self.bounds;
+    [(min[0] + max[0]) / 2.0, (min[1] + max[1]) / 2.0]
+  }
+
+  /// Whether any of the feature is within `min..max` on the Web Mercator
+  /// square.
+  pub fn intersects(&self, (min, max): ([f64; 2], [f64; 2])) -> bool {
+    let (low, high) = self.bounds;
+    low[0] <= max[0] && high[0] >= min[0] && low[1] <= max[1] && high[1] >= min[1]
+  }
+}
+
+/// The features of a map, kept in the order they're drawn in.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct MapData {
+  features: Vec<Feature>,
+}
+
+impl MapData {
+  pub fn new(mut features: Vec<Feature>) -> Self {
+    features.sort_by_key(|feature| feature.kind);
+    Self { features }
+  }
+
+  /// Reads a GeoJSON feature collection, with what each feature is in its
+  /// `kind` property, named like [`FeatureKind`], its name in `name` and
+  /// the [priority](Feature::priority) of its label in `priority`.
+  /// Features of other kinds and geometry collections are left out.
+  ///
+  /// ```json
+  /// { "type": "FeatureCollection", "features": [{
+  ///   "type": "Feature",
+  ///   "properties": { "kind": "major_road", "name": "Unter den Linden", "priority": 2 },
+  ///   "geometry": { "type": "LineString", "coordinates": [[13.377, 52.516], [13.398, 52.517]] }
+  /// }] }
+  /// ```
+  pub fn from_geojson(source: &str) -> Result<Self, String> {
+    let collection: Value = serde_json::from_str(source).map_err(|err| err.to_string())?;
+    let features = collection
+      .get("features")
+      .and_then(Value::as_array)
+      .ok_or("not a GeoJSON feature collection")?;
+    let mut parsed = Vec::new();
+    for feature in features {
+      let properties = feature.get("properties");
+      let property = |name| properties.and_then(|properties| properties.get(name));
+      let Some(Ok(kind)) = property("kind").map(FeatureKind::deserialize) else {
+        continue;
+      };
+      let name = property("name").and_then(Value::as_str).map(str::to_string);
+      let Some(geometry) = feature.get("geometry").and_then(geometry) else {
+        continue;
+      };
+      let priority = property("priority").and_then(Value::as_i64).unwrap_or(0);
+      parsed.push(Feature::new(kind, name, geometry).with_priority(priority as i32));
+    }
+    Ok(Self::new(parsed))
+  }
+
+  pub fn features(&self) -> &[Feature] {
+    &self.features
+  }
+
+  pub fn is_empty(&self) -> bool {
+    self.features.is_empty()
+  }
+}
+
+fn geometry(geometry: &Value) -> Option<Geometry> {
+  let coordinates = geometry.get("coordinates")?;
+  Some(match geometry.get("type")?.as_str()? {
+    "Point" => Geometry::Point(position(coordinates)?),
+    "MultiPoint" => Geometry::Point(position(coordinates.get(0)?)?),
+    "LineString" => Geometry::Lines(vec![line(coordinates)?]),
+    "MultiLineString" => Geometry::Lines(many(coordinates, line)?),
+    "Polygon" => Geometry::Polygons(vec![many(coordinates, line)?]),
+    "MultiPolygon" => Geometry::Polygons(many(coordinates, |polygon| many(polygon, line))?),
+    _ => return None,
+  })
+}
+
+fn many<T>(value: &Value, parse: impl Fn(&Value) -> Option<T>) -> Option<Vec<T>> {
+  value.as_array()?.iter().map(parse).collect()
+}
+
+fn line(value: &Value) -> Option<Vec<[f64; 2]>> {
+  many(value, position)
+}
+
+/// A GeoJSON position, longitude first.
+fn position(value: &Value) -> Option<[f64; 2]> {
+  let longitude = value.get(0)?.as_f64()?;
+  let latitude = value.get(1)?.as_f64()?;
+  Some(Coordinate::new(latitude, longitude).project())
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn reads_geojson_in_drawing_order() {
+    let map = MapData::from_geojson(
+      r#"{ "type": "FeatureCollection", "features": [
+        { "type": "Feature", "properties": { "kind": "major_road", "name": "Main Street", "priority": 3 },
+          "geometry": { "type": "LineString", "coordinates": [[13.0, 52.0], [13.1, 52.1]] } },
+        { "type": "Feature", "properties": { "kind": "water" },
+          "geometry": { "type": "Polygon", "coordinates": [[[13.0, 52.0], [13.1, 52.0], [13.1, 52.1]]] } },
+        { "type": "Feature", "properties": { "kind": "helipad" },
+          "geometry": { "type": "Point", "coordinates": [13.0, 52.0] } },
+        { "type": "Feature", "properties": { "kind": "place", "name": "Town" },
+          "geometry": { "type": "Point", "coordinates": "nowhere" } }
+      ] }"#,
+    )
+    .unwrap();
+    let kinds: Vec<_> = map.features().iter().map(|feature| feature.kind).collect();
+    assert_eq!(kinds, [FeatureKind::Water, FeatureKind::MajorRoad]);
+    let road = &map.features()[1];
+    assert_eq!(road.name.as_deref(), Some("Main Street"));
+    assert_eq!((map.features()[0].priority, road.priority), (0, 3));
+    let near = Coordinate::new(52.05, 13.05).project();
+    assert!(road.intersects((near, near)));
+    let far = Coordinate::new(40.0, 13.05).project();
+    assert!(!road.intersects((far, far)));
+    assert!(MapData::from_geojson("[]").is_err());
+  }
+}
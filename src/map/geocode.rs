@@ -0,0 +1,332 @@
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use tokio::sync::mpsc::{self, UnboundedReceiver, UnboundedSender};
+
+use crate::data::{DataSource, Telemetry};
+use crate::map::{Coordinate, MapData};
+
+/// Something to navigate to, found by a [`Geocoder`].
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Place {
+  pub name: String,
+  /// Where it is, like the street and town, empty if unknown.
+  pub detail: String,
+  pub position: Coordinate,
+}
+
+/// What a [`Geocoder`] found for `query`, best match first.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct SearchResults {
+  pub query: String,
+  pub places: Vec<Place>,
+}
+
+/// Finds places by name or address, e.g. with a web service like
+/// [`Nominatim`] with the `routing-http` feature, or in the map shown with
+/// [`MapGeocoder`].
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+pub trait Geocoder: Send {
+  /// Places matching `query`, preferring those close to `near`.
+  async fn search(&mut self, query: &str, near: Option<Coordinate>) -> Result<Vec<Place>, String>;
+}
+
+#[derive(Debug)]
+struct Query {
+  text: String,
+  near: Option<Coordinate>,
+}
+
+/// Asks a [`SearchSource`] for places, from anywhere in the app.
+#[derive(Clone, Debug)]
+pub struct Searcher {
+  sender: UnboundedSender<Query>,
+}
+
+impl Searcher {
+  /// Searches for `query` near `near`, the results are reported as
+  /// [`Telemetry::search`] once found.
+  pub fn search(&self, query: impl Into<String>, near: Option<Coordinate>) {
+    // The source is gone with the app, there's nothing to search then
+    let _ = self.sender.send(Query {
+      text: query.into(),
+      near,
+    });
+  }
+}
+
+/// Reports what a [`Geocoder`] finds as [`Telemetry::search`], whenever its
+/// [`Searcher`] asks. Queries asked for while searching replace each
+/// other, only the latest is searched for next.
+pub struct SearchSource<G> {
+  geocoder: G,
+  receiver: UnboundedReceiver<Query>,
+}
+
+impl<G: Geocoder> SearchSource<G> {
+  pub fn new(geocoder: G) -> (Self, Searcher) {
+    let (sender, receiver) = mpsc::unbounded_channel();
+    (Self { geocoder, receiver }, Searcher { sender })
+  }
+}
+
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+impl<G: Geocoder> DataSource for SearchSource<G> {
+  async fn poll(&mut self) -> Telemetry {
+    loop {
+      let Some(mut query) = self.receiver.recv().await else {
+        // Every searcher is gone, nobody can search any more
+        return std::future::pending().await;
+      };
+      // Typing asks again with every key, what was typed over is moot
+      while let Ok(newer) = self.receiver.try_recv() {
+        query = newer;
+      }
+      let places = if query.text.trim().is_empty() {
+        Vec::new()
+      } else {
+        match self.geocoder.search(&query.text, query.near).await {
+          Ok(places) => places,
+          Err(err) => {
+            tracing::warn!("unable to search for {:?}: {}", query.text, err);
+            continue;
+          }
+        }
+      };
+      return Telemetry {
+        search: Some(Arc::new(SearchResults {
+          query: query.text,
+          places,
+        })),
+        ..Default::default()
+      };
+    }
+  }
+}
+
+/// Finds the named features of a map, like towns and roads, without a
+/// connection. Names match if they contain every word of the query,
+/// whatever the case.
+pub struct MapGeocoder {
+  pub data: Arc<MapData>,
+  /// Most places found.
+  pub limit: usize,
+}
+
+impl MapGeocoder {
+  /// At most 20 places in `data`.
+  pub fn new(data: impl Into<Arc<MapData>>) -> Self {
+    Self {
+      data: data.into(),
+      limit: 20,
+    }
+  }
+
+  fn find(&self, query: &str, near: Option<Coordinate>) -> Vec<Place> {
+    let words: Vec<_> = query.split_whitespace().map(str::to_lowercase).collect();
+    let mut found: Vec<(f64, Place)> = Vec::new();
+    for feature in self.data.features() {
+      let Some(name) = &feature.name else {
+        continue;
+      };
+      let lower = name.to_lowercase();
+      if !words.iter().all(|word| lower.contains(word.as_str())) {
+        continue;
+      }
+      let position = Coordinate::unproject(feature.center());
+      let distance = near.map_or(0.0, |near| near.distance(position));
+      // Roads are split into many features, the closest part stands for
+      // all of them
+      match found.iter_mut().find(|(_, place)| place.name == *name) {
+        Some((closest, place)) if distance < *closest => {
+          *closest = distance;
+          place.position = position;
+        }
+        Some(_) => {}
+        None => found.push((
+          distance,
+          Place {
+            name: name.clone(),
+            detail: String::new(),
+            position,
+          },
+        )),
+      }
+    }
+    // Names starting with the query first, then the closest
+    let starts = |place: &Place| !place.name.to_lowercase().starts_with(&*words[0]);
+    found
+      .sort_by(|(a, first), (b, second)| starts(first).cmp(&starts(second)).then(a.total_cmp(b)));
+    found.truncate(self.limit);
+    found.into_iter().map(|(_, place)| place).collect()
+  }
+}
+
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+impl Geocoder for MapGeocoder {
+  async fn search(&mut self, query: &str, near: Option<Coordinate>) -> Result<Vec<Place>, String> {
+    if query.split_whitespace().next().is_none() {
+      return Ok(Vec::new());
+    }
+    Ok(self.find(query, near))
+  }
+}
+
+/// A [Nominatim](https://nominatim.org) server, like the public one at
+/// [`NOMINATIM_PUBLIC`] that's fine for trying it out. Not available on the
+/// web.
+#[cfg(all(feature = "routing-http", not(target_arch = "wasm32")))]
+pub struct Nominatim {
+  pub url: String,
+  /// Most places found.
+  pub limit: usize,
+}
+
+/// The public Nominatim server of OpenStreetMap, see its usage policy
+/// before relying on it.
+#[cfg(all(feature = "routing-http", not(target_arch = "wasm32")))]
+pub const NOMINATIM_PUBLIC: &str = "https://nominatim.openstreetmap.org";
+
+#[cfg(all(feature = "routing-http", not(target_arch = "wasm32")))]
+impl Nominatim {
+  /// At most 10 places from the server at `url`.
+  pub fn new(url: impl Into<String>) -> Self {
+    Self {
+      url: url.into(),
+      limit: 10,
+    }
+  }
+}
+
+#[cfg(all(feature = "routing-http", not(target_arch = "wasm32")))]
+#[async_trait]
+impl Geocoder for Nominatim {
+  async fn search(&mut self, query: &str, near: Option<Coordinate>) -> Result<Vec<Place>, String> {
+    let url = format!("{}/search", self.url.trim_end_matches('/'));
+    let mut request = ureq::get(&url)
+      .query("q", query)
+      .query("format", "jsonv2")
+      .query("limit", &self.limit.to_string())
+      .set(
+        "User-Agent",
+        concat!("windshield-rs/", env!("CARGO_PKG_VERSION")),
+      );
+    if let Some(near) = near {
+      // About 50 km around, preferred but not required
+      let viewbox = format!(
+        "{},{},{},{}",
+        near.longitude - 0.5,
+        near.latitude + 0.3,
+        near.longitude + 0.5,
+        near.latitude - 0.3,
+      );
+      request = request.query("viewbox", &viewbox);
+    }
+    let body = crate::map::route::http::fetch(request, None).await?;
+    parse_nominatim(&body)
+  }
+}
+
+#[cfg(all(feature = "routing-http", not(target_arch = "wasm32")))]
+fn parse_nominatim(body: &str) -> Result<Vec<Place>, String> {
+  #[derive(Deserialize)]
+  struct Found {
+    lat: String,
+    lon: String,
+    #[serde(default)]
+    name: String,
+    display_name: String,
+  }
+
+  let found: Vec<Found> = serde_json::from_str(body).map_err(|err| err.to_string())?;
+  found
+    .into_iter()
+    .map(|found| {
+      let latitude = found.lat.parse().map_err(|_| "invalid latitude")?;
+      let longitude = found.lon.parse().map_err(|_| "invalid longitude")?;
+      // The display name starts with the name, the rest is the address
+      let (name, detail) = if !found.name.is_empty() {
+        let detail = found.display_name.strip_prefix(&found.name).unwrap_or("");
+        (
+          found.name.clone(),
+          detail.trim_start_matches(", ").to_string(),
+        )
+      } else {
+        match found.display_name.split_once(", ") {
+          Some((name, detail)) => (name.to_string(), detail.to_string()),
+          None => (found.display_name.clone(), String::new()),
+        }
+      };
+      Ok(Place {
+        name,
+        detail,
+        position: Coordinate::new(latitude, longitude),
+      })
+    })
+    .collect()
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::map::{Feature, FeatureKind, Geometry};
+
+  fn feature(kind: FeatureKind, name: &str, latitude: f64) -> Feature {
+    let point = Coordinate::new(latitude, 13.4).project();
+    Feature::new(kind, Some(name.to_string()), Geometry::Point(point))
+  }
+
+  #[test]
+  fn finds_named_features_closest_first() {
+    let geocoder = MapGeocoder::new(MapData::new(vec![
+      feature(FeatureKind::Road, "Old Main Street", 52.50),
+      feature(FeatureKind::Road, "Main Street", 52.60),
+      feature(FeatureKind::Road, "Main Street", 52.51),
+      feature(FeatureKind::Place, "Mainz", 52.90),
+      feature(FeatureKind::Park, "City Park", 52.50),
+    ]));
+    let near = Some(Coordinate::new(52.5, 13.4));
+    let names: Vec<_> = geocoder
+      .find("main", near)
+      .into_iter()
+      .map(|place| (place.name, (place.position.latitude * 100.0).round()))
+      .collect();
+    assert_eq!(
+      names,
+      [
+        ("Main Street".to_string(), 5251.0),
+        ("Mainz".to_string(), 5290.0),
+        ("Old Main Street".to_string(), 5250.0),
+      ]
+    );
+    assert_eq!(geocoder.find("STREET old", near).len(), 1);
+    assert!(geocoder.find("harbour", near).is_empty());
+  }
+
+  #[cfg(all(feature = "routing-http", not(target_arch = "wasm32")))]
+  #[test]
+  fn parses_nominatim_places() {
+    let places = parse_nominatim(
+      r#"[
+        { "lat": "52.5163", "lon": "13.3777", "name": "Brandenburger Tor",
+          "display_name": "Brandenburger Tor, Pariser Platz, Mitte, Berlin, Deutschland" },
+        { "lat": "52.52", "lon": "13.405", "name": "",
+          "display_name": "Berlin, Deutschland" }
+      ]"#,
+    )
+    .unwrap();
+    assert_eq!(places[0].name, "Brandenburger Tor");
+    assert_eq!(
+      places[0].detail,
+      "Pariser Platz, Mitte, Berlin, Deutschland"
+    );
+    assert_eq!(places[1].name, "Berlin");
+    assert_eq!(places[1].detail, "Deutschland");
+    assert!((places[0].position.longitude - 13.3777).abs() < 1e-9);
+    assert!(parse_nominatim(r#"[{ "lat": "north", "lon": "0", "display_name": "" }]"#).is_err());
+  }
+}
@@ -0,0 +1,226 @@
+//! Vector maps drawn by [`MapView`](crate::widgets::MapView): features
+//! read from GeoJSON or offline bundles of it, the camera looking at them,
+//! places found on them, routes across them and the traffic on those.
+
+use std::f64::consts::PI;
+
+use serde::{Deserialize, Serialize};
+
+pub use self::features::{Feature, FeatureKind, Geometry, MapData};
+
+pub mod bundle;
+pub(crate) mod clip;
+mod features;
+pub mod geocode;
+pub mod route;
+pub mod traffic;
+
+/// Width of the whole world in pixels at zoom 0, like web map tiles.
+pub const TILE_SIZE: f64 = 256.0;
+/// Closest zoom level, about a meter per pixel near the poles and a
+/// quarter of that at the equator.
+pub const MAX_ZOOM: f64 = 19.0;
+/// Furthest zoom level, showing continents.
+pub const MIN_ZOOM: f64 = 2.0;
+/// Latitude Web Mercator cuts off at in degrees, where the world becomes
+/// square.
+const MAX_LATITUDE: f64 = 85.051_128_78;
+
+/// A position on earth in degrees.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Coordinate {
+  /// Positive north of the equator.
+  pub latitude: f64,
+  /// Positive east of Greenwich.
+  pub longitude: f64,
+}
+
+impl Coordinate {
+  pub fn new(latitude: f64, longitude: f64) -> Self {
+    Self {
+      latitude,
+      longitude,
+    }
+  }
+
+  /// Where the coordinate is on the Web Mercator square, from 0 at the top
+  /// left to 1 at the bottom right.
+  pub fn project(self) -> [f64; 2] {
+    let latitude = self
+      .latitude
+      .clamp(-MAX_LATITUDE, MAX_LATITUDE)
+      .to_radians();
+    let x = (self.longitude + 180.0) / 360.0;
+    let y = (1.0 - (latitude.tan() + 1.0 / latitude.cos()).ln() / PI) / 2.0;
+    [x, y]
+  }
+
+  /// The coordinate at `point` on the Web Mercator square.
+  pub fn unproject([x, y]: [f64; 2]) -> Self {
+    let latitude = (PI * (1.0 - 2.0 * y)).sinh().atan().to_degrees();
+    Self::new(latitude, x * 360.0 - 180.0)
+  }
+
+  /// Meters to `other` along the surface, close enough for the distances
+  /// driven.
+  pub fn distance(self, other: Coordinate) -> f64 {
+    const EARTH_RADIUS: f64 = 6_371_000.0;
+    let (a, b) = (self.latitude.to_radians(), other.latitude.to_radians());
+    let dlat = b - a;
+    let dlon = (other.longitude - self.longitude).to_radians();
+    let h = (dlat / 2.0).sin().powi(2) + a.cos() * b.cos() * (dlon / 2.0).sin().powi(2);
+    2.0 * EARTH_RADIUS * h.sqrt().asin()
+  }
+}
+
+/// What part of the map is shown and how: the point in the middle, how far
+/// it's zoomed in and which way is up.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Camera {
+  /// Where the middle of the view is on the Web Mercator square.
+  pub center: [f64; 2],
+  /// Each level shows twice as much detail as the one before, see
+  /// [`MIN_ZOOM`] and [`MAX_ZOOM`].
+  pub zoom: f64,
+  /// Degrees clockwise from north that point up, like a heading.
+  pub bearing: f32,
+}
+
+impl Camera {
+  /// Looking at `center` from `zoom` with north up.
+  pub fn new(center: Coordinate, zoom: f64) -> Self {
+    Self {
+      center: center.project(),
+      zoom: zoom.clamp(MIN_ZOOM, MAX_ZOOM),
+      bearing: 0.0,
+    }
+  }
+
+  pub fn coordinate(&self) -> Coordinate {
+    Coordinate::unproject(self.center)
+  }
+
+  /// Pixels the Web Mercator square is wide at the current zoom.
+  pub fn world_size(&self) -> f64 {
+    TILE_SIZE * self.zoom.exp2()
+  }
+
+  /// Where `point` on the Web Mercator square is drawn, with the middle of
+  /// the view at `origin` on screen.
+  pub fn to_screen(&self, point: [f64; 2], origin: [f32; 2]) -> [f32; 2] {
+    let size = self.world_size();
+    let [dx, dy] = [
+      (point[0] - self.center[0]) * size,
+      (point[1] - self.center[1]) * size,
+    ];
+    let (sin, cos) = (-self.bearing as f64).to_radians().sin_cos();
+    [
+      origin[0] + (dx * cos - dy * sin) as f32,
+      origin[1] + (dx * sin + dy * cos) as f32,
+    ]
+  }
+
+  /// The point on the Web Mercator square drawn at `position`, the inverse
+  /// of [`to_screen`](Self::to_screen).
+  pub fn to_world(&self, position: [f32; 2], origin: [f32; 2]) -> [f64; 2] {
+    let size = self.world_size();
+    let [dx, dy] = [
+      (position[0] - origin[0]) as f64,
+      (position[1] - origin[1]) as f64,
+    ];
+    let (sin, cos) = (self.bearing as f64).to_radians().sin_cos();
+    [
+      self.center[0] + (dx * cos - dy * sin) / size,
+      self.center[1] + (dx * sin + dy * cos) / size,
+    ]
+  }
+
+  /// Zooms by `scale` and turns the map `rotation` radians clockwise around
+  /// `pivot` on screen, then moves it by `translation` pixels, the way
+  /// fingers pinching and turning it would.
+  pub fn manipulate(
+    &mut self,
+    pivot: [f32; 2],
+    translation: [f32; 2],
+    scale: f32,
+    rotation: f32,
+    origin: [f32; 2],
+  ) {
+    let before = [pivot[0] - translation[0], pivot[1] - translation[1]];
+    let anchor = self.to_world(before, origin);
+    self.zoom = (self.zoom + (scale.max(1e-3) as f64).log2()).clamp(MIN_ZOOM, MAX_ZOOM);
+    self.bearing = (self.bearing - rotation.to_degrees()).rem_euclid(360.0);
+    // Whatever was under the fingers stays there
+    let moved = self.to_world(pivot, origin);
+    self.center[0] += anchor[0] - moved[0];
+    self.center[1] += anchor[1] - moved[1];
+    self.center[1] = self.center[1].clamp(0.0, 1.0);
+    self.center[0] = self.center[0].rem_euclid(1.0);
+  }
+
+  /// Part of the Web Mercator square at least partly visible in a view of
+  /// `size` pixels, as the smallest and largest point, wherever it's
+  /// turned.
+  pub fn visible(&self, size: [f32; 2]) -> ([f64; 2], [f64; 2]) {
+    let radius = (size[0] as f64).hypot(size[1] as f64) / 2.0 / self.world_size();
+    let [x, y] = self.center;
+    ([x - radius, y - radius], [x + radius, y + radius])
+  }
+}
+
+/// Signed difference from `from` to `to` in degrees, the shorter way round.
+pub(crate) fn angle_between(from: f32, to: f32) -> f32 {
+  (to - from + 540.0).rem_euclid(360.0) - 180.0
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn close(a: [f64; 2], b: [f64; 2]) -> bool {
+    (a[0] - b[0]).abs() < 1e-9 && (a[1] - b[1]).abs() < 1e-9
+  }
+
+  #[test]
+  fn projects_web_mercator() {
+    assert_eq!(Coordinate::new(0.0, 0.0).project(), [0.5, 0.5]);
+    let berlin = Coordinate::new(52.52, 13.405);
+    let back = Coordinate::unproject(berlin.project());
+    assert!((back.latitude - berlin.latitude).abs() < 1e-9);
+    assert!((back.longitude - berlin.longitude).abs() < 1e-9);
+    let [_, top] = Coordinate::new(89.0, 0.0).project();
+    assert!(top.abs() < 1e-9);
+    let paris = Coordinate::new(48.8566, 2.3522);
+    assert!((berlin.distance(paris) / 1000.0 - 878.0).abs() < 5.0);
+  }
+
+  #[test]
+  fn screen_and_world_round_trip_when_turned() {
+    let mut camera = Camera::new(Coordinate::new(52.52, 13.405), 15.0);
+    camera.bearing = 90.0;
+    let origin = [400.0, 240.0];
+    // Heading east, so east is up
+    let east = [
+      camera.center[0] + 100.0 / camera.world_size(),
+      camera.center[1],
+    ];
+    let [x, y] = camera.to_screen(east, origin);
+    assert!((x - 400.0).abs() < 1e-3 && (y - 140.0).abs() < 1e-3);
+    assert!(close(camera.to_world([x, y], origin), east));
+  }
+
+  #[test]
+  fn manipulating_keeps_the_pivot_in_place() {
+    let mut camera = Camera::new(Coordinate::new(52.52, 13.405), 15.0);
+    let origin = [400.0, 240.0];
+    let pivot = [500.0, 300.0];
+    let under = camera.to_world(pivot, origin);
+    camera.manipulate(pivot, [0.0, 0.0], 2.0, 0.3, origin);
+    assert!((camera.zoom - 16.0).abs() < 1e-6);
+    assert!((angle_between(0.0, camera.bearing) + 0.3f32.to_degrees()).abs() < 1e-3);
+    assert!(close(camera.to_world(pivot, origin), under));
+    // Moved along with the fingers
+    camera.manipulate([520.0, 300.0], [20.0, 0.0], 1.0, 0.0, origin);
+    assert!(close(camera.to_world([520.0, 300.0], origin), under));
+  }
+}
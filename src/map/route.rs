@@ -0,0 +1,628 @@
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use tokio::sync::mpsc::{self, UnboundedReceiver, UnboundedSender};
+
+use crate::data::{DataSource, Telemetry};
+use crate::map::Coordinate;
+
+/// Which way a [`Maneuver`] goes.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Turn {
+  /// Setting off from the start of the route.
+  Depart,
+  Straight,
+  SlightLeft,
+  Left,
+  SharpLeft,
+  SlightRight,
+  Right,
+  SharpRight,
+  UTurn,
+  /// Into a roundabout, leaving it at [`Maneuver::exit`].
+  Roundabout,
+  /// Reaching the destination.
+  Arrive,
+}
+
+#[cfg(all(feature = "routing-http", not(target_arch = "wasm32")))]
+impl Turn {
+  /// From an OSRM maneuver modifier like `"slight left"`.
+  fn from_modifier(modifier: &str) -> Self {
+    match modifier {
+      "uturn" => Self::UTurn,
+      "sharp right" => Self::SharpRight,
+      "right" => Self::Right,
+      "slight right" => Self::SlightRight,
+      "slight left" => Self::SlightLeft,
+      "left" => Self::Left,
+      "sharp left" => Self::SharpLeft,
+      _ => Self::Straight,
+    }
+  }
+}
+
+/// Something to do along a [`Route`], like turning into another road.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Maneuver {
+  pub turn: Turn,
+  /// Exit to take out of a roundabout, counting from the one after
+  /// entering it.
+  pub exit: Option<u8>,
+  pub position: Coordinate,
+  /// m along the route from its start.
+  pub distance: f64,
+  /// Name of the road the maneuver leads onto, empty if it has none.
+  pub road: String,
+}
+
+/// How to get from one place to another, as found by a [`Router`].
+///
+/// An empty route, without geometry, ends the navigation when reported as
+/// [`Telemetry::route`].
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Route {
+  /// The way to drive, start first.
+  pub geometry: Vec<Coordinate>,
+  /// What to do along the way, in the order they come up.
+  pub maneuvers: Vec<Maneuver>,
+  /// m from start to destination.
+  pub distance: f64,
+  /// Seconds it's expected to take.
+  pub duration: f64,
+}
+
+/// Where along a [`Route`] a position is.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Progress {
+  /// m along the route from its start, to the closest point on it.
+  pub along: f64,
+  /// m to the closest point on the route.
+  pub off_route: f64,
+}
+
+impl Route {
+  pub fn is_empty(&self) -> bool {
+    self.geometry.len() < 2
+  }
+
+  /// Where along the route `position` is, by the closest point on it.
+  pub fn progress(&self, position: Coordinate) -> Option<Progress> {
+    let target = position.project();
+    // Meters per unit of the Web Mercator square, which stretches away
+    // from the equator
+    let scale = 40_075_016.686 * position.latitude.to_radians().cos();
+    let mut best: Option<(f64, f64)> = None;
+    let mut along = 0.0;
+    for pair in self.geometry.windows(2) {
+      let (a, b) = (pair[0].project(), pair[1].project());
+      let length = pair[0].distance(pair[1]);
+      let [dx, dy] = [b[0] - a[0], b[1] - a[1]];
+      let squared = dx * dx + dy * dy;
+      let t = if squared > 0.0 {
+        (((target[0] - a[0]) * dx + (target[1] - a[1]) * dy) / squared).clamp(0.0, 1.0)
+      } else {
+        0.0
+      };
+      let closest = [a[0] + dx * t, a[1] + dy * t];
+      let off = (target[0] - closest[0]).hypot(target[1] - closest[1]) * scale;
+      if best.is_none_or(|(_, best)| off < best) {
+        best = Some((along + length * t, off));
+      }
+      along += length;
+    }
+    best.map(|(along, off_route)| Progress { along, off_route })
+  }
+
+  /// The next maneuver `along` m from the start and how many m away it is.
+  pub fn upcoming(&self, along: f64) -> Option<(&Maneuver, f64)> {
+    self
+      .maneuvers
+      .iter()
+      .find(|maneuver| maneuver.distance > along)
+      .map(|maneuver| (maneuver, maneuver.distance - along))
+  }
+}
+
+/// Finds routes, e.g. with a web service, see [`Osrm`] and [`Valhalla`]
+/// with the `routing-http` feature.
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+pub trait Router: Send {
+  async fn route(&mut self, from: Coordinate, to: Coordinate) -> Result<Route, String>;
+}
+
+#[derive(Debug)]
+enum Request {
+  Navigate { from: Coordinate, to: Coordinate },
+  Cancel,
+}
+
+/// Tells a [`RouteSource`] where to go, from anywhere in the app.
+#[derive(Clone, Debug)]
+pub struct Navigator {
+  sender: UnboundedSender<Request>,
+}
+
+impl Navigator {
+  /// Finds a route from `from` to `to`, replacing the current one once
+  /// it's found.
+  pub fn navigate(&self, from: Coordinate, to: Coordinate) {
+    // The source is gone with the app, there's nothing to navigate then
+    let _ = self.sender.send(Request::Navigate { from, to });
+  }
+
+  /// Ends the navigation.
+  pub fn cancel(&self) {
+    let _ = self.sender.send(Request::Cancel);
+  }
+}
+
+/// Reports the routes a [`Router`] finds as [`Telemetry::route`], whenever
+/// its [`Navigator`] asks for one.
+pub struct RouteSource<R> {
+  router: R,
+  receiver: UnboundedReceiver<Request>,
+}
+
+impl<R: Router> RouteSource<R> {
+  pub fn new(router: R) -> (Self, Navigator) {
+    let (sender, receiver) = mpsc::unbounded_channel();
+    (Self { router, receiver }, Navigator { sender })
+  }
+}
+
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+impl<R: Router> DataSource for RouteSource<R> {
+  async fn poll(&mut self) -> Telemetry {
+    loop {
+      let route = match self.receiver.recv().await {
+        Some(Request::Navigate { from, to }) => match self.router.route(from, to).await {
+          Ok(route) => route,
+          // Keeps the route it found last
+          Err(err) => {
+            tracing::warn!("unable to find a route: {}", err);
+            continue;
+          }
+        },
+        Some(Request::Cancel) => Route::default(),
+        // Every navigator is gone, nobody can ask for routes any more
+        None => std::future::pending().await,
+      };
+      return Telemetry {
+        route: Some(Arc::new(route)),
+        ..Default::default()
+      };
+    }
+  }
+}
+
+/// Decodes a line in Google's encoded polyline format with `precision`
+/// decimal places, 5 for OSRM and 6 for Valhalla.
+pub fn decode_polyline(encoded: &str, precision: u32) -> Result<Vec<Coordinate>, String> {
+  let factor = 10f64.powi(precision as i32);
+  let mut bytes = encoded.bytes();
+  let mut next = || -> Result<Option<i64>, String> {
+    let (mut result, mut shift) = (0i64, 0);
+    loop {
+      let Some(byte) = bytes.next() else {
+        return match shift {
+          0 => Ok(None),
+          _ => Err("polyline ends within a value".to_string()),
+        };
+      };
+      let chunk = (byte as i64) - 63;
+      if !(0..64).contains(&chunk) || shift > 60 {
+        return Err(format!("invalid polyline character {:?}", byte as char));
+      }
+      result |= (chunk & 0x1f) << shift;
+      shift += 5;
+      if chunk < 0x20 {
+        let value = if result & 1 == 1 {
+          !(result >> 1)
+        } else {
+          result >> 1
+        };
+        return Ok(Some(value));
+      }
+    }
+  };
+  let (mut latitude, mut longitude) = (0i64, 0i64);
+  let mut points = Vec::new();
+  while let Some(dlat) = next()? {
+    let dlon = next()?.ok_or("polyline ends within a point")?;
+    latitude += dlat;
+    longitude += dlon;
+    points.push(Coordinate::new(
+      latitude as f64 / factor,
+      longitude as f64 / factor,
+    ));
+  }
+  Ok(points)
+}
+
+/// An [OSRM](https://project-osrm.org) server, like the demo server at
+/// [`OSRM_DEMO`] that's fine for trying it out. Not available on the web.
+#[cfg(all(feature = "routing-http", not(target_arch = "wasm32")))]
+pub struct Osrm {
+  pub url: String,
+  /// Which of the server's profiles to route with.
+  pub profile: String,
+}
+
+/// The public OSRM demo server, see its usage policy before relying on it.
+#[cfg(all(feature = "routing-http", not(target_arch = "wasm32")))]
+pub const OSRM_DEMO: &str = "https://router.project-osrm.org";
+
+#[cfg(all(feature = "routing-http", not(target_arch = "wasm32")))]
+impl Osrm {
+  /// The `driving` profile of the server at `url`.
+  pub fn new(url: impl Into<String>) -> Self {
+    Self {
+      url: url.into(),
+      profile: "driving".to_string(),
+    }
+  }
+}
+
+#[cfg(all(feature = "routing-http", not(target_arch = "wasm32")))]
+#[async_trait]
+impl Router for Osrm {
+  async fn route(&mut self, from: Coordinate, to: Coordinate) -> Result<Route, String> {
+    let url = format!(
+      "{}/route/v1/{}/{},{};{},{}?overview=full&geometries=polyline&steps=true",
+      self.url.trim_end_matches('/'),
+      self.profile,
+      from.longitude,
+      from.latitude,
+      to.longitude,
+      to.latitude,
+    );
+    let body = http::fetch(ureq::get(&url), None).await?;
+    parse_osrm(&body)
+  }
+}
+
+#[cfg(all(feature = "routing-http", not(target_arch = "wasm32")))]
+fn parse_osrm(body: &str) -> Result<Route, String> {
+  #[derive(Deserialize)]
+  struct Response {
+    code: String,
+    #[serde(default)]
+    message: Option<String>,
+    #[serde(default)]
+    routes: Vec<OsrmRoute>,
+  }
+  #[derive(Deserialize)]
+  struct OsrmRoute {
+    geometry: String,
+    distance: f64,
+    duration: f64,
+    legs: Vec<Leg>,
+  }
+  #[derive(Deserialize)]
+  struct Leg {
+    steps: Vec<Step>,
+  }
+  #[derive(Deserialize)]
+  struct Step {
+    distance: f64,
+    #[serde(default)]
+    name: String,
+    maneuver: OsrmManeuver,
+  }
+  #[derive(Deserialize)]
+  struct OsrmManeuver {
+    #[serde(rename = "type")]
+    kind: String,
+    #[serde(default)]
+    modifier: String,
+    location: [f64; 2],
+    exit: Option<u8>,
+  }
+
+  let response: Response = serde_json::from_str(body).map_err(|err| err.to_string())?;
+  if response.code != "Ok" {
+    return Err(response.message.unwrap_or(response.code));
+  }
+  let route = response.routes.into_iter().next().ok_or("no route found")?;
+  let mut maneuvers = Vec::new();
+  let mut distance = 0.0;
+  for step in route.legs.iter().flat_map(|leg| &leg.steps) {
+    let maneuver = &step.maneuver;
+    let turn = match maneuver.kind.as_str() {
+      "depart" => Turn::Depart,
+      "arrive" => Turn::Arrive,
+      "roundabout" | "rotary" | "exit roundabout" | "exit rotary" => Turn::Roundabout,
+      _ => Turn::from_modifier(&maneuver.modifier),
+    };
+    let [longitude, latitude] = maneuver.location;
+    maneuvers.push(Maneuver {
+      turn,
+      exit: maneuver.exit.filter(|_| turn == Turn::Roundabout),
+      position: Coordinate::new(latitude, longitude),
+      distance,
+      road: step.name.clone(),
+    });
+    // A step goes from its maneuver to the next one
+    distance += step.distance;
+  }
+  Ok(Route {
+    geometry: decode_polyline(&route.geometry, 5)?,
+    maneuvers,
+    distance: route.distance,
+    duration: route.duration,
+  })
+}
+
+/// A [Valhalla](https://valhalla.github.io/valhalla/) server. Not
+/// available on the web.
+#[cfg(all(feature = "routing-http", not(target_arch = "wasm32")))]
+pub struct Valhalla {
+  pub url: String,
+  /// Which costing model to route with, like `auto` or `truck`.
+  pub costing: String,
+}
+
+#[cfg(all(feature = "routing-http", not(target_arch = "wasm32")))]
+impl Valhalla {
+  /// Routing cars with the server at `url`.
+  pub fn new(url: impl Into<String>) -> Self {
+    Self {
+      url: url.into(),
+      costing: "auto".to_string(),
+    }
+  }
+}
+
+#[cfg(all(feature = "routing-http", not(target_arch = "wasm32")))]
+#[async_trait]
+impl Router for Valhalla {
+  async fn route(&mut self, from: Coordinate, to: Coordinate) -> Result<Route, String> {
+    let url = format!("{}/route", self.url.trim_end_matches('/'));
+    let request = serde_json::json!({
+      "locations": [
+        { "lat": from.latitude, "lon": from.longitude },
+        { "lat": to.latitude, "lon": to.longitude },
+      ],
+      "costing": self.costing,
+      "units": "kilometers",
+    });
+    let body = http::fetch(ureq::post(&url), Some(request.to_string())).await?;
+    parse_valhalla(&body)
+  }
+}
+
+#[cfg(all(feature = "routing-http", not(target_arch = "wasm32")))]
+fn parse_valhalla(body: &str) -> Result<Route, String> {
+  #[derive(Deserialize)]
+  struct Response {
+    trip: Trip,
+  }
+  #[derive(Deserialize)]
+  struct Trip {
+    legs: Vec<Leg>,
+    summary: Summary,
+  }
+  #[derive(Deserialize)]
+  struct Summary {
+    /// km
+    length: f64,
+    time: f64,
+  }
+  #[derive(Deserialize)]
+  struct Leg {
+    shape: String,
+    maneuvers: Vec<ValhallaManeuver>,
+  }
+  #[derive(Deserialize)]
+  struct ValhallaManeuver {
+    #[serde(rename = "type")]
+    kind: u32,
+    /// km
+    length: f64,
+    begin_shape_index: usize,
+    #[serde(default)]
+    street_names: Vec<String>,
+    roundabout_exit_count: Option<u8>,
+  }
+
+  let response: Response = serde_json::from_str(body).map_err(|err| err.to_string())?;
+  let mut geometry = Vec::new();
+  let mut maneuvers = Vec::new();
+  let mut distance = 0.0;
+  for leg in &response.trip.legs {
+    let shape = decode_polyline(&leg.shape, 6)?;
+    for maneuver in &leg.maneuvers {
+      // Valhalla's maneuver types, see its API reference
+      let turn = match maneuver.kind {
+        1..=3 => Turn::Depart,
+        4..=6 => Turn::Arrive,
+        9 | 20 | 23 | 37 => Turn::SlightRight,
+        10 | 18 => Turn::Right,
+        11 => Turn::SharpRight,
+        12 | 13 => Turn::UTurn,
+        14 => Turn::SharpLeft,
+        15 | 19 => Turn::Left,
+        16 | 21 | 24 | 38 => Turn::SlightLeft,
+        26 => Turn::Roundabout,
+        _ => Turn::Straight,
+      };
+      let position = shape
+        .get(maneuver.begin_shape_index)
+        .copied()
+        .ok_or("maneuver beyond the shape")?;
+      maneuvers.push(Maneuver {
+        turn,
+        exit: maneuver
+          .roundabout_exit_count
+          .filter(|_| turn == Turn::Roundabout),
+        position,
+        distance,
+        road: maneuver.street_names.first().cloned().unwrap_or_default(),
+      });
+      distance += maneuver.length * 1000.0;
+    }
+    geometry.extend(shape);
+  }
+  Ok(Route {
+    geometry,
+    maneuvers,
+    distance: response.trip.summary.length * 1000.0,
+    duration: response.trip.summary.time,
+  })
+}
+
+#[cfg(all(feature = "routing-http", not(target_arch = "wasm32")))]
+pub(crate) mod http {
+  use std::time::Duration;
+
+  /// Sends `request`, with `body` as JSON if there is one, and reads the
+  /// response on a blocking thread, since ureq blocks and would hold up
+  /// the other sources.
+  pub(crate) async fn fetch(
+    request: ureq::Request,
+    body: Option<String>,
+  ) -> Result<String, String> {
+    tokio::task::spawn_blocking(move || {
+      let request = request.timeout(Duration::from_secs(30));
+      let response = match body {
+        Some(body) => request
+          .set("Content-Type", "application/json")
+          .send_string(&body),
+        None => request.call(),
+      };
+      response
+        .map_err(|err| err.to_string())?
+        .into_string()
+        .map_err(|err| err.to_string())
+    })
+    .await
+    .map_err(|err| err.to_string())?
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn straight() -> Route {
+    Route {
+      geometry: vec![
+        Coordinate::new(52.5, 13.4),
+        Coordinate::new(52.5, 13.41),
+        Coordinate::new(52.51, 13.41),
+      ],
+      maneuvers: vec![
+        Maneuver {
+          turn: Turn::Left,
+          exit: None,
+          position: Coordinate::new(52.5, 13.41),
+          distance: 677.0,
+          road: "North Street".to_string(),
+        },
+        Maneuver {
+          turn: Turn::Arrive,
+          exit: None,
+          position: Coordinate::new(52.51, 13.41),
+          distance: 1789.0,
+          road: String::new(),
+        },
+      ],
+      distance: 1789.0,
+      duration: 120.0,
+    }
+  }
+
+  #[test]
+  fn decodes_polylines() {
+    // The example of Google's format description
+    let points = decode_polyline("_p~iF~ps|U_ulLnnqC_mqNvxq`@", 5).unwrap();
+    let expected = [(38.5, -120.2), (40.7, -120.95), (43.252, -126.453)];
+    assert_eq!(points.len(), 3);
+    for (point, (latitude, longitude)) in points.iter().zip(expected) {
+      assert!((point.latitude - latitude).abs() < 1e-9);
+      assert!((point.longitude - longitude).abs() < 1e-9);
+    }
+    assert!(decode_polyline("_p~iF", 5).is_err());
+    assert!(decode_polyline("_p~i", 5).is_err());
+    assert_eq!(decode_polyline("", 6), Ok(Vec::new()));
+  }
+
+  #[test]
+  fn finds_progress_along_the_route() {
+    let route = straight();
+    // Half way along the first segment, a little south of it
+    let progress = route.progress(Coordinate::new(52.4999, 13.405)).unwrap();
+    assert!((progress.along - 338.0).abs() < 2.0, "{:?}", progress);
+    assert!((progress.off_route - 11.1).abs() < 0.5, "{:?}", progress);
+    let (next, distance) = route.upcoming(progress.along).unwrap();
+    assert_eq!(next.turn, Turn::Left);
+    assert!((distance - 339.0).abs() < 2.0);
+    assert_eq!(route.upcoming(1800.0), None);
+    assert!(Route::default().is_empty());
+    assert_eq!(Route::default().progress(Coordinate::new(52.5, 13.4)), None);
+  }
+
+  #[cfg(all(feature = "routing-http", not(target_arch = "wasm32")))]
+  #[test]
+  fn parses_osrm_routes() {
+    let route = parse_osrm(
+      r#"{ "code": "Ok", "routes": [{
+        "geometry": "_p~iF~ps|U_ulLnnqC_mqNvxq`@", "distance": 900.5, "duration": 81.2,
+        "legs": [{ "steps": [
+          { "distance": 400.0, "name": "Main Street",
+            "maneuver": { "type": "depart", "location": [-120.2, 38.5] } },
+          { "distance": 500.5, "name": "Ring",
+            "maneuver": { "type": "roundabout", "modifier": "right", "exit": 2, "location": [-120.95, 40.7] } },
+          { "distance": 0.0, "name": "",
+            "maneuver": { "type": "arrive", "location": [-126.453, 43.252] } }
+        ] }]
+      }] }"#,
+    )
+    .unwrap();
+    let turns: Vec<_> = route
+      .maneuvers
+      .iter()
+      .map(|maneuver| maneuver.turn)
+      .collect();
+    assert_eq!(turns, [Turn::Depart, Turn::Roundabout, Turn::Arrive]);
+    assert_eq!(route.maneuvers[1].exit, Some(2));
+    assert_eq!(route.maneuvers[1].distance, 400.0);
+    assert_eq!(route.maneuvers[2].distance, 900.5);
+    assert_eq!(route.geometry.len(), 3);
+    assert_eq!(
+      parse_osrm(r#"{ "code": "NoRoute", "message": "Impossible route" }"#),
+      Err("Impossible route".to_string())
+    );
+  }
+
+  #[cfg(all(feature = "routing-http", not(target_arch = "wasm32")))]
+  #[test]
+  fn parses_valhalla_routes() {
+    let route = parse_valhalla(
+      r#"{ "trip": {
+        "legs": [{ "shape": "_izlhA~rlgdF_{geC~ywl@", "maneuvers": [
+          { "type": 1, "length": 1.2, "begin_shape_index": 0, "street_names": ["Main Street"] },
+          { "type": 15, "length": 0.0, "begin_shape_index": 1, "street_names": [] }
+        ] }],
+        "summary": { "length": 1.2, "time": 95.0 }
+      } }"#,
+    )
+    .unwrap();
+    assert_eq!(route.geometry.len(), 2);
+    assert!((route.geometry[0].latitude - 38.5).abs() < 1e-9);
+    assert_eq!(route.maneuvers[1].turn, Turn::Left);
+    assert_eq!(route.maneuvers[1].distance, 1200.0);
+    assert_eq!(route.distance, 1200.0);
+    assert!(parse_valhalla(
+      r#"{ "trip": { "legs": [{ "shape": "_izlhA~rlgdF", "maneuvers": [
+      { "type": 1, "length": 0.1, "begin_shape_index": 3 }] }],
+      "summary": { "length": 0.1, "time": 1.0 } } }"#
+    )
+    .is_err());
+  }
+}
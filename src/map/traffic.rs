@@ -0,0 +1,199 @@
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::data::{DataSource, Telemetry};
+use crate::map::Coordinate;
+
+/// How often traffic is fetched unless told otherwise, it changes quicker
+/// than the weather.
+pub const DEFAULT_INTERVAL: Duration = Duration::from_secs(2 * 60);
+
+/// How well traffic flows on a [`Segment`], from best to worst.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Congestion {
+  Free,
+  Slow,
+  Jammed,
+  Closed,
+}
+
+impl Congestion {
+  pub const ALL: [Self; 4] = [Self::Free, Self::Slow, Self::Jammed, Self::Closed];
+}
+
+/// A stretch of road traffic is reported for.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Segment {
+  pub geometry: Vec<Coordinate>,
+  pub congestion: Congestion,
+}
+
+/// Traffic around the vehicle, drawn on top of the roads by
+/// [`MapView`](crate::widgets::MapView).
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Traffic {
+  pub segments: Vec<Segment>,
+}
+
+/// Where [`TrafficSource`] gets traffic from, like a web service or a
+/// TMC receiver, see [`TrafficFile`].
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+pub trait TrafficProvider: Send {
+  async fn fetch(&mut self) -> Result<Traffic, String>;
+}
+
+/// Reports the traffic of a [`TrafficProvider`] as [`Telemetry::traffic`],
+/// right away and then every `interval`.
+pub struct TrafficSource<P> {
+  provider: P,
+  interval: Duration,
+  fetched: bool,
+}
+
+impl<P: TrafficProvider> TrafficSource<P> {
+  pub fn new(provider: P, interval: Duration) -> Self {
+    Self {
+      provider,
+      interval,
+      fetched: false,
+    }
+  }
+}
+
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+impl<P: TrafficProvider> DataSource for TrafficSource<P> {
+  async fn poll(&mut self) -> Telemetry {
+    loop {
+      if self.fetched {
+        crate::data::sleep(self.interval).await;
+      }
+      self.fetched = true;
+      match self.provider.fetch().await {
+        Ok(traffic) => {
+          return Telemetry {
+            traffic: Some(Arc::new(traffic)),
+            ..Default::default()
+          }
+        }
+        // Keeps showing what it fetched last
+        Err(err) => tracing::warn!("unable to fetch traffic: {}", err),
+      }
+    }
+  }
+}
+
+/// Traffic from a GeoJSON file rewritten by something else, like a TMC
+/// decoder, read again whenever fetched. Lines have how well traffic
+/// flows on them in their `congestion` property, named like
+/// [`Congestion`], other features are left out.
+///
+/// ```json
+/// { "type": "FeatureCollection", "features": [{
+///   "type": "Feature",
+///   "properties": { "congestion": "jammed" },
+///   "geometry": { "type": "LineString", "coordinates": [[13.377, 52.516], [13.398, 52.517]] }
+/// }] }
+/// ```
+pub struct TrafficFile {
+  pub path: PathBuf,
+}
+
+impl TrafficFile {
+  pub fn new(path: impl Into<PathBuf>) -> Self {
+    Self { path: path.into() }
+  }
+}
+
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+impl TrafficProvider for TrafficFile {
+  async fn fetch(&mut self) -> Result<Traffic, String> {
+    let source = std::fs::read_to_string(&self.path).map_err(|err| err.to_string())?;
+    parse_geojson(&source)
+  }
+}
+
+fn parse_geojson(source: &str) -> Result<Traffic, String> {
+  let collection: Value = serde_json::from_str(source).map_err(|err| err.to_string())?;
+  let features = collection
+    .get("features")
+    .and_then(Value::as_array)
+    .ok_or("not a GeoJSON feature collection")?;
+  let position = |value: &Value| {
+    let longitude = value.get(0)?.as_f64()?;
+    let latitude = value.get(1)?.as_f64()?;
+    Some(Coordinate::new(latitude, longitude))
+  };
+  let line =
+    |value: &Value| -> Option<Vec<Coordinate>> { value.as_array()?.iter().map(position).collect() };
+  let mut segments = Vec::new();
+  for feature in features {
+    let congestion = feature
+      .get("properties")
+      .and_then(|properties| properties.get("congestion"))
+      .map(Congestion::deserialize);
+    let Some(Ok(congestion)) = congestion else {
+      continue;
+    };
+    let Some(geometry) = feature.get("geometry") else {
+      continue;
+    };
+    let coordinates = geometry.get("coordinates");
+    let lines = match (geometry.get("type").and_then(Value::as_str), coordinates) {
+      (Some("LineString"), Some(coordinates)) => line(coordinates).map(|line| vec![line]),
+      (Some("MultiLineString"), Some(coordinates)) => coordinates
+        .as_array()
+        .and_then(|lines| lines.iter().map(line).collect()),
+      _ => None,
+    };
+    for geometry in lines.into_iter().flatten() {
+      segments.push(Segment {
+        geometry,
+        congestion,
+      });
+    }
+  }
+  Ok(Traffic { segments })
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn reads_congested_lines_from_geojson() {
+    let traffic = parse_geojson(
+      r#"{ "type": "FeatureCollection", "features": [
+        { "type": "Feature", "properties": { "congestion": "jammed" },
+          "geometry": { "type": "LineString", "coordinates": [[13.0, 52.0], [13.1, 52.1]] } },
+        { "type": "Feature", "properties": { "congestion": "slow" },
+          "geometry": { "type": "MultiLineString", "coordinates": [
+            [[13.0, 52.0], [13.1, 52.0]], [[13.2, 52.0], [13.3, 52.0]]] } },
+        { "type": "Feature", "properties": { "congestion": "gridlock" },
+          "geometry": { "type": "LineString", "coordinates": [[13.0, 52.0], [13.1, 52.1]] } },
+        { "type": "Feature", "properties": { "congestion": "free" },
+          "geometry": { "type": "Point", "coordinates": [13.0, 52.0] } }
+      ] }"#,
+    )
+    .unwrap();
+    let congestion: Vec<_> = traffic
+      .segments
+      .iter()
+      .map(|segment| segment.congestion)
+      .collect();
+    assert_eq!(
+      congestion,
+      [Congestion::Jammed, Congestion::Slow, Congestion::Slow]
+    );
+    assert_eq!(traffic.segments[0].geometry[1], Coordinate::new(52.1, 13.1));
+    assert!(parse_geojson("{}").is_err());
+  }
+}
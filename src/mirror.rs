@@ -0,0 +1,82 @@
+use serde::{Deserialize, Serialize};
+
+/// How the image is flipped before it is shown, for screens read as a
+/// reflection in the windshield.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Mirror {
+  #[default]
+  None,
+  /// Left and right swapped, for a screen lying flat below the windshield.
+  Horizontal,
+  /// Upside down.
+  Vertical,
+  /// Both, i.e. rotated by 180°.
+  Both,
+}
+
+impl Mirror {
+  /// Factors clip space coordinates are scaled by.
+  pub(crate) fn scale(self) -> [f32; 2] {
+    match self {
+      Self::None => [1.0, 1.0],
+      Self::Horizontal => [-1.0, 1.0],
+      Self::Vertical => [1.0, -1.0],
+      Self::Both => [-1.0, -1.0],
+    }
+  }
+
+  /// Where a point on the screen of `size` shows what was drawn at
+  /// `position`, and the other way around.
+  pub fn apply(self, [x, y]: [f32; 2], [width, height]: [f32; 2]) -> [f32; 2] {
+    let [sx, sy] = self.scale();
+    [
+      if sx < 0.0 { width - x } else { x },
+      if sy < 0.0 { height - y } else { y },
+    ]
+  }
+}
+
+/// How much brighter colors get in high contrast mode, reflections lose
+/// most of the light. Must match `shaders/color.wgsl`.
+const HIGH_CONTRAST_GAIN: f32 = 2.5;
+
+/// A linear RGBA color in high contrast mode: gray, with anything that
+/// isn't dark pushed towards white so it reads as white on black.
+pub(crate) fn high_contrast([r, g, b, a]: [f32; 4]) -> [f32; 4] {
+  let luminance = 0.2126 * r + 0.7152 * g + 0.0722 * b;
+  let value = (luminance * HIGH_CONTRAST_GAIN).min(1.0);
+  [value, value, value, a]
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  const SIZE: [f32; 2] = [800.0, 480.0];
+
+  #[test]
+  fn mirrors_points_across_the_screen() {
+    let point = [200.0, 100.0];
+    assert_eq!(Mirror::None.apply(point, SIZE), [200.0, 100.0]);
+    assert_eq!(Mirror::Horizontal.apply(point, SIZE), [600.0, 100.0]);
+    assert_eq!(Mirror::Vertical.apply(point, SIZE), [200.0, 380.0]);
+    assert_eq!(Mirror::Both.apply(point, SIZE), [600.0, 380.0]);
+    for mirror in [
+      Mirror::None,
+      Mirror::Horizontal,
+      Mirror::Vertical,
+      Mirror::Both,
+    ] {
+      assert_eq!(mirror.apply(mirror.apply(point, SIZE), SIZE), point);
+    }
+  }
+
+  #[test]
+  fn high_contrast_is_bright_gray() {
+    assert_eq!(high_contrast([0.0, 0.0, 0.0, 1.0]), [0.0, 0.0, 0.0, 1.0]);
+    assert_eq!(high_contrast([1.0, 0.5, 0.0, 0.5]), [1.0, 1.0, 1.0, 0.5]);
+    let [r, g, b, _] = high_contrast([0.0, 0.0, 0.2, 1.0]);
+    assert!(r == g && g == b && r < 0.1);
+  }
+}
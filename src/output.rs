@@ -0,0 +1,394 @@
+//! Mirrors the frames shown into a file other processes map or read, like
+//! a compliance recorder or a compositor, see
+//! [`Settings::frame_output`](crate::Settings::frame_output).
+//!
+//! The file starts with a [`HEADER_SIZE`] byte header, little endian:
+//!
+//! | Offset | Size | Value                                             |
+//! |--------|------|---------------------------------------------------|
+//! | 0      | 4    | [`MAGIC`]                                         |
+//! | 4      | 4    | Width in pixels                                   |
+//! | 8      | 4    | Height in pixels                                  |
+//! | 12     | 4    | Bytes per row, four per pixel                     |
+//! | 16     | 4    | [`PixelFormat`]                                   |
+//! | 20     | 4    | Zero                                              |
+//! | 24     | 8    | Sequence number, counting up twice per frame      |
+//!
+//! followed by the rows of pixels, top to bottom. The sequence number is
+//! odd while a frame is being written. Readers copy the frame and compare
+//! the number before and after, like [`read`] does, to never take half of
+//! one. Put the file on a RAM backed file system like `/dev/shm` for it to
+//! be shared memory.
+
+use std::fs::{File, OpenOptions};
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::num::NonZeroU32;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Receiver, TryRecvError};
+
+use wgpu::{
+  Buffer, BufferAsyncError, BufferDescriptor, BufferUsages, Color, CommandEncoder, Device,
+  Extent3d, ImageCopyBuffer, ImageDataLayout, Maintain, MapMode, Queue, TextureFormat, TextureView,
+  COPY_BYTES_PER_ROW_ALIGNMENT,
+};
+
+use crate::error::WindshieldError;
+use crate::pipeline::WARP_SHADER;
+use crate::stats::FrameStats;
+use crate::warp::WarpPass;
+
+/// What every frame file starts with.
+pub const MAGIC: [u8; 4] = *b"WSFB";
+/// Bytes before the first row of pixels.
+pub const HEADER_SIZE: u64 = 32;
+
+/// Order of a pixel's four bytes, each color in sRGB.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[repr(u32)]
+pub enum PixelFormat {
+  Rgba = 0,
+  Bgra = 1,
+}
+
+impl PixelFormat {
+  fn of(format: TextureFormat) -> Option<Self> {
+    match format {
+      TextureFormat::Rgba8Unorm | TextureFormat::Rgba8UnormSrgb => Some(Self::Rgba),
+      TextureFormat::Bgra8Unorm | TextureFormat::Bgra8UnormSrgb => Some(Self::Bgra),
+      _ => None,
+    }
+  }
+}
+
+/// The header of a frame file.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct FrameHeader {
+  pub width: u32,
+  pub height: u32,
+  pub format: PixelFormat,
+  pub sequence: u64,
+}
+
+impl FrameHeader {
+  fn to_bytes(self) -> [u8; HEADER_SIZE as usize] {
+    let mut bytes = [0; HEADER_SIZE as usize];
+    bytes[0..4].copy_from_slice(&MAGIC);
+    bytes[4..8].copy_from_slice(&self.width.to_le_bytes());
+    bytes[8..12].copy_from_slice(&self.height.to_le_bytes());
+    bytes[12..16].copy_from_slice(&(self.width * 4).to_le_bytes());
+    bytes[16..20].copy_from_slice(&(self.format as u32).to_le_bytes());
+    bytes[24..32].copy_from_slice(&self.sequence.to_le_bytes());
+    bytes
+  }
+
+  fn from_bytes(bytes: &[u8; HEADER_SIZE as usize]) -> Option<Self> {
+    let word = |at: usize| u32::from_le_bytes(bytes[at..at + 4].try_into().expect("4 bytes"));
+    let format = match word(16) {
+      0 => PixelFormat::Rgba,
+      1 => PixelFormat::Bgra,
+      _ => return None,
+    };
+    (bytes[0..4] == MAGIC && word(12) == word(4) * 4).then(|| Self {
+      width: word(4),
+      height: word(8),
+      format,
+      sequence: u64::from_le_bytes(bytes[24..32].try_into().expect("8 bytes")),
+    })
+  }
+}
+
+/// Reads the last whole frame from the frame file at `path`, `None` if
+/// there is none yet or one was being written meanwhile.
+pub fn read(path: impl AsRef<Path>) -> io::Result<Option<(FrameHeader, Vec<u8>)>> {
+  let mut file = File::open(path)?;
+  let Some(header) = read_header(&mut file)? else {
+    return Ok(None);
+  };
+  if header.sequence % 2 == 1 {
+    return Ok(None);
+  }
+  let mut pixels = vec![0; header.width as usize * header.height as usize * 4];
+  file.read_exact(&mut pixels)?;
+  let unchanged = read_header(&mut file)?.is_some_and(|after| after == header);
+  Ok(unchanged.then_some((header, pixels)))
+}
+
+fn read_header(file: &mut File) -> io::Result<Option<FrameHeader>> {
+  let mut bytes = [0; HEADER_SIZE as usize];
+  file.seek(SeekFrom::Start(0))?;
+  match file.read_exact(&mut bytes) {
+    Ok(()) => Ok(FrameHeader::from_bytes(&bytes)),
+    Err(err) if err.kind() == io::ErrorKind::UnexpectedEof => Ok(None),
+    Err(err) => Err(err),
+  }
+}
+
+/// Writes frames into a frame file, see the [module](self).
+pub(crate) struct FrameFile {
+  path: PathBuf,
+  file: File,
+  header: Option<FrameHeader>,
+}
+
+impl FrameFile {
+  pub(crate) fn create(path: &Path) -> io::Result<Self> {
+    let file = OpenOptions::new()
+      .read(true)
+      .write(true)
+      .create(true)
+      .truncate(true)
+      .open(path)?;
+    Ok(Self {
+      path: path.to_path_buf(),
+      file,
+      header: None,
+    })
+  }
+
+  /// Writes a frame of `width` × `height` pixels given as rows of `width *
+  /// 4` bytes.
+  pub(crate) fn write<'a>(
+    &mut self,
+    [width, height]: [u32; 2],
+    format: PixelFormat,
+    rows: impl Iterator<Item = &'a [u8]>,
+  ) -> io::Result<()> {
+    let sequence = self.header.map_or(0, |header| header.sequence);
+    let resized = self
+      .header
+      .is_none_or(|header| [header.width, header.height] != [width, height]);
+    let mut header = FrameHeader {
+      width,
+      height,
+      format,
+      sequence: sequence + 1,
+    };
+    self.write_header(header)?;
+    if resized {
+      self
+        .file
+        .set_len(HEADER_SIZE + width as u64 * height as u64 * 4)?;
+    }
+    self.file.seek(SeekFrom::Start(HEADER_SIZE))?;
+    for row in rows {
+      self.file.write_all(row)?;
+    }
+    header.sequence += 1;
+    self.write_header(header)?;
+    self.header = Some(header);
+    Ok(())
+  }
+
+  fn write_header(&mut self, header: FrameHeader) -> io::Result<()> {
+    self.file.seek(SeekFrom::Start(0))?;
+    self.file.write_all(&header.to_bytes())
+  }
+}
+
+/// Renders frames into a texture of its own, draws them onto the screen
+/// from there unchanged and copies them back into a [`FrameFile`], one at
+/// a time while the last one is read back.
+pub(crate) struct FrameOutput {
+  blit: WarpPass,
+  format: PixelFormat,
+  size: [u32; 2],
+  readback: Buffer,
+  // Size of the frame in the readback buffer until it's written
+  copied: Option<[u32; 2]>,
+  mapping: Option<Receiver<Result<(), BufferAsyncError>>>,
+  file: FrameFile,
+}
+
+impl FrameOutput {
+  pub(crate) async fn new(
+    device: &Device,
+    format: TextureFormat,
+    [width, height]: [u32; 2],
+    path: &Path,
+  ) -> Result<Self, WindshieldError> {
+    let pixel_format = PixelFormat::of(format).ok_or(WindshieldError::UnsupportedSurface)?;
+    let file = FrameFile::create(path).map_err(|source| WindshieldError::WriteFile {
+      path: path.to_path_buf(),
+      source,
+    })?;
+    // The bundled shader with the keystone left alone copies the frame as
+    // is, whatever replaced the one warping it
+    let blit = WarpPass::new(device, format, width, height, WARP_SHADER).await?;
+    Ok(Self {
+      blit,
+      format: pixel_format,
+      size: [width, height],
+      readback: readback(device, [width, height]),
+      copied: None,
+      mapping: None,
+      file,
+    })
+  }
+
+  pub(crate) fn resize(&mut self, device: &Device, queue: &Queue, width: u32, height: u32) {
+    self.blit.resize(device, queue, width, height);
+    self.size = [width, height];
+    // A frame still being read back keeps the buffer it's mapped from
+    if self.mapping.is_none() {
+      self.readback = readback(device, self.size);
+    }
+  }
+
+  /// What the frame is rendered into.
+  pub(crate) fn view(&self) -> &TextureView {
+    self.blit.view()
+  }
+
+  /// Records copying the frame out, unless the last one is still being
+  /// read back, and drawing it onto `target`.
+  pub(crate) fn encode(
+    &mut self,
+    encoder: &mut CommandEncoder,
+    target: &TextureView,
+    stats: &mut FrameStats,
+  ) {
+    let [width, height] = self.size;
+    if self.mapping.is_none() && self.readback.size() >= padded_row(width) as u64 * height as u64 {
+      encoder.copy_texture_to_buffer(
+        self.blit.texture().as_image_copy(),
+        ImageCopyBuffer {
+          buffer: &self.readback,
+          layout: ImageDataLayout {
+            offset: 0,
+            bytes_per_row: NonZeroU32::new(padded_row(width)),
+            rows_per_image: None,
+          },
+        },
+        Extent3d {
+          width,
+          height,
+          depth_or_array_layers: 1,
+        },
+      );
+      self.copied = Some(self.size);
+    }
+    self.blit.draw(encoder, target, Color::TRANSPARENT, stats);
+  }
+
+  /// Starts reading back what [`encode`](Self::encode) copied, once its
+  /// commands are submitted.
+  pub(crate) fn submitted(&mut self) {
+    if self.copied.is_none() || self.mapping.is_some() {
+      return;
+    }
+    let (sender, mapped) = mpsc::channel();
+    self
+      .readback
+      .slice(..)
+      .map_async(MapMode::Read, move |result| {
+        let _ = sender.send(result);
+      });
+    self.mapping = Some(mapped);
+  }
+
+  /// Writes the last frame read back into the file, if the GPU is done
+  /// with one.
+  pub(crate) fn poll(&mut self, device: &Device) -> Result<(), WindshieldError> {
+    let (Some(mapped), Some([width, height])) = (&self.mapping, self.copied) else {
+      return Ok(());
+    };
+    device.poll(Maintain::Poll);
+    let result = match mapped.try_recv() {
+      Err(TryRecvError::Empty) => return Ok(()),
+      Ok(result) => result,
+      Err(TryRecvError::Disconnected) => Err(BufferAsyncError),
+    };
+    self.mapping = None;
+    self.copied = None;
+    result?;
+
+    let written = {
+      let padded = self.readback.slice(..).get_mapped_range();
+      let rows = padded
+        .chunks(padded_row(width) as usize)
+        .take(height as usize)
+        .map(|row| &row[..width as usize * 4]);
+      self.file.write([width, height], self.format, rows)
+    };
+    self.readback.unmap();
+    if self.size != [width, height] {
+      self.readback = readback(device, self.size);
+    }
+    written.map_err(|source| WindshieldError::WriteFile {
+      path: self.file.path.clone(),
+      source,
+    })
+  }
+}
+
+/// Rows of buffer copies have to be aligned.
+fn padded_row(width: u32) -> u32 {
+  (width * 4).div_ceil(COPY_BYTES_PER_ROW_ALIGNMENT) * COPY_BYTES_PER_ROW_ALIGNMENT
+}
+
+fn readback(device: &Device, [width, height]: [u32; 2]) -> Buffer {
+  device.create_buffer(&BufferDescriptor {
+    label: Some("Frame Output"),
+    size: padded_row(width) as u64 * height as u64,
+    usage: BufferUsages::COPY_DST | BufferUsages::MAP_READ,
+    mapped_at_creation: false,
+  })
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn frames_read_back_as_written() {
+    let path = std::env::temp_dir().join(format!("windshield-frames-{}", std::process::id()));
+    let mut file = FrameFile::create(&path).unwrap();
+    assert_eq!(read(&path).unwrap(), None);
+
+    let pixels: Vec<u8> = (0..2 * 3 * 4).collect();
+    file
+      .write([2, 3], PixelFormat::Bgra, pixels.chunks(8))
+      .unwrap();
+    let (header, read_pixels) = read(&path).unwrap().unwrap();
+    assert_eq!(
+      header,
+      FrameHeader {
+        width: 2,
+        height: 3,
+        format: PixelFormat::Bgra,
+        sequence: 2,
+      }
+    );
+    assert_eq!(read_pixels, pixels);
+
+    // A smaller frame shrinks the file
+    file
+      .write([1, 1], PixelFormat::Bgra, [&[9, 9, 9, 9][..]].into_iter())
+      .unwrap();
+    let (header, read_pixels) = read(&path).unwrap().unwrap();
+    assert_eq!(
+      [header.width, header.height, header.sequence as u32],
+      [1, 1, 4]
+    );
+    assert_eq!(read_pixels, [9, 9, 9, 9]);
+    assert_eq!(std::fs::metadata(&path).unwrap().len(), HEADER_SIZE + 4);
+    std::fs::remove_file(path).unwrap();
+  }
+
+  #[test]
+  fn frames_being_written_are_skipped() {
+    let header = FrameHeader {
+      width: 4,
+      height: 4,
+      format: PixelFormat::Rgba,
+      sequence: 7,
+    };
+    assert_eq!(FrameHeader::from_bytes(&header.to_bytes()), Some(header));
+    let path = std::env::temp_dir().join(format!("windshield-torn-{}", std::process::id()));
+    let mut bytes = header.to_bytes().to_vec();
+    bytes.resize(HEADER_SIZE as usize + 64, 0);
+    std::fs::write(&path, bytes).unwrap();
+    assert_eq!(read(&path).unwrap(), None);
+    std::fs::remove_file(path).unwrap();
+  }
+}
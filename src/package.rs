@@ -0,0 +1,149 @@
+//! Switching a running app to another config and the themes, fonts and
+//! images it uses, without restarting, like after an over-the-air update.
+//!
+//! A package is a directory with a [`CONFIG_FILE`] and whatever it refers
+//! to. Fleets install each version into a directory of its own, then ask
+//! the app to [switch](PackageSwitch::switch) to it. The app does that
+//! between two frames: it loads the config and builds its scene, fonts
+//! and images first, and keeps showing the current package if anything
+//! fails, so a broken package never shows. A package that loads but turns
+//! out to misbehave is [rolled back](PackageSwitch::rollback) to the one
+//! before.
+
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+use crate::wake::Waker;
+
+/// The config of a package, in its directory.
+pub const CONFIG_FILE: &str = "config.toml";
+
+#[derive(Default)]
+struct Switching {
+  requested: Option<PathBuf>,
+  current: Option<PathBuf>,
+  previous: Option<PathBuf>,
+  // Set once the app runs
+  waker: Option<Waker>,
+}
+
+/// Asks a running app to switch packages, from any thread, see
+/// [`WindshieldAppBuilder::with_package_switch`](crate::WindshieldAppBuilder::with_package_switch).
+#[derive(Clone, Default)]
+pub struct PackageSwitch(Arc<Mutex<Switching>>);
+
+impl PackageSwitch {
+  pub fn new() -> Self {
+    Self::default()
+  }
+
+  /// Switches to the package in `dir`, in place of a switch not done yet.
+  pub fn switch(&self, dir: impl Into<PathBuf>) {
+    let mut switching = self.0.lock().unwrap();
+    switching.requested = Some(dir.into());
+    if let Some(waker) = &switching.waker {
+      waker.wake();
+    }
+  }
+
+  /// Switches back to the package shown before the current one, returning
+  /// whether there was one.
+  pub fn rollback(&self) -> bool {
+    let previous = self.0.lock().unwrap().previous.clone();
+    match previous {
+      Some(previous) => {
+        self.switch(previous);
+        true
+      }
+      None => false,
+    }
+  }
+
+  /// The directory of the package shown, the one the app started with
+  /// until it switched to another.
+  pub fn current(&self) -> Option<PathBuf> {
+    self.0.lock().unwrap().current.clone()
+  }
+
+  pub(crate) fn set_waker(&self, waker: Waker) {
+    self.0.lock().unwrap().waker = Some(waker);
+  }
+
+  /// The package to switch to, if one was asked for since the last call.
+  pub(crate) fn take(&self) -> Option<PathBuf> {
+    self.0.lock().unwrap().requested.take()
+  }
+
+  /// Remembers that the app started with the package in `dir`, so the
+  /// first switch can be rolled back, unless it switched since.
+  pub(crate) fn started(&self, dir: PathBuf) {
+    self.0.lock().unwrap().current.get_or_insert(dir);
+  }
+
+  /// Remembers that the package in `dir` is shown now.
+  pub(crate) fn switched(&self, dir: PathBuf) {
+    let mut switching = self.0.lock().unwrap();
+    switching.previous = switching.current.replace(dir);
+  }
+}
+
+/// Where the config of the package in `dir` is.
+pub(crate) fn config_path(dir: &Path) -> PathBuf {
+  dir.join(CONFIG_FILE)
+}
+
+/// The directory of the package whose config is at `path`, if it's a
+/// [`CONFIG_FILE`].
+pub(crate) fn package_dir(path: &Path) -> Option<PathBuf> {
+  if path.file_name()? != CONFIG_FILE {
+    return None;
+  }
+  match path.parent()? {
+    dir if dir.as_os_str().is_empty() => Some(PathBuf::from(".")),
+    dir => Some(dir.to_path_buf()),
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn rolls_back_to_the_package_before() {
+    let switch = PackageSwitch::new();
+    assert!(!switch.rollback());
+    switch.started(PathBuf::from("/opt/hud/0"));
+    assert_eq!(switch.current().unwrap(), Path::new("/opt/hud/0"));
+    switch.switch("/opt/hud/1");
+    switch.switch("/opt/hud/2");
+    // Only the latest is switched to
+    let dir = switch.take().unwrap();
+    assert_eq!(dir, Path::new("/opt/hud/2"));
+    assert_eq!(switch.take(), None);
+    switch.switched(dir);
+    // Back to the package started with
+    assert!(switch.rollback());
+    assert_eq!(switch.take().unwrap(), Path::new("/opt/hud/0"));
+    switch.switch("/opt/hud/3");
+    switch.switched(switch.take().unwrap());
+    assert!(switch.rollback());
+    assert_eq!(switch.take().unwrap(), Path::new("/opt/hud/2"));
+    assert_eq!(switch.current().unwrap(), Path::new("/opt/hud/3"));
+    // Starting doesn't forget what was switched to
+    switch.started(PathBuf::from("/opt/hud/0"));
+    assert_eq!(switch.current().unwrap(), Path::new("/opt/hud/3"));
+  }
+
+  #[test]
+  fn finds_the_package_of_a_config() {
+    assert_eq!(
+      package_dir(Path::new("/opt/hud/1/config.toml")).unwrap(),
+      Path::new("/opt/hud/1")
+    );
+    assert_eq!(
+      package_dir(Path::new("config.toml")).unwrap(),
+      Path::new(".")
+    );
+    assert_eq!(package_dir(Path::new("/etc/hud/dash.toml")), None);
+  }
+}
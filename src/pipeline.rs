@@ -0,0 +1,419 @@
+use std::borrow::Cow;
+use std::marker::PhantomData;
+use std::mem;
+use std::ops::Range;
+use std::path::Path;
+
+use bytemuck::{Pod, Zeroable};
+use wgpu::util::{BufferInitDescriptor, DeviceExt};
+use wgpu::{
+  vertex_attr_array, BindGroup, BindGroupDescriptor, BindGroupEntry, BindGroupLayout,
+  BindGroupLayoutDescriptor, BindGroupLayoutEntry, BindingType, BlendState, Buffer,
+  BufferBindingType, BufferDescriptor, BufferUsages, ColorTargetState, ColorWrites, Device,
+  ErrorFilter, FragmentState, FrontFace, IndexFormat, MultisampleState, PipelineLayoutDescriptor,
+  PrimitiveState, PrimitiveTopology, Queue, RenderPass, RenderPipeline, RenderPipelineDescriptor,
+  ShaderModule, ShaderModuleDescriptor, ShaderSource, ShaderStages, TextureFormat, VertexAttribute,
+  VertexBufferLayout, VertexState, VertexStepMode,
+};
+
+use crate::error::WindshieldError;
+use crate::mirror::Mirror;
+use crate::stats::FrameStats;
+
+/// The bundled flat color shader, as `(name, source)`.
+pub const COLOR_SHADER: (&str, &str) = ("color.wgsl", include_str!("shaders/color.wgsl"));
+/// The bundled image shader, see [`Frame::image`](crate::Frame::image).
+pub const IMAGE_SHADER: (&str, &str) = ("image.wgsl", include_str!("shaders/image.wgsl"));
+/// The bundled keystone correction shader, see [`Keystone`](crate::warp::Keystone).
+pub const WARP_SHADER: (&str, &str) = ("warp.wgsl", include_str!("shaders/warp.wgsl"));
+
+/// A vertex type that can be stored in a [`GeometryBuffer`] and describes
+/// its own layout to a pipeline.
+pub trait Vertex: Pod {
+  fn layout() -> VertexBufferLayout<'static>;
+}
+
+/// Flat colored vertex, see `shaders/color.wgsl`.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Pod, Zeroable)]
+pub struct ColorVertex {
+  /// Position in physical pixels, the origin is in the top left corner.
+  pub position: [f32; 2],
+  /// Linear RGBA in straight alpha.
+  pub color: [f32; 4],
+}
+
+impl ColorVertex {
+  const ATTRIBUTES: [VertexAttribute; 2] = vertex_attr_array![0 => Float32x2, 1 => Float32x4];
+}
+
+impl Vertex for ColorVertex {
+  fn layout() -> VertexBufferLayout<'static> {
+    VertexBufferLayout {
+      array_stride: mem::size_of::<Self>() as u64,
+      step_mode: VertexStepMode::Vertex,
+      attributes: &Self::ATTRIBUTES,
+    }
+  }
+}
+
+/// Source of a bundled `(name, source)` shader, read from `dir` instead if
+/// it contains a file of that name.
+pub fn shader_source(
+  dir: Option<&Path>,
+  (name, bundled): (&str, &'static str),
+) -> Result<Cow<'static, str>, WindshieldError> {
+  let Some(path) = dir.map(|dir| dir.join(name)).filter(|path| path.exists()) else {
+    return Ok(Cow::Borrowed(bundled));
+  };
+  tracing::debug!("loading shader {}", path.display());
+  std::fs::read_to_string(&path)
+    .map(Cow::Owned)
+    .map_err(|source| WindshieldError::ReadFile { path, source })
+}
+
+/// Compiles WGSL, reporting validation errors instead of panicking on them.
+pub async fn create_shader(
+  device: &Device,
+  name: &str,
+  source: &str,
+) -> Result<ShaderModule, WindshieldError> {
+  device.push_error_scope(ErrorFilter::Validation);
+  let module = device.create_shader_module(ShaderModuleDescriptor {
+    label: Some(name),
+    source: ShaderSource::Wgsl(source.into()),
+  });
+
+  match device.pop_error_scope().await {
+    Some(err) => Err(WindshieldError::ShaderCompile {
+      name: name.to_string(),
+      message: err.to_string(),
+    }),
+    None => Ok(module),
+  }
+}
+
+/// Builds a [`RenderPipeline`] from a shader exposing `vs_main` and
+/// `fs_main` entry points.
+pub struct PipelineBuilder<'a> {
+  label: &'a str,
+  shader: &'a ShaderModule,
+  vertex_layouts: Vec<VertexBufferLayout<'a>>,
+  bind_group_layouts: Vec<&'a BindGroupLayout>,
+  blend: Option<BlendState>,
+  topology: PrimitiveTopology,
+}
+
+impl<'a> PipelineBuilder<'a> {
+  pub fn new(label: &'a str, shader: &'a ShaderModule) -> Self {
+    Self {
+      label,
+      shader,
+      vertex_layouts: Vec::new(),
+      bind_group_layouts: Vec::new(),
+      blend: Some(BlendState::ALPHA_BLENDING),
+      topology: PrimitiveTopology::TriangleList,
+    }
+  }
+
+  /// Adds a vertex buffer slot for `V`, in the order the shader expects them.
+  pub fn vertex<V: Vertex>(mut self) -> Self {
+    self.vertex_layouts.push(V::layout());
+    self
+  }
+
+  pub fn bind_group_layout(mut self, layout: &'a BindGroupLayout) -> Self {
+    self.bind_group_layouts.push(layout);
+    self
+  }
+
+  /// Defaults to alpha blending, `None` replaces the target.
+  pub fn blend(mut self, blend: Option<BlendState>) -> Self {
+    self.blend = blend;
+    self
+  }
+
+  pub fn topology(mut self, topology: PrimitiveTopology) -> Self {
+    self.topology = topology;
+    self
+  }
+
+  pub fn build(self, device: &Device, format: TextureFormat) -> RenderPipeline {
+    let layout = device.create_pipeline_layout(&PipelineLayoutDescriptor {
+      label: Some(self.label),
+      bind_group_layouts: &self.bind_group_layouts,
+      push_constant_ranges: &[],
+    });
+
+    device.create_render_pipeline(&RenderPipelineDescriptor {
+      label: Some(self.label),
+      layout: Some(&layout),
+      vertex: VertexState {
+        module: self.shader,
+        entry_point: "vs_main",
+        buffers: &self.vertex_layouts,
+      },
+      fragment: Some(FragmentState {
+        module: self.shader,
+        entry_point: "fs_main",
+        targets: &[Some(ColorTargetState {
+          format,
+          blend: self.blend,
+          write_mask: ColorWrites::ALL,
+        })],
+      }),
+      primitive: PrimitiveState {
+        topology: self.topology,
+        strip_index_format: None,
+        front_face: FrontFace::Ccw,
+        // 2D geometry is emitted with arbitrary winding
+        cull_mode: None,
+        unclipped_depth: false,
+        polygon_mode: Default::default(),
+        conservative: false,
+      },
+      depth_stencil: None,
+      multisample: MultisampleState::default(),
+      multiview: None,
+    })
+  }
+}
+
+/// Size of the render target, needed by shaders working in pixels, and how
+/// the image is mirrored.
+pub struct ScreenUniform {
+  buffer: Buffer,
+  layout: BindGroupLayout,
+  bind_group: BindGroup,
+  screen: Screen,
+}
+
+/// Layout of `Screen` in the shaders, padded to 16 bytes.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Pod, Zeroable)]
+struct Screen {
+  size: [f32; 2],
+  mirror: [f32; 2],
+  high_contrast: f32,
+  padding: [f32; 3],
+}
+
+impl ScreenUniform {
+  pub fn new(device: &Device, width: u32, height: u32) -> Self {
+    let screen = Screen {
+      size: [width as f32, height as f32],
+      mirror: Mirror::None.scale(),
+      high_contrast: 0.0,
+      padding: [0.0; 3],
+    };
+    let buffer = device.create_buffer_init(&BufferInitDescriptor {
+      label: Some("Screen Uniform"),
+      contents: bytemuck::bytes_of(&screen),
+      usage: BufferUsages::UNIFORM | BufferUsages::COPY_DST,
+    });
+    let layout = device.create_bind_group_layout(&BindGroupLayoutDescriptor {
+      label: Some("Screen Uniform"),
+      entries: &[BindGroupLayoutEntry {
+        binding: 0,
+        // High contrast applies to the pixels of images
+        visibility: ShaderStages::VERTEX | ShaderStages::FRAGMENT,
+        ty: BindingType::Buffer {
+          ty: BufferBindingType::Uniform,
+          has_dynamic_offset: false,
+          min_binding_size: None,
+        },
+        count: None,
+      }],
+    });
+    let bind_group = device.create_bind_group(&BindGroupDescriptor {
+      label: Some("Screen Uniform"),
+      layout: &layout,
+      entries: &[BindGroupEntry {
+        binding: 0,
+        resource: buffer.as_entire_binding(),
+      }],
+    });
+
+    Self {
+      buffer,
+      layout,
+      bind_group,
+      screen,
+    }
+  }
+
+  pub fn resize(&mut self, queue: &Queue, width: u32, height: u32) {
+    self.screen.size = [width as f32, height as f32];
+    self.write(queue);
+  }
+
+  pub fn set_mirror(&mut self, queue: &Queue, mirror: Mirror) {
+    self.screen.mirror = mirror.scale();
+    self.write(queue);
+  }
+
+  /// Draws everything gray and brighter, see [`Mirror`].
+  pub fn set_high_contrast(&mut self, queue: &Queue, high_contrast: bool) {
+    self.screen.high_contrast = if high_contrast { 1.0 } else { 0.0 };
+    self.write(queue);
+  }
+
+  fn write(&self, queue: &Queue) {
+    queue.write_buffer(&self.buffer, 0, bytemuck::bytes_of(&self.screen));
+  }
+
+  pub fn layout(&self) -> &BindGroupLayout {
+    &self.layout
+  }
+
+  pub fn bind_group(&self) -> &BindGroup {
+    &self.bind_group
+  }
+}
+
+/// Vertex and index buffers that grow as needed when geometry is uploaded.
+pub struct GeometryBuffer<V> {
+  label: &'static str,
+  vertices: Buffer,
+  indices: Buffer,
+  vertex_capacity: usize,
+  index_capacity: usize,
+  index_count: u32,
+  _vertex: PhantomData<V>,
+}
+
+impl<V: Vertex> GeometryBuffer<V> {
+  const INITIAL_CAPACITY: usize = 1024;
+
+  pub fn new(device: &Device, label: &'static str) -> Self {
+    Self {
+      label,
+      vertices: create_buffer::<V>(device, label, BufferUsages::VERTEX, Self::INITIAL_CAPACITY),
+      indices: create_buffer::<u32>(device, label, BufferUsages::INDEX, Self::INITIAL_CAPACITY),
+      vertex_capacity: Self::INITIAL_CAPACITY,
+      index_capacity: Self::INITIAL_CAPACITY,
+      index_count: 0,
+      _vertex: PhantomData,
+    }
+  }
+
+  /// Replaces the stored geometry.
+  pub fn upload(&mut self, device: &Device, queue: &Queue, vertices: &[V], indices: &[u32]) {
+    if vertices.len() > self.vertex_capacity {
+      self.vertex_capacity = vertices.len().next_power_of_two();
+      self.vertices = create_buffer::<V>(
+        device,
+        self.label,
+        BufferUsages::VERTEX,
+        self.vertex_capacity,
+      );
+    }
+    if indices.len() > self.index_capacity {
+      self.index_capacity = indices.len().next_power_of_two();
+      self.indices =
+        create_buffer::<u32>(device, self.label, BufferUsages::INDEX, self.index_capacity);
+    }
+
+    if !vertices.is_empty() {
+      queue.write_buffer(&self.vertices, 0, bytemuck::cast_slice(vertices));
+    }
+    if !indices.is_empty() {
+      queue.write_buffer(&self.indices, 0, bytemuck::cast_slice(indices));
+    }
+    self.index_count = indices.len() as u32;
+  }
+
+  pub fn is_empty(&self) -> bool {
+    self.index_count == 0
+  }
+
+  /// Issues an indexed draw of everything uploaded, using vertex slot 0.
+  pub fn draw<'a>(&'a self, pass: &mut RenderPass<'a>, stats: &mut FrameStats) {
+    self.draw_range(pass, 0..self.index_count, stats);
+  }
+
+  /// Like [`draw`](Self::draw) for only the uploaded `indices`.
+  pub fn draw_range<'a>(
+    &'a self,
+    pass: &mut RenderPass<'a>,
+    indices: Range<u32>,
+    stats: &mut FrameStats,
+  ) {
+    let indices = indices.start.min(self.index_count)..indices.end.min(self.index_count);
+    if indices.is_empty() {
+      return;
+    }
+    pass.set_vertex_buffer(0, self.vertices.slice(..));
+    pass.set_index_buffer(self.indices.slice(..), IndexFormat::Uint32);
+    stats.draw_calls += 1;
+    stats.triangles += indices.len() as u32 / 3;
+    pass.draw_indexed(indices, 0, 0..1);
+  }
+}
+
+fn create_buffer<T>(device: &Device, label: &str, usage: BufferUsages, len: usize) -> Buffer {
+  device.create_buffer(&BufferDescriptor {
+    label: Some(label),
+    size: (mem::size_of::<T>() * len) as u64,
+    usage: usage | BufferUsages::COPY_DST,
+    mapped_at_creation: false,
+  })
+}
+
+/// Flat colored triangles in pixel coordinates, drawn with [`COLOR_SHADER`].
+pub(crate) struct ColorPipeline {
+  pipeline: RenderPipeline,
+  screen: ScreenUniform,
+  pub(crate) geometry: GeometryBuffer<ColorVertex>,
+}
+
+impl ColorPipeline {
+  /// Builds the pipeline from `shader`, usually [`COLOR_SHADER`].
+  pub(crate) async fn new(
+    device: &Device,
+    format: TextureFormat,
+    width: u32,
+    height: u32,
+    (name, source): (&str, &str),
+  ) -> Result<Self, WindshieldError> {
+    let shader = create_shader(device, name, source).await?;
+    let screen = ScreenUniform::new(device, width, height);
+    let pipeline = PipelineBuilder::new("Color Pipeline", &shader)
+      .vertex::<ColorVertex>()
+      .bind_group_layout(screen.layout())
+      .build(device, format);
+
+    Ok(Self {
+      pipeline,
+      screen,
+      geometry: GeometryBuffer::new(device, "Color Geometry"),
+    })
+  }
+
+  pub(crate) fn resize(&mut self, queue: &Queue, width: u32, height: u32) {
+    self.screen.resize(queue, width, height);
+  }
+
+  pub(crate) fn set_mirror(&mut self, queue: &Queue, mirror: Mirror) {
+    self.screen.set_mirror(queue, mirror);
+  }
+
+  pub(crate) fn set_high_contrast(&mut self, queue: &Queue, high_contrast: bool) {
+    self.screen.set_high_contrast(queue, high_contrast);
+  }
+
+  /// Draws the uploaded `indices`, a layer of the canvas.
+  pub(crate) fn draw<'a>(
+    &'a self,
+    pass: &mut RenderPass<'a>,
+    indices: Range<u32>,
+    stats: &mut FrameStats,
+  ) {
+    if indices.is_empty() || self.geometry.is_empty() {
+      return;
+    }
+    pass.set_pipeline(&self.pipeline);
+    pass.set_bind_group(0, self.screen.bind_group(), &[]);
+    self.geometry.draw_range(pass, indices, stats);
+  }
+}
@@ -0,0 +1,107 @@
+//! Keeping compiled shaders on disk from one run to the next, with
+//! [`use_dir`].
+//!
+//! wgpu has no pipeline cache of its own, but the drivers compiling its
+//! shaders into GPU code do. They keep theirs in the home directory by
+//! default, which embedded devices often mount read only or in memory, so
+//! every boot compiles every shader again. Pointing them at a directory
+//! that persists skips that after the first boot.
+//!
+//! Drivers are told through environment variables, which only the
+//! embedder can set safely, so the app itself never does.
+
+use std::ffi::OsString;
+use std::fs;
+use std::path::Path;
+
+use crate::error::WindshieldError;
+
+/// How a driver is told where to keep its shader cache.
+struct Driver {
+  /// Environment variable of the cache directory.
+  dir: &'static str,
+  /// Subdirectory it's kept in.
+  subdir: &'static str,
+  /// Environment variables enabling the cache, with their values.
+  enable: &'static [(&'static str, &'static str)],
+}
+
+const DRIVERS: &[Driver] = &[
+  // Mesa, for GL and Vulkan, like on a Raspberry Pi or Intel and AMD
+  // GPUs
+  Driver {
+    dir: "MESA_SHADER_CACHE_DIR",
+    subdir: "mesa",
+    enable: &[("MESA_SHADER_CACHE_DISABLE", "false")],
+  },
+  // NVIDIA's own driver, which limits and cleans up the cache otherwise
+  Driver {
+    dir: "__GL_SHADER_DISK_CACHE_PATH",
+    subdir: "nvidia",
+    enable: &[
+      ("__GL_SHADER_DISK_CACHE", "1"),
+      ("__GL_SHADER_DISK_CACHE_SKIP_CLEANUP", "1"),
+    ],
+  },
+];
+
+/// Points the shader caches of the drivers at `dir`, like Mesa's and
+/// NVIDIA's, by setting their environment variables. Drivers already
+/// configured through them are left as they are.
+///
+/// To be called first thing in `main`, before any threads are started, as
+/// others reading the environment meanwhile is undefined behavior on
+/// Unix, and before the app, whose drivers read the variables once.
+pub fn use_dir(dir: &Path) -> Result<(), WindshieldError> {
+  for (name, value) in variables(dir, |name| std::env::var_os(name)) {
+    let is_dir = DRIVERS.iter().any(|driver| driver.dir == name);
+    if let Some(dir) = is_dir.then_some(&value) {
+      // Drivers don't create them
+      fs::create_dir_all(dir).map_err(|source| WindshieldError::WriteFile {
+        path: dir.into(),
+        source,
+      })?;
+    }
+    tracing::debug!("setting {}={:?}", name, value);
+    std::env::set_var(name, value);
+  }
+  Ok(())
+}
+
+/// The environment variables to set for caching in `dir`, leaving out
+/// those of drivers `var` already has a directory for.
+fn variables(dir: &Path, var: impl Fn(&str) -> Option<OsString>) -> Vec<(&'static str, OsString)> {
+  DRIVERS
+    .iter()
+    .filter(|driver| var(driver.dir).is_none())
+    .flat_map(|driver| {
+      let dir = (driver.dir, dir.join(driver.subdir).into_os_string());
+      let enable = driver
+        .enable
+        .iter()
+        .filter(|(name, _)| var(name).is_none())
+        .map(|(name, value)| (*name, OsString::from(value)));
+      std::iter::once(dir).chain(enable)
+    })
+    .collect()
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn leaves_drivers_configured_elsewhere() {
+    let dir = Path::new("/var/cache/windshield");
+    let set = variables(dir, |name| {
+      (name == "__GL_SHADER_DISK_CACHE_PATH").then(|| "/tmp".into())
+    });
+    assert_eq!(
+      set,
+      [
+        ("MESA_SHADER_CACHE_DIR", "/var/cache/windshield/mesa".into()),
+        ("MESA_SHADER_CACHE_DISABLE", "false".into()),
+      ]
+    );
+  }
+}
@@ -0,0 +1,118 @@
+use std::time::Duration;
+
+use instant::Instant;
+
+use crate::data::Telemetry;
+
+/// Whether the display shows anything. Off, frames are blank and only
+/// drawn when something asks for one, like the watchdog's heartbeats,
+/// until [`Action::Power`](crate::input::Action::Power) or the ignition,
+/// see [`Telemetry::ignition`], turns it back on.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Power {
+  #[default]
+  On,
+  Off,
+}
+
+impl Power {
+  pub fn toggled(self) -> Self {
+    match self {
+      Self::On => Self::Off,
+      Self::Off => Self::On,
+    }
+  }
+}
+
+/// Turns the display off and on with the ignition, only as it changes so
+/// that turning it on by hand with the ignition off sticks. Off only once
+/// the ignition stayed off for
+/// [`Settings::ignition_off_delay`](crate::Settings::ignition_off_delay).
+#[derive(Default)]
+pub(crate) struct Ignition {
+  on: Option<bool>,
+  off_delay: Duration,
+  // When the display turns off, unless the ignition comes back on first
+  off_at: Option<Instant>,
+}
+
+impl Ignition {
+  pub(crate) fn new(off_delay: Duration) -> Self {
+    Self {
+      off_delay,
+      ..Self::default()
+    }
+  }
+
+  pub(crate) fn set_off_delay(&mut self, off_delay: Duration) {
+    self.off_delay = off_delay;
+  }
+
+  /// What the display turns to at `now`, if the ignition changed since
+  /// last time or stayed off long enough.
+  pub(crate) fn update(&mut self, telemetry: &Telemetry, now: Instant) -> Option<Power> {
+    if let Some(on) = telemetry.ignition {
+      if self.on.replace(on) != Some(on) {
+        if on {
+          self.off_at = None;
+          return Some(Power::On);
+        }
+        self.off_at = Some(now + self.off_delay);
+      }
+    }
+    self.off_at.filter(|off_at| *off_at <= now)?;
+    self.off_at = None;
+    Some(Power::Off)
+  }
+
+  /// When the display turns off unless the ignition comes back on.
+  pub(crate) fn due(&self) -> Option<Instant> {
+    self.off_at
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn ignition(on: Option<bool>) -> Telemetry {
+    Telemetry {
+      ignition: on,
+      ..Default::default()
+    }
+  }
+
+  #[test]
+  fn follows_the_ignition_as_it_changes() {
+    let mut tracker = Ignition::default();
+    let now = Instant::now();
+    assert_eq!(tracker.update(&ignition(None), now), None);
+    assert_eq!(tracker.update(&ignition(Some(true)), now), Some(Power::On));
+    assert_eq!(tracker.update(&ignition(Some(true)), now), None);
+    assert_eq!(
+      tracker.update(&ignition(Some(false)), now),
+      Some(Power::Off)
+    );
+    assert_eq!(tracker.update(&ignition(Some(false)), now), None);
+    assert_eq!(tracker.update(&ignition(Some(true)), now), Some(Power::On));
+  }
+
+  #[test]
+  fn stays_on_while_the_ignition_is_off_briefly() {
+    let mut tracker = Ignition::new(Duration::from_secs(5));
+    let start = Instant::now();
+    let at = |seconds| start + Duration::from_secs(seconds);
+    tracker.update(&ignition(Some(true)), at(0));
+    // Cranking
+    assert_eq!(tracker.update(&ignition(Some(false)), at(1)), None);
+    assert_eq!(tracker.due(), Some(at(6)));
+    assert_eq!(
+      tracker.update(&ignition(Some(true)), at(2)),
+      Some(Power::On)
+    );
+    assert_eq!(tracker.update(&ignition(Some(false)), at(10)), None);
+    assert_eq!(tracker.update(&ignition(None), at(14)), None);
+    assert_eq!(tracker.update(&ignition(None), at(15)), Some(Power::Off));
+    assert_eq!(tracker.due(), None);
+  }
+}
@@ -0,0 +1,177 @@
+//! Drawing every pipeline once before the first frame, so that drivers
+//! compiling shaders on first use and glyphs being rasterized don't make
+//! the first frames with text or gauges stutter, see
+//! [`Settings::prewarm`](crate::Settings::prewarm).
+//!
+//! Which [`Variant`]s a run drew can be kept in a [`VariantList`] file, see
+//! [`Settings::prewarm_list`](crate::Settings::prewarm_list), to warm up
+//! just those, in the fonts and sizes used, the next time.
+
+use std::collections::BTreeSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use crate::cache::WidgetCache;
+use crate::canvas::Style;
+use crate::error::WindshieldError;
+use crate::frame::Frame;
+use crate::image::{ColorMatrix, Fit, Image};
+use crate::text::{FontId, TextRenderer, TextSection};
+use crate::widgets::{Rect, Widget};
+
+/// Rasterized for every text variant, what most labels and gauges show.
+const GLYPHS: &str = " !\"#$%&'()*+,-./0123456789:;<=>?@ABCDEFGHIJKLMNOPQRSTUVWXYZ[\\]^_`\
+                      abcdefghijklmnopqrstuvwxyz{|}~°";
+
+/// Text size of the variants warmed up without a list, that of
+/// [`TextSection::new`].
+const DEFAULT_SIZE: u32 = 32;
+
+/// Something drawn differently enough to be worth warming up.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Variant {
+  /// Shapes on the [`Canvas`](crate::canvas::Canvas).
+  Shapes,
+  /// Images, see [`Frame::image`].
+  Images,
+  /// Widgets shown from their cache, see [`Frame::cached_widget`].
+  Cached,
+  /// Text in the font of that [`FontId`] at a size in whole pixels.
+  Text { font: usize, size: u32 },
+}
+
+impl Variant {
+  /// Every pipeline, and text in every font registered with `text` at the
+  /// default size.
+  pub(crate) fn registered(text: &TextRenderer) -> Vec<Self> {
+    let mut variants = vec![Self::Shapes, Self::Images, Self::Cached];
+    variants.extend((0..text.font_count()).map(|font| Self::Text {
+      font,
+      size: DEFAULT_SIZE,
+    }));
+    variants
+  }
+}
+
+/// The variants drawn by earlier runs, saved as JSON.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct VariantList {
+  pub variants: BTreeSet<Variant>,
+}
+
+impl VariantList {
+  pub fn load(path: impl AsRef<Path>) -> Result<Self, WindshieldError> {
+    let path = path.as_ref();
+    let source = fs::read_to_string(path).map_err(|source| WindshieldError::ReadFile {
+      path: path.to_path_buf(),
+      source,
+    })?;
+    serde_json::from_str(&source).map_err(|err| WindshieldError::InvalidVariantList {
+      path: path.to_path_buf(),
+      message: err.to_string(),
+    })
+  }
+
+  pub fn save(&self, path: impl AsRef<Path>) -> Result<(), WindshieldError> {
+    let path = path.as_ref();
+    let list = serde_json::to_string_pretty(self).expect("variant lists serialize");
+    fs::write(path, list).map_err(|source| WindshieldError::WriteFile {
+      path: path.to_path_buf(),
+      source,
+    })
+  }
+}
+
+/// Adds the variants drawn to a [`VariantList`] file, saving it whenever
+/// there's a new one.
+pub(crate) struct VariantRecorder {
+  path: PathBuf,
+  list: VariantList,
+}
+
+impl VariantRecorder {
+  /// Adds to `list`, read from `path` before.
+  pub(crate) fn new(path: PathBuf, list: VariantList) -> Self {
+    Self { path, list }
+  }
+
+  pub(crate) fn record(&mut self, variants: impl IntoIterator<Item = Variant>) {
+    let mut added = false;
+    for variant in variants {
+      added |= self.list.variants.insert(variant);
+    }
+    if added {
+      if let Err(err) = self.list.save(&self.path) {
+        tracing::warn!("{}", err);
+      }
+    }
+  }
+}
+
+/// Draws each of `variants` into the corner of `frame`, invisibly.
+pub(crate) fn draw(
+  frame: &mut Frame,
+  variants: &[Variant],
+  image: &Image,
+  cache: &mut WidgetCache,
+) {
+  let rect = Rect::new(0.0, 0.0, 1.0, 1.0);
+  let clear = [0.0; 4];
+  for variant in variants {
+    match *variant {
+      Variant::Shapes => {
+        frame
+          .canvas
+          .rect([0.0, 0.0], [1.0, 1.0], Style::fill(clear));
+        frame
+          .canvas
+          .circle([0.5, 0.5], 0.5, Style::stroke(clear, 1.0));
+      }
+      Variant::Images => frame.image(image, rect, Fit::Fill, 0.0, ColorMatrix::IDENTITY),
+      Variant::Cached => frame.cached_widget(&mut Blank, rect, cache),
+      // Fonts of a list from another config may not be there
+      Variant::Text { font, size } if font < frame.text.font_count() => {
+        let section = TextSection::new(GLYPHS)
+          .with_font(FontId(font))
+          .with_size(size as f32)
+          .with_color(clear);
+        frame.text.queue(&section);
+      }
+      Variant::Text { .. } => {}
+    }
+  }
+}
+
+/// Draws a shape and nothing else, cached.
+struct Blank;
+
+impl Widget for Blank {
+  fn draw(&self, frame: &mut Frame, rect: Rect) {
+    let style = Style::fill([0.0; 4]);
+    frame
+      .canvas
+      .rect([rect.x, rect.y], [rect.width, rect.height], style);
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn lists_round_trip_as_json() {
+    let list = VariantList {
+      variants: [Variant::Shapes, Variant::Text { font: 1, size: 24 }].into(),
+    };
+    let json = serde_json::to_string(&list).unwrap();
+    assert_eq!(
+      json,
+      r#"{"variants":[{"kind":"shapes"},{"kind":"text","font":1,"size":24}]}"#
+    );
+    assert_eq!(serde_json::from_str::<VariantList>(&json).unwrap(), list);
+  }
+}
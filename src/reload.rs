@@ -0,0 +1,94 @@
+use std::collections::BTreeSet;
+use std::ffi::OsString;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Receiver};
+
+use notify::{Event, RecommendedWatcher, RecursiveMode, Watcher};
+
+use crate::wake::Waker;
+
+/// Watches files for changes so they can be reloaded while running.
+///
+/// The directories containing the files are watched rather than the files
+/// themselves, since many editors save by replacing the file.
+pub(crate) struct FileWatcher {
+  // Stops watching when dropped
+  _watcher: RecommendedWatcher,
+  files: Vec<Watched>,
+  events: Receiver<Event>,
+}
+
+struct Watched {
+  path: PathBuf,
+  dir: PathBuf,
+  name: OsString,
+}
+
+impl FileWatcher {
+  /// Watches `paths`, waking `waker` whenever something happened to them.
+  pub(crate) fn new(paths: &[PathBuf], waker: Waker) -> notify::Result<Self> {
+    let (sender, events) = mpsc::channel();
+    // Nothing is watched on the web, where the waker can't go to another
+    // thread
+    #[cfg(target_arch = "wasm32")]
+    let _ = waker;
+    let mut watcher =
+      notify::recommended_watcher(move |event: notify::Result<Event>| match event {
+        Ok(event) => {
+          if sender.send(event).is_ok() {
+            #[cfg(not(target_arch = "wasm32"))]
+            waker.wake();
+          }
+        }
+        Err(err) => tracing::warn!("file watcher error: {}", err),
+      })?;
+
+    let mut files = Vec::new();
+    let mut dirs = BTreeSet::new();
+    for path in paths {
+      let (Some(parent), Some(name)) = (path.parent(), path.file_name()) else {
+        continue;
+      };
+      let parent = if parent.as_os_str().is_empty() {
+        Path::new(".")
+      } else {
+        parent
+      };
+      let dir = parent.canonicalize()?;
+      if dirs.insert(dir.clone()) {
+        watcher.watch(&dir, RecursiveMode::NonRecursive)?;
+      }
+      files.push(Watched {
+        path: path.clone(),
+        dir,
+        name: name.to_os_string(),
+      });
+    }
+    tracing::debug!("watching {} files for changes", files.len());
+
+    Ok(Self {
+      _watcher: watcher,
+      files,
+      events,
+    })
+  }
+
+  /// Watched files changed since the last call, as they were passed to
+  /// [`new`](Self::new).
+  pub(crate) fn changed(&self) -> Vec<&Path> {
+    let mut changed = BTreeSet::new();
+    for event in self.events.try_iter() {
+      if !(event.kind.is_modify() || event.kind.is_create()) {
+        continue;
+      }
+      for path in &event.paths {
+        for file in &self.files {
+          if path.parent() == Some(&file.dir) && path.file_name() == Some(&file.name) {
+            changed.insert(file.path.as_path());
+          }
+        }
+      }
+    }
+    changed.into_iter().collect()
+  }
+}
@@ -0,0 +1,273 @@
+//! Window input recorded to a file with the frame it came in after, and
+//! played back into the app on the same frames, so a bug report can come
+//! with the touches and keys that led to it, see [`Settings::record`] and
+//! [`Settings::replay`].
+//!
+//! Recordings are JSON lines, one event each:
+//!
+//! ```json
+//! {"frame":12,"type":"touch","id":0,"phase":"Started","x":120.0,"y":300.5}
+//! {"frame":12,"type":"keyboard","input":{"scancode":36,"state":"Pressed","virtual_keycode":"Return","modifiers":{"shift":false,"ctrl":false,"alt":false,"logo":false}}}
+//! ```
+//!
+//! Replays draw frames back to back until the last event is played, in
+//! [deterministic mode][`Settings::deterministic`] the same frames as when
+//! recording deterministically. How long input is held, like for long
+//! presses, goes by the app's clock as well.
+//!
+//! [`Settings::record`]: crate::Settings::record
+//! [`Settings::replay`]: crate::Settings::replay
+//! [`Settings::deterministic`]: crate::Settings::deterministic
+
+use std::collections::VecDeque;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+use winit::dpi::PhysicalPosition;
+use winit::event::{
+  DeviceId, ElementState, KeyboardInput, ModifiersState, MouseButton, MouseScrollDelta, Touch,
+  TouchPhase, WindowEvent,
+};
+
+use crate::error::WindshieldError;
+
+/// A window event that can be recorded, in physical pixels.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Input {
+  Focused {
+    focused: bool,
+  },
+  Keyboard {
+    input: KeyboardInput,
+  },
+  Modifiers {
+    modifiers: ModifiersState,
+  },
+  Character {
+    character: char,
+  },
+  CursorMoved {
+    x: f64,
+    y: f64,
+  },
+  CursorLeft,
+  MouseButton {
+    button: MouseButton,
+    state: ElementState,
+  },
+  MouseWheel {
+    delta: MouseScrollDelta,
+    phase: TouchPhase,
+  },
+  Touch {
+    id: u64,
+    phase: TouchPhase,
+    x: f64,
+    y: f64,
+  },
+}
+
+impl Input {
+  /// The input `event` is, `None` for events that aren't input like
+  /// redraws or that can't be played back like dropped files. Resizes
+  /// aren't either, the window has the size it has, so replay at the size
+  /// recorded at.
+  pub fn from_event(event: &WindowEvent) -> Option<Self> {
+    Some(match *event {
+      WindowEvent::Focused(focused) => Self::Focused { focused },
+      WindowEvent::KeyboardInput { input, .. } => Self::Keyboard { input },
+      WindowEvent::ModifiersChanged(modifiers) => Self::Modifiers { modifiers },
+      WindowEvent::ReceivedCharacter(character) => Self::Character { character },
+      WindowEvent::CursorMoved { position, .. } => Self::CursorMoved {
+        x: position.x,
+        y: position.y,
+      },
+      WindowEvent::CursorLeft { .. } => Self::CursorLeft,
+      WindowEvent::MouseInput { button, state, .. } => Self::MouseButton { button, state },
+      WindowEvent::MouseWheel { delta, phase, .. } => Self::MouseWheel { delta, phase },
+      WindowEvent::Touch(touch) => Self::Touch {
+        id: touch.id,
+        phase: touch.phase,
+        x: touch.location.x,
+        y: touch.location.y,
+      },
+      _ => return None,
+    })
+  }
+
+  /// The event to play back, from a device that doesn't exist.
+  #[allow(deprecated)]
+  pub fn to_event(self) -> WindowEvent<'static> {
+    // Nothing tells devices apart, there's only ever one of each kind
+    let device_id = unsafe { DeviceId::dummy() };
+    match self {
+      Self::Focused { focused } => WindowEvent::Focused(focused),
+      Self::Keyboard { input } => WindowEvent::KeyboardInput {
+        device_id,
+        input,
+        is_synthetic: false,
+      },
+      Self::Modifiers { modifiers } => WindowEvent::ModifiersChanged(modifiers),
+      Self::Character { character } => WindowEvent::ReceivedCharacter(character),
+      Self::CursorMoved { x, y } => WindowEvent::CursorMoved {
+        device_id,
+        position: PhysicalPosition::new(x, y),
+        modifiers: ModifiersState::default(),
+      },
+      Self::CursorLeft => WindowEvent::CursorLeft { device_id },
+      Self::MouseButton { button, state } => WindowEvent::MouseInput {
+        device_id,
+        state,
+        button,
+        modifiers: ModifiersState::default(),
+      },
+      Self::MouseWheel { delta, phase } => WindowEvent::MouseWheel {
+        device_id,
+        delta,
+        phase,
+        modifiers: ModifiersState::default(),
+      },
+      Self::Touch { id, phase, x, y } => WindowEvent::Touch(Touch {
+        device_id,
+        phase,
+        location: PhysicalPosition::new(x, y),
+        force: None,
+        id,
+      }),
+    }
+  }
+}
+
+/// An [`Input`] and the number of frames drawn before it came in.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Recorded {
+  pub frame: u64,
+  #[serde(flatten)]
+  pub input: Input,
+}
+
+/// Writes the input of a window to a recording as it comes in.
+pub struct Recorder {
+  path: PathBuf,
+  writer: BufWriter<File>,
+}
+
+impl Recorder {
+  /// Records to a new file at `path`, replacing what was there.
+  pub fn create(path: impl AsRef<Path>) -> Result<Self, WindshieldError> {
+    let path = path.as_ref().to_path_buf();
+    let file = File::create(&path).map_err(|source| WindshieldError::WriteFile {
+      path: path.clone(),
+      source,
+    })?;
+    Ok(Self {
+      path,
+      writer: BufWriter::new(file),
+    })
+  }
+
+  /// Adds `event`, if it's input, as coming in after `frame` frames.
+  pub fn record(&mut self, frame: u64, event: &WindowEvent) {
+    let Some(input) = Input::from_event(event) else {
+      return;
+    };
+    let line = serde_json::to_string(&Recorded { frame, input }).expect("input serializes");
+    // Flushed right away, the event loop never returns to drop the writer
+    let written = writeln!(self.writer, "{}", line).and_then(|()| self.writer.flush());
+    if let Err(err) = written {
+      tracing::error!("unable to record input to {}: {}", self.path.display(), err);
+    }
+  }
+}
+
+/// A recording played back, see [`Recorder`].
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Replay {
+  events: VecDeque<Recorded>,
+}
+
+impl Replay {
+  pub fn load(path: impl AsRef<Path>) -> Result<Self, WindshieldError> {
+    let path = path.as_ref();
+    let source = std::fs::read_to_string(path).map_err(|source| WindshieldError::ReadFile {
+      path: path.to_path_buf(),
+      source,
+    })?;
+    Self::parse(&source).map_err(|message| WindshieldError::InvalidRecording {
+      path: path.to_path_buf(),
+      message,
+    })
+  }
+
+  pub fn parse(source: &str) -> Result<Self, String> {
+    let mut events = source
+      .lines()
+      .enumerate()
+      .filter(|(_, line)| !line.trim().is_empty())
+      .map(|(index, line)| {
+        serde_json::from_str(line).map_err(|err| format!("line {}: {}", index + 1, err))
+      })
+      .collect::<Result<Vec<Recorded>, _>>()?;
+    // Played in the order of their frames, in the order recorded within one
+    events.sort_by_key(|recorded| recorded.frame);
+    Ok(Self {
+      events: events.into(),
+    })
+  }
+
+  /// The events that came in once `frame` frames were drawn, and any
+  /// earlier ones not played yet.
+  pub fn due(&mut self, frame: u64) -> Vec<WindowEvent<'static>> {
+    let due = self
+      .events
+      .iter()
+      .take_while(|recorded| recorded.frame <= frame)
+      .count();
+    self
+      .events
+      .drain(..due)
+      .map(|recorded| recorded.input.to_event())
+      .collect()
+  }
+
+  /// Whether every event was played.
+  pub fn is_finished(&self) -> bool {
+    self.events.is_empty()
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn plays_back_what_was_recorded_on_its_frames() {
+    let path = std::env::temp_dir().join(format!("windshield-replay-{}", std::process::id()));
+    let mut recorder = Recorder::create(&path).unwrap();
+    let touch = Input::Touch {
+      id: 3,
+      phase: TouchPhase::Started,
+      x: 120.0,
+      y: 300.5,
+    };
+    let key = Input::Character { character: 'a' };
+    recorder.record(2, &key.to_event());
+    recorder.record(0, &touch.to_event());
+    recorder.record(2, &WindowEvent::Occluded(true));
+    drop(recorder);
+
+    let mut replay = Replay::load(&path).unwrap();
+    std::fs::remove_file(path).unwrap();
+    let played = |events: Vec<WindowEvent>| -> Vec<_> {
+      events.iter().filter_map(Input::from_event).collect()
+    };
+    assert_eq!(played(replay.due(1)), [touch]);
+    assert!(!replay.is_finished());
+    assert_eq!(played(replay.due(5)), [key]);
+    assert!(replay.is_finished());
+    assert!(Replay::parse("{\"frame\":1}").is_err());
+  }
+}
@@ -0,0 +1,278 @@
+//! Alerts declared in the config rather than code: conditions over
+//! telemetry that show widgets, light telltales, and ask the application
+//! for chimes and notifications while they hold.
+//!
+//! ```toml
+//! [[rules]]
+//! name = "overheating"
+//! when = "coolant_temp > 110"
+//! until = "coolant_temp < 105"
+//! after = 2.0
+//! show = ["coolant_warning"]
+//! telltale = "coolant"
+//! chime = "warning"
+//! notify = "Engine overheating, stop safely"
+//! ```
+
+use std::time::Duration;
+
+use serde::Deserialize;
+
+use crate::data::expr::Expr;
+use crate::data::Telemetry;
+use crate::safety::{Telltale, Telltales};
+use crate::scene::Scene;
+
+/// A condition and what it does while it holds, see the
+/// [module](self) for an example.
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Rule {
+  pub name: String,
+  /// [Expression](crate::data::expr) making the rule active, combining
+  /// several with `&&` and `||`.
+  pub when: Expr,
+  /// Makes the rule inactive again once it holds, `when` no longer
+  /// holding if unset. A threshold below that of `when` keeps values
+  /// hovering around it from toggling the rule every frame.
+  pub until: Option<Expr>,
+  /// Seconds the rule's condition to change has to keep holding before
+  /// it does, so brief spikes don't flash alerts.
+  #[serde(default)]
+  pub after: f32,
+  /// Names of widgets shown while the rule is active and hidden while
+  /// it's not.
+  #[serde(default)]
+  pub show: Vec<String>,
+  /// Lit while the rule is active, whatever the data sources report.
+  pub telltale: Option<Telltale>,
+  /// Sound for the application to play when the rule becomes active, see
+  /// [`WindshieldAppBuilder::on_rule`](crate::WindshieldAppBuilder::on_rule).
+  pub chime: Option<String>,
+  /// Message for the application to show when the rule becomes active,
+  /// also logged as a warning.
+  pub notify: Option<String>,
+}
+
+impl Rule {
+  pub fn new(name: impl Into<String>, when: Expr) -> Self {
+    Self {
+      name: name.into(),
+      when,
+      until: None,
+      after: 0.0,
+      show: Vec::new(),
+      telltale: None,
+      chime: None,
+      notify: None,
+    }
+  }
+
+  pub fn with_until(mut self, until: Expr) -> Self {
+    self.until = Some(until);
+    self
+  }
+
+  pub fn with_after(mut self, after: Duration) -> Self {
+    self.after = after.as_secs_f32();
+    self
+  }
+
+  pub fn with_show(mut self, name: impl Into<String>) -> Self {
+    self.show.push(name.into());
+    self
+  }
+
+  pub fn with_telltale(mut self, telltale: Telltale) -> Self {
+    self.telltale = Some(telltale);
+    self
+  }
+
+  pub fn with_chime(mut self, chime: impl Into<String>) -> Self {
+    self.chime = Some(chime.into());
+    self
+  }
+
+  pub fn with_notify(mut self, message: impl Into<String>) -> Self {
+    self.notify = Some(message.into());
+    self
+  }
+
+  /// Whether the rule should change from `active`, if the fields it needs
+  /// are known.
+  fn changes(&self, active: bool, telemetry: &Telemetry) -> Option<bool> {
+    match (active, &self.until) {
+      (false, _) => self.when.holds(telemetry),
+      (true, Some(until)) => until.holds(telemetry),
+      (true, None) => self.when.holds(telemetry).map(|holds| !holds),
+    }
+  }
+}
+
+/// Where a rule is at.
+#[derive(Clone, Copy, Debug, Default)]
+struct Progress {
+  active: bool,
+  /// How long the condition to change has held so far.
+  held: Duration,
+}
+
+/// Evaluates [`Rule`]s every frame.
+pub(crate) struct Rules {
+  rules: Vec<Rule>,
+  progress: Vec<Progress>,
+  // Indices of the rules that changed since the last `take_changes`
+  changed: Vec<usize>,
+}
+
+impl Rules {
+  pub(crate) fn new(rules: Vec<Rule>) -> Self {
+    Self {
+      progress: vec![Progress::default(); rules.len()],
+      rules,
+      changed: Vec::new(),
+    }
+  }
+
+  /// Replaces the rules, keeping those of the same name and condition
+  /// active, e.g. after the config is reloaded.
+  pub(crate) fn set(&mut self, rules: Vec<Rule>) {
+    let progress = rules
+      .iter()
+      .map(|rule| {
+        let old = self
+          .rules
+          .iter()
+          .position(|old| old.name == rule.name && old.when == rule.when);
+        old.map_or_else(Progress::default, |old| self.progress[old])
+      })
+      .collect();
+    self.rules = rules;
+    self.progress = progress;
+    self.changed.clear();
+  }
+
+  /// Advances the rules by `delta` with the latest `telemetry`, returning
+  /// whether any became active or inactive.
+  pub(crate) fn update(&mut self, telemetry: &Telemetry, delta: Duration) -> bool {
+    let mut changed = false;
+    for (index, (rule, progress)) in self.rules.iter().zip(&mut self.progress).enumerate() {
+      match rule.changes(progress.active, telemetry) {
+        Some(true) => progress.held += delta,
+        Some(false) => progress.held = Duration::ZERO,
+        // Keeps waiting while unknown, like the rule's state
+        None => continue,
+      }
+      if progress.held.as_secs_f32() >= rule.after {
+        progress.active = !progress.active;
+        progress.held = Duration::ZERO;
+        self.changed.push(index);
+        changed = true;
+        match (&rule.notify, progress.active) {
+          (Some(message), true) => tracing::warn!("{}", message),
+          (None, true) => tracing::info!("rule {} is active", rule.name),
+          (_, false) => tracing::info!("rule {} is no longer active", rule.name),
+        }
+      }
+    }
+    changed
+  }
+
+  /// Whether a rule is waiting for its condition to hold long enough,
+  /// needing more frames to get there.
+  pub(crate) fn is_pending(&self) -> bool {
+    self
+      .progress
+      .iter()
+      .any(|progress| !progress.held.is_zero())
+  }
+
+  /// `telltales` with those of active rules lit.
+  pub(crate) fn light(&self, mut telltales: Telltales) -> Telltales {
+    let active = self.rules.iter().zip(&self.progress);
+    for (rule, _) in active.filter(|(_, progress)| progress.active) {
+      if let Some(telltale) = rule.telltale {
+        telltales.set(telltale, true);
+      }
+    }
+    telltales
+  }
+
+  /// Shows the widgets of active rules in `scene` and hides those of the
+  /// others.
+  pub(crate) fn show(&self, scene: &mut Scene) {
+    for (rule, progress) in self.rules.iter().zip(&self.progress) {
+      for name in &rule.show {
+        if let Some(node) = scene.find_mut(name) {
+          node.visible = progress.active;
+        }
+      }
+    }
+  }
+
+  /// The rules that became active or inactive since the last call, with
+  /// whether they are active now.
+  pub(crate) fn take_changes(&mut self) -> Vec<(&Rule, bool)> {
+    let changed = std::mem::take(&mut self.changed);
+    changed
+      .into_iter()
+      .map(|index| (&self.rules[index], self.progress[index].active))
+      .collect()
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::config::toml_example;
+
+  fn at(temp: f32) -> Telemetry {
+    Telemetry {
+      coolant_temp: Some(temp),
+      ..Default::default()
+    }
+  }
+
+  #[test]
+  fn debounces_with_hysteresis() {
+    let rule = Rule::new("hot", "coolant_temp > 110".parse().unwrap())
+      .with_until("coolant_temp < 105".parse().unwrap())
+      .with_after(Duration::from_secs(1))
+      .with_telltale(Telltale::Coolant);
+    let mut rules = Rules::new(vec![rule]);
+    let half = Duration::from_millis(500);
+    // A spike too short to count
+    assert!(!rules.update(&at(112.0), half));
+    assert!(!rules.update(&at(100.0), half));
+    assert!(!rules.update(&at(112.0), half));
+    assert!(rules.is_pending());
+    assert!(rules.update(&at(112.0), half));
+    let changes = rules.take_changes();
+    assert_eq!(changes.len(), 1);
+    assert_eq!((changes[0].0.name.as_str(), changes[0].1), ("hot", true));
+    assert!(rules.light(Telltales::default()).is_lit(Telltale::Coolant));
+    // Between the thresholds, and unknown, it stays active
+    for telemetry in [at(108.0), at(108.0), at(108.0), Telemetry::default()] {
+      assert!(!rules.update(&telemetry, half));
+    }
+    assert!(!rules.update(&at(104.0), half));
+    assert!(rules.update(&at(104.0), half));
+    assert!(!rules.light(Telltales::default()).is_lit(Telltale::Coolant));
+  }
+
+  #[test]
+  fn parses_the_module_example() {
+    let example = toml_example(include_str!("rules.rs"), "//!");
+    #[derive(Deserialize)]
+    struct Example {
+      rules: Vec<Rule>,
+    }
+    let example: Example = toml::from_str(&example).unwrap();
+    let rule = &example.rules[0];
+    assert_eq!(rule.telltale, Some(Telltale::Coolant));
+    assert_eq!(
+      rule.until.as_ref().unwrap().to_string(),
+      "coolant_temp < 105"
+    );
+  }
+}
@@ -0,0 +1,161 @@
+//! Keeping the HUD clear of what a panel's bezel or curved glass hides,
+//! see [`Settings::safe_area`](crate::Settings::safe_area).
+//!
+//! Scenes and [nodes](crate::layout::Node) are laid out inside the frame
+//! less the insets, and rounded corners are masked in black above
+//! everything drawn, so what's hidden looks the same in mirrors and
+//! snapshots as on the panel:
+//!
+//! ```toml
+//! [display.safe_area]
+//! top = 24
+//! bottom = "5%"
+//! corner_radius = 40
+//! ```
+
+use std::f32::consts::FRAC_PI_2;
+
+use serde::Deserialize;
+
+use crate::canvas::Style;
+use crate::frame::Frame;
+use crate::layout::Length;
+use crate::widgets::Rect;
+
+/// Segments of each masked corner's arc.
+const ARC_SEGMENTS: usize = 16;
+
+/// The hidden edges of the display, in physical pixels or percentages of
+/// the frame's width or height.
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SafeArea {
+  pub top: Length,
+  pub right: Length,
+  pub bottom: Length,
+  pub left: Length,
+  /// Radius of the display's rounded corners in physical pixels, masked
+  /// unless 0.
+  pub corner_radius: f32,
+}
+
+impl Default for SafeArea {
+  fn default() -> Self {
+    Self {
+      top: Length::Px(0.0),
+      right: Length::Px(0.0),
+      bottom: Length::Px(0.0),
+      left: Length::Px(0.0),
+      corner_radius: 0.0,
+    }
+  }
+}
+
+impl SafeArea {
+  /// The same insets along every edge.
+  pub fn uniform(inset: Length) -> Self {
+    Self {
+      top: inset,
+      right: inset,
+      bottom: inset,
+      left: inset,
+      ..Self::default()
+    }
+  }
+
+  pub fn with_corner_radius(mut self, radius: f32) -> Self {
+    self.corner_radius = radius;
+    self
+  }
+
+  /// What's left visible of a frame of `width` by `height`.
+  pub fn rect(&self, width: f32, height: f32) -> Rect {
+    let left = self.left.resolve(width);
+    let top = self.top.resolve(height);
+    let right = self.right.resolve(width);
+    let bottom = self.bottom.resolve(height);
+    Rect::new(
+      left,
+      top,
+      (width - left - right).max(0.0),
+      (height - top - bottom).max(0.0),
+    )
+  }
+
+  /// Outlines of what the rounded corners hide in a frame of `width` by
+  /// `height`, from the corner along the arc, clockwise from the top left.
+  fn corners(&self, width: f32, height: f32) -> Vec<Vec<[f32; 2]>> {
+    let radius = self.corner_radius.min(width.min(height) / 2.0);
+    if radius <= 0.0 {
+      return Vec::new();
+    }
+    let corners = [[0.0, 0.0], [width, 0.0], [width, height], [0.0, height]];
+    corners
+      .into_iter()
+      .enumerate()
+      .map(|(index, corner)| {
+        let center = corner.map(|x| if x == 0.0 { radius } else { x - radius });
+        // Pointing from the center to the left of the top left corner
+        let start = (index as f32 + 2.0) * FRAC_PI_2;
+        let arc = (0..=ARC_SEGMENTS).map(|segment| {
+          let angle = start + segment as f32 / ARC_SEGMENTS as f32 * FRAC_PI_2;
+          [
+            center[0] + radius * angle.cos(),
+            center[1] + radius * angle.sin(),
+          ]
+        });
+        std::iter::once(corner).chain(arc).collect()
+      })
+      .collect()
+  }
+
+  /// Blacks out what the rounded corners hide, above everything drawn so
+  /// far.
+  pub(crate) fn mask(&self, frame: &mut Frame) {
+    let corners = self.corners(frame.width as f32, frame.height as f32);
+    if corners.is_empty() {
+      return;
+    }
+    frame.next_layer();
+    for corner in corners {
+      frame
+        .canvas
+        .polygon(&corner, Style::fill([0.0, 0.0, 0.0, 1.0]));
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::config::toml_example;
+
+  #[test]
+  fn insets_the_frame_and_masks_its_corners() {
+    let area = SafeArea {
+      top: Length::Px(24.0),
+      bottom: Length::Percent(5.0),
+      ..SafeArea::uniform(Length::Px(10.0))
+    };
+    assert_eq!(area.rect(800.0, 480.0), Rect::new(10.0, 24.0, 780.0, 432.0));
+    assert!(area.corners(800.0, 480.0).is_empty());
+    let corners = area.with_corner_radius(40.0).corners(800.0, 480.0);
+    let ends = |corner: &Vec<[f32; 2]>| {
+      [corner[0], corner[1], *corner.last().unwrap()].map(|point| point.map(f32::round))
+    };
+    assert_eq!(ends(&corners[0]), [[0.0, 0.0], [0.0, 40.0], [40.0, 0.0]]);
+    assert_eq!(
+      ends(&corners[2]),
+      [[800.0, 480.0], [800.0, 440.0], [760.0, 480.0]]
+    );
+  }
+
+  #[test]
+  fn parses_the_module_example() {
+    let example = toml_example(include_str!("safe_area.rs"), "//!");
+    let example: toml::Value = toml::from_str(&example).unwrap();
+    let area: SafeArea = example["display"]["safe_area"].clone().try_into().unwrap();
+    assert_eq!(area.bottom, Length::Percent(5.0));
+    assert_eq!(area.corner_radius, 40.0);
+  }
+}
@@ -0,0 +1,213 @@
+use bytemuck::{Pod, Zeroable};
+use serde::{Deserialize, Serialize};
+use wgpu::util::{BufferInitDescriptor, DeviceExt};
+use wgpu::{
+  BindGroup, BindGroupDescriptor, BindGroupEntry, BindGroupLayoutDescriptor, BindGroupLayoutEntry,
+  BindingType, Buffer, BufferBindingType, BufferUsages, CommandBuffer, CommandEncoderDescriptor,
+  Device, LoadOp, Operations, Queue, RenderPassColorAttachment, RenderPassDescriptor,
+  RenderPipeline, ShaderStages, TextureFormat, TextureView,
+};
+
+use crate::error::WindshieldError;
+use crate::mirror::Mirror;
+use crate::pipeline::{create_shader, PipelineBuilder};
+use crate::stats::FrameStats;
+
+/// Always bundled, unlike the other shaders it can't be replaced from the
+/// shader directory.
+const SHADER: (&str, &str) = ("safety.wgsl", include_str!("shaders/safety.wgsl"));
+
+/// A warning lamp of the safety layer, drawn on top of everything else in
+/// a fixed place whenever it's lit, see [`Telltales`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Telltale {
+  /// Brake system failure or the parking brake still on.
+  Brake,
+  /// Malfunction indicator lamp, lit by the engine control unit.
+  CheckEngine,
+  Coolant,
+  /// Oil pressure.
+  Oil,
+  /// Charging system.
+  Battery,
+}
+
+impl Telltale {
+  /// In the order they are shown, left to right. Must match
+  /// `shaders/safety.wgsl`.
+  pub const ALL: [Self; 5] = [
+    Self::Brake,
+    Self::CheckEngine,
+    Self::Coolant,
+    Self::Oil,
+    Self::Battery,
+  ];
+
+  fn bit(self) -> u8 {
+    1 << self as u8
+  }
+}
+
+/// Which telltales are lit, as far as data sources reported them.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Telltales {
+  known: u8,
+  lit: u8,
+}
+
+impl Telltales {
+  pub fn set(&mut self, telltale: Telltale, lit: bool) {
+    self.known |= telltale.bit();
+    if lit {
+      self.lit |= telltale.bit();
+    } else {
+      self.lit &= !telltale.bit();
+    }
+  }
+
+  pub fn is_lit(&self, telltale: Telltale) -> bool {
+    self.lit & telltale.bit() != 0
+  }
+
+  /// Takes over the telltales `update` has been told about, so a source
+  /// only knowing one of them doesn't turn off the others.
+  pub fn merge(&mut self, update: &Telltales) {
+    self.known |= update.known;
+    self.lit = (self.lit & !update.known) | (update.lit & update.known);
+  }
+}
+
+/// Layout of `Safety` in `shaders/safety.wgsl`, padded to 16 bytes.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, Pod, Zeroable)]
+struct SafetyUniform {
+  size: [f32; 2],
+  mirror: [f32; 2],
+  lit: u32,
+  padding: [u32; 3],
+}
+
+/// Draws lit telltales last, in a pass and command buffer of its own, from
+/// a bundled shader that takes nothing but the screen and which lamps are
+/// lit. Whatever the scene or `on_draw` does, it can't cover, blend away or
+/// break them.
+///
+/// The lamps keep their colors in high contrast mode and aren't warped by
+/// the keystone, only mirrored so they read right in the reflection.
+pub(crate) struct SafetyPass {
+  pipeline: RenderPipeline,
+  uniform: Buffer,
+  bind_group: BindGroup,
+  safety: SafetyUniform,
+}
+
+impl SafetyPass {
+  pub(crate) async fn new(
+    device: &Device,
+    format: TextureFormat,
+    width: u32,
+    height: u32,
+  ) -> Result<Self, WindshieldError> {
+    let (name, source) = SHADER;
+    let shader = create_shader(device, name, source).await?;
+    let layout = device.create_bind_group_layout(&BindGroupLayoutDescriptor {
+      label: Some("Safety"),
+      entries: &[BindGroupLayoutEntry {
+        binding: 0,
+        visibility: ShaderStages::VERTEX,
+        ty: BindingType::Buffer {
+          ty: BufferBindingType::Uniform,
+          has_dynamic_offset: false,
+          min_binding_size: None,
+        },
+        count: None,
+      }],
+    });
+    let pipeline = PipelineBuilder::new("Safety Pipeline", &shader)
+      .bind_group_layout(&layout)
+      .build(device, format);
+    let safety = SafetyUniform {
+      size: [width as f32, height as f32],
+      mirror: Mirror::None.scale(),
+      lit: 0,
+      padding: [0; 3],
+    };
+    let uniform = device.create_buffer_init(&BufferInitDescriptor {
+      label: Some("Safety"),
+      contents: bytemuck::bytes_of(&safety),
+      usage: BufferUsages::UNIFORM | BufferUsages::COPY_DST,
+    });
+    let bind_group = device.create_bind_group(&BindGroupDescriptor {
+      label: Some("Safety"),
+      layout: &layout,
+      entries: &[BindGroupEntry {
+        binding: 0,
+        resource: uniform.as_entire_binding(),
+      }],
+    });
+
+    Ok(Self {
+      pipeline,
+      uniform,
+      bind_group,
+      safety,
+    })
+  }
+
+  pub(crate) fn resize(&mut self, queue: &Queue, width: u32, height: u32) {
+    self.write(queue, |safety| safety.size = [width as f32, height as f32]);
+  }
+
+  pub(crate) fn set_mirror(&mut self, queue: &Queue, mirror: Mirror) {
+    self.write(queue, |safety| safety.mirror = mirror.scale());
+  }
+
+  pub(crate) fn set_telltales(&mut self, queue: &Queue, telltales: Telltales) {
+    self.write(queue, |safety| safety.lit = telltales.lit.into());
+  }
+
+  fn write(&mut self, queue: &Queue, change: impl FnOnce(&mut SafetyUniform)) {
+    let before = self.safety;
+    change(&mut self.safety);
+    if self.safety != before {
+      queue.write_buffer(&self.uniform, 0, bytemuck::bytes_of(&self.safety));
+    }
+  }
+
+  /// Commands drawing the lit telltales onto `target`, to be submitted
+  /// after the rest of the frame. `None` if none are lit.
+  pub(crate) fn encode(
+    &self,
+    device: &Device,
+    target: &TextureView,
+    stats: &mut FrameStats,
+  ) -> Option<CommandBuffer> {
+    if self.safety.lit == 0 {
+      return None;
+    }
+    let mut encoder = device.create_command_encoder(&CommandEncoderDescriptor {
+      label: Some("Safety Encoder"),
+    });
+    {
+      let mut pass = encoder.begin_render_pass(&RenderPassDescriptor {
+        label: Some("Safety Pass"),
+        color_attachments: &[Some(RenderPassColorAttachment {
+          view: target,
+          resolve_target: None,
+          ops: Operations {
+            load: LoadOp::Load,
+            store: true,
+          },
+        })],
+        depth_stencil_attachment: None,
+      });
+      pass.set_pipeline(&self.pipeline);
+      pass.set_bind_group(0, &self.bind_group, &[]);
+      pass.draw(0..6, 0..Telltale::ALL.len() as u32);
+      stats.draw_calls += 1;
+      stats.triangles += 2 * Telltale::ALL.len() as u32;
+    }
+    Some(encoder.finish())
+  }
+}
@@ -0,0 +1,515 @@
+use std::time::Duration;
+
+use serde::Deserialize;
+
+use crate::accessibility::{child_id, AccessibleWidget};
+use crate::anim::{Easing, Tween};
+use crate::frame::Frame;
+use crate::input::{Action, PointerEvent, PointerId, PointerPhase};
+use crate::layout::{Length, Node};
+use crate::widgets::{Rect, Widget};
+
+/// How one page of a [`Scene`] makes way for the next.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Transition {
+  /// Shows the next page right away.
+  Cut,
+  /// Pushes the page out to the side the next one comes in from, like
+  /// turning pages. Forward pages come in from the right.
+  #[default]
+  Slide,
+  /// Fades the page out while the next one fades in.
+  Crossfade,
+}
+
+// Where the ids of accessible widgets in the overlay and viewports start,
+// past those of pages
+const OVERLAY: usize = usize::MAX / 2;
+const VIEWPORTS: usize = OVERLAY + 1;
+
+/// Time a transition takes unless configured otherwise.
+pub const DEFAULT_TRANSITION_TIME: Duration = Duration::from_millis(300);
+
+/// A page being left, see [`Scene::set_page`].
+struct Leaving {
+  page: usize,
+  /// From 0 when the transition starts to 1 when it's done.
+  progress: Tween,
+}
+
+/// A scene of its own drawn into part of another one, like a cluster
+/// next to a HUD on an ultrawide panel, see [`Scene::with_viewport`].
+pub struct Viewport {
+  /// From the top left corner of the scene it's in, percentages of it.
+  pub x: Length,
+  pub y: Length,
+  pub width: Length,
+  pub height: Length,
+  pub scene: Scene,
+  // Where it was drawn last, for pointers
+  placed: Rect,
+}
+
+impl Viewport {
+  pub fn new(
+    x: impl Into<Length>,
+    y: impl Into<Length>,
+    width: impl Into<Length>,
+    height: impl Into<Length>,
+    scene: Scene,
+  ) -> Self {
+    Self {
+      x: x.into(),
+      y: y.into(),
+      width: width.into(),
+      height: height.into(),
+      scene,
+      placed: Rect::default(),
+    }
+  }
+}
+
+/// Pages of widgets showing one at a time, e.g. a speed page, an engine
+/// page and a trip computer, with widgets shown on all of them on top.
+///
+/// [`NextPage`](Action::NextPage) and
+/// [`PreviousPage`](Action::PreviousPage) turn the pages unless a widget
+/// on the current one, like a [`Carousel`](crate::widgets::Carousel), uses
+/// them first. Other actions go to the current page, whose focus is kept
+/// while others are shown.
+///
+/// Scenes can also be split into [viewports](Viewport), each turning its
+/// pages on its own, with their pages and overlay drawn on top. Actions go
+/// to the viewport last touched, the first one until then.
+pub struct Scene {
+  pages: Vec<Node>,
+  /// Drawn above every page and not moved by transitions.
+  overlay: Option<Node>,
+  current: usize,
+  leaving: Option<Leaving>,
+  pub transition: Transition,
+  pub transition_time: Duration,
+  pub easing: Easing,
+  // Page every pressed pointer went down on, so a press keeps going to it
+  // if the pages are turned under the finger
+  pressed: Vec<(PointerId, usize)>,
+  viewports: Vec<Viewport>,
+  // Viewport getting the actions
+  focus: usize,
+  // Viewport every pressed pointer went down in
+  pressed_viewports: Vec<(PointerId, usize)>,
+  // Whether the app's `on_scene` has seen the scene
+  pub(crate) set_up: bool,
+}
+
+impl Default for Scene {
+  fn default() -> Self {
+    Self::new()
+  }
+}
+
+impl Scene {
+  /// A scene without pages, drawing nothing.
+  pub fn new() -> Self {
+    Self {
+      pages: Vec::new(),
+      overlay: None,
+      current: 0,
+      leaving: None,
+      transition: Transition::default(),
+      transition_time: DEFAULT_TRANSITION_TIME,
+      easing: Easing::default(),
+      pressed: Vec::new(),
+      viewports: Vec::new(),
+      focus: 0,
+      pressed_viewports: Vec::new(),
+      set_up: false,
+    }
+  }
+
+  /// A scene of only `root`, for dashboards without pages.
+  pub fn single(root: Node) -> Self {
+    Self::new().with_page(root)
+  }
+
+  /// Adds a page after the others, found by its root's name.
+  pub fn with_page(mut self, page: Node) -> Self {
+    self.pages.push(page);
+    self
+  }
+
+  pub fn with_overlay(mut self, overlay: Node) -> Self {
+    self.overlay = Some(overlay);
+    self
+  }
+
+  pub fn with_transition(mut self, transition: Transition, time: Duration) -> Self {
+    self.transition = transition;
+    self.transition_time = time;
+    self
+  }
+
+  pub fn with_easing(mut self, easing: Easing) -> Self {
+    self.easing = easing;
+    self
+  }
+
+  /// Adds a viewport drawn above the others.
+  pub fn with_viewport(mut self, viewport: Viewport) -> Self {
+    self.viewports.push(viewport);
+    self
+  }
+
+  pub fn viewports_mut(&mut self) -> &mut [Viewport] {
+    &mut self.viewports
+  }
+
+  /// Index of the page that is or is about to be shown.
+  pub fn page(&self) -> usize {
+    self.current
+  }
+
+  pub fn page_count(&self) -> usize {
+    self.pages.len()
+  }
+
+  /// Index of the page whose root is called `name`.
+  pub fn find_page(&self, name: &str) -> Option<usize> {
+    self
+      .pages
+      .iter()
+      .position(|page| page.name.as_deref() == Some(name))
+  }
+
+  /// Turns to the page at `index`, clamped to the last one.
+  pub fn set_page(&mut self, index: usize) {
+    let index = index.min(self.pages.len().saturating_sub(1));
+    if index == self.current {
+      return;
+    }
+    self.leaving = (self.transition != Transition::Cut).then_some(Leaving {
+      page: self.current,
+      progress: Tween::new(0.0, 1.0, self.transition_time).with_easing(self.easing),
+    });
+    self.current = index;
+  }
+
+  /// Shows the page at `index` right away, without a transition.
+  pub fn show_page(&mut self, index: usize) {
+    self.set_page(index);
+    self.leaving = None;
+  }
+
+  pub fn page_mut(&mut self, index: usize) -> Option<&mut Node> {
+    self.pages.get_mut(index)
+  }
+
+  pub fn overlay_mut(&mut self) -> Option<&mut Node> {
+    self.overlay.as_mut()
+  }
+
+  /// The node called `name`, in the overlay first, then the pages in
+  /// order and then the viewports'.
+  pub fn find_mut(&mut self, name: &str) -> Option<&mut Node> {
+    let Self {
+      overlay,
+      pages,
+      viewports,
+      ..
+    } = self;
+    overlay
+      .iter_mut()
+      .chain(pages)
+      .find_map(|node| node.find_mut(name))
+      .or_else(|| {
+        viewports
+          .iter_mut()
+          .find_map(|viewport| viewport.scene.find_mut(name))
+      })
+  }
+
+  /// The widget called `name` if it is a `W`, in the overlay first, then
+  /// the pages in order and then the viewports'.
+  pub fn get_mut<W: Widget>(&mut self, name: &str) -> Option<&mut W> {
+    let Self {
+      overlay,
+      pages,
+      viewports,
+      ..
+    } = self;
+    overlay
+      .iter_mut()
+      .chain(pages)
+      .find_map(|node| node.get_mut(name))
+      .or_else(|| {
+        viewports
+          .iter_mut()
+          .find_map(|viewport| viewport.scene.get_mut(name))
+      })
+  }
+
+  /// Calls `f` with every widget that is a `W`, in the overlay first, then
+  /// the pages in order and then the viewports'.
+  pub fn for_each_mut<W: Widget>(&mut self, mut f: impl FnMut(&mut W)) {
+    self.visit_mut(&mut f);
+  }
+
+  fn visit_mut<W: Widget>(&mut self, f: &mut impl FnMut(&mut W)) {
+    for node in self.overlay.iter_mut().chain(&mut self.pages) {
+      node.for_each_mut(&mut *f);
+    }
+    for viewport in &mut self.viewports {
+      viewport.scene.visit_mut(f);
+    }
+  }
+
+  /// Draws the viewports, then the current page, the one it is turning
+  /// from while the transition runs, and the overlay on top. Transitions
+  /// are cut short while [`Frame::reduce_motion`] is set.
+  pub fn draw(&mut self, frame: &mut Frame) {
+    self.draw_in(frame, frame.safe_area);
+  }
+
+  fn draw_in(&mut self, frame: &mut Frame, rect: Rect) {
+    for viewport in &mut self.viewports {
+      viewport.placed = Rect::new(
+        rect.x + viewport.x.resolve(rect.width),
+        rect.y + viewport.y.resolve(rect.height),
+        viewport.width.resolve(rect.width),
+        viewport.height.resolve(rect.height),
+      );
+      // Within whatever this scene is clipped to
+      let clip = frame.clip();
+      let inside = clip.map_or(viewport.placed, |clip| clip.intersect(&viewport.placed));
+      frame.set_clip(Some(inside));
+      viewport.scene.draw_in(frame, viewport.placed);
+      frame.set_clip(clip);
+    }
+    if let Some(leaving) = &mut self.leaving {
+      leaving.progress.update(frame.delta);
+    }
+    let leaving = self
+      .leaving
+      .take()
+      .filter(|leaving| !leaving.progress.is_finished() && !frame.reduce_motion);
+
+    match (&leaving, self.transition) {
+      (Some(leaving), Transition::Slide) => {
+        let eased = leaving.progress.value();
+        // Forward pages come in from the right and push the old one left,
+        // the other way around when reading from right to left
+        let mut side = if self.current > leaving.page {
+          1.0
+        } else {
+          -1.0
+        };
+        if frame.right_to_left {
+          side = -side;
+        }
+        let shifted = |by: f32| Rect {
+          x: rect.x + by * rect.width,
+          ..rect
+        };
+        self.pages[leaving.page].draw_in(frame, shifted(-side * eased));
+        self.pages[self.current].draw_in(frame, shifted(side * (1.0 - eased)));
+      }
+      (Some(leaving), Transition::Crossfade) => {
+        let eased = leaving.progress.value();
+        for (page, opacity) in [(leaving.page, 1.0 - eased), (self.current, eased)] {
+          frame.canvas.set_opacity(opacity);
+          frame.text.set_opacity(opacity);
+          self.pages[page].draw_in(frame, rect);
+        }
+        frame.canvas.set_opacity(1.0);
+        frame.text.set_opacity(1.0);
+      }
+      _ => {
+        if let Some(page) = self.pages.get_mut(self.current) {
+          page.draw_in(frame, rect);
+        }
+      }
+    }
+    if leaving.is_some() {
+      frame.animate();
+    }
+    self.leaving = leaving;
+
+    if let Some(overlay) = &mut self.overlay {
+      overlay.draw_in(frame, rect);
+    }
+  }
+
+  /// Whether a modal widget in the overlay or on the current page takes
+  /// all input.
+  fn modal(&self) -> bool {
+    self.overlay.as_ref().is_some_and(Node::modal)
+      || self.pages.get(self.current).is_some_and(Node::modal)
+  }
+
+  /// Passes pointer input on to the overlay and then to the current page,
+  /// see [`Node::pointer`].
+  ///
+  /// A [modal](Widget::modal) widget on the page keeps the overlay from
+  /// getting anything, like the widgets around it. What the page doesn't
+  /// use goes to the viewport the pointer is in, which gets focused.
+  pub fn pointer(&mut self, event: &PointerEvent) -> bool {
+    let page_modal = self.pages.get(self.current).is_some_and(Node::modal);
+    if let Some(overlay) = self.overlay.as_mut().filter(|_| !page_modal) {
+      if overlay.pointer(event) || overlay.modal() {
+        return true;
+      }
+    }
+    let index = self.pressed.iter().position(|(id, _)| *id == event.id);
+    let page = match (event.phase, index) {
+      (PointerPhase::Down, _) => {
+        self.pressed.push((event.id, self.current));
+        self.current
+      }
+      (PointerPhase::Up | PointerPhase::Cancel, Some(index)) => self.pressed.remove(index).1,
+      (_, Some(index)) => self.pressed[index].1,
+      (_, None) => self.current,
+    };
+    let used = self
+      .pages
+      .get_mut(page)
+      .is_some_and(|page| page.pointer(event));
+    if used || self.modal() {
+      return used;
+    }
+
+    // Presses stay with the viewport they went down in, later ones are on top
+    let under = |viewports: &[Viewport]| {
+      viewports
+        .iter()
+        .rposition(|viewport| viewport.placed.contains(event.position))
+    };
+    let index = self
+      .pressed_viewports
+      .iter()
+      .position(|(id, _)| *id == event.id);
+    let viewport = match (event.phase, index) {
+      (PointerPhase::Down, _) => {
+        let Some(viewport) = under(&self.viewports) else {
+          return false;
+        };
+        self.pressed_viewports.push((event.id, viewport));
+        self.focus = viewport;
+        viewport
+      }
+      (PointerPhase::Up | PointerPhase::Cancel, Some(index)) => {
+        self.pressed_viewports.remove(index).1
+      }
+      (_, Some(index)) => self.pressed_viewports[index].1,
+      (_, None) => match under(&self.viewports) {
+        Some(viewport) => viewport,
+        None => return false,
+      },
+    };
+    self
+      .viewports
+      .get_mut(viewport)
+      .is_some_and(|viewport| viewport.scene.pointer(event))
+  }
+
+  /// The widgets shown that screen readers are told about, see
+  /// [`Node::accessible`]: those of the current page, the overlay and the
+  /// viewports. Only the overlay's have the focus while it shows a
+  /// [modal](Widget::modal) widget.
+  pub fn accessible(&self) -> Vec<AccessibleWidget> {
+    self.accessible_in(0)
+  }
+
+  fn accessible_in(&self, seed: u64) -> Vec<AccessibleWidget> {
+    let mut widgets = self.pages.get(self.current).map_or(Vec::new(), |page| {
+      page.accessible(child_id(seed, self.current))
+    });
+    if let Some(overlay) = &self.overlay {
+      if overlay.modal() {
+        for widget in &mut widgets {
+          widget.focused = false;
+        }
+      }
+      widgets.extend(overlay.accessible(child_id(seed, OVERLAY)));
+    }
+    for (index, viewport) in self.viewports.iter().enumerate() {
+      widgets.extend(
+        viewport
+          .scene
+          .accessible_in(child_id(seed, VIEWPORTS + index)),
+      );
+    }
+    widgets
+  }
+
+  /// Moves the focus to the widget with `id` among those listed by
+  /// [`accessible`](Self::accessible), returning whether it's there and
+  /// can be focused.
+  pub fn focus_accessible(&mut self, id: u64) -> bool {
+    self.focus_accessible_in(0, id)
+  }
+
+  fn focus_accessible_in(&mut self, seed: u64, id: u64) -> bool {
+    let current = self.current;
+    if let Some(page) = self.pages.get_mut(current) {
+      if page.focus_accessible(child_id(seed, current), id) {
+        return true;
+      }
+    }
+    if let Some(overlay) = &mut self.overlay {
+      if overlay.focus_accessible(child_id(seed, OVERLAY), id) {
+        return true;
+      }
+    }
+    for (index, viewport) in self.viewports.iter_mut().enumerate() {
+      if viewport
+        .scene
+        .focus_accessible_in(child_id(seed, VIEWPORTS + index), id)
+      {
+        self.focus = index;
+        return true;
+      }
+    }
+    false
+  }
+
+  /// Passes a navigation action on to the current page, turning the pages
+  /// if nothing on it used [`NextPage`](Action::NextPage) or
+  /// [`PreviousPage`](Action::PreviousPage). Returns whether the action did
+  /// anything.
+  ///
+  /// While a [modal](Widget::modal) widget is shown in the overlay or on
+  /// the page only it gets the actions, and the pages stay where they are.
+  /// Actions nothing there used go on to the focused viewport.
+  pub fn action(&mut self, action: Action) -> bool {
+    if let Some(overlay) = self.overlay.as_mut().filter(|overlay| overlay.modal()) {
+      overlay.action(action);
+      return true;
+    }
+    if let Some(page) = self.pages.get_mut(self.current).filter(|page| page.modal()) {
+      page.action(action);
+      return true;
+    }
+    let used = self
+      .pages
+      .get_mut(self.current)
+      .is_some_and(|page| page.action(action));
+    if used {
+      return true;
+    }
+    let used = self
+      .viewports
+      .get_mut(self.focus)
+      .is_some_and(|viewport| viewport.scene.action(action));
+    if used {
+      return true;
+    }
+    let page = self.current;
+    match action {
+      Action::NextPage if page + 1 < self.pages.len() => self.set_page(page + 1),
+      Action::PreviousPage if page > 0 => self.set_page(page - 1),
+      _ => return false,
+    }
+    true
+  }
+}
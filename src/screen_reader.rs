@@ -0,0 +1,269 @@
+//! Exposing the widgets shown to screen readers through AccessKit, see
+//! [`accessibility`](crate::accessibility).
+//!
+//! The tree is the window with a node for every
+//! [accessible widget](crate::accessibility::AccessibleWidget) and their
+//! parts below them. It's sent whenever it changes while a screen reader
+//! listens. Focusing a widget moves the scene's focus to it, and its
+//! default action, increments and decrements become
+//! [`Action::Activate`], [`Action::Next`] and [`Action::Previous`].
+
+use std::num::NonZeroU128;
+use std::sync::{Arc, Mutex};
+
+use accesskit::kurbo;
+use accesskit::{
+  Action as Request, ActionHandler, ActionRequest, CheckedState, DefaultActionVerb, NodeId,
+  Role as NodeRole, Tree, TreeUpdate,
+};
+use accesskit_winit::Adapter;
+use winit::event::WindowEvent;
+use winit::window::Window;
+
+use crate::accessibility::{child_id, Accessible, AccessibleWidget, Role};
+use crate::input::Action;
+use crate::scene::Scene;
+use crate::wake::Waker;
+
+/// The window, around every widget.
+const ROOT: NodeId = NodeId(NonZeroU128::MIN);
+
+/// What the tree is built from, shared with the adapter, which builds the
+/// first one once a screen reader starts listening.
+#[derive(Default)]
+struct Shown {
+  widgets: Vec<AccessibleWidget>,
+  window_focused: bool,
+}
+
+/// Collects what screen readers ask for, from any thread.
+struct Requests {
+  requests: Arc<Mutex<Vec<ActionRequest>>>,
+  waker: Waker,
+}
+
+impl ActionHandler for Requests {
+  fn do_action(&self, request: ActionRequest) {
+    self.requests.lock().unwrap().push(request);
+    self.waker.wake();
+  }
+}
+
+pub(crate) struct ScreenReader {
+  adapter: Adapter,
+  title: String,
+  shown: Arc<Mutex<Shown>>,
+  requests: Arc<Mutex<Vec<ActionRequest>>>,
+}
+
+impl ScreenReader {
+  /// Exposes `window`, whose title is `title`, waking the event loop with
+  /// `waker` when a screen reader asks for something.
+  pub(crate) fn new(window: &Window, title: &str, waker: Waker) -> Self {
+    let shown = Arc::new(Mutex::new(Shown::default()));
+    let requests = Arc::new(Mutex::new(Vec::new()));
+    let source = {
+      let shown = shown.clone();
+      let title = title.to_string();
+      move || tree(&title, &shown.lock().unwrap())
+    };
+    let handler = Requests {
+      requests: requests.clone(),
+      waker,
+    };
+    let adapter = Adapter::with_action_handler(window, source, Box::new(handler));
+    Self {
+      adapter,
+      title: title.to_string(),
+      shown,
+      requests,
+    }
+  }
+
+  /// Passes `event` on to the adapter, keeping the bounds of the window
+  /// and whether it has the focus up to date.
+  pub(crate) fn on_event(&self, window: &Window, event: &WindowEvent) {
+    if let WindowEvent::Focused(focused) = event {
+      self.shown.lock().unwrap().window_focused = *focused;
+      self.send();
+    }
+    // Never consumes the event
+    let _ = self.adapter.on_event(window, event);
+  }
+
+  /// Tells screen readers what `scene` shows, if it changed.
+  pub(crate) fn update(&self, scene: &Scene) {
+    let widgets = scene.accessible();
+    {
+      let mut shown = self.shown.lock().unwrap();
+      if shown.widgets == widgets {
+        return;
+      }
+      shown.widgets = widgets;
+    }
+    self.send();
+  }
+
+  fn send(&self) {
+    self
+      .adapter
+      .update_if_active(|| tree(&self.title, &self.shown.lock().unwrap()));
+  }
+
+  /// Does what screen readers asked for since the last call in `scene`,
+  /// returning the actions to handle like any other.
+  pub(crate) fn take_actions(&self, scene: &mut Scene) -> Vec<Action> {
+    let requests = std::mem::take(&mut *self.requests.lock().unwrap());
+    let mut actions = Vec::new();
+    for request in requests {
+      let Some((widget, selected)) = self.widget_of(request.target) else {
+        continue;
+      };
+      // Parts, like the items of a menu, are focused through their widget
+      if !scene.focus_accessible(widget) {
+        continue;
+      }
+      let action = match request.action {
+        // Only the chosen part is activated by its widget
+        Request::Default if selected => Action::Activate,
+        Request::Increment => Action::Next,
+        Request::Decrement => Action::Previous,
+        _ => continue,
+      };
+      actions.push(action);
+    }
+    actions
+  }
+
+  /// The widget `node` is or is a part of, and whether it's the chosen
+  /// part if it's one.
+  fn widget_of(&self, node: NodeId) -> Option<(u64, bool)> {
+    let shown = self.shown.lock().unwrap();
+    shown.widgets.iter().find_map(|widget| {
+      if node_id(widget.id) == node {
+        return Some((widget.id, true));
+      }
+      let part = widget
+        .accessible
+        .children
+        .iter()
+        .enumerate()
+        .find(|(index, _)| node_id(child_id(widget.id, *index)) == node);
+      part.map(|(_, part)| (widget.id, part.selected))
+    })
+  }
+}
+
+/// The AccessKit id of a widget or part, clear of [`ROOT`].
+fn node_id(id: u64) -> NodeId {
+  NodeId(NonZeroU128::MIN.saturating_add(id as u128 + 1))
+}
+
+/// The whole tree, focused on the chosen part of the focused widget, like
+/// the highlighted item of a menu, or on the widget itself.
+fn tree(title: &str, shown: &Shown) -> TreeUpdate {
+  let mut nodes = Vec::new();
+  let mut children = Vec::new();
+  let mut focus = None;
+  for widget in &shown.widgets {
+    let id = node_id(widget.id);
+    children.push(id);
+    if widget.focused && focus.is_none() {
+      let chosen = widget
+        .accessible
+        .children
+        .iter()
+        .position(|part| part.selected);
+      focus = Some(chosen.map_or(id, |index| node_id(child_id(widget.id, index))));
+    }
+    let [x, y] = [widget.rect.x as f64, widget.rect.y as f64];
+    let bounds = kurbo::Rect::new(
+      x,
+      y,
+      x + widget.rect.width as f64,
+      y + widget.rect.height as f64,
+    );
+    add_node(
+      &mut nodes,
+      widget.id,
+      &widget.accessible,
+      Some(bounds),
+      widget.focusable,
+    );
+  }
+  let root = accesskit::Node {
+    role: NodeRole::Window,
+    name: Some(title.into()),
+    children,
+    ..Default::default()
+  };
+  nodes.push((ROOT, Arc::new(root)));
+  TreeUpdate {
+    nodes,
+    tree: Some(Tree::new(ROOT)),
+    focus: shown.window_focused.then_some(focus.unwrap_or(ROOT)),
+  }
+}
+
+/// Adds `accessible` and its parts to `nodes`.
+fn add_node(
+  nodes: &mut Vec<(NodeId, Arc<accesskit::Node>)>,
+  id: u64,
+  accessible: &Accessible,
+  bounds: Option<kurbo::Rect>,
+  focusable: bool,
+) {
+  let children = (0..accessible.children.len())
+    .map(|index| child_id(id, index))
+    .collect::<Vec<_>>();
+  for (part, child) in accessible.children.iter().zip(&children) {
+    // Parts are focused in place of their widget
+    add_node(nodes, *child, part, None, focusable);
+  }
+  let checked = |on| match on {
+    true => CheckedState::True,
+    false => CheckedState::False,
+  };
+  let mut node = accesskit::Node {
+    role: role(accessible.role),
+    name: (!accessible.name.is_empty()).then(|| accessible.name.as_str().into()),
+    value: accessible.value.as_deref().map(Into::into),
+    bounds,
+    children: children.into_iter().map(node_id).collect(),
+    focusable,
+    ..Default::default()
+  };
+  match accessible.role {
+    Role::Switch => node.checked_state = Some(checked(accessible.selected)),
+    Role::RadioButton => {
+      node.checked_state = Some(checked(accessible.selected));
+      node.selected = Some(accessible.selected);
+    }
+    Role::MenuItem => node.selected = Some(accessible.selected),
+    Role::Slider => node.actions = Request::Increment | Request::Decrement,
+    _ => {}
+  }
+  if matches!(
+    accessible.role,
+    Role::Button | Role::Switch | Role::RadioButton | Role::MenuItem
+  ) {
+    node.default_action_verb = Some(DefaultActionVerb::Click);
+  }
+  nodes.push((node_id(id), Arc::new(node)));
+}
+
+fn role(role: Role) -> NodeRole {
+  match role {
+    Role::Text => NodeRole::StaticText,
+    Role::Meter => NodeRole::Meter,
+    Role::Button => NodeRole::Button,
+    Role::Switch => NodeRole::Switch,
+    Role::Slider => NodeRole::Slider,
+    Role::RadioGroup => NodeRole::RadioGroup,
+    Role::RadioButton => NodeRole::RadioButton,
+    Role::Menu => NodeRole::Menu,
+    Role::MenuItem => NodeRole::MenuItem,
+    Role::Dialog => NodeRole::Dialog,
+    Role::SearchBox => NodeRole::SearchBox,
+  }
+}
@@ -0,0 +1,50 @@
+use winit::window::Window;
+
+/// Keeps the screen saver and power management from blanking the screen
+/// for as long as `window` exists, see
+/// [`Settings::keep_awake`](crate::Settings::keep_awake).
+#[cfg(any(
+  target_os = "linux",
+  target_os = "dragonfly",
+  target_os = "freebsd",
+  target_os = "netbsd",
+  target_os = "openbsd"
+))]
+pub(crate) fn inhibit(window: &Window) {
+  use std::process::Command;
+
+  use winit::platform::unix::WindowExtUnix;
+
+  let Some(id) = window.xlib_window() else {
+    tracing::warn!("keeping the screen awake is only supported on X11");
+    return;
+  };
+  // Resumes the screen saver by itself once the window is gone, even if
+  // the HUD crashed
+  match Command::new("xdg-screensaver")
+    .arg("suspend")
+    .arg(format!("0x{:x}", id))
+    .status()
+  {
+    Ok(status) if status.success() => {}
+    Ok(status) => tracing::warn!(
+      "unable to keep the screen awake: xdg-screensaver {}",
+      status
+    ),
+    Err(err) => tracing::warn!(
+      "unable to keep the screen awake, is xdg-utils installed? {}",
+      err
+    ),
+  }
+}
+
+#[cfg(not(any(
+  target_os = "linux",
+  target_os = "dragonfly",
+  target_os = "freebsd",
+  target_os = "netbsd",
+  target_os = "openbsd"
+)))]
+pub(crate) fn inhibit(_window: &Window) {
+  tracing::warn!("keeping the screen awake is only supported on X11");
+}
@@ -0,0 +1,291 @@
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::time::Duration;
+
+use wgpu::{Color, CompositeAlphaMode, PowerPreference, PresentMode};
+use winit::dpi::Size;
+
+use crate::accessibility::Accessibility;
+use crate::burn_in::BurnIn;
+use crate::checksum::ChecksumRegion;
+use crate::config::Fullscreen;
+use crate::data::arbiter::{Origin, DEFAULT_FALLBACK_AFTER};
+use crate::data::gps::GpsDevice;
+use crate::data::process::Processor;
+use crate::data::{Field, DEFAULT_STALE_AFTER};
+use crate::input::Bindings;
+use crate::layer_shell::LayerShell;
+use crate::logging::LogBuffer;
+use crate::mirror::Mirror;
+use crate::rules::Rule;
+use crate::safe_area::SafeArea;
+use crate::theme::{ColorVision, Themes};
+use crate::warp::Keystone;
+
+/// Startup options for a [`WindshieldApp`](crate::WindshieldApp).
+#[derive(Clone, Debug)]
+pub struct Settings {
+  pub title: String,
+  /// Initial inner size of the window, platform default if unset.
+  pub size: Option<Size>,
+  /// Whether to prefer an integrated or a discrete GPU.
+  pub power_preference: PowerPreference,
+  /// Borderless, always-on-top, click-through window with a transparent
+  /// background, for using the renderer as a desktop HUD overlay.
+  /// Implies `transparent`.
+  pub overlay: bool,
+  /// Ask the windowing system for a window with an alpha channel.
+  pub transparent: bool,
+  /// Starts in fullscreen, windowed if unset. `F11` toggles it while
+  /// running, borderless unless set to exclusive here.
+  pub fullscreen: Option<Fullscreen>,
+  /// Hides the mouse cursor over the window, for touchscreens and
+  /// displays nobody points at.
+  pub hide_cursor: bool,
+  /// Shows the HUD in a layer surface of the Wayland compositor instead
+  /// of a window, composited above other applications, see
+  /// [`layer_shell`](crate::layer_shell). With the `layer-shell` feature
+  /// only, a window opens without it or off Wayland.
+  pub layer_shell: Option<LayerShell>,
+  /// Keeps the window above all others, implied by `overlay`.
+  pub always_on_top: bool,
+  /// Keeps the screen from blanking while the window is open. Only works
+  /// on X11, through `xdg-screensaver`.
+  pub keep_awake: bool,
+  /// How the surface is composited with what's behind the window.
+  /// `None` picks a blending mode for transparent windows and lets the
+  /// platform decide otherwise.
+  pub alpha_mode: Option<CompositeAlphaMode>,
+  /// How frames are queued for the screen. `Mailbox` and `Immediate` cut
+  /// latency at the cost of drawing frames that are never shown, or of
+  /// tearing. Falls back to `Fifo` if the surface doesn't support it.
+  pub present_mode: PresentMode,
+  /// Draws at most this many frames per second, e.g. 30 to save power
+  /// when running off a car battery. Unlimited if unset.
+  pub max_fps: Option<f32>,
+  /// Only draws a frame when something changed, like telemetry, input or
+  /// an animation, and otherwise once per heartbeat, e.g. every second to
+  /// keep clocks current. Draws continuously if unset. What `on_draw`
+  /// animates has to [ask for frames](crate::Frame::animate) then.
+  pub heartbeat: Option<Duration>,
+  /// Makes the same frames come out of the same input, byte for byte, for
+  /// certification tests and golden images. Time advances by
+  /// [`DETERMINISTIC_STEP`](crate::clock::DETERMINISTIC_STEP) every frame
+  /// whenever it's drawn, the automatic theme takes the time of day from
+  /// that rather than the wall clock, and data sources aren't read.
+  pub deterministic: bool,
+  /// Background color in straight (not premultiplied) alpha. `None` clears
+  /// transparent windows to fully transparent and others to the background
+  /// of the theme.
+  pub clear_color: Option<Color>,
+  /// Directory crash reports are written to on panic or GPU loss. No
+  /// reports are written if unset.
+  pub crash_dir: Option<PathBuf>,
+  /// Recent log events to include in crash reports.
+  pub log_buffer: Option<LogBuffer>,
+  /// Directory [`Action::Snapshot`](crate::input::Action::Snapshot) saves
+  /// snapshots to, the working directory if unset.
+  pub snapshot_dir: Option<PathBuf>,
+  /// [`Snapshot`](crate::snapshot::Snapshot) file to start from instead of
+  /// the configured page, values and display settings.
+  pub restore: Option<PathBuf>,
+  /// File the window's input is recorded to along with the frames it came
+  /// in on, see [`replay`](crate::replay).
+  pub record: Option<PathBuf>,
+  /// Recording to play back into the window as if it came in again, see
+  /// [`replay`](crate::replay).
+  pub replay: Option<PathBuf>,
+  /// Dashboard [`Config`](crate::config::Config) file loaded at startup.
+  /// Its title and background take precedence over the ones set here. A
+  /// URL relative to the page on the web.
+  pub config: Option<PathBuf>,
+  /// Directory to load shaders from instead of the bundled ones, by their
+  /// file name. Shaders missing there fall back to the bundled version.
+  pub shader_dir: Option<PathBuf>,
+  /// Draws every pipeline once before the first frame, so drivers
+  /// compiling shaders on first use don't make the first frames with text
+  /// or gauges stutter. Adds to the time it takes to start, see
+  /// [`prewarm`](crate::prewarm).
+  pub prewarm: bool,
+  /// File listing what was drawn, in which fonts and sizes, to only
+  /// prewarm that. Added to while running, everything is prewarmed until
+  /// there is one.
+  pub prewarm_list: Option<PathBuf>,
+  /// How long startup may take until the first frame is presented, e.g.
+  /// what a vehicle's requirements allow from boot. Exceeding it is
+  /// logged as a warning naming the slowest phase, see
+  /// [`StartupReport`](crate::startup::StartupReport).
+  pub startup_budget: Option<Duration>,
+  /// Watch the config file, its theme file and the shader directory and
+  /// apply changes while running. Changes only a new window can apply, like
+  /// to `transparent`, are logged.
+  pub hot_reload: bool,
+  /// Serial port of an ELM327 OBD-II adapter to read vehicle data from.
+  pub obd: Option<PathBuf>,
+  /// GPS receiver to read ground speed and heading from.
+  pub gps: Option<GpsDevice>,
+  /// Flips the image for screens read as a reflection in the windshield.
+  /// `M` toggles it while running.
+  pub mirror: Mirror,
+  /// Draws everything gray and brighter on black, since reflections are
+  /// faint and lose most color. `H` toggles it while running.
+  pub high_contrast: bool,
+  /// Cuts between pages instead of sliding or fading, and holds still what
+  /// only moves to look nice, like scrolling text and blinking carets, for
+  /// drivers who'd rather not see it move.
+  pub reduce_motion: bool,
+  /// Lays the HUD out from right to left, for Arabic and Hebrew: rows,
+  /// anchors and offsets are mirrored, as are icons pointing along the
+  /// reading direction, unless a node opts out with
+  /// [`Node::mirror`](crate::layout::Node::mirror).
+  pub right_to_left: bool,
+  /// Corrects the distortion of the windshield. `K` starts calibrating it
+  /// while running: `Tab` picks a corner and the arrow keys move it.
+  pub keystone: Keystone,
+  /// Parts of the frame to checksum every frame, for external safety
+  /// monitors, see [`FrameStats::checksums`](crate::stats::FrameStats::checksums).
+  /// Rendering goes through an extra texture while there are any.
+  pub checksum_regions: Vec<ChecksumRegion>,
+  /// Conditions over telemetry showing widgets, lighting telltales and
+  /// asking for chimes and notifications, see [`rules`](crate::rules).
+  pub rules: Vec<Rule>,
+  /// Processors the values of a field go through before widgets and
+  /// rules see them, like a moving average for a noisy sensor, see
+  /// [`process`](crate::data::process).
+  pub signals: HashMap<Field, Vec<Processor>>,
+  /// Data sources a field is taken from, the first still reporting it
+  /// first, like the vehicle's own speed with GPS as a fallback, see
+  /// [`arbiter`](crate::data::arbiter). Fields without any take the latest
+  /// value of any source.
+  pub priorities: HashMap<Field, Vec<Origin>>,
+  /// How long a source in [`priorities`](Self::priorities) may go without
+  /// reporting a field before the next one takes over.
+  pub fallback_after: Duration,
+  /// How long a telemetry field may go without an update before widgets
+  /// showing it are dimmed and marked with a warning, so frozen values
+  /// aren't trusted. Never if unset.
+  pub stale_after: Option<Duration>,
+  /// Scales text and sizes given in pixels, on top of the display's scale
+  /// factor, so a layout made for one display is as legible on a small
+  /// dense panel as on a large cluster. Nodes can have a scale of their
+  /// own, see [`Node::scale`](crate::layout::Node::scale).
+  pub ui_scale: f32,
+  /// Edges and rounded corners of the display hidden behind its bezel or
+  /// curved glass, which the layout keeps clear of, see
+  /// [`safe_area`](crate::safe_area).
+  pub safe_area: SafeArea,
+  /// How long the ignition has to stay off before the display turns off,
+  /// so it stays on while cranking or restarting the engine, see
+  /// [`Telemetry::ignition`](crate::data::Telemetry::ignition).
+  pub ignition_off_delay: Duration,
+  /// Enlarges text, forces high contrast and reduces motion while enabled,
+  /// see [`accessibility`](crate::accessibility).
+  pub accessibility: Accessibility,
+  /// Keeps an OLED panel from burning in what the HUD shows for long, off
+  /// if unset, see [`burn_in`](crate::burn_in).
+  pub burn_in: Option<BurnIn>,
+  /// File every frame shown is mirrored into, like `/dev/shm/windshield`
+  /// for a recorder to map, see [`output`](crate::output) for its layout.
+  /// Rendering goes through an extra texture while set.
+  pub frame_output: Option<PathBuf>,
+  /// Day and night palettes widgets take their colors from. `N` cycles
+  /// through automatic, day and night while running.
+  pub themes: Themes,
+  /// Recolors every frame to how it looks with a kind of color blindness,
+  /// for designers checking that alerts stay apart, see
+  /// [`Themes::color_vision`]. `Ctrl+Shift+C` cycles through the kinds
+  /// while running. Rendering goes through an extra texture while set.
+  pub simulate_color_vision: ColorVision,
+  /// Linux IIO ambient light sensor telling day from night, like
+  /// `/sys/bus/iio/devices/iio:device0`.
+  pub light_sensor: Option<PathBuf>,
+  /// Linux backlight stepped by the brightness actions, like
+  /// `/sys/class/backlight/rpi_backlight`.
+  pub backlight: Option<PathBuf>,
+  /// Keys, mouse buttons and taps doing actions in the window, the
+  /// defaults unless changed, see [`Bindings`].
+  pub bindings: Bindings,
+  /// File mapping rotary encoders and buttons to actions, see
+  /// [`InputMap`](crate::input_map::InputMap). Read with the `input-evdev`
+  /// feature only.
+  pub input_map: Option<PathBuf>,
+  /// Canvas of the page to draw in, a new one is appended to the body if
+  /// unset.
+  #[cfg(target_arch = "wasm32")]
+  pub canvas: Option<web_sys::HtmlCanvasElement>,
+}
+
+impl Default for Settings {
+  fn default() -> Self {
+    Self {
+      title: "windshield-rs".to_string(),
+      size: None,
+      power_preference: PowerPreference::default(),
+      overlay: false,
+      transparent: false,
+      deterministic: false,
+      fullscreen: None,
+      hide_cursor: false,
+      layer_shell: None,
+      always_on_top: false,
+      keep_awake: false,
+      alpha_mode: None,
+      present_mode: PresentMode::Fifo,
+      max_fps: None,
+      heartbeat: None,
+      clear_color: None,
+      crash_dir: None,
+      log_buffer: None,
+      snapshot_dir: None,
+      restore: None,
+      record: None,
+      replay: None,
+      config: None,
+      shader_dir: None,
+      prewarm: false,
+      prewarm_list: None,
+      startup_budget: None,
+      hot_reload: false,
+      obd: None,
+      gps: None,
+      mirror: Mirror::None,
+      high_contrast: false,
+      reduce_motion: false,
+      right_to_left: false,
+      keystone: Keystone::default(),
+      checksum_regions: Vec::new(),
+      rules: Vec::new(),
+      signals: HashMap::new(),
+      priorities: HashMap::new(),
+      fallback_after: DEFAULT_FALLBACK_AFTER,
+      stale_after: Some(DEFAULT_STALE_AFTER),
+      ui_scale: 1.0,
+      safe_area: SafeArea::default(),
+      burn_in: None,
+      accessibility: Accessibility::default(),
+      ignition_off_delay: Duration::ZERO,
+      frame_output: None,
+      themes: Themes::default(),
+      simulate_color_vision: ColorVision::Normal,
+      light_sensor: None,
+      backlight: None,
+      bindings: Bindings::default(),
+      input_map: None,
+      #[cfg(target_arch = "wasm32")]
+      canvas: None,
+    }
+  }
+}
+
+impl Settings {
+  pub(crate) fn is_transparent(&self) -> bool {
+    self.overlay || self.transparent
+  }
+
+  /// The background if it doesn't follow the theme.
+  pub(crate) fn clear_color(&self) -> Option<Color> {
+    self
+      .clear_color
+      .or_else(|| self.is_transparent().then_some(Color::TRANSPARENT))
+  }
+}
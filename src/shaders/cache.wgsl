@@ -0,0 +1,23 @@
+// Multiplies the premultiplied pixels, the opacity in every channel
+struct Shown {
+  tint: vec4<f32>,
+};
+
+@group(0) @binding(0)
+var<uniform> shown: Shown;
+@group(0) @binding(1)
+var cached: texture_2d<f32>;
+
+@vertex
+fn vs_main(@builtin(vertex_index) index: u32) -> @builtin(position) vec4<f32> {
+  // A single triangle covering the screen
+  let x = f32(i32(index & 1u) * 4 - 1);
+  let y = f32(i32(index >> 1u) * 4 - 1);
+  return vec4<f32>(x, y, 0.0, 1.0);
+}
+
+@fragment
+fn fs_main(@builtin(position) position: vec4<f32>) -> @location(0) vec4<f32> {
+  // Cached at the frame's size, so pixels match one to one
+  return textureLoad(cached, vec2<i32>(position.xy), 0) * shown.tint;
+}
@@ -0,0 +1,62 @@
+struct Checksum {
+  // x, y, width and height of every region, 0 to 1 across the frame
+  regions: array<vec4<f32>, 16>,
+  count: u32,
+};
+
+@group(0) @binding(0)
+var<uniform> checksum: Checksum;
+@group(0) @binding(1)
+var frame: texture_2d<f32>;
+@group(0) @binding(2)
+var frame_sampler: sampler;
+
+// Same as checksum::TILE, texels per side of a downsampled region
+let TILE: f32 = 16.0;
+
+struct VertexOutput {
+  @builtin(position) position: vec4<f32>,
+  // Where in the frame the texel is
+  @location(0) uv: vec2<f32>,
+  // How much of the frame a texel covers
+  @location(1) @interpolate(flat) footprint: vec2<f32>,
+};
+
+@vertex
+fn vs_main(
+  @builtin(vertex_index) vertex: u32,
+  @builtin(instance_index) region: u32,
+) -> VertexOutput {
+  var corners = array<vec2<f32>, 6>(
+    vec2<f32>(0.0, 0.0),
+    vec2<f32>(1.0, 0.0),
+    vec2<f32>(0.0, 1.0),
+    vec2<f32>(0.0, 1.0),
+    vec2<f32>(1.0, 0.0),
+    vec2<f32>(1.0, 1.0),
+  );
+  let corner = corners[vertex];
+  let rect = checksum.regions[region];
+
+  // Regions are tiles side by side in the target, left to right
+  let x = (f32(region) + corner.x) / f32(checksum.count);
+  var out: VertexOutput;
+  out.position = vec4<f32>(x * 2.0 - 1.0, 1.0 - corner.y * 2.0, 0.0, 1.0);
+  out.uv = rect.xy + corner * rect.zw;
+  out.footprint = rect.zw / TILE;
+  return out;
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+  // Averages a grid of taps over the texel, so small changes between
+  // them still change the checksum
+  var sum = vec4<f32>(0.0);
+  for (var y = 0; y < 4; y = y + 1) {
+    for (var x = 0; x < 4; x = x + 1) {
+      let offset = (vec2<f32>(f32(x), f32(y)) + 0.5) / 4.0 - 0.5;
+      sum = sum + textureSampleLevel(frame, frame_sampler, in.uv + offset * in.footprint, 0.0);
+    }
+  }
+  return sum / 16.0;
+}
@@ -0,0 +1,41 @@
+struct Screen {
+  size: vec2<f32>,
+  // Clip space is multiplied by this to mirror the image
+  mirror: vec2<f32>,
+  // 1.0 to draw everything gray and brighter, 0.0 otherwise
+  high_contrast: f32,
+};
+
+@group(0) @binding(0)
+var<uniform> screen: Screen;
+
+struct VertexInput {
+  @location(0) position: vec2<f32>,
+  @location(1) color: vec4<f32>,
+};
+
+struct VertexOutput {
+  @builtin(position) position: vec4<f32>,
+  @location(0) color: vec4<f32>,
+};
+
+@vertex
+fn vs_main(in: VertexInput) -> VertexOutput {
+  var out: VertexOutput;
+  // pixels with the origin in the top left corner to clip space
+  let clip = in.position / screen.size * 2.0 - 1.0;
+  let mirrored = vec2<f32>(clip.x, -clip.y) * screen.mirror;
+  out.position = vec4<f32>(mirrored, 0.0, 1.0);
+  out.color = in.color;
+  if (screen.high_contrast > 0.5) {
+    // Same as mirror::high_contrast
+    let luminance = dot(in.color.rgb, vec3<f32>(0.2126, 0.7152, 0.0722));
+    out.color = vec4<f32>(vec3<f32>(min(luminance * 2.5, 1.0)), in.color.a);
+  }
+  return out;
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+  return in.color;
+}
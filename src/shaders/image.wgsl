@@ -0,0 +1,86 @@
+struct Screen {
+  size: vec2<f32>,
+  // Clip space is multiplied by this to mirror the image
+  mirror: vec2<f32>,
+  // 1.0 to draw everything gray and brighter, 0.0 otherwise
+  high_contrast: f32,
+};
+
+@group(0) @binding(0)
+var<uniform> screen: Screen;
+@group(1) @binding(0)
+var image: texture_2d<f32>;
+@group(1) @binding(1)
+var image_sampler: sampler;
+
+struct VertexInput {
+  @location(0) position: vec2<f32>,
+  @location(1) uv: vec2<f32>,
+  // x, y, width and height in pixels the image is cut to
+  @location(2) clip: vec4<f32>,
+  // Corner radius of the clip rect and opacity
+  @location(3) shape: vec2<f32>,
+  // Rows of the color matrix, see image::ColorMatrix, the last column
+  // in offset
+  @location(4) red: vec4<f32>,
+  @location(5) green: vec4<f32>,
+  @location(6) blue: vec4<f32>,
+  @location(7) alpha: vec4<f32>,
+  @location(8) offset: vec4<f32>,
+};
+
+struct VertexOutput {
+  @builtin(position) position: vec4<f32>,
+  @location(0) uv: vec2<f32>,
+  @location(1) pixel: vec2<f32>,
+  @location(2) clip: vec4<f32>,
+  @location(3) shape: vec2<f32>,
+  @location(4) @interpolate(flat) red: vec4<f32>,
+  @location(5) @interpolate(flat) green: vec4<f32>,
+  @location(6) @interpolate(flat) blue: vec4<f32>,
+  @location(7) @interpolate(flat) alpha: vec4<f32>,
+  @location(8) @interpolate(flat) offset: vec4<f32>,
+};
+
+@vertex
+fn vs_main(in: VertexInput) -> VertexOutput {
+  var out: VertexOutput;
+  // pixels with the origin in the top left corner to clip space
+  let clip = in.position / screen.size * 2.0 - 1.0;
+  let mirrored = vec2<f32>(clip.x, -clip.y) * screen.mirror;
+  out.position = vec4<f32>(mirrored, 0.0, 1.0);
+  out.uv = in.uv;
+  out.pixel = in.position;
+  out.clip = in.clip;
+  out.shape = in.shape;
+  out.red = in.red;
+  out.green = in.green;
+  out.blue = in.blue;
+  out.alpha = in.alpha;
+  out.offset = in.offset;
+  return out;
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+  // Signed distance to the rounded clip rect, negative inside
+  let half = in.clip.zw / 2.0;
+  let radius = min(in.shape.x, min(half.x, half.y));
+  let q = abs(in.pixel - in.clip.xy - half) - half + vec2<f32>(radius);
+  let distance = length(max(q, vec2<f32>(0.0))) + min(max(q.x, q.y), 0.0) - radius;
+  let coverage = clamp(0.5 - distance, 0.0, 1.0);
+  let sampled = textureSample(image, image_sampler, in.uv);
+  let transformed = vec4<f32>(
+    dot(in.red, sampled),
+    dot(in.green, sampled),
+    dot(in.blue, sampled),
+    dot(in.alpha, sampled)
+  );
+  var color = clamp(transformed + in.offset, vec4<f32>(0.0), vec4<f32>(1.0));
+  if (screen.high_contrast > 0.5) {
+    // Same as mirror::high_contrast
+    let luminance = dot(color.rgb, vec3<f32>(0.2126, 0.7152, 0.0722));
+    color = vec4<f32>(vec3<f32>(min(luminance * 2.5, 1.0)), color.a);
+  }
+  return vec4<f32>(color.rgb, color.a * coverage * in.shape.y);
+}
@@ -0,0 +1,150 @@
+struct Safety {
+  size: vec2<f32>,
+  // Clip space is multiplied by this to mirror the image
+  mirror: vec2<f32>,
+  // Bit n is set if telltale n is lit, in the order of safety::Telltale
+  lit: u32,
+};
+
+@group(0) @binding(0)
+var<uniform> safety: Safety;
+
+// Same as safety::Telltale::ALL.len()
+let COUNT: u32 = 5u;
+
+struct VertexOutput {
+  @builtin(position) position: vec4<f32>,
+  // -1 to 1 across the lamp, y pointing down
+  @location(0) local: vec2<f32>,
+  @location(1) @interpolate(flat) telltale: u32,
+};
+
+@vertex
+fn vs_main(
+  @builtin(vertex_index) vertex: u32,
+  @builtin(instance_index) telltale: u32,
+) -> VertexOutput {
+  var corners = array<vec2<f32>, 6>(
+    vec2<f32>(0.0, 0.0),
+    vec2<f32>(1.0, 0.0),
+    vec2<f32>(0.0, 1.0),
+    vec2<f32>(0.0, 1.0),
+    vec2<f32>(1.0, 0.0),
+    vec2<f32>(1.0, 1.0),
+  );
+  let corner = corners[vertex];
+
+  // Every lamp has a fixed place in a row along the top edge
+  let lamp = min(safety.size.x, safety.size.y) * 0.1;
+  let gap = lamp * 0.25;
+  let row = f32(COUNT) * lamp + f32(COUNT - 1u) * gap;
+  let origin = vec2<f32>((safety.size.x - row) * 0.5 + f32(telltale) * (lamp + gap), gap);
+  let clip = (origin + corner * lamp) / safety.size * 2.0 - 1.0;
+
+  var out: VertexOutput;
+  out.position = vec4<f32>(vec2<f32>(clip.x, -clip.y) * safety.mirror, 0.0, 1.0);
+  if ((safety.lit & (1u << telltale)) == 0u) {
+    // Unlit lamps collapse to a point
+    out.position = vec4<f32>(0.0, 0.0, 0.0, 1.0);
+  }
+  out.local = corner * 2.0 - 1.0;
+  out.telltale = telltale;
+  return out;
+}
+
+// Signed distances, negative inside
+
+fn circle(p: vec2<f32>, center: vec2<f32>, radius: f32) -> f32 {
+  return length(p - center) - radius;
+}
+
+fn box(p: vec2<f32>, center: vec2<f32>, half: vec2<f32>) -> f32 {
+  let d = abs(p - center) - half;
+  return length(max(d, vec2<f32>(0.0))) + min(max(d.x, d.y), 0.0);
+}
+
+// A circle in brackets with an exclamation mark
+fn brake(p: vec2<f32>) -> f32 {
+  let ring = abs(circle(p, vec2<f32>(0.0), 0.55)) - 0.07;
+  let brackets = max(abs(circle(p, vec2<f32>(0.0), 0.8)) - 0.06, 0.55 - abs(p.x));
+  let bar = box(p, vec2<f32>(0.0, -0.1), vec2<f32>(0.07, 0.22));
+  let dot = circle(p, vec2<f32>(0.0, 0.27), 0.08);
+  return min(min(ring, brackets), min(bar, dot));
+}
+
+// An engine block with its intake on top
+fn engine(p: vec2<f32>) -> f32 {
+  let block = box(p, vec2<f32>(0.0, 0.1), vec2<f32>(0.45, 0.3));
+  let intake = box(p, vec2<f32>(0.0, -0.32), vec2<f32>(0.2, 0.1));
+  let front = box(p, vec2<f32>(-0.55, 0.1), vec2<f32>(0.1, 0.15));
+  let back = box(p, vec2<f32>(0.55, 0.1), vec2<f32>(0.1, 0.22));
+  return min(min(block, intake), min(front, back));
+}
+
+// A thermometer standing in waves
+fn coolant(p: vec2<f32>) -> f32 {
+  let stem = box(p, vec2<f32>(0.0, -0.2), vec2<f32>(0.07, 0.45));
+  let bulb = circle(p, vec2<f32>(0.0, 0.3), 0.17);
+  let left = box(p, vec2<f32>(-0.45, 0.4), vec2<f32>(0.18, 0.05));
+  let right = box(p, vec2<f32>(0.45, 0.4), vec2<f32>(0.18, 0.05));
+  let below = box(p, vec2<f32>(0.0, 0.7), vec2<f32>(0.65, 0.05));
+  return min(min(stem, bulb), min(min(left, right), below));
+}
+
+// A drop of oil
+fn oil(p: vec2<f32>) -> f32 {
+  let round = circle(p, vec2<f32>(0.0, 0.25), 0.4);
+  let tip = max(max(abs(p.x) - (p.y + 0.7) * 0.42, -0.7 - p.y), p.y - 0.25);
+  return min(round, tip);
+}
+
+// A battery with its terminals and their signs
+fn battery(p: vec2<f32>) -> f32 {
+  let shell = abs(box(p, vec2<f32>(0.0, 0.15), vec2<f32>(0.65, 0.4))) - 0.06;
+  let terminals = min(
+    box(p, vec2<f32>(-0.35, -0.35), vec2<f32>(0.12, 0.08)),
+    box(p, vec2<f32>(0.35, -0.35), vec2<f32>(0.12, 0.08)),
+  );
+  let minus = box(p, vec2<f32>(-0.3, 0.15), vec2<f32>(0.15, 0.04));
+  let plus = min(
+    box(p, vec2<f32>(0.3, 0.15), vec2<f32>(0.15, 0.04)),
+    box(p, vec2<f32>(0.3, 0.15), vec2<f32>(0.04, 0.15)),
+  );
+  return min(min(shell, terminals), min(minus, plus));
+}
+
+// Antialiased over `width`, the derivatives are only there in fs_main
+fn coverage(distance: f32, width: f32) -> f32 {
+  return clamp(0.5 - distance / width, 0.0, 1.0);
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+  // Warnings in red, malfunctions in amber, as on a dashboard
+  let red = vec3<f32>(1.0, 0.02, 0.02);
+  let amber = vec3<f32>(1.0, 0.25, 0.0);
+  var symbol = 0.0;
+  var color = red;
+  switch (in.telltale) {
+    case 0u: {
+      symbol = brake(in.local);
+    }
+    case 1u: {
+      symbol = engine(in.local);
+      color = amber;
+    }
+    case 2u: {
+      symbol = coolant(in.local);
+    }
+    case 3u: {
+      symbol = oil(in.local);
+    }
+    default: {
+      symbol = battery(in.local);
+    }
+  }
+  // On an opaque plate, so nothing drawn below can blend it away
+  let plate = box(in.local, vec2<f32>(0.0), vec2<f32>(0.85)) - 0.1;
+  let rgb = mix(vec3<f32>(0.02), color, coverage(symbol, fwidth(symbol)));
+  return vec4<f32>(rgb, coverage(plate, fwidth(plate)));
+}
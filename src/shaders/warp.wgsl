@@ -0,0 +1,34 @@
+struct Warp {
+  // Maps normalized screen positions to normalized positions in the frame
+  inverse: mat3x3<f32>,
+  // Linear RGB to how it looks with the simulated color blindness
+  color: mat3x3<f32>,
+  size: vec2<f32>,
+};
+
+@group(0) @binding(0)
+var<uniform> warp: Warp;
+@group(0) @binding(1)
+var frame: texture_2d<f32>;
+@group(0) @binding(2)
+var frame_sampler: sampler;
+
+@vertex
+fn vs_main(@builtin(vertex_index) index: u32) -> @builtin(position) vec4<f32> {
+  // A single triangle covering the screen
+  let x = f32(i32(index & 1u) * 4 - 1);
+  let y = f32(i32(index >> 1u) * 4 - 1);
+  return vec4<f32>(x, y, 0.0, 1.0);
+}
+
+@fragment
+fn fs_main(@builtin(position) position: vec4<f32>) -> @location(0) vec4<f32> {
+  let mapped = warp.inverse * vec3<f32>(position.xy / warp.size, 1.0);
+  let uv = mapped.xy / mapped.z;
+  // Keep the background outside of the warped frame
+  if (mapped.z <= 0.0 || any(uv < vec2<f32>(0.0)) || any(uv > vec2<f32>(1.0))) {
+    discard;
+  }
+  let color = textureSampleLevel(frame, frame_sampler, uv, 0.0);
+  return vec4<f32>(clamp(warp.color * color.rgb, vec3<f32>(0.0), vec3<f32>(1.0)), color.a);
+}
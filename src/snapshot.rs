@@ -0,0 +1,91 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+
+use serde::{Deserialize, Serialize};
+
+use crate::build_info::build_info;
+use crate::clock;
+use crate::config::Units;
+use crate::data::Telemetry;
+use crate::error::WindshieldError;
+use crate::mirror::Mirror;
+use crate::theme::ThemeMode;
+use crate::warp::Keystone;
+
+/// What the HUD was showing, saved to a JSON file so a bug a user ran into
+/// can be looked at with the same page and values, see
+/// [`Action::Snapshot`](crate::input::Action::Snapshot) and
+/// [`Settings::restore`](crate::Settings::restore).
+///
+/// Animations within widgets aren't saved, widgets settle on the restored
+/// values like they do on telemetry. Data sources keep overwriting the
+/// restored telemetry as they report, so run without them, e.g.
+/// [deterministic](crate::Settings::deterministic), to keep it.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Snapshot {
+  /// The build that saved the snapshot, see
+  /// [`BuildInfo`](crate::build_info::BuildInfo).
+  pub build: String,
+  /// Index of the page shown, see [`Scene::page`](crate::scene::Scene::page).
+  pub page: usize,
+  /// Units the dashboard was switched to, if it came from a config.
+  pub units: Option<Units>,
+  pub telemetry: Telemetry,
+  pub mirror: Mirror,
+  pub high_contrast: bool,
+  pub theme_mode: ThemeMode,
+  pub keystone: Keystone,
+}
+
+impl Snapshot {
+  pub fn load(path: impl AsRef<Path>) -> Result<Self, WindshieldError> {
+    let path = path.as_ref();
+    let source = fs::read_to_string(path).map_err(|source| WindshieldError::ReadFile {
+      path: path.to_path_buf(),
+      source,
+    })?;
+    serde_json::from_str(&source).map_err(|err| WindshieldError::InvalidSnapshot {
+      path: path.to_path_buf(),
+      message: err.to_string(),
+    })
+  }
+
+  pub fn save(&self, path: impl AsRef<Path>) -> Result<(), WindshieldError> {
+    let path = path.as_ref();
+    let snapshot = serde_json::to_string_pretty(self).expect("snapshots serialize");
+    fs::write(path, snapshot).map_err(|source| WindshieldError::WriteFile {
+      path: path.to_path_buf(),
+      source,
+    })
+  }
+
+  /// Saves the snapshot to a new file in `dir`, named after the time, and
+  /// returns its path.
+  pub fn save_in(&mut self, dir: impl AsRef<Path>) -> Result<PathBuf, WindshieldError> {
+    let dir = dir.as_ref();
+    fs::create_dir_all(dir).map_err(|source| WindshieldError::WriteFile {
+      path: dir.to_path_buf(),
+      source,
+    })?;
+    let time = clock::system_time()
+      .duration_since(UNIX_EPOCH)
+      .unwrap_or_default()
+      .as_secs();
+    let path = unused_path(dir, time);
+    self.build = build_info().to_string();
+    self.save(&path)?;
+    Ok(path)
+  }
+}
+
+fn unused_path(dir: &Path, time: u64) -> PathBuf {
+  let mut path = dir.join(format!("snapshot-{}.json", time));
+  let mut n = 1;
+  while path.exists() {
+    path = dir.join(format!("snapshot-{}-{}.json", time, n));
+    n += 1;
+  }
+  path
+}
@@ -0,0 +1,131 @@
+use std::fmt;
+use std::time::Duration;
+
+use instant::Instant;
+
+/// How long each phase between calling `run` and presenting the first frame
+/// took.
+#[derive(Clone, Debug)]
+pub struct StartupReport {
+  pub phases: Vec<(&'static str, Duration)>,
+  pub total: Duration,
+  /// See [`Settings::startup_budget`](crate::Settings::startup_budget).
+  pub budget: Option<Duration>,
+}
+
+impl StartupReport {
+  /// How long the phase called `name` took, e.g. `"adapter"`, `"device"`,
+  /// `"surface"`, `"assets"` or `"first frame"`.
+  pub fn phase(&self, name: &str) -> Option<Duration> {
+    self
+      .phases
+      .iter()
+      .find(|(phase, _)| *phase == name)
+      .map(|(_, duration)| *duration)
+  }
+
+  /// The phase that took longest, where to start when startup is slow.
+  pub fn slowest(&self) -> Option<(&'static str, Duration)> {
+    self
+      .phases
+      .iter()
+      .copied()
+      .max_by_key(|(_, duration)| *duration)
+  }
+
+  pub fn is_over_budget(&self) -> bool {
+    self.budget.is_some_and(|budget| self.total > budget)
+  }
+}
+
+impl fmt::Display for StartupReport {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    write!(f, "startup took {:.1?}", self.total)?;
+    if let Some(budget) = self.budget {
+      write!(f, " of {:.1?}", budget)?;
+    }
+    for (name, duration) in &self.phases {
+      write!(f, ", {} {:.1?}", name, duration)?;
+    }
+    Ok(())
+  }
+}
+
+pub(crate) struct StartupTimer {
+  started: Instant,
+  last: Instant,
+  phases: Vec<(&'static str, Duration)>,
+  budget: Option<Duration>,
+}
+
+impl StartupTimer {
+  pub(crate) fn new() -> Self {
+    let now = Instant::now();
+    Self {
+      started: now,
+      last: now,
+      phases: Vec::new(),
+      budget: None,
+    }
+  }
+
+  pub(crate) fn set_budget(&mut self, budget: Option<Duration>) {
+    self.budget = budget;
+  }
+
+  /// Ends the current phase, attributing the time since the previous one
+  /// to `name`.
+  pub(crate) fn phase(&mut self, name: &'static str) {
+    let now = Instant::now();
+    let duration = now - self.last;
+    self.last = now;
+    let elapsed = now - self.started;
+    tracing::debug!(phase = name, ?duration, ?elapsed, "startup phase finished");
+    if let Some(budget) = self.budget.filter(|budget| elapsed > *budget) {
+      // Once, for the phase it ran out in
+      if elapsed - duration <= budget {
+        tracing::warn!(phase = name, ?budget, "startup ran out of time");
+      }
+    }
+    self.phases.push((name, duration));
+  }
+
+  pub(crate) fn finish(self) -> StartupReport {
+    StartupReport {
+      phases: self.phases,
+      total: self.last - self.started,
+      budget: self.budget,
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn report(budget: u64) -> StartupReport {
+    StartupReport {
+      phases: vec![
+        ("adapter", Duration::from_millis(40)),
+        ("device", Duration::from_millis(120)),
+        ("first frame", Duration::from_millis(30)),
+      ],
+      total: Duration::from_millis(190),
+      budget: Some(Duration::from_millis(budget)),
+    }
+  }
+
+  #[test]
+  fn summarizes_phases_against_the_budget() {
+    let over = report(150);
+    assert_eq!(over.phase("adapter"), Some(Duration::from_millis(40)));
+    assert_eq!(over.phase("assets"), None);
+    assert_eq!(over.slowest(), Some(("device", Duration::from_millis(120))));
+    assert!(over.is_over_budget());
+    assert!(!report(200).is_over_budget());
+    assert_eq!(
+      over.to_string(),
+      "startup took 190.0ms of 150.0ms, adapter 40.0ms, device 120.0ms, first frame 30.0ms"
+    );
+  }
+}
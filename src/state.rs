@@ -0,0 +1,1763 @@
+use std::collections::{HashMap, HashSet};
+use std::num::NonZeroU32;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use instant::Instant;
+use tokio::sync::mpsc::UnboundedReceiver;
+use wgpu::{
+  AdapterInfo, Backends, BufferAsyncError, BufferDescriptor, BufferUsages, Color,
+  CommandEncoderDescriptor, CompositeAlphaMode, Device, DeviceDescriptor, Extent3d, Features,
+  ImageCopyBuffer, ImageDataLayout, Instance, Limits, LoadOp, Maintain, MapMode, Operations,
+  PresentMode, Queue, RenderPassColorAttachment, RenderPassDescriptor, RequestAdapterOptions,
+  Surface, SurfaceConfiguration, SurfaceError, Texture, TextureDescriptor, TextureDimension,
+  TextureFormat, TextureUsages, TextureView, TextureViewDescriptor, COPY_BYTES_PER_ROW_ALIGNMENT,
+};
+use winit::dpi::PhysicalSize;
+use winit::{event::WindowEvent, window::Window};
+
+use crate::about;
+use crate::accessibility::Accessibility;
+use crate::build_info::{build_info, BuildInfo};
+use crate::burn_in::BurnIn;
+use crate::cache::{CachePass, WidgetCache};
+use crate::canvas::Canvas;
+#[cfg(feature = "inspector")]
+use crate::canvas::Style;
+use crate::checksum::{ChecksumPass, ChecksumRegion};
+use crate::clock::{self, Clock, FrameLimiter, RealClock, SteppedClock, DETERMINISTIC_STEP};
+use crate::crash::{CrashReporter, LOST_FRAMES};
+use crate::data::arbiter::{Arbiter, Origin};
+use crate::data::process::{Processor, Signals};
+use crate::data::{Field, Report, Telemetry};
+use crate::diagnostics::Diagnostics;
+use crate::error::WindshieldError;
+use crate::frame::Frame;
+use crate::image::{Image, ImageBatch, ImagePipeline};
+use crate::input::{Action, Bindings, Trigger, TriggerEvent, TriggerTracker};
+use crate::input_map::ButtonMapper;
+#[cfg(feature = "inspector")]
+use crate::inspector::Inspector;
+#[cfg(all(feature = "layer-shell", target_os = "linux"))]
+use crate::layer_shell::LayerSurface;
+use crate::log_view::LogView;
+use crate::mirror::Mirror;
+use crate::output::FrameOutput;
+use crate::pipeline::{shader_source, ColorPipeline, COLOR_SHADER, IMAGE_SHADER, WARP_SHADER};
+use crate::power::{Ignition, Power};
+use crate::prewarm::{self, Variant, VariantRecorder};
+use crate::rules::{Rule, Rules};
+use crate::safe_area::SafeArea;
+use crate::safety::SafetyPass;
+use crate::scene::Scene;
+use crate::settings::Settings;
+use crate::startup::{StartupReport, StartupTimer};
+use crate::stats::{FrameStats, GpuTimer};
+use crate::text::{TextRenderer, TextTarget};
+use crate::theme::{ColorVision, Palette, ThemeMode, Themes};
+use crate::warp::{Keystone, WarpPass};
+use crate::widgets::Rect;
+
+pub(crate) struct State {
+  // Kept to create the surface again when resumed on Android
+  instance: Instance,
+  // None when rendering headless or while suspended
+  surface: Option<Surface>,
+  // Shared with the thread staging images
+  device: Arc<Device>,
+  queue: Queue,
+  config: SurfaceConfiguration,
+  present_modes: Vec<PresentMode>,
+  limiter: Option<FrameLimiter>,
+  // Redrawing only on change if set, and at least this often
+  heartbeat: Option<Duration>,
+  dirty: bool,
+  power: Power,
+  ignition: Ignition,
+  last_frame: Instant,
+  pub(crate) size: winit::dpi::PhysicalSize<u32>,
+  clock: Box<dyn Clock>,
+  // The clock's zero as an instant, see `now`
+  epoch: Instant,
+  /// See [`Settings::deterministic`].
+  deterministic: bool,
+  bindings: Bindings,
+  triggers: TriggerTracker,
+  buttons: ButtonMapper<Trigger>,
+  last_update: Duration,
+  delta: Duration,
+  telemetry: Telemetry,
+  // When each field was last reported, on the app's clock
+  updated: HashMap<Field, Duration>,
+  // Fields not reported for longer than `stale_after`
+  stale: HashSet<Field>,
+  stale_after: Option<Duration>,
+  // Of the window, times `ui_scale` for the frame
+  scale_factor: f64,
+  ui_scale: f32,
+  safe_area: SafeArea,
+  burn_in: Option<BurnIn>,
+  // Of the burn-in shift the layout is at
+  shift_step: u64,
+  sources: Vec<UnboundedReceiver<Report>>,
+  stats: FrameStats,
+  // Set if the background doesn't follow the palette
+  clear_color: Option<Color>,
+  themes: Themes,
+  night: bool,
+  palette: Palette,
+  mirror: Mirror,
+  high_contrast: bool,
+  reduce_motion: bool,
+  right_to_left: bool,
+  accessibility: Accessibility,
+  keystone: Keystone,
+  simulated: ColorVision,
+  // Only created once there is a keystone to correct
+  warp: Option<WarpPass>,
+  checksum: Option<ChecksumPass>,
+  timer: Option<GpuTimer>,
+  // Mirrors every frame into a file if there is one to mirror into
+  output: Option<FrameOutput>,
+  // Drawn into by benchmarks, created on their first frame
+  offscreen: Option<Texture>,
+  // Only with a window to show it in
+  #[cfg(feature = "inspector")]
+  inspector: Option<Inspector>,
+  diagnostics: Diagnostics,
+  // Shown by `about`, see `toggle_about`
+  build: BuildInfo,
+  adapter: AdapterInfo,
+  about: bool,
+  logs: LogView,
+  shader_dir: Option<PathBuf>,
+  canvas: Canvas,
+  shapes: ColorPipeline,
+  images: ImageBatch,
+  pictures: ImagePipeline,
+  caches: CachePass,
+  pub(crate) text: TextRenderer,
+  // Drawn last and on its own, whatever the pipelines above do
+  safety: SafetyPass,
+  rules: Rules,
+  signals: Signals,
+  arbiter: Arbiter,
+  // Taken once the first frame has been presented
+  startup: Option<StartupTimer>,
+  // Until the app has been told, see `take_startup_report`
+  startup_report: Option<StartupReport>,
+  // Of what's drawn, see `record_variants`
+  variants: Option<VariantRecorder>,
+  pub(crate) crash: Option<CrashReporter>,
+  // Frames in a row the surface was lost for
+  lost_frames: u32,
+}
+
+impl State {
+  // Creating some of the wgpu types requires async
+  // code
+  pub(crate) async fn new(
+    window: &Window,
+    settings: &Settings,
+    clock: Option<Box<dyn Clock>>,
+    mut startup: StartupTimer,
+    crash: Option<CrashReporter>,
+  ) -> Result<Self, WindshieldError> {
+    let instance = instance();
+    let surface = unsafe { instance.create_surface(window) };
+    startup.phase("surface");
+    #[allow(unused_mut)]
+    let mut state = Self::create(
+      instance,
+      Some(surface),
+      window.inner_size(),
+      settings,
+      clock,
+      startup,
+      crash,
+    )
+    .await?;
+    #[cfg(feature = "inspector")]
+    {
+      state.inspector = Some(Inspector::new(&state.device, state.config.format, window));
+    }
+    Ok(state)
+  }
+
+  /// Renders into a layer surface of the Wayland compositor instead of
+  /// a window.
+  #[cfg(all(feature = "layer-shell", target_os = "linux"))]
+  pub(crate) async fn on_layer(
+    layer: &LayerSurface,
+    settings: &Settings,
+    clock: Option<Box<dyn Clock>>,
+    mut startup: StartupTimer,
+    crash: Option<CrashReporter>,
+  ) -> Result<Self, WindshieldError> {
+    let instance = instance();
+    let surface = unsafe { instance.create_surface(layer) };
+    startup.phase("surface");
+    Self::create(
+      instance,
+      Some(surface),
+      layer.size(),
+      settings,
+      clock,
+      startup,
+      crash,
+    )
+    .await
+  }
+
+  /// Renders without a window, into [screenshots](Self::screenshot) only.
+  pub(crate) async fn headless(
+    settings: &Settings,
+    clock: Option<Box<dyn Clock>>,
+    size: PhysicalSize<u32>,
+  ) -> Result<Self, WindshieldError> {
+    let instance = instance();
+    let mut startup = StartupTimer::new();
+    startup.set_budget(settings.startup_budget);
+    Self::create(instance, None, size, settings, clock, startup, None).await
+  }
+
+  async fn create(
+    instance: Instance,
+    surface: Option<Surface>,
+    size: PhysicalSize<u32>,
+    settings: &Settings,
+    clock: Option<Box<dyn Clock>>,
+    mut startup: StartupTimer,
+    crash: Option<CrashReporter>,
+  ) -> Result<Self, WindshieldError> {
+    let adapter = instance
+      .request_adapter(&RequestAdapterOptions {
+        power_preference: settings.power_preference,
+        compatible_surface: surface.as_ref(),
+        force_fallback_adapter: false,
+      })
+      .await
+      .ok_or(WindshieldError::AdapterNotFound)?;
+    startup.phase("adapter");
+    let info = adapter.get_info();
+    tracing::info!("{} using {} on {:?}", build_info(), info.name, info.backend);
+    if let Some(crash) = &crash {
+      crash.set_adapter(info.clone());
+    }
+
+    // Frames are timed on the GPU where it can
+    let features = adapter.features() & Features::TIMESTAMP_QUERY;
+    let (device, queue) = adapter
+      .request_device(
+        &DeviceDescriptor {
+          features,
+          // WebGL doesn't support all of wgpu's features, so if
+          // we're building for the web we'll have to disable some.
+          limits: if cfg!(target_arch = "wasm32") {
+            Limits::downlevel_webgl2_defaults()
+          } else {
+            Limits::default()
+          },
+          label: None,
+        },
+        None, // Trace path
+      )
+      .await?;
+    let device = Arc::new(device);
+    startup.phase("device");
+
+    let (config, present_modes) = match &surface {
+      Some(surface) => {
+        let alpha_mode = select_alpha_mode(&surface.get_supported_alpha_modes(&adapter), settings);
+        let present_modes = surface.get_supported_present_modes(&adapter);
+        let present_mode = select_present_mode(&present_modes, settings.present_mode);
+        let format = *surface
+          .get_supported_formats(&adapter)
+          .first()
+          .ok_or(WindshieldError::UnsupportedSurface)?;
+
+        let config = SurfaceConfiguration {
+          usage: TextureUsages::RENDER_ATTACHMENT,
+          format,
+          width: size.width,
+          height: size.height,
+          present_mode,
+          alpha_mode,
+        };
+        surface.configure(&device, &config);
+        (config, present_modes)
+      }
+      // Describes the screenshot texture instead, which PNG wants in
+      // sRGB and straight alpha
+      None => {
+        let config = SurfaceConfiguration {
+          usage: TextureUsages::RENDER_ATTACHMENT | TextureUsages::COPY_SRC,
+          format: TextureFormat::Rgba8UnormSrgb,
+          width: size.width,
+          height: size.height,
+          present_mode: PresentMode::Fifo,
+          alpha_mode: CompositeAlphaMode::PostMultiplied,
+        };
+        (config, Vec::new())
+      }
+    };
+    let format = config.format;
+    startup.phase("configure");
+
+    let color_shader = shader_source(settings.shader_dir.as_deref(), COLOR_SHADER)?;
+    let mut shapes = ColorPipeline::new(
+      &device,
+      format,
+      size.width,
+      size.height,
+      (COLOR_SHADER.0, &color_shader),
+    )
+    .await?;
+    let contrast = settings.high_contrast || settings.accessibility.forces_high_contrast();
+    shapes.set_mirror(&queue, settings.mirror);
+    shapes.set_high_contrast(&queue, contrast);
+    let image_shader = shader_source(settings.shader_dir.as_deref(), IMAGE_SHADER)?;
+    let mut pictures = ImagePipeline::new(
+      &device,
+      format,
+      size.width,
+      size.height,
+      (IMAGE_SHADER.0, &image_shader),
+    )
+    .await?;
+    pictures.set_mirror(&queue, settings.mirror);
+    pictures.set_high_contrast(&queue, contrast);
+    let mut safety = SafetyPass::new(&device, format, size.width, size.height).await?;
+    safety.set_mirror(&queue, settings.mirror);
+    let caches = CachePass::new(&device, format).await?;
+    let mut text = TextRenderer::new(&device, format);
+    text.mirror = settings.mirror;
+    text.high_contrast = contrast;
+    // Checksums sample the frame and color blindness is simulated on it,
+    // so it's rendered into a texture for them as well
+    let warp = if settings.keystone.is_identity()
+      && settings.checksum_regions.is_empty()
+      && settings.simulate_color_vision == ColorVision::Normal
+    {
+      None
+    } else {
+      let warp_shader = shader_source(settings.shader_dir.as_deref(), WARP_SHADER)?;
+      let mut warp = WarpPass::new(
+        &device,
+        format,
+        size.width,
+        size.height,
+        (WARP_SHADER.0, &warp_shader),
+      )
+      .await?;
+      warp.set_keystone(&queue, settings.keystone);
+      warp.set_color_vision(&queue, settings.simulate_color_vision);
+      Some(warp)
+    };
+    let checksum = if settings.checksum_regions.is_empty() {
+      None
+    } else {
+      Some(ChecksumPass::new(&device, settings.checksum_regions.clone()).await?)
+    };
+    startup.phase("pipelines");
+    if let Some(crash) = &crash {
+      crash.set_surface(&config);
+    }
+
+    let timer = features
+      .contains(Features::TIMESTAMP_QUERY)
+      .then(|| GpuTimer::new(&device, &queue));
+    let output = match &settings.frame_output {
+      Some(path) => Some(FrameOutput::new(&device, format, [size.width, size.height], path).await?),
+      None => None,
+    };
+
+    Ok(Self {
+      instance,
+      surface,
+      device,
+      queue,
+      config,
+      present_modes,
+      limiter: settings.max_fps.map(FrameLimiter::new),
+      heartbeat: settings.heartbeat,
+      dirty: true,
+      power: Power::On,
+      ignition: Ignition::new(settings.ignition_off_delay),
+      last_frame: Instant::now(),
+      size,
+      clock: clock.unwrap_or_else(|| {
+        if settings.deterministic {
+          Box::new(SteppedClock::new(DETERMINISTIC_STEP))
+        } else {
+          Box::new(RealClock::new())
+        }
+      }),
+      epoch: Instant::now(),
+      deterministic: settings.deterministic,
+      bindings: settings.bindings.clone(),
+      triggers: TriggerTracker::default(),
+      buttons: settings.bindings.buttons(),
+      last_update: Duration::ZERO,
+      delta: Duration::ZERO,
+      telemetry: Telemetry::default(),
+      updated: HashMap::new(),
+      stale: HashSet::new(),
+      stale_after: settings.stale_after,
+      scale_factor: 1.0,
+      ui_scale: settings.ui_scale,
+      safe_area: settings.safe_area,
+      burn_in: settings.burn_in,
+      shift_step: 0,
+      sources: Vec::new(),
+      stats: FrameStats::default(),
+      clear_color: settings.clear_color(),
+      themes: settings.themes.clone(),
+      night: false,
+      palette: settings.themes.palette(false),
+      mirror: settings.mirror,
+      high_contrast: settings.high_contrast,
+      reduce_motion: settings.reduce_motion,
+      right_to_left: settings.right_to_left,
+      accessibility: settings.accessibility,
+      keystone: settings.keystone,
+      simulated: settings.simulate_color_vision,
+      warp,
+      checksum,
+      timer,
+      output,
+      offscreen: None,
+      #[cfg(feature = "inspector")]
+      inspector: None,
+      diagnostics: Diagnostics::new(),
+      build: build_info(),
+      adapter: info,
+      about: false,
+      logs: LogView::new(settings.log_buffer.clone()),
+      shader_dir: settings.shader_dir.clone(),
+      canvas: Canvas::new(),
+      shapes,
+      images: ImageBatch::new(),
+      pictures,
+      caches,
+      text,
+      safety,
+      rules: Rules::new(settings.rules.clone()),
+      signals: Signals::new(&settings.signals)?,
+      arbiter: Arbiter::new(&settings.priorities, settings.fallback_after),
+      startup: Some(startup),
+      startup_report: None,
+      variants: None,
+      crash,
+      lost_frames: 0,
+    })
+  }
+
+  pub(crate) fn resize(&mut self, new_size: winit::dpi::PhysicalSize<u32>) {
+    if new_size.width > 0 && new_size.height > 0 {
+      self.size = new_size;
+      self.config.width = new_size.width;
+      self.config.height = new_size.height;
+      if let Some(surface) = &self.surface {
+        surface.configure(&self.device, &self.config);
+      }
+      self
+        .shapes
+        .resize(&self.queue, new_size.width, new_size.height);
+      self
+        .pictures
+        .resize(&self.queue, new_size.width, new_size.height);
+      self
+        .safety
+        .resize(&self.queue, new_size.width, new_size.height);
+      if let Some(warp) = &mut self.warp {
+        warp.resize(&self.device, &self.queue, new_size.width, new_size.height);
+      }
+      if let Some(output) = &mut self.output {
+        output.resize(&self.device, &self.queue, new_size.width, new_size.height);
+      }
+      if let Some(crash) = &self.crash {
+        crash.set_surface(&self.config);
+      }
+      self.caches.clear();
+    }
+  }
+
+  /// Drops the surface, Android destroys the window it draws to when the
+  /// app goes to the background.
+  pub(crate) fn suspend(&mut self) {
+    self.surface = None;
+  }
+
+  /// Creates the surface again for the new window Android hands out when
+  /// the app comes back. Does nothing if it wasn't suspended.
+  pub(crate) fn resume(&mut self, window: &Window) {
+    if self.surface.is_some() {
+      return;
+    }
+    self.surface = Some(unsafe { self.instance.create_surface(window) });
+    self.resize(window.inner_size());
+    self.invalidate();
+  }
+
+  pub(crate) fn is_suspended(&self) -> bool {
+    self.surface.is_none()
+  }
+
+  pub(crate) fn set_present_mode(&mut self, mode: PresentMode) {
+    let Some(surface) = &self.surface else {
+      return;
+    };
+    let mode = select_present_mode(&self.present_modes, mode);
+    if mode != self.config.present_mode {
+      self.config.present_mode = mode;
+      surface.configure(&self.device, &self.config);
+      if let Some(crash) = &self.crash {
+        crash.set_surface(&self.config);
+      }
+    }
+  }
+
+  pub(crate) fn set_max_fps(&mut self, max_fps: Option<f32>) {
+    self.limiter = max_fps.map(FrameLimiter::new);
+  }
+
+  pub(crate) fn set_heartbeat(&mut self, heartbeat: Option<Duration>) {
+    self.heartbeat = heartbeat;
+    self.dirty = true;
+  }
+
+  /// Makes the next frame draw even if nothing else changed.
+  pub(crate) fn invalidate(&mut self) {
+    self.dirty = true;
+  }
+
+  pub(crate) fn power(&self) -> Power {
+    self.power
+  }
+
+  /// What the display turns to with the ignition, if it changed or stayed
+  /// off long enough, see [`Settings::ignition_off_delay`].
+  pub(crate) fn poll_ignition(&mut self) -> Option<Power> {
+    self.ignition.update(&self.telemetry, self.now())
+  }
+
+  /// When the display turns off unless the ignition comes back on.
+  pub(crate) fn ignition_due(&self) -> Option<Instant> {
+    self.ignition.due().map(|due| self.wake_at(due))
+  }
+
+  pub(crate) fn set_ignition_off_delay(&mut self, delay: Duration) {
+    self.ignition.set_off_delay(delay);
+  }
+
+  /// Blanks the display or shows it again, either with the next frame.
+  pub(crate) fn set_power(&mut self, power: Power) {
+    self.power = power;
+    self.dirty = true;
+  }
+
+  /// When the next frame should be drawn: right away unless it only
+  /// redraws on change and nothing did, or the frame rate is limited.
+  /// Never while the display is off and blanked already.
+  pub(crate) fn next_frame(&mut self) -> Option<Instant> {
+    self.poll_sources();
+    let due = match self.heartbeat {
+      _ if self.dirty => Instant::now(),
+      _ if self.power == Power::Off => return None,
+      Some(heartbeat) => self.last_frame + heartbeat,
+      None => Instant::now(),
+    };
+    Some(match &self.limiter {
+      Some(limiter) => due.max(limiter.next_frame()),
+      None => due,
+    })
+  }
+
+  /// Rebuilds the pipelines from the shaders in `dir`, keeping the current
+  /// ones if they don't compile.
+  pub(crate) fn reload_shaders(&mut self, dir: &Path) {
+    let shapes = shader_source(Some(dir), COLOR_SHADER).and_then(|source| {
+      pollster::block_on(ColorPipeline::new(
+        &self.device,
+        self.config.format,
+        self.config.width,
+        self.config.height,
+        (COLOR_SHADER.0, &source),
+      ))
+    });
+    match shapes {
+      Ok(mut shapes) => {
+        shapes.set_mirror(&self.queue, self.mirror);
+        shapes.set_high_contrast(&self.queue, self.contrast());
+        self.shapes = shapes;
+        tracing::info!("reloaded shaders from {}", dir.display());
+      }
+      Err(err) => tracing::error!("{}", err),
+    }
+    let pictures = shader_source(Some(dir), IMAGE_SHADER).and_then(|source| {
+      pollster::block_on(ImagePipeline::new(
+        &self.device,
+        self.config.format,
+        self.config.width,
+        self.config.height,
+        (IMAGE_SHADER.0, &source),
+      ))
+    });
+    match pictures {
+      Ok(mut pictures) => {
+        pictures.set_mirror(&self.queue, self.mirror);
+        pictures.set_high_contrast(&self.queue, self.contrast());
+        self.pictures = pictures;
+      }
+      Err(err) => tracing::error!("{}", err),
+    }
+    if self.warp.is_some() {
+      match self.create_warp(Some(dir)) {
+        Ok(warp) => self.warp = Some(warp),
+        Err(err) => tracing::error!("{}", err),
+      }
+    }
+  }
+
+  fn create_warp(&self, dir: Option<&Path>) -> Result<WarpPass, WindshieldError> {
+    let source = shader_source(dir, WARP_SHADER)?;
+    let mut warp = pollster::block_on(WarpPass::new(
+      &self.device,
+      self.config.format,
+      self.config.width,
+      self.config.height,
+      (WARP_SHADER.0, &source),
+    ))?;
+    warp.set_keystone(&self.queue, self.keystone);
+    warp.set_color_vision(&self.queue, self.simulated);
+    Ok(warp)
+  }
+
+  /// Merges every value received into the telemetry passed to frames.
+  pub(crate) fn add_source(&mut self, source: UnboundedReceiver<Report>) {
+    self.sources.push(source);
+  }
+
+  /// Background color in straight alpha, `None` to take the palette's.
+  pub(crate) fn set_clear_color(&mut self, color: Option<Color>) {
+    self.clear_color = color;
+  }
+
+  pub(crate) fn set_themes(&mut self, themes: Themes) {
+    self.themes = themes;
+    self.update_palette();
+  }
+
+  pub(crate) fn theme_mode(&self) -> ThemeMode {
+    self.themes.mode
+  }
+
+  pub(crate) fn set_theme_mode(&mut self, mode: ThemeMode) {
+    self.themes.mode = mode;
+    self.update_palette();
+  }
+
+  /// The time of day, counted from the Unix epoch by the clock rather than
+  /// read from the system in deterministic mode, so the automatic theme
+  /// doesn't depend on when frames are drawn.
+  fn wall_time(&self) -> SystemTime {
+    if self.deterministic {
+      UNIX_EPOCH + self.clock.now()
+    } else {
+      clock::system_time()
+    }
+  }
+
+  /// Switches between the day and night palette as it gets dark or light.
+  fn update_palette(&mut self) {
+    let night = self
+      .themes
+      .is_night(&self.telemetry, self.wall_time(), self.night);
+    if night != self.night {
+      tracing::info!(
+        "switching to the {} palette",
+        if night { "night" } else { "day" }
+      );
+    }
+    self.night = night;
+    self.palette = self.themes.palette(night);
+  }
+
+  pub(crate) fn mirror(&self) -> Mirror {
+    self.mirror
+  }
+
+  pub(crate) fn set_mirror(&mut self, mirror: Mirror) {
+    self.mirror = mirror;
+    self.shapes.set_mirror(&self.queue, mirror);
+    self.pictures.set_mirror(&self.queue, mirror);
+    self.safety.set_mirror(&self.queue, mirror);
+    self.text.mirror = mirror;
+    self.caches.clear();
+  }
+
+  pub(crate) fn high_contrast(&self) -> bool {
+    self.high_contrast
+  }
+
+  pub(crate) fn keystone(&self) -> Keystone {
+    self.keystone
+  }
+
+  pub(crate) fn set_keystone(&mut self, keystone: Keystone) {
+    self.keystone = keystone;
+    match &mut self.warp {
+      Some(warp) => warp.set_keystone(&self.queue, keystone),
+      None if !keystone.is_identity() => match self.create_warp(self.shader_dir.as_deref()) {
+        Ok(warp) => self.warp = Some(warp),
+        Err(err) => tracing::error!("unable to correct keystone: {}", err),
+      },
+      None => {}
+    }
+  }
+
+  /// Recolors frames to how they look with `vision`, for checking that
+  /// alerts stay apart.
+  pub(crate) fn set_simulated_color_vision(&mut self, vision: ColorVision) {
+    self.simulated = vision;
+    self.dirty = true;
+    match &mut self.warp {
+      Some(warp) => warp.set_color_vision(&self.queue, vision),
+      None if vision != ColorVision::Normal => match self.create_warp(self.shader_dir.as_deref()) {
+        Ok(warp) => self.warp = Some(warp),
+        Err(err) => tracing::error!("unable to simulate color blindness: {}", err),
+      },
+      None => {}
+    }
+  }
+
+  /// Simulates the next kind of color blindness, or none after the last.
+  pub(crate) fn cycle_simulated_color_vision(&mut self) {
+    let vision = self.simulated.next();
+    tracing::info!("simulating color vision: {:?}", vision);
+    self.set_simulated_color_vision(vision);
+  }
+
+  /// Replaces the processors of telemetry, starting over with their
+  /// averages and estimates.
+  pub(crate) fn set_priorities(
+    &mut self,
+    priorities: &HashMap<Field, Vec<Origin>>,
+    fallback_after: Duration,
+  ) {
+    self.arbiter = Arbiter::new(priorities, fallback_after);
+  }
+
+  /// Keeps the processors there were if any of `processors` can't
+  /// process values.
+  pub(crate) fn set_signals(
+    &mut self,
+    processors: &HashMap<Field, Vec<Processor>>,
+  ) -> Result<(), WindshieldError> {
+    self.signals = Signals::new(processors)?;
+    Ok(())
+  }
+
+  pub(crate) fn set_rules(&mut self, rules: Vec<Rule>) {
+    self.rules.set(rules);
+    self.set_telltales();
+  }
+
+  /// Rules that became active or inactive since the last call, see
+  /// [`Rules::take_changes`].
+  pub(crate) fn rule_changes(&mut self) -> Vec<(&Rule, bool)> {
+    self.rules.take_changes()
+  }
+
+  /// Shows and hides the widgets of rules in `scene`, see [`Rules::show`].
+  pub(crate) fn show_rules(&self, scene: &mut Scene) {
+    self.rules.show(scene);
+  }
+
+  pub(crate) fn set_checksum_regions(&mut self, regions: Vec<ChecksumRegion>) {
+    let current = self
+      .checksum
+      .as_ref()
+      .map_or(&[][..], ChecksumPass::regions);
+    if current == regions {
+      return;
+    }
+    self.checksum = None;
+    if regions.is_empty() {
+      return;
+    }
+    if self.warp.is_none() {
+      match self.create_warp(self.shader_dir.as_deref()) {
+        Ok(warp) => self.warp = Some(warp),
+        Err(err) => {
+          tracing::error!("unable to checksum frames: {}", err);
+          return;
+        }
+      }
+    }
+    match pollster::block_on(ChecksumPass::new(&self.device, regions)) {
+      Ok(checksum) => self.checksum = Some(checksum),
+      Err(err) => tracing::error!("unable to checksum frames: {}", err),
+    }
+  }
+
+  /// Draws everything gray and brighter on black, for reflections.
+  pub(crate) fn set_high_contrast(&mut self, high_contrast: bool) {
+    self.high_contrast = high_contrast;
+    self.apply_contrast();
+  }
+
+  /// Whether everything is drawn in high contrast, as set or forced by the
+  /// accessibility profile.
+  fn contrast(&self) -> bool {
+    self.high_contrast || self.accessibility.forces_high_contrast()
+  }
+
+  fn apply_contrast(&mut self) {
+    let contrast = self.contrast();
+    self.shapes.set_high_contrast(&self.queue, contrast);
+    self.pictures.set_high_contrast(&self.queue, contrast);
+    self.text.high_contrast = contrast;
+    self.caches.clear();
+    self.dirty = true;
+  }
+
+  pub(crate) fn set_reduce_motion(&mut self, reduce_motion: bool) {
+    self.reduce_motion = reduce_motion;
+    self.dirty = true;
+  }
+
+  pub(crate) fn set_right_to_left(&mut self, right_to_left: bool) {
+    self.right_to_left = right_to_left;
+    // Cached widgets were drawn the other way around
+    self.caches.clear();
+    self.dirty = true;
+  }
+
+  pub(crate) fn set_accessibility(&mut self, accessibility: Accessibility) {
+    self.accessibility = accessibility;
+    self.apply_contrast();
+  }
+
+  /// Switches the accessibility profile on or off.
+  pub(crate) fn toggle_accessibility(&mut self) {
+    self.accessibility.enabled = !self.accessibility.enabled;
+    tracing::info!(
+      "accessibility {}",
+      if self.accessibility.enabled {
+        "on"
+      } else {
+        "off"
+      }
+    );
+    self.apply_contrast();
+  }
+
+  /// The actions `event` triggers, along with long presses that became due
+  /// since. The event is still meant for everything else as well.
+  pub(crate) fn input(&mut self, event: &WindowEvent) -> Vec<Action> {
+    // Anything may change what's shown, like a touch or a resize
+    self.dirty = true;
+    if let WindowEvent::Focused(false) = event {
+      // Nothing held is released while the window doesn't see it
+      self.buttons = self.bindings.buttons();
+    }
+    let now = self.now();
+    match self.triggers.translate(event, now) {
+      Some(TriggerEvent::Press(trigger)) if trigger.is_button() => self.buttons.press(trigger, now),
+      Some(TriggerEvent::Press(trigger)) => self.bindings.get(trigger).into_iter().collect(),
+      // Holding a toggle's key would flip it back and forth
+      Some(TriggerEvent::Repeat(trigger)) => self.bindings.repeated(trigger).into_iter().collect(),
+      Some(TriggerEvent::Release(trigger)) => self.buttons.release(trigger, now),
+      None => self.buttons.poll(now),
+    }
+  }
+
+  /// Long presses of keys and mouse buttons held long enough, and presses
+  /// that weren't followed by a second one in time.
+  pub(crate) fn poll_buttons(&mut self) -> Vec<Action> {
+    self.buttons.poll(self.now())
+  }
+
+  /// When [`poll_buttons`](Self::poll_buttons) may trigger something next.
+  pub(crate) fn buttons_due(&self) -> Option<Instant> {
+    self.buttons.deadline().map(|due| self.wake_at(due))
+  }
+
+  /// The clock's time as an instant, for the timers of buttons, gestures
+  /// and the ignition, so they run with the clock like animations do and
+  /// replays time them the same.
+  pub(crate) fn now(&self) -> Instant {
+    self.epoch + self.clock.now()
+  }
+
+  /// When to wake up for `due`, an instant on the clock, see
+  /// [`now`](Self::now).
+  pub(crate) fn wake_at(&self, due: Instant) -> Instant {
+    wake_at(&*self.clock, self.epoch, due)
+  }
+
+  /// The action a gesture or other trigger is bound to, if any.
+  pub(crate) fn bound(&self, trigger: Trigger) -> Option<Action> {
+    self.bindings.get(trigger)
+  }
+
+  pub(crate) fn set_bindings(&mut self, bindings: Bindings) {
+    self.buttons = bindings.buttons();
+    self.bindings = bindings;
+  }
+
+  /// Values of the data sources so far.
+  pub(crate) fn telemetry(&self) -> &Telemetry {
+    &self.telemetry
+  }
+
+  /// Replaces the telemetry, e.g. from a snapshot. Sources go on merging
+  /// into it.
+  pub(crate) fn set_telemetry(&mut self, telemetry: Telemetry) {
+    self.telemetry = telemetry;
+    self.updated.clear();
+    mark_updated(&mut self.updated, &self.telemetry, self.clock.now());
+    self.dirty = true;
+    self.set_telltales();
+  }
+
+  pub(crate) fn update(&mut self) {
+    let started = Instant::now();
+    self.clock.tick();
+    let now = self.clock.now();
+    self.delta = now - self.last_update;
+    self.last_update = now;
+    tracing::trace!(delta = ?self.delta, "update");
+    self.poll_sources();
+    if self.rules.update(&self.telemetry, self.delta) {
+      self.set_telltales();
+      self.dirty = true;
+    }
+    // Until the condition held long enough, even without new telemetry
+    self.dirty |= self.rules.is_pending();
+    self.update_stale(now);
+    self.update_shift(now);
+    self.update_palette();
+    self.stats.update_time = started.elapsed();
+  }
+
+  /// Marks the fields not reported for longer than `stale_after` as stale,
+  /// drawing again if that changed.
+  fn update_stale(&mut self, now: Duration) {
+    let stale = match self.stale_after {
+      Some(after) => self
+        .updated
+        .iter()
+        .filter(|(_, updated)| now.saturating_sub(**updated) > after)
+        .map(|(field, _)| *field)
+        .collect(),
+      None => HashSet::new(),
+    };
+    if stale != self.stale {
+      for field in stale.difference(&self.stale) {
+        tracing::warn!("{} is stale", field.name());
+      }
+      self.stale = stale;
+      self.dirty = true;
+    }
+  }
+
+  pub(crate) fn set_stale_after(&mut self, stale_after: Option<Duration>) {
+    self.stale_after = stale_after;
+  }
+
+  /// The scale of the UI, see [`Frame::scale`].
+  pub(crate) fn scale(&self) -> f32 {
+    self.scale_factor as f32 * self.ui_scale
+  }
+
+  /// Follows the window to a display with another scale factor.
+  pub(crate) fn set_scale_factor(&mut self, scale_factor: f64) {
+    if scale_factor != self.scale_factor {
+      tracing::info!("scale factor {}", scale_factor);
+      self.scale_factor = scale_factor;
+      self.dirty = true;
+    }
+  }
+
+  pub(crate) fn set_ui_scale(&mut self, ui_scale: f32) {
+    self.ui_scale = ui_scale;
+    self.dirty = true;
+  }
+
+  /// Moves the layout on to its next burn-in position once it's time,
+  /// which also dims what showed the same for long enough by then.
+  fn update_shift(&mut self, now: Duration) {
+    let step = self.burn_in.map_or(0, |burn_in| burn_in.step(now));
+    if step != self.shift_step {
+      self.shift_step = step;
+      self.dirty = true;
+    }
+  }
+
+  pub(crate) fn set_burn_in(&mut self, burn_in: Option<BurnIn>) {
+    self.burn_in = burn_in;
+    self.dirty = true;
+  }
+
+  pub(crate) fn set_safe_area(&mut self, safe_area: SafeArea) {
+    self.safe_area = safe_area;
+    self.dirty = true;
+  }
+
+  /// Lights the telltales the data sources reported and those of active
+  /// rules.
+  fn set_telltales(&mut self) {
+    let lit = self.rules.light(self.telemetry.telltales);
+    self.safety.set_telltales(&self.queue, lit);
+  }
+
+  /// Merges telemetry received since the last call, which needs a redraw
+  /// if there is any.
+  pub(crate) fn poll_sources(&mut self) {
+    let now = self.clock.now();
+    for source in &mut self.sources {
+      while let Ok((name, mut update)) = source.try_recv() {
+        self.arbiter.arbitrate(&name, &mut update, now);
+        self.signals.process(&mut update, now);
+        self.diagnostics.record(&name, &update, now);
+        self.telemetry.merge(&update);
+        mark_updated(&mut self.updated, &update, now);
+        self.dirty = true;
+      }
+    }
+    self.set_telltales();
+  }
+
+  /// Draws a frame and returns the stats collected while producing it.
+  pub(crate) fn render(
+    &mut self,
+    draw: &mut dyn FnMut(&mut Frame),
+  ) -> Result<FrameStats, SurfaceError> {
+    let output = self
+      .surface
+      .as_ref()
+      .ok_or(SurfaceError::Lost)?
+      .get_current_texture()?;
+    let view = output
+      .texture
+      .create_view(&TextureViewDescriptor::default());
+    let stats = self.draw_into(&view, draw, false);
+    output.present();
+    self.lost_frames = 0;
+    Ok(stats)
+  }
+
+  /// Configures the surface again after [`render`](Self::render) found it
+  /// lost, writing a crash report once it keeps getting lost, like after
+  /// the GPU was reset.
+  pub(crate) fn surface_lost(&mut self) {
+    self.lost_frames += 1;
+    if self.lost_frames == LOST_FRAMES {
+      tracing::error!("surface lost {} frames in a row", LOST_FRAMES);
+      if let Some(crash) = &self.crash {
+        crash.write(&format!("surface lost {} frames in a row", LOST_FRAMES));
+      }
+    }
+    self.resize(self.size);
+  }
+
+  /// Draws a frame like [`render`](Self::render), but into a texture
+  /// that is never shown, waiting for the GPU to finish it.
+  pub(crate) fn render_offscreen(&mut self, draw: &mut dyn FnMut(&mut Frame)) -> FrameStats {
+    let (width, height, format) = (self.config.width, self.config.height, self.config.format);
+    let texture = self.offscreen.get_or_insert_with(|| {
+      self.device.create_texture(&TextureDescriptor {
+        label: Some("Offscreen"),
+        size: Extent3d {
+          width,
+          height,
+          depth_or_array_layers: 1,
+        },
+        mip_level_count: 1,
+        sample_count: 1,
+        dimension: TextureDimension::D2,
+        format,
+        usage: TextureUsages::RENDER_ATTACHMENT,
+      })
+    });
+    let view = texture.create_view(&TextureViewDescriptor::default());
+    let stats = self.draw_into(&view, draw, true);
+    self.device.poll(Maintain::Wait);
+    stats
+  }
+
+  /// Draws every pipeline in `variants` once into a texture that's never
+  /// shown, so the first frames drawing them don't wait for drivers to
+  /// compile them or glyphs to be rasterized. Doesn't count as a frame.
+  pub(crate) fn prewarm(&mut self, variants: &[Variant]) {
+    // Nothing of it goes to the stats, checksums or mirrored frames
+    let stats = std::mem::take(&mut self.stats);
+    let startup = self.startup.take();
+    let offscreen = self.offscreen.take();
+    let checksum = self.checksum.take();
+    let timer = self.timer.take();
+    let output = self.output.take();
+    let image = Image::from_rgba(1, 1, vec![0; 4]);
+    let mut cache = WidgetCache::new();
+    self.render_offscreen(&mut |frame| prewarm::draw(frame, variants, &image, &mut cache));
+    self.stats = stats;
+    self.startup = startup;
+    self.offscreen = offscreen;
+    self.checksum = checksum;
+    self.timer = timer;
+    self.output = output;
+    self.dirty = true;
+    self.startup_phase("prewarm");
+  }
+
+  /// Ends a phase of startup that happens outside of the renderer, like
+  /// loading the scene's assets. Does nothing once the first frame has
+  /// been drawn.
+  pub(crate) fn startup_phase(&mut self, name: &'static str) {
+    if let Some(startup) = &mut self.startup {
+      startup.phase(name);
+    }
+  }
+
+  /// How startup went, once after the first frame.
+  pub(crate) fn take_startup_report(&mut self) -> Option<StartupReport> {
+    self.startup_report.take()
+  }
+
+  /// Every pipeline and font there is, to [prewarm](Self::prewarm) without
+  /// a list of those drawn before.
+  pub(crate) fn registered_variants(&self) -> Vec<Variant> {
+    Variant::registered(&self.text)
+  }
+
+  /// Adds what's drawn from now on to the list of `recorder`.
+  pub(crate) fn record_variants(&mut self, recorder: VariantRecorder) {
+    self.text.record_queued();
+    self.variants = Some(recorder);
+  }
+
+  /// Draws a frame like [`render`](Self::render), but into a texture
+  /// that is read back as rows of RGBA pixels in sRGB.
+  pub(crate) fn screenshot(
+    &mut self,
+    draw: &mut dyn FnMut(&mut Frame),
+  ) -> Result<Vec<u8>, WindshieldError> {
+    let (width, height) = (self.config.width, self.config.height);
+    let extent = Extent3d {
+      width,
+      height,
+      depth_or_array_layers: 1,
+    };
+    let texture = self.device.create_texture(&TextureDescriptor {
+      label: Some("Screenshot"),
+      size: extent,
+      mip_level_count: 1,
+      sample_count: 1,
+      dimension: TextureDimension::D2,
+      format: self.config.format,
+      usage: TextureUsages::RENDER_ATTACHMENT | TextureUsages::COPY_SRC,
+    });
+    self.draw_into(
+      &texture.create_view(&TextureViewDescriptor::default()),
+      draw,
+      true,
+    );
+
+    // Rows of buffer copies have to be aligned
+    let row = width * 4;
+    let padded_row = row.div_ceil(COPY_BYTES_PER_ROW_ALIGNMENT) * COPY_BYTES_PER_ROW_ALIGNMENT;
+    let buffer = self.device.create_buffer(&BufferDescriptor {
+      label: Some("Screenshot"),
+      size: padded_row as u64 * height as u64,
+      usage: BufferUsages::COPY_DST | BufferUsages::MAP_READ,
+      mapped_at_creation: false,
+    });
+    let mut encoder = self
+      .device
+      .create_command_encoder(&CommandEncoderDescriptor {
+        label: Some("Screenshot Encoder"),
+      });
+    encoder.copy_texture_to_buffer(
+      texture.as_image_copy(),
+      ImageCopyBuffer {
+        buffer: &buffer,
+        layout: ImageDataLayout {
+          offset: 0,
+          bytes_per_row: NonZeroU32::new(padded_row),
+          rows_per_image: None,
+        },
+      },
+      extent,
+    );
+    self.queue.submit(std::iter::once(encoder.finish()));
+
+    let slice = buffer.slice(..);
+    let (sender, mapped) = std::sync::mpsc::channel();
+    slice.map_async(MapMode::Read, move |result| {
+      let _ = sender.send(result);
+    });
+    self.device.poll(Maintain::Wait);
+    mapped.recv().unwrap_or(Err(BufferAsyncError))?;
+    self.poll_output();
+
+    let padded = slice.get_mapped_range();
+    let mut pixels = Vec::with_capacity((row * height) as usize);
+    for chunk in padded.chunks(padded_row as usize) {
+      pixels.extend_from_slice(&chunk[..row as usize]);
+    }
+    Ok(pixels)
+  }
+
+  pub(crate) fn about_open(&self) -> bool {
+    self.about
+  }
+
+  pub(crate) fn toggle_about(&mut self) {
+    self.about = !self.about;
+    self.dirty = true;
+  }
+
+  pub(crate) fn diagnostics_open(&self) -> bool {
+    self.diagnostics.is_open()
+  }
+
+  pub(crate) fn toggle_diagnostics(&mut self) {
+    self.diagnostics.toggle();
+    self.dirty = true;
+  }
+
+  /// Passes a window event to the diagnostics screen, returning whether it
+  /// used it so the HUD shouldn't.
+  pub(crate) fn diagnostics_event(&mut self, event: &WindowEvent) -> bool {
+    let used = self.diagnostics.event(event);
+    self.dirty |= used;
+    used
+  }
+
+  /// Scrolls or hides the diagnostics screen with a navigation `action`.
+  pub(crate) fn diagnostics_action(&mut self, action: Action) {
+    self.diagnostics.action(action);
+  }
+
+  pub(crate) fn logs_open(&self) -> bool {
+    self.logs.is_open()
+  }
+
+  pub(crate) fn toggle_logs(&mut self) {
+    self.logs.toggle();
+    self.dirty = true;
+  }
+
+  /// Passes a window event to the log overlay, returning whether it used
+  /// it so the HUD shouldn't.
+  pub(crate) fn logs_event(&mut self, event: &WindowEvent) -> bool {
+    let used = self.logs.event(event);
+    self.dirty |= used;
+    used
+  }
+
+  /// Scrolls, filters or hides the log overlay with a navigation `action`.
+  pub(crate) fn logs_action(&mut self, action: Action) {
+    self.logs.action(action);
+  }
+
+  /// Shows or hides the inspector, if there is a window for it.
+  #[cfg(feature = "inspector")]
+  pub(crate) fn toggle_inspector(&mut self) {
+    if let Some(inspector) = &mut self.inspector {
+      inspector.toggle();
+      self.dirty = true;
+    }
+  }
+
+  /// Passes a window event to the inspector, returning whether it used it
+  /// so the HUD shouldn't.
+  #[cfg(feature = "inspector")]
+  pub(crate) fn inspector_event(&mut self, event: &WindowEvent) -> bool {
+    let used = self
+      .inspector
+      .as_mut()
+      .is_some_and(|inspector| inspector.event(event));
+    self.dirty |= used;
+    used
+  }
+
+  /// Builds the inspector for the next frame, listing `scene`.
+  #[cfg(feature = "inspector")]
+  pub(crate) fn inspect(&mut self, window: &Window, scene: Option<&mut Scene>) {
+    if let Some(inspector) = &mut self.inspector {
+      inspector.run(window, scene, &self.telemetry);
+    }
+  }
+
+  /// Writes the last frame mirrored, if read back by now. Mirroring stops
+  /// if the file can't be written.
+  fn poll_output(&mut self) {
+    if let Some(Err(err)) = self.output.as_mut().map(|output| output.poll(&self.device)) {
+      tracing::error!("stopped mirroring frames: {err}");
+      self.output = None;
+    }
+  }
+
+  /// Builds the frame with `draw` and renders it into `target`, with every
+  /// image drawn uploaded if `complete`.
+  fn draw_into(
+    &mut self,
+    target: &TextureView,
+    draw: &mut dyn FnMut(&mut Frame),
+    complete: bool,
+  ) -> FrameStats {
+    let started = Instant::now();
+    if let Some(checksums) = self
+      .checksum
+      .as_mut()
+      .and_then(|checksum| checksum.poll(&self.device))
+    {
+      self.stats.checksums = checksums;
+    }
+    if let Some(timer) = &mut self.timer {
+      self.stats.gpu_time = timer.poll(&self.device);
+    }
+    self.poll_output();
+    let now = self.clock.now();
+    let scale = self.scale();
+    self.text.sizes = self.accessibility.text_sizes(scale);
+    let (width, height) = (self.config.width as f32, self.config.height as f32);
+    let mut safe_area = self.safe_area.rect(width, height);
+    if let Some(burn_in) = &self.burn_in {
+      let [x, y] = burn_in.offset(self.shift_step);
+      safe_area.x += x;
+      safe_area.y += y;
+    }
+    let mut frame = Frame {
+      canvas: &mut self.canvas,
+      text: &mut self.text,
+      images: &mut self.images,
+      caches: &mut self.caches,
+      width: self.config.width,
+      height: self.config.height,
+      safe_area,
+      scale,
+      reduce_motion: self.reduce_motion || self.accessibility.reduces_motion(),
+      right_to_left: self.right_to_left,
+      delta: self.delta,
+      telemetry: &self.telemetry,
+      stale: &self.stale,
+      adapter: &self.adapter,
+      palette: &self.palette,
+      burn_in: self.burn_in,
+      animating: false,
+    };
+    // Blank while off
+    if self.power == Power::On {
+      draw(&mut frame);
+    }
+    // Even while off, they're for finding out why
+    if self.about {
+      frame.next_layer();
+      about::draw(&mut frame, &self.build);
+    }
+    if self.diagnostics.is_open() {
+      frame.next_layer();
+      self.diagnostics.draw(&mut frame, now);
+    }
+    if self.logs.is_open() {
+      frame.next_layer();
+      self.logs.draw(&mut frame);
+    }
+    self.safe_area.mask(&mut frame);
+    #[cfg(feature = "inspector")]
+    if let Some(rect) = self.inspector.as_ref().and_then(Inspector::highlight) {
+      frame.next_layer();
+      let style = Style::stroke([1.0, 0.0, 1.0, 1.0], 2.0);
+      frame
+        .canvas
+        .rect([rect.x, rect.y], [rect.width, rect.height], style);
+    }
+    // Animations need the next frame as well, and so do the diagnostics,
+    // the logs and the inspector showing what changes
+    self.dirty = frame.animating || self.diagnostics.is_open() || self.logs.is_open();
+    #[cfg(feature = "inspector")]
+    {
+      self.dirty |= self.inspector.as_ref().is_some_and(Inspector::is_open);
+    }
+    let now = Instant::now();
+    self.last_frame = now;
+    if let Some(limiter) = &mut self.limiter {
+      limiter.frame(now);
+    }
+    let (vertices, indices) = self.canvas.geometry();
+    self
+      .shapes
+      .geometry
+      .upload(&self.device, &self.queue, vertices, indices);
+    let layers = self.canvas.layers();
+    if let Some(recorder) = &mut self.variants {
+      let pipelines = [
+        (!indices.is_empty()).then_some(Variant::Shapes),
+        (!self.images.is_empty()).then_some(Variant::Images),
+        layers
+          .iter()
+          .any(|(_, layer)| layer.shows.is_some())
+          .then_some(Variant::Cached),
+      ];
+      let text = self.text.take_queued().into_iter().map(|(font, size)| {
+        let font = font.0;
+        Variant::Text { font, size }
+      });
+      recorder.record(pipelines.into_iter().flatten().chain(text));
+    }
+    self.canvas.clear();
+    self
+      .pictures
+      .prepare(&self.device, &self.queue, &mut self.images, complete);
+    // Images show up once uploaded
+    self.dirty |= self.pictures.uploading();
+    let size = [self.config.width, self.config.height];
+    self
+      .caches
+      .prepare(&self.device, &self.queue, size, &layers);
+
+    // With everything on it, the frame is mirrored from a texture of its own
+    let output_view = self.output.as_ref().map_or(target, |output| output.view());
+    // Rendered into a texture first if it needs warping, checksums or
+    // recoloring
+    let warp = self.warp.as_ref().filter(|_| {
+      !self.keystone.is_identity()
+        || self.checksum.is_some()
+        || self.simulated != ColorVision::Normal
+    });
+    let view = warp.map_or(output_view, |warp| warp.view());
+    let background = match self.power {
+      Power::On => self.background(),
+      Power::Off => clear_color(Color::TRANSPARENT, self.config.alpha_mode),
+    };
+    let mut encoder = self
+      .device
+      .create_command_encoder(&CommandEncoderDescriptor {
+        label: Some("Render Encoder"),
+      });
+    if let Some(timer) = &mut self.timer {
+      timer.start(&mut encoder);
+    }
+    // Each layer's images go above its shapes, its text above both and
+    // below the next layer
+    let mut cleared = HashSet::new();
+    for (index, (indices, layer)) in layers.into_iter().enumerate() {
+      let scissor = layer.clip.map(|clip| scissor(clip, size, self.mirror));
+      // Nothing of it shows, but the first layer still clears the frame
+      let hidden = scissor.is_some_and(|[_, _, width, height]| width == 0 || height == 0);
+      // Caches are cleared by the first layer drawing into them
+      let cache = layer.cache.and_then(|id| Some((id, self.caches.view(id)?)));
+      let (target, load) = match cache {
+        Some((id, cache)) if cleared.insert(id) => (cache, LoadOp::Clear(Color::TRANSPARENT)),
+        Some((_, cache)) => (cache, LoadOp::Load),
+        None if index == 0 => (view, LoadOp::Clear(background)),
+        None => (view, LoadOp::Load),
+      };
+      {
+        let mut render_pass = encoder.begin_render_pass(&RenderPassDescriptor {
+          label: Some("Render Pass"),
+          color_attachments: &[Some(RenderPassColorAttachment {
+            view: target,
+            resolve_target: None,
+            ops: Operations { load, store: true },
+          })],
+          depth_stencil_attachment: None,
+        });
+
+        if hidden {
+          continue;
+        }
+        if let Some([x, y, width, height]) = scissor {
+          render_pass.set_scissor_rect(x, y, width, height);
+        }
+        if let Some((id, _)) = layer.shows {
+          self.caches.draw(&mut render_pass, id, &mut self.stats);
+        }
+        self.shapes.draw(&mut render_pass, indices, &mut self.stats);
+        self.pictures.draw(&mut render_pass, index, &mut self.stats);
+      }
+      let target = TextTarget {
+        view: target,
+        size,
+        scissor,
+      };
+      self
+        .text
+        .draw(&self.device, &mut encoder, target, index, &mut self.stats);
+    }
+    self.text.finish();
+    if let Some(warp) = warp {
+      if let Some(checksum) = &mut self.checksum {
+        let size = [self.config.width, self.config.height];
+        checksum.place(&self.queue, size, self.mirror);
+        checksum.encode(&self.device, &mut encoder, warp.view(), &mut self.stats);
+      }
+      warp.draw(&mut encoder, output_view, background, &mut self.stats);
+    }
+
+    let safety = (self.power == Power::On)
+      .then(|| {
+        self
+          .safety
+          .encode(&self.device, output_view, &mut self.stats)
+      })
+      .flatten();
+    let output = self.output.as_mut().map(|output| {
+      let mut encoder = self
+        .device
+        .create_command_encoder(&CommandEncoderDescriptor {
+          label: Some("Output Encoder"),
+        });
+      output.encode(&mut encoder, target, &mut self.stats);
+      encoder.finish()
+    });
+    // On the window only, after everything else
+    #[cfg(feature = "inspector")]
+    let inspector = self.inspector.as_mut().map(|inspector| {
+      let mut encoder = self
+        .device
+        .create_command_encoder(&CommandEncoderDescriptor {
+          label: Some("Inspector Encoder"),
+        });
+      let size = [self.config.width, self.config.height];
+      let callbacks = inspector.encode(&self.device, &self.queue, &mut encoder, target, size);
+      callbacks
+        .into_iter()
+        .chain(std::iter::once(encoder.finish()))
+    });
+    #[cfg(not(feature = "inspector"))]
+    let inspector: Option<std::iter::Empty<_>> = None;
+    // Ends after the telltales, which are submitted on their own
+    let timing = self.timer.as_mut().map(|timer| {
+      let mut encoder = self
+        .device
+        .create_command_encoder(&CommandEncoderDescriptor {
+          label: Some("Timing Encoder"),
+        });
+      timer.end(&mut encoder);
+      encoder.finish()
+    });
+
+    // submit will accept anything that implements IntoIter
+    self.queue.submit(
+      std::iter::once(encoder.finish())
+        .chain(safety)
+        .chain(output)
+        .chain(inspector.into_iter().flatten())
+        .chain(timing),
+    );
+    if let Some(checksum) = &mut self.checksum {
+      checksum.submitted();
+    }
+    if let Some(timer) = &mut self.timer {
+      timer.submitted();
+    }
+    if let Some(output) = &mut self.output {
+      output.submitted();
+    }
+    self.stats.encode_time = started.elapsed();
+    self.text.recall();
+
+    if let Some(mut startup) = self.startup.take() {
+      startup.phase("first frame");
+      let report = startup.finish();
+      match report.slowest() {
+        Some((phase, _)) if report.is_over_budget() => {
+          tracing::warn!("{}, over budget, slowest was {}", report, phase)
+        }
+        _ => tracing::info!("{}", report),
+      }
+      self.startup_report = Some(report);
+    }
+
+    tracing::trace!(stats = ?self.stats, "frame");
+    #[cfg(feature = "inspector")]
+    if let Some(inspector) = &mut self.inspector {
+      inspector.stats = self.stats.clone();
+    }
+    if let Some(crash) = &self.crash {
+      crash.set_last_frame(&self.stats);
+    }
+    let next = FrameStats {
+      frame: self.stats.frame + 1,
+      ..Default::default()
+    };
+
+    std::mem::replace(&mut self.stats, next)
+  }
+
+  /// What frames are cleared to, as the compositor expects it.
+  fn background(&self) -> Color {
+    background(
+      self.palette.background,
+      self.clear_color,
+      self.contrast(),
+      self.config.alpha_mode,
+    )
+  }
+}
+
+/// A handle to the GPUs of every backend, Vulkan, Metal, DX12, GL and
+/// WebGPU in the browser.
+fn instance() -> Instance {
+  Instance::new(Backends::all())
+}
+
+fn select_present_mode(supported: &[PresentMode], mode: PresentMode) -> PresentMode {
+  // The automatic modes fall back on their own
+  if supported.contains(&mode) || matches!(mode, PresentMode::AutoVsync | PresentMode::AutoNoVsync)
+  {
+    return mode;
+  }
+  tracing::warn!("present mode {:?} is not supported by the surface", mode);
+  PresentMode::Fifo
+}
+
+fn select_alpha_mode(supported: &[CompositeAlphaMode], settings: &Settings) -> CompositeAlphaMode {
+  if let Some(mode) = settings.alpha_mode {
+    if supported.contains(&mode) {
+      return mode;
+    }
+    tracing::warn!("alpha mode {:?} is not supported by the surface", mode);
+  }
+
+  if !settings.is_transparent() {
+    return CompositeAlphaMode::Auto;
+  }
+
+  // A transparent window only shows through if the compositor blends
+  // the surface with what's behind it.
+  [
+    CompositeAlphaMode::PreMultiplied,
+    CompositeAlphaMode::PostMultiplied,
+  ]
+  .into_iter()
+  .find(|mode| supported.contains(mode))
+  .unwrap_or_else(|| {
+    tracing::warn!("surface does not support alpha blending, window will be opaque");
+    CompositeAlphaMode::Auto
+  })
+}
+
+/// What frames are cleared to: the `clear` color if set, else the
+/// palette's `background`, black in high `contrast`.
+fn background(
+  [r, g, b, a]: [f32; 4],
+  clear: Option<Color>,
+  contrast: bool,
+  alpha_mode: CompositeAlphaMode,
+) -> Color {
+  let color = clear.unwrap_or(Color {
+    r: r as f64,
+    g: g as f64,
+    b: b as f64,
+    a: a as f64,
+  });
+  let color = if contrast {
+    Color {
+      r: 0.0,
+      g: 0.0,
+      b: 0.0,
+      ..color
+    }
+  } else {
+    color
+  };
+  clear_color(color, alpha_mode)
+}
+
+/// When to wake up for `due`, an instant on `clock` counted from `epoch`.
+/// Clocks that don't keep up with the wall clock just wake up again, and
+/// those only going on with frames, like a stepped one, right away, so
+/// frames are drawn until it's due.
+fn wake_at(clock: &dyn Clock, epoch: Instant, due: Instant) -> Instant {
+  let now = Instant::now();
+  if clock.runs_between_frames() {
+    now + due.saturating_duration_since(epoch + clock.now())
+  } else {
+    now
+  }
+}
+
+/// Converts a straight alpha color into what the compositor expects.
+fn clear_color(color: Color, alpha_mode: CompositeAlphaMode) -> Color {
+  match alpha_mode {
+    CompositeAlphaMode::PreMultiplied => Color {
+      r: color.r * color.a,
+      g: color.g * color.a,
+      b: color.b * color.a,
+      a: color.a,
+    },
+    _ => color,
+  }
+}
+
+/// The pixels of a target `size` big that show what's drawn in `clip`
+/// once mirrored, as x, y, width and height.
+fn scissor(clip: Rect, [width, height]: [u32; 2], mirror: Mirror) -> [u32; 4] {
+  let size = [width as f32, height as f32];
+  let [x0, y0] = mirror.apply([clip.x, clip.y], size);
+  let [x1, y1] = mirror.apply([clip.x + clip.width, clip.y + clip.height], size);
+  let left = x0.min(x1).clamp(0.0, size[0]).floor() as u32;
+  let top = y0.min(y1).clamp(0.0, size[1]).floor() as u32;
+  let right = x0.max(x1).clamp(0.0, size[0]).ceil() as u32;
+  let bottom = y0.max(y1).clamp(0.0, size[1]).ceil() as u32;
+  [left, top, right - left, bottom - top]
+}
+
+/// Notes that the fields `update` has values for were reported at `now`.
+fn mark_updated(updated: &mut HashMap<Field, Duration>, update: &Telemetry, now: Duration) {
+  for field in Field::ALL {
+    if update.get(field).is_some() {
+      updated.insert(field, now);
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::input_map::{ButtonBinding, Timing};
+
+  #[test]
+  fn clears_to_black_in_forced_high_contrast() {
+    let profile = Accessibility {
+      enabled: true,
+      ..Default::default()
+    };
+    let day = [0.1, 0.2, 0.3, 1.0];
+    let contrast = profile.forces_high_contrast();
+    let color = background(day, None, contrast, CompositeAlphaMode::Opaque);
+    assert_eq!(color, Color::BLACK);
+    let color = background(day, None, false, CompositeAlphaMode::Opaque);
+    assert_eq!(color.b, 0.3f32 as f64);
+  }
+
+  #[test]
+  fn long_presses_go_by_frames_under_a_stepped_clock() {
+    let mut clock = SteppedClock::new(DETERMINISTIC_STEP);
+    let epoch = Instant::now();
+    let mut binding = ButtonBinding::new(vec!["ENTER"]);
+    binding.long_press = Some(Action::Back);
+    let mut buttons = ButtonMapper::new(vec![binding], Timing::default());
+    buttons.press("ENTER", epoch + clock.now());
+    let mut frames = 0;
+    while buttons.poll(epoch + clock.now()).is_empty() {
+      // Due right away for the next frame to step the clock, as it stands
+      // still until then
+      let due = buttons.deadline().unwrap();
+      assert!(wake_at(&clock, epoch, due) <= Instant::now());
+      clock.tick();
+      frames += 1;
+      assert!(frames <= 30, "long press still pending");
+    }
+    assert_eq!(frames, 30);
+    assert_eq!(buttons.deadline(), None);
+  }
+}
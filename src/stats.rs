@@ -0,0 +1,148 @@
+use std::sync::mpsc::{self, Receiver, TryRecvError};
+use std::time::Duration;
+
+use wgpu::{
+  Buffer, BufferAsyncError, BufferDescriptor, BufferUsages, CommandEncoder, Device, Maintain,
+  MapMode, QuerySet, QuerySetDescriptor, QueryType, Queue,
+};
+
+use crate::checksum::RegionChecksum;
+
+/// Timings and counters collected while producing a single frame.
+#[derive(Clone, Debug, Default)]
+pub struct FrameStats {
+  /// Index of the frame, starting at zero.
+  pub frame: u64,
+  /// CPU time spent in `update`.
+  pub update_time: Duration,
+  /// CPU time spent recording and submitting command buffers.
+  pub encode_time: Duration,
+  /// GPU time of a frame read back while producing this one, usually of
+  /// the frame before. `None` if the adapter doesn't support timestamp
+  /// queries or the GPU isn't done with one yet.
+  pub gpu_time: Option<Duration>,
+  /// Draws of every pass, shapes, text, warping, telltales and checksums.
+  pub draw_calls: u32,
+  pub triangles: u32,
+  /// Bind groups with a texture set for a draw, like the glyph atlas for
+  /// text and the frame for warping.
+  pub texture_binds: u32,
+  /// Checksums of the regions in
+  /// [`Settings::checksum_regions`](crate::Settings::checksum_regions) read
+  /// back while producing this frame, usually of the frame before. Empty
+  /// if none were configured or the GPU isn't done with them yet.
+  pub checksums: Vec<RegionChecksum>,
+}
+
+/// Measures how long the GPU takes for frames with timestamp queries, one
+/// frame at a time while the last one is read back.
+pub(crate) struct GpuTimer {
+  queries: QuerySet,
+  resolved: Buffer,
+  readback: Buffer,
+  /// Nanoseconds per timestamp tick.
+  period: f32,
+  started: bool,
+  copied: bool,
+  mapping: Option<Receiver<Result<(), BufferAsyncError>>>,
+}
+
+impl GpuTimer {
+  /// Two 64 bit timestamps, the start and the end of the frame.
+  const SIZE: u64 = 16;
+
+  /// Only available if the device was created with
+  /// [`Features::TIMESTAMP_QUERY`](wgpu::Features::TIMESTAMP_QUERY).
+  pub(crate) fn new(device: &Device, queue: &Queue) -> Self {
+    let buffer = |label, usage| {
+      device.create_buffer(&BufferDescriptor {
+        label: Some(label),
+        size: Self::SIZE,
+        usage,
+        mapped_at_creation: false,
+      })
+    };
+    Self {
+      queries: device.create_query_set(&QuerySetDescriptor {
+        label: Some("Frame Timestamps"),
+        ty: QueryType::Timestamp,
+        count: 2,
+      }),
+      resolved: buffer(
+        "Frame Timestamps",
+        BufferUsages::COPY_DST | BufferUsages::COPY_SRC,
+      ),
+      readback: buffer(
+        "Frame Timestamp Readback",
+        BufferUsages::MAP_READ | BufferUsages::COPY_DST,
+      ),
+      period: queue.get_timestamp_period(),
+      started: false,
+      copied: false,
+      mapping: None,
+    }
+  }
+
+  /// Records the start of a frame, unless the last one is still being
+  /// read back.
+  pub(crate) fn start(&mut self, encoder: &mut CommandEncoder) {
+    if self.mapping.is_some() {
+      return;
+    }
+    encoder.write_timestamp(&self.queries, 0);
+    self.started = true;
+  }
+
+  /// Records the end of the frame started, to be submitted after it.
+  pub(crate) fn end(&mut self, encoder: &mut CommandEncoder) {
+    if !std::mem::take(&mut self.started) {
+      return;
+    }
+    encoder.write_timestamp(&self.queries, 1);
+    encoder.resolve_query_set(&self.queries, 0..2, &self.resolved, 0);
+    encoder.copy_buffer_to_buffer(&self.resolved, 0, &self.readback, 0, Self::SIZE);
+    self.copied = true;
+  }
+
+  /// Starts reading back what [`end`](Self::end) copied, once its commands
+  /// are submitted.
+  pub(crate) fn submitted(&mut self) {
+    if !std::mem::take(&mut self.copied) {
+      return;
+    }
+    let (sender, mapped) = mpsc::channel();
+    self
+      .readback
+      .slice(..)
+      .map_async(MapMode::Read, move |result| {
+        let _ = sender.send(result);
+      });
+    self.mapping = Some(mapped);
+  }
+
+  /// How long the last frame read back took, if the GPU is done with one.
+  pub(crate) fn poll(&mut self, device: &Device) -> Option<Duration> {
+    let mapped = self.mapping.as_ref()?;
+    device.poll(Maintain::Poll);
+    let result = match mapped.try_recv() {
+      Err(TryRecvError::Empty) => return None,
+      Ok(result) => result,
+      Err(TryRecvError::Disconnected) => Err(BufferAsyncError),
+    };
+    self.mapping = None;
+    if let Err(err) = result {
+      tracing::warn!("unable to read back frame timestamps: {}", err);
+      return None;
+    }
+
+    let ticks = {
+      let timestamps = self.readback.slice(..).get_mapped_range();
+      let timestamp = |bytes: &[u8]| u64::from_le_bytes(bytes.try_into().expect("8 bytes"));
+      timestamp(&timestamps[8..16]).saturating_sub(timestamp(&timestamps[0..8]))
+    };
+    self.readback.unmap();
+    Some(Duration::from_nanos(
+      (ticks as f64 * self.period as f64) as u64,
+    ))
+  }
+}
@@ -0,0 +1,675 @@
+use std::cell::RefCell;
+use std::collections::{HashMap, HashSet};
+use std::ops::Range;
+use std::path::Path;
+use std::rc::Rc;
+
+use wgpu::util::StagingBelt;
+use wgpu::{CommandEncoder, Device, TextureFormat, TextureView};
+use wgpu_glyph::ab_glyph::{Font, FontArc, FontRef, GlyphId, ScaleFont, VariableFont};
+use wgpu_glyph::{
+  orthographic_projection, GlyphBrush, GlyphBrushBuilder, GlyphCruncher, HorizontalAlign, Layout,
+  OwnedSection, OwnedText, Region, VerticalAlign,
+};
+
+pub use wgpu_glyph::ab_glyph::VariationAxis;
+pub use wgpu_glyph::FontId;
+
+use crate::accessibility::TextSizes;
+use crate::error::WindshieldError;
+use crate::lru::{content_hash, LruCache};
+use crate::mirror::{high_contrast, Mirror};
+use crate::stats::FrameStats;
+
+const DEFAULT_FONT: &[u8] = include_bytes!("../assets/fonts/DejaVuSans.ttf");
+
+/// Texts kept laid out, a few for every label of a busy page.
+const LAYOUT_CAPACITY: usize = 1024;
+/// Frames a text is kept laid out without being drawn.
+const LAYOUT_MAX_AGE: u64 = 120;
+
+/// Weight axis tag of variable fonts, usually ranging from 100 to 900.
+pub const WEIGHT: [u8; 4] = *b"wght";
+/// Width axis tag of variable fonts, in percent of the normal width.
+pub const WIDTH: [u8; 4] = *b"wdth";
+
+/// A variable font registered with [`TextRenderer::add_variable_font`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct VariableFontId(usize);
+
+/// A variable font with rounded axis values.
+type InstanceKey = (VariableFontId, Vec<([u8; 4], i32)>);
+
+/// Byte ranges of a text drawn with the same font, see
+/// [`TextRenderer::runs`].
+type Runs = Rc<[(FontId, Range<usize>)]>;
+
+/// Horizontal alignment of text relative to its position.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Align {
+  #[default]
+  Left,
+  Center,
+  Right,
+}
+
+/// Vertical alignment of text relative to its position.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum VAlign {
+  #[default]
+  Top,
+  Center,
+  Bottom,
+}
+
+/// A border drawn around every glyph.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Outline {
+  /// Linear RGBA in straight alpha.
+  pub color: [f32; 4],
+  /// Thickness in physical pixels.
+  pub width: f32,
+}
+
+/// A copy of the text drawn behind it, offset from it.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Shadow {
+  /// Linear RGBA in straight alpha.
+  pub color: [f32; 4],
+  /// Offset in physical pixels, positive values go right and down.
+  pub offset: [f32; 2],
+}
+
+/// A horizontal band outside of which text is hidden, fading out over
+/// `fade` pixels towards both edges.
+///
+/// Glyphs are faded as a whole by the position of their center, so a hard
+/// edge without fade hides glyphs half way across it.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Clip {
+  pub left: f32,
+  pub right: f32,
+  pub fade: f32,
+}
+
+impl Clip {
+  /// Alpha multiplier of a glyph centered at `x`.
+  fn coverage(&self, x: f32) -> f32 {
+    let distance = (x - self.left).min(self.right - x);
+    if distance <= 0.0 {
+      0.0
+    } else if distance >= self.fade {
+      1.0
+    } else {
+      distance / self.fade
+    }
+  }
+}
+
+/// A glyph of a single line layout, see [`TextRenderer::line_glyphs`].
+pub(crate) struct LineGlyph {
+  /// Bytes of the text the glyph was made from.
+  pub(crate) range: Range<usize>,
+  /// The section's font or one of the fallbacks.
+  pub(crate) font: FontId,
+  pub(crate) id: GlyphId,
+  /// Start of the glyph relative to the start of the line.
+  pub(crate) x: f32,
+  pub(crate) advance: f32,
+}
+
+/// A run of text queued for drawing.
+#[derive(Clone, Debug)]
+pub struct TextSection {
+  pub text: String,
+  /// Anchor point in physical pixels, the origin is in the top left corner.
+  pub position: [f32; 2],
+  pub font: FontId,
+  /// Height of the text in physical pixels.
+  pub size: f32,
+  /// Linear RGBA in straight alpha.
+  pub color: [f32; 4],
+  pub align: Align,
+  pub valign: VAlign,
+  /// Width after which lines are wrapped, unbounded if unset.
+  pub max_width: Option<f32>,
+  pub outline: Option<Outline>,
+  pub shadow: Option<Shadow>,
+  /// Limits the text to a single line drawn inside the band.
+  pub clip: Option<Clip>,
+}
+
+impl TextSection {
+  /// White text in the default font, 32 pixels high.
+  pub fn new(text: impl Into<String>) -> Self {
+    Self {
+      text: text.into(),
+      position: [0.0, 0.0],
+      font: FontId::default(),
+      size: 32.0,
+      color: [1.0, 1.0, 1.0, 1.0],
+      align: Align::Left,
+      valign: VAlign::Top,
+      max_width: None,
+      outline: None,
+      shadow: None,
+      clip: None,
+    }
+  }
+
+  pub fn at(mut self, x: f32, y: f32) -> Self {
+    self.position = [x, y];
+    self
+  }
+
+  pub fn with_font(mut self, font: FontId) -> Self {
+    self.font = font;
+    self
+  }
+
+  pub fn with_size(mut self, size: f32) -> Self {
+    self.size = size;
+    self
+  }
+
+  pub fn with_color(mut self, color: [f32; 4]) -> Self {
+    self.color = color;
+    self
+  }
+
+  pub fn with_align(mut self, align: Align, valign: VAlign) -> Self {
+    self.align = align;
+    self.valign = valign;
+    self
+  }
+
+  pub fn with_max_width(mut self, max_width: f32) -> Self {
+    self.max_width = Some(max_width);
+    self
+  }
+
+  pub fn with_outline(mut self, color: [f32; 4], width: f32) -> Self {
+    self.outline = Some(Outline { color, width });
+    self
+  }
+
+  pub fn with_shadow(mut self, color: [f32; 4], offset: [f32; 2]) -> Self {
+    self.shadow = Some(Shadow { color, offset });
+    self
+  }
+
+  pub fn with_clip(mut self, left: f32, right: f32, fade: f32) -> Self {
+    self.clip = Some(Clip { left, right, fade });
+    self
+  }
+}
+
+/// Where [`TextRenderer::draw`] draws: `view`, `size` pixels big, and
+/// only into the pixels of `scissor` if given, as x, y, width and height.
+pub(crate) struct TextTarget<'a> {
+  pub view: &'a TextureView,
+  pub size: [u32; 2],
+  pub scissor: Option<[u32; 4]>,
+}
+
+/// Rasterizes glyphs into an atlas and draws queued sections on top of the
+/// frame. A default font is always loaded as [`FontId(0)`](FontId).
+///
+/// Characters a section's font has no glyph for are drawn with the first
+/// of the [fallbacks](Self::set_fallbacks) that has one. Which fonts a text
+/// is drawn with and where its glyphs go on a line are kept for texts
+/// drawn again in the next couple of seconds.
+pub struct TextRenderer {
+  brush: GlyphBrush<()>,
+  staging_belt: StagingBelt,
+  fallbacks: Vec<FontId>,
+  // Fonts registered by name, see `register_font`
+  names: HashMap<String, FontId>,
+  variable_fonts: Vec<FontRef<'static>>,
+  // Every distinct set of axis values is its own font to the glyph brush
+  instances: HashMap<InstanceKey, FontId>,
+  // By the hash of the font and text, see `runs`
+  runs: RefCell<LruCache<Runs>>,
+  // By the hash of the font, size and text, see `line_glyphs`
+  lines: RefCell<LruCache<(Rc<[LineGlyph]>, f32)>>,
+  pub(crate) mirror: Mirror,
+  pub(crate) high_contrast: bool,
+  // Of the accessibility profile
+  pub(crate) sizes: TextSizes,
+  opacity: f32,
+  // Fonts and sizes in whole pixels queued, if recorded, see
+  // `record_queued`
+  queued: Option<HashSet<(FontId, u32)>>,
+  // Sections queued this frame by layer, see `Frame::next_layer`
+  layers: Vec<Vec<OwnedSection>>,
+}
+
+impl TextRenderer {
+  pub(crate) fn new(device: &Device, format: TextureFormat) -> Self {
+    let font = FontArc::try_from_slice(DEFAULT_FONT).expect("bundled font is valid");
+    Self {
+      brush: GlyphBrushBuilder::using_font(font).build(device, format),
+      staging_belt: StagingBelt::new(1024),
+      fallbacks: Vec::new(),
+      names: HashMap::new(),
+      variable_fonts: Vec::new(),
+      instances: HashMap::new(),
+      runs: RefCell::new(LruCache::new(LAYOUT_CAPACITY, LAYOUT_MAX_AGE)),
+      lines: RefCell::new(LruCache::new(LAYOUT_CAPACITY, LAYOUT_MAX_AGE)),
+      mirror: Mirror::None,
+      high_contrast: false,
+      sizes: TextSizes::default(),
+      opacity: 1.0,
+      queued: None,
+      layers: vec![Vec::new()],
+    }
+  }
+
+  /// Multiplies the alpha of all text queued from now on, like
+  /// [`Canvas::set_opacity`](crate::canvas::Canvas::set_opacity).
+  pub fn set_opacity(&mut self, opacity: f32) {
+    self.opacity = opacity.clamp(0.0, 1.0);
+  }
+
+  pub fn opacity(&self) -> f32 {
+    self.opacity
+  }
+
+  /// Registers a TrueType/OpenType font from memory.
+  pub fn add_font(&mut self, data: Vec<u8>) -> Result<FontId, WindshieldError> {
+    let font = FontArc::try_from_vec(data).map_err(|_| WindshieldError::InvalidFont)?;
+    Ok(self.brush.add_font(font))
+  }
+
+  /// Registers a TrueType/OpenType font file.
+  pub fn load_font(&mut self, path: impl AsRef<Path>) -> Result<FontId, WindshieldError> {
+    let path = path.as_ref();
+    let data = std::fs::read(path).map_err(|source| WindshieldError::ReadFile {
+      path: path.to_path_buf(),
+      source,
+    })?;
+    self.add_font(data)
+  }
+
+  /// Registers a font from memory under `name`, by which configs can
+  /// refer to it, see [`Config::fonts`](crate::config::Config::fonts).
+  /// Registering another font under the same name replaces it for scenes
+  /// built from then on.
+  pub fn register_font(
+    &mut self,
+    name: impl Into<String>,
+    data: Vec<u8>,
+  ) -> Result<FontId, WindshieldError> {
+    let id = self.add_font(data)?;
+    self.names.insert(name.into(), id);
+    Ok(id)
+  }
+
+  /// The font last registered under `name`.
+  pub fn font_named(&self, name: &str) -> Option<FontId> {
+    self.names.get(name).copied()
+  }
+
+  /// Fonts tried in order for characters the font of a section has no
+  /// glyph for, e.g. a CJK font, then one with symbols, then one with
+  /// emoji. Only outlines are drawn, so emoji fonts need to have them,
+  /// like Noto Emoji, rather than color bitmaps.
+  pub fn set_fallbacks(&mut self, fonts: Vec<FontId>) {
+    self.fallbacks = fonts;
+    self.runs.get_mut().clear();
+    self.lines.get_mut().clear();
+  }
+
+  pub fn fallbacks(&self) -> &[FontId] {
+    &self.fallbacks
+  }
+
+  /// The font `c` is drawn with in a section in `font`.
+  fn resolve(&self, font: FontId, c: char) -> FontId {
+    // Spaces and line breaks don't break runs, whatever has a glyph for them
+    if self.fallbacks.is_empty() || c.is_whitespace() {
+      return font;
+    }
+    std::iter::once(font)
+      .chain(self.fallbacks.iter().copied())
+      .find(|id| self.font(*id).glyph_id(c).0 != 0)
+      .unwrap_or(font)
+  }
+
+  /// Byte ranges of `text` drawn with the same font, starting with `font`.
+  fn runs(&self, font: FontId, text: &str) -> Runs {
+    let key = content_hash(&(font, text));
+    let mut cache = self.runs.borrow_mut();
+    let runs = cache.get_or_insert_with(key, || self.resolve_runs(font, text).into());
+    Rc::clone(runs)
+  }
+
+  fn resolve_runs(&self, font: FontId, text: &str) -> Vec<(FontId, Range<usize>)> {
+    let mut runs: Vec<(FontId, Range<usize>)> = Vec::new();
+    for (start, c) in text.char_indices() {
+      let end = start + c.len_utf8();
+      let resolved = self.resolve(font, c);
+      match runs.last_mut() {
+        Some((last, range)) if *last == resolved => range.end = end,
+        _ => runs.push((resolved, start..end)),
+      }
+    }
+    runs
+  }
+
+  /// Registers an OpenType variable font, whose axes can then be set
+  /// per section with [`variation`](Self::variation).
+  ///
+  /// The font data is kept for the rest of the program's lifetime.
+  pub fn add_variable_font(&mut self, data: Vec<u8>) -> Result<VariableFontId, WindshieldError> {
+    let data: &'static [u8] = Box::leak(data.into_boxed_slice());
+    let font = FontRef::try_from_slice(data).map_err(|_| WindshieldError::InvalidFont)?;
+    if font.variations().is_empty() {
+      return Err(WindshieldError::InvalidFont);
+    }
+    self.variable_fonts.push(font);
+    Ok(VariableFontId(self.variable_fonts.len() - 1))
+  }
+
+  /// Axes supported by a variable font, with their ranges.
+  pub fn axes(&self, font: VariableFontId) -> Vec<VariationAxis> {
+    self.variable_fonts[font.0].variations()
+  }
+
+  /// The font to draw `font` with the given axis values, e.g.
+  /// `&[(WEIGHT, 650.0)]`. Axes not listed keep their default.
+  ///
+  /// Values are rounded to whole units so that animating an axis reuses a
+  /// bounded number of instances and their rasterized glyphs.
+  pub fn variation(&mut self, font: VariableFontId, axes: &[([u8; 4], f32)]) -> FontId {
+    let key = (
+      font,
+      axes
+        .iter()
+        .map(|(tag, value)| (*tag, value.round() as i32))
+        .collect::<Vec<_>>(),
+    );
+    if let Some(id) = self.instances.get(&key) {
+      return *id;
+    }
+
+    let mut instance = self.variable_fonts[font.0].clone();
+    for (tag, value) in &key.1 {
+      if !instance.set_variation(tag, *value as f32) {
+        tracing::warn!("variable font has no {} axis", String::from_utf8_lossy(tag));
+      }
+    }
+    let id = self.brush.add_font(FontArc::new(instance));
+    self.instances.insert(key, id);
+    id
+  }
+
+  pub(crate) fn font(&self, id: FontId) -> &FontArc {
+    &self.brush.fonts()[id.0]
+  }
+
+  /// Fonts registered, their ids counting up from zero.
+  pub(crate) fn font_count(&self) -> usize {
+    self.brush.fonts().len()
+  }
+
+  /// Starts recording the fonts and sizes of the sections queued, see
+  /// [`take_queued`](Self::take_queued).
+  pub(crate) fn record_queued(&mut self) {
+    self.queued.get_or_insert_with(HashSet::new);
+  }
+
+  /// The fonts and sizes queued since the last call, if recorded.
+  pub(crate) fn take_queued(&mut self) -> HashSet<(FontId, u32)> {
+    self.queued.as_mut().map(std::mem::take).unwrap_or_default()
+  }
+
+  /// Width of `section` laid out on a single line, in physical pixels.
+  pub fn line_width(&self, section: &TextSection) -> f32 {
+    self.line_glyphs(section).1
+  }
+
+  /// Lays `section` out on a single line starting at zero, ignoring its
+  /// position and alignment. Returns the glyphs and the total width.
+  pub(crate) fn line_glyphs(&self, section: &TextSection) -> (Rc<[LineGlyph]>, f32) {
+    let section = self.sizes.apply(section);
+    let key = content_hash(&(section.font, section.size.to_bits(), &section.text));
+    let mut cache = self.lines.borrow_mut();
+    let (glyphs, width) = cache.get_or_insert_with(key, || {
+      let (glyphs, width) = self.lay_out_line(&section);
+      (glyphs.into(), width)
+    });
+    (Rc::clone(glyphs), *width)
+  }
+
+  fn lay_out_line(&self, section: &TextSection) -> (Vec<LineGlyph>, f32) {
+    let mut glyphs = Vec::new();
+    let mut width = 0.0;
+    let mut previous = None;
+    for (start, c) in section.text.char_indices() {
+      let font_id = self.resolve(section.font, c);
+      let font = self.font(font_id);
+      let scaled = font.as_scaled(section.size);
+      let id = font.glyph_id(c);
+      // Kerning only applies within a font
+      if let Some((_, previous)) = previous.filter(|(font, _)| *font == font_id) {
+        width += scaled.kern(previous, id);
+      }
+      let advance = scaled.h_advance(id);
+      glyphs.push(LineGlyph {
+        range: start..start + c.len_utf8(),
+        font: font_id,
+        id,
+        x: width,
+        advance,
+      });
+      width += advance;
+      previous = Some((font_id, id));
+    }
+    (glyphs, width)
+  }
+
+  /// Queues a section to be drawn at the end of the current frame.
+  ///
+  /// Shadows and outlines are drawn as extra copies of the text behind it,
+  /// the outline by offsetting a copy in eight directions. That is cheap
+  /// since all copies share the rasterized glyphs, but outlines wider than
+  /// a few pixels start to look lumpy at the corners.
+  pub fn queue(&mut self, section: &TextSection) {
+    let section = &*self.sizes.apply(section);
+    if let Some(queued) = &mut self.queued {
+      queued.insert((section.font, section.size.round() as u32));
+    }
+    let [x, y] = section.position;
+    if let Some(shadow) = section.shadow {
+      let [dx, dy] = shadow.offset;
+      self.queue_copy(section, [x + dx, y + dy], shadow.color);
+      if let Some(outline) = section.outline {
+        self.queue_outline(section, [x + dx, y + dy], outline.width, shadow.color);
+      }
+    }
+    if let Some(outline) = section.outline {
+      self.queue_outline(section, [x, y], outline.width, outline.color);
+    }
+    self.queue_copy(section, [x, y], section.color);
+  }
+
+  fn queue_outline(
+    &mut self,
+    section: &TextSection,
+    position: [f32; 2],
+    width: f32,
+    color: [f32; 4],
+  ) {
+    for i in 0..8 {
+      let angle = i as f32 * std::f32::consts::FRAC_PI_4;
+      let offset = [
+        position[0] + width * angle.cos(),
+        position[1] + width * angle.sin(),
+      ];
+      self.queue_copy(section, offset, color);
+    }
+  }
+
+  fn queue_copy(&mut self, section: &TextSection, position: [f32; 2], color: [f32; 4]) {
+    let [r, g, b, a] = if self.high_contrast {
+      high_contrast(color)
+    } else {
+      color
+    };
+    let color = [r, g, b, a * self.opacity];
+    let h_align = match section.align {
+      Align::Left => HorizontalAlign::Left,
+      Align::Center => HorizontalAlign::Center,
+      Align::Right => HorizontalAlign::Right,
+    };
+    let v_align = match section.valign {
+      VAlign::Top => VerticalAlign::Top,
+      VAlign::Center => VerticalAlign::Center,
+      VAlign::Bottom => VerticalAlign::Bottom,
+    };
+
+    let text = |text: &str| {
+      OwnedText::new(text)
+        .with_font_id(section.font)
+        .with_scale(section.size)
+    };
+    let Some(clip) = section.clip else {
+      let texts = self
+        .runs(section.font, &section.text)
+        .iter()
+        .map(|(font, range)| {
+          text(&section.text[range.clone()])
+            .with_font_id(*font)
+            .with_color(color)
+        })
+        .collect();
+      self.push(OwnedSection {
+        screen_position: (position[0], position[1]),
+        bounds: (section.max_width.unwrap_or(f32::INFINITY), f32::INFINITY),
+        layout: Layout::default().h_align(h_align).v_align(v_align),
+        text: texts,
+      });
+      return;
+    };
+
+    // Every glyph gets its own alpha, laid out here rather than by the brush
+    let (glyphs, width) = self.line_glyphs(section);
+    let left = match section.align {
+      Align::Left => position[0],
+      Align::Center => position[0] - width / 2.0,
+      Align::Right => position[0] - width,
+    };
+    let texts = glyphs
+      .iter()
+      .map(|glyph| {
+        let center = left + glyph.x + glyph.advance / 2.0;
+        let [r, g, b, a] = color;
+        text(&section.text[glyph.range.clone()])
+          .with_font_id(glyph.font)
+          .with_color([r, g, b, a * clip.coverage(center)])
+      })
+      .collect();
+    self.push(OwnedSection {
+      screen_position: (left, position[1]),
+      bounds: (f32::INFINITY, f32::INFINITY),
+      layout: Layout::default_single_line().v_align(v_align),
+      text: texts,
+    });
+  }
+
+  fn push(&mut self, section: OwnedSection) {
+    self
+      .layers
+      .last_mut()
+      .expect("there is always a layer")
+      .push(section);
+  }
+
+  /// Whether nothing was queued in the current layer.
+  pub(crate) fn layer_is_empty(&self) -> bool {
+    self.layers.last().is_none_or(Vec::is_empty)
+  }
+
+  /// Starts a layer drawn above the previous ones.
+  pub(crate) fn next_layer(&mut self) {
+    self.layers.push(Vec::new());
+  }
+
+  /// Records drawing the text queued in `layer` this frame into `target`.
+  pub(crate) fn draw(
+    &mut self,
+    device: &Device,
+    encoder: &mut CommandEncoder,
+    target: TextTarget,
+    layer: usize,
+    stats: &mut FrameStats,
+  ) {
+    let TextTarget {
+      view,
+      size: [width, height],
+      scissor,
+    } = target;
+    let Some(sections) = self
+      .layers
+      .get(layer)
+      .filter(|sections| !sections.is_empty())
+    else {
+      return;
+    };
+    for section in sections {
+      let section = section.to_borrowed();
+      // A quad for every glyph, all in one instanced draw
+      stats.triangles += 2 * self.brush.glyphs(&section).count() as u32;
+      self.brush.queue(section);
+    }
+    stats.draw_calls += 1;
+    stats.texture_binds += 1;
+    let mut transform = orthographic_projection(width, height);
+    // Scale the rows producing clip space x and y, the matrix is column major
+    let [sx, sy] = self.mirror.scale();
+    for column in transform.chunks_mut(4) {
+      column[0] *= sx;
+      column[1] *= sy;
+    }
+    let drawn = match scissor {
+      Some([x, y, width, height]) => self.brush.draw_queued_with_transform_and_scissoring(
+        device,
+        &mut self.staging_belt,
+        encoder,
+        view,
+        transform,
+        Region {
+          x,
+          y,
+          width,
+          height,
+        },
+      ),
+      None => self.brush.draw_queued_with_transform(
+        device,
+        &mut self.staging_belt,
+        encoder,
+        view,
+        transform,
+      ),
+    };
+    if let Err(err) = drawn {
+      tracing::error!("unable to draw text: {}", err);
+    }
+  }
+
+  /// Finishes the frame once every layer is drawn.
+  pub(crate) fn finish(&mut self) {
+    self.staging_belt.finish();
+    self.runs.get_mut().next_generation();
+    self.lines.get_mut().next_generation();
+    self.layers.clear();
+    self.layers.push(Vec::new());
+  }
+
+  /// Reclaims staging buffers once the frame has been submitted.
+  pub(crate) fn recall(&mut self) {
+    self.staging_belt.recall();
+  }
+}
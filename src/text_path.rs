@@ -0,0 +1,157 @@
+use lyon::algorithms::measure::{PathMeasurements, SampleType};
+use lyon::geom::{Angle, Arc};
+use lyon::math::{point, vector, Point, Translation};
+use lyon::path::Path;
+use wgpu_glyph::ab_glyph::{self, Font, OutlineCurve, ScaleFont};
+
+use crate::canvas::Style;
+use crate::frame::Frame;
+use crate::text::{Align, TextSection};
+
+/// The curve a run of text follows.
+#[derive(Clone, Debug)]
+pub enum TextPath {
+  /// A circle around `center`, read clockwise. The text is anchored at
+  /// `angle` (radians, clockwise from the positive x axis) according to the
+  /// section's alignment, e.g. centered on it for [`Align::Center`].
+  Arc {
+    center: [f32; 2],
+    radius: f32,
+    angle: f32,
+  },
+  /// An arbitrary path, the text is anchored at its start, middle or end.
+  Path(Path),
+}
+
+impl Frame<'_> {
+  /// Draws `section` with every glyph placed and rotated along `path`, the
+  /// baseline sitting on the curve. `position`, `valign` and `max_width` of
+  /// the section are ignored, and so is `clip`.
+  ///
+  /// Glyphs are filled as outlines on the canvas, so they are layered with
+  /// the other shapes rather than with regular text.
+  pub fn text_on_path(&mut self, section: &TextSection, path: &TextPath) {
+    let (glyphs, width) = self.text.line_glyphs(section);
+
+    // Arcs become a path spanning exactly the text
+    let arc;
+    let (path, spans_text) = match path {
+      TextPath::Arc {
+        center,
+        radius,
+        angle,
+      } => {
+        let start = match section.align {
+          Align::Left => *angle,
+          Align::Center => angle - width / 2.0 / radius,
+          Align::Right => angle - width / radius,
+        };
+        arc = arc_path(*center, *radius, start, width / radius);
+        (&arc, true)
+      }
+      TextPath::Path(path) => (path, false),
+    };
+    let measurements = PathMeasurements::from_path(path, 0.1);
+    let mut sampler = measurements.create_sampler(path, SampleType::Distance);
+    let offset = match section.align {
+      _ if spans_text => 0.0,
+      Align::Left => 0.0,
+      Align::Center => (measurements.length() - width) / 2.0,
+      Align::Right => measurements.length() - width,
+    };
+
+    for glyph in glyphs.iter() {
+      let (x, advance) = (glyph.x, glyph.advance);
+      // Fonts differ in units per em
+      let font = self.text.font(glyph.font);
+      let scale = font.as_scaled(section.size).scale_factor();
+      let Some(outline) = font.outline(glyph.id) else {
+        continue;
+      };
+
+      // Position and direction of the curve at the glyph's center
+      let sample = sampler.sample(offset + x + advance / 2.0);
+      let center = sample.position();
+      let tangent = sample.tangent().normalize();
+      // Font units point up, screen coordinates down
+      let up = vector(tangent.y, -tangent.x);
+      let transform = |p: ab_glyph::Point| {
+        center + tangent * (p.x * scale.horizontal - advance / 2.0) + up * (p.y * scale.vertical)
+      };
+
+      let glyph = outline_path(&outline.curves, transform);
+      if let Some(shadow) = section.shadow {
+        let offset = vector(shadow.offset[0], shadow.offset[1]);
+        let shadow_path = glyph
+          .clone()
+          .transformed(&Translation::new(offset.x, offset.y));
+        if let Some(outline) = section.outline {
+          self.canvas.path(
+            &shadow_path,
+            Style::stroke(shadow.color, outline.width * 2.0),
+          );
+        }
+        self.canvas.path(&shadow_path, Style::fill(shadow.color));
+      }
+      // Half of the stroke is covered by the fill
+      if let Some(outline) = section.outline {
+        self
+          .canvas
+          .path(&glyph, Style::stroke(outline.color, outline.width * 2.0));
+      }
+      self.canvas.path(&glyph, Style::fill(section.color));
+    }
+  }
+}
+
+fn arc_path(center: [f32; 2], radius: f32, start: f32, sweep: f32) -> Path {
+  let arc = Arc {
+    center: point(center[0], center[1]),
+    radii: vector(radius, radius),
+    start_angle: Angle::radians(start),
+    sweep_angle: Angle::radians(sweep),
+    x_rotation: Angle::zero(),
+  };
+  let mut builder = Path::builder();
+  builder.begin(arc.from());
+  arc.for_each_quadratic_bezier(&mut |segment| {
+    builder.quadratic_bezier_to(segment.ctrl, segment.to);
+  });
+  builder.end(false);
+  builder.build()
+}
+
+fn outline_path(curves: &[OutlineCurve], transform: impl Fn(ab_glyph::Point) -> Point) -> Path {
+  let mut builder = Path::builder();
+  let mut last: Option<ab_glyph::Point> = None;
+  for curve in curves {
+    let (from, to) = match *curve {
+      OutlineCurve::Line(from, to)
+      | OutlineCurve::Quad(from, _, to)
+      | OutlineCurve::Cubic(from, _, _, to) => (from, to),
+    };
+    // Curves are listed one after another, a gap starts a new contour
+    if last != Some(from) {
+      if last.is_some() {
+        builder.end(true);
+      }
+      builder.begin(transform(from));
+    }
+    match *curve {
+      OutlineCurve::Line(_, to) => {
+        builder.line_to(transform(to));
+      }
+      OutlineCurve::Quad(_, ctrl, to) => {
+        builder.quadratic_bezier_to(transform(ctrl), transform(to));
+      }
+      OutlineCurve::Cubic(_, ctrl1, ctrl2, to) => {
+        builder.cubic_bezier_to(transform(ctrl1), transform(ctrl2), transform(to));
+      }
+    }
+    last = Some(to);
+  }
+  if last.is_some() {
+    builder.end(true);
+  }
+  builder.build()
+}
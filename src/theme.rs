@@ -0,0 +1,298 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+
+use crate::data::Telemetry;
+
+/// The colors widgets take theirs from, see
+/// [`Widget::set_palette`](crate::widgets::Widget::set_palette). Linear
+/// RGBA in straight alpha.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Palette {
+  pub background: [f32; 4],
+  /// Panels and gauge faces on top of the background.
+  pub surface: [f32; 4],
+  pub text: [f32; 4],
+  /// Secondary parts, like ticks, tracks and inactive indicators.
+  pub muted: [f32; 4],
+  /// What stands out, like needles, highlights and controls that are on.
+  pub accent: [f32; 4],
+  /// Redlines and destructive actions.
+  pub warning: [f32; 4],
+  /// Whether it's the palette for the night, for widgets with colors of
+  /// their own for it like [`MapView`](crate::widgets::MapView).
+  pub night: bool,
+}
+
+impl Palette {
+  /// Bright and saturated, to be readable in sunlight.
+  pub fn day() -> Self {
+    Self {
+      background: [0.1, 0.2, 0.3, 1.0],
+      surface: [0.12, 0.12, 0.14, 0.85],
+      text: [1.0, 1.0, 1.0, 1.0],
+      muted: [1.0, 1.0, 1.0, 0.5],
+      accent: [0.2, 0.6, 1.0, 1.0],
+      warning: [0.9, 0.1, 0.1, 0.9],
+      night: false,
+    }
+  }
+
+  /// Dim and warm on black, so it doesn't glare or ruin night vision.
+  pub fn night() -> Self {
+    Self {
+      background: [0.0, 0.0, 0.0, 1.0],
+      surface: [0.04, 0.03, 0.02, 0.85],
+      text: [0.7, 0.55, 0.4, 1.0],
+      muted: [0.6, 0.45, 0.3, 0.4],
+      accent: [0.8, 0.35, 0.1, 1.0],
+      warning: [0.6, 0.08, 0.05, 0.9],
+      night: true,
+    }
+  }
+
+  /// This palette with its accent and warning in colors that stay apart
+  /// for drivers with `vision`, dimmed at night.
+  pub fn for_color_vision(mut self, vision: ColorVision) -> Self {
+    if let Some((accent, warning)) = vision.alerts() {
+      let dim = if self.night { NIGHT_ALERT_DIM } else { 1.0 };
+      let adjust = |[r, g, b]: [f32; 3], alpha: f32| [r * dim, g * dim, b * dim, alpha];
+      self.accent = adjust(accent, self.accent[3]);
+      self.warning = adjust(warning, self.warning[3]);
+    }
+    self
+  }
+}
+
+impl Default for Palette {
+  fn default() -> Self {
+    Self::day()
+  }
+}
+
+/// Brightness of the alert colors for color blindness at night, relative
+/// to the day.
+const NIGHT_ALERT_DIM: f32 = 0.7;
+
+/// How drivers tell colors apart, for palettes keeping alerts apart and for
+/// previewing how the HUD looks to them, see
+/// [`Themes::color_vision`] and
+/// [`Settings::simulate_color_vision`](crate::Settings::simulate_color_vision).
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ColorVision {
+  #[default]
+  Normal,
+  /// Missing green cones, the most common: reds and greens look alike.
+  Deuteranopia,
+  /// Missing red cones: reds and greens look alike and reds dark.
+  Protanopia,
+  /// Missing blue cones: blues and greens look alike, as do yellows and
+  /// violets.
+  Tritanopia,
+}
+
+impl ColorVision {
+  /// The kind after this one when cycling through them.
+  pub fn next(self) -> Self {
+    match self {
+      Self::Normal => Self::Deuteranopia,
+      Self::Deuteranopia => Self::Protanopia,
+      Self::Protanopia => Self::Tritanopia,
+      Self::Tritanopia => Self::Normal,
+    }
+  }
+
+  /// Accent and warning colors told apart with it, by day, or `None` if
+  /// the palettes' own are.
+  fn alerts(self) -> Option<([f32; 3], [f32; 3])> {
+    // Blue and orange resp. teal and red from the Okabe-Ito palette
+    match self {
+      Self::Normal => None,
+      Self::Deuteranopia | Self::Protanopia => Some(([0.0, 0.45, 0.7], [0.9, 0.6, 0.0])),
+      Self::Tritanopia => Some(([0.0, 0.6, 0.5], [0.9, 0.1, 0.1])),
+    }
+  }
+
+  /// Row major matrix turning linear RGB into how it looks with this kind
+  /// of color blindness, after Machado, Oliveira and Fernandes (2009) at
+  /// full severity.
+  pub fn simulation(self) -> [[f32; 3]; 3] {
+    match self {
+      Self::Normal => [[1.0, 0.0, 0.0], [0.0, 1.0, 0.0], [0.0, 0.0, 1.0]],
+      Self::Deuteranopia => [
+        [0.367322, 0.860646, -0.227968],
+        [0.280085, 0.672501, 0.047413],
+        [-0.011820, 0.042940, 0.968881],
+      ],
+      Self::Protanopia => [
+        [0.152286, 1.052583, -0.204868],
+        [0.114503, 0.786281, 0.099216],
+        [-0.003882, -0.048116, 1.051998],
+      ],
+      Self::Tritanopia => [
+        [1.255528, -0.076749, -0.178779],
+        [-0.078411, 0.930809, 0.147602],
+        [0.004733, 0.691367, 0.303900],
+      ],
+    }
+  }
+
+  /// How `color` looks with this kind of color blindness.
+  pub fn simulate(self, [r, g, b, a]: [f32; 4]) -> [f32; 4] {
+    let [x, y, z] = self
+      .simulation()
+      .map(|row| (row[0] * r + row[1] * g + row[2] * b).clamp(0.0, 1.0));
+    [x, y, z, a]
+  }
+}
+
+/// Which palette is shown.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ThemeMode {
+  /// Night when it's dark around the vehicle: measured by a light sensor if
+  /// there is one, from the sun's position over the GPS position otherwise.
+  /// Day if neither is known.
+  #[default]
+  Auto,
+  Day,
+  Night,
+}
+
+impl ThemeMode {
+  /// The mode after this one when cycling through them.
+  pub fn next(self) -> Self {
+    match self {
+      Self::Auto => Self::Day,
+      Self::Day => Self::Night,
+      Self::Night => Self::Auto,
+    }
+  }
+}
+
+/// The day and night palettes and when to switch between them.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Themes {
+  pub mode: ThemeMode,
+  pub day: Palette,
+  pub night: Palette,
+  /// Draws the accent and warning of both palettes in colors that stay
+  /// apart for drivers with it, see [`Palette::for_color_vision`].
+  pub color_vision: ColorVision,
+  /// Lux below which it's night. It has to get twice as bright to be day
+  /// again, so passing street lights don't make the palette flicker.
+  pub night_below: f32,
+}
+
+impl Default for Themes {
+  fn default() -> Self {
+    Self {
+      mode: ThemeMode::Auto,
+      day: Palette::day(),
+      night: Palette::night(),
+      color_vision: ColorVision::Normal,
+      night_below: 50.0,
+    }
+  }
+}
+
+/// Sun elevation in degrees below which it's night, the sun's upper edge
+/// is just below the horizon then.
+const SUNSET_ELEVATION: f32 = -0.833;
+
+impl Themes {
+  /// Whether it's night for [`ThemeMode::Auto`], given whether it was
+  /// before.
+  pub fn is_night(&self, telemetry: &Telemetry, now: SystemTime, was_night: bool) -> bool {
+    match self.mode {
+      ThemeMode::Day => false,
+      ThemeMode::Night => true,
+      ThemeMode::Auto => {
+        if let Some(lux) = telemetry.illuminance {
+          let threshold = if was_night {
+            self.night_below * 2.0
+          } else {
+            self.night_below
+          };
+          return lux < threshold;
+        }
+        match (telemetry.latitude, telemetry.longitude) {
+          (Some(latitude), Some(longitude)) => {
+            sun_elevation(latitude, longitude, now) < SUNSET_ELEVATION
+          }
+          _ => false,
+        }
+      }
+    }
+  }
+
+  /// The palette shown, by night or day.
+  pub fn palette(&self, night: bool) -> Palette {
+    let palette = if night { self.night } else { self.day };
+    palette.for_color_vision(self.color_vision)
+  }
+}
+
+/// Degrees the sun is above the horizon at a place and time, accurate to
+/// about a degree, which is plenty to tell day from night.
+pub fn sun_elevation(latitude: f32, longitude: f32, time: SystemTime) -> f32 {
+  let seconds = time
+    .duration_since(UNIX_EPOCH)
+    .unwrap_or_default()
+    .as_secs_f64();
+  // Days since noon on 2000-01-01, in f64 as f32 can't hold them precisely
+  let days = seconds / 86400.0 - 10957.5;
+
+  let anomaly = (357.529 + 0.985_600_28 * days).to_radians();
+  let mean_longitude = 280.459 + 0.985_647_36 * days;
+  let ecliptic_longitude =
+    (mean_longitude + 1.915 * anomaly.sin() + 0.020 * (2.0 * anomaly).sin()).to_radians();
+  let obliquity = (23.439 - 0.000_000_36 * days).to_radians();
+
+  let declination = (obliquity.sin() * ecliptic_longitude.sin()).asin();
+  let right_ascension =
+    (obliquity.cos() * ecliptic_longitude.sin()).atan2(ecliptic_longitude.cos());
+  let sidereal_time = (18.697_374_558 + 24.065_709_824_419_08 * days) * 15.0;
+  let hour_angle = (sidereal_time + longitude as f64).to_radians() - right_ascension;
+
+  let latitude = (latitude as f64).to_radians();
+  let elevation = (latitude.sin() * declination.sin()
+    + latitude.cos() * declination.cos() * hour_angle.cos())
+  .asin();
+  elevation.to_degrees() as f32
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  /// How far apart the accent and warning of `palette` look with `vision`.
+  fn separation(palette: &Palette, vision: ColorVision) -> f32 {
+    let [accent, warning] = [palette.accent, palette.warning].map(|color| vision.simulate(color));
+    (0..3)
+      .map(|channel| (accent[channel] - warning[channel]).powi(2))
+      .sum::<f32>()
+      .sqrt()
+  }
+
+  #[test]
+  fn alert_palettes_stay_apart_for_color_blindness() {
+    assert_eq!(ColorVision::Normal.simulate([0.5; 4]), [0.5; 4]);
+    let gray = ColorVision::Deuteranopia.simulate([0.5, 0.5, 0.5, 1.0]);
+    assert!(gray[..3].iter().all(|channel| (channel - 0.5).abs() < 1e-3));
+
+    let night = Palette::night();
+    for vision in [ColorVision::Deuteranopia, ColorVision::Protanopia] {
+      let alerts = night.for_color_vision(vision);
+      assert!(separation(&alerts, vision) > 1.4 * separation(&night, vision));
+      assert_eq!(alerts.warning[3], night.warning[3]);
+    }
+    let themes = Themes {
+      color_vision: ColorVision::Tritanopia,
+      ..Default::default()
+    };
+    assert_ne!(themes.palette(false).accent, Palette::day().accent);
+    assert_eq!(themes.palette(false).background, Palette::day().background);
+  }
+}
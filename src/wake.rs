@@ -0,0 +1,45 @@
+#[cfg(target_arch = "wasm32")]
+use std::rc::Rc as Shared;
+#[cfg(not(target_arch = "wasm32"))]
+use std::sync::Arc as Shared;
+
+use winit::event_loop::EventLoopProxy;
+
+// Called from the threads input and data arrive on, except on the web
+// where there is only one
+#[cfg(not(target_arch = "wasm32"))]
+type Wake = dyn Fn() + Send + Sync;
+#[cfg(target_arch = "wasm32")]
+type Wake = dyn Fn();
+
+/// Wakes the event loop up when input or data arrived, which it would
+/// otherwise only see once it wakes up for the next frame, a whole
+/// [heartbeat](crate::Settings::heartbeat) later when idle.
+#[derive(Clone)]
+pub(crate) struct Waker(Shared<Wake>);
+
+impl Waker {
+  /// Sends the event loop of `proxy` an event for every wake up.
+  pub(crate) fn event_loop(proxy: EventLoopProxy<()>) -> Self {
+    #[cfg(not(target_arch = "wasm32"))]
+    let proxy = std::sync::Mutex::new(proxy);
+    Self(Shared::new(move || {
+      #[cfg(not(target_arch = "wasm32"))]
+      let Ok(proxy) = proxy.lock() else {
+        return;
+      };
+      // Only fails once the event loop is gone
+      let _ = proxy.send_event(());
+    }))
+  }
+
+  /// Wakes nothing, for input read without an event loop.
+  #[cfg(feature = "input-evdev")]
+  pub(crate) fn none() -> Self {
+    Self(Shared::new(|| {}))
+  }
+
+  pub(crate) fn wake(&self) {
+    (self.0)();
+  }
+}
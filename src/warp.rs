@@ -0,0 +1,432 @@
+use std::fmt;
+
+use bytemuck::{Pod, Zeroable};
+use serde::{Deserialize, Serialize};
+use wgpu::util::{BufferInitDescriptor, DeviceExt};
+use wgpu::{
+  AddressMode, BindGroup, BindGroupDescriptor, BindGroupEntry, BindGroupLayout,
+  BindGroupLayoutDescriptor, BindGroupLayoutEntry, BindingResource, BindingType, Buffer,
+  BufferBindingType, BufferUsages, Color, CommandEncoder, Device, Extent3d, FilterMode, LoadOp,
+  Operations, Queue, RenderPassColorAttachment, RenderPassDescriptor, RenderPipeline, Sampler,
+  SamplerBindingType, SamplerDescriptor, ShaderStages, Texture, TextureDescriptor,
+  TextureDimension, TextureFormat, TextureSampleType, TextureUsages, TextureView,
+  TextureViewDescriptor, TextureViewDimension,
+};
+
+use crate::canvas::Style;
+use crate::error::WindshieldError;
+use crate::frame::Frame;
+use crate::pipeline::{create_shader, PipelineBuilder};
+use crate::stats::FrameStats;
+use crate::theme::ColorVision;
+
+/// Where the corners of the frame end up on the screen, to cancel out the
+/// distortion of a curved or angled windshield.
+///
+/// Corners are top left, top right, bottom right and bottom left, in
+/// fractions of the screen's width and height. The frame is warped in
+/// perspective to fill the quad they span.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct Keystone {
+  pub corners: [[f32; 2]; 4],
+}
+
+impl Default for Keystone {
+  fn default() -> Self {
+    Self {
+      corners: [[0.0, 0.0], [1.0, 0.0], [1.0, 1.0], [0.0, 1.0]],
+    }
+  }
+}
+
+impl Keystone {
+  /// Whether the frame is shown as is.
+  pub fn is_identity(&self) -> bool {
+    *self == Self::default()
+  }
+
+  /// Row major projective transform from normalized frame to normalized
+  /// screen positions.
+  fn homography(&self) -> [[f32; 3]; 3] {
+    let [[x0, y0], [x1, y1], [x2, y2], [x3, y3]] = self.corners;
+    let (dx1, dx2, dx3) = (x1 - x2, x3 - x2, x0 - x1 + x2 - x3);
+    let (dy1, dy2, dy3) = (y1 - y2, y3 - y2, y0 - y1 + y2 - y3);
+    let det = dx1 * dy2 - dx2 * dy1;
+    let (g, h) = if det.abs() < f32::EPSILON {
+      (0.0, 0.0)
+    } else {
+      ((dx3 * dy2 - dx2 * dy3) / det, (dx1 * dy3 - dx3 * dy1) / det)
+    };
+    [
+      [x1 - x0 + g * x1, x3 - x0 + h * x3, x0],
+      [y1 - y0 + g * y1, y3 - y0 + h * y3, y0],
+      [g, h, 1.0],
+    ]
+  }
+
+  /// The inverse of [`homography`](Self::homography), mapping the screen
+  /// back onto the frame.
+  fn inverse(&self) -> [[f32; 3]; 3] {
+    let [[a, b, c], [d, e, f], [g, h, i]] = self.homography();
+    let det = a * (e * i - f * h) - b * (d * i - f * g) + c * (d * h - e * g);
+    let det = if det.abs() < f32::EPSILON { 1.0 } else { det };
+    [
+      [e * i - f * h, c * h - b * i, b * f - c * e],
+      [f * g - d * i, a * i - c * g, c * d - a * f],
+      [d * h - e * g, b * g - a * h, a * e - b * d],
+    ]
+    .map(|row| row.map(|value| value / det))
+  }
+
+  /// Where in the frame of `size` a point on the screen shows, e.g. to
+  /// find what a touch landed on.
+  pub fn unwarp(&self, [x, y]: [f32; 2], [width, height]: [f32; 2]) -> [f32; 2] {
+    let m = self.inverse();
+    let (u, v) = (x / width, y / height);
+    let w = m[2][0] * u + m[2][1] * v + m[2][2];
+    [
+      (m[0][0] * u + m[0][1] * v + m[0][2]) / w * width,
+      (m[1][0] * u + m[1][1] * v + m[1][2]) / w * height,
+    ]
+  }
+}
+
+/// As a TOML value for the config's `display.keystone`.
+impl fmt::Display for Keystone {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    write!(f, "[")?;
+    for (index, [x, y]) in self.corners.iter().enumerate() {
+      if index > 0 {
+        write!(f, ", ")?;
+      }
+      write!(f, "[{:.3}, {:.3}]", x, y)?;
+    }
+    write!(f, "]")
+  }
+}
+
+/// Distance a calibration key press moves a corner, in fractions of the
+/// screen.
+pub(crate) const CALIBRATION_STEP: f32 = 0.005;
+
+/// Draws a grid over the frame, which looks straight from where the driver
+/// sits once the keystone is right, and a handle at `corner` of the frame
+/// showing which corner is being moved.
+pub(crate) fn draw_calibration(frame: &mut Frame, corner: [f32; 2]) {
+  const LINES: usize = 8;
+  let color = [1.0, 1.0, 1.0, 0.8];
+  let (width, height) = (frame.width as f32, frame.height as f32);
+  for line in 0..=LINES {
+    let t = line as f32 / LINES as f32;
+    frame
+      .canvas
+      .line([t * width, 0.0], [t * width, height], color, 2.0);
+    frame
+      .canvas
+      .line([0.0, t * height], [width, t * height], color, 2.0);
+  }
+  let radius = width.min(height) * 0.04;
+  frame
+    .canvas
+    .circle(corner, radius, Style::fill([1.0, 0.5, 0.0, 0.9]));
+}
+
+/// Layout of `Warp` in `shaders/warp.wgsl`, a `mat3x3` has 16 byte
+/// columns.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Pod, Zeroable)]
+struct WarpUniform {
+  inverse: [[f32; 4]; 3],
+  color: [[f32; 4]; 3],
+  size: [f32; 2],
+  padding: [f32; 2],
+}
+
+impl WarpUniform {
+  fn new(keystone: &Keystone, vision: ColorVision, width: u32, height: u32) -> Self {
+    let columns =
+      |m: [[f32; 3]; 3]| [0, 1, 2].map(|column| [m[0][column], m[1][column], m[2][column], 0.0]);
+    Self {
+      inverse: columns(keystone.inverse()),
+      color: columns(vision.simulation()),
+      size: [width as f32, height as f32],
+      padding: [0.0; 2],
+    }
+  }
+}
+
+/// Renders the frame into a texture first and then draws that warped onto
+/// the screen, see [`Keystone`], recolored to how it looks with the
+/// [color blindness simulated](crate::Settings::simulate_color_vision).
+pub(crate) struct WarpPass {
+  format: TextureFormat,
+  pipeline: RenderPipeline,
+  layout: BindGroupLayout,
+  sampler: Sampler,
+  uniform: Buffer,
+  keystone: Keystone,
+  vision: ColorVision,
+  size: [u32; 2],
+  // Recreated on resize together with the bind group
+  texture: Texture,
+  view: TextureView,
+  bind_group: BindGroup,
+}
+
+impl WarpPass {
+  pub(crate) async fn new(
+    device: &Device,
+    format: TextureFormat,
+    width: u32,
+    height: u32,
+    (name, source): (&str, &str),
+  ) -> Result<Self, WindshieldError> {
+    let shader = create_shader(device, name, source).await?;
+    let layout = device.create_bind_group_layout(&BindGroupLayoutDescriptor {
+      label: Some("Warp"),
+      entries: &[
+        BindGroupLayoutEntry {
+          binding: 0,
+          visibility: ShaderStages::FRAGMENT,
+          ty: BindingType::Buffer {
+            ty: BufferBindingType::Uniform,
+            has_dynamic_offset: false,
+            min_binding_size: None,
+          },
+          count: None,
+        },
+        BindGroupLayoutEntry {
+          binding: 1,
+          visibility: ShaderStages::FRAGMENT,
+          ty: BindingType::Texture {
+            sample_type: TextureSampleType::Float { filterable: true },
+            view_dimension: TextureViewDimension::D2,
+            multisampled: false,
+          },
+          count: None,
+        },
+        BindGroupLayoutEntry {
+          binding: 2,
+          visibility: ShaderStages::FRAGMENT,
+          ty: BindingType::Sampler(SamplerBindingType::Filtering),
+          count: None,
+        },
+      ],
+    });
+    let pipeline = PipelineBuilder::new("Warp Pipeline", &shader)
+      .bind_group_layout(&layout)
+      .blend(None)
+      .build(device, format);
+    let sampler = device.create_sampler(&SamplerDescriptor {
+      label: Some("Warp"),
+      address_mode_u: AddressMode::ClampToEdge,
+      address_mode_v: AddressMode::ClampToEdge,
+      mag_filter: FilterMode::Linear,
+      min_filter: FilterMode::Linear,
+      ..Default::default()
+    });
+    let keystone = Keystone::default();
+    let uniform = device.create_buffer_init(&BufferInitDescriptor {
+      label: Some("Warp"),
+      contents: bytemuck::bytes_of(&WarpUniform::new(
+        &keystone,
+        ColorVision::Normal,
+        width,
+        height,
+      )),
+      usage: BufferUsages::UNIFORM | BufferUsages::COPY_DST,
+    });
+    let (texture, view, bind_group) =
+      frame_texture(device, format, [width, height], &layout, &uniform, &sampler);
+
+    Ok(Self {
+      format,
+      pipeline,
+      layout,
+      sampler,
+      uniform,
+      keystone,
+      vision: ColorVision::Normal,
+      size: [width, height],
+      texture,
+      view,
+      bind_group,
+    })
+  }
+
+  pub(crate) fn resize(&mut self, device: &Device, queue: &Queue, width: u32, height: u32) {
+    self.size = [width, height];
+    (self.texture, self.view, self.bind_group) = frame_texture(
+      device,
+      self.format,
+      self.size,
+      &self.layout,
+      &self.uniform,
+      &self.sampler,
+    );
+    self.write(queue);
+  }
+
+  pub(crate) fn set_keystone(&mut self, queue: &Queue, keystone: Keystone) {
+    self.keystone = keystone;
+    self.write(queue);
+  }
+
+  pub(crate) fn set_color_vision(&mut self, queue: &Queue, vision: ColorVision) {
+    self.vision = vision;
+    self.write(queue);
+  }
+
+  fn write(&self, queue: &Queue) {
+    let [width, height] = self.size;
+    let uniform = WarpUniform::new(&self.keystone, self.vision, width, height);
+    queue.write_buffer(&self.uniform, 0, bytemuck::bytes_of(&uniform));
+  }
+
+  /// What the frame is rendered into.
+  pub(crate) fn view(&self) -> &TextureView {
+    &self.view
+  }
+
+  /// The texture behind [`view`](Self::view), e.g. to copy the frame out.
+  pub(crate) fn texture(&self) -> &Texture {
+    &self.texture
+  }
+
+  /// Draws the frame warped onto `target`, cleared to `background`.
+  pub(crate) fn draw(
+    &self,
+    encoder: &mut CommandEncoder,
+    target: &TextureView,
+    background: Color,
+    stats: &mut FrameStats,
+  ) {
+    let mut pass = encoder.begin_render_pass(&RenderPassDescriptor {
+      label: Some("Warp Pass"),
+      color_attachments: &[Some(RenderPassColorAttachment {
+        view: target,
+        resolve_target: None,
+        ops: Operations {
+          load: LoadOp::Clear(background),
+          store: true,
+        },
+      })],
+      depth_stencil_attachment: None,
+    });
+    pass.set_pipeline(&self.pipeline);
+    pass.set_bind_group(0, &self.bind_group, &[]);
+    pass.draw(0..3, 0..1);
+    stats.draw_calls += 1;
+    stats.triangles += 1;
+    stats.texture_binds += 1;
+  }
+}
+
+fn frame_texture(
+  device: &Device,
+  format: TextureFormat,
+  [width, height]: [u32; 2],
+  layout: &BindGroupLayout,
+  uniform: &Buffer,
+  sampler: &Sampler,
+) -> (Texture, TextureView, BindGroup) {
+  let texture = device.create_texture(&TextureDescriptor {
+    label: Some("Warp Frame"),
+    size: Extent3d {
+      width,
+      height,
+      depth_or_array_layers: 1,
+    },
+    mip_level_count: 1,
+    sample_count: 1,
+    dimension: TextureDimension::D2,
+    format,
+    usage: TextureUsages::RENDER_ATTACHMENT
+      | TextureUsages::TEXTURE_BINDING
+      | TextureUsages::COPY_SRC,
+  });
+  let view = texture.create_view(&TextureViewDescriptor::default());
+  let bind_group = device.create_bind_group(&BindGroupDescriptor {
+    label: Some("Warp"),
+    layout,
+    entries: &[
+      BindGroupEntry {
+        binding: 0,
+        resource: uniform.as_entire_binding(),
+      },
+      BindGroupEntry {
+        binding: 1,
+        resource: BindingResource::TextureView(&view),
+      },
+      BindGroupEntry {
+        binding: 2,
+        resource: BindingResource::Sampler(sampler),
+      },
+    ],
+  });
+  (texture, view, bind_group)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  const SIZE: [f32; 2] = [800.0, 480.0];
+
+  /// Narrower at the top, like a windshield leaning away.
+  fn trapezoid() -> Keystone {
+    Keystone {
+      corners: [[0.1, 0.05], [0.9, 0.0], [1.0, 1.0], [0.0, 0.95]],
+    }
+  }
+
+  fn warp(keystone: &Keystone, [u, v]: [f32; 2]) -> [f32; 2] {
+    let m = keystone.homography();
+    let w = m[2][0] * u + m[2][1] * v + m[2][2];
+    [
+      (m[0][0] * u + m[0][1] * v + m[0][2]) / w,
+      (m[1][0] * u + m[1][1] * v + m[1][2]) / w,
+    ]
+  }
+
+  fn assert_near([x, y]: [f32; 2], [ex, ey]: [f32; 2]) {
+    assert!(
+      (x - ex).abs() < 1e-3 && (y - ey).abs() < 1e-3,
+      "{:?} isn't {:?}",
+      [x, y],
+      [ex, ey]
+    );
+  }
+
+  #[test]
+  fn frame_corners_end_up_on_the_keystone_corners() {
+    let keystone = trapezoid();
+    let frame = Keystone::default().corners;
+    for (corner, screen) in frame.into_iter().zip(keystone.corners) {
+      assert_near(warp(&keystone, corner), screen);
+    }
+  }
+
+  #[test]
+  fn unwarp_undoes_the_warp() {
+    let keystone = trapezoid();
+    for point in [[0.5, 0.5], [0.25, 0.75], [0.9, 0.1], [0.0, 1.0]] {
+      let [x, y] = warp(&keystone, point);
+      let [u, v] = keystone.unwarp([x * SIZE[0], y * SIZE[1]], SIZE);
+      assert_near([u / SIZE[0], v / SIZE[1]], point);
+    }
+  }
+
+  #[test]
+  fn default_keystone_shows_the_frame_as_is() {
+    let keystone = Keystone::default();
+    assert!(keystone.is_identity());
+    assert!(!trapezoid().is_identity());
+    assert_near(keystone.unwarp([200.0, 120.0], SIZE), [200.0, 120.0]);
+    assert_eq!(
+      keystone.to_string(),
+      "[[0.000, 0.000], [1.000, 0.000], [1.000, 1.000], [0.000, 1.000]]"
+    );
+  }
+}
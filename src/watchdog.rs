@@ -0,0 +1,174 @@
+use std::fs;
+use std::io;
+use std::net::{Ipv4Addr, Ipv6Addr, SocketAddr, UdpSocket};
+use std::path::PathBuf;
+use std::time::{Duration, UNIX_EPOCH};
+
+use instant::Instant;
+
+use crate::clock;
+
+/// Time between two heartbeats unless given another interval. A
+/// supervisor should allow for a few missed ones before it power-cycles.
+pub const DEFAULT_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Tells an external supervisor the renderer is alive, see
+/// [`WindshieldAppBuilder::with_watchdog`](crate::WindshieldAppBuilder::with_watchdog).
+///
+/// Only called after a frame made it to the screen, so a hung GPU or
+/// event loop stops the heartbeats as surely as a crash does. Must not
+/// block for long, it runs between frames.
+pub trait Heartbeat {
+  fn beat(&mut self) -> io::Result<()>;
+}
+
+/// Writes the current Unix time to a file on every beat, for supervisors
+/// watching its contents or its modification time, like systemd's
+/// `PathModified=` or a shell loop on `stat`.
+pub struct TouchFile {
+  path: PathBuf,
+}
+
+impl TouchFile {
+  pub fn new(path: impl Into<PathBuf>) -> Self {
+    Self { path: path.into() }
+  }
+}
+
+impl Heartbeat for TouchFile {
+  fn beat(&mut self) -> io::Result<()> {
+    let seconds = clock::system_time()
+      .duration_since(UNIX_EPOCH)
+      .unwrap_or_default()
+      .as_secs();
+    fs::write(&self.path, format!("{}\n", seconds))
+  }
+}
+
+/// Sends a small datagram to a supervisor listening on UDP, e.g. a
+/// microcontroller switching the display's power.
+pub struct UdpPacket {
+  socket: UdpSocket,
+  target: SocketAddr,
+  sequence: u32,
+}
+
+impl UdpPacket {
+  /// The packet is `windshield <sequence>`, the sequence counting up from 0
+  /// so the supervisor can tell late packets from repeated ones.
+  pub fn new(target: SocketAddr) -> io::Result<Self> {
+    let local: SocketAddr = match target {
+      SocketAddr::V4(_) => (Ipv4Addr::UNSPECIFIED, 0).into(),
+      SocketAddr::V6(_) => (Ipv6Addr::UNSPECIFIED, 0).into(),
+    };
+    let socket = UdpSocket::bind(local)?;
+    socket.set_nonblocking(true)?;
+    Ok(Self {
+      socket,
+      target,
+      sequence: 0,
+    })
+  }
+}
+
+impl Heartbeat for UdpPacket {
+  fn beat(&mut self) -> io::Result<()> {
+    let packet = format!("windshield {}", self.sequence);
+    self.sequence = self.sequence.wrapping_add(1);
+    self.socket.send_to(packet.as_bytes(), self.target)?;
+    Ok(())
+  }
+}
+
+/// A digital output, e.g. a GPIO line feeding a hardware watchdog.
+pub trait OutputPin {
+  fn set(&mut self, high: bool) -> io::Result<()>;
+}
+
+/// A GPIO line exported through `/sys/class/gpio`, written as `0` or `1`
+/// to its `value` file.
+pub struct SysfsPin {
+  value: PathBuf,
+}
+
+impl SysfsPin {
+  /// The line has to be exported and its direction set to `out` already,
+  /// which usually needs more permissions than the HUD runs with.
+  pub fn new(line: u32) -> Self {
+    Self {
+      value: PathBuf::from(format!("/sys/class/gpio/gpio{}/value", line)),
+    }
+  }
+}
+
+impl OutputPin for SysfsPin {
+  fn set(&mut self, high: bool) -> io::Result<()> {
+    fs::write(&self.value, if high { "1" } else { "0" })
+  }
+}
+
+/// Toggles a pin on every beat, for hardware watchdogs that reset unless
+/// they see an edge in time.
+pub struct TogglePin<P> {
+  pin: P,
+  high: bool,
+}
+
+impl<P: OutputPin> TogglePin<P> {
+  pub fn new(pin: P) -> Self {
+    Self { pin, high: false }
+  }
+}
+
+impl<P: OutputPin> Heartbeat for TogglePin<P> {
+  fn beat(&mut self) -> io::Result<()> {
+    self.high = !self.high;
+    self.pin.set(self.high)
+  }
+}
+
+/// Beats at most every `interval`, whenever a frame was presented.
+pub(crate) struct Watchdog {
+  heartbeat: Box<dyn Heartbeat>,
+  interval: Duration,
+  last_beat: Option<Instant>,
+  failing: bool,
+}
+
+impl Watchdog {
+  pub(crate) fn new(heartbeat: impl Heartbeat + 'static, interval: Duration) -> Self {
+    Self {
+      heartbeat: Box::new(heartbeat),
+      interval,
+      last_beat: None,
+      failing: false,
+    }
+  }
+
+  /// Called after every frame that was presented.
+  pub(crate) fn presented(&mut self) {
+    let now = Instant::now();
+    if self
+      .last_beat
+      .is_some_and(|last_beat| now - last_beat < self.interval)
+    {
+      return;
+    }
+    self.last_beat = Some(now);
+    match self.heartbeat.beat() {
+      Ok(()) => self.failing = false,
+      // Only logged once until it works again, not on every beat
+      Err(err) if !self.failing => {
+        self.failing = true;
+        tracing::warn!("unable to send watchdog heartbeat: {}", err);
+      }
+      Err(_) => {}
+    }
+  }
+
+  /// When the next heartbeat is due, so an otherwise idle HUD still draws
+  /// a frame in time to send it.
+  pub(crate) fn due(&self) -> Option<Instant> {
+    self.last_beat.map(|last_beat| last_beat + self.interval)
+  }
+}
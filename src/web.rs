@@ -0,0 +1,138 @@
+use std::path::Path;
+
+use tracing_subscriber::filter::LevelFilter;
+use tracing_subscriber::prelude::*;
+use wasm_bindgen::prelude::*;
+use wasm_bindgen::JsCast;
+use wasm_bindgen_futures::JsFuture;
+use web_sys::{HtmlCanvasElement, Response};
+use winit::dpi::LogicalSize;
+use winit::platform::web::WindowExtWebSys;
+use winit::window::Window;
+
+use crate::config::Config;
+use crate::error::WindshieldError;
+use crate::logging::LineWriter;
+use crate::WindshieldApp;
+
+/// Starts the HUD in a web page, in the canvas with the id `canvas` or in
+/// one appended to the body if unset. `config` is the URL of the dashboard
+/// [`Config`], relative to the page.
+///
+/// ```js
+/// import init, { start } from "./windshield_rs.js";
+///
+/// await init();
+/// start("hud", "dashboard.toml");
+/// ```
+#[wasm_bindgen]
+pub fn start(canvas: Option<String>, config: Option<String>) {
+  std::panic::set_hook(Box::new(|info| {
+    web_sys::console::error_1(&info.to_string().into());
+  }));
+  // The default timer reads the system clock, which panics on the web
+  let layer = tracing_subscriber::fmt::layer()
+    .without_time()
+    .with_ansi(false)
+    .with_writer(LineWriter(|line| web_sys::console::log_1(&line.into())))
+    .with_filter(LevelFilter::INFO);
+  // Fails if the page started the HUD before, which already logs then
+  let _ = tracing_subscriber::registry().with(layer).try_init();
+
+  let mut builder = WindshieldApp::builder();
+  if let Some(id) = canvas {
+    match find_canvas(&id) {
+      Some(canvas) => builder = builder.with_canvas(canvas),
+      None => tracing::warn!("no canvas with id {:?}, appending one", id),
+    }
+  }
+  if let Some(config) = config {
+    builder = builder.with_config(config);
+  }
+  let app = builder.build();
+  wasm_bindgen_futures::spawn_local(async move {
+    if let Err(err) = app.run().await {
+      tracing::error!("{}", err);
+    }
+  });
+}
+
+fn find_canvas(id: &str) -> Option<HtmlCanvasElement> {
+  web_sys::window()?
+    .document()?
+    .get_element_by_id(id)?
+    .dyn_into()
+    .ok()
+}
+
+/// Fetches the config at `url`, relative to the page, as there are no
+/// files to read on the web.
+pub(crate) async fn fetch_config(url: &Path) -> Result<Config, WindshieldError> {
+  let error = |message: String| WindshieldError::Fetch {
+    path: url.to_path_buf(),
+    message,
+  };
+  let js_error = |err: JsValue| error(format!("{:?}", err));
+
+  let window = web_sys::window().ok_or_else(|| error("not in a browser window".to_string()))?;
+  let response: Response = JsFuture::from(window.fetch_with_str(&url.to_string_lossy()))
+    .await
+    .map_err(js_error)?
+    .dyn_into()
+    .map_err(js_error)?;
+  if !response.ok() {
+    return Err(error(format!("status {}", response.status())));
+  }
+  let text = JsFuture::from(response.text().map_err(js_error)?)
+    .await
+    .map_err(js_error)?;
+  Config::parse(&text.as_string().unwrap_or_default(), url)
+}
+
+/// Appends the canvas of `window` to the page, unless it was given one
+/// that is part of it already.
+pub(crate) fn attach(window: &Window) {
+  let canvas = window.canvas();
+  if canvas.is_connected() {
+    return;
+  }
+  let body = web_sys::window()
+    .and_then(|window| window.document())
+    .and_then(|document| document.body());
+  match body.map(|body| body.append_child(&canvas)) {
+    Some(Ok(_)) => {}
+    _ => tracing::error!("unable to add the canvas to the page"),
+  }
+}
+
+/// Resizes `window` to fill the element its canvas is in, or the browser
+/// window if that's the body. The page lays the canvas out, so no resize
+/// events come from it.
+pub(crate) fn fit(window: &Window) {
+  let Some(browser) = web_sys::window() else {
+    return;
+  };
+  let parent = window.canvas().parent_element();
+  let body = browser.document().and_then(|document| document.body());
+  let size = match parent {
+    Some(parent) if Some(&parent) != body.as_deref() => {
+      LogicalSize::new(parent.client_width() as f64, parent.client_height() as f64)
+    }
+    _ => {
+      let dimension = |value: Result<JsValue, JsValue>| {
+        value.ok().and_then(|value| value.as_f64()).unwrap_or(0.0)
+      };
+      LogicalSize::new(
+        dimension(browser.inner_width()),
+        dimension(browser.inner_height()),
+      )
+    }
+  };
+  let current = window.inner_size().to_logical::<f64>(window.scale_factor());
+  if size.width > 0.0
+    && size.height > 0.0
+    && ((size.width - current.width).abs() >= 1.0 || (size.height - current.height).abs() >= 1.0)
+  {
+    window.set_inner_size(size);
+  }
+}
@@ -0,0 +1,303 @@
+use std::time::Duration;
+
+use crate::anim::Spring;
+use crate::canvas::Style;
+use crate::data::Telemetry;
+use crate::frame::Frame;
+use crate::input::{Action, PointerEvent, PointerId, PointerPhase};
+use crate::theme::Palette;
+use crate::widgets::{Rect, Widget};
+
+/// Distance in physical pixels a pointer has to move sideways before it
+/// swipes instead of going to the page.
+const SWIPE_SLOP: f32 = 12.0;
+/// Stiffness of the spring snapping to a page, per second squared.
+const STIFFNESS: f32 = 180.0;
+/// Release speed in pages per second from which a swipe flips the page
+/// even if it wasn't dragged half way.
+const FLICK_SPEED: f32 = 0.6;
+/// How much dragging past the first or last page moves the pages.
+const OVERSCROLL: f32 = 0.3;
+
+struct Drag {
+  id: PointerId,
+  start_x: f32,
+  start_offset: f32,
+  last_x: f32,
+  last_time: Duration,
+  /// Whether the carousel took over the pointer from the page.
+  swiping: bool,
+}
+
+/// Pages side by side, switched between by swiping, with dots showing the
+/// current one.
+///
+/// Pages are drawn next to each other while swiping and aren't clipped, so
+/// the carousel is meant to span the width of the window.
+pub struct Carousel {
+  pages: Vec<Box<dyn Widget>>,
+  /// Page that is or is about to be shown.
+  target: usize,
+  /// Position in pages, fractional while moving, and its speed in pages
+  /// per second.
+  position: Spring,
+  drag: Option<Drag>,
+  time: Duration,
+  /// Linear RGBA in straight alpha for the current and the other pages'
+  /// dots, no dots are drawn if unset.
+  pub indicator: Option<([f32; 4], [f32; 4])>,
+}
+
+impl Default for Carousel {
+  fn default() -> Self {
+    Self::new()
+  }
+}
+
+impl Carousel {
+  pub fn new() -> Self {
+    Self {
+      pages: Vec::new(),
+      target: 0,
+      position: Spring::new(0.0, STIFFNESS),
+      drag: None,
+      time: Duration::ZERO,
+      indicator: Some(([1.0, 1.0, 1.0, 1.0], [1.0, 1.0, 1.0, 0.35])),
+    }
+  }
+
+  pub fn with_page(mut self, page: impl Widget + 'static) -> Self {
+    self.pages.push(Box::new(page));
+    self
+  }
+
+  pub fn with_indicator(mut self, indicator: Option<([f32; 4], [f32; 4])>) -> Self {
+    self.indicator = indicator;
+    self
+  }
+
+  pub fn page(&self) -> usize {
+    self.target
+  }
+
+  pub fn page_mut(&mut self, index: usize) -> Option<&mut dyn Widget> {
+    Some(self.pages.get_mut(index)?.as_mut())
+  }
+
+  /// Slides to the page at `index`.
+  pub fn set_page(&mut self, index: usize) {
+    self.target = index.min(self.pages.len().saturating_sub(1));
+    self.position.target = self.target as f32;
+  }
+
+  fn last(&self) -> f32 {
+    self.pages.len().saturating_sub(1) as f32
+  }
+
+  fn page_rect(&self, index: usize, rect: Rect) -> Rect {
+    Rect {
+      x: rect.x + (index as f32 - self.position.value()) * rect.width,
+      ..rect
+    }
+  }
+
+  /// Pages at least partly inside the carousel.
+  fn visible(&self) -> impl Iterator<Item = usize> + '_ {
+    (0..self.pages.len()).filter(|index| (*index as f32 - self.position.value()).abs() < 1.0)
+  }
+
+  fn swipe(&mut self, event: &PointerEvent, rect: Rect) {
+    let last = self.last();
+    let Some(drag) = &mut self.drag else {
+      return;
+    };
+    let x = event.position[0];
+    let width = rect.width.max(1.0);
+
+    let dt = (self.time - drag.last_time).as_secs_f32();
+    if dt > 0.0 {
+      // Smoothed, single moves are noisy
+      let velocity = -(x - drag.last_x) / width / dt;
+      let smoothed = self.position.velocity() * 0.5 + velocity * 0.5;
+      self.position.set_velocity(smoothed);
+    }
+    drag.last_x = x;
+    drag.last_time = self.time;
+
+    let offset = drag.start_offset - (x - drag.start_x) / width;
+    self.position.set_value(if offset < 0.0 {
+      offset * OVERSCROLL
+    } else if offset > last {
+      last + (offset - last) * OVERSCROLL
+    } else {
+      offset
+    });
+  }
+
+  fn release(&mut self) {
+    let (offset, velocity) = (self.position.value(), self.position.velocity());
+    let target = if velocity > FLICK_SPEED {
+      offset.ceil()
+    } else if velocity < -FLICK_SPEED {
+      offset.floor()
+    } else {
+      offset.round()
+    };
+    self.set_page(target.max(0.0) as usize);
+  }
+}
+
+impl Widget for Carousel {
+  fn focusable(&self) -> bool {
+    !self.pages.is_empty()
+  }
+
+  // The current page gets the action first, what it doesn't use turns the
+  // pages
+  fn action(&mut self, action: Action) -> bool {
+    let target = self.target;
+    if let Some(page) = self.pages.get_mut(target) {
+      if page.focusable() && page.action(action) {
+        return true;
+      }
+    }
+    match action {
+      Action::Next | Action::NextPage if target < self.pages.len().saturating_sub(1) => {
+        self.set_page(target + 1)
+      }
+      Action::Previous | Action::PreviousPage if target > 0 => self.set_page(target - 1),
+      _ => return false,
+    }
+    true
+  }
+
+  fn set_palette(&mut self, palette: &Palette) {
+    if self.indicator.is_some() {
+      self.indicator = Some((palette.text, palette.muted));
+    }
+    for page in &mut self.pages {
+      page.set_palette(palette);
+    }
+  }
+
+  fn set_telemetry(&mut self, telemetry: &Telemetry) {
+    for page in &mut self.pages {
+      page.set_telemetry(telemetry);
+    }
+  }
+
+  fn update(&mut self, delta: Duration) {
+    self.time += delta;
+    for page in &mut self.pages {
+      page.update(delta);
+    }
+    if self.drag.as_ref().is_some_and(|drag| drag.swiping) {
+      return;
+    }
+
+    // Critically damped, settling on the page without overshooting it
+    self.position.update(delta);
+  }
+
+  fn animating(&self) -> bool {
+    self.drag.is_some()
+      || !self.position.is_settled()
+      || self.pages.iter().any(|page| page.animating())
+  }
+
+  fn draw(&self, frame: &mut Frame, rect: Rect) {
+    for index in self.visible() {
+      self.pages[index].draw(frame, self.page_rect(index, rect));
+    }
+
+    let Some((current, other)) = self.indicator else {
+      return;
+    };
+    let radius = (rect.height * 0.01).max(3.0);
+    let spacing = radius * 4.0;
+    let y = rect.y + rect.height - radius * 4.0;
+    let start = rect.center()[0] - spacing * self.last() / 2.0;
+    for index in 0..self.pages.len() {
+      let color = if index == self.target { current } else { other };
+      frame.canvas.circle(
+        [start + spacing * index as f32, y],
+        radius,
+        Style::fill(color),
+      );
+    }
+  }
+
+  fn pointer(&mut self, event: &PointerEvent, rect: Rect) -> bool {
+    match event.phase {
+      PointerPhase::Down if self.drag.is_none() => {
+        self.drag = Some(Drag {
+          id: event.id,
+          start_x: event.position[0],
+          start_offset: self.position.value(),
+          last_x: event.position[0],
+          last_time: self.time,
+          swiping: false,
+        });
+      }
+      PointerPhase::Move => {
+        if let Some(drag) = &mut self.drag {
+          // Pages dragging something of their own keep the pointer
+          let page_captures = self.pages.iter().any(|page| page.captures(event.id));
+          if drag.id == event.id
+            && !drag.swiping
+            && !page_captures
+            && (event.position[0] - drag.start_x).abs() > SWIPE_SLOP
+          {
+            drag.swiping = true;
+            self.position.set_velocity(0.0);
+            // The page may be tracking the pointer, e.g. for a tap
+            let cancel = PointerEvent {
+              phase: PointerPhase::Cancel,
+              ..*event
+            };
+            for index in self.visible().collect::<Vec<_>>() {
+              let page_rect = self.page_rect(index, rect);
+              self.pages[index].pointer(&cancel, page_rect);
+            }
+          }
+        }
+        if self
+          .drag
+          .as_ref()
+          .is_some_and(|drag| drag.id == event.id && drag.swiping)
+        {
+          self.swipe(event, rect);
+          return true;
+        }
+      }
+      PointerPhase::Up | PointerPhase::Cancel => {
+        if let Some(drag) = self.drag.take_if(|drag| drag.id == event.id) {
+          if drag.swiping {
+            self.release();
+            return true;
+          }
+        }
+      }
+      _ => {}
+    }
+
+    // Not a swipe, so it's for the pages
+    let mut used = false;
+    for index in self.visible().collect::<Vec<_>>() {
+      let page_rect = self.page_rect(index, rect);
+      let page = &mut self.pages[index];
+      if event.phase != PointerPhase::Down || page.hit(page_rect, event.position) {
+        used |= page.pointer(event, page_rect);
+      }
+    }
+    used || self.drag.is_some()
+  }
+
+  fn captures(&self, id: PointerId) -> bool {
+    let swiping = self
+      .drag
+      .as_ref()
+      .is_some_and(|drag| drag.id == id && drag.swiping);
+    swiping || self.pages.iter().any(|page| page.captures(id))
+  }
+}
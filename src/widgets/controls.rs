@@ -0,0 +1,656 @@
+use std::cell::Cell;
+use std::fmt;
+use std::ops::RangeInclusive;
+use std::rc::Rc;
+use std::time::Duration;
+
+use crate::accessibility::{Accessible, Role};
+use crate::canvas::Style;
+use crate::frame::Frame;
+use crate::input::{Action, PointerEvent, PointerId, PointerPhase};
+use crate::text::{Align, TextSection, VAlign};
+use crate::theme::Palette;
+use crate::widgets::{Rect, Widget, MIN_TARGET};
+
+/// A value shared between a control and the rest of the application.
+///
+/// Clones refer to the same value: the control shows whatever it is set
+/// to and writes to it when used.
+#[derive(Clone, Default)]
+pub struct Binding<T: Copy>(Rc<Cell<T>>);
+
+impl<T: Copy> Binding<T> {
+  pub fn new(value: T) -> Self {
+    Self(Rc::new(Cell::new(value)))
+  }
+
+  pub fn get(&self) -> T {
+    self.0.get()
+  }
+
+  pub fn set(&self, value: T) {
+    self.0.set(value);
+  }
+}
+
+impl<T: Copy + fmt::Debug> fmt::Debug for Binding<T> {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    f.debug_tuple("Binding").field(&self.get()).finish()
+  }
+}
+
+/// Moments a control may want to confirm with a haptic pulse or a sound.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Feedback {
+  Press,
+  Release,
+  /// The value was changed by the user.
+  Change,
+  /// A stepped slider moved onto the next step.
+  Detent,
+}
+
+type FeedbackHook = Box<dyn FnMut(Feedback)>;
+type ChangeHook<T> = Box<dyn FnMut(T)>;
+
+/// Linear RGBA colors in straight alpha.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ControlColors {
+  /// Inactive parts, like the empty part of a slider.
+  pub track: [f32; 4],
+  /// Active parts, like a toggle that is on.
+  pub accent: [f32; 4],
+  pub knob: [f32; 4],
+  pub text: [f32; 4],
+}
+
+impl Default for ControlColors {
+  fn default() -> Self {
+    Self {
+      track: [1.0, 1.0, 1.0, 0.25],
+      accent: [0.2, 0.6, 1.0, 1.0],
+      knob: [1.0, 1.0, 1.0, 1.0],
+      text: [1.0, 1.0, 1.0, 1.0],
+    }
+  }
+}
+
+impl From<&Palette> for ControlColors {
+  fn from(palette: &Palette) -> Self {
+    Self {
+      track: palette.muted,
+      accent: palette.accent,
+      knob: palette.text,
+      text: palette.text,
+    }
+  }
+}
+
+/// The state and hooks every control shares.
+struct Control<T> {
+  colors: ControlColors,
+  pressed: Option<PointerId>,
+  on_change: Option<ChangeHook<T>>,
+  on_feedback: Option<FeedbackHook>,
+}
+
+impl<T> Control<T> {
+  fn new() -> Self {
+    Self {
+      colors: ControlColors::default(),
+      pressed: None,
+      on_change: None,
+      on_feedback: None,
+    }
+  }
+
+  fn feedback(&mut self, feedback: Feedback) {
+    if let Some(on_feedback) = &mut self.on_feedback {
+      on_feedback(feedback);
+    }
+  }
+
+  fn changed(&mut self, value: T) {
+    if let Some(on_change) = &mut self.on_change {
+      on_change(value);
+    }
+    self.feedback(Feedback::Change);
+  }
+
+  /// Tracks which pointer pressed the control, returning the phase of
+  /// events from it.
+  fn track(&mut self, event: &PointerEvent) -> Option<PointerPhase> {
+    match event.phase {
+      PointerPhase::Down if self.pressed.is_none() => {
+        self.pressed = Some(event.id);
+        self.feedback(Feedback::Press);
+        Some(PointerPhase::Down)
+      }
+      PointerPhase::Move if self.pressed == Some(event.id) => Some(PointerPhase::Move),
+      PointerPhase::Up | PointerPhase::Cancel if self.pressed == Some(event.id) => {
+        self.pressed = None;
+        self.feedback(Feedback::Release);
+        Some(event.phase)
+      }
+      _ => None,
+    }
+  }
+}
+
+/// An on/off switch, flipped by tapping it.
+pub struct Toggle {
+  pub value: Binding<bool>,
+  control: Control<bool>,
+  // Position of the knob, animated from 0 (off) to 1 (on)
+  knob: f32,
+}
+
+impl Toggle {
+  pub fn new(value: Binding<bool>) -> Self {
+    let knob = if value.get() { 1.0 } else { 0.0 };
+    Self {
+      value,
+      control: Control::new(),
+      knob,
+    }
+  }
+
+  pub fn with_colors(mut self, colors: ControlColors) -> Self {
+    self.control.colors = colors;
+    self
+  }
+
+  pub fn on_change(mut self, callback: impl FnMut(bool) + 'static) -> Self {
+    self.control.on_change = Some(Box::new(callback));
+    self
+  }
+
+  pub fn on_feedback(mut self, callback: impl FnMut(Feedback) + 'static) -> Self {
+    self.control.on_feedback = Some(Box::new(callback));
+    self
+  }
+
+  /// The switch itself, a pill twice as wide as high centered in `rect`.
+  fn pill(rect: Rect) -> Rect {
+    let height = rect.height.min(rect.width / 2.0);
+    let [x, y] = rect.center();
+    Rect::new(x - height, y - height / 2.0, height * 2.0, height)
+  }
+}
+
+impl Widget for Toggle {
+  fn set_palette(&mut self, palette: &Palette) {
+    self.control.colors = palette.into();
+  }
+
+  fn focusable(&self) -> bool {
+    true
+  }
+
+  // Named by the node, see `Node::accessible_name`
+  fn accessible(&self) -> Option<Accessible> {
+    Some(Accessible::new(Role::Switch, "").with_selected(self.value.get()))
+  }
+
+  fn action(&mut self, action: Action) -> bool {
+    if action != Action::Activate {
+      return false;
+    }
+    let value = !self.value.get();
+    self.value.set(value);
+    self.control.feedback(Feedback::Press);
+    self.control.changed(value);
+    true
+  }
+
+  fn update(&mut self, delta: Duration) {
+    let target = if self.value.get() { 1.0 } else { 0.0 };
+    let step = delta.as_secs_f32() * 8.0;
+    self.knob += (target - self.knob).clamp(-step, step);
+  }
+
+  fn animating(&self) -> bool {
+    self.knob != if self.value.get() { 1.0 } else { 0.0 }
+  }
+
+  fn draw(&self, frame: &mut Frame, rect: Rect) {
+    let pill = Self::pill(rect);
+    let radius = pill.height / 2.0;
+    let y = pill.y + radius;
+    let (left, right) = (pill.x + radius, pill.x + pill.width - radius);
+    let colors = &self.control.colors;
+
+    let track = if self.value.get() {
+      colors.accent
+    } else {
+      colors.track
+    };
+    frame.canvas.line([left, y], [right, y], track, pill.height);
+    frame.canvas.circle(
+      [left + (right - left) * self.knob, y],
+      radius * 0.8,
+      Style::fill(colors.knob),
+    );
+  }
+
+  fn hit(&self, rect: Rect, position: [f32; 2]) -> bool {
+    Self::pill(rect).at_least(MIN_TARGET).contains(position)
+  }
+
+  fn pointer(&mut self, event: &PointerEvent, rect: Rect) -> bool {
+    match self.control.track(event) {
+      // Letting go elsewhere aborts the tap
+      Some(PointerPhase::Up) if self.hit(rect, event.position) => {
+        let value = !self.value.get();
+        self.value.set(value);
+        self.control.changed(value);
+        true
+      }
+      Some(_) => true,
+      None => false,
+    }
+  }
+}
+
+/// A horizontal slider, dragged or tapped to a value in `range`.
+pub struct Slider {
+  pub value: Binding<f32>,
+  pub range: RangeInclusive<f32>,
+  /// Snaps the value to multiples of the step counted from the start of
+  /// the range.
+  pub step: Option<f32>,
+  control: Control<f32>,
+}
+
+impl Slider {
+  pub fn new(value: Binding<f32>, range: RangeInclusive<f32>) -> Self {
+    Self {
+      value,
+      range,
+      step: None,
+      control: Control::new(),
+    }
+  }
+
+  pub fn with_step(mut self, step: f32) -> Self {
+    self.step = Some(step);
+    self
+  }
+
+  pub fn with_colors(mut self, colors: ControlColors) -> Self {
+    self.control.colors = colors;
+    self
+  }
+
+  pub fn on_change(mut self, callback: impl FnMut(f32) + 'static) -> Self {
+    self.control.on_change = Some(Box::new(callback));
+    self
+  }
+
+  pub fn on_feedback(mut self, callback: impl FnMut(Feedback) + 'static) -> Self {
+    self.control.on_feedback = Some(Box::new(callback));
+    self
+  }
+
+  fn knob_radius(rect: Rect) -> f32 {
+    (rect.height * 0.35).min(rect.width / 4.0)
+  }
+
+  /// The part of `rect` the knob's center moves along, as start and end x.
+  fn span(rect: Rect) -> (f32, f32) {
+    let radius = Self::knob_radius(rect);
+    (rect.x + radius, rect.x + rect.width - radius)
+  }
+
+  fn fraction(&self) -> f32 {
+    let (min, max) = (*self.range.start(), *self.range.end());
+    if max > min {
+      ((self.value.get() - min) / (max - min)).clamp(0.0, 1.0)
+    } else {
+      0.0
+    }
+  }
+
+  fn drag_to(&mut self, rect: Rect, x: f32) {
+    let (start, end) = Self::span(rect);
+    let (min, max) = (*self.range.start(), *self.range.end());
+    let t = ((x - start) / (end - start).max(1.0)).clamp(0.0, 1.0);
+    self.set(min + (max - min) * t);
+  }
+
+  /// Moves the value by a step, or a tenth of the range if it has none,
+  /// returning whether it moved.
+  fn nudge(&mut self, direction: f32) -> bool {
+    let (min, max) = (*self.range.start(), *self.range.end());
+    let step = self
+      .step
+      .filter(|step| *step > 0.0)
+      .unwrap_or((max - min) / 10.0);
+    let value = self.value.get();
+    self.set((value + step * direction).clamp(min, max));
+    self.value.get() != value
+  }
+
+  /// Snaps `value` to the steps and makes it the slider's value.
+  fn set(&mut self, mut value: f32) {
+    let (min, max) = (*self.range.start(), *self.range.end());
+    if let Some(step) = self.step.filter(|step| *step > 0.0) {
+      value = (min + ((value - min) / step).round() * step).min(max);
+    }
+
+    if value != self.value.get() {
+      self.value.set(value);
+      self.control.changed(value);
+      if self.step.is_some() {
+        self.control.feedback(Feedback::Detent);
+      }
+    }
+  }
+}
+
+impl Widget for Slider {
+  fn set_palette(&mut self, palette: &Palette) {
+    self.control.colors = palette.into();
+  }
+
+  fn focusable(&self) -> bool {
+    true
+  }
+
+  fn accessible(&self) -> Option<Accessible> {
+    // Rounded, so binary fractions read like the numbers they stand for
+    let value = (self.value.get() * 100.0).round() / 100.0;
+    Some(Accessible::new(Role::Slider, "").with_value(value.to_string()))
+  }
+
+  // At either end of the range the focus moves on
+  fn action(&mut self, action: Action) -> bool {
+    match action {
+      Action::Next => self.nudge(1.0),
+      Action::Previous => self.nudge(-1.0),
+      _ => false,
+    }
+  }
+
+  fn draw(&self, frame: &mut Frame, rect: Rect) {
+    let radius = Self::knob_radius(rect);
+    let (start, end) = Self::span(rect);
+    let y = rect.y + rect.height / 2.0;
+    let x = start + (end - start) * self.fraction();
+    let colors = &self.control.colors;
+
+    frame
+      .canvas
+      .line([start, y], [end, y], colors.track, radius * 0.5);
+    frame
+      .canvas
+      .line([start, y], [x, y], colors.accent, radius * 0.5);
+    frame
+      .canvas
+      .circle([x, y], radius, Style::fill(colors.knob));
+  }
+
+  fn hit(&self, rect: Rect, position: [f32; 2]) -> bool {
+    rect.at_least(MIN_TARGET).contains(position)
+  }
+
+  fn pointer(&mut self, event: &PointerEvent, rect: Rect) -> bool {
+    match self.control.track(event) {
+      Some(PointerPhase::Down | PointerPhase::Move) => {
+        self.drag_to(rect, event.position[0]);
+        true
+      }
+      Some(_) => true,
+      None => false,
+    }
+  }
+
+  // Dragging starts right away, a swipe mustn't move the knob as well
+  fn captures(&self, id: PointerId) -> bool {
+    self.control.pressed == Some(id)
+  }
+}
+
+/// A list of options of which exactly one is selected, stacked top to
+/// bottom.
+pub struct RadioGroup {
+  pub options: Vec<String>,
+  /// Index of the selected option.
+  pub selected: Binding<usize>,
+  control: Control<usize>,
+  // Option under the pointer when it was pressed
+  pressed_option: Option<usize>,
+}
+
+impl RadioGroup {
+  pub fn new<S: Into<String>>(
+    options: impl IntoIterator<Item = S>,
+    selected: Binding<usize>,
+  ) -> Self {
+    Self {
+      options: options.into_iter().map(Into::into).collect(),
+      selected,
+      control: Control::new(),
+      pressed_option: None,
+    }
+  }
+
+  pub fn with_colors(mut self, colors: ControlColors) -> Self {
+    self.control.colors = colors;
+    self
+  }
+
+  pub fn on_change(mut self, callback: impl FnMut(usize) + 'static) -> Self {
+    self.control.on_change = Some(Box::new(callback));
+    self
+  }
+
+  pub fn on_feedback(mut self, callback: impl FnMut(Feedback) + 'static) -> Self {
+    self.control.on_feedback = Some(Box::new(callback));
+    self
+  }
+
+  fn row_height(&self, rect: Rect) -> f32 {
+    rect.height / self.options.len().max(1) as f32
+  }
+
+  fn option_at(&self, rect: Rect, position: [f32; 2]) -> Option<usize> {
+    if !rect.contains(position) {
+      return None;
+    }
+    let row = ((position[1] - rect.y) / self.row_height(rect)) as usize;
+    (row < self.options.len()).then_some(row)
+  }
+}
+
+impl Widget for RadioGroup {
+  fn set_palette(&mut self, palette: &Palette) {
+    self.control.colors = palette.into();
+  }
+
+  fn focusable(&self) -> bool {
+    true
+  }
+
+  fn accessible(&self) -> Option<Accessible> {
+    let selected = self.selected.get();
+    let options = self
+      .options
+      .iter()
+      .enumerate()
+      .map(|(index, option)| {
+        Accessible::new(Role::RadioButton, option.as_str()).with_selected(index == selected)
+      })
+      .collect();
+    Some(Accessible::new(Role::RadioGroup, "").with_children(options))
+  }
+
+  // Past the first or last option the focus moves on
+  fn action(&mut self, action: Action) -> bool {
+    let selected = self.selected.get();
+    let option = match action {
+      Action::Next if selected + 1 < self.options.len() => selected + 1,
+      Action::Previous if selected > 0 => selected - 1,
+      _ => return false,
+    };
+    self.selected.set(option);
+    self.control.changed(option);
+    true
+  }
+
+  fn draw(&self, frame: &mut Frame, rect: Rect) {
+    let height = self.row_height(rect);
+    let radius = height * 0.25;
+    let colors = &self.control.colors;
+
+    for (i, option) in self.options.iter().enumerate() {
+      let y = rect.y + height * (i as f32 + 0.5);
+      let center = [rect.x + height / 2.0, y];
+      let selected = i == self.selected.get();
+      let ring = if selected {
+        colors.accent
+      } else {
+        colors.track
+      };
+      frame
+        .canvas
+        .circle(center, radius, Style::stroke(ring, radius * 0.3));
+      if selected {
+        frame
+          .canvas
+          .circle(center, radius * 0.5, Style::fill(colors.accent));
+      }
+      frame.text.queue(
+        &TextSection::new(option.as_str())
+          .at(rect.x + height, y)
+          .with_size(height * 0.45)
+          .with_color(colors.text)
+          .with_align(Align::Left, VAlign::Center),
+      );
+    }
+  }
+
+  fn pointer(&mut self, event: &PointerEvent, rect: Rect) -> bool {
+    match self.control.track(event) {
+      Some(PointerPhase::Down) => {
+        self.pressed_option = self.option_at(rect, event.position);
+        true
+      }
+      Some(PointerPhase::Up) => {
+        // Only a tap starting and ending on the same option selects it
+        let released = self.option_at(rect, event.position);
+        let tapped = self
+          .pressed_option
+          .take()
+          .filter(|option| Some(*option) == released);
+        if let Some(option) = tapped.filter(|option| *option != self.selected.get()) {
+          self.selected.set(option);
+          self.control.changed(option);
+        }
+        true
+      }
+      Some(_) => true,
+      None => false,
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use std::cell::RefCell;
+
+  use super::*;
+
+  const RECT: Rect = Rect {
+    x: 0.0,
+    y: 0.0,
+    width: 200.0,
+    height: 60.0,
+  };
+
+  fn event(phase: PointerPhase, position: [f32; 2]) -> PointerEvent {
+    PointerEvent {
+      id: PointerId::Touch(1),
+      phase,
+      position,
+    }
+  }
+
+  /// A hook recording what it's called with.
+  fn recorder<T: 'static>() -> (Rc<RefCell<Vec<T>>>, impl FnMut(T)) {
+    let calls = Rc::new(RefCell::new(Vec::new()));
+    let recorded = calls.clone();
+    (calls, move |value| recorded.borrow_mut().push(value))
+  }
+
+  #[test]
+  fn toggle_flips_on_taps_released_on_it() {
+    let value = Binding::new(false);
+    let (changes, on_change) = recorder();
+    let mut toggle = Toggle::new(value.clone()).on_change(on_change);
+    let center = [100.0, 30.0];
+    assert!(toggle.pointer(&event(PointerPhase::Down, center), RECT));
+    assert!(!value.get());
+    assert!(toggle.pointer(&event(PointerPhase::Up, center), RECT));
+    assert!(value.get());
+
+    // Letting go elsewhere aborts it
+    toggle.pointer(&event(PointerPhase::Down, center), RECT);
+    toggle.pointer(&event(PointerPhase::Up, [500.0, 300.0]), RECT);
+    assert!(value.get());
+    assert!(toggle.action(Action::Activate));
+    assert_eq!(*changes.borrow(), [true, false]);
+  }
+
+  #[test]
+  fn slider_follows_the_drag_in_steps() {
+    let value = Binding::new(0.0);
+    let (feedback, on_feedback) = recorder();
+    let mut slider = Slider::new(value.clone(), 0.0..=100.0)
+      .with_step(25.0)
+      .on_feedback(on_feedback);
+    let (start, end) = Slider::span(RECT);
+    slider.pointer(&event(PointerPhase::Down, [start - 50.0, 30.0]), RECT);
+    assert_eq!(value.get(), 0.0);
+    assert!(slider.captures(PointerId::Touch(1)));
+    slider.pointer(
+      &event(PointerPhase::Move, [start + (end - start) * 0.4, 30.0]),
+      RECT,
+    );
+    assert_eq!(value.get(), 50.0);
+    slider.pointer(&event(PointerPhase::Up, [end + 50.0, 30.0]), RECT);
+    assert!(!slider.captures(PointerId::Touch(1)));
+    assert_eq!(
+      *feedback.borrow(),
+      [
+        Feedback::Press,
+        Feedback::Change,
+        Feedback::Detent,
+        Feedback::Release
+      ]
+    );
+
+    // Encoders step it, until it's at the end of the range
+    assert!(slider.action(Action::Next));
+    assert_eq!(value.get(), 75.0);
+    assert!(slider.action(Action::Next));
+    assert!(!slider.action(Action::Next));
+    assert_eq!(value.get(), 100.0);
+  }
+
+  #[test]
+  fn radio_group_selects_the_option_tapped() {
+    let selected = Binding::new(0);
+    let mut radio = RadioGroup::new(["Off", "Low", "High"], selected.clone());
+    // Rows are 20 pixels high
+    radio.pointer(&event(PointerPhase::Down, [10.0, 45.0]), RECT);
+    radio.pointer(&event(PointerPhase::Up, [10.0, 50.0]), RECT);
+    assert_eq!(selected.get(), 2);
+    // Sliding onto another option isn't a tap on either
+    radio.pointer(&event(PointerPhase::Down, [10.0, 5.0]), RECT);
+    radio.pointer(&event(PointerPhase::Up, [10.0, 30.0]), RECT);
+    assert_eq!(selected.get(), 2);
+    assert!(radio.action(Action::Previous));
+    assert_eq!(selected.get(), 1);
+  }
+}
@@ -0,0 +1,359 @@
+use std::time::Duration;
+
+use crate::accessibility::{Accessible, Role};
+use crate::canvas::Style;
+use crate::frame::Frame;
+use crate::input::{Action, PointerEvent, PointerId, PointerPhase};
+use crate::text::{Align, TextSection, VAlign};
+use crate::theme::Palette;
+use crate::widgets::{Rect, Widget};
+
+/// How a [`Dialog`] was closed.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Choice {
+  Confirm,
+  Cancel,
+  /// Nobody answered in time, to be treated like cancelling.
+  Timeout,
+}
+
+type CloseHook = Box<dyn FnMut(Choice)>;
+
+/// How far across the panel a swipe has to go to dismiss the dialog.
+const DISMISS_SWIPE: f32 = 0.4;
+
+/// Linear RGBA colors in straight alpha.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct DialogColors {
+  /// Dims everything behind the dialog.
+  pub backdrop: [f32; 4],
+  pub panel: [f32; 4],
+  pub button: [f32; 4],
+  /// The confirm button, e.g. red for destructive actions.
+  pub confirm: [f32; 4],
+  pub text: [f32; 4],
+}
+
+impl Default for DialogColors {
+  fn default() -> Self {
+    Self {
+      backdrop: [0.0, 0.0, 0.0, 0.6],
+      panel: [0.12, 0.12, 0.14, 1.0],
+      button: [1.0, 1.0, 1.0, 0.15],
+      confirm: [0.2, 0.6, 1.0, 1.0],
+      text: [1.0, 1.0, 1.0, 1.0],
+    }
+  }
+}
+
+/// A confirmation prompt shown on top of everything else, e.g. "Factory
+/// reset?".
+///
+/// While open it is [modal](Widget::modal): it covers its whole rect and
+/// takes all pointer input and actions, so nothing behind it can be used
+/// until it is answered. [`Next`](Action::Next) and
+/// [`Previous`](Action::Previous) move between its buttons,
+/// [`Back`](Action::Back) cancels. Swiping sideways across it dismisses it
+/// like cancelling, for touchscreens out of reach of a precise tap. Give
+/// it the full window and a high z.
+pub struct Dialog {
+  pub title: String,
+  pub message: String,
+  pub confirm_label: String,
+  pub cancel_label: String,
+  /// Closes the dialog with [`Choice::Timeout`] after it has been open
+  /// this long.
+  pub timeout: Option<Duration>,
+  pub colors: DialogColors,
+  open: bool,
+  remaining: Duration,
+  pressed: Option<(PointerId, Choice)>,
+  // None until the first action, like the focus of nodes
+  focus: Option<Choice>,
+  /// Pointer and where it was pressed, anywhere on the dialog.
+  swipe: Option<(PointerId, f32)>,
+  on_close: Option<CloseHook>,
+}
+
+impl Dialog {
+  /// A closed dialog, see [`open`](Self::open).
+  pub fn new(title: impl Into<String>, message: impl Into<String>) -> Self {
+    Self {
+      title: title.into(),
+      message: message.into(),
+      confirm_label: "OK".to_string(),
+      cancel_label: "Cancel".to_string(),
+      timeout: None,
+      colors: DialogColors::default(),
+      open: false,
+      remaining: Duration::ZERO,
+      pressed: None,
+      focus: None,
+      swipe: None,
+      on_close: None,
+    }
+  }
+
+  pub fn with_labels(mut self, confirm: impl Into<String>, cancel: impl Into<String>) -> Self {
+    self.confirm_label = confirm.into();
+    self.cancel_label = cancel.into();
+    self
+  }
+
+  pub fn with_timeout(mut self, timeout: Duration) -> Self {
+    self.timeout = Some(timeout);
+    self
+  }
+
+  pub fn with_colors(mut self, colors: DialogColors) -> Self {
+    self.colors = colors;
+    self
+  }
+
+  /// Called with the answer whenever the dialog closes.
+  pub fn on_close(mut self, callback: impl FnMut(Choice) + 'static) -> Self {
+    self.on_close = Some(Box::new(callback));
+    self
+  }
+
+  pub fn open(&mut self) {
+    self.open = true;
+    self.pressed = None;
+    self.focus = None;
+    self.swipe = None;
+    self.remaining = self.timeout.unwrap_or_default();
+  }
+
+  pub fn is_open(&self) -> bool {
+    self.open
+  }
+
+  pub fn close(&mut self, choice: Choice) {
+    if !self.open {
+      return;
+    }
+    self.open = false;
+    if let Some(on_close) = &mut self.on_close {
+      on_close(choice);
+    }
+  }
+
+  fn panel(rect: Rect) -> Rect {
+    let width = (rect.width * 0.8).min(560.0);
+    let height = (rect.height * 0.6).min(280.0);
+    let [x, y] = rect.center();
+    Rect::new(x - width / 2.0, y - height / 2.0, width, height)
+  }
+
+  /// The cancel and confirm buttons along the bottom of the panel.
+  fn buttons(panel: Rect) -> [(Choice, Rect); 2] {
+    let padding = panel.height * 0.08;
+    let height = panel.height * 0.22;
+    let width = (panel.width - padding * 3.0) / 2.0;
+    let y = panel.y + panel.height - padding - height;
+    [
+      (
+        Choice::Cancel,
+        Rect::new(panel.x + padding, y, width, height),
+      ),
+      (
+        Choice::Confirm,
+        Rect::new(panel.x + padding * 2.0 + width, y, width, height),
+      ),
+    ]
+  }
+
+  fn button_at(rect: Rect, position: [f32; 2]) -> Option<Choice> {
+    Self::buttons(Self::panel(rect))
+      .into_iter()
+      .find(|(_, button)| button.contains(position))
+      .map(|(choice, _)| choice)
+  }
+}
+
+impl Widget for Dialog {
+  fn set_palette(&mut self, palette: &Palette) {
+    self.colors = DialogColors {
+      panel: palette.surface,
+      button: palette.muted,
+      confirm: palette.accent,
+      text: palette.text,
+      ..self.colors
+    };
+  }
+
+  fn update(&mut self, delta: Duration) {
+    if !self.open || self.timeout.is_none() {
+      return;
+    }
+    self.remaining = self.remaining.saturating_sub(delta);
+    if self.remaining.is_zero() {
+      self.close(Choice::Timeout);
+    }
+  }
+
+  // Counting down to the timeout
+  fn animating(&self) -> bool {
+    self.open && self.timeout.is_some()
+  }
+
+  fn draw(&self, frame: &mut Frame, rect: Rect) {
+    if !self.open {
+      return;
+    }
+    let colors = &self.colors;
+    frame.canvas.rect(
+      [rect.x, rect.y],
+      [rect.width, rect.height],
+      Style::fill(colors.backdrop),
+    );
+
+    let panel = Self::panel(rect);
+    frame.canvas.rect(
+      [panel.x, panel.y],
+      [panel.width, panel.height],
+      Style::fill(colors.panel),
+    );
+    let padding = panel.height * 0.08;
+    let [center_x, _] = panel.center();
+    frame.text.queue(
+      &TextSection::new(self.title.as_str())
+        .at(center_x, panel.y + padding)
+        .with_size(panel.height * 0.13)
+        .with_color(colors.text)
+        .with_align(Align::Center, VAlign::Top),
+    );
+    frame.text.queue(
+      &TextSection::new(self.message.as_str())
+        .at(center_x, panel.y + padding + panel.height * 0.2)
+        .with_size(panel.height * 0.09)
+        .with_color(colors.text)
+        .with_align(Align::Center, VAlign::Top)
+        .with_max_width(panel.width - padding * 2.0),
+    );
+
+    for (choice, button) in Self::buttons(panel) {
+      let (mut color, label) = match (choice, self.timeout) {
+        (Choice::Confirm, _) => (colors.confirm, self.confirm_label.clone()),
+        // Counting down to the timeout, which cancels
+        (_, Some(_)) => (
+          colors.button,
+          format!(
+            "{} ({})",
+            self.cancel_label,
+            self.remaining.as_secs_f32().ceil()
+          ),
+        ),
+        (_, None) => (colors.button, self.cancel_label.clone()),
+      };
+      if self.pressed.map(|(_, pressed)| pressed) == Some(choice) {
+        color[3] *= 0.7;
+      }
+      frame.canvas.rect(
+        [button.x, button.y],
+        [button.width, button.height],
+        Style::fill(color),
+      );
+      if self.focus == Some(choice) {
+        frame.canvas.rect(
+          [button.x, button.y],
+          [button.width, button.height],
+          Style::stroke(colors.text, 3.0),
+        );
+      }
+      let [x, y] = button.center();
+      frame.text.queue(
+        &TextSection::new(label)
+          .at(x, y)
+          .with_size(button.height * 0.4)
+          .with_color(colors.text)
+          .with_align(Align::Center, VAlign::Center),
+      );
+    }
+  }
+
+  fn modal(&self) -> bool {
+    self.open
+  }
+
+  fn accessible(&self) -> Option<Accessible> {
+    if !self.open {
+      return None;
+    }
+    let button = |choice, label: &str| {
+      Accessible::new(Role::Button, label).with_selected(self.focus == Some(choice))
+    };
+    let dialog = Accessible::new(Role::Dialog, self.title.as_str())
+      .with_value(self.message.as_str())
+      .with_children(vec![
+        button(Choice::Confirm, &self.confirm_label),
+        button(Choice::Cancel, &self.cancel_label),
+      ]);
+    Some(dialog)
+  }
+
+  // Nothing below shares the pointer while it is open
+  fn captures(&self, _id: PointerId) -> bool {
+    self.open
+  }
+
+  // Every action is used while open, so the focus stays on the dialog
+  fn action(&mut self, action: Action) -> bool {
+    if !self.open {
+      return false;
+    }
+    // Cancel first, answering by accident should do no harm
+    let focus = self.focus.unwrap_or(Choice::Cancel);
+    match action {
+      Action::Next => self.focus = Some(Choice::Confirm),
+      Action::Previous => self.focus = Some(Choice::Cancel),
+      Action::Activate => self.close(focus),
+      Action::Back => self.close(Choice::Cancel),
+      _ => {}
+    }
+    true
+  }
+
+  fn hit(&self, _rect: Rect, _position: [f32; 2]) -> bool {
+    // Presses anywhere are caught while open, that's the point
+    self.open
+  }
+
+  fn pointer(&mut self, event: &PointerEvent, rect: Rect) -> bool {
+    if !self.open {
+      return false;
+    }
+    let pressed = self.pressed.filter(|(id, _)| *id == event.id);
+    let swipe = self.swipe.filter(|(id, _)| *id == event.id);
+    if event.phase == PointerPhase::Down && self.swipe.is_none() {
+      self.swipe = Some((event.id, event.position[0]));
+    } else if matches!(event.phase, PointerPhase::Up | PointerPhase::Cancel) && swipe.is_some() {
+      self.swipe = None;
+    }
+    let swiped = swipe.is_some_and(|(_, start)| {
+      event.phase == PointerPhase::Up
+        && (event.position[0] - start).abs() >= Self::panel(rect).width * DISMISS_SWIPE
+    });
+    if swiped {
+      self.pressed = None;
+      self.close(Choice::Cancel);
+      return true;
+    }
+    match event.phase {
+      PointerPhase::Down if self.pressed.is_none() => {
+        self.pressed = Self::button_at(rect, event.position).map(|choice| (event.id, choice));
+      }
+      PointerPhase::Up if pressed.is_some() => {
+        self.pressed = None;
+        // Only a tap that ends on the button it started on counts
+        let choice = pressed.map(|(_, choice)| choice);
+        if let Some(choice) = choice.filter(|c| Self::button_at(rect, event.position) == Some(*c)) {
+          self.close(choice);
+        }
+      }
+      PointerPhase::Cancel if pressed.is_some() => self.pressed = None,
+      _ => {}
+    }
+    true
+  }
+}
@@ -0,0 +1,344 @@
+use std::f32::consts::{PI, TAU};
+use std::time::Duration;
+
+use crate::anim::{self, SETTLED};
+use crate::canvas::Style;
+use crate::data::Telemetry;
+use crate::frame::Frame;
+use crate::locale::NumberFormat;
+use crate::text::{Align, TextSection, VAlign};
+use crate::theme::Palette;
+use crate::widgets::{Rect, Widget};
+
+/// kW below which nothing is flowing.
+const IDLE_POWER: f32 = 0.5;
+/// State of charge in percent below which the charge is shown as low.
+const LOW_CHARGE: f32 = 15.0;
+const MILES_PER_KM: f32 = 0.621_371;
+
+/// Linear RGBA colors in straight alpha.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct EnergyColors {
+  /// The empty part of the charge arc and the flow line.
+  pub track: [f32; 4],
+  pub charge: [f32; 4],
+  /// The charge arc while the charge is low.
+  pub low: [f32; 4],
+  /// Flowing from the battery to the motor.
+  pub drive: [f32; 4],
+  /// Flowing back into the battery.
+  pub regen: [f32; 4],
+  pub text: [f32; 4],
+}
+
+impl Default for EnergyColors {
+  fn default() -> Self {
+    Self {
+      track: [1.0, 1.0, 1.0, 0.3],
+      charge: [0.3, 0.85, 0.4, 1.0],
+      low: [0.9, 0.1, 0.1, 1.0],
+      drive: [0.2, 0.6, 1.0, 1.0],
+      regen: [0.3, 0.85, 0.4, 1.0],
+      text: [1.0, 1.0, 1.0, 1.0],
+    }
+  }
+}
+
+/// Where the energy of an electric vehicle flows: the battery with its
+/// state of charge and range on the left, the motor with the power on the
+/// right and dashes running between them, towards the motor while driving
+/// and back into the battery while regenerating.
+///
+/// Shows [`Telemetry::state_of_charge`], [`Telemetry::battery_power`] and
+/// [`Telemetry::range`]. The dashes run faster the more power flows.
+#[derive(Clone, Debug)]
+pub struct EnergyFlow {
+  /// kW at which the dashes run fastest.
+  pub max_power: f32,
+  /// Shows the range in miles rather than km.
+  pub miles: bool,
+  /// How the charge, range and power are written, like with a decimal
+  /// comma.
+  pub number_format: NumberFormat,
+  pub colors: EnergyColors,
+  /// How quickly the charge arc catches up with the charge, per second.
+  pub response: f32,
+  charge: Option<f32>,
+  // Normalized, none until the charge is first known so it doesn't fill
+  // up from empty
+  shown_charge: Option<f32>,
+  power: Option<f32>,
+  range: Option<f32>,
+  // Dashes moved along the line, in dash spacings
+  flow: f32,
+  // Turns of the motor's rotor
+  rotor: f32,
+}
+
+impl Default for EnergyFlow {
+  fn default() -> Self {
+    Self::new()
+  }
+}
+
+impl EnergyFlow {
+  /// Dashes running fastest at 150 kW, the range in km.
+  pub fn new() -> Self {
+    Self {
+      max_power: 150.0,
+      miles: false,
+      number_format: NumberFormat::default(),
+      colors: EnergyColors::default(),
+      response: 4.0,
+      charge: None,
+      shown_charge: None,
+      power: None,
+      range: None,
+      flow: 0.0,
+      rotor: 0.0,
+    }
+  }
+
+  pub fn with_max_power(mut self, max_power: f32) -> Self {
+    self.max_power = max_power;
+    self
+  }
+
+  pub fn miles(mut self) -> Self {
+    self.miles = true;
+    self
+  }
+
+  pub fn with_number_format(mut self, number_format: NumberFormat) -> Self {
+    self.number_format = number_format;
+    self
+  }
+
+  pub fn with_colors(mut self, colors: EnergyColors) -> Self {
+    self.colors = colors;
+    self
+  }
+
+  /// Dash spacings per second, negative towards the battery.
+  fn flow_rate(&self) -> f32 {
+    match self.power {
+      Some(power) if power.abs() >= IDLE_POWER => {
+        let share = (power.abs() / self.max_power).min(1.0);
+        (0.5 + 2.5 * share) * power.signum()
+      }
+      _ => 0.0,
+    }
+  }
+
+  fn range_text(&self) -> String {
+    match self.range {
+      Some(range) if self.miles => {
+        format!("{} mi", self.number_format.format(range * MILES_PER_KM, 0))
+      }
+      Some(range) => format!("{} km", self.number_format.format(range, 0)),
+      None => "-- km".to_string(),
+    }
+  }
+}
+
+impl Widget for EnergyFlow {
+  fn set_palette(&mut self, palette: &Palette) {
+    self.colors.track = palette.muted;
+    self.colors.drive = palette.accent;
+    self.colors.low = palette.warning;
+    self.colors.text = palette.text;
+  }
+
+  fn set_telemetry(&mut self, telemetry: &Telemetry) {
+    self.charge = telemetry.state_of_charge;
+    self.power = telemetry.battery_power;
+    self.range = telemetry.range;
+  }
+
+  fn update(&mut self, delta: Duration) {
+    if let Some(charge) = self.charge {
+      let target = charge / 100.0;
+      let shown = self.shown_charge.unwrap_or(target);
+      self.shown_charge = Some(anim::follow(shown, target, self.response, delta));
+    }
+    let rate = self.flow_rate();
+    // Wrapped so the dashes stay exact however long it runs
+    self.flow = (self.flow + rate * delta.as_secs_f32()).rem_euclid(1.0);
+    self.rotor = (self.rotor + rate.abs() * 0.5 * delta.as_secs_f32()).rem_euclid(1.0);
+  }
+
+  fn animating(&self) -> bool {
+    let settled = match (self.charge, self.shown_charge) {
+      (Some(charge), Some(shown)) => (charge / 100.0 - shown).abs() <= SETTLED,
+      _ => true,
+    };
+    self.flow_rate() != 0.0 || !settled
+  }
+
+  fn draw(&self, frame: &mut Frame, rect: Rect) {
+    let colors = &self.colors;
+    let r = (rect.height * 0.4).min(rect.width * 0.2);
+    let [_, cy] = rect.center();
+    let battery = [rect.x + rect.width * 0.25, cy - r * 0.1];
+    let motor = [rect.x + rect.width * 0.78, battery[1]];
+    let canvas = &mut *frame.canvas;
+
+    // The charge as an arc open at the bottom, like the gauges
+    let (start, sweep) = (0.75 * PI, 1.5 * PI);
+    let width = r * 0.12;
+    canvas.arc(
+      battery,
+      r * 0.9,
+      start,
+      sweep,
+      Style::stroke(colors.track, width),
+    );
+    let charge_color = match self.charge {
+      Some(charge) if charge < LOW_CHARGE => colors.low,
+      _ => colors.charge,
+    };
+    let shown = self.shown_charge.unwrap_or(0.0).clamp(0.0, 1.0);
+    if self.charge.is_some() && shown > SETTLED {
+      canvas.arc(
+        battery,
+        r * 0.9,
+        start,
+        sweep * shown,
+        Style::stroke(charge_color, width),
+      );
+    }
+
+    // A battery filling up from the bottom
+    let (body_w, body_h) = (r * 0.42, r * 0.7);
+    let body = [battery[0] - body_w / 2.0, battery[1] - r * 0.5];
+    canvas.rect(
+      [battery[0] - r * 0.09, body[1] - r * 0.07],
+      [r * 0.18, r * 0.07],
+      Style::fill(colors.text),
+    );
+    canvas.rounded_rect(
+      body,
+      [body_w, body_h],
+      r * 0.05,
+      Style::stroke(colors.text, r * 0.04),
+    );
+    let inset = r * 0.07;
+    let level = (body_h - 2.0 * inset) * shown;
+    if level > 0.0 {
+      canvas.rect(
+        [body[0] + inset, body[1] + body_h - inset - level],
+        [body_w - 2.0 * inset, level],
+        Style::fill(charge_color),
+      );
+    }
+
+    let flow_color = if self.flow_rate() < 0.0 {
+      colors.regen
+    } else {
+      colors.drive
+    };
+    let from = battery[0] + r * 1.05;
+    let to = motor[0] - r * 0.6;
+    let y = battery[1];
+    canvas.line([from, y], [to, y], colors.track, r * 0.06);
+    if self.flow_rate() != 0.0 {
+      let spacing = r * 0.35;
+      let dash = spacing * 0.45;
+      let length = to - from;
+      let count = (length / spacing).ceil() as usize + 1;
+      for index in 0..count {
+        // Moving with the flow, which runs backwards while regenerating
+        let start = (index as f32 - 1.0 + self.flow) * spacing;
+        let (start, end) = (start.max(0.0), (start + dash).min(length));
+        if end > start {
+          canvas.line([from + start, y], [from + end, y], flow_color, r * 0.08);
+        }
+      }
+    }
+
+    // The motor with a rotor turning while power flows
+    canvas.circle(motor, r * 0.45, Style::stroke(colors.text, r * 0.06));
+    for spoke in 0..3 {
+      let angle = (self.rotor + spoke as f32 / 3.0) * TAU;
+      let tip = [
+        motor[0] + angle.cos() * r * 0.32,
+        motor[1] + angle.sin() * r * 0.32,
+      ];
+      canvas.line(motor, tip, colors.text, r * 0.07);
+    }
+
+    let text = &mut *frame.text;
+    let charge = match self.charge {
+      Some(charge) => format!("{}%", self.number_format.format(charge, 0)),
+      None => "--%".to_string(),
+    };
+    text.queue(
+      &TextSection::new(charge)
+        .at(battery[0], battery[1] + r * 0.45)
+        .with_size(r * 0.24)
+        .with_color(colors.text)
+        .with_align(Align::Center, VAlign::Center),
+    );
+    text.queue(
+      &TextSection::new(self.range_text())
+        .at(battery[0], battery[1] + r * 1.05)
+        .with_size(r * 0.2)
+        .with_color(colors.text)
+        .with_align(Align::Center, VAlign::Center),
+    );
+    let kw = |power| format!("{} kW", self.number_format.format(power, 0));
+    let (power, mode) = match self.power {
+      Some(power) if self.flow_rate() < 0.0 => (kw(power.abs()), "Regen"),
+      Some(power) if self.flow_rate() > 0.0 => (kw(power), "Drive"),
+      Some(_) => ("0 kW".to_string(), "Idle"),
+      None => ("-- kW".to_string(), ""),
+    };
+    text.queue(
+      &TextSection::new(power)
+        .at(motor[0], motor[1] + r * 0.75)
+        .with_size(r * 0.24)
+        .with_color(colors.text)
+        .with_align(Align::Center, VAlign::Center),
+    );
+    text.queue(
+      &TextSection::new(mode)
+        .at(motor[0], motor[1] + r * 1.05)
+        .with_size(r * 0.16)
+        .with_color(flow_color)
+        .with_align(Align::Center, VAlign::Center),
+    );
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn flow(power: Option<f32>) -> EnergyFlow {
+    let mut flow = EnergyFlow::new();
+    flow.set_telemetry(&Telemetry {
+      battery_power: power,
+      ..Default::default()
+    });
+    flow
+  }
+
+  #[test]
+  fn flows_faster_with_more_power() {
+    assert_eq!(flow(None).flow_rate(), 0.0);
+    assert_eq!(flow(Some(0.2)).flow_rate(), 0.0);
+    assert!(!flow(Some(0.2)).animating());
+    assert!(flow(Some(30.0)).flow_rate() < flow(Some(90.0)).flow_rate());
+    assert_eq!(flow(Some(300.0)).flow_rate(), 3.0);
+    assert_eq!(flow(Some(-300.0)).flow_rate(), -3.0);
+  }
+
+  #[test]
+  fn shows_the_range_in_either_unit() {
+    let mut flow = flow(None);
+    assert_eq!(flow.range_text(), "-- km");
+    flow.range = Some(300.0);
+    assert_eq!(flow.range_text(), "300 km");
+    assert_eq!(flow.miles().range_text(), "186 mi");
+  }
+}
@@ -0,0 +1,369 @@
+use std::time::Duration;
+
+use crate::anim::{self, SETTLED};
+use crate::canvas::{Canvas, Style};
+use crate::data::cruise::{Cruise, MAX_GAP};
+use crate::data::Telemetry;
+use crate::frame::Frame;
+use crate::locale::NumberFormat;
+use crate::text::{Align, TextSection, VAlign};
+use crate::theme::Palette;
+use crate::widgets::{Rect, Widget};
+
+/// Blinks per second while the gap is too short.
+const BLINK_RATE: f32 = 3.0;
+const MILES_PER_KM: f32 = 0.621_371;
+
+/// How close the vehicle ahead is, by the time gap to it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Level {
+  Normal,
+  Caution,
+  Alert,
+}
+
+/// Linear RGBA colors in straight alpha.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct GapColors {
+  pub lane: [f32; 4],
+  /// The own vehicle and the one ahead.
+  pub vehicle: [f32; 4],
+  /// Gap bars while engaged.
+  pub engaged: [f32; 4],
+  /// Gap bars while not engaged.
+  pub idle: [f32; 4],
+  pub caution: [f32; 4],
+  pub alert: [f32; 4],
+  pub text: [f32; 4],
+}
+
+impl Default for GapColors {
+  fn default() -> Self {
+    Self {
+      lane: [1.0, 1.0, 1.0, 0.3],
+      vehicle: [1.0, 1.0, 1.0, 1.0],
+      engaged: [0.2, 0.6, 1.0, 1.0],
+      idle: [1.0, 1.0, 1.0, 0.5],
+      caution: [1.0, 0.75, 0.2, 1.0],
+      alert: [0.9, 0.1, 0.1, 1.0],
+      text: [1.0, 1.0, 1.0, 1.0],
+    }
+  }
+}
+
+/// The lane ahead for adaptive cruise control: the own vehicle at the
+/// bottom with the chosen following gap as bars in front of it, the vehicle
+/// ahead at its distance and the set speed beside them, from
+/// [`Telemetry::cruise`].
+///
+/// The bars turn to the caution color once the time gap to the vehicle
+/// ahead drops below `caution_gap` and blink in the alert color below
+/// `alert_gap`. The vehicle ahead glides to its distance rather than
+/// jumping.
+#[derive(Clone, Debug)]
+pub struct FollowingGap {
+  /// m at the top of the lane, vehicles further away are drawn there.
+  pub max_distance: f32,
+  /// Seconds below which the gap is shown in the caution color.
+  pub caution_gap: f32,
+  /// Seconds below which the gap alerts.
+  pub alert_gap: f32,
+  /// Shows the set speed in mph rather than km/h.
+  pub miles: bool,
+  /// How the set speed and distance are written.
+  pub number_format: NumberFormat,
+  pub colors: GapColors,
+  /// How quickly the vehicle ahead catches up with its distance, per
+  /// second.
+  pub response: f32,
+  cruise: Cruise,
+  speed: f32,
+  // Normalized along the lane, none while there is nothing ahead
+  lead: Option<f32>,
+  time: Duration,
+}
+
+impl Default for FollowingGap {
+  fn default() -> Self {
+    Self::new()
+  }
+}
+
+impl FollowingGap {
+  /// 100 m of lane, cautioning below 1.2 s and alerting below 0.6 s.
+  pub fn new() -> Self {
+    Self {
+      max_distance: 100.0,
+      caution_gap: 1.2,
+      alert_gap: 0.6,
+      miles: false,
+      number_format: NumberFormat::default(),
+      colors: GapColors::default(),
+      response: 6.0,
+      cruise: Cruise::default(),
+      speed: 0.0,
+      lead: None,
+      time: Duration::ZERO,
+    }
+  }
+
+  pub fn with_thresholds(mut self, caution_gap: f32, alert_gap: f32) -> Self {
+    self.caution_gap = caution_gap;
+    self.alert_gap = alert_gap;
+    self
+  }
+
+  pub fn with_max_distance(mut self, max_distance: f32) -> Self {
+    self.max_distance = max_distance;
+    self
+  }
+
+  pub fn miles(mut self) -> Self {
+    self.miles = true;
+    self
+  }
+
+  pub fn with_number_format(mut self, number_format: NumberFormat) -> Self {
+    self.number_format = number_format;
+    self
+  }
+
+  pub fn with_colors(mut self, colors: GapColors) -> Self {
+    self.colors = colors;
+    self
+  }
+
+  fn level(&self) -> Level {
+    match self.cruise.time_gap(self.speed) {
+      Some(gap) if gap < self.alert_gap => Level::Alert,
+      Some(gap) if gap < self.caution_gap => Level::Caution,
+      _ => Level::Normal,
+    }
+  }
+
+  /// Where the vehicle ahead is along the lane, none without one.
+  fn lead_target(&self) -> Option<f32> {
+    if self.cruise.lead != Some(true) {
+      return None;
+    }
+    let distance = self.cruise.lead_distance?;
+    Some((distance / self.max_distance).clamp(0.0, 1.0))
+  }
+
+  fn set_speed_text(&self) -> (String, &'static str) {
+    let unit = if self.miles { "mph" } else { "km/h" };
+    let speed = match self.cruise.set_speed {
+      Some(speed) if self.miles => self.number_format.format(speed * MILES_PER_KM, 0),
+      Some(speed) => self.number_format.format(speed, 0),
+      None => "--".to_string(),
+    };
+    (speed, unit)
+  }
+}
+
+impl Widget for FollowingGap {
+  fn set_palette(&mut self, palette: &Palette) {
+    self.colors.lane = palette.muted;
+    self.colors.vehicle = palette.text;
+    self.colors.engaged = palette.accent;
+    self.colors.idle = palette.muted;
+    self.colors.alert = palette.warning;
+    self.colors.text = palette.text;
+  }
+
+  fn set_telemetry(&mut self, telemetry: &Telemetry) {
+    self.cruise = telemetry.cruise;
+    self.speed = telemetry.speed.unwrap_or(0.0);
+  }
+
+  fn update(&mut self, delta: Duration) {
+    self.lead = match (self.lead, self.lead_target()) {
+      (Some(lead), Some(target)) => Some(anim::follow(lead, target, self.response, delta)),
+      // Appears right where it is
+      (None, target) | (_, target @ None) => target,
+    };
+    if self.level() == Level::Alert {
+      self.time += delta;
+    } else {
+      self.time = Duration::ZERO;
+    }
+  }
+
+  fn animating(&self) -> bool {
+    let gliding = match (self.lead, self.lead_target()) {
+      (Some(lead), Some(target)) => (lead - target).abs() > SETTLED,
+      _ => false,
+    };
+    gliding || self.level() == Level::Alert
+  }
+
+  fn draw(&self, frame: &mut Frame, rect: Rect) {
+    let colors = &self.colors;
+    let level = self.level();
+    let blink_off = (self.time.as_secs_f32() * BLINK_RATE).fract() >= 0.6;
+    let bars = match level {
+      Level::Normal if self.cruise.engaged == Some(true) => colors.engaged,
+      Level::Normal => colors.idle,
+      Level::Caution => colors.caution,
+      Level::Alert if blink_off => colors.idle,
+      Level::Alert => colors.alert,
+    };
+
+    // The lane narrowing towards the top, like seen from behind
+    let width = (rect.width * 0.5).min(rect.height * 0.6);
+    let center = rect.x + rect.width * 0.38;
+    let (bottom, top) = (rect.y + rect.height * 0.95, rect.y + rect.height * 0.05);
+    let lane = Lane {
+      center,
+      bottom,
+      top,
+      width,
+    };
+    let canvas = &mut *frame.canvas;
+    for side in [-1.0, 1.0] {
+      canvas.line(
+        lane.at(side, 0.0),
+        lane.at(side, 1.0),
+        colors.lane,
+        width * 0.03,
+      );
+    }
+
+    // The own vehicle takes up the bottom of the lane
+    let own = 0.22;
+    draw_vehicle(canvas, &lane, 0.02, own - 0.04, colors.vehicle);
+    let gap = self.cruise.gap.unwrap_or(0).min(MAX_GAP);
+    let room = self.lead.map_or(1.0, |lead| lead.max(own + 0.1)) - own;
+    for bar in 0..gap {
+      let t = own + room * (bar as f32 + 0.5) / (MAX_GAP as f32 + 1.0);
+      let [left, y] = lane.at(-0.7, t);
+      let [right, _] = lane.at(0.7, t);
+      canvas.line([left, y], [right, y], bars, width * 0.05 * lane.scale(t));
+    }
+    if let Some(lead) = self.lead {
+      let lead = lead.max(own + 0.1);
+      let color = match level {
+        Level::Alert if !blink_off => colors.alert,
+        _ => colors.vehicle,
+      };
+      draw_vehicle(canvas, &lane, lead, lead + 0.16, color);
+      if let Some(distance) = self.cruise.lead_distance {
+        let [x, y] = lane.at(1.0, lead + 0.08);
+        frame.text.queue(
+          &TextSection::new(format!("{} m", self.number_format.format(distance, 0)))
+            .at(x + width * 0.08, y)
+            .with_size(rect.height * 0.06)
+            .with_color(colors.text)
+            .with_align(Align::Left, VAlign::Center),
+        );
+      }
+    }
+
+    let (speed, unit) = self.set_speed_text();
+    let x = rect.x + rect.width * 0.82;
+    let color = match self.cruise.engaged {
+      Some(true) => colors.engaged,
+      _ => colors.idle,
+    };
+    frame.text.queue(
+      &TextSection::new(speed)
+        .at(x, rect.y + rect.height * 0.42)
+        .with_size(rect.height * 0.22)
+        .with_color(color)
+        .with_align(Align::Center, VAlign::Center),
+    );
+    frame.text.queue(
+      &TextSection::new(unit)
+        .at(x, rect.y + rect.height * 0.58)
+        .with_size(rect.height * 0.08)
+        .with_color(colors.text)
+        .with_align(Align::Center, VAlign::Center),
+    );
+  }
+}
+
+/// Maps `t` from the bottom of the lane at zero to its top at one.
+struct Lane {
+  center: f32,
+  bottom: f32,
+  top: f32,
+  width: f32,
+}
+
+impl Lane {
+  /// How much smaller things are drawn at `t`.
+  fn scale(&self, t: f32) -> f32 {
+    anim::lerp(1.0, 0.4, t)
+  }
+
+  /// `side` from -1 on the left to 1 on the right edge.
+  fn at(&self, side: f32, t: f32) -> [f32; 2] {
+    [
+      self.center + side * self.width / 2.0 * self.scale(t),
+      anim::lerp(self.bottom, self.top, t),
+    ]
+  }
+}
+
+/// A vehicle from behind between `from` and `to` along the lane.
+fn draw_vehicle(canvas: &mut Canvas, lane: &Lane, from: f32, to: f32, color: [f32; 4]) {
+  let [left, bottom] = lane.at(-0.55, from);
+  let [right, top] = lane.at(0.55, to);
+  let (width, height) = (right - left, bottom - top);
+  canvas.rounded_rect(
+    [left, top],
+    [width, height],
+    width * 0.18,
+    Style::fill(color),
+  );
+  // Rear window
+  let [r, g, b, a] = color;
+  canvas.rounded_rect(
+    [left + width * 0.15, top + height * 0.12],
+    [width * 0.7, height * 0.3],
+    width * 0.08,
+    Style::fill([r * 0.3, g * 0.3, b * 0.3, a]),
+  );
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn following(distance: f32, speed: f32) -> FollowingGap {
+    let mut gap = FollowingGap::new();
+    gap.set_telemetry(&Telemetry {
+      speed: Some(speed),
+      cruise: Cruise {
+        engaged: Some(true),
+        lead: Some(true),
+        lead_distance: Some(distance),
+        ..Default::default()
+      },
+      ..Default::default()
+    });
+    gap
+  }
+
+  #[test]
+  fn levels_follow_the_time_gap() {
+    // 25 m/s
+    assert_eq!(following(50.0, 90.0).level(), Level::Normal);
+    assert_eq!(following(25.0, 90.0).level(), Level::Caution);
+    assert_eq!(following(10.0, 90.0).level(), Level::Alert);
+    assert!(following(10.0, 90.0).animating());
+    // Standing close behind is fine
+    assert_eq!(following(3.0, 0.0).level(), Level::Normal);
+  }
+
+  #[test]
+  fn the_vehicle_ahead_appears_where_it_is() {
+    let mut gap = following(50.0, 90.0);
+    gap.update(Duration::from_millis(16));
+    assert_eq!(gap.lead, Some(0.5));
+    assert!(!gap.animating());
+    gap.set_telemetry(&Telemetry::default());
+    gap.update(Duration::from_millis(16));
+    assert_eq!(gap.lead, None);
+  }
+}
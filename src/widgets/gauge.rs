@@ -0,0 +1,396 @@
+use std::f32::consts::{FRAC_PI_2, PI};
+use std::time::Duration;
+
+use crate::accessibility::{Accessible, Role};
+use crate::anim::{self, SETTLED};
+use crate::canvas::Style;
+use crate::frame::Frame;
+use crate::locale::NumberFormat;
+use crate::text::{Align, TextSection, VAlign};
+use crate::theme::Palette;
+use crate::widgets::{Rect, Widget};
+
+/// A colored band along the scale, e.g. a redline. Bounds are normalized
+/// values like the gauge's own.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Zone {
+  pub from: f32,
+  pub to: f32,
+  /// Linear RGBA in straight alpha.
+  pub color: [f32; 4],
+}
+
+impl Zone {
+  pub fn new(from: f32, to: f32, color: [f32; 4]) -> Self {
+    Self { from, to, color }
+  }
+}
+
+/// Digital display of the value in the center of the gauge, mapping the
+/// normalized value onto `min..=max`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Readout {
+  pub min: f32,
+  pub max: f32,
+  pub unit: String,
+  pub decimals: usize,
+  pub number_format: NumberFormat,
+}
+
+impl Readout {
+  pub fn new(min: f32, max: f32, unit: impl Into<String>) -> Self {
+    Self {
+      min,
+      max,
+      unit: unit.into(),
+      decimals: 0,
+      number_format: NumberFormat::default(),
+    }
+  }
+
+  pub fn with_decimals(mut self, decimals: usize) -> Self {
+    self.decimals = decimals;
+    self
+  }
+
+  pub fn with_number_format(mut self, number_format: NumberFormat) -> Self {
+    self.number_format = number_format;
+    self
+  }
+
+  fn format(&self, value: f32) -> String {
+    let value = self.min + (self.max - self.min) * value;
+    self.number_format.format(value, self.decimals)
+  }
+}
+
+/// Linear RGBA colors in straight alpha.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct GaugeColors {
+  /// Background disc, none to leave the gauge see-through.
+  pub face: Option<[f32; 4]>,
+  pub ticks: [f32; 4],
+  pub needle: [f32; 4],
+  pub text: [f32; 4],
+}
+
+impl Default for GaugeColors {
+  fn default() -> Self {
+    Self {
+      face: None,
+      ticks: [1.0, 1.0, 1.0, 0.9],
+      needle: [1.0, 0.3, 0.1, 1.0],
+      text: [1.0, 1.0, 1.0, 1.0],
+    }
+  }
+}
+
+const REDLINE: [f32; 4] = [0.9, 0.1, 0.1, 0.8];
+
+/// A dial with a needle sweeping over a tick scale.
+///
+/// The value is normalized to `0.0..=1.0`; the needle follows it smoothly
+/// rather than jumping, so noisy sensor data doesn't make it jitter.
+#[derive(Clone, Debug)]
+pub struct RadialGauge {
+  /// Angle of the scale's start in radians, clockwise from the positive x
+  /// axis.
+  pub start_angle: f32,
+  /// Angle covered by the scale, clockwise.
+  pub sweep: f32,
+  /// Number of labelled intervals on the scale.
+  pub major_ticks: u32,
+  /// Number of intervals every major interval is split into.
+  pub minor_ticks: u32,
+  /// Drawn at the major ticks, starting at the scale's start.
+  pub tick_labels: Vec<String>,
+  pub zones: Vec<Zone>,
+  /// Shown above the center, e.g. "km/h".
+  pub label: Option<String>,
+  pub readout: Option<Readout>,
+  pub colors: GaugeColors,
+  /// How quickly the needle catches up with the value, per second. Zero
+  /// moves it immediately.
+  pub response: f32,
+  value: f32,
+  needle: f32,
+  // Shows dashes rather than a frozen value
+  stale: bool,
+  // Color of the zones following the palette's warnings
+  warning: [f32; 4],
+}
+
+impl Default for RadialGauge {
+  fn default() -> Self {
+    Self::new()
+  }
+}
+
+impl RadialGauge {
+  /// A 270° scale open at the bottom with ten intervals and no labels.
+  pub fn new() -> Self {
+    Self {
+      start_angle: 0.75 * PI,
+      sweep: 1.5 * PI,
+      major_ticks: 10,
+      minor_ticks: 5,
+      tick_labels: Vec::new(),
+      zones: Vec::new(),
+      label: None,
+      readout: None,
+      colors: GaugeColors::default(),
+      response: 8.0,
+      value: 0.0,
+      needle: 0.0,
+      stale: false,
+      warning: REDLINE,
+    }
+  }
+
+  /// Speed from zero to `max` km/h, labelled every 20 km/h. `max` is
+  /// rounded up to the next label.
+  pub fn speedometer(max: f32) -> Self {
+    let majors = (max / 20.0).ceil().max(1.0) as u32;
+    let max = (majors * 20) as f32;
+    Self::new()
+      .with_scale(majors, 4)
+      .with_tick_labels((0..=majors).map(|i| (i * 20).to_string()))
+      .with_label("km/h")
+      .with_readout(Readout::new(0.0, max, "km/h"))
+  }
+
+  /// Engine speed from zero to `max` rpm, labelled in thousands with the
+  /// range from `redline` upwards marked. `max` is rounded up to the next
+  /// thousand.
+  pub fn tachometer(max: f32, redline: f32) -> Self {
+    let majors = (max / 1000.0).ceil().max(1.0) as u32;
+    let max = (majors * 1000) as f32;
+    Self::new()
+      .with_scale(majors, 4)
+      .with_tick_labels((0..=majors).map(|i| i.to_string()))
+      .with_label("x1000 rpm")
+      .with_zone(Zone::new(redline / max, 1.0, REDLINE))
+      .with_readout(Readout::new(0.0, max, "rpm"))
+  }
+
+  /// Fuel level from empty to full, with the reserve marked.
+  pub fn fuel() -> Self {
+    Self::new()
+      .with_sweep(1.25 * PI, 0.5 * PI)
+      .with_scale(4, 2)
+      .with_tick_labels(["E", "", "½", "", "F"])
+      .with_zone(Zone::new(0.0, 0.125, REDLINE))
+  }
+
+  /// Coolant temperature from cold to hot, with overheating marked.
+  pub fn temperature() -> Self {
+    Self::new()
+      .with_sweep(1.25 * PI, 0.5 * PI)
+      .with_scale(2, 4)
+      .with_tick_labels(["C", "", "H"])
+      .with_zone(Zone::new(0.85, 1.0, REDLINE))
+  }
+
+  pub fn with_sweep(mut self, start_angle: f32, sweep: f32) -> Self {
+    self.start_angle = start_angle;
+    self.sweep = sweep;
+    self
+  }
+
+  pub fn with_scale(mut self, major_ticks: u32, minor_ticks: u32) -> Self {
+    self.major_ticks = major_ticks;
+    self.minor_ticks = minor_ticks;
+    self
+  }
+
+  pub fn with_tick_labels<S: Into<String>>(mut self, labels: impl IntoIterator<Item = S>) -> Self {
+    self.tick_labels = labels.into_iter().map(Into::into).collect();
+    self
+  }
+
+  pub fn with_zone(mut self, zone: Zone) -> Self {
+    self.zones.push(zone);
+    self
+  }
+
+  pub fn with_label(mut self, label: impl Into<String>) -> Self {
+    self.label = Some(label.into());
+    self
+  }
+
+  pub fn with_readout(mut self, readout: Readout) -> Self {
+    self.readout = Some(readout);
+    self
+  }
+
+  pub fn with_colors(mut self, colors: GaugeColors) -> Self {
+    self.colors = colors;
+    self
+  }
+
+  pub fn with_response(mut self, response: f32) -> Self {
+    self.response = response;
+    self
+  }
+
+  /// Sets the value the needle moves to, clamped to `0.0..=1.0`.
+  pub fn set_value(&mut self, value: f32) {
+    self.value = value.clamp(0.0, 1.0);
+  }
+
+  pub fn value(&self) -> f32 {
+    self.value
+  }
+
+  fn angle(&self, value: f32) -> f32 {
+    self.start_angle + self.sweep * value
+  }
+}
+
+impl Widget for RadialGauge {
+  fn set_palette(&mut self, palette: &Palette) {
+    self.colors = GaugeColors {
+      face: Some(palette.surface),
+      ticks: palette.muted,
+      needle: palette.accent,
+      text: palette.text,
+    };
+    // Zones in the warning color follow it, others were colored on purpose
+    for zone in &mut self.zones {
+      if zone.color == self.warning {
+        zone.color = palette.warning;
+      }
+    }
+    self.warning = palette.warning;
+  }
+
+  fn update(&mut self, delta: Duration) {
+    self.needle = anim::follow(self.needle, self.value, self.response, delta);
+  }
+
+  fn animating(&self) -> bool {
+    (self.value - self.needle).abs() > SETTLED
+  }
+
+  fn set_value(&mut self, value: f32) {
+    self.set_value(value);
+  }
+
+  fn set_stale(&mut self, stale: bool) {
+    self.stale = stale;
+  }
+
+  // The value it settles at rather than where the needle is
+  fn accessible(&self) -> Option<Accessible> {
+    let unit = self.readout.as_ref().map(|readout| readout.unit.as_str());
+    let name = self.label.as_deref().or(unit).unwrap_or_default();
+    let value = match &self.readout {
+      _ if self.stale => "unknown".to_string(),
+      Some(readout) => format!("{} {}", readout.format(self.value), readout.unit),
+      None => format!("{:.0}%", self.value * 100.0),
+    };
+    Some(Accessible::new(Role::Meter, name).with_value(value))
+  }
+
+  fn draw(&self, frame: &mut Frame, rect: Rect) {
+    let center = rect.center();
+    let radius = rect.min_side() / 2.0;
+    let at = |angle: f32, distance: f32| {
+      [
+        center[0] + distance * radius * angle.cos(),
+        center[1] + distance * radius * angle.sin(),
+      ]
+    };
+    let colors = &self.colors;
+
+    if let Some(face) = colors.face {
+      frame.canvas.circle(center, radius, Style::fill(face));
+    }
+
+    for zone in &self.zones {
+      frame.canvas.arc(
+        center,
+        radius * 0.9,
+        self.angle(zone.from),
+        self.sweep * (zone.to - zone.from),
+        Style::stroke(zone.color, radius * 0.06),
+      );
+    }
+
+    let minor = self.minor_ticks.max(1);
+    let steps = self.major_ticks.max(1) * minor;
+    for i in 0..=steps {
+      let angle = self.angle(i as f32 / steps as f32);
+      let (inner, width) = if i % minor == 0 {
+        (0.78, 0.025)
+      } else {
+        (0.87, 0.01)
+      };
+      frame.canvas.line(
+        at(angle, inner),
+        at(angle, 0.95),
+        colors.ticks,
+        radius * width,
+      );
+    }
+
+    for (i, label) in self.tick_labels.iter().enumerate() {
+      let angle = self.angle(i as f32 / self.major_ticks.max(1) as f32);
+      let [x, y] = at(angle, 0.62);
+      frame.text.queue(
+        &TextSection::new(label.as_str())
+          .at(x, y)
+          .with_size(radius * 0.14)
+          .with_color(colors.text)
+          .with_align(Align::Center, VAlign::Center),
+      );
+    }
+
+    if let Some(label) = &self.label {
+      frame.text.queue(
+        &TextSection::new(label.as_str())
+          .at(center[0], center[1] - radius * 0.3)
+          .with_size(radius * 0.12)
+          .with_color(colors.text)
+          .with_align(Align::Center, VAlign::Center),
+      );
+    }
+
+    if let Some(readout) = &self.readout {
+      let value = if self.stale {
+        "--".to_string()
+      } else {
+        // Counts along with the needle rather than jumping ahead of it
+        readout.format(self.needle)
+      };
+      frame.text.queue(
+        &TextSection::new(value)
+          .at(center[0], center[1] + radius * 0.45)
+          .with_size(radius * 0.24)
+          .with_color(colors.text)
+          .with_align(Align::Center, VAlign::Center),
+      );
+      frame.text.queue(
+        &TextSection::new(readout.unit.as_str())
+          .at(center[0], center[1] + radius * 0.64)
+          .with_size(radius * 0.1)
+          .with_color(colors.text)
+          .with_align(Align::Center, VAlign::Center),
+      );
+    }
+
+    let angle = self.angle(self.needle);
+    frame.canvas.polygon(
+      &[
+        at(angle + FRAC_PI_2, 0.035),
+        at(angle, 0.85),
+        at(angle - FRAC_PI_2, 0.035),
+        at(angle + PI, 0.12),
+      ],
+      Style::fill(colors.needle),
+    );
+    frame
+      .canvas
+      .circle(center, radius * 0.06, Style::fill(colors.needle));
+  }
+}
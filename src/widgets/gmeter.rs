@@ -0,0 +1,284 @@
+use std::collections::VecDeque;
+use std::time::Duration;
+
+use crate::canvas::Style;
+use crate::data::Telemetry;
+use crate::frame::Frame;
+use crate::locale::NumberFormat;
+use crate::text::{Align, TextSection, VAlign};
+use crate::theme::Palette;
+use crate::widgets::{Rect, Widget};
+
+/// Linear RGBA colors in straight alpha.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct GMeterColors {
+  /// Background disc, none to leave the meter see-through.
+  pub face: Option<[f32; 4]>,
+  /// Rings and the crosshair.
+  pub scale: [f32; 4],
+  /// The dot and its trail.
+  pub dot: [f32; 4],
+  /// The largest acceleration seen.
+  pub max: [f32; 4],
+  pub text: [f32; 4],
+}
+
+impl Default for GMeterColors {
+  fn default() -> Self {
+    Self {
+      face: None,
+      scale: [1.0, 1.0, 1.0, 0.4],
+      dot: [1.0, 0.3, 0.1, 1.0],
+      max: [1.0, 0.75, 0.2, 1.0],
+      text: [1.0, 1.0, 1.0, 1.0],
+    }
+  }
+}
+
+/// Lateral and longitudinal acceleration as a dot in a circle, with a trail
+/// of where it just was and a marker holding the largest acceleration
+/// seen, from [`Telemetry::lateral_g`] and [`Telemetry::longitudinal_g`].
+///
+/// The dot goes up while accelerating, down while braking and towards the
+/// side the vehicle turns to. Rings are drawn every half g up to `max_g`,
+/// larger accelerations stay on the edge.
+#[derive(Clone, Debug)]
+pub struct GMeter {
+  /// g at the edge of the circle.
+  pub max_g: f32,
+  /// How long the trail is.
+  pub trail: Duration,
+  /// How accelerations are written, like with a decimal comma.
+  pub number_format: NumberFormat,
+  pub colors: GMeterColors,
+  value: Option<[f32; 2]>,
+  // Oldest first, with the time they were seen at
+  points: VecDeque<(Duration, [f32; 2])>,
+  max: Option<[f32; 2]>,
+  time: Duration,
+}
+
+impl Default for GMeter {
+  fn default() -> Self {
+    Self::new()
+  }
+}
+
+impl GMeter {
+  /// Up to 1.5 g with a one second trail.
+  pub fn new() -> Self {
+    Self {
+      max_g: 1.5,
+      trail: Duration::from_secs(1),
+      number_format: NumberFormat::default(),
+      colors: GMeterColors::default(),
+      value: None,
+      points: VecDeque::new(),
+      max: None,
+      time: Duration::ZERO,
+    }
+  }
+
+  pub fn with_max_g(mut self, max_g: f32) -> Self {
+    self.max_g = max_g;
+    self
+  }
+
+  pub fn with_trail(mut self, trail: Duration) -> Self {
+    self.trail = trail;
+    self
+  }
+
+  pub fn with_number_format(mut self, number_format: NumberFormat) -> Self {
+    self.number_format = number_format;
+    self
+  }
+
+  pub fn with_colors(mut self, colors: GMeterColors) -> Self {
+    self.colors = colors;
+    self
+  }
+
+  /// Largest acceleration seen since the last reset, lateral first.
+  pub fn max(&self) -> Option<[f32; 2]> {
+    self.max
+  }
+
+  /// Forgets the largest acceleration seen, e.g. at the start of a lap.
+  pub fn reset_max(&mut self) {
+    self.max = None;
+  }
+}
+
+fn magnitude([x, y]: [f32; 2]) -> f32 {
+  x.hypot(y)
+}
+
+impl Widget for GMeter {
+  fn set_palette(&mut self, palette: &Palette) {
+    self.colors.face = self.colors.face.map(|_| palette.surface);
+    self.colors.scale = palette.muted;
+    self.colors.dot = palette.accent;
+    self.colors.text = palette.text;
+  }
+
+  fn set_telemetry(&mut self, telemetry: &Telemetry) {
+    self.value = telemetry
+      .lateral_g
+      .zip(telemetry.longitudinal_g)
+      .map(|(x, y)| [x, y]);
+  }
+
+  fn update(&mut self, delta: Duration) {
+    self.time += delta;
+    while let Some((seen, _)) = self.points.front() {
+      if self.time.saturating_sub(*seen) <= self.trail {
+        break;
+      }
+      self.points.pop_front();
+    }
+    let Some(value) = self.value else {
+      return;
+    };
+    // Standing still doesn't grow the trail
+    if self.points.back().map(|(_, point)| *point) != Some(value) {
+      self.points.push_back((self.time, value));
+    }
+    if self.max.is_none_or(|max| magnitude(value) > magnitude(max)) {
+      self.max = Some(value);
+    }
+  }
+
+  fn animating(&self) -> bool {
+    self.points.len() > 1
+  }
+
+  fn draw(&self, frame: &mut Frame, rect: Rect) {
+    let colors = &self.colors;
+    let center = rect.center();
+    let radius = rect.min_side() / 2.0 * 0.85;
+    let at = |[x, y]: [f32; 2]| {
+      // Kept inside the circle in the direction it points
+      let scale = (self.max_g / magnitude([x, y]).max(self.max_g)) * radius / self.max_g;
+      [center[0] + x * scale, center[1] - y * scale]
+    };
+
+    let canvas = &mut *frame.canvas;
+    if let Some(face) = colors.face {
+      canvas.circle(center, radius, Style::fill(face));
+    }
+    let rings = (self.max_g / 0.5).ceil().max(1.0) as usize;
+    for ring in 1..=rings {
+      let g = (ring as f32 * 0.5).min(self.max_g);
+      canvas.circle(
+        center,
+        radius * g / self.max_g,
+        Style::stroke(colors.scale, radius * 0.012),
+      );
+    }
+    let [x, y] = center;
+    canvas.line(
+      [x - radius, y],
+      [x + radius, y],
+      colors.scale,
+      radius * 0.01,
+    );
+    canvas.line(
+      [x, y - radius],
+      [x, y + radius],
+      colors.scale,
+      radius * 0.01,
+    );
+
+    let [r, g, b, a] = colors.dot;
+    let mut previous: Option<[f32; 2]> = None;
+    for (seen, point) in &self.points {
+      let age = self.time.saturating_sub(*seen).as_secs_f32() / self.trail.as_secs_f32().max(1e-3);
+      let fade = (1.0 - age).clamp(0.0, 1.0);
+      let position = at(*point);
+      if let Some(previous) = previous {
+        canvas.line(
+          previous,
+          position,
+          [r, g, b, a * fade * 0.6],
+          radius * 0.03 * (0.3 + fade),
+        );
+      }
+      previous = Some(position);
+    }
+
+    if let Some(max) = self.max {
+      canvas.circle(
+        at(max),
+        radius * 0.05,
+        Style::stroke(colors.max, radius * 0.02),
+      );
+    }
+    if let Some(value) = self.value {
+      canvas.circle(at(value), radius * 0.06, Style::fill(colors.dot));
+    }
+
+    let text = &mut *frame.text;
+    let label = |g: Option<f32>| match g {
+      Some(g) => format!("{} g", self.number_format.format(g, 2)),
+      None => "-- g".to_string(),
+    };
+    text.queue(
+      &TextSection::new(label(self.value.map(magnitude)))
+        .at(x + radius * 0.7, y + radius * 0.85)
+        .with_size(radius * 0.12)
+        .with_color(colors.text)
+        .with_align(Align::Left, VAlign::Center),
+    );
+    text.queue(
+      &TextSection::new(format!("max {}", label(self.max.map(magnitude))))
+        .at(x + radius * 0.7, y + radius * 0.99)
+        .with_size(radius * 0.09)
+        .with_color(colors.max)
+        .with_align(Align::Left, VAlign::Center),
+    );
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn feel(meter: &mut GMeter, lateral: f32, longitudinal: f32, delta: Duration) {
+    meter.set_telemetry(&Telemetry {
+      lateral_g: Some(lateral),
+      longitudinal_g: Some(longitudinal),
+      ..Default::default()
+    });
+    meter.update(delta);
+  }
+
+  #[test]
+  fn holds_the_largest_acceleration_until_reset() {
+    let mut meter = GMeter::new();
+    let frame = Duration::from_millis(100);
+    feel(&mut meter, 0.2, 0.1, frame);
+    feel(&mut meter, 0.6, -0.8, frame);
+    feel(&mut meter, -0.3, 0.3, frame);
+    assert_eq!(meter.max(), Some([0.6, -0.8]));
+    meter.reset_max();
+    feel(&mut meter, -0.3, 0.3, frame);
+    assert_eq!(meter.max(), Some([-0.3, 0.3]));
+  }
+
+  #[test]
+  fn the_trail_ages_out() {
+    let mut meter = GMeter::new().with_trail(Duration::from_millis(250));
+    let frame = Duration::from_millis(100);
+    for step in 0..5 {
+      feel(&mut meter, step as f32 * 0.1, 0.0, frame);
+    }
+    // The last three, within 250 ms of the last
+    assert_eq!(meter.points.len(), 3);
+    for _ in 0..3 {
+      feel(&mut meter, 0.4, 0.0, frame);
+    }
+    assert_eq!(meter.points.len(), 1);
+    assert!(!meter.animating());
+  }
+}
@@ -0,0 +1,149 @@
+use std::time::Duration;
+
+use crate::accessibility::{Accessible, Role};
+use crate::frame::Frame;
+use crate::text::{Align, TextSection, VAlign};
+use crate::theme::Palette;
+use crate::widgets::{Rect, Widget};
+
+/// How a [`Label`] scrolls text too wide for its rect.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Marquee {
+  /// Scrolling speed in pixels per second, scaled like the text.
+  pub speed: f32,
+  /// How long the text rests at its start before every pass.
+  pub pause: Duration,
+  /// Space between the end of the text and its repetition.
+  pub gap: f32,
+  /// Width of the soft edges the text fades out over.
+  pub fade: f32,
+}
+
+impl Default for Marquee {
+  fn default() -> Self {
+    Self {
+      speed: 60.0,
+      pause: Duration::from_secs(2),
+      gap: 48.0,
+      fade: 16.0,
+    }
+  }
+}
+
+/// A single line of text, vertically centered in its rect. Its size and
+/// the marquee's distances are multiplied with [`Frame::scale`], and the
+/// marquee holds still at the start with [`Frame::reduce_motion`].
+///
+/// With a [`Marquee`] set, text that doesn't fit loops through the rect
+/// instead of overflowing it; text that fits is drawn as usual.
+#[derive(Clone, Debug)]
+pub struct Label {
+  /// Style of the text, its position is taken from the rect and only the
+  /// horizontal alignment is used.
+  pub section: TextSection,
+  pub marquee: Option<Marquee>,
+  elapsed: Duration,
+}
+
+impl Label {
+  pub fn new(text: impl Into<String>) -> Self {
+    Self {
+      section: TextSection::new(text),
+      marquee: None,
+      elapsed: Duration::ZERO,
+    }
+  }
+
+  pub fn with_section(mut self, section: TextSection) -> Self {
+    self.section = section;
+    self
+  }
+
+  pub fn with_marquee(mut self, marquee: Marquee) -> Self {
+    self.marquee = Some(marquee);
+    self
+  }
+
+  /// Replaces the text, restarting the marquee if it changed.
+  pub fn set_text(&mut self, text: impl Into<String>) {
+    let text = text.into();
+    if text != self.section.text {
+      self.section.text = text;
+      self.elapsed = Duration::ZERO;
+    }
+  }
+
+  /// Horizontal scroll position of the text at the current time.
+  fn scroll(&self, marquee: &Marquee, distance: f32) -> f32 {
+    if marquee.speed <= 0.0 {
+      return 0.0;
+    }
+    let pause = marquee.pause.as_secs_f32();
+    let period = pause + distance / marquee.speed;
+    let t = self.elapsed.as_secs_f32() % period;
+    (t - pause).max(0.0) * marquee.speed
+  }
+}
+
+impl Widget for Label {
+  fn set_palette(&mut self, palette: &Palette) {
+    self.section.color = palette.text;
+  }
+
+  fn accessible(&self) -> Option<Accessible> {
+    Some(Accessible::new(Role::Text, self.section.text.as_str()))
+  }
+
+  fn update(&mut self, delta: Duration) {
+    self.elapsed += delta;
+  }
+
+  // Even if the text fits and doesn't scroll, which is only known when
+  // drawing
+  fn animating(&self) -> bool {
+    self.marquee.is_some()
+  }
+
+  fn draw(&self, frame: &mut Frame, rect: Rect) {
+    let y = rect.y + rect.height / 2.0;
+    let section = self
+      .section
+      .clone()
+      .with_size(self.section.size * frame.scale)
+      .with_align(self.section.align, VAlign::Center);
+    let width = frame.text.line_width(&section);
+
+    let marquee = self.marquee.map(|marquee| Marquee {
+      speed: marquee.speed * frame.scale,
+      gap: marquee.gap * frame.scale,
+      fade: marquee.fade * frame.scale,
+      ..marquee
+    });
+    match marquee {
+      Some(marquee) if width > rect.width => {
+        let distance = width + marquee.gap;
+        let scroll = match frame.reduce_motion {
+          true => 0.0,
+          false => self.scroll(&marquee, distance),
+        };
+        let x = rect.x - scroll;
+        let section = section.with_align(Align::Left, VAlign::Center).with_clip(
+          rect.x,
+          rect.x + rect.width,
+          marquee.fade,
+        );
+        // The repetition follows, so the loop is seamless
+        frame.text.queue(&section.clone().at(x, y));
+        frame.text.queue(&section.at(x + distance, y));
+      }
+      _ => {
+        let x = match section.align {
+          Align::Left => rect.x,
+          Align::Center => rect.x + rect.width / 2.0,
+          Align::Right => rect.x + rect.width,
+        };
+        frame.text.queue(&section.at(x, y));
+      }
+    }
+  }
+}
@@ -0,0 +1,949 @@
+use std::cell::RefCell;
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+use std::time::Duration;
+
+use lyon::math::point;
+use lyon::path::Path;
+
+use crate::anim::{self, mix, SETTLED};
+use crate::canvas::{Canvas, Style};
+use crate::data::Telemetry;
+use crate::frame::Frame;
+use crate::input::{Manipulation, Motion, PointerEvent, PointerId, PointerPhase};
+use crate::map::route::Route;
+use crate::map::traffic::{Congestion, Traffic};
+use crate::map::{self, clip, Camera, Coordinate, FeatureKind, Geometry, MapData};
+use crate::text::{Align, TextSection, VAlign};
+use crate::theme::Palette;
+use crate::widgets::{Rect, TrafficColors, Widget};
+
+/// Share of the momentum lost per second after letting go.
+const FRICTION: f32 = 4.0;
+/// Pixels per second below which the map stops drifting.
+const MIN_SPEED: f32 = 5.0;
+/// Degrees from north within which letting go turns the map back to it.
+const SNAP_ANGLE: f32 = 12.0;
+/// How quickly the map turns back to north, per second.
+const SNAP_RESPONSE: f32 = 10.0;
+/// Longest the pointers may be held still before letting go for the map
+/// to keep drifting.
+const FLING_TIME: Duration = Duration::from_millis(100);
+/// Distance in physical pixels the pointers have to move before the map
+/// takes them over from the widgets around it.
+const DRAG_SLOP: f32 = 8.0;
+/// Seconds the map takes to fade between its day and night colors.
+const STYLE_FADE: f32 = 2.0;
+/// Seconds labels take to fade in or out.
+const LABEL_FADE: f32 = 0.25;
+/// Distance between labels of the same name, in label heights, below which
+/// only one of them is shown.
+const LABEL_REPEAT: f32 = 8.0;
+
+/// Linear RGBA colors in straight alpha.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct MapColors {
+  pub land: [f32; 4],
+  pub park: [f32; 4],
+  pub water: [f32; 4],
+  pub building: [f32; 4],
+  pub path: [f32; 4],
+  pub rail: [f32; 4],
+  pub road: [f32; 4],
+  pub major_road: [f32; 4],
+  pub motorway: [f32; 4],
+  pub label: [f32; 4],
+  /// The route navigated along.
+  pub route: [f32; 4],
+  /// Drawn over the roads it's on.
+  pub traffic: TrafficColors,
+  /// The vehicle and the north arrow.
+  pub position: [f32; 4],
+}
+
+impl Default for MapColors {
+  fn default() -> Self {
+    Self::day()
+  }
+}
+
+impl MapColors {
+  /// Dark, but bright enough to tell roads apart in sunlight.
+  pub fn day() -> Self {
+    Self {
+      land: [0.02, 0.022, 0.028, 1.0],
+      park: [0.02, 0.06, 0.03, 1.0],
+      water: [0.01, 0.03, 0.08, 1.0],
+      building: [0.05, 0.055, 0.065, 1.0],
+      path: [0.1, 0.1, 0.11, 1.0],
+      rail: [0.12, 0.12, 0.15, 1.0],
+      road: [0.13, 0.14, 0.16, 1.0],
+      major_road: [0.3, 0.25, 0.12, 1.0],
+      motorway: [0.45, 0.25, 0.08, 1.0],
+      label: [1.0, 1.0, 1.0, 0.8],
+      route: [0.1, 0.35, 1.0, 0.85],
+      traffic: TrafficColors::default(),
+      position: [0.2, 0.6, 1.0, 1.0],
+    }
+  }
+
+  /// Dim and warm on black, like the night palette.
+  pub fn night() -> Self {
+    Self {
+      land: [0.0, 0.0, 0.0, 1.0],
+      park: [0.008, 0.02, 0.01, 1.0],
+      water: [0.0, 0.008, 0.025, 1.0],
+      building: [0.022, 0.02, 0.018, 1.0],
+      path: [0.045, 0.04, 0.035, 1.0],
+      rail: [0.055, 0.05, 0.045, 1.0],
+      road: [0.065, 0.055, 0.045, 1.0],
+      major_road: [0.16, 0.1, 0.04, 1.0],
+      motorway: [0.25, 0.11, 0.03, 1.0],
+      label: [0.7, 0.55, 0.4, 0.8],
+      route: [0.05, 0.18, 0.55, 0.85],
+      traffic: TrafficColors::night(),
+      position: [0.8, 0.35, 0.1, 1.0],
+    }
+  }
+
+  /// The colors `t` of the way from these to `other`.
+  pub fn mix(&self, other: &Self, t: f32) -> Self {
+    Self {
+      land: mix(self.land, other.land, t),
+      park: mix(self.park, other.park, t),
+      water: mix(self.water, other.water, t),
+      building: mix(self.building, other.building, t),
+      path: mix(self.path, other.path, t),
+      rail: mix(self.rail, other.rail, t),
+      road: mix(self.road, other.road, t),
+      major_road: mix(self.major_road, other.major_road, t),
+      motorway: mix(self.motorway, other.motorway, t),
+      label: mix(self.label, other.label, t),
+      route: mix(self.route, other.route, t),
+      traffic: self.traffic.mix(&other.traffic, t),
+      position: mix(self.position, other.position, t),
+    }
+  }
+
+  fn of(&self, kind: FeatureKind) -> [f32; 4] {
+    match kind {
+      FeatureKind::Land => self.land,
+      FeatureKind::Park => self.park,
+      FeatureKind::Water => self.water,
+      FeatureKind::Building => self.building,
+      FeatureKind::Path => self.path,
+      FeatureKind::Rail => self.rail,
+      FeatureKind::Road => self.road,
+      FeatureKind::MajorRoad => self.major_road,
+      FeatureKind::Motorway => self.motorway,
+      FeatureKind::Place => self.label,
+    }
+  }
+}
+
+/// Width of lines of `kind` in pixels at zoom 16, halving with every level
+/// zoomed out.
+fn line_width(kind: FeatureKind) -> f32 {
+  match kind {
+    FeatureKind::Path => 2.0,
+    FeatureKind::Rail => 3.0,
+    FeatureKind::Road => 6.0,
+    FeatureKind::MajorRoad => 10.0,
+    FeatureKind::Motorway => 14.0,
+    _ => 1.0,
+  }
+}
+
+/// Lines on the Web Mercator square of each congestion level.
+type Levels = Vec<(Congestion, Vec<Vec<[f64; 2]>>)>;
+
+/// How the pointers moved lately, to keep the map going once they let go.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+struct Momentum {
+  /// Pixels per second.
+  pan: [f32; 2],
+  /// Zoom levels per second.
+  zoom: f32,
+  /// Radians per second, clockwise.
+  spin: f32,
+}
+
+impl Momentum {
+  fn is_moving(&self) -> bool {
+    self.pan[0].hypot(self.pan[1]) > MIN_SPEED || self.zoom.abs() > 0.05 || self.spin.abs() > 0.05
+  }
+}
+
+/// A vector map of [`MapData`] with the vehicle on it, dragged with one
+/// finger and zoomed and turned with two like maps on a phone.
+///
+/// Flicking it keeps it drifting for a moment, and letting go of a map
+/// turned less than a few degrees from north turns it back. Until it's
+/// dragged, the map follows the vehicle's position from
+/// [`Telemetry::latitude`] and [`Telemetry::longitude`], see
+/// [`recenter`](Self::recenter). Traffic from [`Telemetry::traffic`] and
+/// over it the route from [`Telemetry::route`] are drawn over the roads.
+///
+/// The map fades from its `colors` to its `night_colors` as the
+/// [palette](Widget::set_palette) turns to the night one, which happens
+/// after sunset with the automatic theme, and back in the morning.
+pub struct MapView {
+  pub camera: Camera,
+  pub colors: MapColors,
+  pub night_colors: MapColors,
+  /// Whether the map keeps the vehicle in the middle.
+  pub follow: bool,
+  data: Arc<MapData>,
+  manipulation: Manipulation,
+  // Where the pointers went down, until they moved far enough to drag
+  pressed: Option<[f32; 2]>,
+  dragging: bool,
+  momentum: Momentum,
+  // Where zooming and turning drift around and the middle of the view
+  pivot: [f32; 2],
+  origin: [f32; 2],
+  snapping: bool,
+  moved: Duration,
+  position: Option<(Coordinate, Option<f32>)>,
+  // Projected once per route rather than every frame
+  route: Option<(Arc<Route>, Vec<[f64; 2]>)>,
+  // Likewise, grouped by congestion to draw each in one go
+  traffic: Option<(Arc<Traffic>, Levels)>,
+  // Opacity of the labels shown or fading out by feature, their size
+  // depends on the font. Unset until the first frame, which shows its
+  // labels right away
+  labels: RefCell<Option<HashMap<usize, f32>>>,
+  // From 0 by day to 1 by night, towards `night` once there's a palette
+  night: Option<bool>,
+  dusk: f32,
+  shown: MapColors,
+  time: Duration,
+}
+
+impl MapView {
+  /// `data` at zoom 15 with north up.
+  pub fn new(data: impl Into<Arc<MapData>>) -> Self {
+    Self {
+      camera: Camera::new(Coordinate::default(), 15.0),
+      colors: MapColors::day(),
+      night_colors: MapColors::night(),
+      follow: true,
+      data: data.into(),
+      manipulation: Manipulation::new(),
+      pressed: None,
+      dragging: false,
+      momentum: Momentum::default(),
+      pivot: [0.0, 0.0],
+      origin: [0.0, 0.0],
+      snapping: false,
+      moved: Duration::ZERO,
+      position: None,
+      route: None,
+      traffic: None,
+      labels: RefCell::default(),
+      night: None,
+      dusk: 0.0,
+      shown: MapColors::day(),
+      time: Duration::ZERO,
+    }
+  }
+
+  /// Looks at `center` from `zoom` rather than following the vehicle.
+  pub fn with_camera(mut self, center: Coordinate, zoom: f64) -> Self {
+    self.camera = Camera::new(center, zoom);
+    self.follow = false;
+    self
+  }
+
+  pub fn with_zoom(mut self, zoom: f64) -> Self {
+    self.camera.zoom = zoom.clamp(map::MIN_ZOOM, map::MAX_ZOOM);
+    self
+  }
+
+  pub fn with_colors(mut self, colors: MapColors) -> Self {
+    self.colors = colors;
+    self.shown = self.colors.mix(&self.night_colors, self.dusk);
+    self
+  }
+
+  pub fn with_night_colors(mut self, colors: MapColors) -> Self {
+    self.night_colors = colors;
+    self.shown = self.colors.mix(&self.night_colors, self.dusk);
+    self
+  }
+
+  pub fn data(&self) -> &Arc<MapData> {
+    &self.data
+  }
+
+  pub fn set_data(&mut self, data: impl Into<Arc<MapData>>) {
+    self.data = data.into();
+    *self.labels.get_mut() = None;
+  }
+
+  /// Follows the vehicle again after the map was dragged away from it.
+  pub fn recenter(&mut self) {
+    self.follow = true;
+    self.momentum = Momentum::default();
+  }
+
+  fn manipulate(&mut self, motion: Motion) {
+    let dt = self.time.saturating_sub(self.moved).as_secs_f32();
+    self.moved = self.time;
+    self.camera.manipulate(
+      motion.center,
+      motion.translation,
+      motion.scale,
+      motion.rotation,
+      self.origin,
+    );
+    self.pivot = motion.center;
+    if dt > 0.0 {
+      // Smoothed, single moves are noisy
+      let [x, y] = motion.translation;
+      let momentum = Momentum {
+        pan: [x / dt, y / dt],
+        zoom: motion.scale.max(1e-3).log2() / dt,
+        spin: motion.rotation / dt,
+      };
+      let mix = |a: f32, b: f32| a * 0.5 + b * 0.5;
+      self.momentum = Momentum {
+        pan: [
+          mix(self.momentum.pan[0], momentum.pan[0]),
+          mix(self.momentum.pan[1], momentum.pan[1]),
+        ],
+        zoom: mix(self.momentum.zoom, momentum.zoom),
+        spin: mix(self.momentum.spin, momentum.spin),
+      };
+    }
+  }
+
+  fn release(&mut self) {
+    if self.time.saturating_sub(self.moved) > FLING_TIME {
+      self.momentum = Momentum::default();
+    }
+    // Turned just a little, most likely by accident while pinching
+    if map::angle_between(0.0, self.camera.bearing).abs() < SNAP_ANGLE {
+      self.snapping = true;
+    }
+  }
+
+  fn draw_features(&self, canvas: &mut Canvas, rect: Rect) {
+    let origin = rect.center();
+    let visible = self.camera.visible([rect.width, rect.height]);
+    // Roads get thinner zoomed out, but stay visible
+    let scale = ((self.camera.zoom - 16.0).exp2() as f32).clamp(0.15, 4.0);
+    for feature in self.data.features() {
+      if !feature.intersects(visible) {
+        continue;
+      }
+      let color = self.shown.of(feature.kind);
+      let screen = |points: &[[f64; 2]]| -> Vec<[f32; 2]> {
+        points
+          .iter()
+          .map(|point| self.camera.to_screen(*point, origin))
+          .collect()
+      };
+      match &feature.geometry {
+        Geometry::Polygons(polygons) => {
+          let mut builder = Path::builder();
+          let mut empty = true;
+          for ring in polygons.iter().flatten() {
+            let ring = clip::polygon(&screen(ring), rect);
+            let Some((first, rest)) = ring.split_first() else {
+              continue;
+            };
+            builder.begin(point(first[0], first[1]));
+            for [x, y] in rest {
+              builder.line_to(point(*x, *y));
+            }
+            builder.end(true);
+            empty = false;
+          }
+          if !empty {
+            canvas.path(&builder.build(), Style::fill(color));
+          }
+        }
+        Geometry::Lines(lines) => {
+          let width = (line_width(feature.kind) * scale).max(1.0);
+          // Round caps would poke out of the edges
+          let inside = Rect::new(
+            rect.x + width / 2.0,
+            rect.y + width / 2.0,
+            rect.width - width,
+            rect.height - width,
+          );
+          let mut builder = Path::builder();
+          let mut empty = true;
+          for line in lines {
+            for part in clip::line(&screen(line), inside) {
+              builder.begin(point(part[0][0], part[0][1]));
+              for [x, y] in &part[1..] {
+                builder.line_to(point(*x, *y));
+              }
+              builder.end(false);
+              empty = false;
+            }
+          }
+          if !empty {
+            canvas.path(&builder.build(), Style::stroke(color, width));
+          }
+        }
+        Geometry::Point(_) => {}
+      }
+    }
+  }
+
+  /// Strokes `lines` on the Web Mercator square, clipped to `rect`.
+  fn stroke<'a>(
+    &self,
+    canvas: &mut Canvas,
+    rect: Rect,
+    lines: impl IntoIterator<Item = &'a Vec<[f64; 2]>>,
+    style: Style,
+    width: f32,
+  ) {
+    let origin = rect.center();
+    let inside = Rect::new(
+      rect.x + width / 2.0,
+      rect.y + width / 2.0,
+      rect.width - width,
+      rect.height - width,
+    );
+    let mut builder = Path::builder();
+    let mut empty = true;
+    for line in lines {
+      let screen: Vec<_> = line
+        .iter()
+        .map(|point| self.camera.to_screen(*point, origin))
+        .collect();
+      for part in clip::line(&screen, inside) {
+        builder.begin(point(part[0][0], part[0][1]));
+        for [x, y] in &part[1..] {
+          builder.line_to(point(*x, *y));
+        }
+        builder.end(false);
+        empty = false;
+      }
+    }
+    if !empty {
+      canvas.path(&builder.build(), style);
+    }
+  }
+
+  fn draw_traffic(&self, canvas: &mut Canvas, rect: Rect) {
+    let Some((_, levels)) = &self.traffic else {
+      return;
+    };
+    let scale = ((self.camera.zoom - 16.0).exp2() as f32).clamp(0.15, 4.0);
+    // Narrower than the roads, so they still show what kind they are
+    let width = (line_width(FeatureKind::Road) * 0.6 * scale).max(2.0);
+    for (congestion, lines) in levels {
+      let color = self.shown.traffic.of(*congestion);
+      self.stroke(canvas, rect, lines, Style::stroke(color, width), width);
+    }
+  }
+
+  fn draw_route(&self, canvas: &mut Canvas, rect: Rect) {
+    let Some((_, points)) = &self.route else {
+      return;
+    };
+    let scale = ((self.camera.zoom - 16.0).exp2() as f32).clamp(0.15, 4.0);
+    let width = (line_width(FeatureKind::MajorRoad) * 0.7 * scale).max(3.0);
+    let style = Style::stroke(self.shown.route, width);
+    self.stroke(canvas, rect, [points], style, width);
+  }
+
+  fn draw_labels(&self, frame: &mut Frame, rect: Rect) {
+    let origin = rect.center();
+    let size = (rect.min_side() * 0.04).max(12.0);
+    let mut labels = self.labels.borrow_mut();
+    let first = labels.is_none();
+    let labels = labels.get_or_insert_with(HashMap::new);
+    let section = |name: &str, [x, y]: [f32; 2]| {
+      TextSection::new(name)
+        .at(x, y)
+        .with_size(size)
+        .with_align(Align::Center, VAlign::Center)
+    };
+    let mut candidates = Vec::new();
+    for (index, feature) in self.data.features().iter().enumerate() {
+      let (Geometry::Point(point), Some(name)) = (&feature.geometry, &feature.name) else {
+        continue;
+      };
+      let center = self.camera.to_screen(*point, origin);
+      if !rect.contains(center) {
+        continue;
+      }
+      // A little apart, so labels don't read as one
+      let width = frame.text.line_width(&section(name, center)) + size * 0.6;
+      let height = size * 1.4;
+      candidates.push(Candidate {
+        feature: index,
+        name,
+        priority: feature.priority,
+        bounds: Rect::new(
+          center[0] - width / 2.0,
+          center[1] - height / 2.0,
+          width,
+          height,
+        ),
+        shown: labels.get(&index).is_some_and(|opacity| *opacity > 0.0),
+      });
+    }
+    let placed = place(&mut candidates, size * LABEL_REPEAT);
+
+    let step = frame.delta.as_secs_f32() / LABEL_FADE;
+    for feature in &placed {
+      labels
+        .entry(*feature)
+        .or_insert(if first { 1.0 } else { 0.0 });
+    }
+    let visible: HashSet<_> = candidates
+      .iter()
+      .map(|candidate| candidate.feature)
+      .collect();
+    labels.retain(|feature, opacity| {
+      let target = if placed.contains(feature) { 1.0 } else { 0.0 };
+      *opacity = if *opacity < target {
+        (*opacity + step).min(target)
+      } else {
+        (*opacity - step).max(target)
+      };
+      // Labels scrolled out of view are gone rather than fading there
+      *opacity > 0.0 && visible.contains(feature)
+    });
+    for candidate in &candidates {
+      let Some(opacity) = labels.get(&candidate.feature) else {
+        continue;
+      };
+      let [r, g, b, a] = self.shown.label;
+      frame.text.queue(
+        &section(candidate.name, candidate.bounds.center()).with_color([r, g, b, a * opacity]),
+      );
+    }
+  }
+
+  fn draw_position(&self, canvas: &mut Canvas, rect: Rect) {
+    let Some((position, heading)) = self.position else {
+      return;
+    };
+    let center = self.camera.to_screen(position.project(), rect.center());
+    if !rect.contains(center) {
+      return;
+    }
+    let size = (rect.min_side() * 0.04).max(10.0);
+    let color = self.shown.position;
+    match heading {
+      Some(heading) => {
+        let angle = (heading - self.camera.bearing).to_radians();
+        let (sin, cos) = angle.sin_cos();
+        let at = |x: f32, y: f32| [center[0] + x * cos - y * sin, center[1] + x * sin + y * cos];
+        canvas.polygon(
+          &[
+            at(0.0, -size),
+            at(size * 0.7, size * 0.8),
+            at(0.0, size * 0.4),
+            at(-size * 0.7, size * 0.8),
+          ],
+          Style::fill(color),
+        );
+      }
+      None => canvas.circle(center, size * 0.6, Style::fill(color)),
+    }
+  }
+
+  fn draw_compass(&self, frame: &mut Frame, rect: Rect) {
+    let bearing = map::angle_between(0.0, self.camera.bearing);
+    if bearing.abs() < 0.5 {
+      return;
+    }
+    let radius = (rect.min_side() * 0.05).max(14.0);
+    let center = [rect.x + rect.width - radius * 1.6, rect.y + radius * 1.6];
+    let (sin, cos) = (-bearing).to_radians().sin_cos();
+    let at = |x: f32, y: f32| [center[0] + x * cos - y * sin, center[1] + x * sin + y * cos];
+    frame
+      .canvas
+      .circle(center, radius, Style::fill(self.shown.land));
+    frame.canvas.polygon(
+      &[
+        at(0.0, -radius * 0.8),
+        at(radius * 0.3, 0.0),
+        at(-radius * 0.3, 0.0),
+      ],
+      Style::fill(self.shown.position),
+    );
+    frame.canvas.polygon(
+      &[
+        at(0.0, radius * 0.8),
+        at(radius * 0.3, 0.0),
+        at(-radius * 0.3, 0.0),
+      ],
+      Style::fill(self.shown.label),
+    );
+  }
+}
+
+/// A label that could be shown, in screen space.
+#[derive(Clone, Debug)]
+struct Candidate<'a> {
+  feature: usize,
+  name: &'a str,
+  priority: i32,
+  bounds: Rect,
+  /// Whether it's shown already, to keep it over labels of the same
+  /// priority rather than flickering between them.
+  shown: bool,
+}
+
+fn overlap(a: Rect, b: Rect) -> bool {
+  a.x < b.x + b.width && b.x < a.x + a.width && a.y < b.y + b.height && b.y < a.y + a.height
+}
+
+/// The features of the `candidates` shown, the highest priorities first,
+/// leaving out labels overlapping those and labels closer than `repeat` to
+/// one of the same name.
+fn place(candidates: &mut [Candidate], repeat: f32) -> HashSet<usize> {
+  candidates.sort_by_key(|candidate| (-candidate.priority, !candidate.shown, candidate.feature));
+  let mut placed: Vec<&Candidate> = Vec::new();
+  for candidate in candidates.iter() {
+    let [x, y] = candidate.bounds.center();
+    let free = placed.iter().all(|other| {
+      let [other_x, other_y] = other.bounds.center();
+      let repeated = other.name == candidate.name && (x - other_x).hypot(y - other_y) < repeat;
+      !repeated && !overlap(other.bounds, candidate.bounds)
+    });
+    if free {
+      placed.push(candidate);
+    }
+  }
+  placed.iter().map(|candidate| candidate.feature).collect()
+}
+
+impl Widget for MapView {
+  fn set_palette(&mut self, palette: &Palette) {
+    let colors = if palette.night {
+      &mut self.night_colors
+    } else {
+      &mut self.colors
+    };
+    colors.label = palette.text;
+    colors.position = palette.accent;
+    // The first palette is there from the start, nothing to fade from
+    if self.night.is_none() {
+      self.dusk = if palette.night { 1.0 } else { 0.0 };
+    }
+    self.night = Some(palette.night);
+    self.shown = self.colors.mix(&self.night_colors, self.dusk);
+  }
+
+  fn set_telemetry(&mut self, telemetry: &Telemetry) {
+    self.position = match (telemetry.latitude, telemetry.longitude) {
+      (Some(latitude), Some(longitude)) => Some((
+        Coordinate::new(latitude as f64, longitude as f64),
+        telemetry.heading,
+      )),
+      _ => None,
+    };
+    match &telemetry.route {
+      Some(route) if route.is_empty() => self.route = None,
+      Some(route)
+        if self
+          .route
+          .as_ref()
+          .is_none_or(|(shown, _)| !Arc::ptr_eq(shown, route)) =>
+      {
+        let points = route.geometry.iter().map(|point| point.project()).collect();
+        self.route = Some((route.clone(), points));
+      }
+      _ => {}
+    }
+    match &telemetry.traffic {
+      Some(traffic)
+        if self
+          .traffic
+          .as_ref()
+          .is_none_or(|(shown, _)| !Arc::ptr_eq(shown, traffic)) =>
+      {
+        let levels = Congestion::ALL
+          .into_iter()
+          .map(|congestion| {
+            let lines = traffic
+              .segments
+              .iter()
+              .filter(|segment| segment.congestion == congestion)
+              .map(|segment| {
+                segment
+                  .geometry
+                  .iter()
+                  .map(|point| point.project())
+                  .collect()
+              })
+              .collect();
+            (congestion, lines)
+          })
+          .collect();
+        self.traffic = Some((traffic.clone(), levels));
+      }
+      _ => {}
+    }
+  }
+
+  fn update(&mut self, delta: Duration) {
+    self.time += delta;
+    let target = if self.night == Some(true) { 1.0 } else { 0.0 };
+    let step = delta.as_secs_f32() / STYLE_FADE;
+    self.dusk = if self.dusk < target {
+      (self.dusk + step).min(target)
+    } else {
+      (self.dusk - step).max(target)
+    };
+    self.shown = self.colors.mix(&self.night_colors, self.dusk);
+    if !self.manipulation.is_active() && self.momentum.is_moving() {
+      let dt = delta.as_secs_f32();
+      let Momentum { pan, zoom, spin } = self.momentum;
+      let translation = [pan[0] * dt, pan[1] * dt];
+      self.pivot = [
+        self.pivot[0] + translation[0],
+        self.pivot[1] + translation[1],
+      ];
+      self.camera.manipulate(
+        self.pivot,
+        translation,
+        (zoom * dt).exp2(),
+        spin * dt,
+        self.origin,
+      );
+      let keep = (-FRICTION * dt).exp();
+      self.momentum = Momentum {
+        pan: [pan[0] * keep, pan[1] * keep],
+        zoom: zoom * keep,
+        spin: spin * keep,
+      };
+    }
+    if self.snapping && !self.manipulation.is_active() && !self.momentum.is_moving() {
+      let bearing = map::angle_between(0.0, self.camera.bearing);
+      let turned = anim::follow(bearing, 0.0, SNAP_RESPONSE, delta);
+      self.camera.bearing = turned.rem_euclid(360.0);
+      if turned.abs() <= SETTLED {
+        self.camera.bearing = 0.0;
+        self.snapping = false;
+      }
+    }
+    if let Some((position, _)) = self.position.filter(|_| self.follow) {
+      self.camera.center = position.project();
+    }
+  }
+
+  fn animating(&self) -> bool {
+    let idle = !self.manipulation.is_active();
+    let fading = self
+      .labels
+      .borrow()
+      .iter()
+      .flat_map(HashMap::values)
+      .any(|opacity| *opacity < 1.0);
+    let dusk = self.dusk != if self.night == Some(true) { 1.0 } else { 0.0 };
+    fading || dusk || idle && (self.momentum.is_moving() || self.snapping)
+  }
+
+  fn draw(&self, frame: &mut Frame, rect: Rect) {
+    frame.canvas.rect(
+      [rect.x, rect.y],
+      [rect.width, rect.height],
+      Style::fill(self.shown.land),
+    );
+    self.draw_features(frame.canvas, rect);
+    self.draw_traffic(frame.canvas, rect);
+    self.draw_route(frame.canvas, rect);
+    self.draw_labels(frame, rect);
+    self.draw_position(frame.canvas, rect);
+    self.draw_compass(frame, rect);
+  }
+
+  fn pointer(&mut self, event: &PointerEvent, rect: Rect) -> bool {
+    self.origin = rect.center();
+    let followed = self.manipulation.follows(event.id);
+    let motion = self.manipulation.pointer(event);
+    match event.phase {
+      PointerPhase::Down if self.manipulation.follows(event.id) => {
+        self.momentum = Momentum::default();
+        self.snapping = false;
+        self.moved = self.time;
+        if self.manipulation.pointers() == 1 {
+          self.pressed = Some(event.position);
+          self.dragging = false;
+        } else {
+          // A second finger is meant for the map
+          self.dragging = true;
+        }
+      }
+      PointerPhase::Move => {
+        if let Some(start) = self.pressed.filter(|_| !self.dragging) {
+          let [dx, dy] = [event.position[0] - start[0], event.position[1] - start[1]];
+          self.dragging = dx.hypot(dy) > DRAG_SLOP;
+        }
+        if let Some(motion) = motion.filter(|_| self.dragging) {
+          self.follow = false;
+          self.manipulate(motion);
+        }
+      }
+      // Once the last finger is up
+      PointerPhase::Up | PointerPhase::Cancel if followed && !self.manipulation.is_active() => {
+        if self.dragging && event.phase == PointerPhase::Up {
+          self.release();
+        } else {
+          self.momentum = Momentum::default();
+        }
+        self.pressed = None;
+        self.dragging = false;
+      }
+      _ => {}
+    }
+    followed || self.manipulation.follows(event.id)
+  }
+
+  fn captures(&self, id: PointerId) -> bool {
+    self.dragging && self.manipulation.follows(id)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  const RECT: Rect = Rect {
+    x: 0.0,
+    y: 0.0,
+    width: 800.0,
+    height: 480.0,
+  };
+
+  fn finger(id: u64, phase: PointerPhase, position: [f32; 2]) -> PointerEvent {
+    PointerEvent {
+      id: PointerId::Touch(id),
+      phase,
+      position,
+    }
+  }
+
+  fn view() -> MapView {
+    MapView::new(MapData::default()).with_camera(Coordinate::new(52.52, 13.405), 15.0)
+  }
+
+  #[test]
+  fn fades_to_the_night_colors_with_the_palette() {
+    let mut map = view();
+    map.set_palette(&Palette::night());
+    assert_eq!(map.shown, map.night_colors);
+    assert!(!map.animating());
+
+    map.set_palette(&Palette::day());
+    map.update(Duration::from_secs(1));
+    assert!(map.animating());
+    assert_eq!(map.shown, map.colors.mix(&map.night_colors, 0.5));
+    map.update(Duration::from_secs(1));
+    assert_eq!(map.shown, map.colors);
+    assert!(!map.animating());
+  }
+
+  #[test]
+  fn places_labels_by_priority_without_overlaps() {
+    let label = |feature, name, priority, x| Candidate {
+      feature,
+      name,
+      priority,
+      bounds: Rect::new(x, 100.0, 80.0, 20.0),
+      shown: false,
+    };
+    let mut candidates = [
+      label(0, "Village", 0, 100.0),
+      label(1, "City", 5, 150.0),
+      label(2, "Hamlet", 0, 240.0),
+      label(3, "City", 0, 320.0),
+      label(4, "Farm", 0, 240.0),
+      label(5, "City", 0, 700.0),
+    ];
+    let placed = place(&mut candidates, 200.0);
+    // The village is under the city, the farm under the hamlet and the
+    // second city too close to the first
+    assert_eq!(placed, HashSet::from([1, 2, 5]));
+
+    // Already shown labels stay over others of the same priority
+    candidates[4].shown = true;
+    assert_eq!(place(&mut candidates, 200.0), HashSet::from([1, 4, 5]));
+  }
+
+  #[test]
+  fn flicking_keeps_the_map_drifting() {
+    let mut map = view();
+    let frame = Duration::from_millis(16);
+    map.pointer(&finger(1, PointerPhase::Down, [400.0, 240.0]), RECT);
+    for step in 1..=5 {
+      map.update(frame);
+      map.pointer(
+        &finger(1, PointerPhase::Move, [400.0 - step as f32 * 20.0, 240.0]),
+        RECT,
+      );
+    }
+    assert!(map.captures(PointerId::Touch(1)));
+    map.pointer(&finger(1, PointerPhase::Up, [300.0, 240.0]), RECT);
+    assert!(map.animating());
+    let released = map.camera.center;
+    map.update(frame);
+    // Dragged to the left, so it keeps moving east
+    assert!(map.camera.center[0] > released[0]);
+    for _ in 0..200 {
+      map.update(frame);
+    }
+    assert!(!map.animating());
+  }
+
+  #[test]
+  fn pinching_zooms_and_snaps_back_to_north() {
+    let mut map = view();
+    map.pointer(&finger(1, PointerPhase::Down, [300.0, 240.0]), RECT);
+    map.pointer(&finger(2, PointerPhase::Down, [500.0, 240.0]), RECT);
+    map.update(Duration::from_millis(16));
+    // Twice as far apart and turned a little clockwise
+    let turn = 0.1f32;
+    map.pointer(
+      &finger(
+        2,
+        PointerPhase::Move,
+        [300.0 + 400.0 * turn.cos(), 240.0 + 400.0 * turn.sin()],
+      ),
+      RECT,
+    );
+    assert!((map.camera.zoom - 16.0).abs() < 1e-3);
+    assert!(map.camera.bearing > 300.0);
+    map.update(Duration::from_millis(500));
+    map.pointer(&finger(2, PointerPhase::Up, [640.0, 280.0]), RECT);
+    map.pointer(&finger(1, PointerPhase::Up, [300.0, 240.0]), RECT);
+    assert!(map.animating());
+    for _ in 0..100 {
+      map.update(Duration::from_millis(16));
+    }
+    assert_eq!(map.camera.bearing, 0.0);
+    assert!(!map.animating());
+  }
+
+  #[test]
+  fn follows_the_vehicle_until_dragged() {
+    let mut map = MapView::new(MapData::default());
+    let telemetry = Telemetry {
+      latitude: Some(48.0),
+      longitude: Some(11.0),
+      ..Default::default()
+    };
+    map.set_telemetry(&telemetry);
+    map.update(Duration::from_millis(16));
+    assert_eq!(map.camera.center, Coordinate::new(48.0, 11.0).project());
+    map.pointer(&finger(1, PointerPhase::Down, [400.0, 240.0]), RECT);
+    map.pointer(&finger(1, PointerPhase::Move, [300.0, 240.0]), RECT);
+    assert!(!map.follow);
+    map.recenter();
+    map.update(Duration::from_millis(16));
+    assert_eq!(map.camera.center, Coordinate::new(48.0, 11.0).project());
+  }
+}
@@ -0,0 +1,385 @@
+use std::cell::RefCell;
+
+use crate::accessibility::{Accessible, Role};
+use crate::canvas::Style;
+use crate::frame::Frame;
+use crate::input::{Action, PointerEvent, PointerId, PointerPhase};
+use crate::text::{Align, TextSection, VAlign};
+use crate::theme::Palette;
+use crate::widgets::{Rect, Widget, MIN_TARGET};
+
+/// An entry of a [`Menu`].
+#[derive(Clone, Debug, PartialEq)]
+pub struct MenuItem {
+  pub label: String,
+  pub kind: MenuItemKind,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum MenuItemKind {
+  /// Reported to [`Menu::on_select`] by its id when activated.
+  Action(String),
+  /// Opens a nested list.
+  Submenu(Vec<MenuItem>),
+}
+
+impl MenuItem {
+  pub fn action(label: impl Into<String>, id: impl Into<String>) -> Self {
+    Self {
+      label: label.into(),
+      kind: MenuItemKind::Action(id.into()),
+    }
+  }
+
+  pub fn submenu(label: impl Into<String>, items: Vec<MenuItem>) -> Self {
+    Self {
+      label: label.into(),
+      kind: MenuItemKind::Submenu(items),
+    }
+  }
+}
+
+type SelectHook = Box<dyn FnMut(&str)>;
+
+/// Linear RGBA colors in straight alpha.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct MenuColors {
+  pub background: [f32; 4],
+  /// Behind the highlighted item.
+  pub highlight: [f32; 4],
+  pub text: [f32; 4],
+  /// The breadcrumbs of the levels above.
+  pub breadcrumbs: [f32; 4],
+}
+
+impl Default for MenuColors {
+  fn default() -> Self {
+    Self {
+      background: [0.0, 0.0, 0.0, 0.5],
+      highlight: [0.2, 0.6, 1.0, 0.6],
+      text: [1.0, 1.0, 1.0, 1.0],
+      breadcrumbs: [1.0, 1.0, 1.0, 0.6],
+    }
+  }
+}
+
+/// A list of items that opens nested lists, with breadcrumbs leading back
+/// up along the top.
+///
+/// Touch works by tapping items and breadcrumbs; rotary encoders and keys
+/// drive it through [`next`](Self::next), [`previous`](Self::previous),
+/// [`activate`](Self::activate) and [`back`](Self::back).
+pub struct Menu {
+  /// The first breadcrumb, leading back to the top level.
+  pub title: String,
+  pub items: Vec<MenuItem>,
+  pub row_height: f32,
+  pub colors: MenuColors,
+  /// Indices of the submenus opened, from the top level down.
+  path: Vec<usize>,
+  highlighted: usize,
+  pressed: Option<(PointerId, Target)>,
+  // Where the breadcrumbs were last drawn, their width depends on the font
+  crumbs: RefCell<Vec<Rect>>,
+  on_select: Option<SelectHook>,
+}
+
+/// What a press landed on.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Target {
+  Item(usize),
+  /// The breadcrumb leading this many levels down.
+  Breadcrumb(usize),
+}
+
+impl Menu {
+  pub fn new(title: impl Into<String>, items: Vec<MenuItem>) -> Self {
+    Self {
+      title: title.into(),
+      items,
+      row_height: 56.0,
+      colors: MenuColors::default(),
+      path: Vec::new(),
+      highlighted: 0,
+      pressed: None,
+      crumbs: RefCell::default(),
+      on_select: None,
+    }
+  }
+
+  pub fn with_row_height(mut self, row_height: f32) -> Self {
+    self.row_height = row_height;
+    self
+  }
+
+  pub fn with_colors(mut self, colors: MenuColors) -> Self {
+    self.colors = colors;
+    self
+  }
+
+  /// Called with the id of every action activated.
+  pub fn on_select(mut self, callback: impl FnMut(&str) + 'static) -> Self {
+    self.on_select = Some(Box::new(callback));
+    self
+  }
+
+  /// Items of the open level.
+  pub fn current(&self) -> &[MenuItem] {
+    self.level(self.path.len())
+  }
+
+  /// Items `depth` submenus down the open path.
+  fn level(&self, depth: usize) -> &[MenuItem] {
+    let mut items = self.items.as_slice();
+    for index in &self.path[..depth] {
+      if let MenuItemKind::Submenu(children) = &items[*index].kind {
+        items = children;
+      }
+    }
+    items
+  }
+
+  /// The title followed by the labels of the opened submenus.
+  pub fn breadcrumbs(&self) -> Vec<&str> {
+    let submenus =
+      (0..self.path.len()).map(|depth| self.level(depth)[self.path[depth]].label.as_str());
+    std::iter::once(self.title.as_str())
+      .chain(submenus)
+      .collect()
+  }
+
+  pub fn highlighted(&self) -> usize {
+    self.highlighted
+  }
+
+  pub fn next(&mut self) {
+    let len = self.current().len();
+    if len > 0 {
+      self.highlighted = (self.highlighted + 1) % len;
+    }
+  }
+
+  pub fn previous(&mut self) {
+    let len = self.current().len();
+    if len > 0 {
+      self.highlighted = (self.highlighted + len - 1) % len;
+    }
+  }
+
+  /// Opens the highlighted submenu or selects the highlighted action.
+  pub fn activate(&mut self) {
+    let Some(item) = self.current().get(self.highlighted) else {
+      return;
+    };
+    match &item.kind {
+      MenuItemKind::Submenu(_) => {
+        self.path.push(self.highlighted);
+        self.highlighted = 0;
+      }
+      MenuItemKind::Action(id) => {
+        let id = id.clone();
+        if let Some(on_select) = &mut self.on_select {
+          on_select(&id);
+        }
+      }
+    }
+  }
+
+  /// Goes up a level, highlighting the submenu that was open. Returns
+  /// whether there was a level to go up to.
+  pub fn back(&mut self) -> bool {
+    match self.path.pop() {
+      Some(index) => {
+        self.highlighted = index;
+        true
+      }
+      None => false,
+    }
+  }
+
+  /// Goes up to `depth` levels below the top.
+  fn back_to(&mut self, depth: usize) {
+    while self.path.len() > depth {
+      self.back();
+    }
+  }
+
+  /// Number of items that fit below the breadcrumbs.
+  fn rows(&self, rect: Rect) -> usize {
+    ((rect.height / self.row_height) as usize)
+      .saturating_sub(1)
+      .max(1)
+  }
+
+  /// First item shown, scrolled so the highlighted one is visible.
+  fn first_visible(&self, rect: Rect) -> usize {
+    (self.highlighted + 1).saturating_sub(self.rows(rect))
+  }
+
+  fn target(&self, rect: Rect, position: [f32; 2]) -> Option<Target> {
+    if !rect.contains(position) {
+      return None;
+    }
+    if position[1] < rect.y + self.row_height {
+      return self
+        .crumbs
+        .borrow()
+        .iter()
+        .position(|crumb| crumb.contains(position))
+        .map(Target::Breadcrumb);
+    }
+    let row = ((position[1] - rect.y - self.row_height) / self.row_height) as usize;
+    let index = self.first_visible(rect) + row;
+    (index < self.current().len()).then_some(Target::Item(index))
+  }
+}
+
+impl Widget for Menu {
+  fn focusable(&self) -> bool {
+    true
+  }
+
+  fn accessible(&self) -> Option<Accessible> {
+    let items = self
+      .current()
+      .iter()
+      .enumerate()
+      .map(|(index, item)| {
+        Accessible::new(Role::MenuItem, item.label.as_str())
+          .with_selected(index == self.highlighted)
+      })
+      .collect();
+    Some(Accessible::new(Role::Menu, self.breadcrumbs().join(" › ")).with_children(items))
+  }
+
+  fn action(&mut self, action: Action) -> bool {
+    match action {
+      Action::Next => self.next(),
+      Action::Previous => self.previous(),
+      Action::Activate => self.activate(),
+      // At the top the focus stays, there's nowhere to go back to
+      Action::Back => return self.back(),
+      _ => return false,
+    }
+    true
+  }
+
+  fn set_palette(&mut self, palette: &Palette) {
+    let [r, g, b, _] = palette.accent;
+    self.colors = MenuColors {
+      background: palette.surface,
+      highlight: [r, g, b, 0.6],
+      text: palette.text,
+      breadcrumbs: palette.muted,
+    };
+  }
+
+  fn draw(&self, frame: &mut Frame, rect: Rect) {
+    let colors = &self.colors;
+    let size = self.row_height * 0.4;
+    let padding = self.row_height * 0.3;
+    frame.canvas.rect(
+      [rect.x, rect.y],
+      [rect.width, rect.height],
+      Style::fill(colors.background),
+    );
+
+    let y = rect.y + self.row_height / 2.0;
+    // Breadcrumbs and labels start on the right when reading from right
+    // to left, and the way into submenus points left
+    let [start, end] = [rect.x + padding, rect.x + rect.width - padding];
+    let (start, end, align, end_align, chevron, advance) = match frame.right_to_left {
+      true => (end, start, Align::Right, Align::Left, "‹", -1.0),
+      false => (start, end, Align::Left, Align::Right, "›", 1.0),
+    };
+    let mut x = start;
+    let mut crumbs = self.crumbs.borrow_mut();
+    crumbs.clear();
+    let breadcrumbs = self.breadcrumbs();
+    for (depth, label) in breadcrumbs.iter().enumerate() {
+      let last = depth + 1 == breadcrumbs.len();
+      let section = TextSection::new(*label)
+        .at(x, y)
+        .with_size(size)
+        .with_color(if last {
+          colors.text
+        } else {
+          colors.breadcrumbs
+        })
+        .with_align(align, VAlign::Center);
+      let width = frame.text.line_width(&section);
+      frame.text.queue(&section);
+      let left = if frame.right_to_left { x - width } else { x };
+      crumbs.push(Rect::new(left, rect.y, width, self.row_height).at_least(MIN_TARGET));
+      x += advance * width;
+
+      if !last {
+        let separator = TextSection::new(format!("  {}  ", chevron))
+          .at(x, y)
+          .with_size(size)
+          .with_color(colors.breadcrumbs)
+          .with_align(align, VAlign::Center);
+        x += advance * frame.text.line_width(&separator);
+        frame.text.queue(&separator);
+      }
+    }
+
+    let first = self.first_visible(rect);
+    let items = self.current().iter().enumerate().skip(first);
+    for (row, (index, item)) in items.take(self.rows(rect)).enumerate() {
+      let top = rect.y + self.row_height * (row + 1) as f32;
+      if index == self.highlighted {
+        frame.canvas.rect(
+          [rect.x, top],
+          [rect.width, self.row_height],
+          Style::fill(colors.highlight),
+        );
+      }
+      let y = top + self.row_height / 2.0;
+      frame.text.queue(
+        &TextSection::new(item.label.as_str())
+          .at(start, y)
+          .with_size(size)
+          .with_color(colors.text)
+          .with_align(align, VAlign::Center),
+      );
+      if let MenuItemKind::Submenu(_) = item.kind {
+        frame.text.queue(
+          &TextSection::new(chevron)
+            .at(end, y)
+            .with_size(size)
+            .with_color(colors.text)
+            .with_align(end_align, VAlign::Center),
+        );
+      }
+    }
+  }
+
+  fn pointer(&mut self, event: &PointerEvent, rect: Rect) -> bool {
+    match event.phase {
+      PointerPhase::Down if self.pressed.is_none() => {
+        let target = self.target(rect, event.position);
+        self.pressed = target.map(|target| (event.id, target));
+        if let Some(Target::Item(index)) = target {
+          self.highlighted = index;
+        }
+        target.is_some()
+      }
+      PointerPhase::Up | PointerPhase::Cancel => {
+        let Some((_, target)) = self.pressed.filter(|(id, _)| *id == event.id) else {
+          return false;
+        };
+        self.pressed = None;
+        // Only a tap ending where it started counts
+        if event.phase == PointerPhase::Up && self.target(rect, event.position) == Some(target) {
+          match target {
+            Target::Item(_) => self.activate(),
+            Target::Breadcrumb(depth) => self.back_to(depth),
+          }
+        }
+        true
+      }
+      _ => false,
+    }
+  }
+}
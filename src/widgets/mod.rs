@@ -0,0 +1,217 @@
+use std::any::Any;
+use std::time::Duration;
+
+use crate::accessibility::Accessible;
+use crate::data::Telemetry;
+use crate::frame::Frame;
+use crate::input::{Action, PointerEvent, PointerId};
+use crate::theme::Palette;
+
+mod carousel;
+mod controls;
+mod dialog;
+mod energy;
+mod gap;
+mod gauge;
+mod gmeter;
+mod label;
+mod map;
+mod menu;
+mod navigation;
+mod parking;
+mod picture;
+mod qr;
+mod ring;
+mod search;
+mod traffic;
+mod vehicle;
+mod weather;
+
+pub use self::carousel::Carousel;
+pub use self::controls::{Binding, ControlColors, Feedback, RadioGroup, Slider, Toggle};
+pub use self::dialog::{Choice, Dialog, DialogColors};
+pub use self::energy::{EnergyColors, EnergyFlow};
+pub use self::gap::{FollowingGap, GapColors};
+pub use self::gauge::{GaugeColors, RadialGauge, Readout, Zone};
+pub use self::gmeter::{GMeter, GMeterColors};
+pub use self::label::{Label, Marquee};
+pub use self::map::{MapColors, MapView};
+pub use self::menu::{Menu, MenuColors, MenuItem, MenuItemKind};
+pub use self::navigation::{NavigationArrow, NavigationColors};
+pub use self::parking::{Beep, ParkingAssist, ParkingColors, Proximity};
+pub use self::picture::Picture;
+pub use self::qr::{Correction, QrCode};
+pub use self::ring::{Cap, ProgressRing};
+pub use self::search::{SearchColors, SearchScreen};
+pub use self::traffic::{TrafficColors, TrafficLegend};
+pub use self::vehicle::{Part, PartState, PressureUnit, VehicleColors, VehicleSchematic};
+pub use self::weather::WeatherPanel;
+
+/// Smallest hit target of interactive widgets in physical pixels, so small
+/// controls can still be hit with a finger.
+const MIN_TARGET: f32 = 48.0;
+
+/// An axis aligned rectangle in physical pixels, the origin is in the top
+/// left corner.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Rect {
+  pub x: f32,
+  pub y: f32,
+  pub width: f32,
+  pub height: f32,
+}
+
+impl Rect {
+  pub fn new(x: f32, y: f32, width: f32, height: f32) -> Self {
+    Self {
+      x,
+      y,
+      width,
+      height,
+    }
+  }
+
+  pub fn center(&self) -> [f32; 2] {
+    [self.x + self.width / 2.0, self.y + self.height / 2.0]
+  }
+
+  /// The shorter of both sides.
+  pub fn min_side(&self) -> f32 {
+    self.width.min(self.height)
+  }
+
+  pub fn contains(&self, point: [f32; 2]) -> bool {
+    let [x, y] = point;
+    x >= self.x && x < self.x + self.width && y >= self.y && y < self.y + self.height
+  }
+
+  /// Where both rects are, empty if they don't overlap.
+  pub fn intersect(&self, other: &Rect) -> Rect {
+    let x = self.x.max(other.x);
+    let y = self.y.max(other.y);
+    let right = (self.x + self.width).min(other.x + other.width);
+    let bottom = (self.y + self.height).min(other.y + other.height);
+    Rect::new(x, y, (right - x).max(0.0), (bottom - y).max(0.0))
+  }
+
+  /// Grows the rect around its center until both sides are at least
+  /// `size` long.
+  pub fn at_least(&self, size: f32) -> Rect {
+    let width = self.width.max(size);
+    let height = self.height.max(size);
+    let [x, y] = self.center();
+    Rect::new(x - width / 2.0, y - height / 2.0, width, height)
+  }
+}
+
+/// Something that draws itself into a [`Rect`] of the frame.
+///
+/// Widgets own their configuration and animation state, the space they
+/// occupy is passed in every frame:
+///
+/// ```no_run
+/// use windshield_rs::widgets::{RadialGauge, Rect};
+///
+/// let mut speed = RadialGauge::speedometer(240.0);
+/// let app = windshield_rs::WindshieldApp::builder()
+///   .on_draw(move |frame| {
+///     speed.set_value(0.4);
+///     frame.widget(&mut speed, Rect::new(20.0, 20.0, 300.0, 300.0));
+///   })
+///   .build();
+/// ```
+pub trait Widget: Any {
+  /// Advances animations, called once per frame before drawing.
+  fn update(&mut self, _delta: Duration) {}
+
+  fn draw(&self, frame: &mut Frame, rect: Rect);
+
+  /// Sets the normalized value shown, for widgets that show one.
+  fn set_value(&mut self, _value: f32) {}
+
+  /// Takes what the widget shows from the latest telemetry, called every
+  /// frame before [`update`](Self::update). For widgets showing several
+  /// values, those showing one are driven by a
+  /// [`ValueSource`](crate::data::ValueSource) instead.
+  fn set_telemetry(&mut self, _telemetry: &Telemetry) {}
+
+  /// Whether the telemetry behind the value set with
+  /// [`set_value`](Self::set_value) stopped updating, see
+  /// [`Settings::stale_after`](crate::Settings::stale_after). Widgets
+  /// showing it as a number show dashes instead, the
+  /// [`Node`](crate::layout::Node) dims them either way.
+  fn set_stale(&mut self, _stale: bool) {}
+
+  /// Takes the widget's colors from `palette`, whenever the theme changes.
+  /// Containers pass it on to what they contain.
+  fn set_palette(&mut self, _palette: &Palette) {}
+
+  /// Whether a pointer pressed at `position` is meant for this widget,
+  /// drawn into `rect`.
+  fn hit(&self, rect: Rect, position: [f32; 2]) -> bool {
+    rect.contains(position)
+  }
+
+  /// Handles pointer input, returning whether it was used.
+  ///
+  /// Presses are only passed on if they [`hit`](Self::hit) the widget, the
+  /// rest of the pointer's events go to the widgets that used the press so
+  /// that drags can continue outside of them.
+  fn pointer(&mut self, _event: &PointerEvent, _rect: Rect) -> bool {
+    false
+  }
+
+  /// Whether the widget wants pointer `id` to itself, e.g. once a drag went
+  /// far enough to be a swipe. Asked after every event while overlapping
+  /// widgets share the pointer, the others get a
+  /// [`Cancel`](crate::input::PointerPhase::Cancel) once one does, see
+  /// [`GestureArena`](crate::input::GestureArena).
+  fn captures(&self, _id: PointerId) -> bool {
+    false
+  }
+
+  /// Whether the widget can have focus and handle [`Action`]s.
+  fn focusable(&self) -> bool {
+    false
+  }
+
+  /// Whether the widget takes all input while it is shown, like an open
+  /// [`Dialog`]. Presses and actions then only go to the topmost modal
+  /// widget and those above it, whatever has focus.
+  fn modal(&self) -> bool {
+    false
+  }
+
+  /// Whether the widget still changes on its own, e.g. while an animation
+  /// settles. Frames keep being drawn while one does.
+  fn animating(&self) -> bool {
+    false
+  }
+
+  /// How screen readers present the widget, or `None` for those they
+  /// skip, like decorations. See [`accessibility`](crate::accessibility).
+  fn accessible(&self) -> Option<Accessible> {
+    None
+  }
+
+  /// Handles an action while the widget has focus, returning whether it
+  /// was used. Unused [`Next`](Action::Next) and
+  /// [`Previous`](Action::Previous) move the focus on to the neighbouring
+  /// widget.
+  fn action(&mut self, _action: Action) -> bool {
+    false
+  }
+}
+
+impl Frame<'_> {
+  /// Updates `widget` from the telemetry and by the time since the last
+  /// frame and draws it into `rect`.
+  pub fn widget(&mut self, widget: &mut dyn Widget, rect: Rect) {
+    widget.set_telemetry(self.telemetry);
+    widget.update(self.delta);
+    widget.draw(self, rect);
+    if widget.animating() {
+      self.animate();
+    }
+  }
+}
@@ -0,0 +1,418 @@
+use std::f32::consts::{FRAC_PI_2, PI};
+
+use crate::canvas::{Canvas, Style};
+use crate::data::Telemetry;
+use crate::frame::Frame;
+use crate::locale::NumberFormat;
+use crate::map::route::{Maneuver, Turn};
+use crate::map::Coordinate;
+use crate::text::{Align, TextSection, VAlign};
+use crate::theme::Palette;
+use crate::widgets::{Rect, Widget};
+
+const FEET_PER_METER: f32 = 3.280_84;
+const METERS_PER_MILE: f32 = 1609.344;
+
+/// Linear RGBA colors in straight alpha.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct NavigationColors {
+  pub arrow: [f32; 4],
+  /// The roundabout around the arrow and what isn't taken.
+  pub road: [f32; 4],
+  pub text: [f32; 4],
+  /// Shown while too far from the route.
+  pub off_route: [f32; 4],
+}
+
+impl Default for NavigationColors {
+  fn default() -> Self {
+    Self {
+      arrow: [1.0, 1.0, 1.0, 1.0],
+      road: [1.0, 1.0, 1.0, 0.3],
+      text: [1.0, 1.0, 1.0, 1.0],
+      off_route: [1.0, 0.75, 0.2, 1.0],
+    }
+  }
+}
+
+/// The next maneuver along [`Telemetry::route`] as an arrow, with how far
+/// away it is and the road it leads onto.
+///
+/// Where the vehicle is along the route comes from
+/// [`Telemetry::latitude`] and [`Telemetry::longitude`]. Nothing is shown
+/// without a route, and a warning once the vehicle is further than
+/// `off_route` from it.
+#[derive(Clone, Debug)]
+pub struct NavigationArrow {
+  /// m from the route beyond which the vehicle is considered off it.
+  pub off_route: f64,
+  /// Shows distances in miles and feet rather than km and m.
+  pub miles: bool,
+  /// How distances are written, like with a decimal comma.
+  pub number_format: NumberFormat,
+  pub colors: NavigationColors,
+  next: Option<Next>,
+}
+
+/// What [`NavigationArrow`] shows.
+#[derive(Clone, Debug, PartialEq)]
+enum Next {
+  Maneuver { maneuver: Maneuver, distance: f64 },
+  OffRoute,
+}
+
+impl Default for NavigationArrow {
+  fn default() -> Self {
+    Self::new()
+  }
+}
+
+impl NavigationArrow {
+  /// In km, off the route beyond 50 m.
+  pub fn new() -> Self {
+    Self {
+      off_route: 50.0,
+      miles: false,
+      number_format: NumberFormat::default(),
+      colors: NavigationColors::default(),
+      next: None,
+    }
+  }
+
+  pub fn miles(mut self) -> Self {
+    self.miles = true;
+    self
+  }
+
+  pub fn with_number_format(mut self, number_format: NumberFormat) -> Self {
+    self.number_format = number_format;
+    self
+  }
+
+  pub fn with_colors(mut self, colors: NavigationColors) -> Self {
+    self.colors = colors;
+    self
+  }
+
+  /// `meters` rounded the way navigation announces them, coarser the
+  /// further away. The unit and decimals go by the rounded distance, so
+  /// 995 m is 1.0 km rather than 1000 m.
+  fn distance(&self, meters: f64) -> String {
+    let meters = meters as f32;
+    let number = |value, decimals| self.number_format.format(value, decimals);
+    // Rounded to a tenth of `value`'s unit
+    let tenths = |value: f32| (value * 10.0).round() / 10.0;
+    if self.miles {
+      let miles = meters / METERS_PER_MILE;
+      if miles < 0.1 {
+        let feet = ((meters * FEET_PER_METER / 50.0).round() * 50.0).max(50.0);
+        format!("{} ft", number(feet, 0))
+      } else if tenths(miles) < 10.0 {
+        format!("{} mi", number(miles, 1))
+      } else {
+        format!("{} mi", number(miles, 0))
+      }
+    } else {
+      let rounded = ((meters / 10.0).round() * 10.0).max(10.0);
+      let km = meters / 1000.0;
+      if rounded < 1000.0 {
+        format!("{} m", number(rounded, 0))
+      } else if tenths(km) < 10.0 {
+        format!("{} km", number(km, 1))
+      } else {
+        format!("{} km", number(km, 0))
+      }
+    }
+  }
+}
+
+/// Degrees clockwise from straight ahead a turn goes.
+fn angle(turn: Turn) -> f32 {
+  match turn {
+    Turn::SlightLeft => -45.0,
+    Turn::Left => -90.0,
+    Turn::SharpLeft => -135.0,
+    Turn::SlightRight => 45.0,
+    Turn::Right => 90.0,
+    Turn::SharpRight => 135.0,
+    _ => 0.0,
+  }
+}
+
+/// An arrow head at `tip` pointing `direction` radians clockwise from up.
+fn head(canvas: &mut Canvas, tip: [f32; 2], direction: f32, size: f32, color: [f32; 4]) {
+  let (sin, cos) = direction.sin_cos();
+  let [forward, side] = [[sin, -cos], [cos, sin]];
+  let back = [tip[0] - forward[0] * size, tip[1] - forward[1] * size];
+  canvas.polygon(
+    &[
+      tip,
+      [
+        back[0] + side[0] * size * 0.7,
+        back[1] + side[1] * size * 0.7,
+      ],
+      [
+        back[0] - side[0] * size * 0.7,
+        back[1] - side[1] * size * 0.7,
+      ],
+    ],
+    Style::fill(color),
+  );
+}
+
+impl NavigationArrow {
+  /// The icon of `maneuver` in a square around `center`, `size` wide.
+  fn draw_icon(&self, canvas: &mut Canvas, center: [f32; 2], size: f32, maneuver: &Maneuver) {
+    let colors = &self.colors;
+    let width = size * 0.1;
+    let [x, y] = center;
+    let bottom = [x, y + size * 0.45];
+    match maneuver.turn {
+      Turn::Arrive => {
+        // A pin on the destination
+        canvas.line(bottom, [x, y + size * 0.05], colors.arrow, width);
+        canvas.circle([x, y - size * 0.12], size * 0.2, Style::fill(colors.arrow));
+      }
+      Turn::UTurn => {
+        let radius = size * 0.2;
+        let left = x - radius * 2.0;
+        canvas.line(bottom, [x, y - size * 0.1], colors.arrow, width);
+        canvas.arc(
+          [x - radius, y - size * 0.1],
+          radius,
+          0.0,
+          -PI,
+          Style::stroke(colors.arrow, width),
+        );
+        canvas.line(
+          [left, y - size * 0.1],
+          [left, y + size * 0.15],
+          colors.arrow,
+          width,
+        );
+        head(
+          canvas,
+          [left, y + size * 0.35],
+          PI,
+          size * 0.22,
+          colors.arrow,
+        );
+      }
+      Turn::Roundabout => {
+        let radius = size * 0.2;
+        canvas.circle(center, radius, Style::stroke(colors.road, width));
+        canvas.line(bottom, [x, y + radius], colors.arrow, width);
+        // Driving counterclockwise, the first exit on the right
+        let exit = maneuver.exit.unwrap_or(1).max(1) as f32;
+        let direction = (FRAC_PI_2 - (exit - 1.0) * FRAC_PI_2).max(-PI * 0.75);
+        let (sin, cos) = direction.sin_cos();
+        // Screen angles start at the right and go clockwise
+        let from = FRAC_PI_2;
+        let to = direction - FRAC_PI_2;
+        canvas.arc(
+          center,
+          radius,
+          from,
+          (to - from).rem_euclid(2.0 * PI) - 2.0 * PI,
+          Style::stroke(colors.arrow, width),
+        );
+        let start = [x + sin * radius, y - cos * radius];
+        let end = [x + sin * size * 0.32, y - cos * size * 0.32];
+        canvas.line(start, end, colors.arrow, width);
+        head(
+          canvas,
+          [x + sin * size * 0.48, y - cos * size * 0.48],
+          direction,
+          size * 0.2,
+          colors.arrow,
+        );
+      }
+      turn => {
+        let direction = angle(turn).to_radians();
+        let middle = [x, y + size * 0.05];
+        let (sin, cos) = direction.sin_cos();
+        if turn != Turn::Straight && turn != Turn::Depart {
+          // The road carrying on straight, not taken
+          canvas.line(middle, [x, y - size * 0.45], colors.road, width);
+        }
+        let reach = size * 0.3;
+        let end = [middle[0] + sin * reach, middle[1] - cos * reach];
+        canvas.line(bottom, middle, colors.arrow, width);
+        canvas.line(middle, end, colors.arrow, width);
+        head(
+          canvas,
+          [end[0] + sin * size * 0.15, end[1] - cos * size * 0.15],
+          direction,
+          size * 0.22,
+          colors.arrow,
+        );
+      }
+    }
+  }
+}
+
+impl Widget for NavigationArrow {
+  fn set_palette(&mut self, palette: &Palette) {
+    self.colors.arrow = palette.text;
+    self.colors.text = palette.text;
+    self.colors.road = palette.muted;
+  }
+
+  fn set_telemetry(&mut self, telemetry: &Telemetry) {
+    let route = telemetry.route.as_deref().filter(|route| !route.is_empty());
+    let position = match (telemetry.latitude, telemetry.longitude) {
+      (Some(latitude), Some(longitude)) => Some(Coordinate::new(latitude as f64, longitude as f64)),
+      _ => None,
+    };
+    self.next = match (route, position) {
+      (Some(route), Some(position)) => match route.progress(position) {
+        Some(progress) if progress.off_route > self.off_route => Some(Next::OffRoute),
+        Some(progress) => {
+          route
+            .upcoming(progress.along)
+            .map(|(maneuver, distance)| Next::Maneuver {
+              maneuver: maneuver.clone(),
+              distance,
+            })
+        }
+        None => None,
+      },
+      // Until there's a position, the way to set off
+      (Some(route), None) => route.maneuvers.first().map(|maneuver| Next::Maneuver {
+        maneuver: maneuver.clone(),
+        distance: 0.0,
+      }),
+      (None, _) => None,
+    };
+  }
+
+  fn draw(&self, frame: &mut Frame, rect: Rect) {
+    let Some(next) = &self.next else {
+      return;
+    };
+    let size = rect.width.min(rect.height * 0.6);
+    let [x, _] = rect.center();
+    let icon = [x, rect.y + rect.height * 0.3];
+    let text_size = rect.height * 0.14;
+    let (distance, road, color) = match next {
+      Next::Maneuver { maneuver, distance } => {
+        self.draw_icon(frame.canvas, icon, size * 0.9, maneuver);
+        (
+          self.distance(*distance),
+          maneuver.road.as_str(),
+          self.colors.text,
+        )
+      }
+      Next::OffRoute => {
+        let colors = &self.colors;
+        frame.canvas.circle(
+          icon,
+          size * 0.3,
+          Style::stroke(colors.off_route, size * 0.08),
+        );
+        frame.canvas.line(
+          [icon[0], icon[1] - size * 0.15],
+          [icon[0], icon[1] + size * 0.03],
+          colors.off_route,
+          size * 0.08,
+        );
+        frame.canvas.circle(
+          [icon[0], icon[1] + size * 0.15],
+          size * 0.045,
+          Style::fill(colors.off_route),
+        );
+        ("Off route".to_string(), "", colors.off_route)
+      }
+    };
+    frame.text.queue(
+      &TextSection::new(&distance)
+        .at(x, rect.y + rect.height * 0.72)
+        .with_size(text_size)
+        .with_color(color)
+        .with_align(Align::Center, VAlign::Center),
+    );
+    if !road.is_empty() {
+      let [r, g, b, a] = self.colors.text;
+      frame.text.queue(
+        &TextSection::new(road)
+          .at(x, rect.y + rect.height * 0.88)
+          .with_size(text_size * 0.6)
+          .with_color([r, g, b, a * 0.7])
+          .with_align(Align::Center, VAlign::Center),
+      );
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use std::sync::Arc;
+
+  use super::*;
+  use crate::map::route::Route;
+
+  fn route() -> Route {
+    let maneuver = |turn, latitude, distance| Maneuver {
+      turn,
+      exit: None,
+      position: Coordinate::new(latitude, 13.4),
+      distance,
+      road: "North Street".to_string(),
+    };
+    Route {
+      geometry: vec![Coordinate::new(52.5, 13.4), Coordinate::new(52.51, 13.4)],
+      maneuvers: vec![
+        maneuver(Turn::Depart, 52.5, 0.0),
+        maneuver(Turn::Right, 52.505, 556.0),
+        maneuver(Turn::Arrive, 52.51, 1112.0),
+      ],
+      distance: 1112.0,
+      duration: 90.0,
+    }
+  }
+
+  fn at(latitude: f32, longitude: f32) -> Telemetry {
+    Telemetry {
+      latitude: Some(latitude),
+      longitude: Some(longitude),
+      route: Some(Arc::new(route())),
+      ..Default::default()
+    }
+  }
+
+  #[test]
+  fn shows_the_next_maneuver_until_off_the_route() {
+    let mut arrow = NavigationArrow::new();
+    arrow.set_telemetry(&at(52.502, 13.4));
+    let Some(Next::Maneuver { maneuver, distance }) = &arrow.next else {
+      panic!("{:?}", arrow.next);
+    };
+    assert_eq!(maneuver.turn, Turn::Right);
+    assert!((distance - 334.0).abs() < 3.0, "{}", distance);
+    assert_eq!(arrow.distance(*distance), "330 m");
+    arrow.set_telemetry(&at(52.502, 13.41));
+    assert_eq!(arrow.next, Some(Next::OffRoute));
+    arrow.set_telemetry(&Telemetry::default());
+    assert_eq!(arrow.next, None);
+  }
+
+  #[test]
+  fn rounds_distances_coarser_further_away() {
+    let mut arrow = NavigationArrow::new();
+    assert_eq!(arrow.distance(3.0), "10 m");
+    assert_eq!(arrow.distance(2345.0), "2.3 km");
+    assert_eq!(arrow.distance(23456.0), "23 km");
+    // Rounded before the unit is picked
+    assert_eq!(arrow.distance(994.0), "990 m");
+    assert_eq!(arrow.distance(995.0), "1.0 km");
+    assert_eq!(arrow.distance(9940.0), "9.9 km");
+    assert_eq!(arrow.distance(9960.0), "10 km");
+    arrow.number_format = NumberFormat::for_tag("de").unwrap();
+    assert_eq!(arrow.distance(2345.0), "2,3 km");
+    arrow.number_format = NumberFormat::default();
+    arrow.miles = true;
+    assert_eq!(arrow.distance(100.0), "350 ft");
+    assert_eq!(arrow.distance(2000.0), "1.2 mi");
+    assert_eq!(arrow.distance(9.96 * METERS_PER_MILE as f64), "10 mi");
+  }
+}
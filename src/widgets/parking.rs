@@ -0,0 +1,325 @@
+use std::f32::consts::PI;
+use std::time::Duration;
+
+use crate::anim::{self, SETTLED};
+use crate::canvas::Style;
+use crate::data::Telemetry;
+use crate::frame::Frame;
+use crate::theme::Palette;
+use crate::widgets::{Rect, Widget};
+
+/// Angle covered by the four sensors at either end.
+const SWEEP: f32 = 140.0 * PI / 180.0;
+/// Angle left out between neighbouring segments.
+const SPACING: f32 = 3.0 * PI / 180.0;
+
+/// How close an obstacle is, by the ranges of a [`ParkingAssist`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Proximity {
+  Far,
+  Near,
+  Close,
+}
+
+/// What a parking sensor warning should sound like, sent whenever the
+/// closest obstacle moves into another range.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Beep {
+  /// Nothing in range any more, stop beeping.
+  Stop,
+  Slow,
+  Fast,
+  Continuous,
+}
+
+impl From<Option<Proximity>> for Beep {
+  fn from(proximity: Option<Proximity>) -> Self {
+    match proximity {
+      None => Beep::Stop,
+      Some(Proximity::Far) => Beep::Slow,
+      Some(Proximity::Near) => Beep::Fast,
+      Some(Proximity::Close) => Beep::Continuous,
+    }
+  }
+}
+
+type BeepHook = Box<dyn FnMut(Beep)>;
+
+/// Linear RGBA colors in straight alpha.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ParkingColors {
+  pub vehicle: [f32; 4],
+  /// Ranges without an obstacle.
+  pub track: [f32; 4],
+  pub far: [f32; 4],
+  pub near: [f32; 4],
+  pub close: [f32; 4],
+}
+
+impl Default for ParkingColors {
+  fn default() -> Self {
+    Self {
+      vehicle: [1.0, 1.0, 1.0, 1.0],
+      track: [1.0, 1.0, 1.0, 0.15],
+      far: [0.3, 0.85, 0.4, 1.0],
+      near: [1.0, 0.75, 0.2, 1.0],
+      close: [0.9, 0.1, 0.1, 1.0],
+    }
+  }
+}
+
+/// The vehicle from above with arcs in front and behind it lighting up
+/// where the parking sensors see something, from the outermost range for
+/// far obstacles to the innermost for close ones, see
+/// [`Telemetry::parking`].
+///
+/// Segments fade in and out rather than flickering with the sensors, and
+/// [`on_beep`](Self::on_beep) is told how to beep as the closest obstacle
+/// changes ranges:
+///
+/// ```no_run
+/// use windshield_rs::widgets::{Beep, ParkingAssist};
+///
+/// let assist = ParkingAssist::new().on_beep(|beep| match beep {
+///   Beep::Stop => { /* silence */ }
+///   beep => tracing::info!("beep {:?}", beep),
+/// });
+/// ```
+pub struct ParkingAssist {
+  /// m below which obstacles are far, near and close.
+  pub ranges: [f32; 3],
+  pub colors: ParkingColors,
+  /// How quickly segments fade in and out, per second.
+  pub response: f32,
+  // Front sensors first, then the rear ones
+  sensors: [Option<Proximity>; 8],
+  // Opacity and the last range shown, kept while fading out
+  fade: [(f32, Proximity); 8],
+  closest: Option<Proximity>,
+  on_beep: Option<BeepHook>,
+}
+
+impl Default for ParkingAssist {
+  fn default() -> Self {
+    Self::new()
+  }
+}
+
+impl ParkingAssist {
+  /// Far below 1.5 m, near below 0.9 m and close below 0.4 m.
+  pub fn new() -> Self {
+    Self {
+      ranges: [1.5, 0.9, 0.4],
+      colors: ParkingColors::default(),
+      response: 10.0,
+      sensors: [None; 8],
+      fade: [(0.0, Proximity::Far); 8],
+      closest: None,
+      on_beep: None,
+    }
+  }
+
+  pub fn with_ranges(mut self, far: f32, near: f32, close: f32) -> Self {
+    self.ranges = [far, near, close];
+    self
+  }
+
+  pub fn with_colors(mut self, colors: ParkingColors) -> Self {
+    self.colors = colors;
+    self
+  }
+
+  /// Calls `callback` whenever the closest obstacle moves into another
+  /// range.
+  pub fn on_beep(mut self, callback: impl FnMut(Beep) + 'static) -> Self {
+    self.on_beep = Some(Box::new(callback));
+    self
+  }
+
+  fn proximity(&self, distance: f32) -> Option<Proximity> {
+    let [far, near, close] = self.ranges;
+    if distance < close {
+      Some(Proximity::Close)
+    } else if distance < near {
+      Some(Proximity::Near)
+    } else if distance < far {
+      Some(Proximity::Far)
+    } else {
+      None
+    }
+  }
+
+  fn color(&self, proximity: Proximity) -> [f32; 4] {
+    match proximity {
+      Proximity::Far => self.colors.far,
+      Proximity::Near => self.colors.near,
+      Proximity::Close => self.colors.close,
+    }
+  }
+}
+
+impl Widget for ParkingAssist {
+  fn set_palette(&mut self, palette: &Palette) {
+    self.colors.vehicle = palette.text;
+    let [r, g, b, a] = palette.muted;
+    self.colors.track = [r, g, b, a * 0.3];
+    self.colors.close = palette.warning;
+  }
+
+  fn set_telemetry(&mut self, telemetry: &Telemetry) {
+    let distances = telemetry.parking.unwrap_or_default().all();
+    self.sensors = distances.map(|distance| distance.and_then(|distance| self.proximity(distance)));
+    let closest = self.sensors.iter().flatten().max().copied();
+    if closest != self.closest {
+      self.closest = closest;
+      if let Some(on_beep) = &mut self.on_beep {
+        on_beep(closest.into());
+      }
+    }
+  }
+
+  fn update(&mut self, delta: Duration) {
+    for (sensor, (opacity, shown)) in self.sensors.iter().zip(&mut self.fade) {
+      let target = match sensor {
+        Some(proximity) => {
+          *shown = *proximity;
+          1.0
+        }
+        None => 0.0,
+      };
+      *opacity = anim::follow(*opacity, target, self.response, delta);
+    }
+  }
+
+  fn animating(&self) -> bool {
+    self
+      .sensors
+      .iter()
+      .zip(&self.fade)
+      .any(|(sensor, (opacity, _))| {
+        let target = if sensor.is_some() { 1.0 } else { 0.0 };
+        (target - opacity).abs() > SETTLED
+      })
+  }
+
+  fn draw(&self, frame: &mut Frame, rect: Rect) {
+    let colors = &self.colors;
+    // Room for three ranges at both ends
+    let h = (rect.height * 0.5).min(rect.width * 0.5);
+    let w = h * 0.45;
+    let [cx, cy] = rect.center();
+    let (x, y) = (cx - w / 2.0, cy - h / 2.0);
+    let canvas = &mut *frame.canvas;
+    canvas.rounded_rect([x, y], [w, h], w * 0.35, Style::fill(colors.vehicle));
+    let [r, g, b, a] = colors.vehicle;
+    let window = Style::fill([r * 0.3, g * 0.3, b * 0.3, a]);
+    canvas.polygon(
+      &[
+        [x + w * 0.12, y + h * 0.28],
+        [x + w * 0.88, y + h * 0.28],
+        [x + w * 0.8, y + h * 0.37],
+        [x + w * 0.2, y + h * 0.37],
+      ],
+      window,
+    );
+
+    let band = h * 0.09;
+    let segment = (SWEEP - 3.0 * SPACING) / 4.0;
+    for (index, (opacity, proximity)) in self.fade.iter().enumerate() {
+      let front = index < 4;
+      // Left to right, above the front or below the rear
+      let (center, start) = if front {
+        let start = 1.5 * PI - SWEEP / 2.0;
+        (
+          [cx, y + w * 0.5],
+          start + (segment + SPACING) * index as f32,
+        )
+      } else {
+        let start = 0.5 * PI + SWEEP / 2.0 - segment;
+        (
+          [cx, y + h - w * 0.5],
+          start - (segment + SPACING) * (index - 4) as f32,
+        )
+      };
+      for (ring, range) in [Proximity::Close, Proximity::Near, Proximity::Far]
+        .into_iter()
+        .enumerate()
+      {
+        let inner = w * 0.75 + band * ring as f32 * 1.25;
+        let points = band_segment(center, inner, inner + band, start, segment);
+        let color = match range == *proximity && *opacity > SETTLED {
+          true => {
+            let [r, g, b, a] = self.color(range);
+            [r, g, b, a * opacity]
+          }
+          false => colors.track,
+        };
+        canvas.polygon(&points, Style::fill(color));
+      }
+    }
+  }
+}
+
+/// Outline of the part of a ring between `inner` and `outer` covering
+/// `sweep` from `start`, cut off square at both ends.
+fn band_segment(center: [f32; 2], inner: f32, outer: f32, start: f32, sweep: f32) -> Vec<[f32; 2]> {
+  const STEPS: usize = 8;
+  let at = |step: usize, radius: f32| {
+    let angle = start + sweep * step as f32 / STEPS as f32;
+    [
+      center[0] + radius * angle.cos(),
+      center[1] + radius * angle.sin(),
+    ]
+  };
+  let outside = (0..=STEPS).map(|step| at(step, outer));
+  let inside = (0..=STEPS).rev().map(|step| at(step, inner));
+  outside.chain(inside).collect()
+}
+
+#[cfg(test)]
+mod tests {
+  use std::cell::RefCell;
+  use std::rc::Rc;
+
+  use super::*;
+  use crate::data::vehicle::ParkingSensors;
+
+  fn sensed(front: [Option<f32>; 4]) -> Telemetry {
+    Telemetry {
+      parking: Some(ParkingSensors {
+        front,
+        ..Default::default()
+      }),
+      ..Default::default()
+    }
+  }
+
+  #[test]
+  fn beeps_as_the_closest_obstacle_changes_ranges() {
+    let beeps = Rc::new(RefCell::new(Vec::new()));
+    let recorded = beeps.clone();
+    let mut assist = ParkingAssist::new().on_beep(move |beep| recorded.borrow_mut().push(beep));
+    assist.set_telemetry(&sensed([Some(2.0), None, None, None]));
+    assist.set_telemetry(&sensed([Some(1.2), None, None, None]));
+    assist.set_telemetry(&sensed([Some(1.0), Some(0.8), None, None]));
+    assist.set_telemetry(&sensed([Some(1.0), Some(0.7), None, None]));
+    assist.set_telemetry(&sensed([None, None, Some(0.3), None]));
+    assist.set_telemetry(&Telemetry::default());
+    use Beep::*;
+    assert_eq!(*beeps.borrow(), [Slow, Fast, Continuous, Stop]);
+  }
+
+  #[test]
+  fn segments_fade_out_in_their_last_range() {
+    let mut assist = ParkingAssist::new();
+    assist.set_telemetry(&sensed([None, Some(0.5), None, None]));
+    assist.update(Duration::from_secs(1));
+    assert!(assist.fade[1].0 > 0.99);
+    assert_eq!(assist.fade[1].1, Proximity::Near);
+    assist.set_telemetry(&Telemetry::default());
+    assert!(assist.animating());
+    assist.update(Duration::from_millis(50));
+    assert!(assist.fade[1].0 > 0.0 && assist.fade[1].0 < 1.0);
+    assert_eq!(assist.fade[1].1, Proximity::Near);
+  }
+}
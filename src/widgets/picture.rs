@@ -0,0 +1,118 @@
+use crate::canvas::Style;
+use crate::frame::Frame;
+use crate::image::{ColorMatrix, Fit, Image};
+use crate::theme::Palette;
+use crate::widgets::{Rect, Widget};
+
+/// Picture in picture: an [`Image`], like the frames of a rear camera or a
+/// media preview, in a frame of its own with rounded corners and a border.
+///
+/// The background shows where the image doesn't cover the frame, and
+/// until it has pixels:
+///
+/// ```no_run
+/// use windshield_rs::image::{Fit, Image};
+/// use windshield_rs::widgets::{Picture, Rect};
+///
+/// let camera = Image::new();
+/// let frames = camera.clone();
+/// std::thread::spawn(move || loop {
+///   // Decoded from the camera
+///   frames.set(640, 480, vec![128; 640 * 480 * 4]);
+/// });
+/// let mut picture = Picture::new(camera).with_fit(Fit::Cover).with_radius(16.0);
+/// let app = windshield_rs::WindshieldApp::builder()
+///   .on_draw(move |frame| frame.widget(&mut picture, Rect::new(20.0, 20.0, 320.0, 240.0)))
+///   .build();
+/// ```
+#[derive(Clone, Debug)]
+pub struct Picture {
+  pub image: Image,
+  pub fit: Fit,
+  /// Of the frame's corners in physical pixels.
+  pub radius: f32,
+  /// Width of the border in physical pixels, zero for none.
+  pub border: f32,
+  /// Linear RGBA in straight alpha, none to leave the bars see-through.
+  pub background: Option<[f32; 4]>,
+  pub border_color: [f32; 4],
+  /// What the image's colors are changed by, like
+  /// [`ColorMatrix::dim`] to keep a bright camera from glaring at night.
+  pub matrix: ColorMatrix,
+}
+
+impl Picture {
+  pub fn new(image: Image) -> Self {
+    Self {
+      image,
+      fit: Fit::default(),
+      radius: 0.0,
+      border: 0.0,
+      background: None,
+      border_color: [1.0, 1.0, 1.0, 0.9],
+      matrix: ColorMatrix::IDENTITY,
+    }
+  }
+
+  pub fn with_fit(mut self, fit: Fit) -> Self {
+    self.fit = fit;
+    self
+  }
+
+  pub fn with_radius(mut self, radius: f32) -> Self {
+    self.radius = radius;
+    self
+  }
+
+  pub fn with_border(mut self, border: f32) -> Self {
+    self.border = border;
+    self
+  }
+
+  pub fn with_matrix(mut self, matrix: ColorMatrix) -> Self {
+    self.matrix = matrix;
+    self
+  }
+}
+
+impl Widget for Picture {
+  fn set_palette(&mut self, palette: &Palette) {
+    self.background = Some(palette.surface);
+    self.border_color = palette.muted;
+  }
+
+  fn draw(&self, frame: &mut Frame, rect: Rect) {
+    let Rect {
+      x,
+      y,
+      width,
+      height,
+    } = rect;
+    if let Some(background) = self.background {
+      frame.canvas.rounded_rect(
+        [x, y],
+        [width, height],
+        self.radius,
+        Style::fill(background),
+      );
+    }
+    // Inside the border, images go above the shapes and would cover it
+    let border = self.border.clamp(0.0, rect.min_side() / 2.0);
+    let inner = Rect::new(
+      x + border,
+      y + border,
+      width - 2.0 * border,
+      height - 2.0 * border,
+    );
+    let radius = (self.radius - border).max(0.0);
+    frame.image(&self.image, inner, self.fit, radius, self.matrix);
+    if border > 0.0 {
+      frame.canvas.rounded_rect(
+        [x + border / 2.0, y + border / 2.0],
+        [width - border, height - border],
+        self.radius - border / 2.0,
+        Style::stroke(self.border_color, border),
+      );
+    }
+  }
+}
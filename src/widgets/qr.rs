@@ -0,0 +1,205 @@
+use qrcodegen::QrCodeEcc;
+use serde::Deserialize;
+
+use crate::canvas::Style;
+use crate::frame::Frame;
+use crate::widgets::{Rect, Widget};
+
+/// Modules of light margin around the code scanners need to find it.
+const QUIET_ZONE: u32 = 4;
+
+/// How much of a [`QrCode`] can be damaged or covered and still scan, at
+/// the cost of more modules.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Correction {
+  /// About 7%.
+  Low,
+  /// About 15%.
+  #[default]
+  Medium,
+  /// About 25%.
+  Quartile,
+  /// About 30%.
+  High,
+}
+
+impl Correction {
+  fn ecc(self) -> QrCodeEcc {
+    match self {
+      Self::Low => QrCodeEcc::Low,
+      Self::Medium => QrCodeEcc::Medium,
+      Self::Quartile => QrCodeEcc::Quartile,
+      Self::High => QrCodeEcc::High,
+    }
+  }
+}
+
+/// A QR code of some text, like a link to the companion app or the
+/// network to pair with, see [`wifi`](Self::wifi).
+///
+/// Modules are a whole number of pixels wide so they stay sharp, as large
+/// as the rect fits with the quiet zone around them, and centered. The
+/// code is always dark on light whatever the theme, which is what scanners
+/// read best.
+#[derive(Clone, Debug)]
+pub struct QrCode {
+  text: String,
+  correction: Correction,
+  /// Dark modules by row, `size` by `size`.
+  modules: Vec<bool>,
+  size: u32,
+  /// Linear RGBA in straight alpha.
+  pub dark: [f32; 4],
+  pub light: [f32; 4],
+}
+
+impl QrCode {
+  pub fn new(text: impl Into<String>) -> Self {
+    let mut code = Self {
+      text: text.into(),
+      correction: Correction::default(),
+      modules: Vec::new(),
+      size: 0,
+      dark: [0.0, 0.0, 0.0, 1.0],
+      light: [1.0, 1.0, 1.0, 1.0],
+    };
+    code.encode();
+    code
+  }
+
+  /// Joins the Wi-Fi network `ssid` with a WPA `password` when scanned,
+  /// or an open one without.
+  pub fn wifi(ssid: &str, password: Option<&str>) -> Self {
+    Self::new(wifi_text(ssid, password))
+  }
+
+  pub fn with_correction(mut self, correction: Correction) -> Self {
+    self.correction = correction;
+    self.encode();
+    self
+  }
+
+  pub fn text(&self) -> &str {
+    &self.text
+  }
+
+  /// Encodes `text` if it changed. Text too long for any QR code draws
+  /// nothing.
+  pub fn set_text(&mut self, text: impl Into<String>) {
+    let text = text.into();
+    if text != self.text {
+      self.text = text;
+      self.encode();
+    }
+  }
+
+  /// Modules along a side without the quiet zone, zero if the text
+  /// didn't fit.
+  pub fn size(&self) -> u32 {
+    self.size
+  }
+
+  fn encode(&mut self) {
+    match qrcodegen::QrCode::encode_text(&self.text, self.correction.ecc()) {
+      Ok(code) => {
+        let size = code.size();
+        self.size = size as u32;
+        self.modules = (0..size * size)
+          .map(|index| code.get_module(index % size, index / size))
+          .collect();
+      }
+      Err(err) => {
+        tracing::warn!("unable to encode {:?} as a QR code: {}", self.text, err);
+        self.size = 0;
+        self.modules.clear();
+      }
+    }
+  }
+}
+
+/// The text phones join a Wi-Fi network from, with the characters that
+/// have a meaning in it escaped.
+fn wifi_text(ssid: &str, password: Option<&str>) -> String {
+  let escape = |text: &str| {
+    text.chars().fold(String::new(), |mut escaped, c| {
+      if matches!(c, '\\' | ';' | ',' | ':' | '"') {
+        escaped.push('\\');
+      }
+      escaped.push(c);
+      escaped
+    })
+  };
+  match password {
+    Some(password) => format!("WIFI:T:WPA;S:{};P:{};;", escape(ssid), escape(password)),
+    None => format!("WIFI:T:nopass;S:{};;", escape(ssid)),
+  }
+}
+
+impl Widget for QrCode {
+  fn draw(&self, frame: &mut Frame, rect: Rect) {
+    if self.size == 0 {
+      return;
+    }
+    let modules = self.size + 2 * QUIET_ZONE;
+    let module = (rect.min_side() / modules as f32).floor().max(1.0);
+    let side = module * modules as f32;
+    let [cx, cy] = rect.center();
+    let (x, y) = ((cx - side / 2.0).round(), (cy - side / 2.0).round());
+    frame
+      .canvas
+      .rect([x, y], [side, side], Style::fill(self.light));
+
+    let origin = [
+      x + module * QUIET_ZONE as f32,
+      y + module * QUIET_ZONE as f32,
+    ];
+    let size = self.size as usize;
+    for (row, modules) in self.modules.chunks(size).enumerate() {
+      // A rect for every run of dark modules
+      let mut column = 0;
+      while column < size {
+        if !modules[column] {
+          column += 1;
+          continue;
+        }
+        let start = column;
+        while column < size && modules[column] {
+          column += 1;
+        }
+        frame.canvas.rect(
+          [
+            origin[0] + module * start as f32,
+            origin[1] + module * row as f32,
+          ],
+          [module * (column - start) as f32, module],
+          Style::fill(self.dark),
+        );
+      }
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn escapes_wifi_credentials() {
+    assert_eq!(
+      wifi_text("Car;5G", Some("pa:ss\\word")),
+      "WIFI:T:WPA;S:Car\\;5G;P:pa\\:ss\\\\word;;"
+    );
+    assert_eq!(wifi_text("Guest", None), "WIFI:T:nopass;S:Guest;;");
+  }
+
+  #[test]
+  fn encodes_the_smallest_code_that_fits() {
+    let code = QrCode::new("HELLO WORLD");
+    assert_eq!(code.size(), 21);
+    assert_eq!(code.modules.len(), 21 * 21);
+    // The finder pattern's corner
+    assert!(code.modules[0]);
+    assert_eq!(QrCode::new("x".repeat(8000)).size(), 0);
+  }
+}
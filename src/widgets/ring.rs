@@ -0,0 +1,281 @@
+use std::f32::consts::{FRAC_PI_2, PI, SQRT_2};
+use std::time::Duration;
+
+use crate::anim::{self, SETTLED};
+use crate::canvas::{Canvas, Style};
+use crate::data::Telemetry;
+use crate::frame::Frame;
+use crate::theme::Palette;
+use crate::widgets::{Rect, Widget};
+
+/// Largest angle drawn as one piece, small enough for gradients and the
+/// arc's curvature to look smooth.
+const MAX_STEP: f32 = 0.05;
+
+/// Shape of the ends of a [`ProgressRing`]'s arc.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Cap {
+  /// Cut off square at the exact angle.
+  Butt,
+  /// Rounded, extending half the thickness past the angle.
+  #[default]
+  Round,
+}
+
+/// A ring filling up along an arc, for charge levels, timers and loading
+/// indicators. Unlike a [`RadialGauge`](crate::widgets::RadialGauge) it has
+/// no scale, but it can hold another widget in its center.
+pub struct ProgressRing {
+  /// Angle the ring starts filling at in radians, clockwise from the
+  /// positive x axis. Defaults to the top.
+  pub start_angle: f32,
+  /// Angle of the full ring, clockwise.
+  pub sweep: f32,
+  /// Thickness of the ring as a fraction of its radius.
+  pub thickness: f32,
+  pub cap: Cap,
+  /// Linear RGBA in straight alpha, blended from the start to the end of
+  /// the full sweep. A single color fills the ring evenly.
+  pub gradient: Vec<[f32; 4]>,
+  /// Color of the unfilled part of the ring, not drawn if unset.
+  pub track: Option<[f32; 4]>,
+  /// How quickly the ring catches up with the value, per second. Zero
+  /// moves it immediately.
+  pub response: f32,
+  /// Drawn inside the ring, e.g. a [`Label`](crate::widgets::Label) with
+  /// the percentage.
+  pub content: Option<Box<dyn Widget>>,
+  value: f32,
+  shown: f32,
+}
+
+impl Default for ProgressRing {
+  fn default() -> Self {
+    Self::new()
+  }
+}
+
+impl ProgressRing {
+  /// A full circle starting at the top, filled in a single color.
+  pub fn new() -> Self {
+    Self {
+      start_angle: -0.5 * PI,
+      sweep: 2.0 * PI,
+      thickness: 0.15,
+      cap: Cap::Round,
+      gradient: vec![[0.2, 0.8, 0.3, 1.0]],
+      track: Some([1.0, 1.0, 1.0, 0.15]),
+      response: 8.0,
+      content: None,
+      value: 0.0,
+      shown: 0.0,
+    }
+  }
+
+  pub fn with_sweep(mut self, start_angle: f32, sweep: f32) -> Self {
+    self.start_angle = start_angle;
+    self.sweep = sweep;
+    self
+  }
+
+  pub fn with_thickness(mut self, thickness: f32) -> Self {
+    self.thickness = thickness;
+    self
+  }
+
+  pub fn with_cap(mut self, cap: Cap) -> Self {
+    self.cap = cap;
+    self
+  }
+
+  pub fn with_color(mut self, color: [f32; 4]) -> Self {
+    self.gradient = vec![color];
+    self
+  }
+
+  /// Colors spread evenly along the full sweep.
+  pub fn with_gradient(mut self, colors: impl IntoIterator<Item = [f32; 4]>) -> Self {
+    self.gradient = colors.into_iter().collect();
+    self
+  }
+
+  pub fn with_track(mut self, track: Option<[f32; 4]>) -> Self {
+    self.track = track;
+    self
+  }
+
+  pub fn with_response(mut self, response: f32) -> Self {
+    self.response = response;
+    self
+  }
+
+  pub fn with_content(mut self, content: impl Widget + 'static) -> Self {
+    self.content = Some(Box::new(content));
+    self
+  }
+
+  /// Sets the filled fraction, clamped to `0.0..=1.0`.
+  pub fn set_value(&mut self, value: f32) {
+    self.value = value.clamp(0.0, 1.0);
+  }
+
+  pub fn value(&self) -> f32 {
+    self.value
+  }
+
+  pub fn content_mut(&mut self) -> Option<&mut dyn Widget> {
+    self.content.as_deref_mut()
+  }
+
+  /// Color of the gradient at a fraction of the full sweep.
+  fn color_at(&self, t: f32) -> [f32; 4] {
+    match self.gradient.len() {
+      0 => [0.0; 4],
+      1 => self.gradient[0],
+      len => {
+        let position = t.clamp(0.0, 1.0) * (len - 1) as f32;
+        let index = (position as usize).min(len - 2);
+        let (from, to) = (self.gradient[index], self.gradient[index + 1]);
+        let f = position - index as f32;
+        [0, 1, 2, 3].map(|i| from[i] + (to[i] - from[i]) * f)
+      }
+    }
+  }
+}
+
+impl Widget for ProgressRing {
+  fn set_palette(&mut self, palette: &Palette) {
+    // Gradients are picked for what the ring shows, a single color isn't
+    if self.gradient.len() == 1 {
+      self.gradient = vec![palette.accent];
+    }
+    if self.track.is_some() {
+      self.track = Some(palette.muted);
+    }
+    if let Some(content) = &mut self.content {
+      content.set_palette(palette);
+    }
+  }
+
+  fn set_telemetry(&mut self, telemetry: &Telemetry) {
+    if let Some(content) = &mut self.content {
+      content.set_telemetry(telemetry);
+    }
+  }
+
+  fn update(&mut self, delta: Duration) {
+    self.shown = anim::follow(self.shown, self.value, self.response, delta);
+    if let Some(content) = &mut self.content {
+      content.update(delta);
+    }
+  }
+
+  fn animating(&self) -> bool {
+    (self.value - self.shown).abs() > SETTLED
+      || self
+        .content
+        .as_ref()
+        .is_some_and(|content| content.animating())
+  }
+
+  fn set_value(&mut self, value: f32) {
+    self.set_value(value);
+  }
+
+  fn set_stale(&mut self, stale: bool) {
+    if let Some(content) = &mut self.content {
+      content.set_stale(stale);
+    }
+  }
+
+  fn draw(&self, frame: &mut Frame, rect: Rect) {
+    let outer = rect.min_side() / 2.0;
+    let ring = Annulus {
+      center: rect.center(),
+      inner: outer * (1.0 - self.thickness),
+      outer,
+    };
+    let angle = |t: f32| self.start_angle + self.sweep * t;
+    let round = self.cap == Cap::Round;
+
+    if let Some(track) = self.track {
+      ring.band(frame.canvas, angle(0.0), angle(1.0), |_| track);
+      if round && self.sweep.abs() < 2.0 * PI {
+        ring.cap(frame.canvas, angle(0.0), -self.sweep, track);
+        ring.cap(frame.canvas, angle(1.0), self.sweep, track);
+      }
+    }
+
+    if self.shown > 0.0 {
+      ring.band(frame.canvas, angle(0.0), angle(self.shown), |a| {
+        self.color_at((a - self.start_angle) / self.sweep)
+      });
+      if round {
+        ring.cap(frame.canvas, angle(0.0), -self.sweep, self.color_at(0.0));
+        ring.cap(
+          frame.canvas,
+          angle(self.shown),
+          self.sweep,
+          self.color_at(self.shown),
+        );
+      }
+    }
+
+    if let Some(content) = &self.content {
+      // The largest square inside the ring
+      let side = ring.inner * SQRT_2;
+      let [x, y] = ring.center;
+      content.draw(frame, Rect::new(x - side / 2.0, y - side / 2.0, side, side));
+    }
+  }
+}
+
+struct Annulus {
+  center: [f32; 2],
+  inner: f32,
+  outer: f32,
+}
+
+impl Annulus {
+  fn at(&self, angle: f32, radius: f32) -> [f32; 2] {
+    [
+      self.center[0] + radius * angle.cos(),
+      self.center[1] + radius * angle.sin(),
+    ]
+  }
+
+  /// Fills the part between `from` and `to`, colored piece by piece by the
+  /// angle in the middle of each.
+  fn band(&self, canvas: &mut Canvas, from: f32, to: f32, color: impl Fn(f32) -> [f32; 4]) {
+    let steps = ((to - from).abs() / MAX_STEP).ceil().max(1.0) as usize;
+    let step = (to - from) / steps as f32;
+    for i in 0..steps {
+      let a = from + step * i as f32;
+      let b = a + step;
+      canvas.polygon(
+        &[
+          self.at(a, self.inner),
+          self.at(a, self.outer),
+          self.at(b, self.outer),
+          self.at(b, self.inner),
+        ],
+        Style::fill(color(a + step / 2.0)),
+      );
+    }
+  }
+
+  /// Rounds off a band ending at `angle` with a half disc, `outwards` being
+  /// the direction along the ring that points away from the band.
+  fn cap(&self, canvas: &mut Canvas, angle: f32, outwards: f32, color: [f32; 4]) {
+    let width = self.outer - self.inner;
+    let position = self.at(angle, self.inner + width / 2.0);
+    let facing = angle + outwards.signum() * FRAC_PI_2;
+    canvas.arc(
+      position,
+      width / 2.0,
+      facing - FRAC_PI_2,
+      PI,
+      Style::fill(color),
+    );
+  }
+}
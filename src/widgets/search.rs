@@ -0,0 +1,725 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::accessibility::{Accessible, Role};
+use crate::anim::{self, SETTLED};
+use crate::canvas::{Canvas, Style};
+use crate::data::Telemetry;
+use crate::frame::Frame;
+use crate::input::{Action, PointerEvent, PointerId, PointerPhase};
+use crate::map::geocode::{Place, SearchResults, Searcher};
+use crate::map::route::Navigator;
+use crate::map::Coordinate;
+use crate::text::{Align, TextRenderer, TextSection, VAlign};
+use crate::theme::Palette;
+use crate::widgets::{Rect, Widget, MIN_TARGET};
+
+/// How long typing has to pause before searching for what was typed.
+const DEBOUNCE: Duration = Duration::from_millis(300);
+/// Distance in physical pixels a press has to move before it scrolls the
+/// results rather than choosing one.
+const DRAG_SLOP: f32 = 8.0;
+/// How quickly the results scroll to the closest whole row, per second.
+const SNAP_RESPONSE: f32 = 14.0;
+const LETTERS: [&str; 3] = ["qwertyuiop", "asdfghjkl", "zxcvbnm"];
+
+/// Linear RGBA colors in straight alpha.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SearchColors {
+  pub background: [f32; 4],
+  /// The text field and the keys.
+  pub key: [f32; 4],
+  /// Keys while pressed.
+  pub pressed: [f32; 4],
+  pub text: [f32; 4],
+  /// The placeholder and the details of places.
+  pub muted: [f32; 4],
+  /// Behind the highlighted place.
+  pub highlight: [f32; 4],
+}
+
+impl Default for SearchColors {
+  fn default() -> Self {
+    Self {
+      background: [0.0, 0.0, 0.0, 0.5],
+      key: [1.0, 1.0, 1.0, 0.12],
+      pressed: [1.0, 1.0, 1.0, 0.35],
+      text: [1.0, 1.0, 1.0, 1.0],
+      muted: [1.0, 1.0, 1.0, 0.6],
+      highlight: [0.2, 0.6, 1.0, 0.6],
+    }
+  }
+}
+
+/// A key of the on-screen keyboard.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Key {
+  Letter(char),
+  Space,
+  Backspace,
+}
+
+/// What a press landed on.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Target {
+  Key(Key),
+  /// The button emptying the text field.
+  Clear,
+  /// A place in the results, or the space below them.
+  Row(Option<usize>),
+}
+
+/// Where everything goes in the widget's rect.
+struct Areas {
+  field: Rect,
+  clear: Rect,
+  list: Rect,
+  keyboard: Rect,
+  row_height: f32,
+}
+
+/// A search for a destination: a text field typed into with an on-screen
+/// keyboard and the places found below it, scrolled by dragging.
+///
+/// Queries go to the [`Searcher`] it's [connected](Self::connect) to once
+/// typing pauses, the places found come back as [`Telemetry::search`].
+/// Choosing one, by tapping it or with [`Action::Activate`], navigates
+/// there from [`Telemetry::latitude`] and [`Telemetry::longitude`] with
+/// the [`Navigator`]. Only the rows in view are laid out, however many
+/// places were found.
+pub struct SearchScreen {
+  pub placeholder: String,
+  pub colors: SearchColors,
+  searcher: Option<Searcher>,
+  navigator: Option<Navigator>,
+  query: String,
+  // The query last searched for and when the query last changed
+  asked: String,
+  edited: Duration,
+  results: Option<Arc<SearchResults>>,
+  position: Option<Coordinate>,
+  highlighted: Option<usize>,
+  chosen: Option<usize>,
+  // Pixels the results are scrolled by and settle at
+  scroll: f32,
+  settle: f32,
+  dragging: bool,
+  pressed: Option<(PointerId, Target, [f32; 2], f32)>,
+  time: Duration,
+}
+
+impl Default for SearchScreen {
+  fn default() -> Self {
+    Self::new()
+  }
+}
+
+impl SearchScreen {
+  /// Not searching until [connected](Self::connect).
+  pub fn new() -> Self {
+    Self {
+      placeholder: "Search".to_string(),
+      colors: SearchColors::default(),
+      searcher: None,
+      navigator: None,
+      query: String::new(),
+      asked: String::new(),
+      edited: Duration::ZERO,
+      results: None,
+      position: None,
+      highlighted: None,
+      chosen: None,
+      scroll: 0.0,
+      settle: 0.0,
+      dragging: false,
+      pressed: None,
+      time: Duration::ZERO,
+    }
+  }
+
+  pub fn with_placeholder(mut self, placeholder: impl Into<String>) -> Self {
+    self.placeholder = placeholder.into();
+    self
+  }
+
+  pub fn with_colors(mut self, colors: SearchColors) -> Self {
+    self.colors = colors;
+    self
+  }
+
+  /// Searches with `searcher` and navigates to the places chosen with
+  /// `navigator`.
+  pub fn connect(&mut self, searcher: Searcher, navigator: Navigator) {
+    self.searcher = Some(searcher);
+    self.navigator = Some(navigator);
+  }
+
+  pub fn query(&self) -> &str {
+    &self.query
+  }
+
+  /// Replaces what was typed, searching for it once typing pauses.
+  pub fn set_query(&mut self, query: impl Into<String>) {
+    self.query = query.into();
+    self.edited = self.time;
+    if self.query.trim().is_empty() {
+      self.results = None;
+    }
+  }
+
+  fn places(&self) -> &[Place] {
+    self
+      .results
+      .as_deref()
+      .map_or(&[], |results| &results.places)
+  }
+
+  /// Navigates to the place at `index` of the results.
+  fn choose(&mut self, index: usize) {
+    let Some(place) = self.places().get(index) else {
+      return;
+    };
+    match (&self.navigator, self.position) {
+      (Some(navigator), Some(from)) => {
+        tracing::info!("navigating to {}", place.name);
+        navigator.navigate(from, place.position);
+      }
+      (Some(_), None) => tracing::warn!("no position to navigate to {} from", place.name),
+      (None, _) => tracing::warn!("no router to navigate to {} with", place.name),
+    }
+    self.chosen = Some(index);
+    self.highlighted = Some(index);
+  }
+
+  fn press(&mut self, key: Key) {
+    let mut query = std::mem::take(&mut self.query);
+    match key {
+      Key::Letter(letter) => query.push(letter),
+      Key::Space if !query.is_empty() && !query.ends_with(' ') => query.push(' '),
+      Key::Space => {}
+      Key::Backspace => {
+        query.pop();
+      }
+    }
+    self.set_query(query);
+  }
+
+  fn areas(rect: Rect) -> Areas {
+    let row_height = (rect.height * 0.11).max(MIN_TARGET);
+    let padding = row_height * 0.15;
+    let field = Rect::new(
+      rect.x + padding,
+      rect.y + padding,
+      rect.width - padding * 2.0,
+      row_height - padding,
+    );
+    let keyboard_height = rect.height * 0.42;
+    let keyboard = Rect::new(
+      rect.x + padding,
+      rect.y + rect.height - keyboard_height,
+      rect.width - padding * 2.0,
+      keyboard_height - padding,
+    );
+    let top = field.y + field.height + padding;
+    Areas {
+      clear: Rect::new(
+        field.x + field.width - field.height,
+        field.y,
+        field.height,
+        field.height,
+      ),
+      list: Rect::new(rect.x, top, rect.width, keyboard.y - padding - top),
+      field,
+      keyboard,
+      row_height,
+    }
+  }
+
+  /// The keys of the keyboard in `rect`, letters in three staggered rows
+  /// and a row with the space bar below.
+  fn keys(rect: Rect) -> Vec<(Key, Rect)> {
+    let width = rect.width / 10.0;
+    let height = rect.height / 4.0;
+    let gap = width.min(height) * 0.08;
+    let key = |column: f32, row: f32, span: f32| {
+      Rect::new(
+        rect.x + column * width + gap,
+        rect.y + row * height + gap,
+        span * width - gap * 2.0,
+        height - gap * 2.0,
+      )
+    };
+    let mut keys = Vec::new();
+    for (row, letters) in LETTERS.iter().enumerate() {
+      let indent = row as f32 * 0.5;
+      for (column, letter) in letters.chars().enumerate() {
+        keys.push((
+          Key::Letter(letter),
+          key(indent + column as f32, row as f32, 1.0),
+        ));
+      }
+    }
+    keys.push((Key::Backspace, key(8.0, 2.0, 2.0)));
+    keys.push((Key::Space, key(2.0, 3.0, 6.0)));
+    keys
+  }
+
+  fn max_scroll(&self, areas: &Areas) -> f32 {
+    let rows = self.places().len() as f32;
+    (rows * areas.row_height - areas.list.height).max(0.0)
+  }
+
+  fn target(&self, rect: Rect, position: [f32; 2]) -> Option<Target> {
+    let areas = Self::areas(rect);
+    if areas.clear.contains(position) && !self.query.is_empty() {
+      return Some(Target::Clear);
+    }
+    if areas.list.contains(position) {
+      let row = ((position[1] - areas.list.y + self.scroll) / areas.row_height) as usize;
+      return Some(Target::Row((row < self.places().len()).then_some(row)));
+    }
+    Self::keys(areas.keyboard)
+      .into_iter()
+      .find(|(_, rect)| rect.contains(position))
+      .map(|(key, _)| Target::Key(key))
+  }
+
+  /// Scrolls just far enough for the highlighted place to be in view.
+  fn reveal(&mut self, rect: Rect) {
+    let Some(index) = self.highlighted else {
+      return;
+    };
+    let areas = Self::areas(rect);
+    let top = index as f32 * areas.row_height;
+    let bottom = top + areas.row_height;
+    if top < self.settle {
+      self.settle = top;
+    } else if bottom > self.settle + areas.list.height {
+      // Whole rows down to the place
+      let rows = (areas.list.height / areas.row_height).floor().max(1.0);
+      self.settle = (index as f32 + 1.0 - rows) * areas.row_height;
+    }
+  }
+
+  fn draw_key(&self, canvas: &mut Canvas, text: &mut TextRenderer, key: Key, rect: Rect) {
+    let pressed = matches!(self.pressed, Some((_, Target::Key(down), _, _)) if down == key);
+    let color = if pressed {
+      self.colors.pressed
+    } else {
+      self.colors.key
+    };
+    let radius = rect.min_side() * 0.15;
+    canvas.rounded_rect(
+      [rect.x, rect.y],
+      [rect.width, rect.height],
+      radius,
+      Style::fill(color),
+    );
+    let [x, y] = rect.center();
+    let size = rect.height * 0.45;
+    match key {
+      Key::Letter(letter) => text.queue(
+        &TextSection::new(letter.to_uppercase().to_string())
+          .at(x, y)
+          .with_size(size)
+          .with_color(self.colors.text)
+          .with_align(Align::Center, VAlign::Center),
+      ),
+      Key::Space => {}
+      // An arrow pointing left into a box, like on phone keyboards
+      Key::Backspace => {
+        let [w, h] = [size * 0.8, size * 0.5];
+        canvas.polygon(
+          &[
+            [x - w, y],
+            [x - w * 0.4, y - h],
+            [x + w, y - h],
+            [x + w, y + h],
+            [x - w * 0.4, y + h],
+          ],
+          Style::stroke(self.colors.text, size * 0.1),
+        );
+      }
+    }
+  }
+}
+
+impl Widget for SearchScreen {
+  fn focusable(&self) -> bool {
+    true
+  }
+
+  fn accessible(&self) -> Option<Accessible> {
+    let places = self
+      .places()
+      .iter()
+      .enumerate()
+      .map(|(index, place)| {
+        Accessible::new(Role::MenuItem, place.name.as_str())
+          .with_value(place.detail.as_str())
+          .with_selected(self.highlighted == Some(index))
+      })
+      .collect();
+    let search = Accessible::new(Role::SearchBox, self.placeholder.as_str())
+      .with_value(self.query.as_str())
+      .with_children(places);
+    Some(search)
+  }
+
+  fn action(&mut self, action: Action) -> bool {
+    let len = self.places().len();
+    match action {
+      Action::Next if len > 0 => {
+        self.highlighted = Some(self.highlighted.map_or(0, |index| (index + 1) % len));
+      }
+      Action::Previous if len > 0 => {
+        self.highlighted = Some(
+          self
+            .highlighted
+            .map_or(len - 1, |index| (index + len - 1) % len),
+        );
+      }
+      Action::Activate => match self.highlighted {
+        Some(index) => self.choose(index),
+        None => return false,
+      },
+      // Going back empties the field first
+      Action::Back if !self.query.is_empty() => self.set_query(""),
+      _ => return false,
+    }
+    true
+  }
+
+  fn set_palette(&mut self, palette: &Palette) {
+    let [r, g, b, a] = palette.muted;
+    let [ar, ag, ab, _] = palette.accent;
+    self.colors = SearchColors {
+      background: palette.surface,
+      key: [r, g, b, a * 0.25],
+      pressed: [r, g, b, a * 0.7],
+      text: palette.text,
+      muted: palette.muted,
+      highlight: [ar, ag, ab, 0.6],
+    };
+  }
+
+  fn set_telemetry(&mut self, telemetry: &Telemetry) {
+    self.position = match (telemetry.latitude, telemetry.longitude) {
+      (Some(latitude), Some(longitude)) => Some(Coordinate::new(latitude as f64, longitude as f64)),
+      _ => None,
+    };
+    let Some(results) = &telemetry.search else {
+      return;
+    };
+    let new = self
+      .results
+      .as_ref()
+      .is_none_or(|shown| !Arc::ptr_eq(shown, results));
+    // Results for what was typed over are moot
+    if new && results.query == self.query {
+      self.results = Some(results.clone());
+      self.highlighted = None;
+      self.chosen = None;
+      self.scroll = 0.0;
+      self.settle = 0.0;
+    }
+  }
+
+  fn update(&mut self, delta: Duration) {
+    self.time += delta;
+    let paused = self.time.saturating_sub(self.edited) >= DEBOUNCE;
+    if paused && self.asked != self.query {
+      self.asked.clone_from(&self.query);
+      if let Some(searcher) = &self.searcher {
+        searcher.search(self.query.clone(), self.position);
+      }
+    }
+    if !self.dragging {
+      self.scroll = anim::follow(self.scroll, self.settle, SNAP_RESPONSE, delta);
+      if (self.scroll - self.settle).abs() < SETTLED {
+        self.scroll = self.settle;
+      }
+    }
+  }
+
+  fn animating(&self) -> bool {
+    // Until the query is searched for and the results settle
+    self.asked != self.query || (!self.dragging && self.scroll != self.settle)
+  }
+
+  fn draw(&self, frame: &mut Frame, rect: Rect) {
+    let colors = &self.colors;
+    let areas = Self::areas(rect);
+    let canvas = &mut *frame.canvas;
+    canvas.rect(
+      [rect.x, rect.y],
+      [rect.width, rect.height],
+      Style::fill(colors.background),
+    );
+
+    // The text field, showing its end if what was typed is too long
+    let field = areas.field;
+    let radius = field.height * 0.2;
+    canvas.rounded_rect(
+      [field.x, field.y],
+      [field.width, field.height],
+      radius,
+      Style::fill(colors.key),
+    );
+    let size = field.height * 0.5;
+    let left = field.x + field.height * 0.3;
+    let right = areas.clear.x;
+    let [_, y] = field.center();
+    if self.query.is_empty() {
+      frame.text.queue(
+        &TextSection::new(self.placeholder.as_str())
+          .at(left, y)
+          .with_size(size)
+          .with_color(colors.muted)
+          .with_align(Align::Left, VAlign::Center),
+      );
+    } else {
+      let section = TextSection::new(self.query.as_str())
+        .with_size(size)
+        .with_color(colors.text);
+      let width = frame.text.line_width(&section);
+      let x = (right - width - size * 0.2).min(left);
+      frame.text.queue(
+        &section
+          .at(x, y)
+          .with_align(Align::Left, VAlign::Center)
+          .with_clip(left, right, size),
+      );
+      // The caret blinks at the end, unless motion is reduced
+      if frame.reduce_motion || self.time.as_secs_f32().fract() < 0.6 {
+        let caret = x + width + size * 0.08;
+        canvas.line(
+          [caret, y - size * 0.5],
+          [caret, y + size * 0.5],
+          colors.text,
+          size * 0.06,
+        );
+      }
+      let [cx, cy] = areas.clear.center();
+      let cross = areas.clear.height * 0.18;
+      for dx in [-cross, cross] {
+        canvas.line(
+          [cx - dx, cy - cross],
+          [cx + dx, cy + cross],
+          colors.muted,
+          size * 0.1,
+        );
+      }
+    }
+
+    // Only the rows wholly in view, nothing can be drawn cut off
+    let list = areas.list;
+    let row_height = areas.row_height;
+    let first = (self.scroll / row_height).ceil().max(0.0) as usize;
+    let padding = row_height * 0.3;
+    for (index, place) in self.places().iter().enumerate().skip(first) {
+      let top = list.y + index as f32 * row_height - self.scroll;
+      if top + row_height > list.y + list.height + 0.5 {
+        break;
+      }
+      if self.highlighted == Some(index) || self.chosen == Some(index) {
+        canvas.rect(
+          [list.x, top],
+          [list.width, row_height],
+          Style::fill(colors.highlight),
+        );
+      }
+      let clip = list.x + list.width - padding;
+      let (name_y, size) = match place.detail.is_empty() {
+        true => (top + row_height / 2.0, row_height * 0.4),
+        false => (top + row_height * 0.36, row_height * 0.36),
+      };
+      frame.text.queue(
+        &TextSection::new(place.name.as_str())
+          .at(list.x + padding, name_y)
+          .with_size(size)
+          .with_color(colors.text)
+          .with_align(Align::Left, VAlign::Center)
+          .with_clip(list.x, clip, padding),
+      );
+      if !place.detail.is_empty() {
+        frame.text.queue(
+          &TextSection::new(place.detail.as_str())
+            .at(list.x + padding, top + row_height * 0.72)
+            .with_size(row_height * 0.24)
+            .with_color(colors.muted)
+            .with_align(Align::Left, VAlign::Center)
+            .with_clip(list.x, clip, padding),
+        );
+      }
+    }
+    if self.places().is_empty() && !self.query.trim().is_empty() {
+      let message = match &self.results {
+        Some(results) if results.query == self.query => "Nothing found",
+        _ => "Searching…",
+      };
+      let [x, _] = list.center();
+      frame.text.queue(
+        &TextSection::new(message)
+          .at(x, list.y + row_height / 2.0)
+          .with_size(row_height * 0.35)
+          .with_color(colors.muted)
+          .with_align(Align::Center, VAlign::Center),
+      );
+    }
+
+    for (key, rect) in Self::keys(areas.keyboard) {
+      self.draw_key(frame.canvas, frame.text, key, rect);
+    }
+  }
+
+  fn pointer(&mut self, event: &PointerEvent, rect: Rect) -> bool {
+    match event.phase {
+      PointerPhase::Down if self.pressed.is_none() => {
+        let target = self.target(rect, event.position);
+        self.pressed = target.map(|target| (event.id, target, event.position, self.scroll));
+        true
+      }
+      PointerPhase::Move => {
+        let Some((_, target, start, scroll)) = self.pressed.filter(|(id, ..)| *id == event.id)
+        else {
+          return false;
+        };
+        let dy = event.position[1] - start[1];
+        if matches!(target, Target::Row(_)) && (self.dragging || dy.abs() > DRAG_SLOP) {
+          self.dragging = true;
+          let areas = Self::areas(rect);
+          self.scroll = (scroll - dy).clamp(0.0, self.max_scroll(&areas));
+          self.settle = self.scroll;
+        }
+        true
+      }
+      PointerPhase::Up | PointerPhase::Cancel => {
+        let Some((_, target, ..)) = self.pressed.filter(|(id, ..)| *id == event.id) else {
+          return false;
+        };
+        self.pressed = None;
+        let dragged = std::mem::take(&mut self.dragging);
+        if dragged {
+          // Whole rows, since the canvas can't cut off the one scrolled out
+          let row = Self::areas(rect).row_height;
+          self.settle = (self.scroll / row).round() * row;
+        }
+        // Only a tap ending where it started counts
+        if event.phase == PointerPhase::Up
+          && !dragged
+          && self.target(rect, event.position) == Some(target)
+        {
+          match target {
+            Target::Key(key) => self.press(key),
+            Target::Clear => self.set_query(""),
+            Target::Row(Some(index)) => {
+              self.choose(index);
+              self.reveal(rect);
+            }
+            Target::Row(None) => {}
+          }
+        }
+        true
+      }
+      _ => false,
+    }
+  }
+
+  fn captures(&self, id: PointerId) -> bool {
+    self.dragging && matches!(self.pressed, Some((pressed, ..)) if pressed == id)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use async_trait::async_trait;
+
+  use super::*;
+  use crate::map::geocode::{MapGeocoder, SearchSource};
+  use crate::map::route::{Route, RouteSource, Router};
+  use crate::map::MapData;
+
+  const RECT: Rect = Rect {
+    x: 0.0,
+    y: 0.0,
+    width: 800.0,
+    height: 480.0,
+  };
+
+  fn tap(search: &mut SearchScreen, position: [f32; 2]) {
+    for phase in [PointerPhase::Down, PointerPhase::Up] {
+      let event = PointerEvent {
+        id: PointerId::Mouse,
+        phase,
+        position,
+      };
+      search.pointer(&event, RECT);
+    }
+  }
+
+  fn key(letter: char) -> [f32; 2] {
+    let areas = SearchScreen::areas(RECT);
+    let (_, rect) = SearchScreen::keys(areas.keyboard)
+      .into_iter()
+      .find(|(key, _)| *key == Key::Letter(letter))
+      .unwrap();
+    rect.center()
+  }
+
+  #[test]
+  fn types_searches_once_paused_and_navigates_to_a_place() {
+    struct Nowhere;
+    #[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
+    #[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+    impl Router for Nowhere {
+      async fn route(&mut self, _: Coordinate, _: Coordinate) -> Result<Route, String> {
+        Err("nowhere".to_string())
+      }
+    }
+    let (_, navigator) = RouteSource::new(Nowhere);
+    let (_, searcher) = SearchSource::new(MapGeocoder::new(MapData::default()));
+    let mut search = SearchScreen::new();
+    search.connect(searcher, navigator);
+    for letter in "park".chars() {
+      tap(&mut search, key(letter));
+    }
+    assert_eq!(search.query(), "park");
+    search.update(Duration::from_millis(100));
+    assert!(search.animating());
+    search.update(Duration::from_millis(300));
+    assert_eq!(search.asked, "park");
+
+    let place = |name: &str| Place {
+      name: name.to_string(),
+      detail: String::new(),
+      position: Coordinate::new(52.5, 13.4),
+    };
+    let results = |query: &str| Telemetry {
+      search: Some(Arc::new(SearchResults {
+        query: query.to_string(),
+        places: (0..50)
+          .map(|index| place(&format!("Park {}", index)))
+          .collect(),
+      })),
+      latitude: Some(52.0),
+      longitude: Some(13.0),
+      ..Default::default()
+    };
+    search.set_telemetry(&results("par"));
+    assert!(search.places().is_empty());
+    search.set_telemetry(&results("park"));
+    assert_eq!(search.places().len(), 50);
+
+    let areas = SearchScreen::areas(RECT);
+    let second = [400.0, areas.list.y + areas.row_height * 1.5];
+    tap(&mut search, second);
+    assert_eq!(search.chosen, Some(1));
+    assert!(search.action(Action::Next));
+    assert!(search.action(Action::Activate));
+    assert_eq!(search.chosen, Some(2));
+    assert!(search.action(Action::Back));
+    assert_eq!(search.query(), "");
+    assert!(search.places().is_empty());
+  }
+}
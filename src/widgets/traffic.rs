@@ -0,0 +1,155 @@
+use crate::anim::mix;
+use crate::canvas::Style;
+use crate::frame::Frame;
+use crate::map::traffic::Congestion;
+use crate::text::{Align, TextSection, VAlign};
+use crate::theme::Palette;
+use crate::widgets::{Rect, Widget};
+
+/// Linear RGBA colors in straight alpha, of traffic on the map and in
+/// [`TrafficLegend`].
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct TrafficColors {
+  pub free: [f32; 4],
+  pub slow: [f32; 4],
+  pub jammed: [f32; 4],
+  pub closed: [f32; 4],
+}
+
+impl Default for TrafficColors {
+  fn default() -> Self {
+    Self {
+      free: [0.1, 0.7, 0.3, 0.8],
+      slow: [1.0, 0.65, 0.1, 0.9],
+      jammed: [0.9, 0.1, 0.1, 0.9],
+      closed: [0.35, 0.0, 0.05, 1.0],
+    }
+  }
+}
+
+impl TrafficColors {
+  /// Dimmer, for the night.
+  pub fn night() -> Self {
+    Self {
+      free: [0.05, 0.4, 0.15, 0.8],
+      slow: [0.6, 0.38, 0.05, 0.9],
+      jammed: [0.55, 0.05, 0.05, 0.9],
+      closed: [0.25, 0.0, 0.03, 1.0],
+    }
+  }
+
+  pub(crate) fn mix(&self, other: &Self, t: f32) -> Self {
+    Self {
+      free: mix(self.free, other.free, t),
+      slow: mix(self.slow, other.slow, t),
+      jammed: mix(self.jammed, other.jammed, t),
+      closed: mix(self.closed, other.closed, t),
+    }
+  }
+
+  pub fn of(&self, congestion: Congestion) -> [f32; 4] {
+    match congestion {
+      Congestion::Free => self.free,
+      Congestion::Slow => self.slow,
+      Congestion::Jammed => self.jammed,
+      Congestion::Closed => self.closed,
+    }
+  }
+}
+
+/// What the colors of traffic on the map mean, side by side or stacked if
+/// taller than wide. Faded while there's no
+/// [`Telemetry::traffic`](crate::data::Telemetry::traffic), since the map
+/// shows none then.
+#[derive(Clone, Debug)]
+pub struct TrafficLegend {
+  pub colors: TrafficColors,
+  /// Shown with the night palette, like on the map.
+  pub night_colors: TrafficColors,
+  /// Linear RGBA in straight alpha.
+  pub text: [f32; 4],
+  night: bool,
+}
+
+impl Default for TrafficLegend {
+  fn default() -> Self {
+    Self::new()
+  }
+}
+
+impl TrafficLegend {
+  pub fn new() -> Self {
+    Self {
+      colors: TrafficColors::default(),
+      night_colors: TrafficColors::night(),
+      text: [1.0, 1.0, 1.0, 1.0],
+      night: false,
+    }
+  }
+
+  pub fn with_colors(mut self, colors: TrafficColors) -> Self {
+    self.colors = colors;
+    self
+  }
+}
+
+fn name(congestion: Congestion) -> &'static str {
+  match congestion {
+    Congestion::Free => "Free",
+    Congestion::Slow => "Slow",
+    Congestion::Jammed => "Jammed",
+    Congestion::Closed => "Closed",
+  }
+}
+
+impl Widget for TrafficLegend {
+  fn set_palette(&mut self, palette: &Palette) {
+    self.text = palette.text;
+    self.night = palette.night;
+  }
+
+  fn draw(&self, frame: &mut Frame, rect: Rect) {
+    let alpha = if frame.telemetry.traffic.is_some() {
+      1.0
+    } else {
+      0.35
+    };
+    let faded = |[r, g, b, a]: [f32; 4]| [r, g, b, a * alpha];
+    let colors = if self.night {
+      &self.night_colors
+    } else {
+      &self.colors
+    };
+    let count = Congestion::ALL.len() as f32;
+    let stacked = rect.height > rect.width;
+    let (width, height) = if stacked {
+      (rect.width, rect.height / count)
+    } else {
+      (rect.width / count, rect.height)
+    };
+    let size = height.min(width / 3.0) * 0.5;
+    for (index, congestion) in Congestion::ALL.into_iter().enumerate() {
+      let (x, y) = if stacked {
+        (rect.x, rect.y + height * index as f32)
+      } else {
+        (rect.x + width * index as f32, rect.y)
+      };
+      let middle = y + height / 2.0;
+      let swatch = [size * 1.6, size * 0.6];
+      frame.canvas.rounded_rect(
+        [x + size * 0.3, middle - swatch[1] / 2.0],
+        swatch,
+        swatch[1] / 2.0,
+        Style::fill(faded(colors.of(congestion))),
+      );
+      frame.text.queue(
+        &TextSection::new(name(congestion))
+          .at(x + size * 2.2, middle)
+          .with_size(size)
+          .with_color(faded(self.text))
+          .with_align(Align::Left, VAlign::Center)
+          .with_max_width(width - size * 2.2),
+      );
+    }
+  }
+}
@@ -0,0 +1,445 @@
+use std::time::Duration;
+
+use serde::Deserialize;
+
+use crate::canvas::{Canvas, Style};
+use crate::data::vehicle::{Corner, Lights, VehicleStatus};
+use crate::data::Telemetry;
+use crate::frame::Frame;
+use crate::locale::NumberFormat;
+use crate::text::{Align, TextSection, VAlign};
+use crate::theme::Palette;
+use crate::widgets::{Rect, Widget};
+
+/// Blinks of alerting parts per second.
+const BLINK_RATE: f32 = 2.0;
+
+/// A part of the vehicle a [`VehicleSchematic`] draws.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Part {
+  Tire(Corner),
+  Door(Corner),
+  Trunk,
+  Hood,
+  Lights,
+}
+
+/// How a part of a [`VehicleSchematic`] is drawn.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PartState {
+  Normal,
+  /// In the accent color, like an open door while parked.
+  Highlight,
+  /// Blinking in the warning color, like a flat tire.
+  Alert,
+}
+
+/// Unit tire pressures are shown in.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PressureUnit {
+  #[default]
+  Kpa,
+  Bar,
+  Psi,
+}
+
+impl PressureUnit {
+  fn format(self, kpa: f32, number_format: &NumberFormat) -> String {
+    match self {
+      Self::Kpa => format!("{} kPa", number_format.format(kpa, 0)),
+      Self::Bar => format!("{} bar", number_format.format(kpa / 100.0, 1)),
+      Self::Psi => format!("{} psi", number_format.format(kpa * 0.145_038, 0)),
+    }
+  }
+}
+
+/// Linear RGBA colors in straight alpha.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct VehicleColors {
+  pub body: [f32; 4],
+  /// Windows and closed doors.
+  pub window: [f32; 4],
+  /// Parts in [`PartState::Normal`].
+  pub normal: [f32; 4],
+  pub highlight: [f32; 4],
+  pub alert: [f32; 4],
+  pub text: [f32; 4],
+}
+
+impl Default for VehicleColors {
+  fn default() -> Self {
+    Self {
+      body: [0.12, 0.12, 0.14, 1.0],
+      window: [1.0, 1.0, 1.0, 0.3],
+      normal: [1.0, 1.0, 1.0, 0.5],
+      highlight: [0.2, 0.6, 1.0, 1.0],
+      alert: [0.9, 0.1, 0.1, 1.0],
+      text: [1.0, 1.0, 1.0, 1.0],
+    }
+  }
+}
+
+/// The vehicle from above with its front up, showing the tire pressures
+/// next to the tires, open doors, trunk and hood and the headlights from
+/// [`Telemetry::vehicle`].
+///
+/// Tires outside of the pressure range and anything open while driving
+/// alert, anything else open or lit is highlighted. Parts can also be
+/// highlighted by the application, e.g. to point out which tire a message
+/// is about.
+#[derive(Clone, Debug)]
+pub struct VehicleSchematic {
+  /// kPa, tires below alert.
+  pub min_pressure: f32,
+  /// kPa, tires above alert.
+  pub max_pressure: f32,
+  pub pressure_unit: PressureUnit,
+  /// How pressures are written, like with a decimal comma.
+  pub number_format: NumberFormat,
+  /// km/h above which anything open alerts.
+  pub moving_speed: f32,
+  pub colors: VehicleColors,
+  highlighted: Vec<Part>,
+  status: VehicleStatus,
+  speed: f32,
+  time: Duration,
+}
+
+impl Default for VehicleSchematic {
+  fn default() -> Self {
+    Self::new()
+  }
+}
+
+impl VehicleSchematic {
+  /// Alerting below 200 and above 300 kPa, shown in kPa.
+  pub fn new() -> Self {
+    Self {
+      min_pressure: 200.0,
+      max_pressure: 300.0,
+      pressure_unit: PressureUnit::default(),
+      number_format: NumberFormat::default(),
+      moving_speed: 5.0,
+      colors: VehicleColors::default(),
+      highlighted: Vec::new(),
+      status: VehicleStatus::default(),
+      speed: 0.0,
+      time: Duration::ZERO,
+    }
+  }
+
+  pub fn with_pressure_range(mut self, min: f32, max: f32) -> Self {
+    self.min_pressure = min;
+    self.max_pressure = max;
+    self
+  }
+
+  pub fn with_pressure_unit(mut self, unit: PressureUnit) -> Self {
+    self.pressure_unit = unit;
+    self
+  }
+
+  pub fn with_number_format(mut self, number_format: NumberFormat) -> Self {
+    self.number_format = number_format;
+    self
+  }
+
+  pub fn with_colors(mut self, colors: VehicleColors) -> Self {
+    self.colors = colors;
+    self
+  }
+
+  /// Highlights `part` whatever its state, or stops doing so.
+  pub fn set_highlight(&mut self, part: Part, highlight: bool) {
+    self.highlighted.retain(|highlighted| *highlighted != part);
+    if highlight {
+      self.highlighted.push(part);
+    }
+  }
+
+  pub fn state(&self, part: Part) -> PartState {
+    let status = &self.status;
+    let open = match part {
+      Part::Tire(corner) => {
+        let pressure = status.tire_pressure(corner);
+        if pressure
+          .is_some_and(|pressure| !(self.min_pressure..=self.max_pressure).contains(&pressure))
+        {
+          return PartState::Alert;
+        }
+        false
+      }
+      Part::Door(corner) => status.door(corner) == Some(true),
+      Part::Trunk => status.trunk == Some(true),
+      Part::Hood => status.hood == Some(true),
+      Part::Lights => status.lights.is_some_and(|lights| lights != Lights::Off),
+    };
+    let drivable = matches!(part, Part::Lights);
+    if open && !drivable && self.speed > self.moving_speed {
+      PartState::Alert
+    } else if open || self.highlighted.contains(&part) {
+      PartState::Highlight
+    } else {
+      PartState::Normal
+    }
+  }
+
+  fn parts() -> impl Iterator<Item = Part> {
+    let corners = Corner::ALL.into_iter();
+    corners
+      .clone()
+      .map(Part::Tire)
+      .chain(corners.map(Part::Door))
+      .chain([Part::Trunk, Part::Hood])
+  }
+
+  fn color(&self, part: Part) -> [f32; 4] {
+    match self.state(part) {
+      PartState::Normal => self.colors.normal,
+      PartState::Highlight => self.colors.highlight,
+      // Off for the last part of every blink
+      PartState::Alert if (self.time.as_secs_f32() * BLINK_RATE).fract() < 0.65 => {
+        self.colors.alert
+      }
+      PartState::Alert => self.colors.normal,
+    }
+  }
+}
+
+impl Widget for VehicleSchematic {
+  fn set_palette(&mut self, palette: &Palette) {
+    self.colors = VehicleColors {
+      body: palette.surface,
+      window: palette.muted,
+      normal: palette.muted,
+      highlight: palette.accent,
+      alert: palette.warning,
+      text: palette.text,
+    };
+  }
+
+  fn set_telemetry(&mut self, telemetry: &Telemetry) {
+    self.status = telemetry.vehicle;
+    self.speed = telemetry.speed.unwrap_or(0.0);
+  }
+
+  fn update(&mut self, delta: Duration) {
+    if self.animating() {
+      self.time += delta;
+    } else {
+      // Blinks start on
+      self.time = Duration::ZERO;
+    }
+  }
+
+  fn animating(&self) -> bool {
+    Self::parts().any(|part| self.state(part) == PartState::Alert)
+  }
+
+  fn draw(&self, frame: &mut Frame, rect: Rect) {
+    // Room for the pressures on both sides and the beams in front
+    let h = (rect.height * 0.8).min(rect.width / 1.3);
+    let w = h * 0.45;
+    let [cx, cy] = rect.center();
+    let (x, y) = (cx - w / 2.0, cy - h / 2.0 + h * 0.06);
+    let colors = &self.colors;
+    let canvas = &mut *frame.canvas;
+
+    if let Some(lights) = self.status.lights {
+      draw_lights(canvas, lights, [x, y], [w, h], colors);
+    }
+    canvas.rounded_rect([x, y], [w, h], w * 0.35, Style::fill(colors.body));
+    canvas.rounded_rect(
+      [x, y],
+      [w, h],
+      w * 0.35,
+      Style::stroke(colors.normal, w * 0.02),
+    );
+    let at = |u: f32, v: f32| [x + w * u, y + h * v];
+    canvas.polygon(
+      &[at(0.12, 0.28), at(0.88, 0.28), at(0.8, 0.37), at(0.2, 0.37)],
+      Style::fill(colors.window),
+    );
+    canvas.polygon(
+      &[at(0.2, 0.7), at(0.8, 0.7), at(0.86, 0.77), at(0.14, 0.77)],
+      Style::fill(colors.window),
+    );
+
+    for (part, top, bottom) in [(Part::Hood, 0.0, 0.26), (Part::Trunk, 0.8, 1.0)] {
+      if self.state(part) != PartState::Normal {
+        let [r, g, b, a] = self.color(part);
+        canvas.rounded_rect(
+          at(0.0, top),
+          [w, h * (bottom - top)],
+          w * 0.3,
+          Style::fill([r, g, b, a * 0.6]),
+        );
+      }
+    }
+
+    for corner in Corner::ALL {
+      // Hinged at the front, opening outwards
+      let (hinge, length) = if corner.is_front() {
+        (0.38, 0.17)
+      } else {
+        (0.55, 0.15)
+      };
+      let (side, outwards) = if corner.is_left() {
+        (x, -1.0)
+      } else {
+        (x + w, 1.0)
+      };
+      let from = [side, y + h * hinge];
+      let part = Part::Door(corner);
+      if self.state(part) == PartState::Normal {
+        let to = [side, from[1] + h * length];
+        canvas.line(from, to, colors.window, w * 0.03);
+      } else {
+        // Opened by about 50°
+        let (sin, cos) = 0.87_f32.sin_cos();
+        let to = [
+          side + outwards * h * length * sin,
+          from[1] + h * length * cos,
+        ];
+        canvas.line(from, to, self.color(part), w * 0.05);
+      }
+    }
+
+    let (tire_w, tire_h) = (w * 0.16, h * 0.17);
+    for corner in Corner::ALL {
+      let part = Part::Tire(corner);
+      let (side, outwards) = if corner.is_left() {
+        (x, -1.0)
+      } else {
+        (x + w, 1.0)
+      };
+      let center = [side, y + h * if corner.is_front() { 0.2 } else { 0.78 }];
+      canvas.rounded_rect(
+        [center[0] - tire_w / 2.0, center[1] - tire_h / 2.0],
+        [tire_w, tire_h],
+        tire_w * 0.3,
+        Style::fill(self.color(part)),
+      );
+      let text = match self.status.tire_pressure(corner) {
+        Some(pressure) => self.pressure_unit.format(pressure, &self.number_format),
+        None => "--".to_string(),
+      };
+      let align = if corner.is_left() {
+        Align::Right
+      } else {
+        Align::Left
+      };
+      let color = match self.state(part) {
+        PartState::Normal => colors.text,
+        _ => self.color(part),
+      };
+      frame.text.queue(
+        &TextSection::new(text)
+          .at(center[0] + outwards * tire_w * 0.9, center[1])
+          .with_size(h * 0.065)
+          .with_color(color)
+          .with_align(align, VAlign::Center),
+      );
+    }
+  }
+}
+
+/// Beams in front of the headlights, or just the lights for parking ones.
+fn draw_lights(
+  canvas: &mut Canvas,
+  lights: Lights,
+  [x, y]: [f32; 2],
+  [w, h]: [f32; 2],
+  colors: &VehicleColors,
+) {
+  let [r, g, b, a] = colors.highlight;
+  for (left, headlight) in [(true, x + w * 0.22), (false, x + w * 0.78)] {
+    let source = [headlight, y + h * 0.03];
+    let reach = match lights {
+      Lights::Off => continue,
+      Lights::Parking => {
+        canvas.circle(source, w * 0.05, Style::fill(colors.highlight));
+        continue;
+      }
+      Lights::Low => 0.1,
+      Lights::High => 0.16,
+    };
+    // Spreading outwards a little more than inwards
+    let (outer, inner) = if left { (-0.3, 0.15) } else { (0.3, -0.15) };
+    canvas.polygon(
+      &[
+        source,
+        [headlight + w * outer, y - h * reach],
+        [headlight + w * inner, y - h * reach],
+      ],
+      Style::fill([r, g, b, a * 0.35]),
+    );
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn schematic(status: VehicleStatus, speed: f32) -> VehicleSchematic {
+    let mut schematic = VehicleSchematic::new();
+    schematic.set_telemetry(&Telemetry {
+      speed: Some(speed),
+      vehicle: status,
+      ..Default::default()
+    });
+    schematic
+  }
+
+  #[test]
+  fn alerts_tires_outside_the_pressure_range() {
+    let status = VehicleStatus {
+      tire_pressure: [Some(240.0), Some(180.0), None, Some(310.0)],
+      ..Default::default()
+    };
+    let schematic = schematic(status, 0.0);
+    let states = Corner::ALL.map(|corner| schematic.state(Part::Tire(corner)));
+    use PartState::*;
+    assert_eq!(states, [Normal, Alert, Normal, Alert]);
+    assert!(schematic.animating());
+  }
+
+  #[test]
+  fn alerts_open_doors_only_while_driving() {
+    let status = VehicleStatus {
+      doors: [Some(true), Some(false), None, None],
+      lights: Some(Lights::High),
+      ..Default::default()
+    };
+    let mut parked = schematic(status, 0.0);
+    assert_eq!(
+      parked.state(Part::Door(Corner::FrontLeft)),
+      PartState::Highlight
+    );
+    assert!(!parked.animating());
+    parked.set_highlight(Part::Trunk, true);
+    assert_eq!(parked.state(Part::Trunk), PartState::Highlight);
+
+    let driving = schematic(status, 50.0);
+    assert_eq!(
+      driving.state(Part::Door(Corner::FrontLeft)),
+      PartState::Alert
+    );
+    assert_eq!(
+      driving.state(Part::Door(Corner::FrontRight)),
+      PartState::Normal
+    );
+    assert_eq!(driving.state(Part::Lights), PartState::Highlight);
+  }
+
+  #[test]
+  fn formats_pressures() {
+    let plain = NumberFormat::default();
+    assert_eq!(PressureUnit::Kpa.format(241.4, &plain), "241 kPa");
+    assert_eq!(PressureUnit::Bar.format(241.4, &plain), "2.4 bar");
+    assert_eq!(PressureUnit::Psi.format(241.4, &plain), "35 psi");
+    let german = NumberFormat::for_tag("de").unwrap();
+    assert_eq!(PressureUnit::Bar.format(241.4, &german), "2,4 bar");
+  }
+}
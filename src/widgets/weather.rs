@@ -0,0 +1,263 @@
+use std::f32::consts::TAU;
+
+use crate::canvas::{Canvas, Style};
+use crate::data::weather::{Condition, Weather};
+use crate::frame::Frame;
+use crate::text::{Align, TextSection, VAlign};
+use crate::theme::Palette;
+use crate::widgets::{Rect, Widget};
+
+const SUN: [f32; 4] = [1.0, 0.75, 0.2, 1.0];
+const PRECIPITATION: [f32; 4] = [0.35, 0.6, 1.0, 1.0];
+
+/// The current weather as an icon and temperature, with a strip of the
+/// next days below if there is room for one.
+///
+/// Shows [`Telemetry::weather`](crate::data::Telemetry::weather) unless
+/// given weather of its own with [`set_weather`](Self::set_weather).
+#[derive(Clone, Debug)]
+pub struct WeatherPanel {
+  /// Days of forecast shown at most, zero for none.
+  pub days: usize,
+  /// Shows temperatures in °F rather than °C.
+  pub fahrenheit: bool,
+  /// Linear RGBA in straight alpha.
+  pub text: [f32; 4],
+  /// Clouds and fog, opaque.
+  pub cloud: [f32; 4],
+  weather: Option<Weather>,
+}
+
+impl Default for WeatherPanel {
+  fn default() -> Self {
+    Self::new()
+  }
+}
+
+impl WeatherPanel {
+  /// Three days of forecast in °C.
+  pub fn new() -> Self {
+    Self {
+      days: 3,
+      fahrenheit: false,
+      text: [1.0, 1.0, 1.0, 1.0],
+      cloud: [0.8, 0.8, 0.85, 1.0],
+      weather: None,
+    }
+  }
+
+  pub fn with_days(mut self, days: usize) -> Self {
+    self.days = days;
+    self
+  }
+
+  pub fn fahrenheit(mut self) -> Self {
+    self.fahrenheit = true;
+    self
+  }
+
+  /// Shows `weather` instead of the telemetry's, none to go back to it.
+  pub fn set_weather(&mut self, weather: Option<Weather>) {
+    self.weather = weather;
+  }
+
+  fn degrees(&self, celsius: f32) -> String {
+    let value = if self.fahrenheit {
+      celsius * 9.0 / 5.0 + 32.0
+    } else {
+      celsius
+    };
+    // Adding zero turns the -0 that rounding small frosts gives into 0
+    format!("{}°", value.round() + 0.0)
+  }
+}
+
+impl Widget for WeatherPanel {
+  fn set_palette(&mut self, palette: &Palette) {
+    self.text = palette.text;
+    // Opaque so the circles a cloud is made of don't show where they overlap
+    let [r, g, b, _] = palette.muted;
+    self.cloud = [r, g, b, 1.0];
+  }
+
+  fn draw(&self, frame: &mut Frame, rect: Rect) {
+    let Some(weather) = self.weather.as_ref().or(frame.telemetry.weather.as_ref()) else {
+      let [x, y] = rect.center();
+      frame.text.queue(
+        &TextSection::new("--°")
+          .at(x, y)
+          .with_size(rect.height * 0.4)
+          .with_color(self.cloud)
+          .with_align(Align::Center, VAlign::Center),
+      );
+      return;
+    };
+    let days = self.days.min(weather.forecast.len());
+    // The forecast only goes below if it isn't squeezed too much
+    let current = if days > 0 && rect.height > rect.width * 0.4 {
+      Rect {
+        height: rect.height * 0.6,
+        ..rect
+      }
+    } else {
+      rect
+    };
+
+    let side = current.height.min(current.width / 2.0);
+    let icon = [current.x + side / 2.0, current.y + current.height / 2.0];
+    draw_icon(
+      frame.canvas,
+      weather.condition,
+      icon,
+      side * 0.45,
+      self.cloud,
+    );
+    frame.text.queue(
+      &TextSection::new(self.degrees(weather.temperature))
+        .at(current.x + side * 1.1, icon[1])
+        .with_size(current.height * 0.5)
+        .with_color(self.text)
+        .with_align(Align::Left, VAlign::Center),
+    );
+
+    if current.height == rect.height {
+      return;
+    }
+    let strip = Rect::new(
+      rect.x,
+      current.y + current.height,
+      rect.width,
+      rect.height - current.height,
+    );
+    let width = strip.width / days as f32;
+    for (index, day) in weather.forecast.iter().take(days).enumerate() {
+      let x = strip.x + width * (index as f32 + 0.5);
+      let h = strip.height;
+      frame.text.queue(
+        &TextSection::new(day.day.as_str())
+          .at(x, strip.y + h * 0.15)
+          .with_size(h * 0.22)
+          .with_color(self.text)
+          .with_align(Align::Center, VAlign::Center),
+      );
+      let radius = (h * 0.2).min(width * 0.3);
+      draw_icon(
+        frame.canvas,
+        day.condition,
+        [x, strip.y + h * 0.5],
+        radius,
+        self.cloud,
+      );
+      frame.text.queue(
+        &TextSection::new(format!(
+          "{} / {}",
+          self.degrees(day.high),
+          self.degrees(day.low)
+        ))
+        .at(x, strip.y + h * 0.85)
+        .with_size(h * 0.18)
+        .with_color(self.text)
+        .with_align(Align::Center, VAlign::Center),
+      );
+    }
+  }
+}
+
+/// Draws the icon of `condition` into a circle of `radius` around `center`.
+fn draw_icon(
+  canvas: &mut Canvas,
+  condition: Condition,
+  center: [f32; 2],
+  radius: f32,
+  cloud: [f32; 4],
+) {
+  let [x, y] = center;
+  let r = radius;
+  // Precipitation falls from a cloud in the upper part
+  let high = [x, y - r * 0.25];
+  let below = |index: usize| x + (index as f32 - 1.0) * r * 0.45;
+  match condition {
+    Condition::Clear => draw_sun(canvas, center, r),
+    Condition::PartlyCloudy => {
+      draw_sun(canvas, [x - r * 0.3, y - r * 0.3], r * 0.65);
+      draw_cloud(canvas, [x + r * 0.15, y + r * 0.2], r * 0.8, cloud);
+    }
+    Condition::Cloudy => draw_cloud(canvas, center, r, cloud),
+    Condition::Fog => {
+      for (index, width) in [1.6, 1.2, 1.6].into_iter().enumerate() {
+        let row = y + (index as f32 - 1.0) * r * 0.5;
+        let half = r * width / 2.0;
+        canvas.line([x - half, row], [x + half, row], cloud, r * 0.16);
+      }
+    }
+    Condition::Drizzle | Condition::Rain => {
+      draw_cloud(canvas, high, r * 0.85, cloud);
+      let length = if condition == Condition::Rain {
+        0.45
+      } else {
+        0.2
+      };
+      for index in 0..3 {
+        let top = [below(index), y + r * 0.45];
+        let bottom = [top[0] - r * 0.15, top[1] + r * length];
+        canvas.line(top, bottom, PRECIPITATION, r * 0.1);
+      }
+    }
+    Condition::Snow => {
+      draw_cloud(canvas, high, r * 0.85, cloud);
+      for index in 0..3 {
+        let flake = [below(index), y + r * (0.55 + 0.2 * (index % 2) as f32)];
+        canvas.circle(flake, r * 0.1, Style::fill([1.0, 1.0, 1.0, 1.0]));
+      }
+    }
+    Condition::Thunderstorm => {
+      draw_cloud(canvas, high, r * 0.85, cloud);
+      let bolt = [
+        [x + r * 0.1, y + r * 0.25],
+        [x - r * 0.2, y + r * 0.65],
+        [x, y + r * 0.65],
+        [x - r * 0.15, y + r],
+        [x + r * 0.25, y + r * 0.5],
+        [x + r * 0.05, y + r * 0.5],
+      ];
+      canvas.polygon(&bolt, Style::fill(SUN));
+    }
+  }
+}
+
+fn draw_sun(canvas: &mut Canvas, center: [f32; 2], radius: f32) {
+  canvas.circle(center, radius * 0.5, Style::fill(SUN));
+  for ray in 0..8 {
+    let angle = ray as f32 / 8.0 * TAU;
+    let (sin, cos) = angle.sin_cos();
+    let at = |distance: f32| [center[0] + cos * distance, center[1] + sin * distance];
+    canvas.line(at(radius * 0.68), at(radius * 0.95), SUN, radius * 0.1);
+  }
+}
+
+fn draw_cloud(canvas: &mut Canvas, [x, y]: [f32; 2], radius: f32, color: [f32; 4]) {
+  let r = radius;
+  let style = Style::fill(color);
+  canvas.circle([x - r * 0.4, y + r * 0.1], r * 0.35, style);
+  canvas.circle([x + r * 0.05, y - r * 0.12], r * 0.45, style);
+  canvas.circle([x + r * 0.45, y + r * 0.15], r * 0.3, style);
+  canvas.rounded_rect(
+    [x - r * 0.75, y + r * 0.05],
+    [r * 1.5, r * 0.4],
+    r * 0.2,
+    style,
+  );
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn rounds_degrees_in_either_unit() {
+    let panel = WeatherPanel::new();
+    assert_eq!(panel.degrees(11.4), "11°");
+    assert_eq!(panel.degrees(-0.4), "0°");
+    assert_eq!(panel.fahrenheit().degrees(20.0), "68°");
+  }
+}